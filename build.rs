@@ -67,8 +67,16 @@ fn generate_man_page() -> Result<()> {
                 .long("completions")
                 .help("Generate shell completion scripts for the specified shell")
                 .value_name("SHELL")
-                .value_parser(["bash", "zsh", "fish", "powershell"])
-        );
+                .value_parser(["bash", "zsh", "fish", "powershell", "elvish", "nushell"])
+        )
+        .subcommand(clap::Command::new("ocr").about("Extract text from a single document (same as the flat invocation)"))
+        .subcommand(clap::Command::new("batch").about("Extract text from many documents into an output directory"))
+        .subcommand(clap::Command::new("watch").about("Process documents as they appear in a directory"))
+        .subcommand(clap::Command::new("cache").about("Inspect and clean the persistent result cache"))
+        .subcommand(clap::Command::new("config").about("Show, locate, or check the configuration"))
+        .subcommand(clap::Command::new("files").about("List, inspect, and delete files uploaded to the Mistral Files API"))
+        .subcommand(clap::Command::new("serve").about("Accept documents over HTTP and answer with the JSON output"))
+        .subcommand(clap::Command::new("completions").about("Print a shell completion script"));
 
     // Generate the man page
     let man = clap_mangen::Man::new(cmd);
@@ -0,0 +1,25 @@
+/* C interface of the paperless-ngx-ocr2 cdylib (see src/ffi.rs) */
+#ifndef PAPERLESS_NGX_OCR2_H
+#define PAPERLESS_NGX_OCR2_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Extract text from the file at `path`.
+ *
+ * `config_json` is NULL or a JSON object with config.toml keys, e.g.
+ * {"api_key": "..."}. Returns the CLI's --json output (success or error) as a
+ * NUL-terminated string that must be released with ocr_free_string.
+ */
+char *ocr_extract(const char *path, const char *config_json);
+
+/* Release a string returned by ocr_extract. */
+void ocr_free_string(char *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PAPERLESS_NGX_OCR2_H */
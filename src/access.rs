@@ -0,0 +1,364 @@
+//! Bearer tokens for `serve`
+//!
+//! With tokens in the configuration, `POST /ocr` and the job endpoints require
+//! one of them in an `Authorization: Bearer <token>` header; `GET /health`
+//! stays open for liveness probes. Each token may carry its own rate limit:
+//!
+//! ```toml
+//! [[server.tokens]]
+//! name = "paperless-home"
+//! token = "3f1c9a6e0d2b47d8a5e4c7b1f0e9d2a6"
+//! requests_per_minute = 30
+//! ```
+//!
+//! Requests without a valid token are answered with 401, requests over the
+//! limit with 429 and a `Retry-After` header. Limits refill continuously, so a
+//! client may burst up to its per-minute limit and then continue at its rate.
+
+use crate::error::FieldErrors;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest token accepted, so tokens cannot be guessed
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+/// A client allowed to use the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenConfig {
+    /// Name of the client, for logs
+    pub name: String,
+
+    /// Bearer token the client sends
+    pub token: String,
+
+    /// Requests per minute allowed with the token; unset allows any
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+
+    /// Tenant profile for the requests of the token (see [`crate::tenant`])
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Check the tokens of the `[server]` section
+pub fn check_tokens(tokens: &[TokenConfig], errors: &mut FieldErrors) {
+    let mut names = HashSet::new();
+    let mut values = HashSet::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let field = |name: &str| format!("tokens[{}].{}", i, name);
+        if token.name.trim().is_empty() {
+            errors.add(field("name"), "Token name must not be empty");
+        } else if !names.insert(token.name.as_str()) {
+            errors.add(
+                field("name"),
+                format!("Token name '{}' is used twice", token.name),
+            );
+        }
+        if token.token.chars().count() < MIN_TOKEN_LENGTH {
+            errors.add(
+                field("token"),
+                format!("Token must be at least {} characters", MIN_TOKEN_LENGTH),
+            );
+        } else if !values.insert(token.token.as_str()) {
+            errors.add(field("token"), "Token is used twice");
+        }
+        if token.requests_per_minute == Some(0) {
+            errors.add(
+                field("requests_per_minute"),
+                "Requests per minute must be at least 1",
+            );
+        }
+    }
+}
+
+/// Client a request was granted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grant<'a> {
+    /// Name of the token
+    pub name: &'a str,
+
+    /// Tenant profile of the token
+    pub profile: Option<&'a str>,
+}
+
+/// Outcome of checking a request's `Authorization` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access<'a> {
+    /// The request may proceed; the client, if tokens are required
+    Granted(Option<Grant<'a>>),
+
+    /// No token or an unknown token
+    Denied,
+
+    /// The client used up its rate limit; it may retry after the duration
+    Limited(Duration),
+}
+
+/// Token bucket holding up to a minute's worth of requests
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(requests_per_minute);
+        Self {
+            capacity,
+            available: capacity,
+            refilled_at: now,
+        }
+    }
+
+    /// Take a request, or the time until one is available
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let per_second = self.capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.available = (self.available + elapsed.as_secs_f64() * per_second).min(self.capacity);
+        self.refilled_at = now;
+
+        if self.available >= 1.0 {
+            self.available -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.available) / per_second))
+        }
+    }
+
+    /// Return a request taken for one that was not served
+    fn refund(&mut self) {
+        self.available = (self.available + 1.0).min(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    name: String,
+    profile: Option<String>,
+    hash: [u8; 32],
+    bucket: Option<Mutex<Bucket>>,
+}
+
+impl Client {
+    fn grant(&self) -> Grant<'_> {
+        Grant {
+            name: &self.name,
+            profile: self.profile.as_deref(),
+        }
+    }
+}
+
+/// Checks bearer tokens and their rate limits
+#[derive(Debug, Default)]
+pub struct TokenAuth {
+    clients: Vec<Client>,
+}
+
+impl TokenAuth {
+    /// Authentication with the configured tokens; without any, every request
+    /// is granted
+    pub fn new(tokens: &[TokenConfig]) -> Self {
+        let now = Instant::now();
+        Self {
+            clients: tokens
+                .iter()
+                .map(|token| Client {
+                    name: token.name.clone(),
+                    profile: token.profile.clone(),
+                    hash: hash(&token.token),
+                    bucket: token
+                        .requests_per_minute
+                        .map(|limit| Mutex::new(Bucket::new(limit, now))),
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether requests need a token
+    pub fn is_enabled(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// Check the value of a request's `Authorization` header
+    pub fn authorize(&self, authorization: Option<&str>) -> Access<'_> {
+        self.authorize_at(authorization, Instant::now())
+    }
+
+    fn authorize_at(&self, authorization: Option<&str>, now: Instant) -> Access<'_> {
+        if !self.is_enabled() {
+            return Access::Granted(None);
+        }
+        let Some(client) = self.client(authorization) else {
+            return Access::Denied;
+        };
+
+        if let Some(ref bucket) = client.bucket {
+            let mut bucket = bucket.lock().unwrap_or_else(|e| e.into_inner());
+            if let Err(retry_after) = bucket.take(now) {
+                return Access::Limited(retry_after);
+            }
+        }
+        Access::Granted(Some(client.grant()))
+    }
+
+    /// Give back the request counted for `client` when the server turned it
+    /// away for its own reasons, such as being busy
+    pub fn refund(&self, client: Option<Grant<'_>>) {
+        let Some(client) = client else {
+            return;
+        };
+        let bucket = self
+            .clients
+            .iter()
+            .find(|c| c.name == client.name)
+            .and_then(|c| c.bucket.as_ref());
+        if let Some(bucket) = bucket {
+            bucket.lock().unwrap_or_else(|e| e.into_inner()).refund();
+        }
+    }
+
+    /// Check a request's `Authorization` header without counting the request
+    /// against the rate limit, for polling results
+    pub fn identify(&self, authorization: Option<&str>) -> Access<'_> {
+        if !self.is_enabled() {
+            return Access::Granted(None);
+        }
+        match self.client(authorization) {
+            Some(client) => Access::Granted(Some(client.grant())),
+            None => Access::Denied,
+        }
+    }
+
+    fn client(&self, authorization: Option<&str>) -> Option<&Client> {
+        let token = authorization.and_then(bearer_token)?;
+
+        // Compare hashes in constant time, so the time taken does not reveal
+        // how much of a token was right
+        let presented = hash(token);
+        self.clients
+            .iter()
+            .find(|client| constant_time_eq(&client.hash, &presented))
+    }
+}
+
+/// Token of a `Bearer` authorization
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+fn hash(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(name: &str, token: &str, requests_per_minute: Option<u32>) -> TokenConfig {
+        TokenConfig {
+            name: name.to_string(),
+            token: token.to_string(),
+            requests_per_minute,
+            profile: None,
+        }
+    }
+
+    fn granted(name: &str) -> Access<'_> {
+        Access::Granted(Some(Grant {
+            name,
+            profile: None,
+        }))
+    }
+
+    #[test]
+    fn test_authorize_checks_tokens_and_rate_limits() {
+        assert_eq!(TokenAuth::new(&[]).authorize(None), Access::Granted(None));
+
+        let auth = TokenAuth::new(&[
+            token("home", "home-token-0123456789", Some(2)),
+            token("office", "office-token-0123456789", None),
+        ]);
+        let start = Instant::now();
+        let home = Some("Bearer home-token-0123456789");
+
+        assert_eq!(auth.authorize_at(None, start), Access::Denied);
+        assert_eq!(
+            auth.authorize_at(Some("Bearer wrong-token-0123456789"), start),
+            Access::Denied
+        );
+        assert_eq!(
+            auth.authorize_at(Some("Basic home-token-0123456789"), start),
+            Access::Denied
+        );
+        assert_eq!(auth.authorize_at(home, start), granted("home"));
+        assert_eq!(
+            auth.authorize_at(Some("bearer  home-token-0123456789 "), start),
+            granted("home")
+        );
+        assert_eq!(
+            auth.authorize_at(home, start),
+            Access::Limited(Duration::from_secs(30))
+        );
+
+        assert_eq!(auth.identify(home), granted("home"));
+        assert_eq!(auth.identify(None), Access::Denied);
+
+        // A refunded request can be sent again, up to the limit
+        auth.refund(Some(Grant {
+            name: "home",
+            profile: None,
+        }));
+        assert_eq!(auth.authorize_at(home, start), granted("home"));
+        assert!(matches!(auth.authorize_at(home, start), Access::Limited(_)));
+
+        // Two requests per minute refill one every 30 seconds
+        let later = start + Duration::from_secs(30);
+        assert_eq!(auth.authorize_at(home, later), granted("home"));
+        for _ in 0..5 {
+            assert_eq!(
+                auth.authorize_at(Some("Bearer office-token-0123456789"), later),
+                granted("office")
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_tokens() {
+        let mut errors = FieldErrors::new();
+        check_tokens(
+            &[
+                token("home", "home-token-0123456789", Some(10)),
+                token("home", "short", Some(0)),
+                token(" ", "home-token-0123456789", None),
+            ],
+            &mut errors,
+        );
+        let error = errors.into_config_result().unwrap_err();
+        let fields: Vec<&str> = error
+            .field_errors()
+            .iter()
+            .map(|field| field.field.as_str())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "tokens[1].name",
+                "tokens[1].token",
+                "tokens[1].requests_per_minute",
+                "tokens[2].name",
+                "tokens[2].token",
+            ]
+        );
+    }
+}
@@ -41,6 +41,8 @@ impl AuthHandler {
             HeaderValue::from_static("gzip, deflate, br"),
         );
 
+        crate::trace_context::inject(&mut headers);
+
         Ok(headers)
     }
 
@@ -62,6 +64,8 @@ impl AuthHandler {
 
         // Note: Content-Type for multipart is set automatically by reqwest
 
+        crate::trace_context::inject(&mut headers);
+
         Ok(headers)
     }
 
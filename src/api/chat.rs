@@ -0,0 +1,336 @@
+//! Mistral AI Chat Completions API client
+//!
+//! This module implements the chat completions client used for post-processing
+//! extracted text (summaries, translation, entity extraction and similar text
+//! tasks).
+//! Documentation: https://docs.mistral.ai/api/#tag/chat
+//!
+//! Requests are sent to /v1/chat/completions with a system prompt describing the
+//! task and the extracted document text as the user message.
+
+use crate::api::MistralClient;
+use crate::error::{parse_json, Error, Result};
+use crate::metrics::ApiOperation;
+use crate::ocr::Entities;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Maximum number of characters of document text sent in a single chat request
+pub const MAX_CHAT_INPUT_CHARS: usize = 32_000;
+
+/// Chat message structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    /// Create a system message
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            content: content.into(),
+        }
+    }
+
+    /// Create a user message
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Chat completion request structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Requested chat response format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    pub format_type: String,
+}
+
+impl ResponseFormat {
+    /// Request a JSON object response
+    pub fn json_object() -> Self {
+        Self {
+            format_type: "json_object".to_string(),
+        }
+    }
+}
+
+impl ChatRequest {
+    /// Create a new chat request
+    pub fn new(model: String, messages: Vec<ChatMessage>) -> Self {
+        Self {
+            model,
+            messages,
+            temperature: None,
+            response_format: None,
+        }
+    }
+
+    /// Validate the chat request
+    pub fn validate(&self) -> Result<()> {
+        if self.model.is_empty() {
+            return Err(Error::validation("Chat model cannot be empty".to_string()));
+        }
+
+        if self.messages.is_empty() {
+            return Err(Error::validation(
+                "Chat request must contain at least one message".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Chat completion choice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatChoice {
+    pub index: i32,
+    pub message: ChatMessage,
+    pub finish_reason: Option<String>,
+}
+
+/// Chat completion response structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub id: String,
+    pub model: String,
+    pub choices: Vec<ChatChoice>,
+}
+
+impl ChatResponse {
+    /// Get the content of the first choice
+    pub fn content(&self) -> Result<&str> {
+        self.choices
+            .first()
+            .map(|choice| choice.message.content.trim())
+            .ok_or_else(|| Error::api("Chat response contained no choices".to_string()))
+    }
+}
+
+/// Chat Completions API client
+pub struct ChatClient {
+    client: MistralClient,
+    model: String,
+}
+
+impl ChatClient {
+    /// Create a new chat client using the given model
+    pub fn new(client: MistralClient, model: String) -> Self {
+        Self { client, model }
+    }
+
+    /// Send a chat completion request
+    pub async fn complete(&self, chat_request: &ChatRequest) -> Result<ChatResponse> {
+        let url = self.client.build_url("v1/chat/completions");
+
+        self.client.log_request("POST", &url);
+
+        chat_request.validate()?;
+
+        // Get authorization headers
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let body = self.client.json_body(&chat_request)?;
+
+        // Send request with retry logic and metrics
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
+                let body = body.clone();
+
+                async move {
+                    let request = client.post(&url).headers(auth_headers);
+                    let response = body.apply(request).send().await.map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+
+        // Read the body and record the call with the bytes sent and received
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Chat, response, start_time, body.len() as u64)
+            .await?;
+
+        parse_json(&response_text, "Failed to parse chat response")
+    }
+
+    /// Summarize extracted document text in one or two sentences
+    pub async fn summarize(&self, text: &str) -> Result<String> {
+        let chat_request = ChatRequest {
+            temperature: Some(0.2),
+            ..ChatRequest::new(
+                self.model.clone(),
+                vec![
+                    ChatMessage::system(
+                        "You summarize OCR text of scanned documents. Reply with one or two \
+                         sentences describing what the document is (type, sender, subject, \
+                         key date or amount). Reply with the summary only.",
+                    ),
+                    ChatMessage::user(truncate_for_chat(text)),
+                ],
+            )
+        };
+
+        let response = self.complete(&chat_request).await?;
+        Ok(response.content()?.to_string())
+    }
+
+    /// Translate extracted document text into the target language
+    ///
+    /// Long documents are translated chunk by chunk so no text is dropped.
+    pub async fn translate(&self, text: &str, target_language: &str) -> Result<String> {
+        let mut translated_chunks = Vec::new();
+
+        for chunk in chunk_for_chat(text) {
+            let chat_request = ChatRequest {
+                temperature: Some(0.0),
+                ..ChatRequest::new(
+                    self.model.clone(),
+                    vec![
+                        ChatMessage::system(format!(
+                            "You translate OCR text of scanned documents into the language \
+                             with code '{}'. Preserve line breaks, numbers, names and markdown \
+                             formatting. Reply with the translation only.",
+                            target_language
+                        )),
+                        ChatMessage::user(chunk),
+                    ],
+                )
+            };
+
+            let response = self.complete(&chat_request).await?;
+            translated_chunks.push(response.content()?.to_string());
+        }
+
+        Ok(translated_chunks.join("\n\n"))
+    }
+
+    /// Extract named entities and keywords from extracted document text
+    pub async fn extract_entities(&self, text: &str) -> Result<(Entities, Vec<String>)> {
+        let chat_request = ChatRequest {
+            temperature: Some(0.0),
+            response_format: Some(ResponseFormat::json_object()),
+            ..ChatRequest::new(
+                self.model.clone(),
+                vec![
+                    ChatMessage::system(
+                        "You extract structured data from OCR text of scanned documents. \
+                         Reply with a JSON object with the keys \"people\", \"organizations\", \
+                         \"dates\", \"amounts\" and \"keywords\", each an array of strings. \
+                         Use values exactly as written in the text; keywords are at most ten \
+                         short topical terms suitable as document tags.",
+                    ),
+                    ChatMessage::user(truncate_for_chat(text)),
+                ],
+            )
+        };
+
+        let response = self.complete(&chat_request).await?;
+        parse_entities(response.content()?)
+    }
+}
+
+/// Raw entity extraction reply from the chat model
+#[derive(Debug, Default, Deserialize)]
+struct EntityReply {
+    #[serde(default)]
+    people: Vec<String>,
+    #[serde(default)]
+    organizations: Vec<String>,
+    #[serde(default)]
+    dates: Vec<String>,
+    #[serde(default)]
+    amounts: Vec<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+}
+
+/// Parse an entity extraction reply into entities and keywords
+///
+/// Values are trimmed and de-duplicated; markdown code fences around the JSON are tolerated.
+pub fn parse_entities(content: &str) -> Result<(Entities, Vec<String>)> {
+    let json = content
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    let reply: EntityReply = parse_json(json, "Failed to parse entity extraction reply")?;
+
+    let entities = Entities {
+        people: normalize_values(reply.people),
+        organizations: normalize_values(reply.organizations),
+        dates: normalize_values(reply.dates),
+        amounts: normalize_values(reply.amounts),
+    };
+
+    Ok((entities, normalize_values(reply.keywords)))
+}
+
+/// Trim values and drop empties and case-insensitive duplicates, keeping order
+fn normalize_values(values: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    values
+        .into_iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty() && seen.insert(value.to_lowercase()))
+        .collect()
+}
+
+/// Split document text into chunks no longer than the maximum chat input size
+///
+/// Chunks are split on paragraph boundaries where possible; single paragraphs
+/// that exceed the limit are split on char boundaries.
+pub fn chunk_for_chat(text: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+
+    while remaining.chars().count() > MAX_CHAT_INPUT_CHARS {
+        let limit = truncate_for_chat(remaining).len();
+        let split_at = match remaining[..limit].rfind("\n\n") {
+            Some(index) if index > 0 => index,
+            _ => limit,
+        };
+
+        chunks.push(&remaining[..split_at]);
+        remaining = remaining[split_at..].trim_start_matches('\n');
+    }
+
+    if !remaining.is_empty() {
+        chunks.push(remaining);
+    }
+
+    chunks
+}
+
+/// Truncate document text to the maximum chat input size on a char boundary
+pub fn truncate_for_chat(text: &str) -> &str {
+    match text.char_indices().nth(MAX_CHAT_INPUT_CHARS) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
@@ -0,0 +1,307 @@
+//! Name resolution settings for the API client
+//!
+//! Split-horizon networks sometimes resolve the API host to an address that is
+//! not reachable from the machine running the tool. Two settings help:
+//!
+//! - Static overrides in curl syntax (`--resolve api.mistral.ai:443:10.0.0.5`)
+//! - An IP family preference that drops addresses of the other family
+//!
+//! The client resolves a host name once per new connection, so the resolver
+//! also counts lookups to tell how often pooled connections were reused.
+//!
+//! Resolvers only see the host name, not the port of the connection, so each
+//! resolver is built for the port of the one URL its client talks to and
+//! applies only the overrides for that port.
+
+use crate::error::{Error, Result};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Static DNS override (`host:port:address`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ResolveOverride {
+    /// Host name to override
+    pub host: String,
+
+    /// Port of the URL the override applies to
+    pub port: u16,
+
+    /// Address to connect to instead of the resolved one
+    pub address: IpAddr,
+}
+
+impl ResolveOverride {
+    /// Socket address to connect to
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+}
+
+impl FromStr for ResolveOverride {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            Error::Config(format!(
+                "Invalid resolve override: '{}'. Expected HOST:PORT:ADDRESS",
+                s
+            ))
+        };
+
+        let mut parts = s.splitn(3, ':');
+        let (Some(host), Some(port), Some(address)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        // IPv6 addresses may be written in brackets as in curl
+        let address = address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map_err(|_| invalid())?;
+
+        Ok(Self {
+            host: host.to_lowercase(),
+            port,
+            address,
+        })
+    }
+}
+
+impl TryFrom<String> for ResolveOverride {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<ResolveOverride> for String {
+    fn from(value: ResolveOverride) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for ResolveOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address {
+            IpAddr::V4(address) => write!(f, "{}:{}:{}", self.host, self.port, address),
+            IpAddr::V6(address) => write!(f, "{}:{}:[{}]", self.host, self.port, address),
+        }
+    }
+}
+
+/// Which IP family to connect with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpPreference {
+    /// Use every resolved address
+    #[default]
+    Auto,
+    /// Use IPv4 addresses when the host has any
+    Ipv4,
+    /// Use IPv6 addresses when the host has any
+    Ipv6,
+}
+
+impl IpPreference {
+    /// Keep only addresses of the preferred family, unless there are none
+    pub fn filter(self, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        let preferred: Vec<SocketAddr> = match self {
+            Self::Auto => return addrs,
+            Self::Ipv4 => addrs.iter().copied().filter(SocketAddr::is_ipv4).collect(),
+            Self::Ipv6 => addrs.iter().copied().filter(SocketAddr::is_ipv6).collect(),
+        };
+
+        if preferred.is_empty() {
+            addrs
+        } else {
+            preferred
+        }
+    }
+}
+
+impl FromStr for IpPreference {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "ipv4" | "4" => Ok(Self::Ipv4),
+            "ipv6" | "6" => Ok(Self::Ipv6),
+            other => Err(Error::Config(format!(
+                "Unknown IP preference: {}. Supported: auto, ipv4, ipv6",
+                other
+            ))),
+        }
+    }
+}
+
+/// System resolver that applies static overrides and an IP family preference,
+/// and counts the lookups made for new connections
+#[derive(Debug, Clone, Default)]
+pub struct ApiResolver {
+    preference: IpPreference,
+    overrides: Vec<ResolveOverride>,
+    lookups: Arc<AtomicU64>,
+}
+
+impl ApiResolver {
+    /// Create a resolver for connections to `port`; overrides for any other
+    /// port are ignored
+    pub fn new(preference: IpPreference, overrides: Vec<ResolveOverride>, port: u16) -> Self {
+        let (overrides, ignored): (Vec<_>, Vec<_>) =
+            overrides.into_iter().partition(|o| o.port == port);
+        for ignored in &ignored {
+            tracing::warn!(
+                "Ignoring resolve override {}: the API URL uses port {}",
+                ignored,
+                port
+            );
+        }
+
+        Self {
+            preference,
+            overrides,
+            lookups: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Create a resolver for connections to the port of `url`
+    pub fn for_url(preference: IpPreference, overrides: Vec<ResolveOverride>, url: &str) -> Self {
+        let port = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.port_or_known_default())
+            .unwrap_or(443);
+        Self::new(preference, overrides, port)
+    }
+
+    /// Counter of lookups, shared by all clones of the resolver
+    pub fn lookups(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.lookups)
+    }
+}
+
+impl Resolve for ApiResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+
+        let overridden: Vec<SocketAddr> = self
+            .overrides
+            .iter()
+            .filter(|o| o.host.eq_ignore_ascii_case(name.as_str()))
+            .map(ResolveOverride::socket_addr)
+            .collect();
+        let preference = self.preference;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = if overridden.is_empty() {
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect()
+            } else {
+                overridden
+            };
+            let addrs: Addrs = Box::new(preference.filter(addrs).into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolve_override() {
+        let ipv4: ResolveOverride = "API.mistral.ai:443:10.0.0.5".parse().unwrap();
+        assert_eq!(ipv4.host, "api.mistral.ai");
+        assert_eq!(ipv4.socket_addr(), "10.0.0.5:443".parse().unwrap());
+
+        let ipv6: ResolveOverride = "api.mistral.ai:443:[fd00::5]".parse().unwrap();
+        assert_eq!(ipv6.address, "fd00::5".parse::<IpAddr>().unwrap());
+        assert_eq!(ipv6.to_string(), "api.mistral.ai:443:[fd00::5]");
+
+        assert!("api.mistral.ai:10.0.0.5"
+            .parse::<ResolveOverride>()
+            .is_err());
+        assert!("api.mistral.ai:https:10.0.0.5"
+            .parse::<ResolveOverride>()
+            .is_err());
+        assert!(":443:10.0.0.5".parse::<ResolveOverride>().is_err());
+    }
+
+    #[test]
+    fn test_ip_preference_filter() {
+        let addrs: Vec<SocketAddr> = vec![
+            "[2001:db8::1]:443".parse().unwrap(),
+            "192.0.2.1:443".parse().unwrap(),
+        ];
+
+        assert_eq!(IpPreference::Auto.filter(addrs.clone()), addrs);
+        assert_eq!(IpPreference::Ipv4.filter(addrs.clone()), vec![addrs[1]]);
+        assert_eq!(IpPreference::Ipv6.filter(addrs.clone()), vec![addrs[0]]);
+
+        // Falls back to the other family rather than failing
+        let only_v4 = vec![addrs[1]];
+        assert_eq!(IpPreference::Ipv6.filter(only_v4.clone()), only_v4);
+    }
+
+    #[tokio::test]
+    async fn test_resolver_applies_overrides_and_counts_lookups() {
+        let resolver = ApiResolver::new(
+            IpPreference::Auto,
+            vec!["api.mistral.ai:443:10.0.0.5".parse().unwrap()],
+            443,
+        );
+        let lookups = resolver.lookups();
+
+        let addrs: Vec<SocketAddr> = resolver
+            .resolve("api.mistral.ai".parse().unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, vec!["10.0.0.5:443".parse().unwrap()]);
+        assert_eq!(lookups.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolver_ignores_overrides_for_other_ports() {
+        let resolver = ApiResolver::for_url(
+            IpPreference::Auto,
+            vec![
+                "localhost:8443:10.0.0.5".parse().unwrap(),
+                "localhost:443:127.0.0.1".parse().unwrap(),
+            ],
+            "https://localhost/v1",
+        );
+
+        let addrs: Vec<SocketAddr> = resolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, vec!["127.0.0.1:443".parse().unwrap()]);
+
+        let resolver = ApiResolver::for_url(
+            IpPreference::Auto,
+            vec!["localhost:8443:10.0.0.5".parse().unwrap()],
+            "https://localhost:8443/v1",
+        );
+        let addrs: Vec<SocketAddr> = resolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, vec!["10.0.0.5:8443".parse().unwrap()]);
+    }
+}
@@ -0,0 +1,157 @@
+//! Mistral AI Embeddings API client
+//!
+//! This module requests vector embeddings of extracted text so archives can
+//! later be searched by meaning rather than by keyword.
+//! Documentation: https://docs.mistral.ai/api/#tag/embeddings
+//!
+//! Requests are sent to /v1/embeddings with the document text as the only input.
+
+use crate::api::MistralClient;
+use crate::error::{parse_json, Error, Result};
+use crate::metrics::ApiOperation;
+use crate::ocr::Embedding;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Maximum number of characters of document text sent for an embedding
+///
+/// The beginning of a document identifies it best, so longer text is truncated
+/// to stay within the model's context.
+pub const MAX_EMBEDDING_INPUT_CHARS: usize = 16_000;
+
+/// Embeddings request structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+impl EmbeddingRequest {
+    /// Create a request for a single text
+    pub fn new(model: String, text: &str) -> Self {
+        Self {
+            model,
+            input: vec![truncate_for_embedding(text).to_string()],
+        }
+    }
+
+    /// Validate the embeddings request
+    pub fn validate(&self) -> Result<()> {
+        if self.model.is_empty() {
+            return Err(Error::validation(
+                "Embedding model cannot be empty".to_string(),
+            ));
+        }
+
+        if self.input.iter().all(|text| text.trim().is_empty()) {
+            return Err(Error::validation(
+                "Embedding input cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A single embedding in the response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    pub index: i32,
+    pub embedding: Vec<f32>,
+}
+
+/// Embeddings response structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub model: String,
+    pub data: Vec<EmbeddingData>,
+}
+
+impl EmbeddingResponse {
+    /// Get the first embedding
+    pub fn into_embedding(self) -> Result<Embedding> {
+        let vector = self
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .filter(|vector| !vector.is_empty())
+            .ok_or_else(|| Error::api("Embeddings response contained no vector".to_string()))?;
+
+        Ok(Embedding {
+            model: self.model,
+            vector,
+        })
+    }
+}
+
+/// Embeddings API client
+pub struct EmbeddingsClient {
+    client: MistralClient,
+    model: String,
+}
+
+impl EmbeddingsClient {
+    /// Create a new embeddings client using the given model
+    pub fn new(client: MistralClient, model: String) -> Self {
+        Self { client, model }
+    }
+
+    /// Request an embedding of the extracted text
+    pub async fn embed(&self, text: &str) -> Result<Embedding> {
+        let url = self.client.build_url("v1/embeddings");
+
+        self.client.log_request("POST", &url);
+
+        let embedding_request = EmbeddingRequest::new(self.model.clone(), text);
+        embedding_request.validate()?;
+
+        // Get authorization headers
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let body = self.client.json_body(&embedding_request)?;
+
+        // Send request with retry logic and metrics
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
+                let body = body.clone();
+
+                async move {
+                    let request = client.post(&url).headers(auth_headers);
+                    let response = body.apply(request).send().await.map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+
+        // Read the body and record the call with the bytes sent and received
+        let response_text = self
+            .client
+            .read_body(
+                ApiOperation::Embeddings,
+                response,
+                start_time,
+                body.len() as u64,
+            )
+            .await?;
+
+        let embedding_response: EmbeddingResponse =
+            parse_json(&response_text, "Failed to parse embeddings response")?;
+        embedding_response.into_embedding()
+    }
+}
+
+/// Truncate text to the maximum embedding input size on a char boundary
+pub fn truncate_for_embedding(text: &str) -> &str {
+    match text.char_indices().nth(MAX_EMBEDDING_INPUT_CHARS) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
@@ -15,7 +15,7 @@ impl APIErrorResponse {
     /// Validate API error response
     pub fn validate(&self) -> Result<()> {
         if self.error.is_empty() {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "Error message cannot be empty".to_string(),
             ));
         }
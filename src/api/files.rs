@@ -5,16 +5,118 @@
 //!
 //! Files must be uploaded before they can be processed via the OCR API.
 //! Supports multipart/form-data uploads with file and purpose fields.
+//!
+//! The bytes of the file part are counted and hashed as they are sent (see
+//! [`UploadDigest`]); an upload the server stored with another size, or a file
+//! that changed size while it was read, fails instead of reaching OCR
+//! truncated.
 
 use crate::api::MistralClient;
-use crate::error::{Error, Result};
+use crate::error::{parse_json, Error, Result};
 use crate::file::FileUpload;
-use crate::metrics::GLOBAL_METRICS;
+use crate::metrics::ApiOperation;
 use chrono;
 use reqwest::multipart;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
 use std::time::Instant;
 use tokio::fs::File;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::io::ReaderStream;
+
+/// Byte count and SHA-256 of the file part of an upload, as it was sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadDigest {
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl UploadDigest {
+    /// Digest of an in-memory file part
+    pub fn of(data: &[u8]) -> Self {
+        Self {
+            bytes: data.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+
+    /// Compare with the size of the file when it was validated and the size
+    /// the server stored
+    pub fn check(&self, expected_bytes: u64, response: &FileUploadResponse) -> Result<()> {
+        if self.bytes != expected_bytes {
+            return Err(Error::api(format!(
+                "Upload of {} sent {} of {} bytes; the file changed while it was read",
+                response.filename, self.bytes, expected_bytes
+            )));
+        }
+        response.check_integrity(self.bytes)
+    }
+}
+
+/// Running count and hash of the bytes read for a streamed file part, shared
+/// with the body it is read into
+#[derive(Clone, Default)]
+pub struct BodyDigest {
+    state: Arc<Mutex<(u64, Sha256)>>,
+}
+
+impl std::fmt::Debug for BodyDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyDigest")
+            .field("bytes", &self.lock().0)
+            .finish()
+    }
+}
+
+impl BodyDigest {
+    fn lock(&self) -> std::sync::MutexGuard<'_, (u64, Sha256)> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn update(&self, data: &[u8]) {
+        let mut state = self.lock();
+        state.0 += data.len() as u64;
+        state.1.update(data);
+    }
+
+    /// Start over, for another attempt of the same upload
+    pub fn reset(&self) {
+        *self.lock() = (0, Sha256::new());
+    }
+
+    /// Digest of the bytes read so far
+    pub fn finish(&self) -> UploadDigest {
+        let state = self.lock();
+        UploadDigest {
+            bytes: state.0,
+            sha256: format!("{:x}", state.1.clone().finalize()),
+        }
+    }
+}
+
+/// Reader that feeds what passes through it into a [`BodyDigest`]
+struct DigestReader<R> {
+    inner: R,
+    digest: BodyDigest,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DigestReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        self.digest.update(&buf.filled()[before..]);
+        Poll::Ready(Ok(()))
+    }
+}
 
 /// File upload request structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,15 +139,15 @@ impl FileUploadRequest {
     /// Validate the upload request
     pub fn validate(&self) -> Result<()> {
         if self.file_data.is_empty() {
-            return Err(Error::Validation("File data cannot be empty".to_string()));
+            return Err(Error::validation("File data cannot be empty".to_string()));
         }
 
         if self.filename.is_empty() {
-            return Err(Error::Validation("Filename cannot be empty".to_string()));
+            return Err(Error::validation("Filename cannot be empty".to_string()));
         }
 
         if self.purpose != "ocr" {
-            return Err(Error::Validation("Purpose must be 'ocr'".to_string()));
+            return Err(Error::validation("Purpose must be 'ocr'".to_string()));
         }
 
         Ok(())
@@ -67,7 +169,7 @@ impl FileUploadRequest {
             let part = multipart::Part::bytes(self.file_data.clone())
                 .file_name(self.filename.clone())
                 .mime_str("application/octet-stream")
-                .map_err(|e| Error::Internal(format!("Failed to create multipart part: {}", e)))?;
+                .map_err(|e| Error::internal_from("Failed to create multipart part", e))?;
 
             let form = multipart::Form::new()
                 .part("file", part)
@@ -82,9 +184,7 @@ impl FileUploadRequest {
                     multipart::Part::bytes(self.file_data.clone())
                         .file_name(self.filename.clone())
                         .mime_str("application/octet-stream")
-                        .map_err(|e| {
-                            Error::Internal(format!("Failed to create file part: {}", e))
-                        })?,
+                        .map_err(|e| Error::internal_from("Failed to create file part", e))?,
                 )
                 .text("purpose", self.purpose.clone());
 
@@ -96,6 +196,33 @@ impl FileUploadRequest {
     pub async fn to_streaming_multipart_form(
         file_path: &str,
         purpose: &str,
+    ) -> Result<multipart::Form> {
+        let mime_type = FileUpload::guess_mime_type(file_path);
+        Self::streaming_form(file_path, &mime_type, purpose, &BodyDigest::default()).await
+    }
+
+    /// Create streaming multipart form for a validated file, whose file part
+    /// carries the file's MIME type (including a `--mime-type` override) and
+    /// is counted and hashed into `digest` as it is sent
+    pub async fn to_digesting_multipart_form(
+        file_upload: &FileUpload,
+        purpose: &str,
+        digest: &BodyDigest,
+    ) -> Result<multipart::Form> {
+        Self::streaming_form(
+            &file_upload.file_path,
+            &file_upload.mime_type,
+            purpose,
+            digest,
+        )
+        .await
+    }
+
+    async fn streaming_form(
+        file_path: &str,
+        mime_type: &str,
+        purpose: &str,
+        digest: &BodyDigest,
     ) -> Result<multipart::Form> {
         let file = File::open(file_path).await.map_err(Error::Io)?;
 
@@ -105,21 +232,19 @@ impl FileUploadRequest {
             .unwrap_or("unknown")
             .to_string();
 
-        // Determine MIME type from file extension
-        let mime_type = mime_guess::MimeGuess::from_path(file_path)
-            .first_or_octet_stream()
-            .to_string();
-
         // Get file size for streaming
         let file_size = file.metadata().await.map_err(Error::Io)?.len();
 
         // Create streaming part
-        let part = multipart::Part::stream_with_length(file, file_size)
+        let reader = DigestReader {
+            inner: file,
+            digest: digest.clone(),
+        };
+        let body = reqwest::Body::wrap_stream(ReaderStream::new(reader));
+        let part = multipart::Part::stream_with_length(body, file_size)
             .file_name(filename)
-            .mime_str(&mime_type)
-            .map_err(|e| {
-                Error::Internal(format!("Failed to create streaming multipart part: {}", e))
-            })?;
+            .mime_str(mime_type)
+            .map_err(|e| Error::internal_from("Failed to create streaming multipart part", e))?;
 
         let form = multipart::Form::new()
             .part("file", part)
@@ -156,7 +281,7 @@ impl FileUploadResponse {
     pub fn validate(&self) -> Result<()> {
         // Validate file ID format and content
         if self.id.is_empty() {
-            return Err(Error::Validation("File ID cannot be empty".to_string()));
+            return Err(Error::validation("File ID cannot be empty".to_string()));
         }
 
         // Validate file ID format (should be alphanumeric with possible dashes/underscores)
@@ -165,7 +290,7 @@ impl FileUploadResponse {
             .chars()
             .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
         {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Invalid file ID format: '{}' contains invalid characters",
                 self.id
             )));
@@ -173,7 +298,7 @@ impl FileUploadResponse {
 
         // Validate object type
         if self.object != "file" {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Object must be 'file', got '{}'",
                 self.object
             )));
@@ -181,7 +306,7 @@ impl FileUploadResponse {
 
         // Validate file size with reasonable bounds
         if self.bytes <= 0 {
-            return Err(Error::Validation("File size must be positive".to_string()));
+            return Err(Error::validation("File size must be positive".to_string()));
         }
 
         // Check for unreasonably large files (>1GB)
@@ -191,7 +316,7 @@ impl FileUploadResponse {
 
         // Validate timestamp (should be reasonable Unix timestamp)
         if self.created_at <= 0 {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "Created timestamp must be positive".to_string(),
             ));
         }
@@ -199,7 +324,7 @@ impl FileUploadResponse {
         // Check if timestamp is too far in the future (more than 1 hour)
         let now = chrono::Utc::now().timestamp();
         if self.created_at > now + 3600 {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Created timestamp is too far in the future: {}",
                 self.created_at
             )));
@@ -207,12 +332,12 @@ impl FileUploadResponse {
 
         // Validate filename
         if self.filename.is_empty() {
-            return Err(Error::Validation("Filename cannot be empty".to_string()));
+            return Err(Error::validation("Filename cannot be empty".to_string()));
         }
 
         // Validate filename doesn't contain path separators
         if self.filename.contains('/') || self.filename.contains('\\') {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Filename cannot contain path separators: '{}'",
                 self.filename
             )));
@@ -220,7 +345,7 @@ impl FileUploadResponse {
 
         // Validate purpose
         if self.purpose != "ocr" {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Purpose must be 'ocr', got '{}'",
                 self.purpose
             )));
@@ -230,7 +355,7 @@ impl FileUploadResponse {
         if let Some(ref status) = self.status {
             let valid_statuses = ["uploaded", "processing", "processed", "error"];
             if !valid_statuses.contains(&status.as_str()) {
-                return Err(Error::Validation(format!(
+                return Err(Error::validation(format!(
                     "Invalid status '{}', must be one of: {}",
                     status,
                     valid_statuses.join(", ")
@@ -250,6 +375,50 @@ impl FileUploadResponse {
     }
 }
 
+/// Signed URL response from `GET /v1/files/{id}/url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedUrlResponse {
+    pub url: String,
+}
+
+/// File list response from `GET /v1/files`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileListResponse {
+    pub data: Vec<FileUploadResponse>,
+    pub object: String,
+    #[serde(default)]
+    pub total: Option<i64>,
+}
+
+/// File deletion response from `DELETE /v1/files/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDeleteResponse {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
+impl FileUploadResponse {
+    /// Check that the stored file matches what was uploaded
+    pub fn check_integrity(&self, expected_bytes: u64) -> Result<()> {
+        if self.bytes < 0 || self.bytes as u64 != expected_bytes {
+            return Err(Error::api(format!(
+                "Upload integrity check failed for {}: sent {} bytes, server stored {} bytes",
+                self.id, expected_bytes, self.bytes
+            )));
+        }
+
+        if self.status.as_deref() == Some("error") {
+            return Err(Error::api(format!(
+                "Upload integrity check failed for {}: server reports status 'error'",
+                self.id
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 /// Files API client
 pub struct FilesClient {
     client: MistralClient,
@@ -262,7 +431,13 @@ impl FilesClient {
     }
 
     /// Upload a file to Mistral AI Files API with streaming support for large files
-    pub async fn upload_file(&self, file_upload: &FileUpload) -> Result<FileUploadResponse> {
+    ///
+    /// Returns the digest of what was sent, after checking it against the size
+    /// of the file and the size the server stored.
+    pub async fn upload_file(
+        &self,
+        file_upload: &FileUpload,
+    ) -> Result<(FileUploadResponse, UploadDigest)> {
         let url = self.client.build_url("v1/files");
 
         self.client.log_request("POST", &url);
@@ -277,22 +452,31 @@ impl FilesClient {
             );
 
             // Use streaming upload for large files
-            return self.upload_file_streaming(&file_upload.file_path).await;
+            let (response, digest) = self.upload_file_streaming(file_upload).await?;
+            digest.check(file_upload.file_size, &response)?;
+            return Ok((response, digest));
         }
 
-        // Read file data for smaller files
+        // Read file data for smaller files; the original and the copy sent by
+        // the current attempt are in memory at the same time
+        let _reservation = self
+            .client
+            .memory()
+            .reserve(
+                self.client.memory_budget_mb(),
+                file_upload.file_size * 2,
+                &file_upload.get_filename(),
+            )
+            .await;
         let file_data = file_upload.read_file_data()?;
+        let digest = UploadDigest::of(&file_data);
 
         // Create upload request
         let upload_request = FileUploadRequest::new(file_data.clone(), file_upload.get_filename());
         upload_request.validate()?;
 
         // Get authorization headers
-        let auth_headers =
-            crate::api::auth::AuthHandler::new(crate::credentials::APICredentials::new(
-                self.client.credentials.api_key.clone(),
-                self.client.credentials.api_base_url.clone(),
-            )?)
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
             .get_multipart_headers()?;
 
         // Send request with retry logic and metrics
@@ -325,65 +509,229 @@ impl FilesClient {
             })
             .await;
 
-        // Record metrics
-        let duration = start_time.elapsed();
-        match &response {
-            Ok(_) => {
-                GLOBAL_METRICS
-                    .record_success(duration, file_upload.file_size, 0)
-                    .await;
-            }
-            Err(_) => {
-                GLOBAL_METRICS.record_failure(duration).await;
-            }
-        }
+        // Read the body and record the call with the bytes sent and received
+        let response_text = self
+            .client
+            .read_body(
+                ApiOperation::Upload,
+                response,
+                start_time,
+                file_upload.file_size,
+            )
+            .await?;
 
-        let response = response?;
+        let upload_response: FileUploadResponse =
+            parse_json(&response_text, "Failed to parse upload response")?;
 
-        // Parse response
-        let status = response.status().as_u16();
-        let response_text = response.text().await.map_err(Error::Network)?;
+        upload_response.validate()?;
+        digest.check(file_upload.file_size, &upload_response)?;
 
-        self.client.log_response(status, Some(response_text.len()));
+        Ok((upload_response, digest))
+    }
 
-        let upload_response: FileUploadResponse = serde_json::from_str(&response_text)
-            .map_err(|e| Error::Api(format!("Failed to parse upload response: {}", e)))?;
+    /// Retrieve the metadata of an uploaded file
+    pub async fn get_file(&self, file_id: &str) -> Result<FileUploadResponse> {
+        let url = self.client.build_url(&format!("v1/files/{}", file_id));
 
-        upload_response.validate()?;
+        self.client.log_request("GET", &url);
+
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
 
-        Ok(upload_response)
+                async move {
+                    let response = client
+                        .get(&url)
+                        .headers(auth_headers)
+                        .send()
+                        .await
+                        .map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Files, response, start_time, 0)
+            .await?;
+
+        parse_json(&response_text, "Failed to parse file response")
+    }
+
+    /// List the files uploaded with this API key
+    pub async fn list_files(&self) -> Result<FileListResponse> {
+        let url = self.client.build_url("v1/files");
+
+        self.client.log_request("GET", &url);
+
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
+
+                async move {
+                    let response = client
+                        .get(&url)
+                        .headers(auth_headers)
+                        .send()
+                        .await
+                        .map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Files, response, start_time, 0)
+            .await?;
+
+        parse_json(&response_text, "Failed to parse file list response")
+    }
+
+    /// Delete an uploaded file
+    pub async fn delete_file(&self, file_id: &str) -> Result<FileDeleteResponse> {
+        let url = self.client.build_url(&format!("v1/files/{}", file_id));
+
+        self.client.log_request("DELETE", &url);
+
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
+
+                async move {
+                    let response = client
+                        .delete(&url)
+                        .headers(auth_headers)
+                        .send()
+                        .await
+                        .map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Files, response, start_time, 0)
+            .await?;
+
+        parse_json(&response_text, "Failed to parse file deletion response")
+    }
+
+    /// Retrieve a time-limited signed URL for an uploaded file
+    pub async fn get_signed_url(&self, file_id: &str, expiry_hours: u32) -> Result<String> {
+        let url = self
+            .client
+            .build_url(&format!("v1/files/{}/url?expiry={}", file_id, expiry_hours));
+
+        self.client.log_request("GET", &url);
+
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
+            .get_auth_headers()?;
+
+        let start_time = Instant::now();
+        let response = self
+            .client
+            .execute_with_retry(|| {
+                let client = self.client.client().clone();
+                let url = url.clone();
+                let auth_headers = auth_headers.clone();
+
+                async move {
+                    let response = client
+                        .get(&url)
+                        .headers(auth_headers)
+                        .send()
+                        .await
+                        .map_err(Error::Network)?;
+
+                    MistralClient::handle_response(response).await
+                }
+            })
+            .await;
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Files, response, start_time, 0)
+            .await?;
+
+        let signed: SignedUrlResponse =
+            parse_json(&response_text, "Failed to parse signed URL response")?;
+        if signed.url.is_empty() {
+            return Err(Error::api(format!(
+                "Empty signed URL returned for file {}",
+                file_id
+            )));
+        }
+
+        Ok(signed.url)
+    }
+
+    /// Confirm that an uploaded file has the size we sent and is not in error
+    pub async fn verify_upload(&self, file_id: &str, expected_bytes: u64) -> Result<()> {
+        let file = self.get_file(file_id).await?;
+        file.check_integrity(expected_bytes)?;
+        tracing::debug!("Upload verified: {} ({} bytes)", file_id, file.bytes);
+        Ok(())
     }
 
     /// Upload a file using streaming (memory-efficient for large files)
-    async fn upload_file_streaming(&self, file_path: &str) -> Result<FileUploadResponse> {
+    async fn upload_file_streaming(
+        &self,
+        file_upload: &FileUpload,
+    ) -> Result<(FileUploadResponse, UploadDigest)> {
         let url = self.client.build_url("v1/files");
 
         self.client.log_request("POST", &url);
 
         // Get authorization headers
-        let auth_headers =
-            crate::api::auth::AuthHandler::new(crate::credentials::APICredentials::new(
-                self.client.credentials.api_key.clone(),
-                self.client.credentials.api_base_url.clone(),
-            )?)
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
             .get_multipart_headers()?;
 
-        // Create streaming multipart form
-        let _form = FileUploadRequest::to_streaming_multipart_form(file_path, "ocr").await?;
+        let file_size = tokio::fs::metadata(&file_upload.file_path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
 
         // Send request with retry logic
+        let digest = BodyDigest::default();
+        let start_time = Instant::now();
         let response = self
             .client
             .execute_with_retry(|| {
                 let client = self.client.client().clone();
                 let url = url.clone();
                 let auth_headers = auth_headers.clone();
-                let file_path = file_path.to_string();
+                let digest = digest.clone();
 
                 async move {
-                    // Recreate the streaming form inside the closure
+                    // Recreate the streaming form inside the closure; only the
+                    // last attempt counts
+                    digest.reset();
                     let form =
-                        FileUploadRequest::to_streaming_multipart_form(&file_path, "ocr").await?;
+                        FileUploadRequest::to_digesting_multipart_form(file_upload, "ocr", &digest)
+                            .await?;
 
                     let response = client
                         .post(&url)
@@ -396,21 +744,19 @@ impl FilesClient {
                     MistralClient::handle_response(response).await
                 }
             })
+            .await;
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Upload, response, start_time, file_size)
             .await?;
 
-        // Parse response
-        let status = response.status().as_u16();
-        let response_text = response.text().await.map_err(Error::Network)?;
-
-        self.client.log_response(status, Some(response_text.len()));
-
         // Parse JSON response
-        let upload_response: FileUploadResponse = serde_json::from_str(&response_text)
-            .map_err(|e| Error::Api(format!("Failed to parse upload response: {}", e)))?;
+        let upload_response: FileUploadResponse =
+            parse_json(&response_text, "Failed to parse upload response")?;
 
         // Validate response
         upload_response.validate()?;
 
-        Ok(upload_response)
+        Ok((upload_response, digest.finish()))
     }
 }
@@ -6,47 +6,350 @@
 //! The client supports:
 //! - Files API for uploading documents
 //! - OCR API for text extraction
+//! - Chat Completions API for post-processing extracted text
+//! - Embeddings API for semantic search over archives
 //! - Authentication with Bearer tokens
-//! - Retry logic and error handling
+//! - Retry logic with decorrelated jitter and a cool-down after rate limits
+//!   (HTTP 429), shared by all clients of the same account
+//! - A limit on concurrent API calls per account (`max_concurrent_requests`)
+//! - Static DNS overrides and IP family preference (`network`)
 
+use crate::config::{Config, HttpConfig, HttpVersion, NetworkConfig, RetryPolicy};
 use crate::credentials::APICredentials;
-use crate::error::{Error, Result};
+use crate::error::{with_request_id, Error, Result};
+use crate::memory::MemoryBudget;
+use crate::metrics::{ApiOperation, MetricsCollector};
+use dns::ApiResolver;
 use reqwest::{Client, Response};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
+use tokio::sync::Semaphore;
 use tokio::time::sleep;
+use tracing::Instrument;
 
 pub mod auth;
+pub mod chat;
+pub mod dns;
+pub mod embeddings;
 pub mod error;
 pub mod files;
 pub mod ocr;
 
+/// Default limit on concurrent API calls per account
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Default time allowed for establishing a connection
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default limit on the size of a response body
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Limit on the size of an error response body, of which only the message is kept
+const MAX_ERROR_BODY_BYTES: u64 = 64 * 1024;
+
+/// Length of an error message taken from a response body, in characters
+const MAX_ERROR_MESSAGE_CHARS: usize = 1000;
+
+/// Semaphore shared by all clients, with the limit it was created for
+#[derive(Debug, Default)]
+struct RequestLimiter {
+    shared: OnceLock<(usize, Arc<Semaphore>)>,
+}
+
+impl RequestLimiter {
+    /// Get the shared semaphore, created with the first client's limit
+    ///
+    /// It is never replaced: a new semaphore would let calls under a changed
+    /// limit run next to those still holding permits of the old one.
+    fn semaphore(&self, max_concurrent_requests: usize) -> Arc<Semaphore> {
+        let max_concurrent_requests = max_concurrent_requests.max(1);
+        let (limit, semaphore) = self.shared.get_or_init(|| {
+            (
+                max_concurrent_requests,
+                Arc::new(Semaphore::new(max_concurrent_requests)),
+            )
+        });
+        if *limit != max_concurrent_requests {
+            tracing::debug!(
+                "Keeping the account's limit of {} concurrent requests (asked for {})",
+                limit,
+                max_concurrent_requests
+            );
+        }
+        semaphore.clone()
+    }
+}
+
+/// Time until which no task sends requests after a rate limit
+#[derive(Debug, Default)]
+struct CoolDown {
+    until: Mutex<Option<tokio::time::Instant>>,
+}
+
+impl CoolDown {
+    /// Hold off all tasks for at least `delay` from now
+    fn extend(&self, delay: Duration) {
+        let until = tokio::time::Instant::now() + delay;
+        let mut current = self.until.lock().unwrap_or_else(|p| p.into_inner());
+        if current.is_none_or(|current| current < until) {
+            *current = Some(until);
+        }
+    }
+
+    /// Time left in the cool-down, if one is active
+    fn remaining(&self) -> Option<Duration> {
+        let current = *self.until.lock().unwrap_or_else(|p| p.into_inner());
+        current
+            .map(|until| until.saturating_duration_since(tokio::time::Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// Concurrency limit and rate-limit cool-down of one account
+///
+/// Rate limits apply per API key, so clients of the same account share them
+/// while another account (e.g. a `serve` profile with its own key) is not
+/// held back by them.
+#[derive(Debug, Default)]
+struct AccountLimits {
+    limiter: RequestLimiter,
+    cool_down: CoolDown,
+}
+
+/// Limits of the accounts used in this process, by API URL and key hash
+#[derive(Debug, Default)]
+struct AccountRegistry {
+    accounts: Mutex<HashMap<(String, String), Arc<AccountLimits>>>,
+}
+
+impl AccountRegistry {
+    /// Limits shared by all clients of these credentials
+    fn limits(&self, credentials: &APICredentials) -> Arc<AccountLimits> {
+        let key = (
+            credentials.api_base_url.trim_end_matches('/').to_string(),
+            // Only a hash of the key outlives the client
+            format!("{:x}", Sha256::digest(credentials.api_key.as_bytes())),
+        );
+        let mut accounts = self.accounts.lock().unwrap_or_else(|p| p.into_inner());
+        accounts.entry(key).or_default().clone()
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Limits of each account, shared by all clients using it
+    static ref ACCOUNTS: AccountRegistry = AccountRegistry::default();
+}
+
+/// Point in time by which all API work for a document must be finished
+///
+/// Shared by every request, retry, and backoff of a document, so the configured
+/// timeout bounds total wall-clock time rather than each HTTP attempt.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: tokio::time::Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    /// Deadline `budget` from now
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: tokio::time::Instant::now() + budget,
+            budget,
+        }
+    }
+
+    /// Time left before the deadline
+    pub fn remaining(&self) -> Duration {
+        self.at
+            .saturating_duration_since(tokio::time::Instant::now())
+    }
+
+    /// Error reported when the deadline has passed
+    pub fn exceeded_error(&self) -> Error {
+        Error::api(format!(
+            "Request timed out: document processing exceeded the {}s timeout",
+            self.budget.as_secs_f64()
+        ))
+    }
+
+    /// Run a future, failing when it does not finish before the deadline
+    pub async fn run<T, F>(&self, future: F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        tokio::time::timeout_at(self.at, future)
+            .await
+            .unwrap_or_else(|_| Err(self.exceeded_error()))
+    }
+}
+
 /// Base API client for Mistral AI
 #[derive(Debug, Clone)]
 pub struct MistralClient {
     client: Client,
     pub credentials: APICredentials,
     base_url: String,
+    limiter: Arc<Semaphore>,
+    account: Arc<AccountLimits>,
+    deadline: Option<Deadline>,
+    retry_policy: RetryPolicy,
+    metrics: MetricsCollector,
+    max_response_bytes: u64,
+    memory_budget_mb: Option<u64>,
+    memory: MemoryBudget,
+    http: HttpConfig,
+    connections: Option<Arc<AtomicU64>>,
 }
 
 impl MistralClient {
+    /// Create a client with the HTTP settings from the configuration
+    pub fn from_config(credentials: APICredentials, config: &Config) -> Result<Self> {
+        Ok(Self::build(
+            credentials,
+            Duration::from_secs(config.timeout_seconds),
+            Duration::from_secs(config.connect_timeout_seconds),
+            config.max_concurrent_requests,
+            &config.network,
+            &config.http,
+        )?
+        .with_retry_policy(config.retry_policy.clone())
+        .with_max_response_size(config.max_response_size_mb * 1024 * 1024)
+        .with_memory_budget(config.memory_budget_mb))
+    }
+
     /// Create a new Mistral AI API client with compression support
     pub fn new(credentials: APICredentials, timeout_seconds: u64) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(timeout_seconds))
+        Self::build(
+            credentials,
+            Duration::from_secs(timeout_seconds),
+            DEFAULT_CONNECT_TIMEOUT,
+            DEFAULT_MAX_CONCURRENT_REQUESTS,
+            &NetworkConfig::default(),
+            &HttpConfig::default(),
+        )
+    }
+
+    /// Build the client; `timeout` bounds the whole request, `connect_timeout`
+    /// only connection establishment so dead hosts fail fast
+    fn build(
+        credentials: APICredentials,
+        timeout: Duration,
+        connect_timeout: Duration,
+        max_concurrent_requests: usize,
+        network: &NetworkConfig,
+        http: &HttpConfig,
+    ) -> Result<Self> {
+        let mut builder = Client::builder()
+            .timeout(timeout)
+            .connect_timeout(connect_timeout.min(timeout))
             .user_agent(format!("paperless-ngx-ocr2/{}", env!("CARGO_PKG_VERSION")))
             .gzip(true) // Enable gzip compression
             .brotli(true) // Enable brotli compression
-            .deflate(true) // Enable deflate compression
+            .deflate(true); // Enable deflate compression
+
+        builder = builder
+            .pool_idle_timeout(Duration::from_secs(http.pool_idle_timeout_seconds))
+            .pool_max_idle_per_host(http.pool_max_idle_per_host);
+        builder = match http.version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+
+        for resolve in &network.resolve {
+            tracing::debug!("Resolving {} to {}", resolve.host, resolve.address);
+        }
+        let resolver = ApiResolver::for_url(
+            network.ip_preference,
+            network.resolve.clone(),
+            &credentials.api_base_url,
+        );
+        // Hosts given as IP addresses are connected to without a lookup
+        let connections = match reqwest::Url::parse(&credentials.api_base_url) {
+            Ok(url) if matches!(url.host(), Some(url::Host::Domain(_))) => Some(resolver.lookups()),
+            _ => None,
+        };
+        builder = builder.dns_resolver(Arc::new(resolver));
+
+        let client = builder
             .build()
-            .map_err(|e| Error::Internal(format!("Failed to create HTTP client: {}", e)))?;
+            .map_err(|e| Error::internal_from("Failed to create HTTP client", e))?;
 
+        let account = ACCOUNTS.limits(&credentials);
         Ok(Self {
             client,
             base_url: credentials.api_base_url.clone(),
             credentials,
+            limiter: account.limiter.semaphore(max_concurrent_requests),
+            account,
+            deadline: None,
+            retry_policy: RetryPolicy::default(),
+            metrics: MetricsCollector::new(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            memory_budget_mb: None,
+            memory: MemoryBudget::default(),
+            http: http.clone(),
+            connections,
         })
     }
 
+    /// Retry rate-limited requests according to this policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Fail responses whose (decompressed) body is larger than `bytes`
+    pub fn with_max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = bytes;
+        self
+    }
+
+    /// Hold documents read into memory for upload within a memory budget of
+    /// `budget_mb` (see [`crate::memory`])
+    pub fn with_memory_budget(mut self, budget_mb: Option<u64>) -> Self {
+        self.memory_budget_mb = budget_mb;
+        self
+    }
+
+    /// Reserve upload buffers from the run's memory budget, shared with its
+    /// other clients
+    pub fn with_memory(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Memory budget in MB, if any
+    pub fn memory_budget_mb(&self) -> Option<u64> {
+        self.memory_budget_mb
+    }
+
+    /// Memory budget the upload buffers are reserved from
+    pub fn memory(&self) -> &MemoryBudget {
+        &self.memory
+    }
+
+    /// Record API metrics in the given collector instead of a private one
+    pub fn with_metrics(mut self, metrics: MetricsCollector) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Get the collector receiving this client's API metrics
+    pub fn metrics(&self) -> &MetricsCollector {
+        &self.metrics
+    }
+
+    /// Bound all requests made through this client, including retries, by a deadline
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
     /// Get the HTTP client
     pub fn client(&self) -> &Client {
         &self.client
@@ -79,22 +382,94 @@ impl MistralClient {
             Ok(response)
         } else {
             let status_code = status.as_u16();
-            let error_text = response
-                .text()
+            let request_id = request_id(&response);
+            // Error bodies are not read beyond a small limit, whatever their size
+            let error_text = read_limited(response, MAX_ERROR_BODY_BYTES)
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
 
             // Try to parse as JSON error response
-            if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-                if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                    Err(Error::from_http_status(status_code, error_msg.to_string()))
-                } else {
-                    Err(Error::from_http_status(status_code, error_text))
+            let message = serde_json::from_str::<serde_json::Value>(&error_text)
+                .ok()
+                .and_then(|error_json| error_json.get("error")?.as_str().map(str::to_string))
+                .unwrap_or(error_text);
+            let message = truncate_message(message);
+            tracing::debug!(
+                "API Response: {} (request ID: {})",
+                status_code,
+                request_id.as_deref().unwrap_or("none")
+            );
+            Err(Error::http(status_code, message, request_id))
+        }
+    }
+
+    /// Read the body of a response from `execute_with_retry` and record the
+    /// call in the metrics
+    ///
+    /// Downloaded bytes are the size of the body after reqwest has removed any
+    /// gzip, brotli, or deflate content encoding. Bodies above the client's
+    /// response size limit fail without being buffered in full.
+    pub async fn read_body(
+        &self,
+        operation: ApiOperation,
+        response: Result<Response>,
+        start_time: std::time::Instant,
+        bytes_uploaded: u64,
+    ) -> Result<String> {
+        let body = match response {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let request_id = request_id(&response);
+                let body = read_limited(response, self.max_response_bytes).await;
+                if let Ok(body) = &body {
+                    self.log_response(status, Some(body.len()), request_id.as_deref());
                 }
-            } else {
-                Err(Error::from_http_status(status_code, error_text))
+                body
             }
+            Err(e) => Err(e),
+        };
+
+        let duration = start_time.elapsed();
+        self.metrics
+            .record_operation_latency(operation, duration)
+            .await;
+        match &body {
+            Ok(body) => {
+                self.metrics
+                    .record_success(duration, bytes_uploaded, body.len() as u64)
+                    .await
+            }
+            Err(_) => self.metrics.record_failure(duration).await,
         }
+
+        body
+    }
+
+    /// Serialize a JSON request body, compressed as configured in `[http]`
+    pub fn json_body<T: serde::Serialize>(&self, value: &T) -> Result<JsonBody> {
+        let json = serde_json::to_vec(value)
+            .map_err(|e| Error::internal_from("Failed to serialize request", e))?;
+        let compression = self.http.request_compression;
+
+        if compression.content_encoding().is_none() || json.len() < self.http.compression_min_bytes
+        {
+            return Ok(JsonBody {
+                bytes: json,
+                content_encoding: None,
+            });
+        }
+
+        let bytes = compression.compress(&json)?;
+        tracing::debug!(
+            "Request body compressed with {}: {} -> {} bytes",
+            compression,
+            json.len(),
+            bytes.len()
+        );
+        Ok(JsonBody {
+            bytes,
+            content_encoding: compression.content_encoding(),
+        })
     }
 
     /// Log API request details (for debugging)
@@ -108,11 +483,22 @@ impl MistralClient {
     }
 
     /// Log API response details (for debugging)
-    pub fn log_response(&self, status: u16, response_size: Option<usize>) {
+    pub fn log_response(
+        &self,
+        status: u16,
+        response_size: Option<usize>,
+        request_id: Option<&str>,
+    ) {
+        let request_id = request_id.unwrap_or("none");
         if let Some(size) = response_size {
-            tracing::debug!("API Response: {} ({} bytes)", status, size);
+            tracing::debug!(
+                "API Response: {} ({} bytes, request ID: {})",
+                status,
+                size,
+                request_id
+            );
         } else {
-            tracing::debug!("API Response: {}", status);
+            tracing::debug!("API Response: {} (request ID: {})", status, request_id);
         }
     }
 
@@ -139,65 +525,755 @@ impl MistralClient {
         }
     }
 
-    /// Execute request with retry logic for rate limits
+    /// Execute request with retry logic for transient failures
+    ///
+    /// Failures are retried when [`Error::is_retryable`] says so (rate limits,
+    /// gateway errors, connection resets); other errors return immediately.
+    /// The delay grows with decorrelated jitter (see [`RetryPolicy::next_delay`]),
+    /// honoring `Retry-After`, and after HTTP 429 a cool-down holds back the
+    /// other workers of the same account until it has passed.
     pub async fn execute_with_retry<F, Fut>(&self, request_fn: F) -> Result<Response>
     where
         F: Fn() -> Fut,
         Fut: std::future::Future<Output = Result<Response>>,
     {
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 1000; // 1 second base delay
-
-        for attempt in 0..=MAX_RETRIES {
-            match request_fn().await {
-                Ok(response) => {
-                    let status = response.status();
-
-                    // Check if it's a rate limit error (HTTP 429)
-                    if status == 429 {
-                        if attempt < MAX_RETRIES {
-                            let delay_ms = BASE_DELAY_MS * 2_u64.pow(attempt); // Exponential backoff
-                            tracing::warn!(
-                                "Rate limit hit (HTTP 429), retrying in {}ms (attempt {}/{})",
-                                delay_ms,
-                                attempt + 1,
-                                MAX_RETRIES
-                            );
-                            sleep(Duration::from_millis(delay_ms)).await;
-                            continue;
-                        } else {
-                            return Err(Error::from_http_status(
-                                429,
-                                "Rate limit exceeded after 3 retries".to_string(),
-                            ));
-                        }
-                    }
+        let policy = &self.retry_policy;
+        let mut delay = Duration::from_millis(policy.base_delay_ms);
+
+        for attempt in 0..=policy.max_retries {
+            self.wait_for_cool_down().await?;
 
-                    return Ok(response);
+            let span = tracing::debug_span!(
+                "api_attempt",
+                attempt = attempt + 1,
+                max_attempts = policy.max_retries + 1
+            );
+            let outcome = async {
+                match self.deadline {
+                    Some(deadline) => deadline.run(self.send_limited(&request_fn)).await,
+                    None => self.send_limited(&request_fn).await,
                 }
-                Err(e) => {
-                    // Check if it's a rate limit error by checking the error message
-                    if let Error::Api(ref api_error) = e {
-                        if (api_error.contains("429") || api_error.contains("rate limit"))
-                            && attempt < MAX_RETRIES
-                        {
-                            let delay_ms = BASE_DELAY_MS * 2_u64.pow(attempt);
-                            tracing::warn!(
-                                "Rate limit hit (HTTP 429), retrying in {}ms (attempt {}/{})",
-                                delay_ms,
-                                attempt + 1,
-                                MAX_RETRIES
-                            );
-                            sleep(Duration::from_millis(delay_ms)).await;
-                            continue;
-                        }
-                    }
+            }
+            .instrument(span)
+            .await;
+            self.record_connections().await;
 
-                    return Err(e);
+            // Transient failures arrive as responses with a retryable status or as
+            // errors (when the request maps statuses through `handle_response`)
+            let (rate_limited, retry_after) = match &outcome {
+                Ok(response) if Error::is_retryable_status(response.status().as_u16()) => {
+                    (response.status() == 429, retry_after(response))
                 }
+                Err(e) if e.is_retryable() => (e.is_rate_limit(), None),
+                _ => return outcome,
+            };
+            if rate_limited {
+                self.metrics.record_rate_limit_hit().await;
+            }
+
+            if attempt == policy.max_retries {
+                if !rate_limited {
+                    return outcome;
+                }
+                let request_id = match &outcome {
+                    Ok(response) => request_id(response),
+                    Err(e) => e.request_id().map(str::to_string),
+                };
+                return Err(Error::http(
+                    429,
+                    format!("Rate limit exceeded after {} retries", policy.max_retries),
+                    request_id,
+                ));
+            }
+
+            self.metrics.record_retry().await;
+            delay = policy.next_delay(attempt + 1, delay);
+            if let Some(retry_after) = retry_after {
+                delay = delay.max(retry_after);
+            }
+            if rate_limited {
+                self.account.cool_down.extend(delay);
+                tracing::warn!(
+                    "Rate limit hit (HTTP 429), retrying in {}ms (attempt {}/{})",
+                    delay.as_millis(),
+                    attempt + 1,
+                    policy.max_retries
+                );
+            } else {
+                tracing::warn!(
+                    "Transient failure ({}), retrying in {}ms (attempt {}/{})",
+                    describe_outcome(&outcome),
+                    delay.as_millis(),
+                    attempt + 1,
+                    policy.max_retries
+                );
             }
+            self.backoff(delay).await?;
         }
 
         unreachable!()
     }
+
+    /// Record a sent request and the connections opened for it
+    async fn record_connections(&self) {
+        if let Some(ref connections) = self.connections {
+            let opened = connections.swap(0, Ordering::Relaxed);
+            self.metrics.record_request_sent(opened).await;
+        }
+    }
+
+    /// Wait out a cool-down started by any task of this account, plus a random share of the
+    /// base delay so waiting tasks do not resume at the same instant
+    async fn wait_for_cool_down(&self) -> Result<()> {
+        if let Some(remaining) = self.account.cool_down.remaining() {
+            let spread = rand::random::<f64>() * self.retry_policy.base_delay_ms as f64;
+            let wait = remaining + Duration::from_millis(spread as u64);
+            tracing::debug!(
+                "Waiting {}ms for the rate limit cool-down",
+                wait.as_millis()
+            );
+            self.backoff(wait).await?;
+        }
+        Ok(())
+    }
+
+    /// Send one request while holding a permit, so waiting in backoff frees the slot
+    async fn send_limited<F, Fut>(&self, request_fn: &F) -> Result<Response>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<Response>>,
+    {
+        let _permit = self
+            .limiter
+            .acquire()
+            .await
+            .map_err(|e| Error::internal_from("Request limiter closed", e))?;
+        request_fn().await
+    }
+
+    /// Wait before a retry; fails at once when the wait would pass the deadline
+    async fn backoff(&self, delay: Duration) -> Result<()> {
+        if let Some(deadline) = self.deadline {
+            if delay >= deadline.remaining() {
+                return Err(deadline.exceeded_error());
+            }
+        }
+        sleep(delay).await;
+        Ok(())
+    }
+}
+
+/// Short description of a failed attempt for logging
+fn describe_outcome(outcome: &Result<Response>) -> String {
+    match outcome {
+        Ok(response) => with_request_id(
+            format!("HTTP {}", response.status().as_u16()),
+            request_id(response).as_deref(),
+        ),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// A serialized JSON request body from [`MistralClient::json_body`]
+#[derive(Debug, Clone)]
+pub struct JsonBody {
+    bytes: Vec<u8>,
+    content_encoding: Option<&'static str>,
+}
+
+impl JsonBody {
+    /// Size of the body as sent
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the body is empty
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Attach the body with its content type and encoding to a request
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(self.bytes.clone());
+        match self.content_encoding {
+            Some(encoding) => request.header(reqwest::header::CONTENT_ENCODING, encoding),
+            None => request,
+        }
+    }
+}
+
+/// Read a response body as text, failing once it grows beyond `limit` bytes
+async fn read_limited(mut response: Response, limit: u64) -> Result<String> {
+    let too_large = || {
+        Error::api(format!(
+            "Response body exceeds the limit of {} bytes (max_response_size_mb)",
+            limit
+        ))
+    };
+    // Content-Length is absent when reqwest decompresses the body
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(Error::Network)? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8(body)
+        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+}
+
+/// Shorten an error message from a response body to `MAX_ERROR_MESSAGE_CHARS`
+fn truncate_message(message: String) -> String {
+    match message.char_indices().nth(MAX_ERROR_MESSAGE_CHARS) {
+        Some((index, _)) => format!("{}...", &message[..index]),
+        None => message,
+    }
+}
+
+/// Response headers carrying the provider's ID of a request, in order of preference
+pub const REQUEST_ID_HEADERS: &[&str] = &["x-request-id", "mistral-correlation-id", "request-id"];
+
+/// Provider ID of the request a response answers, for support tickets
+pub fn request_id(response: &Response) -> Option<String> {
+    REQUEST_ID_HEADERS.iter().find_map(|name| {
+        let value = response.headers().get(*name)?.to_str().ok()?.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Delay requested by a `Retry-After` header in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dns::IpPreference;
+    use super::*;
+
+    #[test]
+    fn test_request_limiter_is_never_replaced() {
+        let limiter = RequestLimiter::default();
+        let first = limiter.semaphore(3);
+        let second = limiter.semaphore(3);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.available_permits(), 3);
+
+        let _permit = first.clone().try_acquire_owned().unwrap();
+        let resized = limiter.semaphore(5);
+        assert!(Arc::ptr_eq(&first, &resized));
+        assert_eq!(resized.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cool_down_only_extends() {
+        let cool_down = CoolDown::default();
+        assert!(cool_down.remaining().is_none());
+
+        cool_down.extend(Duration::from_secs(10));
+        cool_down.extend(Duration::from_secs(1));
+        let remaining = cool_down.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(9), "{:?}", remaining);
+    }
+
+    #[tokio::test]
+    async fn test_accounts_do_not_share_rate_limits() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let client = |api_key: &str| {
+            let credentials =
+                APICredentials::new(api_key.to_string(), "https://api.mistral.ai".to_string())
+                    .unwrap();
+            MistralClient::new(credentials, 5).unwrap()
+        };
+        let tenant_a = client("sk-tenant-a-cool-down");
+        let tenant_b = client("sk-tenant-b-cool-down");
+
+        // A rate limit of one profile holds back its other clients only
+        tenant_a.account.cool_down.extend(Duration::from_secs(60));
+        assert!(client("sk-tenant-a-cool-down")
+            .account
+            .cool_down
+            .remaining()
+            .is_some());
+        assert!(tenant_b.account.cool_down.remaining().is_none());
+        assert!(!Arc::ptr_eq(&tenant_a.limiter, &tenant_b.limiter));
+
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            tenant_b.execute_with_retry(|| {
+                let request = tenant_b.client().get(server.uri());
+                async move { request.send().await.map_err(Error::Network) }
+            }),
+        )
+        .await
+        .expect("the other profile waited for the cool-down")
+        .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_request_is_retried() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let credentials = APICredentials::new(
+            "sk-test123456789".to_string(),
+            "https://api.mistral.ai".to_string(),
+        )
+        .unwrap();
+        let metrics = MetricsCollector::new();
+        let client = MistralClient::new(credentials, 5)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                base_delay_ms: 10,
+                max_delay_ms: 50,
+                ..RetryPolicy::default()
+            })
+            .with_metrics(metrics.clone());
+
+        let response = client
+            .execute_with_retry(|| {
+                let request = client.client().get(server.uri());
+                async move { request.send().await.map_err(Error::Network) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        // The collector passed in receives the client's metrics
+        let recorded = metrics.get_metrics().await;
+        assert_eq!(recorded.total_retries, 1);
+        assert_eq!(recorded.rate_limit_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_transient_errors_are_retried() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/bad-request"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/unavailable"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let credentials = APICredentials::new(
+            "sk-test123456789".to_string(),
+            "https://api.mistral.ai".to_string(),
+        )
+        .unwrap();
+        let client = MistralClient::new(credentials, 5)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                base_delay_ms: 10,
+                max_delay_ms: 50,
+                ..RetryPolicy::default()
+            });
+        async fn get(client: &MistralClient, url: String) -> Result<Response> {
+            client
+                .execute_with_retry(|| {
+                    let request = client.client().get(&url);
+                    async move {
+                        let response = request.send().await.map_err(Error::Network)?;
+                        MistralClient::handle_response(response).await
+                    }
+                })
+                .await
+        }
+
+        let url = |route: &str| format!("{}{}", server.uri(), route);
+        let error = get(&client, url("/bad-request")).await.unwrap_err();
+        assert_eq!(error.http_status(), Some(400));
+        let error = get(&client, url("/unavailable")).await.unwrap_err();
+        assert_eq!(error.http_status(), Some(503));
+
+        let requests = server.received_requests().await.unwrap();
+        let count = |route: &str| requests.iter().filter(|r| r.url.path() == route).count();
+        assert_eq!(count("/bad-request"), 1);
+        assert_eq!(count("/unavailable"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_error_includes_request_id() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .insert_header("x-request-id", "req-abc123")
+                    .set_body_json(serde_json::json!({"error": "Invalid model"})),
+            )
+            .mount(&server)
+            .await;
+
+        let response = reqwest::get(server.uri()).await.unwrap();
+        let error = MistralClient::handle_response(response).await.unwrap_err();
+
+        assert_eq!(error.request_id(), Some("req-abc123"));
+        assert_eq!(
+            error.to_string(),
+            "Validation error: Client error (400): Invalid model (request ID: req-abc123)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_body_records_decompressed_size() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = "x".repeat(10_000);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(crate::compress::gzip(body.as_bytes()).unwrap()),
+            )
+            .mount(&server)
+            .await;
+
+        let credentials = APICredentials::new(
+            "sk-test123456789".to_string(),
+            "https://api.mistral.ai".to_string(),
+        )
+        .unwrap();
+        let client = MistralClient::new(credentials, 5).unwrap();
+
+        let start_time = std::time::Instant::now();
+        let response = client
+            .execute_with_retry(|| {
+                let request = client.client().get(server.uri());
+                async move { request.send().await.map_err(Error::Network) }
+            })
+            .await;
+        let text = client
+            .read_body(ApiOperation::Ocr, response, start_time, 42)
+            .await
+            .unwrap();
+        assert_eq!(text, body);
+
+        let recorded = client.metrics().get_metrics().await;
+        assert_eq!(recorded.total_bytes_downloaded, 10_000);
+        assert_eq!(recorded.total_bytes_uploaded, 42);
+    }
+
+    #[tokio::test]
+    async fn test_read_body_enforces_response_size_limit() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = "x".repeat(10_000);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/plain"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body.clone()))
+            .mount(&server)
+            .await;
+        // Compressed bodies are limited by their decompressed size
+        Mock::given(method("GET"))
+            .and(path("/gzip"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(crate::compress::gzip(body.as_bytes()).unwrap()),
+            )
+            .mount(&server)
+            .await;
+
+        let credentials = APICredentials::new(
+            "sk-test123456789".to_string(),
+            "https://api.mistral.ai".to_string(),
+        )
+        .unwrap();
+        async fn read(client: &MistralClient, url: String) -> Result<String> {
+            let response = client
+                .execute_with_retry(|| {
+                    let request = client.client().get(&url);
+                    async move { request.send().await.map_err(Error::Network) }
+                })
+                .await;
+            client
+                .read_body(ApiOperation::Ocr, response, std::time::Instant::now(), 0)
+                .await
+        }
+
+        let limited = MistralClient::new(credentials.clone(), 5)
+            .unwrap()
+            .with_max_response_size(1_000);
+        for route in ["/plain", "/gzip"] {
+            let error = read(&limited, format!("{}{}", server.uri(), route))
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("exceeds the limit"), "{}", error);
+            assert!(!error.is_retryable());
+        }
+        assert_eq!(limited.metrics().get_metrics().await.failed_calls, 2);
+
+        let client = MistralClient::new(credentials, 5)
+            .unwrap()
+            .with_max_response_size(10_000);
+        let text = read(&client, format!("{}/gzip", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(text, body);
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_are_limited_and_truncated() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/huge"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("x".repeat(1024 * 1024)))
+            .mount(&server)
+            .await;
+        Mock::given(path("/long"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("y".repeat(5_000)))
+            .mount(&server)
+            .await;
+
+        let response = reqwest::get(format!("{}/huge", server.uri()))
+            .await
+            .unwrap();
+        let error = MistralClient::handle_response(response).await.unwrap_err();
+        assert!(error.to_string().contains("Unknown error"), "{}", error);
+
+        let response = reqwest::get(format!("{}/long", server.uri()))
+            .await
+            .unwrap();
+        let error = MistralClient::handle_response(response).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(&format!("{}...", "y".repeat(1000))));
+        assert!(!error.to_string().contains(&"y".repeat(1001)));
+    }
+
+    #[tokio::test]
+    async fn test_json_body_compression() {
+        use crate::compress::RequestCompression;
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("content-encoding", "zstd"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            api_key: "sk-test123456789".to_string(),
+            http: HttpConfig {
+                request_compression: RequestCompression::Zstd,
+                compression_min_bytes: 100,
+                ..HttpConfig::default()
+            },
+            ..Config::default()
+        };
+        let credentials = APICredentials::from_config(&config).unwrap();
+        let client = MistralClient::from_config(credentials, &config).unwrap();
+
+        // Small bodies are not worth compressing
+        let small = client.json_body(&serde_json::json!({"a": 1})).unwrap();
+        assert_eq!(small.len(), 7);
+
+        let value = serde_json::json!({ "schema": "field ".repeat(100) });
+        let body = client.json_body(&value).unwrap();
+        assert!(body.len() < 100);
+        let response = body
+            .apply(client.client().post(server.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = &server.received_requests().await.unwrap()[0];
+        let decoded: serde_json::Value =
+            serde_json::from_slice(&zstd::decode_all(received.body.as_slice()).unwrap()).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[tokio::test]
+    async fn test_connection_reuse_is_counted() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        // Connections are counted by name lookups, so address the server by name
+        let url = format!("http://localhost:{}/", server.address().port());
+
+        async fn get_twice(max_idle: usize, url: &str) -> crate::metrics::APIMetrics {
+            let config = Config {
+                api_key: "sk-test123456789".to_string(),
+                http: HttpConfig {
+                    pool_max_idle_per_host: max_idle,
+                    ..HttpConfig::default()
+                },
+                ..Config::default()
+            };
+            let credentials = APICredentials::from_config(&config).unwrap();
+            let client = MistralClient::from_config(credentials, &config).unwrap();
+            for _ in 0..2 {
+                let response = client
+                    .execute_with_retry(|| {
+                        let request = client.client().get(url);
+                        async move { request.send().await.map_err(Error::Network) }
+                    })
+                    .await
+                    .unwrap();
+                // Reading the body returns the connection to the pool
+                response.bytes().await.unwrap();
+            }
+            client.metrics().get_metrics().await
+        }
+
+        let pooled = get_twice(32, &url).await;
+        assert_eq!(pooled.requests_sent, 2);
+        assert_eq!(pooled.connections_opened, 1);
+        assert_eq!(pooled.connections_reused(), 1);
+
+        let unpooled = get_twice(0, &url).await;
+        assert_eq!(unpooled.connections_opened, 2);
+        assert_eq!(unpooled.connections_reused(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_http_version_setting() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        for (version, expected) in [
+            (HttpVersion::Http1, reqwest::Version::HTTP_11),
+            (HttpVersion::Http2, reqwest::Version::HTTP_2),
+        ] {
+            let config = Config {
+                api_key: "sk-test123456789".to_string(),
+                http: HttpConfig {
+                    version,
+                    ..HttpConfig::default()
+                },
+                ..Config::default()
+            };
+            let credentials = APICredentials::from_config(&config).unwrap();
+            let client = MistralClient::from_config(credentials, &config).unwrap();
+
+            let response = client.client().get(server.uri()).send().await.unwrap();
+            assert_eq!(response.version(), expected, "{:?}", version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_from_config_with_network_settings() {
+        let config = Config {
+            api_key: "sk-test123456789".to_string(),
+            network: NetworkConfig {
+                resolve: vec!["api.mistral.ai:443:10.0.0.5".parse().unwrap()],
+                ip_preference: IpPreference::Ipv4,
+            },
+            ..Config::default()
+        };
+        let credentials = APICredentials::from_config(&config).unwrap();
+
+        assert!(MistralClient::from_config(credentials, &config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_deadline_stops_slow_future() {
+        let deadline = Deadline::after(Duration::from_millis(20));
+        let result = deadline
+            .run(async {
+                sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+
+        assert!(
+            matches!(result, Err(Error::Api { ref message, .. }) if message.contains("timed out"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_backoff_respects_deadline() {
+        let credentials = APICredentials::new(
+            "sk-test123456789".to_string(),
+            "https://api.mistral.ai".to_string(),
+        )
+        .unwrap();
+        let client = MistralClient::new(credentials, 30)
+            .unwrap()
+            .with_deadline(Deadline::after(Duration::from_millis(200)));
+
+        // A rate-limited request would back off for a second, past the deadline
+        let started = std::time::Instant::now();
+        let result = client
+            .execute_with_retry(|| async {
+                Err(Error::from_http_status(429, "rate limit".to_string()))
+            })
+            .await;
+
+        assert!(
+            matches!(result, Err(Error::Api { ref message, .. }) if message.contains("timed out"))
+        );
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
 }
@@ -8,53 +8,200 @@
 //! 2. Process file via OCR API (/v1/ocr) using the file ID
 
 use crate::api::MistralClient;
-use crate::error::{Error, Result};
-use crate::metrics::GLOBAL_METRICS;
+use crate::error::{parse_json, Error, Result};
+use crate::metrics::ApiOperation;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
 use std::time::Instant;
 
+/// Model requested for OCR
+pub const OCR_MODEL: &str = "mistral-ocr-latest";
+
+/// Version of the OCR endpoint
+pub const OCR_API_VERSION: &str = "v1";
+
+lazy_static::lazy_static! {
+    /// Markdown image references the OCR API inserts for embedded images
+    static ref IMAGE_REFERENCE: Regex = Regex::new(r"!\[[^\]]*\]\([^)]*\)").expect("valid regex");
+}
+
 /// OCR request structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OCRRequest {
     pub model: String,
     pub document: DocumentChunk,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_annotation_format: Option<AnnotationFormat>,
+    /// Free-form guidance for the model (e.g. "preserve table structure")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Output format of the page text (markdown when not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Output format of the page text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// Markdown with headings, tables and image references
+    #[default]
+    Markdown,
+    /// Plain text without markdown syntax
+    Text,
+}
+
+impl FromStr for ResponseFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "text" | "plain" => Ok(Self::Text),
+            other => Err(format!(
+                "Unknown response format: {}. Supported: markdown, text",
+                other
+            )),
+        }
+    }
+}
+
+/// Optional settings sent with an OCR request
+#[derive(Debug, Clone, Default)]
+pub struct OCROptions {
+    /// Structured document annotation to request
+    pub annotation_format: Option<AnnotationFormat>,
+
+    /// Free-form guidance for the model
+    pub instructions: Option<String>,
+
+    /// Output format of the page text
+    pub response_format: Option<ResponseFormat>,
+
+    /// Model to read the document with instead of [`OCR_MODEL`]
+    pub model: Option<String>,
 }
 
 /// Document chunk structure for OCR
+///
+/// References an uploaded file (`file`) or a URL the OCR service fetches
+/// itself (`document_url` for PDFs, `image_url` for images).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentChunk {
     #[serde(rename = "type")]
     pub chunk_type: String,
-    pub file_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+}
+
+impl DocumentChunk {
+    /// Reference an uploaded file by its ID
+    pub fn file(file_id: String) -> Self {
+        Self {
+            chunk_type: "file".to_string(),
+            file_id: Some(file_id),
+            document_url: None,
+            image_url: None,
+        }
+    }
+
+    /// Reference a document (PDF) by URL
+    pub fn document_url(url: String) -> Self {
+        Self {
+            chunk_type: "document_url".to_string(),
+            file_id: None,
+            document_url: Some(url),
+            image_url: None,
+        }
+    }
+
+    /// Reference an image by URL
+    pub fn image_url(url: String) -> Self {
+        Self {
+            chunk_type: "image_url".to_string(),
+            file_id: None,
+            document_url: None,
+            image_url: Some(url),
+        }
+    }
+}
+
+/// Annotation format requesting structured output for the whole document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationFormat {
+    #[serde(rename = "type")]
+    pub format_type: String,
+    pub json_schema: JsonSchema,
+}
+
+/// Named JSON schema used for document annotations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchema {
+    pub name: String,
+    pub schema: serde_json::Value,
+    pub strict: bool,
+}
+
+impl AnnotationFormat {
+    /// Create a strict JSON schema annotation format
+    pub fn json_schema(name: &str, schema: serde_json::Value) -> Self {
+        Self {
+            format_type: "json_schema".to_string(),
+            json_schema: JsonSchema {
+                name: name.to_string(),
+                schema,
+                strict: true,
+            },
+        }
+    }
 }
 
 impl OCRRequest {
     /// Create a new OCR request
     pub fn new(file_id: String) -> Self {
+        Self::for_document(DocumentChunk::file(file_id))
+    }
+
+    /// Create a new OCR request for any document reference
+    pub fn for_document(document: DocumentChunk) -> Self {
         Self {
-            model: "mistral-ocr-latest".to_string(),
-            document: DocumentChunk {
-                chunk_type: "file".to_string(),
-                file_id,
-            },
+            model: OCR_MODEL.to_string(),
+            document,
+            document_annotation_format: None,
+            instructions: None,
+            response_format: None,
         }
     }
 
     /// Validate the OCR request
     pub fn validate(&self) -> Result<()> {
-        if self.document.file_id.is_empty() {
-            return Err(Error::Validation("File ID cannot be empty".to_string()));
-        }
+        let reference = match self.document.chunk_type.as_str() {
+            "file" => &self.document.file_id,
+            "document_url" => &self.document.document_url,
+            "image_url" => &self.document.image_url,
+            _ => {
+                return Err(Error::validation(
+                    "Invalid document type for OCR processing".to_string(),
+                ))
+            }
+        };
 
-        if self.model != "mistral-ocr-latest" {
-            return Err(Error::Validation(
-                "Invalid model for OCR processing".to_string(),
-            ));
+        if reference.as_deref().unwrap_or_default().is_empty() {
+            return Err(Error::validation(match self.document.chunk_type.as_str() {
+                "file" => "File ID cannot be empty".to_string(),
+                _ => "Document URL cannot be empty".to_string(),
+            }));
         }
 
-        if self.document.chunk_type != "file" {
-            return Err(Error::Validation(
-                "Invalid document type for OCR processing".to_string(),
+        if self.model.is_empty() {
+            return Err(Error::validation(
+                "Invalid model for OCR processing".to_string(),
             ));
         }
 
@@ -91,6 +238,91 @@ pub struct Page {
     pub dimensions: Dimensions,
 }
 
+impl Page {
+    /// Number of alphanumeric characters in the page text, ignoring image references
+    pub fn text_char_count(&self) -> usize {
+        IMAGE_REFERENCE
+            .replace_all(&self.markdown, "")
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .count()
+    }
+
+    /// Whether the page has less text than the threshold (empty pages are always blank)
+    pub fn is_blank(&self, threshold: usize) -> bool {
+        self.text_char_count() < threshold.max(1)
+    }
+
+    /// SHA-256 of the page text with image references removed and whitespace collapsed
+    pub fn text_hash(&self) -> [u8; 32] {
+        let text = IMAGE_REFERENCE.replace_all(&self.markdown, "");
+        let normalized = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        Sha256::digest(normalized.as_bytes()).into()
+    }
+}
+
+/// A page repeating the text of the page before it (e.g. a double-fed sheet)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicatePage {
+    /// Index of the repeated page
+    pub index: i32,
+
+    /// Index of the first page with the same text
+    pub duplicate_of: i32,
+}
+
+/// How page texts are joined into the document text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageLayout {
+    /// Text placed between pages
+    #[serde(default = "default_page_separator")]
+    pub separator: String,
+
+    /// Line placed before each page; `{page}` is replaced by the page number
+    #[serde(default)]
+    pub header: Option<String>,
+}
+
+/// Page header used by `--page-header` without a template
+pub const DEFAULT_PAGE_HEADER: &str = "--- page {page} ---";
+
+fn default_page_separator() -> String {
+    "\n\n".to_string()
+}
+
+impl Default for PageLayout {
+    fn default() -> Self {
+        Self {
+            separator: default_page_separator(),
+            header: None,
+        }
+    }
+}
+
+/// Expand `\n`, `\t`, `\f` and `\\` escapes typed on the command line
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
 /// Usage information for OCR response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageInfo {
@@ -110,25 +342,92 @@ pub struct OCRResponse {
 impl OCRResponse {
     /// Get extracted text from the response
     pub fn get_extracted_text(&self) -> String {
-        self.pages
+        self.extracted_text(&PageLayout::default(), None)
+    }
+
+    /// Get extracted text, omitting blank pages
+    pub fn get_extracted_text_skipping_blank(&self, threshold: usize) -> String {
+        self.extracted_text(&PageLayout::default(), Some(threshold))
+    }
+
+    /// Pages in page order, optionally omitting blank pages
+    pub fn ordered_pages(&self, skip_blank: Option<usize>) -> Vec<&Page> {
+        // The API does not guarantee page order
+        let mut pages: Vec<&Page> = self
+            .pages
+            .iter()
+            .filter(|page| skip_blank.is_none_or(|threshold| !page.is_blank(threshold)))
+            .collect();
+        pages.sort_by_key(|page| page.index);
+        pages
+    }
+
+    /// Join the page texts in page order, optionally omitting blank pages
+    pub fn extracted_text(&self, layout: &PageLayout, skip_blank: Option<usize>) -> String {
+        self.ordered_pages(skip_blank)
             .iter()
-            .map(|page| page.markdown.clone())
+            .map(|page| match &layout.header {
+                Some(header) => format!(
+                    "{}\n{}",
+                    header.replace("{page}", &(page.index + 1).to_string()),
+                    page.markdown
+                ),
+                None => page.markdown.clone(),
+            })
             .collect::<Vec<String>>()
-            .join("\n\n")
+            .join(&layout.separator)
+    }
+
+    /// Pages whose text repeats the preceding page
+    ///
+    /// Blank pages are ignored, so the blank back of a double-fed sheet does not
+    /// hide the repeated front.
+    pub fn duplicate_pages(&self, blank_threshold: usize) -> Vec<DuplicatePage> {
+        let mut duplicates = Vec::new();
+        let mut previous: Option<([u8; 32], i32)> = None;
+
+        for page in self.ordered_pages(Some(blank_threshold)) {
+            let hash = page.text_hash();
+            match previous {
+                Some((previous_hash, original)) if previous_hash == hash => {
+                    duplicates.push(DuplicatePage {
+                        index: page.index,
+                        duplicate_of: original,
+                    });
+                }
+                _ => previous = Some((hash, page.index)),
+            }
+        }
+
+        duplicates
+    }
+
+    /// Drop the pages with the given indices
+    pub fn remove_pages(&mut self, indices: &[i32]) {
+        self.pages.retain(|page| !indices.contains(&page.index));
+    }
+
+    /// Indices of pages whose text is below the blank page threshold
+    pub fn blank_page_indices(&self, threshold: usize) -> Vec<i32> {
+        self.pages
+            .iter()
+            .filter(|page| page.is_blank(threshold))
+            .map(|page| page.index)
+            .collect()
     }
 
     /// Validate the OCR response
     pub fn validate(&self) -> Result<()> {
         // Validate model field
         if self.model.is_empty() {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "Response model cannot be empty".to_string(),
             ));
         }
 
         // Validate model name format
         if !self.model.starts_with("mistral-") {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Invalid model name format: expected 'mistral-*', got '{}'",
                 self.model
             )));
@@ -136,7 +435,7 @@ impl OCRResponse {
 
         // Validate pages array
         if self.pages.is_empty() {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "Response must contain at least one page".to_string(),
             ));
         }
@@ -144,7 +443,7 @@ impl OCRResponse {
         // Validate each page structure and content
         for (i, page) in self.pages.iter().enumerate() {
             if page.index != i as i32 {
-                return Err(Error::Validation(format!(
+                return Err(Error::validation(format!(
                     "Page index mismatch: expected {}, got {}",
                     i, page.index
                 )));
@@ -157,7 +456,7 @@ impl OCRResponse {
 
             // Validate dimensions if present
             if page.dimensions.width <= 0 || page.dimensions.height <= 0 {
-                return Err(Error::Validation(format!(
+                return Err(Error::validation(format!(
                     "Invalid page dimensions: width={}, height={}",
                     page.dimensions.width, page.dimensions.height
                 )));
@@ -171,7 +470,7 @@ impl OCRResponse {
 
         // Validate usage info if present
         if self.usage_info.pages_processed != self.pages.len() as i32 {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Usage info pages_processed ({}) doesn't match actual pages ({})",
                 self.usage_info.pages_processed,
                 self.pages.len()
@@ -179,7 +478,7 @@ impl OCRResponse {
         }
 
         if self.usage_info.doc_size_bytes <= 0 {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "Invalid document size in usage info: {} bytes",
                 self.usage_info.doc_size_bytes
             )));
@@ -202,22 +501,58 @@ impl OCRClient {
 
     /// Process a file with OCR
     pub async fn process_ocr(&self, file_id: &str) -> Result<OCRResponse> {
-        let url = self.client.build_url("v1/ocr");
+        self.process_ocr_with_annotation(file_id, None).await
+    }
+
+    /// Process a file with OCR, optionally requesting a structured document annotation
+    pub async fn process_ocr_with_annotation(
+        &self,
+        file_id: &str,
+        annotation_format: Option<AnnotationFormat>,
+    ) -> Result<OCRResponse> {
+        let options = OCROptions {
+            annotation_format,
+            ..OCROptions::default()
+        };
+        self.process_ocr_with_options(file_id, options).await
+    }
+
+    /// Process a file with OCR using the given request options
+    pub async fn process_ocr_with_options(
+        &self,
+        file_id: &str,
+        options: OCROptions,
+    ) -> Result<OCRResponse> {
+        self.process_document(DocumentChunk::file(file_id.to_string()), options)
+            .await
+    }
+
+    /// Process a document reference (file ID or URL) with OCR
+    pub async fn process_document(
+        &self,
+        document: DocumentChunk,
+        options: OCROptions,
+    ) -> Result<OCRResponse> {
+        let url = self.client.build_url(&format!("{}/ocr", OCR_API_VERSION));
 
         self.client.log_request("POST", &url);
 
         // Create OCR request
-        let ocr_request = OCRRequest::new(file_id.to_string());
+        let mut ocr_request = OCRRequest::for_document(document);
+        ocr_request.document_annotation_format = options.annotation_format;
+        ocr_request.instructions = options.instructions;
+        ocr_request.response_format = options.response_format;
+        if let Some(model) = options.model {
+            ocr_request.model = model;
+        }
         ocr_request.validate()?;
 
         // Get authorization headers
-        let auth_headers =
-            crate::api::auth::AuthHandler::new(crate::credentials::APICredentials::new(
-                self.client.credentials.api_key.clone(),
-                self.client.credentials.api_base_url.clone(),
-            )?)
+        let auth_headers = crate::api::auth::AuthHandler::new(self.client.credentials.clone())
             .get_auth_headers()?;
 
+        let body = self.client.json_body(&ocr_request)?;
+
         // Send request with retry logic and metrics
         let start_time = Instant::now();
         let response = self
@@ -226,46 +561,27 @@ impl OCRClient {
                 let client = self.client.client().clone();
                 let url = url.clone();
                 let auth_headers = auth_headers.clone();
-                let ocr_request = ocr_request.clone();
+                let body = body.clone();
 
                 async move {
-                    let response = client
-                        .post(&url)
-                        .headers(auth_headers)
-                        .json(&ocr_request)
-                        .send()
-                        .await
-                        .map_err(Error::Network)?;
+                    let request = client.post(&url).headers(auth_headers);
+                    let response = body.apply(request).send().await.map_err(Error::Network)?;
 
                     MistralClient::handle_response(response).await
                 }
             })
             .await;
 
-        // Record metrics
-        let duration = start_time.elapsed();
-        match &response {
-            Ok(_) => {
-                GLOBAL_METRICS.record_success(duration, 0, 0).await;
-            }
-            Err(_) => {
-                GLOBAL_METRICS.record_failure(duration).await;
-            }
-        }
-
-        let response = response?;
-
-        // Parse response
-        let status = response.status().as_u16();
-        let response_text = response.text().await.map_err(Error::Network)?;
-
-        self.client.log_response(status, Some(response_text.len()));
+        // Read the body and record the call with the bytes sent and received
+        let response_text = self
+            .client
+            .read_body(ApiOperation::Ocr, response, start_time, body.len() as u64)
+            .await?;
 
         // Debug: Log the raw response for troubleshooting
         tracing::debug!("Raw OCR response: {}", response_text);
 
-        let ocr_response: OCRResponse = serde_json::from_str(&response_text)
-            .map_err(|e| Error::Api(format!("Failed to parse OCR response: {}", e)))?;
+        let ocr_response: OCRResponse = parse_json(&response_text, "Failed to parse OCR response")?;
 
         ocr_response.validate()?;
 
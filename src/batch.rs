@@ -0,0 +1,408 @@
+//! Processing many documents in one run
+//!
+//! `batch` expands the given files and directories into the supported documents
+//! they contain and writes each result to `--output-dir`, keeping the directory
+//! layout below each given directory (`in/2024/scan.pdf` ->
+//! `out/2024/scan.pdf.txt`). `watch` uses the same output layout for the files
+//! appearing in a directory.
+//!
+//! A batch runs as a pipeline of stages connected by bounded channels:
+//!
+//! ```text
+//! reader -> validator -> uploader -> OCR -> writer
+//! ```
+//!
+//! The reader hands out the documents in order, the validator checks their
+//! type and size, the uploader sends documents bound for Mistral to the Files
+//! API, the OCR stage runs the provider chain, and the writer formats and
+//! writes the results. The uploader and OCR stages keep up to
+//! `max_concurrent_requests` documents in flight and hand them on in order.
+//! At most [`STAGE_CAPACITY`] documents wait between two stages; a full
+//! channel makes the stage before it wait, so a slow API holds back
+//! validation instead of letting thousands of queued documents pile up in
+//! memory.
+
+use crate::compress::GZIP_SUFFIX;
+use crate::error::{Error, Result};
+use crate::file::{FileUpload, ValidationRules};
+use crate::output::{count, duration, Styler};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Documents that may wait between two stages of a batch
+pub const STAGE_CAPACITY: usize = 4;
+
+/// A document found in the batch inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchInput {
+    /// Path of the document
+    pub path: PathBuf,
+
+    /// Path below the given directory, or the file name for given files
+    pub relative: PathBuf,
+}
+
+/// Order in which documents are processed, from `--order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessingOrder {
+    /// By path
+    Name,
+    /// Smallest first, so quick documents finish early
+    SizeAsc,
+    /// Largest first, so the longest documents start early
+    SizeDesc,
+    /// Oldest modification time first
+    Mtime,
+}
+
+impl FromStr for ProcessingOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "size-asc" => Ok(Self::SizeAsc),
+            "size-desc" => Ok(Self::SizeDesc),
+            "mtime" => Ok(Self::Mtime),
+            other => Err(format!(
+                "Unknown order: {}. Supported: size-asc, size-desc, mtime, name",
+                other
+            )),
+        }
+    }
+}
+
+impl ProcessingOrder {
+    /// Sort documents into this order; documents that compare equal, or whose
+    /// size or time cannot be read, keep their relative order
+    pub fn sort<T>(self, items: &mut [T], path: impl Fn(&T) -> &Path) {
+        let metadata = |item: &T| fs::metadata(path(item)).ok();
+        match self {
+            Self::Name => items.sort_by(|a, b| path(a).cmp(path(b))),
+            Self::SizeAsc => items.sort_by_cached_key(|item| metadata(item).map(|m| m.len())),
+            Self::SizeDesc => {
+                items.sort_by_cached_key(|item| std::cmp::Reverse(metadata(item).map(|m| m.len())))
+            }
+            Self::Mtime => {
+                items.sort_by_cached_key(|item| metadata(item).and_then(|m| m.modified().ok()))
+            }
+        }
+    }
+}
+
+/// Expand files and directories into the supported documents they contain,
+/// sorted by path
+///
+/// Given files are always included, so unsupported ones fail validation like
+/// they would with `--file`; files inside directories are skipped unless their
+/// MIME type is allowed.
+pub fn collect_inputs(
+    paths: &[PathBuf],
+    recursive: bool,
+    rules: &ValidationRules,
+) -> Result<Vec<BatchInput>> {
+    let mut inputs = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            collect_dir(path, recursive, rules, &mut found)?;
+            found.sort();
+            inputs.extend(found.into_iter().map(|file| BatchInput {
+                relative: file.strip_prefix(path).unwrap_or(&file).to_path_buf(),
+                path: file,
+            }));
+        } else if path.is_file() {
+            inputs.push(BatchInput {
+                relative: PathBuf::from(path.file_name().unwrap_or(path.as_os_str())),
+                path: path.clone(),
+            });
+        } else {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Input not found: {}", path.display()),
+            )));
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn collect_dir(
+    dir: &Path,
+    recursive: bool,
+    rules: &ValidationRules,
+    found: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(Error::Io)? {
+        let path = entry.map_err(Error::Io)?.path();
+        if path.is_dir() {
+            if recursive {
+                collect_dir(&path, recursive, rules, found)?;
+            }
+        } else if is_supported(&path, rules) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether a file found in a directory is a document to process
+pub fn is_supported(path: &Path, rules: &ValidationRules) -> bool {
+    path.is_file() && rules.allows(&FileUpload::guess_mime_type(path))
+}
+
+/// Path of a document's result in the output directory
+/// (`scan.pdf` -> `<dir>/scan.pdf.txt`, or `.json` for JSON output)
+///
+/// Compressed results get a `.gz` suffix and encrypted ones an `.age` suffix
+/// after that, like sidecars.
+pub fn output_path(
+    output_dir: &Path,
+    relative: &Path,
+    json: bool,
+    compressed: bool,
+    encrypted: bool,
+) -> PathBuf {
+    let mut path = output_dir.join(relative).into_os_string();
+    path.push(if json { ".json" } else { ".txt" });
+    if compressed {
+        path.push(GZIP_SUFFIX);
+    }
+    if encrypted {
+        path.push(".age");
+    }
+    PathBuf::from(path)
+}
+
+/// Outcome of one document in a batch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchFile {
+    /// Input document
+    pub file: String,
+
+    /// Whether the document was processed
+    pub success: bool,
+
+    /// Pages extracted
+    pub pages: u64,
+
+    /// Processing time of the document
+    pub duration_ms: u64,
+
+    /// Written result, if any
+    pub output: Option<String>,
+
+    /// Why the document failed
+    pub error: Option<String>,
+
+    /// Exit code of the failure (see `explain`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl BatchFile {
+    /// One line for the terminal
+    pub fn to_line(&self) -> String {
+        self.render(&Styler::plain())
+    }
+
+    /// One line with an aligned status column, the page count and timing
+    pub fn render(&self, styler: &Styler) -> String {
+        let status = styler.status(self.success);
+        if let Some(ref error) = self.error {
+            return format!("{} {}: {}", status, self.file, error);
+        }
+        let details = styler.dim(&format!(
+            "({}, {})",
+            count(self.pages, "page", "pages"),
+            duration(self.duration_ms)
+        ));
+        match self.output {
+            Some(ref output) => format!("{} {} -> {} {}", status, self.file, output, details),
+            None => format!("{} {} {}", status, self.file, details),
+        }
+    }
+}
+
+/// Outcomes of a batch run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchReport {
+    /// Per-document outcomes in processing order
+    pub files: Vec<BatchFile>,
+
+    /// Documents processed
+    pub succeeded: usize,
+
+    /// Documents that failed
+    pub failed: usize,
+
+    /// Documents not attempted because the run stopped early
+    pub skipped: usize,
+}
+
+impl BatchReport {
+    /// Record a processed document
+    pub fn push_success(
+        &mut self,
+        file: &Path,
+        pages: u64,
+        duration_ms: u64,
+        output: Option<&Path>,
+    ) {
+        self.succeeded += 1;
+        self.files.push(BatchFile {
+            file: file.display().to_string(),
+            success: true,
+            pages,
+            duration_ms,
+            output: output.map(|output| output.display().to_string()),
+            error: None,
+            exit_code: None,
+        });
+    }
+
+    /// Record a failed document
+    pub fn push_failure(&mut self, file: &Path, error: &Error) {
+        self.failed += 1;
+        self.files.push(BatchFile {
+            file: file.display().to_string(),
+            success: false,
+            pages: 0,
+            duration_ms: 0,
+            output: None,
+            error: Some(error.user_message()),
+            exit_code: Some(error.exit_code()),
+        });
+    }
+
+    /// Number of succeeded, failed, and skipped documents
+    pub fn totals(&self) -> String {
+        self.render_totals(&Styler::plain())
+    }
+
+    /// Totals for the terminal, with failures highlighted
+    pub fn render_totals(&self, styler: &Styler) -> String {
+        let succeeded = styler.success(&format!("{} succeeded", self.succeeded));
+        let failed = format!("{} failed", self.failed);
+        let failed = if self.failed > 0 {
+            styler.failure(&failed)
+        } else {
+            failed
+        };
+        let mut totals = format!("{}, {}", succeeded, failed);
+        if self.skipped > 0 {
+            totals.push_str(&format!(
+                ", {}",
+                styler.warning(&format!("{} skipped", self.skipped))
+            ));
+        }
+        totals
+    }
+
+    /// Format for the terminal, one line per document and the totals
+    pub fn to_human_readable(&self) -> String {
+        self.render(&Styler::plain())
+    }
+
+    /// Format with an aligned status column and colored totals
+    pub fn render(&self, styler: &Styler) -> String {
+        let mut lines: Vec<String> = self.files.iter().map(|file| file.render(styler)).collect();
+        lines.push(self.render_totals(styler));
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_inputs_keeps_relative_paths() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("2024")).unwrap();
+        fs::write(dir.path().join("b.pdf"), b"%PDF-1.4\n").unwrap();
+        fs::write(dir.path().join("2024/a.pdf"), b"%PDF-1.4\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"not a document").unwrap();
+        let rules = crate::config::Config::default().validation_rules();
+
+        let flat = collect_inputs(&[dir.path().to_path_buf()], false, &rules).unwrap();
+        assert_eq!(flat.len(), 1);
+        assert_eq!(flat[0].relative, PathBuf::from("b.pdf"));
+
+        let recursive = collect_inputs(&[dir.path().to_path_buf()], true, &rules).unwrap();
+        let relative: Vec<_> = recursive.iter().map(|input| &input.relative).collect();
+        assert_eq!(relative, [Path::new("2024/a.pdf"), Path::new("b.pdf")]);
+
+        assert!(collect_inputs(&[dir.path().join("missing")], false, &rules).is_err());
+    }
+
+    #[test]
+    fn test_processing_order() {
+        let dir = TempDir::new().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        fs::write(path("a.pdf"), vec![0; 30]).unwrap();
+        fs::write(path("b.pdf"), vec![0; 10]).unwrap();
+        fs::write(path("c.pdf"), vec![0; 20]).unwrap();
+        let sorted = |order: &str| {
+            let mut paths = vec![path("c.pdf"), path("a.pdf"), path("b.pdf")];
+            order
+                .parse::<ProcessingOrder>()
+                .unwrap()
+                .sort(&mut paths, |path| path);
+            paths
+        };
+
+        assert_eq!(
+            sorted("size-asc"),
+            [path("b.pdf"), path("c.pdf"), path("a.pdf")]
+        );
+        assert_eq!(
+            sorted("size-desc"),
+            [path("a.pdf"), path("c.pdf"), path("b.pdf")]
+        );
+        assert_eq!(
+            sorted("name"),
+            [path("a.pdf"), path("b.pdf"), path("c.pdf")]
+        );
+        assert!("random".parse::<ProcessingOrder>().is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        let dir = Path::new("/out");
+        assert_eq!(
+            output_path(dir, Path::new("2024/scan.pdf"), false, false, false),
+            Path::new("/out/2024/scan.pdf.txt")
+        );
+        assert_eq!(
+            output_path(dir, Path::new("scan.pdf"), true, true, true),
+            Path::new("/out/scan.pdf.json.gz.age")
+        );
+    }
+
+    #[test]
+    fn test_report_lines_align_statuses() {
+        let mut report = BatchReport::default();
+        report.push_success(
+            Path::new("a.pdf"),
+            3,
+            4230,
+            Some(Path::new("out/a.pdf.txt")),
+        );
+        report.push_failure(Path::new("b.pdf"), &Error::validation("empty".to_string()));
+
+        assert_eq!(
+            report.to_human_readable(),
+            "ok     a.pdf -> out/a.pdf.txt (3 pages, 4.2s)\n\
+             FAILED b.pdf: Validation error: empty\n\
+             1 succeeded, 1 failed"
+        );
+        assert!(report
+            .render(&Styler::new(true))
+            .contains("\x1b[1;31m1 failed"));
+    }
+}
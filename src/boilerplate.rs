@@ -0,0 +1,144 @@
+//! Repeated header and footer lines
+//!
+//! Letterheads, running titles and page numbers repeat on most pages of a
+//! document and add noise to the merged text. Lines near the top or bottom of
+//! a page are compared across pages with digits masked, so "Page 3 of 12"
+//! matches "Page 4 of 12"; lines found on most pages are reported and can be
+//! stripped with `--strip-headers`.
+
+use std::collections::{HashMap, HashSet};
+
+/// Fraction of pages a line must appear on to count as a header or footer
+pub const MIN_PAGE_RATIO: f64 = 0.6;
+
+/// Documents with fewer pages are not checked
+const MIN_PAGES: usize = 3;
+
+/// Non-empty lines at the top and bottom of a page considered
+const EDGE_LINES: usize = 3;
+
+/// Lines repeated near the top or bottom of most pages, in first-seen order
+///
+/// Lines are returned with digits masked as `#` (see [`line_key`]).
+pub fn detect_repeated_lines(pages: &[&str]) -> Vec<String> {
+    if pages.len() < MIN_PAGES {
+        return Vec::new();
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for page in pages {
+        let lines: Vec<&str> = page.lines().filter(|l| !l.trim().is_empty()).collect();
+        let bottom = lines.len().saturating_sub(EDGE_LINES).max(EDGE_LINES);
+        let edges = lines
+            .iter()
+            .take(EDGE_LINES)
+            .chain(lines.iter().skip(bottom));
+
+        // Count each line once per page
+        let keys: HashSet<String> = edges.filter_map(|line| line_key(line)).collect();
+        for key in keys {
+            *counts.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    let min_pages = (pages.len() as f64 * MIN_PAGE_RATIO).ceil() as usize;
+    let mut repeated: Vec<String> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_pages)
+        .map(|(key, _)| key)
+        .collect();
+    repeated.sort_by_key(|key| first_position(pages, key));
+    repeated
+}
+
+/// Remove lines matching any of the repeated lines from a page
+pub fn strip_repeated_lines(page: &str, repeated: &[String]) -> String {
+    if repeated.is_empty() {
+        return page.to_string();
+    }
+
+    page.lines()
+        .filter(|line| line_key(line).is_none_or(|key| !repeated.contains(&key)))
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Comparison key of a line: whitespace collapsed and digit runs replaced by `#`
+///
+/// Returns None for lines without letters or digits (rules, table separators).
+fn line_key(line: &str) -> Option<String> {
+    if !line.chars().any(char::is_alphanumeric) {
+        return None;
+    }
+
+    let mut key = String::with_capacity(line.len());
+    for word in line.split_whitespace() {
+        if !key.is_empty() {
+            key.push(' ');
+        }
+        let mut in_digits = false;
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !in_digits {
+                    key.push('#');
+                }
+                in_digits = true;
+            } else {
+                key.push(c);
+                in_digits = false;
+            }
+        }
+    }
+    Some(key)
+}
+
+/// Page and line number where a key first appears
+fn first_position(pages: &[&str], key: &str) -> (usize, usize) {
+    pages
+        .iter()
+        .enumerate()
+        .find_map(|(page_index, page)| {
+            page.lines()
+                .position(|line| line_key(line).as_deref() == Some(key))
+                .map(|line_index| (page_index, line_index))
+        })
+        .unwrap_or((usize::MAX, usize::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGES: [&str; 4] = [
+        "ACME GmbH - Annual Report\n\nIntroduction\n\nPage 1 of 4",
+        "ACME GmbH - Annual Report\n\n| a | b |\n|---|---|\n\nPage 2 of 4",
+        "ACME GmbH - Annual Report\n\nResults\n\n| a | b |\n|---|---|\n\nPage 3 of 4",
+        "Appendix\n\nPage 4 of 4",
+    ];
+
+    #[test]
+    fn test_detects_letterhead_and_page_numbers() {
+        assert_eq!(
+            detect_repeated_lines(&PAGES),
+            ["ACME GmbH - Annual Report", "Page # of #"]
+        );
+    }
+
+    #[test]
+    fn test_strip_repeated_lines() {
+        let repeated = detect_repeated_lines(&PAGES);
+        assert_eq!(strip_repeated_lines(PAGES[0], &repeated), "Introduction");
+        assert_eq!(strip_repeated_lines(PAGES[3], &repeated), "Appendix");
+        assert_eq!(
+            strip_repeated_lines(PAGES[1], &repeated),
+            "| a | b |\n|---|---|"
+        );
+    }
+
+    #[test]
+    fn test_short_documents_are_not_checked() {
+        assert!(detect_repeated_lines(&PAGES[..2]).is_empty());
+    }
+}
@@ -3,6 +3,7 @@
 use crate::error::Result;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -49,15 +50,21 @@ pub struct FileCacheKey {
     pub purpose: String,
 }
 
-/// Cache key for OCR requests (based on file ID and model)
+/// Cache key for OCR requests (based on file ID, model and request options)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OCRCacheKey {
     pub file_id: String,
     pub model: String,
+    /// Instructions change the output, so results with different ones are distinct
+    pub instructions: Option<String>,
+    /// Requested page text format
+    pub response_format: Option<crate::api::ocr::ResponseFormat>,
+    /// Requested document annotation, serialized
+    pub annotation_format: Option<String>,
 }
 
-/// Generic cache implementation
-#[derive(Debug)]
+/// Generic cache implementation; clones share the entries
+#[derive(Debug, Clone)]
 pub struct Cache<K, V>
 where
     K: Hash + Eq + Clone + Send + Sync + 'static,
@@ -66,6 +73,7 @@ where
     entries: Arc<RwLock<HashMap<K, CacheEntry<V>>>>,
     default_ttl: Duration,
     max_entries: usize,
+    hits: Arc<AtomicU64>,
 }
 
 impl<K, V> Cache<K, V>
@@ -79,6 +87,7 @@ where
             entries: Arc::new(RwLock::new(HashMap::new())),
             default_ttl,
             max_entries,
+            hits: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -91,6 +100,7 @@ where
                 entries.remove(key);
                 None
             } else {
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 Some(entry.data.clone())
             }
         } else {
@@ -174,6 +184,7 @@ where
             active_entries: total_entries - expired_entries,
             max_entries: self.max_entries,
             estimated_size_bytes: total_size_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
         }
     }
 
@@ -199,6 +210,8 @@ pub struct CacheStats {
     pub active_entries: usize,
     pub max_entries: usize,
     pub estimated_size_bytes: usize,
+    /// Lookups answered from the cache
+    pub hits: u64,
 }
 
 /// File upload cache (caches file upload responses)
@@ -207,8 +220,8 @@ pub type FileUploadCache = Cache<FileCacheKey, crate::api::files::FileUploadResp
 /// OCR result cache (caches OCR responses)
 pub type OCRResultCache = Cache<OCRCacheKey, crate::api::ocr::OCRResponse>;
 
-/// Cache manager for all caches
-#[derive(Debug)]
+/// Cache manager for all caches; clones share the caches
+#[derive(Debug, Clone)]
 pub struct CacheManager {
     pub file_upload_cache: FileUploadCache,
     pub ocr_result_cache: OCRResultCache,
@@ -259,10 +272,6 @@ pub struct CombinedCacheStats {
     pub total_estimated_size_bytes: usize,
 }
 
-lazy_static::lazy_static! {
-    pub static ref GLOBAL_CACHE: CacheManager = CacheManager::new();
-}
-
 /// Helper function to generate file hash for caching
 pub fn generate_file_hash(file_data: &[u8]) -> String {
     use std::collections::hash_map::DefaultHasher;
@@ -277,6 +286,23 @@ mod tests {
     use super::*;
     use tokio::time::Duration;
 
+    #[tokio::test]
+    async fn test_ocr_cache_key_includes_instructions() {
+        let cache: Cache<OCRCacheKey, String> = Cache::new(Duration::from_secs(60), 10);
+        let key = |instructions: Option<&str>| OCRCacheKey {
+            file_id: "file-1".to_string(),
+            model: "mistral-ocr-latest".to_string(),
+            instructions: instructions.map(str::to_string),
+            response_format: None,
+            annotation_format: None,
+        };
+
+        cache.put(key(None), "plain".to_string()).await.unwrap();
+        assert!(cache.get(&key(Some("keep tables"))).await.is_none());
+        assert_eq!(cache.get(&key(None)).await, Some("plain".to_string()));
+        assert_eq!(cache.stats().await.hits, 1);
+    }
+
     #[tokio::test]
     async fn test_cache_basic_operations() {
         let cache: Cache<String, String> = Cache::new(Duration::from_secs(1), 10);
@@ -372,6 +398,9 @@ mod tests {
         let ocr_key = OCRCacheKey {
             file_id: "test_file_id".to_string(),
             model: "mistral-large".to_string(),
+            instructions: None,
+            response_format: None,
+            annotation_format: None,
         };
 
         // Test file upload cache
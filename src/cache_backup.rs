@@ -0,0 +1,260 @@
+//! Backups of the result cache
+//!
+//! `cache export FILE` writes the unexpired entries of the result cache into a
+//! zstd-compressed tar archive, and with `--results DIR` the result files of a
+//! result store as well (see [`crate::store`]); `cache import FILE` unpacks it
+//! on another host:
+//!
+//! ```text
+//! cache/<key>.json          cache entries
+//! results/<path>.json[.gz]  result files, relative to the result store
+//! ```
+//!
+//! Entries keep their modification time, so they expire on the new host when
+//! they would have on the old one. Importing never replaces newer cache entries
+//! or existing result files.
+
+use crate::error::{Error, Result};
+use crate::result_cache::ResultCache;
+use crate::store::ResultStore;
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Archive directory of cache entries
+const CACHE_DIR: &str = "cache";
+
+/// Archive directory of result files
+const RESULTS_DIR: &str = "results";
+
+/// Outcome of `cache export` or `cache import`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheBackupReport {
+    /// Cache entries written
+    pub entries: usize,
+
+    /// Result files written
+    pub results: usize,
+
+    /// Archive members left out: newer or existing files, results without a
+    /// `--results` directory, and anything else in the archive
+    pub skipped: usize,
+}
+
+/// Write the cache entries, and the result files of `results`, to a
+/// zstd-compressed tar archive at `path`
+pub fn export(
+    cache: &ResultCache,
+    results: Option<&Path>,
+    path: &Path,
+) -> Result<CacheBackupReport> {
+    let mut report = CacheBackupReport::default();
+    let partial = partial_path(path);
+    let file = fs::File::create(&partial).map_err(Error::Io)?;
+    let encoder = zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(Error::Io)?;
+    let mut archive = tar::Builder::new(encoder);
+
+    for entry in cache.entry_paths()? {
+        let Some(name) = entry.file_name() else {
+            continue;
+        };
+        archive
+            .append_path_with_name(&entry, Path::new(CACHE_DIR).join(name))
+            .map_err(Error::Io)?;
+        report.entries += 1;
+    }
+
+    if let Some(results) = results {
+        for file in ResultStore::new(results).result_files()? {
+            let relative = file.strip_prefix(results).unwrap_or(&file);
+            archive
+                .append_path_with_name(&file, Path::new(RESULTS_DIR).join(relative))
+                .map_err(Error::Io)?;
+            report.results += 1;
+        }
+    }
+
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(Error::Io)?;
+    fs::rename(&partial, path).map_err(Error::Io)?;
+    Ok(report)
+}
+
+/// Unpack an archive written by [`export`] into the cache, and its result files
+/// into `results`
+pub fn import(
+    cache: &ResultCache,
+    results: Option<&Path>,
+    path: &Path,
+) -> Result<CacheBackupReport> {
+    let mut report = CacheBackupReport::default();
+    let file = fs::File::open(path).map_err(Error::Io)?;
+    let decoder = zstd::Decoder::new(file).map_err(Error::Io)?;
+    let mut archive = tar::Archive::new(decoder);
+
+    for entry in archive.entries().map_err(Error::Io)? {
+        let mut entry = entry.map_err(Error::Io)?;
+        let member = entry.path().map_err(Error::Io)?.into_owned();
+        if !entry.header().entry_type().is_file() {
+            report.skipped += 1;
+            continue;
+        }
+
+        match destination(&member, cache.dir(), results) {
+            Some((Destination::Cache, target)) => {
+                // A newer entry on this host wins
+                let archived = entry.header().mtime().unwrap_or(0);
+                if modified_secs(&target).is_some_and(|existing| existing >= archived) {
+                    report.skipped += 1;
+                    continue;
+                }
+                fs::create_dir_all(cache.dir()).map_err(Error::Io)?;
+                let partial = partial_path(&target);
+                entry.unpack(&partial).map_err(Error::Io)?;
+                fs::rename(&partial, &target).map_err(Error::Io)?;
+                report.entries += 1;
+            }
+            Some((Destination::Results, target)) => {
+                if target.exists() {
+                    report.skipped += 1;
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(Error::Io)?;
+                }
+                entry.unpack(&target).map_err(Error::Io)?;
+                report.results += 1;
+            }
+            None => {
+                tracing::debug!("Skipping archive member {}", member.display());
+                report.skipped += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Where an archive member is unpacked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Destination {
+    Cache,
+    Results,
+}
+
+/// Target of an archive member, if it is one this tool writes; members that
+/// would leave their directory are refused
+fn destination(
+    member: &Path,
+    cache_dir: &Path,
+    results: Option<&Path>,
+) -> Option<(Destination, PathBuf)> {
+    let mut components = member.components();
+    let top = components.next()?;
+    let rest = components.as_path();
+    if rest.as_os_str().is_empty()
+        || !rest
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    match top.as_os_str().to_str()? {
+        CACHE_DIR => {
+            let is_entry =
+                rest.components().count() == 1 && rest.extension().is_some_and(|ext| ext == "json");
+            is_entry.then(|| (Destination::Cache, cache_dir.join(rest)))
+        }
+        RESULTS_DIR => results.map(|results| (Destination::Results, results.join(rest))),
+        _ => None,
+    }
+}
+
+/// Modification time of a file in seconds since the epoch
+fn modified_secs(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|age| age.as_secs())
+}
+
+/// Sibling of a path that is written first and renamed once complete
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let source = TempDir::new().unwrap();
+        let cache = ResultCache::new(source.path().join("cache"), Duration::from_secs(3600));
+        cache.put("abc", &"Invoice 42").unwrap();
+        let results = source.path().join("results");
+        fs::create_dir_all(results.join("2024")).unwrap();
+        fs::write(results.join("2024/invoice.pdf.json"), "{}").unwrap();
+        fs::write(results.join("2024/invoice.pdf"), "%PDF").unwrap();
+
+        let archive = source.path().join("backup.tar.zst");
+        let exported = export(&cache, Some(&results), &archive).unwrap();
+        assert_eq!((exported.entries, exported.results), (1, 1));
+
+        let target = TempDir::new().unwrap();
+        let imported_cache =
+            ResultCache::new(target.path().join("cache"), Duration::from_secs(3600));
+        let imported_results = target.path().join("results");
+        let imported = import(&imported_cache, Some(&imported_results), &archive).unwrap();
+        assert_eq!(imported, exported);
+        assert_eq!(
+            imported_cache.get::<String>("abc").as_deref(),
+            Some("Invoice 42")
+        );
+        assert!(imported_results.join("2024/invoice.pdf.json").is_file());
+        // Originals are not part of the backup
+        assert!(!imported_results.join("2024/invoice.pdf").exists());
+
+        // Existing files are kept, and results need a directory
+        let again = import(&imported_cache, None, &archive).unwrap();
+        assert_eq!((again.entries, again.results, again.skipped), (0, 0, 2));
+    }
+
+    #[test]
+    fn test_destination_refuses_escaping_members() {
+        let cache = Path::new("/cache");
+        let results = Some(Path::new("/results"));
+
+        assert_eq!(
+            destination(Path::new("cache/abc.json"), cache, results),
+            Some((Destination::Cache, PathBuf::from("/cache/abc.json")))
+        );
+        assert_eq!(
+            destination(Path::new("results/a/b.json"), cache, results),
+            Some((Destination::Results, PathBuf::from("/results/a/b.json")))
+        );
+        assert_eq!(destination(Path::new("results/a.json"), cache, None), None);
+        assert_eq!(
+            destination(Path::new("cache/a/b.json"), cache, results),
+            None
+        );
+        assert_eq!(
+            destination(Path::new("cache/abc.txt"), cache, results),
+            None
+        );
+        assert_eq!(
+            destination(Path::new("results/../x.json"), cache, results),
+            None
+        );
+        assert_eq!(destination(Path::new("/etc/passwd"), cache, results), None);
+        assert_eq!(destination(Path::new("cache"), cache, results), None);
+    }
+}
@@ -1,100 +1,1503 @@
 //! CLI command implementations
 
-use crate::api::{files::FilesClient, ocr::OCRClient, MistralClient};
+use crate::api::{
+    files::{FileUploadResponse, FilesClient},
+    ocr::{OCRResponse, Page, PageLayout, UsageInfo},
+    MistralClient,
+};
+use crate::batch::{collect_inputs, output_path, BatchReport, STAGE_CAPACITY};
+use crate::cache::CacheManager;
+use crate::cache_backup;
+use crate::cli::{
+    BatchArgs, CacheCommand, CompareArgs, ConfigCommand, EvalArgs, ExportPaperlessArgs,
+    FilesCommand, OutputFormat, SuggestTagsArgs, WatchArgs,
+};
+use crate::compare::{ComparisonReport, DiffBaseline, DiffReport, ProviderRun};
+use crate::compress::gzip;
+use crate::concat::Concatenation;
 use crate::config::Config;
 use crate::credentials::APICredentials;
+use crate::diagnostics::GLOBAL_IN_FLIGHT;
+use crate::encrypt::encrypt;
 use crate::error::{Error, Result};
+use crate::eval::{AccuracyReport, DocumentAccuracy, FailedDocument};
 use crate::file::FileUpload;
+use crate::job::{JobManifest, JobResults};
+use crate::lock::{DocumentLock, LOCK_DIR};
+use crate::manifest::RunManifest;
+use crate::metrics::MetricsCollector;
 use crate::ocr::OCRResult;
-use std::path::Path;
+use crate::output::Styler;
+use crate::paperless::{file_checksum, MatchStrategy, PaperlessClient, PaperlessDocument};
+use crate::pipeline::{
+    cache_options, ocr_validated, upload_validated, validate_file, validate_input_file, RunContext,
+};
+use crate::plain_text::strip_pages;
+use crate::provider::{text_page, ProviderKind, ProviderOutput, ProviderRequest};
+use crate::result_cache::{CacheWarmReport, CachedOutput, ResultCache};
+use crate::sidecar::{write_sidecar, written_sidecar_path};
+use crate::store::{ResultStore, StoredResult};
+use crate::tags::suggest_tags;
+use crate::transform::{self, Outcome};
+use crate::watch::{is_up_to_date, DirectoryWatcher};
+use chrono::Utc;
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+pub use crate::pipeline::{ocr_file, ProcessingOptions};
 
 /// Process OCR command
+///
+/// Returns the bytes to write to stdout, which are binary with `--compress-output`.
 pub async fn process_ocr_command(
     input_file_path: &str,
     app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+    styler: &Styler,
+    enable_verbose_logging: bool,
+) -> Result<Vec<u8>> {
+    let outcome = match ocr_file(input_file_path, app_config, options, enable_verbose_logging).await
+    {
+        Ok(result) => {
+            print_result(
+                input_file_path,
+                result,
+                app_config,
+                options,
+                output_format,
+                styler,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    run_hooks(
+        Path::new(input_file_path),
+        outcome.as_ref().map(|(result, _)| (result, None)),
+        app_config,
+        options,
+    )
+    .await;
+    outcome.map(|(_, output)| output)
+}
+
+/// Run the transform script on a result and format it for stdout
+///
+/// A document skipped by the script prints nothing.
+async fn print_result(
+    input_file_path: &str,
+    mut result: OCRResult,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+    styler: &Styler,
+) -> Result<(OCRResult, Vec<u8>)> {
+    let input = Path::new(input_file_path);
+    let relative = PathBuf::from(input.file_name().unwrap_or_default());
+    if transform::apply(&app_config.transform, input, &relative, &mut result)? == Outcome::Skip {
+        tracing::info!(
+            "Skipping {}: skipped by the transform script",
+            input.display()
+        );
+        return Ok((result, Vec::new()));
+    }
+    let output = format_result(
+        input_file_path,
+        &result,
+        app_config,
+        options,
+        output_format,
+        styler,
+    )
+    .await?;
+    Ok((result, output))
+}
+
+/// Run the `--on-success` or `--on-failure` hook for a document
+///
+/// `output` is the result file in the output directory; without one, the
+/// sidecar is passed when one was written.
+pub(crate) async fn run_hooks(
+    input: &Path,
+    outcome: std::result::Result<(&OCRResult, Option<&Path>), &Error>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+) {
+    match outcome {
+        Ok((result, output)) => {
+            let sidecar = options.sidecar.then(|| {
+                written_sidecar_path(
+                    input,
+                    options.compress_output,
+                    app_config.encryption.is_enabled(),
+                )
+            });
+            options
+                .hooks
+                .succeeded(input, output.or(sidecar.as_deref()), result)
+                .await
+        }
+        Err(e) => options.hooks.failed(input, e).await,
+    }
+}
+
+/// Write the sidecar, if requested, and format a result for output
+///
+/// The output is compressed and encrypted as requested, so it is binary with
+/// `--compress-output`. Text is only colored when it is neither.
+pub async fn format_result(
+    input_file_path: &str,
+    result: &OCRResult,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+    styler: &Styler,
+) -> Result<Vec<u8>> {
+    if options.sidecar {
+        let sidecar = write_sidecar(
+            Path::new(input_file_path),
+            result,
+            options.compress_output,
+            &app_config.encryption,
+        )
+        .await?;
+        tracing::info!("Sidecar written to {}", sidecar.display());
+    }
+
+    // Format output based on user preference
+    let json = match output_format {
+        OutputFormat::Text | OutputFormat::Plain => None,
+        OutputFormat::Json => Some(result.to_json_output()),
+        OutputFormat::PagesJson => Some(result.to_pages_json_output()),
+    };
+    let mut output = match json {
+        Some(json) => serde_json::to_string_pretty(&json)
+            .map_err(|e| Error::internal_from("Failed to serialize JSON", e))?,
+        None if output_format == OutputFormat::Plain => {
+            strip_pages(&result.extracted_text, &app_config.pages.separator)
+        }
+        None if options.compress_output || app_config.encryption.is_enabled() => {
+            result.to_human_readable()
+        }
+        None => result.render(styler),
+    };
+    output.push('\n');
+
+    let mut output = output.into_bytes();
+    if options.compress_output {
+        output = gzip(&output)?;
+    }
+    if app_config.encryption.is_enabled() {
+        // Armor keeps uncompressed output printable; compressed output is binary anyway
+        output = encrypt(&output, &app_config.encryption, !options.compress_output).await?;
+    }
+
+    Ok(output)
+}
+
+/// Run the same document through several providers and compare the outputs
+pub async fn compare_command(
+    args: &CompareArgs,
+    app_config: &Config,
+    metrics: &MetricsCollector,
+    run: &RunContext,
+    enable_json_output: bool,
+) -> Result<String> {
+    let providers = ProviderKind::parse_list(&args.providers)?;
+    if providers.len() < 2 {
+        return Err(Error::validation(
+            "At least two providers are required for a comparison".to_string(),
+        ));
+    }
+
+    let file_upload = validate_input_file(&args.file, app_config, None)?;
+    let request = ProviderRequest {
+        metrics: metrics.clone(),
+        run: run.clone(),
+        ..ProviderRequest::default()
+    };
+
+    let mut runs = Vec::with_capacity(providers.len());
+    for provider in providers {
+        let start_time = Instant::now();
+        let run = crate::provider::process(provider, &file_upload, app_config, &request).await;
+        let duration_ms = start_time.elapsed().as_millis() as u64;
+
+        runs.push(match run {
+            Ok(output) => ProviderRun::success(
+                provider.name(),
+                duration_ms,
+                output.response.pages.len(),
+                output.response.get_extracted_text(),
+            ),
+            Err(e) => ProviderRun::failure(provider.name(), duration_ms, e.user_message()),
+        });
+    }
+
+    let report = ComparisonReport::new(&file_upload.get_filename(), runs);
+
+    if enable_json_output {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "success": true,
+            "data": report,
+        }))
+        .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+    } else {
+        Ok(report.to_human_readable())
+    }
+}
+
+/// OCR a document and compare the text with its existing text
+///
+/// Prints the similarity and a unified diff from the existing to the new text
+/// instead of the result, to decide whether re-OCR improved the document.
+pub async fn diff_command(
+    input_file_path: &str,
+    baseline: &DiffBaseline,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    enable_json_output: bool,
+    enable_verbose_logging: bool,
+) -> Result<String> {
+    // Fail before paying for OCR when there is nothing to compare with
+    let (label, existing) = match baseline {
+        DiffBaseline::File(path) => (
+            path.display().to_string(),
+            std::fs::read_to_string(path).map_err(Error::Io)?,
+        ),
+        DiffBaseline::Paperless => {
+            let client = PaperlessClient::new(app_config)?;
+            let data = std::fs::read(input_file_path).map_err(Error::Io)?;
+            let title = Path::new(input_file_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let document = match client.find_by_checksum(&file_checksum(&data)).await? {
+                Some(document) => Some(document),
+                None => client.find_by_title(&title).await?,
+            };
+            let document = document.ok_or_else(|| {
+                Error::validation(format!(
+                    "No paperless-ngx document matches {}",
+                    input_file_path
+                ))
+            })?;
+            (
+                format!("paperless-ngx document {}", document.id),
+                document.content.unwrap_or_default(),
+            )
+        }
+    };
+
+    let result = ocr_file(input_file_path, app_config, options, enable_verbose_logging).await?;
+    let report = DiffReport::new(&result.file_name, &label, &existing, &result.extracted_text);
+
+    if enable_json_output {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "success": true,
+            "data": report,
+        }))
+        .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+    } else {
+        Ok(report.to_human_readable())
+    }
+}
+
+/// OCR a document and suggest matching paperless-ngx tags
+pub async fn suggest_tags_command(
+    args: &SuggestTagsArgs,
+    app_config: &Config,
+    metrics: &MetricsCollector,
+    run: &RunContext,
     enable_json_output: bool,
     enable_verbose_logging: bool,
 ) -> Result<String> {
-    if enable_verbose_logging {
-        tracing::info!("Processing OCR command for file: {}", input_file_path);
+    if !(0.0..=1.0).contains(&args.min_similarity) {
+        return Err(Error::validation(
+            "Minimum similarity must be between 0.0 and 1.0".to_string(),
+        ));
     }
 
-    // Validate file exists and is supported format
-    let file_upload = FileUpload::new(input_file_path)?;
+    let client = PaperlessClient::new(app_config)?;
+    let options = ProcessingOptions {
+        metrics: metrics.clone(),
+        run: run.clone(),
+        ..ProcessingOptions::default()
+    };
+    let result = ocr_file(&args.file, app_config, &options, enable_verbose_logging).await?;
 
-    if enable_verbose_logging {
-        tracing::debug!(
-            "File validation passed: {} ({} bytes, {})",
-            file_upload.get_filename(),
-            file_upload.file_size,
-            file_upload.mime_type
-        );
+    let tags = client.list_tags().await?;
+    let suggestions = suggest_tags(&result.extracted_text, &tags, args.min_similarity);
+    tracing::info!(
+        "{} of {} paperless-ngx tags match {}",
+        suggestions.len(),
+        tags.len(),
+        result.file_name
+    );
+
+    if enable_json_output {
+        let tag_ids: Vec<i64> = suggestions.iter().map(|suggestion| suggestion.id).collect();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "success": true,
+            "data": {
+                "file_name": result.file_name,
+                "tag_ids": tag_ids,
+                "suggestions": suggestions,
+            },
+        }))
+        .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+    } else if suggestions.is_empty() {
+        Ok(format!("No tags suggested for {}", result.file_name))
+    } else {
+        let mut output = format!("Suggested tags for {}:", result.file_name);
+        for suggestion in &suggestions {
+            output.push_str(&format!(
+                "\n  {} (#{}): {:?} match on \"{}\", score {:.2}",
+                suggestion.name,
+                suggestion.id,
+                suggestion.match_kind,
+                suggestion.matched,
+                suggestion.score
+            ));
+        }
+        Ok(output)
     }
+}
 
-    // Check file size against configuration
-    let max_size_bytes = app_config.max_file_size_mb * 1024 * 1024;
-    if file_upload.file_size > max_size_bytes {
-        return Err(Error::Validation(format!(
-            "File size ({:.2} MB) exceeds maximum allowed size ({} MB)",
-            file_upload.file_size as f64 / (1024.0 * 1024.0),
-            app_config.max_file_size_mb
+/// Evaluate OCR accuracy against reference transcripts
+pub async fn eval_command(
+    args: &EvalArgs,
+    app_config: &Config,
+    metrics: &MetricsCollector,
+    cache: &CacheManager,
+    run: &RunContext,
+    enable_json_output: bool,
+    enable_verbose_logging: bool,
+) -> Result<String> {
+    if !args.reference.is_dir() {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "Reference directory not found: {}",
+                args.reference.display()
+            ),
         )));
     }
 
-    // Create API credentials and client
-    let api_credentials = APICredentials::from_config(app_config)?;
-    let mistral_client = MistralClient::new(api_credentials, app_config.timeout_seconds)?;
+    let reference_path = |file_name: &str| {
+        let stem = Path::new(file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.to_string());
+        args.reference.join(format!("{}.txt", stem))
+    };
 
-    if enable_verbose_logging {
-        tracing::debug!("API client initialized");
+    // Collect (file name, OCR text) pairs, either from stored results or by
+    // running OCR; documents without a reference are not sent to OCR
+    let mut outputs: Vec<(String, Result<String>)> = Vec::new();
+    let mut missing_reference = Vec::new();
+    if args.stored {
+        for stored in ResultStore::new(&args.source).load_all()? {
+            outputs.push((stored.file_name, Ok(stored.extracted_text)));
+        }
+    } else {
+        let rules = app_config.validation_rules();
+        let mut documents: Vec<_> = std::fs::read_dir(&args.source)
+            .map_err(Error::Io)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && rules.allows(&FileUpload::guess_mime_type(path)))
+            .collect();
+        documents.sort();
+
+        let options = ProcessingOptions {
+            metrics: metrics.clone(),
+            run: run.clone(),
+            ..ProcessingOptions::default()
+        };
+        for document in documents {
+            let file_name = document
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if !reference_path(&file_name).is_file() {
+                missing_reference.push(file_name);
+                continue;
+            }
+            let text = ocr_file(
+                &document.to_string_lossy(),
+                app_config,
+                &options,
+                enable_verbose_logging,
+            )
+            .await
+            .map(|result| result.extracted_text);
+            if let Err(e @ Error::BudgetExceeded(_)) = text {
+                return Err(e);
+            }
+            outputs.push((file_name, text));
+        }
     }
 
-    // Upload file to Mistral AI Files API
-    let files_client = FilesClient::new(mistral_client.clone());
-    let upload_response = files_client.upload_file(&file_upload).await?;
+    let mut accuracies = Vec::new();
+    let mut failed = Vec::new();
+    for (file_name, text) in outputs {
+        let reference_path = reference_path(&file_name);
+        if !reference_path.is_file() {
+            missing_reference.push(file_name);
+            continue;
+        }
 
-    if enable_verbose_logging {
-        tracing::info!("File uploaded successfully: {}", upload_response.id);
+        match text {
+            Ok(text) => {
+                let reference = std::fs::read_to_string(&reference_path).map_err(Error::Io)?;
+                accuracies.push(DocumentAccuracy::compare(&file_name, &reference, &text));
+            }
+            Err(e) => failed.push(FailedDocument {
+                file_name,
+                reason: e.user_message(),
+            }),
+        }
     }
 
-    // Process with OCR API
-    let ocr_client = OCRClient::new(mistral_client);
-    let ocr_response = ocr_client.process_ocr(&upload_response.id).await?;
+    let report = AccuracyReport::new(accuracies, missing_reference, failed);
 
-    if enable_verbose_logging {
-        tracing::info!("OCR processing completed");
+    if enable_json_output {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "success": true,
+            "data": report,
+            "summary": run.counters.summary(metrics, cache).await,
+        }))
+        .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+    } else {
+        Ok(report.to_human_readable())
     }
+}
 
-    // Create result from API response
-    let result = OCRResult::from_extracted_text(
-        ocr_response.get_extracted_text(),
-        upload_response.id,
-        ocr_response.model,
-        file_upload.get_filename(),
-        file_upload.file_size,
-        {
-            let mut usage_map = std::collections::HashMap::new();
-            usage_map.insert(
-                "pages_processed".to_string(),
-                ocr_response.usage_info.pages_processed as i64,
-            );
-            usage_map.insert(
-                "doc_size_bytes".to_string(),
-                ocr_response.usage_info.doc_size_bytes as i64,
+/// Outcome of exporting a single stored result
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportOutcome {
+    pub file_name: String,
+    pub status: String,
+    pub document_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// Export stored OCR results to paperless-ngx documents
+pub async fn export_paperless_command(
+    args: &ExportPaperlessArgs,
+    app_config: &Config,
+    metrics: &MetricsCollector,
+    cache: &CacheManager,
+    run: &RunContext,
+    enable_json_output: bool,
+) -> Result<String> {
+    let store = ResultStore::new(&args.source);
+    let stored_results = store.load_all()?;
+
+    tracing::info!(
+        "Exporting {} stored results from {} to paperless-ngx",
+        stored_results.len(),
+        store.root().display()
+    );
+
+    let client = PaperlessClient::new(app_config)?;
+
+    let mut outcomes = Vec::with_capacity(stored_results.len());
+    for stored in &stored_results {
+        let _in_flight = GLOBAL_IN_FLIGHT.track(&stored.file_name);
+        let outcome = match find_paperless_document(&client, stored, args.match_strategy).await {
+            Ok(Some(document)) if args.dry_run => ExportOutcome {
+                file_name: stored.file_name.clone(),
+                status: "would_export".to_string(),
+                document_id: Some(document.id),
+                reason: None,
+            },
+            Ok(Some(document)) => match client
+                .update_content(document.id, &stored.extracted_text)
+                .await
+            {
+                Ok(()) => ExportOutcome {
+                    file_name: stored.file_name.clone(),
+                    status: "exported".to_string(),
+                    document_id: Some(document.id),
+                    reason: None,
+                },
+                Err(e) => ExportOutcome {
+                    file_name: stored.file_name.clone(),
+                    status: "failed".to_string(),
+                    document_id: Some(document.id),
+                    reason: Some(e.user_message()),
+                },
+            },
+            Ok(None) => ExportOutcome {
+                file_name: stored.file_name.clone(),
+                status: "unmatched".to_string(),
+                document_id: None,
+                reason: None,
+            },
+            Err(e) => ExportOutcome {
+                file_name: stored.file_name.clone(),
+                status: "failed".to_string(),
+                document_id: None,
+                reason: Some(e.user_message()),
+            },
+        };
+
+        tracing::debug!("{}: {}", outcome.file_name, outcome.status);
+        match outcome.status.as_str() {
+            "failed" => run.counters.record_failure(
+                &outcome.file_name,
+                outcome.reason.as_deref().unwrap_or_default(),
+            ),
+            "exported" => run.counters.record_file(&outcome.file_name, 0),
+            // Unmatched results and dry runs leave the document unchanged
+            _ => run.counters.record_skip(),
+        }
+        outcomes.push(outcome);
+    }
+
+    let count = |status: &str| outcomes.iter().filter(|o| o.status == status).count();
+
+    if enable_json_output {
+        let output = serde_json::json!({
+            "success": true,
+            "data": {
+                "dry_run": args.dry_run,
+                "exported": count(if args.dry_run { "would_export" } else { "exported" }),
+                "unmatched": count("unmatched"),
+                "failed": count("failed"),
+                "results": outcomes,
+            },
+            "summary": run.counters.summary(metrics, cache).await,
+        });
+        serde_json::to_string_pretty(&output)
+            .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+    } else {
+        let mut lines: Vec<String> = outcomes
+            .iter()
+            .map(|o| match (o.document_id, &o.reason) {
+                (Some(id), None) => format!("{}: {} (document {})", o.file_name, o.status, id),
+                (_, Some(reason)) => format!("{}: {} ({})", o.file_name, o.status, reason),
+                (None, None) => format!("{}: {}", o.file_name, o.status),
+            })
+            .collect();
+        lines.push(format!(
+            "{} {}, {} unmatched, {} failed",
+            count(if args.dry_run {
+                "would_export"
+            } else {
+                "exported"
+            }),
+            if args.dry_run {
+                "would be exported"
+            } else {
+                "exported"
+            },
+            count("unmatched"),
+            count("failed")
+        ));
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Locate the paperless-ngx document for a stored result
+async fn find_paperless_document(
+    client: &PaperlessClient,
+    stored: &StoredResult,
+    strategy: MatchStrategy,
+) -> Result<Option<PaperlessDocument>> {
+    let checksum = match &stored.original_path {
+        Some(path) => Some(file_checksum(&std::fs::read(path).map_err(Error::Io)?)),
+        None => None,
+    };
+
+    match (strategy, checksum) {
+        (MatchStrategy::Checksum, None) => Err(Error::validation(format!(
+            "Original file for {} not found next to its result; cannot match by checksum",
+            stored.file_name
+        ))),
+        (MatchStrategy::Checksum, Some(checksum)) => client.find_by_checksum(&checksum).await,
+        (MatchStrategy::Title, _) => client.find_by_title(&stored.default_title()).await,
+        (MatchStrategy::Auto, Some(checksum)) => match client.find_by_checksum(&checksum).await? {
+            Some(document) => Ok(Some(document)),
+            None => client.find_by_title(&stored.default_title()).await,
+        },
+        (MatchStrategy::Auto, None) => client.find_by_title(&stored.default_title()).await,
+    }
+}
+
+/// OCR a document and write its result below `output_dir`
+///
+/// Returns the result and the written path. Without an output directory only
+/// the sidecar (if requested) is written.
+async fn process_document(
+    input: &Path,
+    relative: &Path,
+    output_dir: Option<&Path>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+    enable_verbose_logging: bool,
+) -> Result<(OCRResult, Option<PathBuf>)> {
+    let result = ocr_file(
+        &input.to_string_lossy(),
+        app_config,
+        options,
+        enable_verbose_logging,
+    )
+    .await?;
+    let output = write_result(
+        input,
+        relative,
+        &result,
+        output_dir,
+        app_config,
+        options,
+        output_format,
+    )
+    .await?;
+    Ok((result, output))
+}
+
+/// Write the sidecar and the result of a document below `output_dir`
+///
+/// The transform script, if any, runs first and may change the text and the
+/// path below `output_dir`, or skip the document. Returns the written path;
+/// without an output directory only the sidecar (if requested) is written.
+async fn write_result(
+    input: &Path,
+    relative: &Path,
+    result: &OCRResult,
+    output_dir: Option<&Path>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+) -> Result<Option<PathBuf>> {
+    let mut result = result.clone();
+    let relative = match transform::apply(&app_config.transform, input, relative, &mut result)? {
+        Outcome::Write(relative) => relative,
+        Outcome::Skip => {
+            tracing::info!(
+                "Skipping {}: skipped by the transform script",
+                input.display()
             );
-            Some(usage_map)
+            return Ok(None);
+        }
+    };
+
+    let output = format_result(
+        &input.to_string_lossy(),
+        &result,
+        app_config,
+        options,
+        output_format,
+        &Styler::plain(),
+    )
+    .await?;
+
+    let Some(output_dir) = output_dir else {
+        return Ok(None);
+    };
+    let path = output_path(
+        output_dir,
+        &relative,
+        output_format.is_json(),
+        options.compress_output,
+        app_config.encryption.is_enabled(),
+    );
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(Error::Io)?;
+    }
+    std::fs::write(&path, output).map_err(Error::Io)?;
+
+    Ok(Some(path))
+}
+
+/// The items sent to a channel, as a stream
+fn receiver_stream<T>(receiver: mpsc::Receiver<T>) -> impl Stream<Item = T> {
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|item| (item, receiver))
+    })
+}
+
+/// Process the documents of a batch and write their results
+///
+/// The documents flow through bounded channels between the reader,
+/// validator, OCR, and writer stages (see [`crate::batch`]), so a slow API
+/// holds back the earlier stages instead of letting work pile up in memory.
+///
+/// Returns the formatted report and the error of the first failed document,
+/// which decides the exit code. A budget error stops the batch.
+pub async fn batch_command(
+    args: &BatchArgs,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    cache: &CacheManager,
+    output_format: OutputFormat,
+    styler: &Styler,
+    enable_verbose_logging: bool,
+) -> Result<(String, Option<Error>)> {
+    let started_at = Utc::now();
+    let job = args
+        .job_manifest
+        .as_deref()
+        .map(JobManifest::load)
+        .transpose()?;
+    let job_args;
+    let args = match job {
+        Some(ref job) => {
+            job_args = args.for_job(job)?;
+            &job_args
+        }
+        None => args,
+    };
+
+    let mut inputs = match job {
+        Some(ref job) => job.inputs(),
+        None => collect_inputs(&args.paths, args.recursive, &app_config.validation_rules())?,
+    };
+    if let Some(order) = args.order {
+        order.sort(&mut inputs, |input| &input.path);
+    }
+    if inputs.is_empty() {
+        return Err(Error::validation(
+            "No supported documents found in the given paths".to_string(),
+        ));
+    }
+
+    let mut report = BatchReport::default();
+    let mut concat = args.concat.as_ref().map(|_| Concatenation::default());
+    let mut manifest = args
+        .manifest_dir()
+        .map(|dir| (dir, RunManifest::start(&inputs, app_config)));
+    let mut first_error = None;
+
+    let total = inputs.len();
+    let inputs = &inputs;
+    let concurrency = app_config.max_concurrent_requests;
+    // The budget is checked against the documents finished so far, so with a
+    // budget documents are processed one at a time
+    let ocr_concurrency = if app_config.budget.is_limited() {
+        1
+    } else {
+        concurrency
+    };
+    let (read_tx, mut read_rx) = mpsc::channel(STAGE_CAPACITY);
+    let (validated_tx, validated_rx) = mpsc::channel(STAGE_CAPACITY);
+    let (uploaded_tx, uploaded_rx) = mpsc::channel(STAGE_CAPACITY);
+    let (ocr_tx, mut ocr_rx) = mpsc::channel(STAGE_CAPACITY);
+
+    // Each stage stops once the next one has gone, so stopping the OCR stage
+    // after a budget error stops the whole pipeline
+    let reader = async move {
+        for input in inputs {
+            if read_tx.send(input).await.is_err() {
+                break;
+            }
+        }
+    };
+    let validator = async move {
+        while let Some(input) = read_rx.recv().await {
+            let validated = validate_file(&input.path.to_string_lossy(), app_config, options);
+            if validated_tx.send((input, validated)).await.is_err() {
+                break;
+            }
+        }
+    };
+    // The uploader and OCR stages keep several documents in flight and hand
+    // them on in order
+    let uploader = async move {
+        let mut uploaded = pin!(receiver_stream(validated_rx)
+            .map(|(input, validated)| async move {
+                (
+                    input,
+                    upload_validated(validated, app_config, options).await,
+                )
+            })
+            .buffered(concurrency));
+        while let Some(uploaded) = uploaded.next().await {
+            if uploaded_tx.send(uploaded).await.is_err() {
+                break;
+            }
+        }
+    };
+    let ocr = async move {
+        let mut results = pin!(receiver_stream(uploaded_rx)
+            .map(|(input, validated)| async move {
+                let result = ocr_validated(
+                    &input.path.to_string_lossy(),
+                    validated,
+                    app_config,
+                    options,
+                    enable_verbose_logging,
+                )
+                .await;
+                (input, result)
+            })
+            .buffered(ocr_concurrency));
+        while let Some((input, result)) = results.next().await {
+            let stop = matches!(result, Err(Error::BudgetExceeded(_)));
+            if ocr_tx.send((input, result)).await.is_err() || stop {
+                break;
+            }
+        }
+    };
+    let writer = async {
+        while let Some((input, result)) = ocr_rx.recv().await {
+            let written = match result {
+                Ok(result) => write_result(
+                    &input.path,
+                    &input.relative,
+                    &result,
+                    args.output_dir.as_deref(),
+                    app_config,
+                    options,
+                    output_format,
+                )
+                .await
+                .map(|output| (result, output)),
+                Err(e) => Err(e),
+            };
+            run_hooks(
+                &input.path,
+                written
+                    .as_ref()
+                    .map(|(result, output)| (result, output.as_deref())),
+                app_config,
+                options,
+            )
+            .await;
+            let (result, error) = match written {
+                Ok((result, output)) => {
+                    report.push_success(
+                        &input.path,
+                        result.page_count(),
+                        result.get_processing_time_ms(),
+                        output.as_deref(),
+                    );
+                    if let Some(ref mut concat) = concat {
+                        concat.push(&input.relative, &result.extracted_text);
+                    }
+                    (Some(result), None)
+                }
+                Err(e) => {
+                    report.push_failure(&input.path, &e);
+                    (None, Some(e))
+                }
+            };
+            if let (Some((_, manifest)), Some(file)) = (manifest.as_mut(), report.files.last()) {
+                manifest.record(file, result.as_ref());
+            }
+            if let Some(e) = error {
+                first_error.get_or_insert(e);
+            }
+        }
+    };
+    tokio::join!(reader, validator, uploader, ocr, writer);
+    report.skipped = total - report.files.len();
+
+    let concat_path = match (concat, args.concat.as_ref()) {
+        (Some(concat), Some(path)) => match concat.write(path) {
+            Ok(()) => {
+                tracing::info!(
+                    "Combined {} documents into {}",
+                    concat.len(),
+                    path.display()
+                );
+                Some(path.clone())
+            }
+            Err(e) => {
+                tracing::error!("Failed to write the combined output: {}", e.user_message());
+                first_error.get_or_insert(e);
+                None
+            }
         },
+        _ => None,
+    };
+
+    // A run without its manifest is not auditable, so a failed write fails the run
+    let manifest_path = match manifest {
+        Some((dir, mut manifest)) => {
+            manifest.finish(&report);
+            match manifest.write(&dir) {
+                Ok(path) => {
+                    tracing::info!("Run manifest written to {}", path.display());
+                    Some(path)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to write the run manifest: {}", e.user_message());
+                    first_error.get_or_insert(e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    // The orchestrator reads the outcome from the results, so they are written
+    // last and carry the exit code of the whole run
+    let results_path = match job {
+        Some(ref job) => {
+            let path = job.results_path(args.output_dir.as_deref());
+            let results = JobResults::new(job, &report, first_error.as_ref(), started_at);
+            match results.write(&path) {
+                Ok(()) => {
+                    tracing::info!("Job results written to {}", path.display());
+                    Some(path)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to write the job results: {}", e.user_message());
+                    first_error.get_or_insert(e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let output = if !output_format.is_json() {
+        let mut output = report.render(styler);
+        if let Some(ref path) = concat_path {
+            output.push_str(&format!(
+                "\n{}",
+                styler.dim(&format!("Combined output: {}", path.display()))
+            ));
+        }
+        if let Some(ref path) = manifest_path {
+            output.push_str(&format!(
+                "\n{}",
+                styler.dim(&format!("Run manifest: {}", path.display()))
+            ));
+        }
+        if let Some(ref path) = results_path {
+            output.push_str(&format!(
+                "\n{}",
+                styler.dim(&format!("Job results: {}", path.display()))
+            ));
+        }
+        output
+    } else {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "success": report.failed == 0,
+            "data": report,
+            "concat": concat_path,
+            "manifest": manifest_path,
+            "results": results_path,
+            "summary": options.run.counters.summary(&options.metrics, cache).await,
+        }))
+        .map_err(|e| Error::internal_from("Failed to serialize batch report", e))?
+    };
+
+    Ok((output, first_error))
+}
+
+/// Process documents appearing in a directory until interrupted
+///
+/// Prints one line per document as it is processed (NDJSON with JSON output)
+/// and returns the totals. A budget error stops watching.
+pub async fn watch_command(
+    args: &WatchArgs,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    output_format: OutputFormat,
+    styler: &Styler,
+    enable_verbose_logging: bool,
+) -> Result<String> {
+    let mut watcher = DirectoryWatcher::new(
+        args.dir.clone(),
+        std::time::Duration::from_secs(args.settle),
+    )?
+    .with_excludes(args.exclude.clone());
+    let rules = app_config.validation_rules();
+    let json = output_format.is_json();
+    let interval = std::time::Duration::from_secs(args.interval);
+    let lock_dir = args
+        .lock_dir
+        .clone()
+        .unwrap_or_else(|| watcher.dir().join(LOCK_DIR));
+    let mut report = BatchReport::default();
+
+    tracing::info!(
+        "Watching {} every {}s",
+        watcher.dir().display(),
+        args.interval
     );
+    loop {
+        let mut found = watcher.scan(&rules)?;
+        if let Some(order) = args.order {
+            order.sort(&mut found, |path| path);
+        }
+        for input in found {
+            let relative = PathBuf::from(input.file_name().unwrap_or_default());
+            let existing = match args.output_dir {
+                Some(ref output_dir) => output_path(
+                    output_dir,
+                    &relative,
+                    json,
+                    options.compress_output,
+                    app_config.encryption.is_enabled(),
+                ),
+                None => written_sidecar_path(
+                    &input,
+                    options.compress_output,
+                    app_config.encryption.is_enabled(),
+                ),
+            };
+            if is_up_to_date(&input, &existing) {
+                tracing::debug!("Skipping {}: result is up to date", input.display());
+                continue;
+            }
 
-    // Format output based on user preference
-    let output = if enable_json_output {
-        serde_json::to_string_pretty(&result.to_json_output())
-            .map_err(|e| Error::Internal(format!("Failed to serialize JSON: {}", e)))?
+            let processed = match DocumentLock::try_acquire(&lock_dir, &input) {
+                Ok(None) => {
+                    tracing::debug!("Skipping {}: locked by another worker", input.display());
+                    continue;
+                }
+                // Another worker may have finished it between the check and the lock
+                Ok(Some(_)) if is_up_to_date(&input, &existing) => continue,
+                Ok(Some(_lock)) => {
+                    process_document(
+                        &input,
+                        &relative,
+                        args.output_dir.as_deref(),
+                        app_config,
+                        options,
+                        output_format,
+                        enable_verbose_logging,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            run_hooks(
+                &input,
+                processed
+                    .as_ref()
+                    .map(|(result, output)| (result, output.as_deref())),
+                app_config,
+                options,
+            )
+            .await;
+            match processed {
+                Ok((result, output)) => report.push_success(
+                    &input,
+                    result.page_count(),
+                    result.get_processing_time_ms(),
+                    output.as_deref(),
+                ),
+                Err(e @ Error::BudgetExceeded(_)) => return Err(e),
+                Err(e) => report.push_failure(&input, &e),
+            }
+
+            // Print and drop each outcome so long runs only keep the totals
+            if let Some(file) = report.files.pop() {
+                if json {
+                    let line = serde_json::to_string(&file)
+                        .map_err(|e| Error::internal_from("Failed to serialize watch event", e))?;
+                    println!("{}", line);
+                } else {
+                    println!("{}", file.render(styler));
+                }
+            }
+        }
+
+        // --once still waits for the documents that are being written
+        let wait = match (args.once, watcher.next_settle()) {
+            (true, None) => break,
+            (true, Some(settle)) => settle,
+            (false, _) => interval,
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    if json {
+        Ok(serde_json::json!({
+            "succeeded": report.succeeded,
+            "failed": report.failed,
+        })
+        .to_string())
+    } else {
+        Ok(report.render_totals(styler))
+    }
+}
+
+/// Inspect or clean the persistent result cache
+pub fn cache_command(
+    command: &CacheCommand,
+    app_config: &Config,
+    enable_json_output: bool,
+) -> Result<String> {
+    let cache = app_config.cache.open()?;
+    let (text, json) = match command {
+        CacheCommand::Stats => {
+            let stats = cache.stats()?;
+            (stats.to_human_readable(), serde_json::json!(stats))
+        }
+        CacheCommand::Prune => {
+            let removed = cache.prune()?;
+            (
+                format!("Removed {} expired cache entries", removed),
+                serde_json::json!({ "removed": removed }),
+            )
+        }
+        CacheCommand::Clear => {
+            let removed = cache.clear()?;
+            (
+                format!("Removed {} cache entries", removed),
+                serde_json::json!({ "removed": removed }),
+            )
+        }
+        CacheCommand::Path => (
+            cache.dir().display().to_string(),
+            serde_json::json!({ "dir": cache.dir() }),
+        ),
+        CacheCommand::Warm(ref args) => {
+            let options = args.options.processing_options(
+                &MetricsCollector::default(),
+                &CacheManager::default(),
+                &RunContext::default(),
+            );
+            let report = warm_cache(
+                &cache,
+                &args.dir,
+                args.documents.as_deref(),
+                app_config,
+                &options,
+            )?;
+            (report.to_human_readable(), serde_json::json!(report))
+        }
+        CacheCommand::Export(ref args) => {
+            let report = cache_backup::export(&cache, args.results.as_deref(), &args.file)?;
+            (
+                format!(
+                    "Exported {} cache entries and {} results to {}",
+                    report.entries,
+                    report.results,
+                    args.file.display()
+                ),
+                serde_json::json!(report),
+            )
+        }
+        CacheCommand::Import(ref args) => {
+            let report = cache_backup::import(&cache, args.results.as_deref(), &args.file)?;
+            (
+                format!(
+                    "Imported {} cache entries and {} results ({} skipped)",
+                    report.entries, report.results, report.skipped
+                ),
+                serde_json::json!(report),
+            )
+        }
+    };
+
+    if enable_json_output {
+        serde_json::to_string_pretty(&json)
+            .map_err(|e| Error::internal_from("Failed to serialize cache output", e))
+    } else {
+        Ok(text)
+    }
+}
+
+/// Add the stored results of a directory to the cache, under the options of
+/// the runs that should find them
+fn warm_cache(
+    cache: &ResultCache,
+    dir: &Path,
+    documents: Option<&Path>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+) -> Result<CacheWarmReport> {
+    let cache_options = cache_options(app_config, options);
+    let mut report = CacheWarmReport::default();
+
+    for stored in ResultStore::new(dir).load_all()? {
+        report.results += 1;
+        let Some(provider) = stored
+            .provider
+            .as_deref()
+            .and_then(|provider| provider.parse::<ProviderKind>().ok())
+        else {
+            tracing::warn!(
+                "Skipping {}: no provider recorded",
+                stored.result_path.display()
+            );
+            report.skipped += 1;
+            continue;
+        };
+        if !is_current_model(provider, stored.model.as_deref(), app_config) {
+            tracing::warn!(
+                "Skipping {}: made with {} {}, not {}",
+                stored.result_path.display(),
+                provider,
+                stored.model.as_deref().unwrap_or("an unknown model"),
+                provider.model(app_config)
+            );
+            report.skipped += 1;
+            continue;
+        }
+        let original = stored.original_path.clone().or_else(|| {
+            documents
+                .map(|documents| documents.join(&stored.file_name))
+                .filter(|path| path.is_file())
+        });
+
+        // The document itself is the better source; the stored hash is only
+        // used when it is gone
+        let sha256 = match original {
+            Some(ref original) => {
+                let sha256 = match FileUpload::new(original).and_then(|upload| upload.sha256()) {
+                    Ok(sha256) => sha256,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Skipping {}: {}",
+                            stored.result_path.display(),
+                            e.user_message()
+                        );
+                        report.skipped += 1;
+                        continue;
+                    }
+                };
+                if stored
+                    .sha256
+                    .as_ref()
+                    .is_some_and(|stored| *stored != sha256)
+                {
+                    tracing::warn!(
+                        "Skipping {}: {} changed since it was processed",
+                        stored.result_path.display(),
+                        original.display()
+                    );
+                    report.skipped += 1;
+                    continue;
+                }
+                sha256
+            }
+            None => match stored.sha256 {
+                Some(ref sha256) => sha256.clone(),
+                None => {
+                    tracing::warn!(
+                        "Skipping {}: neither {} nor its SHA-256 was found",
+                        stored.result_path.display(),
+                        stored.file_name
+                    );
+                    report.skipped += 1;
+                    continue;
+                }
+            },
+        };
+
+        let key = ResultCache::key(&sha256, &cache_options);
+        if cache.contains(&key) {
+            report.cached += 1;
+            continue;
+        }
+        let entry = CachedOutput::new(
+            stored_output(&stored, provider, &app_config.pages),
+            app_config,
+            &cache_options,
+        );
+        cache.put(&key, &entry)?;
+        report.warmed += 1;
+    }
+
+    Ok(report)
+}
+
+/// Whether a stored result was made with the model the provider reads with now
+///
+/// Mistral reports the dated version it used (`mistral-ocr-2505-completion`
+/// for `mistral-ocr-2505`), so results are only current under a pinned
+/// `ocr_model`; under an alias such as `mistral-ocr-latest` the version it
+/// resolves to now is unknown. Local providers read the same way under the
+/// same configuration.
+fn is_current_model(provider: ProviderKind, model: Option<&str>, app_config: &Config) -> bool {
+    match (provider, model) {
+        (ProviderKind::Mistral, Some(model)) => {
+            let current = &app_config.ocr_model;
+            model == current
+                || model
+                    .strip_prefix(current.as_str())
+                    .is_some_and(|suffix| suffix.starts_with('-'))
+        }
+        (ProviderKind::Mistral, None) => false,
+        _ => true,
+    }
+}
+
+/// Provider output standing in for a stored result
+fn stored_output(
+    stored: &StoredResult,
+    provider: ProviderKind,
+    layout: &PageLayout,
+) -> ProviderOutput {
+    let pages = stored_pages(stored, layout);
+    let pages_processed = stored.page_count.unwrap_or(pages.len() as u64);
+
+    ProviderOutput {
+        provider,
+        file_id: "warm".to_string(),
+        response: OCRResponse {
+            usage_info: UsageInfo {
+                pages_processed: pages_processed as i32,
+                doc_size_bytes: 0,
+            },
+            pages,
+            model: stored
+                .model
+                .clone()
+                .unwrap_or_else(|| provider.name().to_string()),
+            document_annotation: None,
+        },
+        upload_ms: 0,
+        ocr_ms: 0,
+    }
+}
+
+/// Pages of a stored result: its text split on the page separator when that
+/// gives the stored page count, or else all of it as one page, as blank pages
+/// may have been left out of it
+fn stored_pages(stored: &StoredResult, layout: &PageLayout) -> Vec<Page> {
+    let text = &stored.extracted_text;
+    let parts: Vec<&str> = if layout.separator.is_empty() {
+        vec![text]
     } else {
-        result.to_human_readable()
+        text.split(layout.separator.as_str()).collect()
     };
+    if parts.len() < 2 || stored.page_count != Some(parts.len() as u64) {
+        return vec![text_page(0, text)];
+    }
 
-    Ok(output)
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let header = layout
+                .header
+                .as_ref()
+                .map(|header| format!("{}\n", header.replace("{page}", &(index + 1).to_string())));
+            let part = header
+                .and_then(|header| part.strip_prefix(header.as_str()))
+                .unwrap_or(part);
+            text_page(index, part)
+        })
+        .collect()
+}
+
+/// Show, locate, or check the configuration
+///
+/// `load_config` is only called by the commands that need the settings, so
+/// `config path` also works when the file does not parse.
+pub fn config_command(
+    command: &ConfigCommand,
+    config_path: &Path,
+    load_config: impl FnOnce() -> Result<Config>,
+    enable_json_output: bool,
+) -> Result<String> {
+    let serialize_error = |e: &dyn std::fmt::Display| {
+        Error::internal(format!("Failed to serialize configuration: {}", e))
+    };
+
+    match command {
+        ConfigCommand::Path if enable_json_output => Ok(serde_json::json!({
+            "path": config_path,
+            "exists": config_path.exists(),
+        })
+        .to_string()),
+        ConfigCommand::Path => Ok(config_path.display().to_string()),
+        ConfigCommand::Show => {
+            let app_config = load_config()?.redacted();
+            if enable_json_output {
+                serde_json::to_string_pretty(&app_config).map_err(|e| serialize_error(&e))
+            } else {
+                toml::to_string_pretty(&app_config)
+                    .map(|toml| toml.trim_end().to_string())
+                    .map_err(|e| serialize_error(&e))
+            }
+        }
+        ConfigCommand::Validate => {
+            load_config()?.validate()?;
+            if enable_json_output {
+                Ok(serde_json::json!({ "valid": true, "path": config_path }).to_string())
+            } else if config_path.exists() {
+                Ok(format!("Configuration is valid: {}", config_path.display()))
+            } else {
+                Ok("Configuration is valid (no config file; defaults and environment)".to_string())
+            }
+        }
+    }
+}
+
+/// Manage files uploaded to the Mistral Files API
+pub async fn files_command(
+    command: &FilesCommand,
+    app_config: &Config,
+    metrics: &MetricsCollector,
+    enable_json_output: bool,
+) -> Result<String> {
+    let api_credentials = APICredentials::from_config(app_config)?;
+    let files_client = FilesClient::new(
+        MistralClient::from_config(api_credentials, app_config)?.with_metrics(metrics.clone()),
+    );
+    fn to_json(value: &impl Serialize) -> Result<String> {
+        serde_json::to_string_pretty(value)
+            .map_err(|e| Error::internal_from("Failed to serialize files output", e))
+    }
+
+    match command {
+        FilesCommand::List => {
+            let list = files_client.list_files().await?;
+            if enable_json_output {
+                return to_json(&list);
+            }
+            if list.data.is_empty() {
+                return Ok("No files".to_string());
+            }
+            Ok(list
+                .data
+                .iter()
+                .map(file_line)
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        FilesCommand::Get { id } => {
+            let file = files_client.get_file(id).await?;
+            if enable_json_output {
+                to_json(&file)
+            } else {
+                Ok(file_line(&file))
+            }
+        }
+        FilesCommand::Delete { id } => {
+            let deleted = files_client.delete_file(id).await?;
+            if enable_json_output {
+                to_json(&deleted)
+            } else if deleted.deleted {
+                Ok(format!("Deleted file {}", deleted.id))
+            } else {
+                Err(Error::api(format!("File {} was not deleted", deleted.id)))
+            }
+        }
+    }
+}
+
+/// One line per uploaded file: ID, size, purpose, status, and name
+fn file_line(file: &FileUploadResponse) -> String {
+    format!(
+        "{}  {:>10} bytes  {}  {}  {}",
+        file.id,
+        file.bytes,
+        file.purpose,
+        file.status.as_deref().unwrap_or("-"),
+        file.filename
+    )
 }
 
 /// Validate input file path and format
@@ -111,7 +1514,7 @@ pub fn validate_file_path(input_file_path: &str) -> Result<()> {
 
     // Check if it's a file (not directory)
     if !file_path.is_file() {
-        return Err(Error::Validation(format!(
+        return Err(Error::validation(format!(
             "Path is not a file: {}",
             input_file_path
         )));
@@ -125,11 +1528,11 @@ pub fn validate_file_path(input_file_path: &str) -> Result<()> {
 
     match extension.as_deref() {
         Some("pdf") | Some("png") | Some("jpg") | Some("jpeg") => Ok(()),
-        Some(ext) => Err(Error::Validation(format!(
+        Some(ext) => Err(Error::validation(format!(
             "Unsupported file format: .{}. Supported formats: pdf, png, jpg, jpeg",
             ext
         ))),
-        None => Err(Error::Validation(
+        None => Err(Error::validation(
             "File has no extension. Supported formats: pdf, png, jpg, jpeg".to_string(),
         )),
     }
@@ -147,6 +1550,17 @@ pub fn show_help_with_examples() -> String {
 
 USAGE:
     paperless-ngx-ocr2 [OPTIONS] --file <FILE>
+    paperless-ngx-ocr2 [OPTIONS] <COMMAND>
+
+COMMANDS:
+    ocr          Extract text from a single document (same as --file)
+    batch        Extract text from many documents into an output directory
+    watch        Process documents as they appear in a directory
+    cache        Inspect, clean, and back up the persistent result cache
+    config       Show, locate, or check the configuration
+    files        List, inspect, and delete files uploaded to the Mistral Files API
+    serve        Accept documents over HTTP and answer with the JSON output
+    completions  Print a shell completion script
 
 EXAMPLES:
     # Extract text from a PDF file
@@ -165,6 +1579,21 @@ EXAMPLES:
     # Use custom API endpoint
     paperless-ngx-ocr2 --file document.pdf --api-key your-api-key --api-base-url https://custom.api.url
 
+    # Record the API calls once, then replay them offline
+    paperless-ngx-ocr2 --file document.pdf --record fixtures/
+    paperless-ngx-ocr2 --file document.pdf --replay fixtures/
+
+    # Extract every document below scans/ into text/
+    paperless-ngx-ocr2 batch scans/ --recursive --output-dir text/
+    paperless-ngx-ocr2 batch scans/ --recursive --output-dir text/ --order size-asc
+
+    # Process documents dropped into inbox/ and write sidecars
+    paperless-ngx-ocr2 watch inbox/ --sidecar
+    paperless-ngx-ocr2 watch inbox/ --sidecar --log-file ocr.log --log-rotation daily
+
+    # Check the configuration
+    paperless-ngx-ocr2 config validate
+
 SUPPORTED FILE FORMATS:
     - PDF files (.pdf) up to 100MB
     - PNG images (.png) up to 100MB  
@@ -174,23 +1603,60 @@ CONFIGURATION:
     Configuration can be provided via:
     1. Command-line arguments (highest priority)
     2. Environment variables
-    3. TOML configuration file at ~/.config/paperless-ngx-ocr2/config.toml
+    3. --env-file, then .env next to the config file, then .env in the current directory
+    4. TOML configuration file at ~/.config/paperless-ngx-ocr2/config.toml
 
-ENVIRONMENT VARIABLES:
+ENVIRONMENT VARIABLES (prefix set by env_prefix in the config file):
     PAPERLESS_OCR_API_KEY          Mistral AI API key
     PAPERLESS_OCR_API_BASE_URL     API base URL (default: https://api.mistral.ai)
-    PAPERLESS_OCR_TIMEOUT          Request timeout in seconds (default: 30)
-    PAPERLESS_OCR_MAX_FILE_SIZE    Maximum file size in MB (default: 100)
+    PAPERLESS_OCR_ALLOW_INSECURE_LOOPBACK
+                                   Allow a plain HTTP API base URL on this machine (default: false)
+    PAPERLESS_OCR_TIMEOUT          Per-document timeout in seconds (default: 30)
+    PAPERLESS_OCR_CONNECT_TIMEOUT  Connect timeout in seconds (default: 10)
+    PAPERLESS_OCR_MAX_FILE_SIZE_MB Maximum file size in MB (default: 100, max: 1024)
+    PAPERLESS_OCR_MAX_RESPONSE_SIZE_MB
+                                   Maximum API response size in MB (default: 64, max: 1024)
+    PAPERLESS_OCR_ALLOWED_MIME_TYPES
+                                   Accepted MIME types, comma-separated
+    PAPERLESS_OCR_VALIDATION       Content checks: strict or permissive (default: strict)
+    PAPERLESS_OCR_USE_SIGNED_URL   Reference uploads by signed URL (default: false)
+    PAPERLESS_OCR_PAGE_SEPARATOR   Text between pages, escapes allowed (default: \n\n)
+    PAPERLESS_OCR_PAGE_HEADER      Header before each page, e.g. "--- page {{page}} ---"
+    PAPERLESS_OCR_MAX_CONCURRENT_REQUESTS
+                                   Concurrent API calls per account (default: 4)
+    PAPERLESS_OCR_MEMORY_BUDGET_MB Memory for document buffers, process-wide (default: no limit)
+    PAPERLESS_OCR_HTTP_VERSION     HTTP protocol: auto, http1, http2 (default: auto)
+    PAPERLESS_OCR_REQUEST_COMPRESSION
+                                   Request body encoding: none, gzip, zstd (default: none)
+    PAPERLESS_OCR_POOL_IDLE_TIMEOUT
+                                   Seconds idle connections are kept (default: 90)
+    PAPERLESS_OCR_POOL_MAX_IDLE_PER_HOST
+                                   Idle connections kept per host (default: 32)
     PAPERLESS_OCR_LOG_LEVEL        Log level (default: info)
+    PAPERLESS_OCR_OCR_MODEL        Mistral OCR model (default: mistral-ocr-latest)
+    PAPERLESS_OCR_EMBEDDING_MODEL  Model for --embed (default: mistral-embed)
+    PAPERLESS_OCR_PROVIDERS        OCR providers in fallback order (default: mistral)
+    PAPERLESS_OCR_MERGE_STRATEGY   Combine providers: fallback or ensemble (default: fallback)
+    PAPERLESS_OCR_MOCK_LATENCY_MS  Simulated time per document of the mock provider (default: 0)
+    PAPERLESS_OCR_MOCK_ERROR_RATE  Fraction of mock documents failing, 0.0-1.0 (default: 0)
+    PAPERLESS_OCR_MAX_PAGES        Stop after this many pages in one run
+    PAPERLESS_OCR_MAX_COST         Stop once the estimated cost reaches this amount
+    PAPERLESS_OCR_COST_PER_PAGE    Price per page for cost estimates (default: 0.001)
+    PAPERLESS_OCR_MONTHLY_QUOTA    Mistral pages per month before warning
+    PAPERLESS_OCR_ENFORCE_QUOTA    Refuse Mistral OCR once the quota is used up (default: false)
+    PAPERLESS_OCR_STATE_FILE       Monthly page counts (default: $XDG_STATE_HOME/paperless-ngx-ocr2/state.json)
+    PAPERLESS_OCR_ENCRYPT_RECIPIENTS
+                                   age recipients for --encrypt-output, comma-separated
+    PAPERLESS_OCR_CACHE            Reuse stored OCR results (default: false)
+    PAPERLESS_OCR_CACHE_DIR        Result cache (default: $XDG_CACHE_HOME/paperless-ngx-ocr2/results)
+    PAPERLESS_OCR_OTEL             Propagate W3C trace context to the API (default: false)
+    PAPERLESS_OCR_TRANSFORM_SCRIPT Rhai script run on each result before it is written
+    TRACEPARENT                    Trace to continue when PAPERLESS_OCR_OTEL is enabled
+    NO_COLOR                       Disable colors with --color auto
 
-EXIT CODES:
-    0    Success
-    2    Validation error (invalid file, configuration)
-    3    I/O error (file read/write issues)
-    4    Configuration error (missing API key, invalid config)
-    5    Internal error (API errors, network issues)
-"#,
+{}"#,
         env!("CARGO_PKG_NAME"),
-        env!("CARGO_PKG_VERSION")
+        env!("CARGO_PKG_VERSION"),
+        crate::error::exit_code_reference()
     )
 }
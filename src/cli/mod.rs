@@ -1,8 +1,33 @@
 //! CLI argument parsing and command handling
 
+use crate::api::dns::{IpPreference, ResolveOverride};
+use crate::api::ocr::{unescape, ResponseFormat, DEFAULT_PAGE_HEADER};
+use crate::batch::ProcessingOrder;
+use crate::cache::CacheManager;
+use crate::compare::DiffBaseline;
 use crate::config::Config;
-use crate::error::{Error, Result};
-use clap::Parser;
+use crate::ensemble::MergeStrategy;
+use crate::error::{Error, FieldError, FieldErrors, Result};
+use crate::extraction::ExtractionPreset;
+use crate::file::ValidationMode;
+use crate::fixtures::FixtureServer;
+use crate::hooks::Hooks;
+use crate::job::JobManifest;
+use crate::log_file::{LogRotation, RotatingFile, RotationPolicy};
+use crate::metrics::{MetricsCollector, MetricsFormat};
+use crate::output::ColorChoice;
+use crate::paperless::MatchStrategy;
+use crate::pipeline::RunContext;
+use crate::progress::ProgressEvent;
+use crate::provider::ProviderKind;
+use crate::quality::LowQualityAction;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{generate, Shell};
+use clap_complete_nushell::Nushell;
+use std::io::{IsTerminal, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 // use std::env; // Removed - no longer needed
 
 pub mod commands;
@@ -22,7 +47,45 @@ pub struct CLISuccessData {
     pub file_name: String,
     pub file_size: i64,
     pub processing_time_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<i64>,
     pub confidence: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<crate::quality::QualityScore>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blank_pages: Vec<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_pages: Vec<crate::api::ocr::DuplicatePage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_pages: Vec<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repeated_lines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_date: Option<chrono::NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correspondents: Vec<crate::correspondents::CorrespondentCandidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<crate::ocr::Translation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<crate::ocr::Entities>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction: Option<crate::extraction::Extraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<crate::ocr::Embedding>,
 }
 
 /// Error data structure for CLI JSON output  
@@ -30,33 +93,372 @@ pub struct CLISuccessData {
 pub struct CLIErrorData {
     #[serde(rename = "type")]
     pub error_type: String,
+    /// Stable named error code, such as `E_RATE_LIMIT`
+    #[serde(default)]
+    pub code: String,
+    /// Process exit code the error causes
+    #[serde(default)]
+    pub exit_code: i32,
     pub message: String,
     pub details: Option<String>,
+    /// Messages of the underlying errors, outermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+    /// Invalid settings or arguments of a validation error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    /// Provider request ID of the failed API call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl CLIOutput {
+    /// Failed output for an error
+    pub fn from_error(e: &Error) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(CLIErrorData {
+                error_type: e.error_type().to_string(),
+                code: e.code().to_string(),
+                exit_code: e.exit_code(),
+                message: e.user_message(),
+                details: Some(e.to_string()),
+                causes: e.causes(),
+                fields: e.field_errors().to_vec(),
+                request_id: e.request_id().map(str::to_string),
+            }),
+        }
+    }
+
     /// Validate CLI output structure
     pub fn validate(&self) -> Result<()> {
         // Ensure mutual exclusivity of data and error
         match (self.success, &self.data, &self.error) {
             (true, Some(_), None) => Ok(()),
             (false, None, Some(_)) => Ok(()),
-            _ => Err(Error::Validation(
+            _ => Err(Error::validation(
                 "CLI output must have either data (success=true) or error (success=false), not both".to_string()
             ))
         }
     }
 }
 
+/// Output format of the OCR result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON with the pages joined into `extracted_text`
+    Json,
+    /// JSON with the `pages` array (index, markdown, dpi, width, height)
+    PagesJson,
+    /// Only the extracted text, with the markdown syntax removed
+    Plain,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "pages-json" => Ok(Self::PagesJson),
+            "plain" => Ok(Self::Plain),
+            other => Err(format!(
+                "Unknown output format: {}. Supported: text, json, pages-json, plain",
+                other
+            )),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Whether the output is JSON
+    pub fn is_json(self) -> bool {
+        matches!(self, Self::Json | Self::PagesJson)
+    }
+}
+
+/// Shells with completion scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "ps1")]
+    Powershell,
+    Elvish,
+    #[value(alias = "nu")]
+    Nushell,
+}
+
 #[derive(Parser)]
 #[command(
     name = "paperless-ngx-ocr2",
     author = "fzymgc-house",
     version,
     about = "OCR CLI tool that uploads PDF/image files to Mistral AI APIs for text extraction",
-    long_about = "A command-line tool for extracting text from PDF and image files using Mistral AI's OCR capabilities. Supports TOML configuration, 12-factor app principles, and provides both human-readable and JSON output formats."
+    long_about = "A command-line tool for extracting text from PDF and image files using Mistral AI's OCR capabilities. Supports TOML configuration, 12-factor app principles, and provides both human-readable and JSON output formats.",
+    after_help = crate::error::exit_code_reference()
 )]
 pub struct Cli {
+    /// File and processing flags of the flat invocation (same as `ocr`)
+    #[command(flatten)]
+    pub ocr: OcrArgs,
+
+    /// API key for Mistral AI
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Mistral AI API key (default: PAPERLESS_OCR_API_KEY)",
+        value_name = "KEY"
+    )]
+    pub api_key: Option<String>,
+
+    /// API base URL
+    #[arg(
+        long,
+        global = true,
+        help = "Mistral AI API base URL (default: PAPERLESS_OCR_API_BASE_URL, or https://api.mistral.ai)",
+        value_name = "URL"
+    )]
+    pub api_base_url: Option<String>,
+
+    /// Output format as JSON
+    #[arg(
+        long,
+        global = true,
+        help = "Output result in JSON format instead of human-readable text"
+    )]
+    pub json: bool,
+
+    /// Output format of the OCR result
+    #[arg(
+        long,
+        global = true,
+        help = "Output format: text (default), json, pages-json for the per-page array with dimensions, or plain for the text without markdown",
+        value_name = "FORMAT"
+    )]
+    pub format: Option<OutputFormat>,
+
+    /// Verbose output
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Enable verbose logging output and show the causes of errors"
+    )]
+    pub verbose: bool,
+
+    /// Suppress the run summary
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Do not print the run summary (files, pages, cache hits, retries, duration) to stderr"
+    )]
+    pub quiet: bool,
+
+    /// When to color human-readable output
+    #[arg(
+        long,
+        global = true,
+        value_name = "WHEN",
+        default_value = "auto",
+        help = "Color human-readable output: auto (default; terminals only, off when NO_COLOR is set), always, or never"
+    )]
+    pub color: ColorChoice,
+
+    /// Print API metrics after the run
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        help = "Print API call counts and latency percentiles (p50/p95/p99) to stderr after the run: text (default), json, or prometheus"
+    )]
+    pub show_metrics: Option<MetricsFormat>,
+
+    /// Progress events as NDJSON
+    #[arg(
+        long,
+        global = true,
+        help = "Write machine-readable progress events (NDJSON) to stderr"
+    )]
+    pub progress_json: bool,
+
+    /// Static DNS overrides
+    #[arg(
+        long,
+        global = true,
+        help = "Connect to ADDRESS for HOST:PORT instead of resolving it (repeatable)",
+        value_name = "HOST:PORT:ADDRESS"
+    )]
+    pub resolve: Vec<ResolveOverride>,
+
+    /// IP family preference
+    #[arg(
+        long,
+        global = true,
+        help = "IP family to connect with: auto, ipv4, ipv6",
+        value_name = "FAMILY"
+    )]
+    pub ip_preference: Option<IpPreference>,
+
+    /// Record API interactions as fixtures
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "replay",
+        help = "Record the API interactions of this run as sanitized fixtures in DIR",
+        value_name = "DIR"
+    )]
+    pub record: Option<PathBuf>,
+
+    /// Replay recorded API interactions
+    #[arg(
+        long,
+        global = true,
+        help = "Answer API requests from fixtures recorded with --record instead of calling the API",
+        value_name = "DIR"
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// Page budget for the run
+    #[arg(
+        long,
+        global = true,
+        help = "Stop processing further files once this many pages were processed (exit code 7)",
+        value_name = "PAGES"
+    )]
+    pub max_pages: Option<u64>,
+
+    /// Cost budget for the run
+    #[arg(
+        long,
+        global = true,
+        help = "Stop processing further files once the estimated cost reaches this amount (exit code 7)",
+        value_name = "AMOUNT"
+    )]
+    pub max_cost: Option<f64>,
+
+    /// Refuse documents once the monthly quota is used up
+    #[arg(
+        long,
+        global = true,
+        help = "Refuse Mistral OCR once the monthly page quota (quota.monthly_pages) is used up (exit code 7)"
+    )]
+    pub enforce_quota: bool,
+
+    /// Custom configuration file path
+    #[arg(
+        long,
+        global = true,
+        help = "Path to custom configuration file",
+        value_name = "PATH"
+    )]
+    pub config: Option<String>,
+
+    /// Environment file loaded before the configuration
+    #[arg(
+        long,
+        global = true,
+        help = "Load environment variables from PATH (in addition to .env next to the config file and in the current directory)",
+        value_name = "PATH"
+    )]
+    pub env_file: Option<PathBuf>,
+
+    /// Log file instead of stderr
+    #[arg(
+        long,
+        global = true,
+        help = "Write logs to PATH instead of stderr, rotated by --log-max-size and --log-rotation",
+        value_name = "PATH"
+    )]
+    pub log_file: Option<PathBuf>,
+
+    /// Log file size that triggers rotation
+    #[arg(
+        long,
+        global = true,
+        help = "Rotate the log file before it grows past MB megabytes (0 = no size limit)",
+        value_name = "MB",
+        default_value_t = 10,
+        requires = "log_file"
+    )]
+    pub log_max_size: u64,
+
+    /// Time-based log file rotation
+    #[arg(
+        long,
+        global = true,
+        help = "Also rotate the log file every hour or day: never (default), hourly, or daily",
+        value_name = "WHEN",
+        default_value = "never",
+        requires = "log_file"
+    )]
+    pub log_rotation: LogRotation,
+
+    /// Rotated log files to keep
+    #[arg(
+        long,
+        global = true,
+        help = "Keep the newest N rotated log files and delete older ones",
+        value_name = "N",
+        default_value_t = 5,
+        requires = "log_file"
+    )]
+    pub log_keep: usize,
+
+    /// Keep intermediate files
+    #[arg(
+        long,
+        global = true,
+        help = "Keep intermediate files (rendered pages, uploads) in the run's temporary directory for debugging"
+    )]
+    pub keep_temp: bool,
+
+    /// Generate shell completion scripts
+    #[arg(
+        long,
+        help = "Generate shell completion scripts for the specified shell",
+        value_name = "SHELL"
+    )]
+    pub completions: Option<CompletionShell>,
+
+    /// Print the exit code reference
+    #[arg(
+        long,
+        help = "Print what each exit code means (as JSON with --json) and exit"
+    )]
+    pub explain_exit_codes: bool,
+
+    /// Subcommand to run instead of processing a single file
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// API metrics of this run, shared by every client it creates
+    #[arg(skip)]
+    metrics: MetricsCollector,
+
+    /// Caches of this run
+    #[arg(skip)]
+    cache: CacheManager,
+
+    /// Temporary directory, memory budget, and counters of this run
+    #[arg(skip)]
+    run: RunContext,
+}
+
+/// Arguments for `ocr` and the flat invocation
+#[derive(Args, Debug, Clone, Default)]
+pub struct OcrArgs {
     /// File to process for OCR
     #[arg(
         short,
@@ -66,79 +468,1333 @@ pub struct Cli {
     )]
     pub file: Option<String>,
 
-    /// API key for Mistral AI
+    /// Existing text to compare with
+    #[arg(
+        long,
+        help = "Print a unified diff and similarity score against an existing text instead of the result",
+        value_name = "FILE"
+    )]
+    pub diff: Option<PathBuf>,
+
+    /// Compare with the paperless-ngx document
+    #[arg(
+        long,
+        help = "Like --diff, against the current content of the matching paperless-ngx document (by checksum, then title)",
+        conflicts_with = "diff"
+    )]
+    pub diff_paperless: bool,
+
+    #[command(flatten)]
+    pub options: OcrOptions,
+}
+
+/// Processing flags shared by `ocr`, `batch`, `watch`, and `serve`
+#[derive(Args, Debug, Clone, Default)]
+pub struct OcrOptions {
+    /// OCR providers in fallback order
+    #[arg(
+        long,
+        alias = "provider",
+        help = "Comma-separated OCR providers tried in order: mistral, tesseract, mock, local-text (default: PAPERLESS_OCR_PROVIDERS)",
+        value_name = "LIST"
+    )]
+    pub providers: Option<String>,
+
+    /// How provider outputs are combined
+    #[arg(
+        long,
+        help = "How multiple providers are combined: fallback (first success) or ensemble (experimental per-page vote)",
+        value_name = "STRATEGY"
+    )]
+    pub merge_strategy: Option<MergeStrategy>,
+
+    /// Summarize extracted text
+    #[arg(
+        long,
+        help = "Add a short summary of the extracted text to the output (uses the chat API)"
+    )]
+    pub summarize: bool,
+
+    /// Translate extracted text
+    #[arg(
+        long,
+        help = "Translate extracted text into the given language code (uses the chat API)",
+        value_name = "LANG"
+    )]
+    pub translate_to: Option<String>,
+
+    /// Extract entities and keywords
+    #[arg(
+        long,
+        help = "Extract people, organizations, dates, amounts and keywords (uses the chat API)"
+    )]
+    pub extract_entities: bool,
+
+    /// Embed extracted text
+    #[arg(
+        long,
+        help = "Store an embedding of the extracted text in the output and sidecar (uses the embeddings API)"
+    )]
+    pub embed: bool,
+
+    /// Structured field extraction preset
+    #[arg(
+        long,
+        help = "Extract structured fields with a built-in preset: invoice",
+        value_name = "PRESET"
+    )]
+    pub extract: Option<ExtractionPreset>,
+
+    /// Omit blank pages from the extracted text
+    #[arg(
+        long,
+        help = "Omit pages without meaningful text from the output (see blank_page_threshold)"
+    )]
+    pub skip_blank_pages: bool,
+
+    /// Use the text layer of digital-born PDFs instead of OCR
+    #[arg(
+        long,
+        help = "Use the embedded text of PDFs that have text on every page instead of OCR (needs pdftotext; see [text_layer])"
+    )]
+    pub skip_text_pdfs: bool,
+
+    /// Omit duplicate pages from the extracted text
+    #[arg(
+        long,
+        help = "Omit pages repeating the preceding page (scanner double-feeds) from the output"
+    )]
+    pub remove_duplicate_pages: bool,
+
+    /// Strip repeated header and footer lines
+    #[arg(
+        long,
+        help = "Remove lines repeated on most pages (letterheads, page numbers) from the extracted text"
+    )]
+    pub strip_headers: bool,
+
+    /// Text between pages
+    #[arg(
+        long,
+        help = "Text placed between pages; \\n, \\t and \\f escapes are expanded (default: \\n\\n)",
+        value_name = "SEP"
+    )]
+    pub page_separator: Option<String>,
+
+    /// Header before each page
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_PAGE_HEADER,
+        help = "Put a header line before each page; {page} is the page number (default: \"--- page {page} ---\")",
+        value_name = "TEMPLATE"
+    )]
+    pub page_header: Option<String>,
+
+    /// Write a sidecar JSON file next to the input
+    #[arg(
+        long,
+        help = "Write the result to <file>.json next to the input (text, checksum, pages, language, timings)"
+    )]
+    pub sidecar: bool,
+
+    /// Gzip-compress outputs
+    #[arg(
+        long,
+        help = "Gzip-compress stdout and write sidecars as <file>.json.gz (redirect stdout, e.g. > scan.txt.gz)"
+    )]
+    pub compress_output: bool,
+
+    /// Encrypt outputs for an age recipient
+    #[arg(
+        long,
+        help = "Encrypt stdout and sidecar outputs for this age recipient (age1... or SSH public key; repeatable)",
+        value_name = "RECIPIENT"
+    )]
+    pub encrypt_output: Vec<String>,
+
+    /// Command run after each document that succeeded
+    #[arg(
+        long,
+        help = "Run COMMAND after each processed document; {input}, {name}, {output}, {pages}, and {provider} are replaced (also set as OCR_* variables)",
+        value_name = "COMMAND"
+    )]
+    pub on_success: Option<String>,
+
+    /// Command run after each document that failed
+    #[arg(
+        long,
+        help = "Run COMMAND after each failed document; {input}, {name}, {error}, and {exit_code} are replaced (also set as OCR_* variables)",
+        value_name = "COMMAND"
+    )]
+    pub on_failure: Option<String>,
+
+    /// Guidance for the OCR model
+    #[arg(
+        long,
+        help = "Guidance sent with the OCR request, e.g. \"preserve table structure\" (mistral only)",
+        value_name = "TEXT"
+    )]
+    pub instructions: Option<String>,
+
+    /// Reference the upload by signed URL
+    #[arg(
+        long,
+        help = "Send the OCR request a signed URL of the upload instead of its file ID (mistral only)"
+    )]
+    pub signed_url: bool,
+
+    /// Page text format requested from the OCR API
+    #[arg(
+        long,
+        help = "Page text format requested from the OCR API: markdown (default) or text (mistral only)",
+        value_name = "FORMAT"
+    )]
+    pub response_format: Option<ResponseFormat>,
+
+    /// MIME type override
+    #[arg(
+        long,
+        help = "Treat the file as this MIME type instead of detecting it from its content",
+        value_name = "TYPE"
+    )]
+    pub mime_type: Option<String>,
+
+    /// Client-side validation mode
+    #[arg(
+        long,
+        help = "strict rejects files that fail content checks; permissive only warns and lets the API decide",
+        value_name = "MODE"
+    )]
+    pub validation: Option<ValidationMode>,
+
+    /// Minimum number of extracted characters
+    #[arg(
+        long,
+        help = "Exit with code 6 when fewer than N non-whitespace characters are extracted",
+        value_name = "N"
+    )]
+    pub min_chars: Option<usize>,
+
+    /// Fail when no text is extracted
+    #[arg(
+        long,
+        help = "Exit with code 6 when no text is extracted (same as --min-chars 1)"
+    )]
+    pub fail_on_empty: bool,
+
+    /// Minimum OCR quality score
+    #[arg(
+        long,
+        help = "Minimum acceptable OCR quality score between 0.0 and 1.0",
+        value_name = "SCORE"
+    )]
+    pub min_quality: Option<f64>,
+
+    /// Action for results below the minimum quality
+    #[arg(
+        long,
+        help = "What to do when quality is below --min-quality: warn, rerun, fail",
+        value_name = "ACTION"
+    )]
+    pub on_low_quality: Option<LowQualityAction>,
+}
+
+/// Subcommands available in addition to the flat OCR invocation
+#[derive(Subcommand, Debug, Clone)]
+pub enum Commands {
+    /// Extract text from a single document (same as the flat invocation)
+    Ocr(OcrArgs),
+
+    /// Extract text from many documents into an output directory
+    Batch(BatchArgs),
+
+    /// Process documents as they appear in a directory
+    Watch(WatchArgs),
+
+    /// Inspect, clean, warm, and back up the persistent result cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
+
+    /// Show, locate, or check the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// List, inspect, and delete files uploaded to the Mistral Files API
+    #[command(subcommand)]
+    Files(FilesCommand),
+
+    /// Accept documents over HTTP and answer with the JSON output
+    Serve(ServeArgs),
+
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+
+    /// Export stored OCR results to other systems
+    #[command(subcommand)]
+    Export(ExportTarget),
+
+    /// Measure OCR accuracy (CER/WER) against reference transcripts
+    Eval(EvalArgs),
+
+    /// Run a document through several providers and compare the results
+    Compare(CompareArgs),
+
+    /// Suggest existing paperless-ngx tags that match a document's text
+    SuggestTags(SuggestTagsArgs),
+
+    /// Describe an exit code with likely causes and remediation steps
+    Explain(ExplainArgs),
+}
+
+/// Arguments for `batch`
+#[derive(Args, Debug, Clone)]
+pub struct BatchArgs {
+    /// Documents and directories to process
+    #[arg(
+        required_unless_present = "job_manifest",
+        conflicts_with = "job_manifest",
+        value_name = "PATH"
+    )]
+    pub paths: Vec<PathBuf>,
+
+    /// Manifest of the files to process
+    #[arg(
+        long,
+        help = "Process the files listed in a JSON job manifest, write a results manifest, and exit with the aggregate status (for Kubernetes Jobs and other orchestrators)",
+        value_name = "FILE"
+    )]
+    pub job_manifest: Option<PathBuf>,
+
+    /// Descend into subdirectories
+    #[arg(short, long, help = "Also process documents in subdirectories")]
+    pub recursive: bool,
+
+    /// Processing order
+    #[arg(
+        long,
+        help = "Process documents by size-asc, size-desc, mtime (oldest first), or name; default: given paths in order, each directory by name",
+        value_name = "ORDER",
+        conflicts_with = "job_manifest"
+    )]
+    pub order: Option<ProcessingOrder>,
+
+    /// Directory for the results
+    #[arg(
+        short,
+        long,
+        help = "Write each result to DIR/<document>.txt (.json with --json), keeping subdirectories",
+        value_name = "DIR"
+    )]
+    pub output_dir: Option<PathBuf>,
+
+    /// Combined output file
+    #[arg(
+        long,
+        help = "Also merge the extracted texts into one Markdown file with a heading per document and a table of contents",
+        value_name = "FILE"
+    )]
+    pub concat: Option<PathBuf>,
+
+    /// Directory for the run manifest
+    #[arg(
+        long,
+        help = "Write the run manifest (run-<timestamp>.json) to DIR instead of the output directory",
+        value_name = "DIR",
+        conflicts_with = "no_manifest"
+    )]
+    pub manifest_dir: Option<PathBuf>,
+
+    /// Skip the run manifest
+    #[arg(long, help = "Do not write a run manifest")]
+    pub no_manifest: bool,
+
+    #[command(flatten)]
+    pub options: OcrOptions,
+}
+
+impl BatchArgs {
+    /// Directory for the run manifest, None with `--no-manifest`
+    ///
+    /// Defaults to the output directory, or the platform state directory when
+    /// only sidecars are written (the current directory if there is none).
+    pub fn manifest_dir(&self) -> Option<PathBuf> {
+        if self.no_manifest {
+            return None;
+        }
+        self.manifest_dir
+            .clone()
+            .or_else(|| self.output_dir.clone())
+            .or_else(crate::paths::manifest_dir)
+            .or_else(|| Some(PathBuf::from(".")))
+    }
+
+    /// Validate the output and processing flags
+    ///
+    /// A job manifest may name the output directory, so jobs are checked for a
+    /// destination in [`BatchArgs::for_job`].
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.job_manifest.is_none() {
+            self.check_destination(&mut errors);
+        }
+        self.options.check(&mut errors);
+        errors.into_arguments_result()
+    }
+
+    /// The arguments for a job, with the output directory of its manifest
+    /// unless `--output-dir` is given
+    pub fn for_job(&self, job: &JobManifest) -> Result<Self> {
+        let args = Self {
+            output_dir: self.output_dir.clone().or_else(|| job.output_dir.clone()),
+            ..self.clone()
+        };
+        let mut errors = FieldErrors::new();
+        args.check_destination(&mut errors);
+        errors.into_arguments_result()?;
+        Ok(args)
+    }
+
+    fn check_destination(&self, errors: &mut FieldErrors) {
+        if self.output_dir.is_none() && !self.options.sidecar && self.concat.is_none() {
+            errors.add(
+                "--output-dir",
+                "Results need a destination: --output-dir, --sidecar, or --concat",
+            );
+        }
+    }
+}
+
+/// Arguments for `watch`
+#[derive(Args, Debug, Clone)]
+pub struct WatchArgs {
+    /// Directory to watch
+    #[arg(value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// Seconds between scans
+    #[arg(
+        long,
+        help = "Seconds between directory scans",
+        value_name = "SECONDS",
+        default_value_t = 5
+    )]
+    pub interval: u64,
+
+    /// Seconds a new document must stay unchanged before it is processed
+    #[arg(
+        long,
+        help = "Process a new or changed document only once its size and mtime have not changed for SECONDS (0 = at once)",
+        value_name = "SECONDS",
+        default_value_t = 2
+    )]
+    pub settle: u64,
+
+    /// Glob patterns of files to skip
+    #[arg(
+        long,
+        help = "Skip files matching GLOB (.gitignore syntax; repeatable), in addition to DIR/.ocrignore and hidden, .partial, .part, .tmp, and .crdownload files",
+        value_name = "GLOB"
+    )]
+    pub exclude: Vec<String>,
+
+    /// Processing order of the documents found in a scan
+    #[arg(
+        long,
+        help = "Process the documents found in a scan by size-asc, size-desc, mtime (oldest first), or name (default)",
+        value_name = "ORDER"
+    )]
+    pub order: Option<ProcessingOrder>,
+
+    /// Directory for the results
+    #[arg(
+        short,
+        long,
+        help = "Write each result to DIR/<document>.txt (.json with --json)",
+        value_name = "DIR"
+    )]
+    pub output_dir: Option<PathBuf>,
+
+    /// Scan once and exit
+    #[arg(
+        long,
+        help = "Process the documents present now and exit instead of watching"
+    )]
+    pub once: bool,
+
+    /// Directory for the document locks shared by concurrent workers
+    #[arg(
+        long,
+        help = "Directory for the locks that keep concurrent watchers from processing the same document (default: DIR/.ocr-locks)",
+        value_name = "DIR"
+    )]
+    pub lock_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: OcrOptions,
+}
+
+impl WatchArgs {
+    /// Validate the output and processing flags
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        if self.output_dir.is_none() && !self.options.sidecar {
+            errors.add(
+                "--output-dir",
+                "Results need a destination: --output-dir or --sidecar",
+            );
+        }
+        if self.interval == 0 {
+            errors.add("--interval", "Interval must be at least 1 second");
+        }
+        for pattern in &self.exclude {
+            if let Err(e) = crate::exclude::check_pattern(pattern) {
+                errors.add("--exclude", e);
+            }
+        }
+        self.options.check(&mut errors);
+        errors.into_arguments_result()
+    }
+}
+
+/// Subcommands of `cache`
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Show the number and size of cached results
+    Stats,
+
+    /// Remove expired results
+    Prune,
+
+    /// Remove all cached results
+    Clear,
+
+    /// Print the cache directory
+    Path,
+
+    /// Fill the cache from stored results, so their documents are not OCRed again
+    Warm(Box<CacheWarmArgs>),
+
+    /// Write the cache, and optionally a result store, to a .tar.zst archive
+    Export(CacheBackupArgs),
+
+    /// Unpack an archive written by `cache export`
+    Import(CacheBackupArgs),
+}
+
+/// Arguments for `cache export` and `cache import`
+#[derive(Args, Debug, Clone)]
+pub struct CacheBackupArgs {
+    /// Archive file
+    #[arg(help = "Archive file (zstd-compressed tar)", value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Result store to include or unpack into
+    #[arg(
+        long,
+        help = "Result store directory to back up along with the cache, or to restore its results into",
+        value_name = "DIR"
+    )]
+    pub results: Option<PathBuf>,
+}
+
+/// Arguments for `cache warm`
+#[derive(Args, Debug, Clone)]
+pub struct CacheWarmArgs {
+    /// Directory of stored results
+    #[arg(
+        help = "Directory of stored OCR results (saved --json output or sidecar files)",
+        value_name = "DIR"
+    )]
+    pub dir: PathBuf,
+
+    /// Directory of the original documents
+    #[arg(
+        long,
+        help = "Directory of the original documents, when they are not next to their results",
+        value_name = "DIR"
+    )]
+    pub documents: Option<PathBuf>,
+
+    /// Options of the runs that should find the results
+    #[command(flatten)]
+    pub options: OcrOptions,
+}
+
+/// Subcommands of `config`
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Print the effective configuration with secrets redacted
+    Show,
+
+    /// Print the path of the configuration file
+    Path,
+
+    /// Check the configuration and report every invalid setting
+    Validate,
+}
+
+/// Subcommands of `files`
+#[derive(Subcommand, Debug, Clone)]
+pub enum FilesCommand {
+    /// List uploaded files
+    List,
+
+    /// Show an uploaded file
+    Get {
+        /// File ID
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+
+    /// Delete an uploaded file
+    Delete {
+        /// File ID
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+}
+
+/// Arguments for `serve`
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(
+        long,
+        help = "Address to listen on",
+        value_name = "ADDRESS",
+        default_value = "127.0.0.1:8080"
+    )]
+    pub listen: SocketAddr,
+
+    /// Listen on addresses other than loopback without `[[server.tokens]]`
+    #[arg(
+        long,
+        help = "Listen on addresses other than loopback without [[server.tokens]]"
+    )]
+    pub allow_unauthenticated: bool,
+
+    #[command(flatten)]
+    pub options: OcrOptions,
+}
+
+/// Arguments for `completions`
+#[derive(Args, Debug, Clone)]
+pub struct CompletionsArgs {
+    /// Shell to complete for
+    #[arg(value_name = "SHELL")]
+    pub shell: CompletionShell,
+}
+
+/// Arguments for `explain`
+#[derive(Args, Debug, Clone)]
+pub struct ExplainArgs {
+    /// Exit code (`5`) or named error code (`E_RATE_LIMIT`) to describe
+    #[arg(value_name = "CODE")]
+    pub code: String,
+}
+
+/// Arguments for `suggest-tags`
+#[derive(Args, Debug, Clone)]
+pub struct SuggestTagsArgs {
+    /// File to OCR and match
+    #[arg(
+        short,
+        long,
+        help = "Path to the PDF or image file to process",
+        value_name = "FILE"
+    )]
+    pub file: String,
+
+    /// paperless-ngx base URL
+    #[arg(
+        long,
+        help = "paperless-ngx base URL (default: PAPERLESS_OCR_PAPERLESS_URL)",
+        value_name = "URL"
+    )]
+    pub paperless_url: Option<String>,
+
+    /// paperless-ngx API token
+    #[arg(
+        long,
+        help = "paperless-ngx API token (default: PAPERLESS_OCR_PAPERLESS_TOKEN)",
+        value_name = "TOKEN"
+    )]
+    pub paperless_token: Option<String>,
+
+    /// Minimum similarity for fuzzy matches
+    #[arg(
+        long,
+        help = "Minimum similarity between 0.0 and 1.0 for fuzzy tag matches",
+        value_name = "SCORE",
+        default_value_t = crate::tags::DEFAULT_MIN_SIMILARITY
+    )]
+    pub min_similarity: f64,
+}
+
+/// Arguments for `compare`
+#[derive(Args, Debug, Clone)]
+pub struct CompareArgs {
+    /// File to compare
     #[arg(
         short,
         long,
-        env = "PAPERLESS_OCR_API_KEY",
-        help = "Mistral AI API key (can also be set via environment variable)",
-        value_name = "KEY"
+        help = "Path to the PDF or image file to process",
+        value_name = "FILE"
+    )]
+    pub file: String,
+
+    /// Providers to compare
+    #[arg(
+        long,
+        help = "Comma-separated providers to compare, e.g. mistral,tesseract",
+        value_name = "LIST"
+    )]
+    pub providers: String,
+}
+
+/// Arguments for `eval`
+#[derive(Args, Debug, Clone)]
+pub struct EvalArgs {
+    /// Directory of reference transcripts
+    #[arg(
+        long,
+        help = "Directory of known-good transcripts named <document stem>.txt",
+        value_name = "DIR"
+    )]
+    pub reference: PathBuf,
+
+    /// Directory of documents (or stored results with --stored)
+    #[arg(
+        long,
+        help = "Directory of documents to OCR and evaluate",
+        value_name = "DIR"
+    )]
+    pub source: PathBuf,
+
+    /// Evaluate stored results instead of running OCR
+    #[arg(
+        long,
+        help = "Treat --source as stored OCR results (saved --json output) instead of documents"
+    )]
+    pub stored: bool,
+}
+
+/// Export destinations
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExportTarget {
+    /// Push stored OCR text to matching paperless-ngx documents
+    Paperless(ExportPaperlessArgs),
+}
+
+/// Arguments for `export paperless`
+#[derive(Args, Debug, Clone)]
+pub struct ExportPaperlessArgs {
+    /// Directory containing stored OCR results
+    #[arg(
+        long,
+        help = "Directory of stored OCR results (saved --json output or sidecar files)",
+        value_name = "DIR"
+    )]
+    pub source: PathBuf,
+
+    /// paperless-ngx base URL
+    #[arg(
+        long,
+        help = "paperless-ngx base URL (default: PAPERLESS_OCR_PAPERLESS_URL)",
+        value_name = "URL"
     )]
-    pub api_key: Option<String>,
+    pub paperless_url: Option<String>,
 
-    /// API base URL
+    /// paperless-ngx API token
     #[arg(
         long,
-        env = "PAPERLESS_OCR_API_BASE_URL",
-        help = "Mistral AI API base URL",
-        value_name = "URL",
-        default_value = "https://api.mistral.ai"
+        help = "paperless-ngx API token (default: PAPERLESS_OCR_PAPERLESS_TOKEN)",
+        value_name = "TOKEN"
     )]
-    pub api_base_url: Option<String>,
+    pub paperless_token: Option<String>,
 
-    /// Output format as JSON
+    /// Strategy used to match results to documents
     #[arg(
-        long,
-        help = "Output result in JSON format instead of human-readable text"
+        long = "match",
+        help = "How to match results to documents: auto, checksum, title",
+        value_name = "STRATEGY",
+        default_value = "auto"
     )]
-    pub json: bool,
+    pub match_strategy: MatchStrategy,
 
-    /// Verbose output
-    #[arg(short, long, help = "Enable verbose logging output")]
-    pub verbose: bool,
+    /// Report matches without updating documents
+    #[arg(long, help = "Show what would be exported without updating documents")]
+    pub dry_run: bool,
+}
 
-    /// Custom configuration file path
-    #[arg(long, help = "Path to custom configuration file", value_name = "PATH")]
-    pub config: Option<String>,
+impl OcrArgs {
+    /// Long names of the flags given on the command line
+    fn given_flags(&self) -> Vec<&'static str> {
+        let OcrArgs {
+            file,
+            diff,
+            diff_paperless,
+            options,
+        } = self;
+        let mut flags: Vec<&'static str> = [
+            ("--file", file.is_some()),
+            ("--diff", diff.is_some()),
+            ("--diff-paperless", *diff_paperless),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect();
+        flags.extend(options.given_flags());
+        flags
+    }
 
-    /// Generate shell completion scripts
-    #[arg(
-        long,
-        help = "Generate shell completion scripts for the specified shell",
-        value_name = "SHELL"
-    )]
-    pub completions: Option<String>,
+    /// Existing text to compare the result with, if any
+    pub fn diff_baseline(&self) -> Option<DiffBaseline> {
+        match self.diff {
+            Some(ref path) => Some(DiffBaseline::File(path.clone())),
+            None if self.diff_paperless => Some(DiffBaseline::Paperless),
+            None => None,
+        }
+    }
+
+    /// Validate the file and processing flags
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        // For OCR processing, file is required
+        match self.file.as_deref() {
+            None => errors.add("--file", "File path is required for OCR processing"),
+            Some("") => errors.add("--file", "File path cannot be empty"),
+            Some(_) => {}
+        }
+
+        self.options.check(&mut errors);
+        errors.into_arguments_result()
+    }
+}
+
+impl OcrOptions {
+    /// Long names of the flags given on the command line
+    fn given_flags(&self) -> Vec<&'static str> {
+        // Destructured so that a new flag cannot be left out here
+        let OcrOptions {
+            providers,
+            merge_strategy,
+            summarize,
+            translate_to,
+            extract_entities,
+            embed,
+            extract,
+            skip_blank_pages,
+            skip_text_pdfs,
+            remove_duplicate_pages,
+            strip_headers,
+            page_separator,
+            page_header,
+            sidecar,
+            compress_output,
+            encrypt_output,
+            on_success,
+            on_failure,
+            instructions,
+            signed_url,
+            response_format,
+            mime_type,
+            validation,
+            min_chars,
+            fail_on_empty,
+            min_quality,
+            on_low_quality,
+        } = self;
+        [
+            ("--providers", providers.is_some()),
+            ("--merge-strategy", merge_strategy.is_some()),
+            ("--summarize", *summarize),
+            ("--translate-to", translate_to.is_some()),
+            ("--extract-entities", *extract_entities),
+            ("--embed", *embed),
+            ("--extract", extract.is_some()),
+            ("--skip-blank-pages", *skip_blank_pages),
+            ("--skip-text-pdfs", *skip_text_pdfs),
+            ("--remove-duplicate-pages", *remove_duplicate_pages),
+            ("--strip-headers", *strip_headers),
+            ("--page-separator", page_separator.is_some()),
+            ("--page-header", page_header.is_some()),
+            ("--sidecar", *sidecar),
+            ("--compress-output", *compress_output),
+            ("--encrypt-output", !encrypt_output.is_empty()),
+            ("--on-success", on_success.is_some()),
+            ("--on-failure", on_failure.is_some()),
+            ("--instructions", instructions.is_some()),
+            ("--signed-url", *signed_url),
+            ("--response-format", response_format.is_some()),
+            ("--mime-type", mime_type.is_some()),
+            ("--validation", validation.is_some()),
+            ("--min-chars", min_chars.is_some()),
+            ("--fail-on-empty", *fail_on_empty),
+            ("--min-quality", min_quality.is_some()),
+            ("--on-low-quality", on_low_quality.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect()
+    }
+
+    /// Add the errors of the processing flags
+    fn check(&self, errors: &mut FieldErrors) {
+        // Validate translation target language if provided
+        if let Some(ref language) = self.translate_to {
+            let valid = !language.is_empty()
+                && language.len() <= 35
+                && language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                errors.add(
+                    "--translate-to",
+                    format!("Invalid translation language code: '{}'", language),
+                );
+            }
+        }
+
+        for (flag, hook) in [
+            ("--on-success", &self.on_success),
+            ("--on-failure", &self.on_failure),
+        ] {
+            if let Some(Err(e)) = hook.as_deref().map(crate::hooks::check_template) {
+                errors.add(flag, e);
+            }
+        }
+    }
+
+    /// Override the configuration with the processing flags
+    pub fn apply(&self, config: &mut Config) -> Result<()> {
+        if let Some(ref providers) = self.providers {
+            config.providers = ProviderKind::parse_list(providers)?;
+        }
+
+        if let Some(strategy) = self.merge_strategy {
+            config.merge_strategy = strategy;
+        }
+
+        if let Some(min_quality) = self.min_quality {
+            config.quality.min_score = Some(min_quality);
+        }
+
+        if let Some(action) = self.on_low_quality {
+            config.quality.on_low_quality = action;
+        }
+
+        if let Some(mode) = self.validation {
+            config.validation = mode;
+        }
+
+        if self.signed_url {
+            config.use_signed_url = true;
+        }
+
+        if !self.encrypt_output.is_empty() {
+            config.encryption.recipients = self.encrypt_output.clone();
+        }
+
+        if let Some(ref separator) = self.page_separator {
+            config.pages.separator = unescape(separator);
+        }
+
+        if let Some(ref header) = self.page_header {
+            config.pages.header = Some(header.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Processing steps for the pipeline, reporting API calls to `metrics`,
+    /// keeping OCR responses in `cache`, and sharing the state of `run`
+    pub fn processing_options(
+        &self,
+        metrics: &MetricsCollector,
+        cache: &CacheManager,
+        run: &RunContext,
+    ) -> commands::ProcessingOptions {
+        commands::ProcessingOptions {
+            summarize: self.summarize,
+            translate_to: self.translate_to.clone(),
+            extract_entities: self.extract_entities,
+            embed: self.embed,
+            extract: self.extract,
+            skip_blank_pages: self.skip_blank_pages,
+            skip_text_pdfs: self.skip_text_pdfs,
+            remove_duplicate_pages: self.remove_duplicate_pages,
+            strip_headers: self.strip_headers,
+            min_chars: self
+                .min_chars
+                .or(if self.fail_on_empty { Some(1) } else { None }),
+            sidecar: self.sidecar,
+            compress_output: self.compress_output,
+            mime_type: self.mime_type.clone(),
+            instructions: self.instructions.clone(),
+            response_format: self.response_format,
+            hooks: Hooks {
+                on_success: self.on_success.clone(),
+                on_failure: self.on_failure.clone(),
+            },
+            metrics: metrics.clone(),
+            cache: cache.clone(),
+            run: run.clone(),
+        }
+    }
 }
 
 impl Cli {
     /// Execute the CLI command
     pub async fn execute(&self) -> Result<()> {
         // Handle completion generation first
-        if let Some(ref shell) = self.completions {
+        if let Some(shell) = self.completions {
             return self.generate_completion_script(shell);
         }
+        if let Some(Commands::Completions(ref args)) = self.command {
+            return self.generate_completion_script(args.shell);
+        }
 
+        if self.explain_exit_codes {
+            return self.print_exit_codes();
+        }
+
+        // Explaining an exit code needs no configuration
+        if let Some(Commands::Explain(ref args)) = self.command {
+            return self.print_result(self.explain(&args.code));
+        }
+
+        // Management commands process no documents and print no run summary
+        match self.command {
+            Some(Commands::Config(ref command)) => return self.execute_config(command),
+            Some(Commands::Cache(ref command)) => return self.execute_cache(command),
+            Some(Commands::Files(ref command)) => return self.execute_files(command).await,
+            _ => {}
+        }
+
+        crate::progress::GLOBAL_PROGRESS.set_enabled(self.progress_json);
+        self.run.counters.start();
+        // Per-file records are only needed for the e-mail report
+        self.run.counters.keep_records(
+            self.load_config()
+                .is_ok_and(|config| config.notify.email.is_some()),
+        );
+        let _temp = self.run.temp.start(self.keep_temp);
+        // watch and serve stop gracefully on Ctrl-C and clean up on return
+        let _signals = crate::scratch::cleanup_on_signal(
+            self.run.temp.clone(),
+            !matches!(self.command, Some(Commands::Watch(_) | Commands::Serve(_))),
+        );
+
+        let result = match self.command {
+            Some(Commands::Ocr(ref args)) => self.execute_ocr(args).await,
+            Some(ref command) => self.execute_subcommand(command).await,
+            None => self.execute_ocr(&self.ocr).await,
+        };
+
+        // Stale cache entries served during the run are refreshed before exit
+        self.run.revalidations.wait().await;
+        self.print_summary().await;
+        self.print_metrics().await;
+        self.send_report().await;
+        result
+    }
+
+    /// Process a single file
+    async fn execute_ocr(&self, args: &OcrArgs) -> Result<()> {
         // Validate CLI arguments first
-        self.validate()?;
+        self.validate().map_err(|e| self.setup_error(e))?;
 
         // Initialize logging
-        crate::init_logging(self.verbose)?;
+        self.init_logging()?;
 
         tracing::debug!(
             "CLI arguments parsed: file={:?}, json={}, verbose={}",
-            self.file,
+            args.file,
             self.json,
             self.verbose
         );
 
         // Load configuration - use custom path if provided, otherwise use default search
-        let mut config = if let Some(ref config_path) = self.config {
-            Config::load_from_path(config_path)?
-        } else {
-            Config::load_without_validation()?
-        };
+        let mut config = self.load_config().map_err(|e| self.setup_error(e))?;
 
         // Override config with CLI arguments
+        self.apply_api_overrides(&mut config);
+        args.options
+            .apply(&mut config)
+            .map_err(|e| self.setup_error(e))?;
+        self.apply_network_overrides(&mut config);
+        self.apply_budget_overrides(&mut config);
+        let _fixtures = self
+            .start_fixture_server(&mut config)
+            .map_err(|e| self.setup_error(e))?;
+
+        // Validate final configuration after all overrides
+        config.validate().map_err(|e| self.setup_error(e))?;
+
+        tracing::debug!("Configuration loaded and validated");
+
+        // Check if file is provided
+        let file = args.file.as_ref().ok_or_else(|| {
+            Error::validation("File path is required for OCR processing".to_string())
+        })?;
+
+        let options = args
+            .options
+            .processing_options(&self.metrics, &self.cache, &self.run);
+
+        if options.compress_output && std::io::stdout().is_terminal() {
+            return Err(Error::validation(
+                "--compress-output writes binary data; redirect stdout to a file (e.g. > scan.txt.gz)"
+                    .to_string(),
+            ));
+        }
+
+        if let Some(baseline) = args.diff_baseline() {
+            return self.print_result(
+                commands::diff_command(file, &baseline, &config, &options, self.json, self.verbose)
+                    .await,
+            );
+        }
+
+        // Process the file using commands module; output goes to stdout (constitutional requirement)
+        self.print_output(
+            commands::process_ocr_command(
+                file,
+                &config,
+                &options,
+                self.output_format(),
+                &self.color.stdout(),
+                self.verbose,
+            )
+            .await,
+        )
+    }
+
+    /// Execute a subcommand
+    async fn execute_subcommand(&self, command: &Commands) -> Result<()> {
+        self.validate().map_err(|e| self.setup_error(e))?;
+        self.init_logging()?;
+
+        let mut config = self.load_config().map_err(|e| self.setup_error(e))?;
+        self.apply_api_overrides(&mut config);
+        if let Commands::Batch(BatchArgs { options, .. })
+        | Commands::Watch(WatchArgs { options, .. })
+        | Commands::Serve(ServeArgs { options, .. }) = command
+        {
+            options
+                .apply(&mut config)
+                .map_err(|e| self.setup_error(e))?;
+        }
+        self.apply_network_overrides(&mut config);
+        self.apply_budget_overrides(&mut config);
+        let _fixtures = self
+            .start_fixture_server(&mut config)
+            .map_err(|e| self.setup_error(e))?;
+
+        // Dump metrics and in-flight files on SIGUSR1 during long runs
+        let _diagnostics =
+            crate::diagnostics::listen_for_dump_signal(self.metrics.clone(), self.cache.clone());
+
+        let result = match command {
+            Commands::Batch(args) => {
+                config.validate().map_err(|e| self.setup_error(e))?;
+                let options =
+                    args.options
+                        .processing_options(&self.metrics, &self.cache, &self.run);
+                return match commands::batch_command(
+                    args,
+                    &config,
+                    &options,
+                    &self.cache,
+                    self.output_format(),
+                    &self.color.stdout(),
+                    self.verbose,
+                )
+                .await
+                {
+                    // The report lists every failure; the first decides the exit code
+                    Ok((report, failure)) => {
+                        println!("{}", report);
+                        failure.map_or(Ok(()), Err)
+                    }
+                    Err(e) => self.print_result(Err(e)),
+                };
+            }
+            Commands::Watch(args) => {
+                config.validate().map_err(|e| self.setup_error(e))?;
+                let options =
+                    args.options
+                        .processing_options(&self.metrics, &self.cache, &self.run);
+                commands::watch_command(
+                    args,
+                    &config,
+                    &options,
+                    self.output_format(),
+                    &self.color.stdout(),
+                    self.verbose,
+                )
+                .await
+            }
+            Commands::Serve(args) => {
+                config.validate().map_err(|e| self.setup_error(e))?;
+                return self.serve(args, config).await;
+            }
+            Commands::Export(ExportTarget::Paperless(args)) => {
+                if let Some(ref url) = args.paperless_url {
+                    config.paperless.url = Some(url.clone());
+                }
+                if let Some(ref token) = args.paperless_token {
+                    config.paperless.token = Some(token.clone());
+                }
+                commands::export_paperless_command(
+                    args,
+                    &config,
+                    &self.metrics,
+                    &self.cache,
+                    &self.run,
+                    self.json,
+                )
+                .await
+            }
+            Commands::Compare(args) => {
+                commands::compare_command(args, &config, &self.metrics, &self.run, self.json).await
+            }
+            Commands::SuggestTags(args) => {
+                if let Some(ref url) = args.paperless_url {
+                    config.paperless.url = Some(url.clone());
+                }
+                if let Some(ref token) = args.paperless_token {
+                    config.paperless.token = Some(token.clone());
+                }
+                commands::suggest_tags_command(
+                    args,
+                    &config,
+                    &self.metrics,
+                    &self.run,
+                    self.json,
+                    self.verbose,
+                )
+                .await
+            }
+            Commands::Eval(args) => {
+                commands::eval_command(
+                    args,
+                    &config,
+                    &self.metrics,
+                    &self.cache,
+                    &self.run,
+                    self.json,
+                    self.verbose,
+                )
+                .await
+            }
+            Commands::Explain(args) => self.explain(&args.code),
+            Commands::Ocr(_)
+            | Commands::Cache(_)
+            | Commands::Config(_)
+            | Commands::Files(_)
+            | Commands::Completions(_) => {
+                unreachable!("handled before running a subcommand")
+            }
+        };
+
+        self.print_result(result)
+    }
+
+    /// Serve OCR requests until interrupted
+    async fn serve(&self, args: &ServeArgs, mut config: Config) -> Result<()> {
+        let options = args
+            .options
+            .processing_options(&self.metrics, &self.cache, &self.run);
+        config.server.allow_unauthenticated |= args.allow_unauthenticated;
+        let result = async {
+            let server =
+                crate::server::OcrServer::bind(args.listen, config, options, self.verbose)?;
+            tracing::info!("Listening on http://{}", server.local_addr()?);
+            server
+                .run(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => self.print_result(Err(e)),
+        }
+    }
+
+    /// Show, locate, or check the configuration
+    fn execute_config(&self, command: &ConfigCommand) -> Result<()> {
+        self.validate().map_err(|e| self.setup_error(e))?;
+
+        let config_path = self
+            .config
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(Config::get_config_path);
+        let load_config = || {
+            if let Some(ref env_file) = self.env_file {
+                Config::load_env_file(env_file)?;
+            }
+            let mut config = Config::load_unchecked(&config_path)?;
+            self.apply_api_overrides(&mut config);
+            self.apply_network_overrides(&mut config);
+            self.apply_budget_overrides(&mut config);
+            Ok(config)
+        };
+
+        self.print_result(commands::config_command(
+            command,
+            &config_path,
+            load_config,
+            self.output_format().is_json(),
+        ))
+    }
+
+    /// Inspect or clean the persistent result cache
+    fn execute_cache(&self, command: &CacheCommand) -> Result<()> {
+        self.init_logging()?;
+
+        let result = self.load_config().and_then(|mut config| {
+            // Entries are keyed by the options of the runs that will read them
+            if let CacheCommand::Warm(ref args) = *command {
+                args.options.apply(&mut config)?;
+            }
+            commands::cache_command(command, &config, self.output_format().is_json())
+        });
+        self.print_result(result)
+    }
+
+    /// Manage files uploaded to the Mistral Files API
+    async fn execute_files(&self, command: &FilesCommand) -> Result<()> {
+        self.validate().map_err(|e| self.setup_error(e))?;
+        self.init_logging()?;
+
+        let mut config = self.load_config().map_err(|e| self.setup_error(e))?;
+        self.apply_api_overrides(&mut config);
+        self.apply_network_overrides(&mut config);
+        let _fixtures = self
+            .start_fixture_server(&mut config)
+            .map_err(|e| self.setup_error(e))?;
+
+        self.print_result(
+            commands::files_command(
+                command,
+                &config,
+                &self.metrics,
+                self.output_format().is_json(),
+            )
+            .await,
+        )
+    }
+
+    /// Apply the API key and base URL flags to the configuration
+    fn apply_api_overrides(&self, config: &mut Config) {
         if let Some(ref api_key) = self.api_key {
             config.api_key = api_key.clone();
         }
@@ -146,229 +1802,355 @@ impl Cli {
         if let Some(ref api_base_url) = self.api_base_url {
             config.api_base_url = api_base_url.clone();
         }
+    }
 
-        // Validate final configuration after all overrides
-        config.validate()?;
+    /// Apply name resolution flags to the configuration
+    fn apply_network_overrides(&self, config: &mut Config) {
+        if !self.resolve.is_empty() {
+            config.network.resolve = self.resolve.clone();
+        }
 
-        tracing::debug!("Configuration loaded and validated");
+        if let Some(preference) = self.ip_preference {
+            config.network.ip_preference = preference;
+        }
+    }
 
-        // Check if file is provided
-        let file = self.file.as_ref().ok_or_else(|| {
-            Error::Validation("File path is required for OCR processing".to_string())
-        })?;
+    /// Start the local fixture server for `--record` or `--replay` and send
+    /// API requests to it; the server runs until the returned guard is dropped
+    fn start_fixture_server(&self, config: &mut Config) -> Result<Option<FixtureServer>> {
+        let server = if let Some(ref dir) = self.record {
+            FixtureServer::record(dir, &config.api_base_url, &config.api_key, &config.network)?
+        } else if let Some(ref dir) = self.replay {
+            // Replays need no real key, but the client requires one
+            if config.api_key.is_empty() {
+                config.api_key = "replay".to_string();
+            }
+            FixtureServer::replay(dir)?
+        } else {
+            return Ok(None);
+        };
+
+        tracing::debug!(
+            "API requests go through the fixture server at {}",
+            server.url()
+        );
+        config.api_base_url = server.url().to_string();
+        // The fixture server is ours and only listens on loopback
+        config.allow_insecure_loopback = true;
+        Ok(Some(server))
+    }
+
+    /// Apply budget and quota flags to the configuration
+    fn apply_budget_overrides(&self, config: &mut Config) {
+        if let Some(max_pages) = self.max_pages {
+            config.budget.max_pages = Some(max_pages);
+        }
+
+        if let Some(max_cost) = self.max_cost {
+            config.budget.max_cost = Some(max_cost);
+        }
+
+        if self.enforce_quota {
+            config.quota.enforce = true;
+        }
+    }
+
+    /// Load configuration from the custom path or the default search locations
+    /// Log to stderr, or to the rotating `--log-file`
+    fn init_logging(&self) -> Result<()> {
+        let log_file = match self.log_file {
+            Some(ref path) => Some(RotatingFile::open(
+                path.clone(),
+                RotationPolicy {
+                    max_bytes: (self.log_max_size > 0)
+                        .then(|| self.log_max_size.saturating_mul(1024 * 1024)),
+                    rotation: self.log_rotation,
+                    keep: self.log_keep,
+                },
+            )?),
+            None => None,
+        };
+        crate::init_logging_to(self.verbose, log_file)
+    }
+
+    fn load_config(&self) -> Result<Config> {
+        if let Some(ref env_file) = self.env_file {
+            Config::load_env_file(env_file)?;
+        }
+        if let Some(ref config_path) = self.config {
+            Config::load_from_path(config_path)
+        } else {
+            Config::load_without_validation()
+        }
+    }
+
+    /// Output format from `--format`, or `--json`
+    fn output_format(&self) -> OutputFormat {
+        match self.format {
+            Some(format) => format,
+            None if self.json => OutputFormat::Json,
+            None => OutputFormat::Text,
+        }
+    }
+
+    /// Print the run summary to stderr, or as a progress event
+    async fn print_summary(&self) {
+        if self.quiet {
+            return;
+        }
+
+        let summary = self.run.counters.summary(&self.metrics, &self.cache).await;
+        if crate::progress::GLOBAL_PROGRESS.is_enabled() {
+            crate::progress::GLOBAL_PROGRESS.emit(ProgressEvent::RunCompleted(&summary));
+        } else {
+            eprintln!("{}", summary.render(&self.color.stderr()));
+        }
+    }
+
+    /// Print the API metrics requested with `--show-metrics` to stderr
+    async fn print_metrics(&self) {
+        if let Some(format) = self.show_metrics {
+            eprint!("{}", self.metrics.render(format).await);
+        }
+    }
 
-        // Process the file using commands module
-        match commands::process_ocr_command(file, &config, self.json, self.verbose).await {
+    /// Mail the run report when `[notify.email]` is configured
+    async fn send_report(&self) {
+        let Ok(config) = self.load_config() else {
+            return;
+        };
+        let Some(ref email) = config.notify.email else {
+            return;
+        };
+
+        let summary = self.run.counters.summary(&self.metrics, &self.cache).await;
+        if !email.should_notify(&summary) {
+            return;
+        }
+        match crate::notify::send_report(email, &summary, &self.run.counters.take_records()).await {
+            Ok(()) => tracing::info!("Run report sent to {}", email.to.join(", ")),
+            Err(e) => tracing::warn!("Failed to send the run report: {}", e.user_message()),
+        }
+    }
+
+    /// Write raw output bytes to stdout, or print the error like `print_result`
+    fn print_output(&self, result: Result<Vec<u8>>) -> Result<()> {
+        match result {
+            Ok(output) => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&output).map_err(Error::Io)?;
+                stdout.flush().map_err(Error::Io)
+            }
+            Err(e) => self.print_result(Err(e)),
+        }
+    }
+
+    /// Print command output, or the error in the selected output format
+    fn print_result(&self, result: Result<String>) -> Result<()> {
+        match result {
             Ok(output) => {
-                // Output result to stdout (constitutional requirement)
                 println!("{}", output);
                 Ok(())
             }
             Err(e) => {
-                // Handle error output format
-                if self.json {
-                    // JSON error output
-                    let json_error = CLIOutput {
-                        success: false,
-                        data: None,
-                        error: Some(CLIErrorData {
-                            error_type: e.error_type().to_string(),
-                            message: e.user_message(),
-                            details: Some(e.to_string()),
-                        }),
-                    };
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json_error)
-                            .unwrap_or_else(|_| "{}".to_string())
-                    );
+                if self.output_format().is_json() {
+                    self.print_json_error(&e);
                 } else {
-                    // Human-readable error output goes to stderr
-                    eprintln!("Error: {}", e.user_message());
+                    let message = if self.verbose {
+                        e.verbose_message()
+                    } else {
+                        e.user_message()
+                    };
+                    let label = format!("Error [{}]:", e.code());
+                    eprintln!("{} {}", self.color.stderr().failure(&label), message);
                 }
-
-                // Return the original error
                 Err(e)
             }
         }
     }
 
+    /// Print the exit code reference
+    fn print_exit_codes(&self) -> Result<()> {
+        if !self.output_format().is_json() {
+            print!("{}", crate::error::exit_code_reference());
+        } else {
+            let env_prefix = self.env_prefix();
+            let codes: Vec<serde_json::Value> = crate::error::EXIT_CODES
+                .iter()
+                .map(|info| info.json_description(&env_prefix))
+                .collect();
+            let json = serde_json::to_string_pretty(&codes)
+                .map_err(|e| Error::internal_from("Failed to serialize exit codes", e))?;
+            println!("{}", json);
+        }
+        Ok(())
+    }
+
+    /// Description of an exit code or a named error code for the `explain`
+    /// subcommand
+    fn explain(&self, code: &str) -> Result<String> {
+        let Ok(exit_code) = code.trim().parse::<i32>() else {
+            return self.explain_error_code(code.trim());
+        };
+        let info = crate::error::exit_code_info(exit_code).ok_or_else(|| {
+            let known: Vec<String> = crate::error::EXIT_CODES
+                .iter()
+                .map(|info| info.code.to_string())
+                .collect();
+            Error::validation(format!(
+                "Unknown exit code {}. Known codes: {}",
+                exit_code,
+                known.join(", ")
+            ))
+        })?;
+
+        if !self.output_format().is_json() {
+            Ok(info.explanation(&self.env_prefix()).trim_end().to_string())
+        } else {
+            serde_json::to_string_pretty(&info.json_description(&self.env_prefix()))
+                .map_err(|e| Error::internal_from("Failed to serialize exit code", e))
+        }
+    }
+
+    /// Prefix of the environment variables from the configuration file, or
+    /// the default when it cannot be read; the settings need not be valid
+    fn env_prefix(&self) -> String {
+        let config = match self.config {
+            Some(ref config_path) => Config::load_unchecked(Path::new(config_path)),
+            None => Config::load_without_validation(),
+        };
+        config
+            .map(|config| config.env_prefix)
+            .unwrap_or_else(|_| crate::config::DEFAULT_ENV_PREFIX.to_string())
+    }
+
+    /// Description of a named error code, followed by its exit code
+    fn explain_error_code(&self, name: &str) -> Result<String> {
+        let info = crate::error::error_code_info(name).ok_or_else(|| {
+            let known: Vec<&str> = crate::error::ERROR_CODES
+                .iter()
+                .map(|info| info.name)
+                .collect();
+            Error::validation(format!(
+                "Unknown error code {}. Known codes: {}",
+                name,
+                known.join(", ")
+            ))
+        })?;
+        let exit = crate::error::exit_code_info(info.exit_code);
+
+        if !self.output_format().is_json() {
+            let mut text = format!("Error code {}: {}\n", info.name, info.description);
+            if let Some(exit) = exit {
+                text.push('\n');
+                text.push_str(&exit.explanation(&self.env_prefix()));
+            }
+            Ok(text.trim_end().to_string())
+        } else {
+            let json = serde_json::json!({
+                "name": info.name,
+                "description": info.description,
+                "exit_code": exit.map(|exit| exit.json_description(&self.env_prefix())),
+            });
+            serde_json::to_string_pretty(&json)
+                .map_err(|e| Error::internal_from("Failed to serialize error code", e))
+        }
+    }
+
+    /// Print an error as a JSON `CLIOutput`
+    fn print_json_error(&self, e: &Error) {
+        let json_error = CLIOutput::from_error(e);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json_error).unwrap_or_else(|_| "{}".to_string())
+        );
+    }
+
+    /// Report an argument or configuration error in JSON mode before it is
+    /// returned; in text mode `main` prints it
+    fn setup_error(&self, e: Error) -> Error {
+        if self.output_format().is_json() {
+            self.print_json_error(&e);
+        }
+        e
+    }
+
     /// Validate CLI arguments
     pub fn validate(&self) -> Result<()> {
-        // If generating completions, file is not required
-        if self.completions.is_some() {
+        // If generating completions or explaining exit codes, file is not required
+        if self.completions.is_some() || self.explain_exit_codes {
             return Ok(());
         }
 
-        // For OCR processing, file is required
-        if self.file.is_none() {
-            return Err(Error::Validation(
-                "File path is required for OCR processing".to_string(),
-            ));
+        // The flat invocation's flags would be ignored by a subcommand
+        if self.command.is_some() {
+            let mut errors = FieldErrors::new();
+            for flag in self.ocr.given_flags() {
+                if flag == "--file" {
+                    errors.add(
+                        flag,
+                        "--file before a subcommand is not used; use `ocr --file` or `batch <PATH>`",
+                    );
+                } else {
+                    errors.add(
+                        flag,
+                        format!(
+                            "{} before a subcommand is not used; put it after the subcommand",
+                            flag
+                        ),
+                    );
+                }
+            }
+            errors.into_arguments_result()?;
         }
 
-        // Validate file argument if provided
-        if let Some(ref file) = self.file {
-            if file.is_empty() {
-                return Err(Error::Validation("File path cannot be empty".to_string()));
+        match self.command {
+            None => self.ocr.validate()?,
+            Some(Commands::Ocr(ref args)) => args.validate()?,
+            Some(Commands::Batch(ref args)) => args.validate()?,
+            Some(Commands::Watch(ref args)) => args.validate()?,
+            Some(Commands::Serve(ref args)) => {
+                let mut errors = FieldErrors::new();
+                args.options.check(&mut errors);
+                errors.into_arguments_result()?;
             }
+            Some(_) => {}
         }
 
+        // Settings given as arguments are configuration errors (exit code 4)
+        let mut settings = FieldErrors::new();
+
         // Validate API key if provided
-        if let Some(ref api_key) = self.api_key {
-            if api_key.is_empty() {
-                return Err(Error::Config("API key cannot be empty".to_string()));
-            }
+        if self.api_key.as_deref() == Some("") {
+            settings.add("--api-key", "API key cannot be empty");
         }
 
         // Validate API base URL if provided
-        if let Some(ref api_base_url) = self.api_base_url {
-            if api_base_url.is_empty() {
-                return Err(Error::Config("API base URL cannot be empty".to_string()));
-            }
+        if self.api_base_url.as_deref() == Some("") {
+            settings.add("--api-base-url", "API base URL cannot be empty");
         }
 
-        Ok(())
+        settings.into_config_result()
     }
 
-    /// Generate shell completion script
-    fn generate_completion_script(&self, shell: &str) -> Result<()> {
+    /// Generate a shell completion script from the command definition, so
+    /// every subcommand and flag is completed
+    fn generate_completion_script(&self, shell: CompletionShell) -> Result<()> {
         let bin_name = "paperless-ngx-ocr2";
+        let mut command = Cli::command();
+        let mut script = Vec::new();
 
-        match shell.to_lowercase().as_str() {
-            "bash" => self.generate_bash_completion(bin_name),
-            "zsh" => self.generate_zsh_completion(bin_name),
-            "fish" => self.generate_fish_completion(bin_name),
-            "powershell" | "ps1" => self.generate_powershell_completion(bin_name),
-            _ => Err(Error::Config(format!(
-                "Unsupported shell: {}. Supported shells: bash, zsh, fish, powershell",
-                shell
-            ))),
+        match shell {
+            CompletionShell::Bash => generate(Shell::Bash, &mut command, bin_name, &mut script),
+            CompletionShell::Zsh => generate(Shell::Zsh, &mut command, bin_name, &mut script),
+            CompletionShell::Fish => generate(Shell::Fish, &mut command, bin_name, &mut script),
+            CompletionShell::Powershell => {
+                generate(Shell::PowerShell, &mut command, bin_name, &mut script)
+            }
+            CompletionShell::Elvish => generate(Shell::Elvish, &mut command, bin_name, &mut script),
+            CompletionShell::Nushell => generate(Nushell, &mut command, bin_name, &mut script),
         }
-    }
-
-    /// Generate bash completion script
-    fn generate_bash_completion(&self, bin_name: &str) -> Result<()> {
-        println!(
-            r#"# Bash completion for {}
-complete -F _paperless_ngx_ocr2_completion {} 
-
-_paperless_ngx_ocr2_completion() {{
-    local cur prev opts
-    COMPREPLY=()
-    cur="${{COMP_WORDS[COMP_CWORD]}}"
-    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
-    
-    opts="-f --file -a --api-key --api-base-url --config --json -v --verbose -h --help -V --version --completions"
-    
-    case "${{prev}}" in
-        -f|--file)
-            COMPREPLY=( $(compgen -f -- "$cur") )
-            return 0
-            ;;
-        -a|--api-key)
-            return 0
-            ;;
-        --api-base-url)
-            return 0
-            ;;
-        --config)
-            COMPREPLY=( $(compgen -f -- "$cur") )
-            return 0
-            ;;
-        --completions)
-            COMPREPLY=( $(compgen -W "bash zsh fish powershell" -- "$cur") )
-            return 0
-            ;;
-    esac
-    
-    if [[ $cur == -* ]]; then
-        COMPREPLY=( $(compgen -W "$opts" -- "$cur") )
-        return 0
-    fi
-}}"#,
-            bin_name, bin_name
-        );
-        Ok(())
-    }
-
-    /// Generate zsh completion script
-    fn generate_zsh_completion(&self, bin_name: &str) -> Result<()> {
-        println!(
-            r#"#compdef {}
-local -a opts
-opts=(
-    '(-f --file)'{{-f,--file}}'[Path to the PDF or image file to process]:file:_files'
-    '(-a --api-key)'{{-a,--api-key}}'[Mistral AI API key]:key:'
-    '--api-base-url[Mistral AI API base URL]:url:'
-    '--config[Path to custom configuration file]:file:_files'
-    '--json[Output result in JSON format instead of human-readable text]'
-    '(-v --verbose)'{{-v,--verbose}}'[Enable verbose logging output]'
-    '--completions[Generate shell completion scripts for the specified shell]:shell:(bash zsh fish powershell)'
-    '(-h --help)'{{-h,--help}}'[Print help]'
-    '(-V --version)'{{-V,--version}}'[Print version]'
-)
-_arguments $opts"#,
-            bin_name
-        );
-        Ok(())
-    }
 
-    /// Generate fish completion script
-    fn generate_fish_completion(&self, bin_name: &str) -> Result<()> {
-        println!(
-            r#"# Fish completion for {}
-complete -c {} -s f -l file -d "Path to the PDF or image file to process" -r
-complete -c {} -s a -l api-key -d "Mistral AI API key" -r
-complete -c {} -l api-base-url -d "Mistral AI API base URL" -r
-complete -c {} -l config -d "Path to custom configuration file" -r -F
-complete -c {} -l json -d "Output result in JSON format instead of human-readable text"
-complete -c {} -s v -l verbose -d "Enable verbose logging output"
-complete -c {} -l completions -d "Generate shell completion scripts for the specified shell" -x -a "bash zsh fish powershell"
-complete -c {} -s h -l help -d "Print help"
-complete -c {} -s V -l version -d "Print version""#,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name,
-            bin_name
-        );
-        Ok(())
-    }
-
-    /// Generate PowerShell completion script
-    fn generate_powershell_completion(&self, bin_name: &str) -> Result<()> {
-        println!(
-            r#"# PowerShell completion for {}
-Register-ArgumentCompleter -CommandName {} -ScriptBlock {{
-    param($commandName, $parameterName, $wordToComplete, $commandAst, $fakeBoundParameters)
-    
-    $completions = @()
-    
-    switch ($parameterName) {{
-        'File' {{
-            $completions = Get-ChildItem -Path . -Name | Where-Object {{ $_ -like "$wordToComplete*" }}
-        }}
-        'Config' {{
-            $completions = Get-ChildItem -Path . -Name -Include "*.toml" | Where-Object {{ $_ -like "$wordToComplete*" }}
-        }}
-        'Completions' {{
-            $completions = @('bash', 'zsh', 'fish', 'powershell') | Where-Object {{ $_ -like "$wordToComplete*" }}
-        }}
-        default {{
-            $completions = @('-f', '--file', '-a', '--api-key', '--api-base-url', '--config', '--json', '-v', '--verbose', '--completions', '-h', '--help', '-V', '--version') | Where-Object {{ $_ -like "$wordToComplete*" }}
-        }}
-    }}
-    
-    return $completions
-}}"#,
-            bin_name, bin_name
-        );
-        Ok(())
+        std::io::stdout().write_all(&script).map_err(Error::Io)
     }
 }
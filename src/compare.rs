@@ -0,0 +1,335 @@
+//! Side-by-side comparison of OCR providers
+//!
+//! Runs the same document through several providers and reports per-provider
+//! metrics (time, pages, text size, quality) plus pairwise text similarity and
+//! a unified diff against the first successful provider. `--diff` compares a
+//! new OCR result with the existing text of the document instead.
+
+use crate::quality::QualityScore;
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::path::PathBuf;
+
+/// Result of running one provider
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderRun {
+    /// Provider name
+    pub provider: String,
+
+    /// Wall-clock time of the provider run
+    pub duration_ms: u64,
+
+    /// Number of pages returned
+    pub pages: usize,
+
+    /// Number of characters extracted
+    pub chars: usize,
+
+    /// Number of words extracted
+    pub words: usize,
+
+    /// Quality heuristic of the extracted text
+    pub quality: Option<QualityScore>,
+
+    /// Error message when the provider failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Extracted text (not serialized; used for comparisons)
+    #[serde(skip)]
+    pub text: String,
+}
+
+impl ProviderRun {
+    /// Build a successful run from the extracted text
+    pub fn success(provider: &str, duration_ms: u64, pages: usize, text: String) -> Self {
+        Self {
+            provider: provider.to_string(),
+            duration_ms,
+            pages,
+            chars: text.chars().count(),
+            words: text.split_whitespace().count(),
+            quality: QualityScore::from_text(&text),
+            error: None,
+            text,
+        }
+    }
+
+    /// Build a failed run
+    pub fn failure(provider: &str, duration_ms: u64, error: String) -> Self {
+        Self {
+            provider: provider.to_string(),
+            duration_ms,
+            pages: 0,
+            chars: 0,
+            words: 0,
+            quality: None,
+            error: Some(error),
+            text: String::new(),
+        }
+    }
+}
+
+/// Comparison of two provider outputs
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// Baseline provider
+    pub baseline: String,
+
+    /// Compared provider
+    pub candidate: String,
+
+    /// Word-level similarity from 0.0 (different) to 1.0 (identical)
+    pub similarity: f64,
+
+    /// Unified line diff from baseline to candidate
+    pub diff: String,
+}
+
+/// Comparison report for a single document
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    /// Compared document
+    pub file_name: String,
+
+    /// Per-provider results in the requested order
+    pub providers: Vec<ProviderRun>,
+
+    /// Comparisons of each successful provider against the first successful one
+    pub comparisons: Vec<Comparison>,
+}
+
+impl ComparisonReport {
+    /// Build the report, comparing every successful run with the first one
+    pub fn new(file_name: &str, runs: Vec<ProviderRun>) -> Self {
+        let successful: Vec<&ProviderRun> = runs.iter().filter(|r| r.error.is_none()).collect();
+
+        let comparisons = match successful.split_first() {
+            Some((baseline, candidates)) => candidates
+                .iter()
+                .map(|candidate| Comparison {
+                    baseline: baseline.provider.clone(),
+                    candidate: candidate.provider.clone(),
+                    similarity: similarity(&baseline.text, &candidate.text),
+                    diff: unified_diff(
+                        &baseline.text,
+                        &candidate.text,
+                        &baseline.provider,
+                        &candidate.provider,
+                    ),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Self {
+            file_name: file_name.to_string(),
+            providers: runs,
+            comparisons,
+        }
+    }
+
+    /// Format the report as a metrics table followed by the diffs
+    pub fn to_human_readable(&self) -> String {
+        let mut lines = vec![
+            format!("Comparison for {}", self.file_name),
+            String::new(),
+            format!(
+                "{:<10}  {:>9}  {:>5}  {:>8}  {:>7}  {:>7}",
+                "Provider", "Time (ms)", "Pages", "Chars", "Words", "Quality"
+            ),
+        ];
+
+        for run in &self.providers {
+            match &run.error {
+                Some(error) => lines.push(format!("{:<10}  failed: {}", run.provider, error)),
+                None => lines.push(format!(
+                    "{:<10}  {:>9}  {:>5}  {:>8}  {:>7}  {:>7}",
+                    run.provider,
+                    run.duration_ms,
+                    run.pages,
+                    run.chars,
+                    run.words,
+                    run.quality
+                        .map_or("-".to_string(), |q| format!("{:.2}", q.score))
+                )),
+            }
+        }
+
+        for comparison in &self.comparisons {
+            lines.push(String::new());
+            lines.push(format!(
+                "{} vs {}: {:.1}% similar",
+                comparison.baseline,
+                comparison.candidate,
+                comparison.similarity * 100.0
+            ));
+            if !comparison.diff.is_empty() {
+                lines.push(comparison.diff.trim_end().to_string());
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Existing text a new OCR result is compared with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffBaseline {
+    /// A text file
+    File(PathBuf),
+
+    /// The content of the matching paperless-ngx document
+    Paperless,
+}
+
+/// Comparison of a new OCR result with the existing text of a document
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    /// Processed document
+    pub file_name: String,
+
+    /// Where the existing text came from
+    pub baseline: String,
+
+    /// Word-level similarity from 0.0 (different) to 1.0 (identical)
+    pub similarity: f64,
+
+    /// Lines only in the new text
+    pub lines_added: usize,
+
+    /// Lines only in the existing text
+    pub lines_removed: usize,
+
+    /// Quality heuristic of the existing text
+    pub existing_quality: Option<QualityScore>,
+
+    /// Quality heuristic of the new text
+    pub new_quality: Option<QualityScore>,
+
+    /// Unified line diff from the existing to the new text
+    pub diff: String,
+}
+
+impl DiffReport {
+    /// Compare the `existing` text from `baseline` with the `new` OCR text
+    pub fn new(file_name: &str, baseline: &str, existing: &str, new: &str) -> Self {
+        let lines = TextDiff::from_lines(existing, new);
+        let count = |tag| lines.iter_all_changes().filter(|c| c.tag() == tag).count();
+        Self {
+            file_name: file_name.to_string(),
+            baseline: baseline.to_string(),
+            similarity: similarity(existing, new),
+            lines_added: count(ChangeTag::Insert),
+            lines_removed: count(ChangeTag::Delete),
+            existing_quality: QualityScore::from_text(existing),
+            new_quality: QualityScore::from_text(new),
+            diff: unified_diff(existing, new, baseline, &format!("{} (OCR)", file_name)),
+        }
+    }
+
+    /// Format the report as a summary followed by the diff
+    pub fn to_human_readable(&self) -> String {
+        let quality = |score: Option<QualityScore>| {
+            score.map_or("-".to_string(), |q| format!("{:.2}", q.score))
+        };
+        let mut lines = vec![
+            format!(
+                "{} vs {}: {:.1}% similar, +{} -{} lines",
+                self.file_name,
+                self.baseline,
+                self.similarity * 100.0,
+                self.lines_added,
+                self.lines_removed
+            ),
+            format!(
+                "Quality: {} existing, {} new",
+                quality(self.existing_quality),
+                quality(self.new_quality)
+            ),
+        ];
+        if !self.diff.is_empty() {
+            lines.push(String::new());
+            lines.push(self.diff.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Word-level similarity ratio of two texts
+pub fn similarity(a: &str, b: &str) -> f64 {
+    f64::from(TextDiff::from_words(a, b).ratio())
+}
+
+/// Unified line diff between two texts; empty when they are identical
+pub fn unified_diff(a: &str, b: &str, label_a: &str, label_b: &str) -> String {
+    TextDiff::from_lines(a, b)
+        .unified_diff()
+        .context_radius(2)
+        .header(label_a, label_b)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_outputs() {
+        let report = ComparisonReport::new(
+            "scan.png",
+            vec![
+                ProviderRun::success("mistral", 900, 1, "Invoice 42\nTotal 100".to_string()),
+                ProviderRun::success("tesseract", 300, 1, "Invoice 42\nTotal 100".to_string()),
+            ],
+        );
+
+        assert_eq!(report.comparisons.len(), 1);
+        assert_eq!(report.comparisons[0].similarity, 1.0);
+        assert!(report.comparisons[0].diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_report_counts_changed_lines() {
+        let report = DiffReport::new(
+            "scan.png",
+            "scan.txt",
+            "lnvoice 42\nTotal 100\n",
+            "Invoice 42\nTotal 100\nPaid\n",
+        );
+
+        assert_eq!((report.lines_added, report.lines_removed), (2, 1));
+        assert!(report.similarity < 1.0);
+        assert!(report
+            .diff
+            .starts_with("--- scan.txt\n+++ scan.png (OCR)\n"));
+        assert!(report
+            .to_human_readable()
+            .starts_with("scan.png vs scan.txt: "));
+
+        let unchanged = DiffReport::new("scan.png", "scan.txt", "Total 100\n", "Total 100\n");
+        assert_eq!(unchanged.similarity, 1.0);
+        assert!(unchanged.diff.is_empty());
+    }
+
+    #[test]
+    fn test_different_outputs_produce_diff() {
+        let report = ComparisonReport::new(
+            "scan.png",
+            vec![
+                ProviderRun::success("mistral", 900, 1, "Invoice 42\nTotal 100\n".to_string()),
+                ProviderRun::failure("mock", 1, "boom".to_string()),
+                ProviderRun::success("tesseract", 300, 1, "lnvoice 42\nTotal 100\n".to_string()),
+            ],
+        );
+
+        let comparison = &report.comparisons[0];
+        assert_eq!(comparison.candidate, "tesseract");
+        assert!(comparison.similarity < 1.0);
+        assert!(comparison.diff.contains("-Invoice 42"));
+        assert!(comparison.diff.contains("+lnvoice 42"));
+        assert!(report
+            .to_human_readable()
+            .contains("mock        failed: boom"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Compression of outputs and request bodies
+//!
+//! Results of multi-hundred-page documents run into megabytes of text. With
+//! `--compress-output`, stdout is gzip-compressed and sidecars are written as
+//! `<document>.json.gz`; the result store reads compressed sidecars back
+//! transparently.
+//!
+//! JSON request bodies (annotation schemas, chat post-processing) can be sent
+//! with a gzip or zstd `Content-Encoding` when the gateway accepts it.
+
+use crate::error::{Error, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// File name suffix of compressed outputs
+pub const GZIP_SUFFIX: &str = ".gz";
+
+/// Gzip-compress data
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(Error::Io)?;
+    encoder.finish().map_err(Error::Io)
+}
+
+/// Decompress gzip data
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .map_err(Error::Io)?;
+    Ok(decompressed)
+}
+
+/// Whether a path names a gzip-compressed file
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("gz")
+}
+
+/// Content encoding of request bodies sent to the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestCompression {
+    /// Send bodies uncompressed
+    #[default]
+    None,
+    /// `Content-Encoding: gzip`
+    Gzip,
+    /// `Content-Encoding: zstd`
+    Zstd,
+}
+
+impl RequestCompression {
+    /// Value of the `Content-Encoding` header, if bodies are compressed
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gzip"),
+            Self::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Compress a request body
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data.to_vec()),
+            Self::Gzip => gzip(data),
+            Self::Zstd => {
+                zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(Error::Io)
+            }
+        }
+    }
+}
+
+impl fmt::Display for RequestCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.content_encoding().unwrap_or("none"))
+    }
+}
+
+impl FromStr for RequestCompression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(Error::Config(format!(
+                "Unknown request compression: {}. Supported: none, gzip, zstd",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let text = "Page one\n\n".repeat(1000);
+        let compressed = gzip(text.as_bytes()).unwrap();
+
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(gunzip(&compressed).unwrap(), text.as_bytes());
+        assert!(gunzip(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_request_compression_round_trip() {
+        let body = br#"{"model":"mistral-ocr-latest","schema":{}}"#.repeat(100);
+
+        let gzipped = RequestCompression::Gzip.compress(&body).unwrap();
+        assert_eq!(gunzip(&gzipped).unwrap(), body);
+
+        let zstd = RequestCompression::Zstd.compress(&body).unwrap();
+        assert!(zstd.len() < body.len() / 10);
+        assert_eq!(zstd::decode_all(zstd.as_slice()).unwrap(), body);
+
+        assert_eq!(RequestCompression::None.compress(&body).unwrap(), body);
+        assert_eq!(
+            "ZSTD".parse::<RequestCompression>().unwrap(),
+            RequestCompression::Zstd
+        );
+        assert!("brotli".parse::<RequestCompression>().is_err());
+    }
+
+    #[test]
+    fn test_is_gzip_path() {
+        assert!(is_gzip_path(Path::new("scan.pdf.json.gz")));
+        assert!(!is_gzip_path(Path::new("scan.pdf.json")));
+    }
+}
@@ -0,0 +1,168 @@
+//! Combined document of a batch
+//!
+//! `batch --concat FILE` merges the extracted texts of a batch into one
+//! Markdown document, for a single searchable archive file:
+//!
+//! ```text
+//! # Contents
+//!
+//! 1. [2024/invoice.pdf](#2024invoicepdf)
+//! 2. [letter.pdf](#letterpdf)
+//!
+//! # 2024/invoice.pdf
+//!
+//! <extracted text>
+//! ```
+//!
+//! Documents appear in processing order under a heading with their path
+//! relative to the batch input. Links use the anchors GitHub and most Markdown
+//! renderers generate for headings. Failed documents are left out.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A document of the combined output
+#[derive(Debug, Clone)]
+struct Section {
+    title: String,
+    anchor: String,
+    text: String,
+}
+
+/// Texts collected for the combined output
+#[derive(Debug, Default)]
+pub struct Concatenation {
+    sections: Vec<Section>,
+    anchors: HashMap<String, usize>,
+}
+
+impl Concatenation {
+    /// Add the text of a document at `relative` below the batch input
+    pub fn push(&mut self, relative: &Path, text: &str) {
+        let title = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        // Renderers number repeated anchors: name, name-1, name-2, ...; the
+        // contents heading comes first
+        let slug = slug(&title);
+        let seen = self
+            .anchors
+            .entry(slug.clone())
+            .or_insert(usize::from(slug == "contents"));
+        let anchor = match *seen {
+            0 => slug,
+            n => format!("{}-{}", slug, n),
+        };
+        *seen += 1;
+
+        self.sections.push(Section {
+            title,
+            anchor,
+            text: text.trim().to_string(),
+        });
+    }
+
+    /// Number of documents collected
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Whether no documents were collected
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The combined Markdown document
+    pub fn render(&self) -> String {
+        let mut output = String::from("# Contents\n\n");
+        for (i, section) in self.sections.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. [{}](#{})\n",
+                i + 1,
+                escape(&section.title),
+                section.anchor
+            ));
+        }
+        for section in &self.sections {
+            output.push_str(&format!("\n# {}\n\n", escape(&section.title)));
+            if !section.text.is_empty() {
+                output.push_str(&section.text);
+                output.push('\n');
+            }
+        }
+        output
+    }
+
+    /// Write the combined document to `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        fs::write(path, self.render()).map_err(Error::Io)
+    }
+}
+
+/// Anchor of a heading: lowercase, spaces as dashes, other punctuation dropped
+fn slug(title: &str) -> String {
+    title
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Escape the characters that would make a file name Markdown markup
+fn escape(title: &str) -> String {
+    let mut escaped = String::with_capacity(title.len());
+    for c in title.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_links_each_document() {
+        let mut concat = Concatenation::default();
+        concat.push(Path::new("2024/Invoice 1.pdf"), "INVOICE 42\n\n");
+        concat.push(Path::new("letter.pdf"), "Dear Sir");
+        concat.push(Path::new("letter.pdf"), "");
+        concat.push(Path::new("scan_[draft].png"), "Draft");
+        concat.push(Path::new("Contents"), "Index");
+        assert_eq!(concat.len(), 5);
+
+        assert_eq!(
+            concat.render(),
+            "# Contents\n\n\
+             1. [2024/Invoice 1.pdf](#2024invoice-1pdf)\n\
+             2. [letter.pdf](#letterpdf)\n\
+             3. [letter.pdf](#letterpdf-1)\n\
+             4. [scan\\_\\[draft\\].png](#scan_draftpng)\n\
+             5. [Contents](#contents-1)\n\
+             \n# 2024/Invoice 1.pdf\n\nINVOICE 42\n\
+             \n# letter.pdf\n\nDear Sir\n\
+             \n# letter.pdf\n\n\
+             \n# scan\\_\\[draft\\].png\n\nDraft\n\
+             \n# Contents\n\nIndex\n"
+        );
+    }
+}
@@ -1,10 +1,31 @@
 //! Configuration management for the OCR CLI tool
 
-use crate::error::{Error, Result};
+use crate::access::{check_tokens, TokenConfig};
+use crate::api::dns::{IpPreference, ResolveOverride};
+use crate::api::ocr::{unescape, PageLayout};
+use crate::compress::RequestCompression;
+use crate::dates::DateOrder;
+use crate::encrypt::EncryptionConfig;
+use crate::ensemble::MergeStrategy;
+use crate::error::{Error, FieldErrors, Result};
+use crate::file::{ValidationMode, ValidationRules, DEFAULT_MAX_FILE_SIZE_MB};
+use crate::notify::NotifyConfig;
+use crate::provider::{MockConfig, ProviderKind, TesseractConfig};
+use crate::quality::LowQualityAction;
+use crate::quota::{default_state_file, QuotaConfig};
+use crate::render::RenderConfig;
+use crate::result_cache::ResultCacheConfig;
+use crate::split::SplitConfig;
+use crate::tenant::{check_profiles, ProfileConfig};
+use crate::text_layer::TextLayerConfig;
+use crate::transform::TransformConfig;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 use url::Url;
 
@@ -61,32 +82,446 @@ impl RetryPolicy {
         Duration::from_millis(delay_ms)
     }
 
+    /// Delay before the next retry with decorrelated jitter
+    ///
+    /// The delay is drawn between the base delay and three times the previous
+    /// one (capped at the maximum), so tasks that were rate limited together
+    /// spread out instead of retrying in lockstep. Without exponential backoff
+    /// this falls back to [`RetryPolicy::calculate_delay`].
+    pub fn next_delay(&self, attempt: u32, previous: Duration) -> Duration {
+        if !self.exponential_backoff {
+            return self.calculate_delay(attempt);
+        }
+
+        let base = self.base_delay_ms;
+        let upper = (previous.as_millis() as u64)
+            .saturating_mul(3)
+            .clamp(base, self.max_delay_ms.max(base));
+        let delay_ms = if upper > base {
+            rand::thread_rng().gen_range(base..=upper)
+        } else {
+            base
+        };
+
+        Duration::from_millis(delay_ms.min(self.max_delay_ms))
+    }
+
     /// Validate retry policy configuration
     pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
         if self.max_retries > 10 {
-            return Err(Error::Config("Max retries cannot exceed 10".to_string()));
+            errors.add("max_retries", "Max retries cannot exceed 10");
         }
 
         if self.base_delay_ms == 0 {
-            return Err(Error::Config(
-                "Base delay must be greater than 0".to_string(),
-            ));
+            errors.add("base_delay_ms", "Base delay must be greater than 0");
         }
 
         if self.max_delay_ms < self.base_delay_ms {
-            return Err(Error::Config("Max delay must be >= base delay".to_string()));
+            errors.add("max_delay_ms", "Max delay must be >= base delay");
         }
 
         if self.jitter_factor < 0.0 || self.jitter_factor > 1.0 {
-            return Err(Error::Config(
-                "Jitter factor must be between 0.0 and 1.0".to_string(),
-            ));
+            errors.add("jitter_factor", "Jitter factor must be between 0.0 and 1.0");
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// Name resolution settings for the API client
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Static DNS overrides in curl syntax (`host:port:address`)
+    #[serde(default)]
+    pub resolve: Vec<ResolveOverride>,
+
+    /// IP family to connect with
+    #[serde(default)]
+    pub ip_preference: IpPreference,
+}
+
+/// HTTP protocol version used for API requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// HTTP/2 when negotiated with ALPN over TLS, HTTP/1.1 otherwise
+    #[default]
+    Auto,
+    /// Always HTTP/1.1, for gateways with a broken HTTP/2 implementation
+    Http1,
+    /// HTTP/2 without negotiation (prior knowledge), also over plain HTTP
+    Http2,
+}
+
+impl FromStr for HttpVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "http1" | "http/1.1" | "1.1" => Ok(Self::Http1),
+            "http2" | "h2" | "2" => Ok(Self::Http2),
+            other => Err(Error::Config(format!(
+                "Unknown HTTP version: {}. Supported: auto, http1, http2",
+                other
+            ))),
+        }
+    }
+}
+
+/// HTTP protocol settings for API requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Protocol version: negotiated, HTTP/1.1 only, or HTTP/2 prior knowledge
+    #[serde(default)]
+    pub version: HttpVersion,
+
+    /// Content encoding of JSON request bodies; only for gateways that accept it
+    #[serde(default)]
+    pub request_compression: RequestCompression,
+
+    /// Bodies smaller than this many bytes are sent uncompressed
+    #[serde(default = "default_compression_min_bytes")]
+    pub compression_min_bytes: usize,
+
+    /// Seconds an idle keep-alive connection stays in the pool
+    #[serde(default = "default_pool_idle_timeout_seconds")]
+    pub pool_idle_timeout_seconds: u64,
+
+    /// Idle connections kept per host; 0 disables keep-alive reuse
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+}
+
+fn default_compression_min_bytes() -> usize {
+    1024
+}
+
+fn default_pool_idle_timeout_seconds() -> u64 {
+    90
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    32
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            version: HttpVersion::default(),
+            request_compression: RequestCompression::default(),
+            compression_min_bytes: default_compression_min_bytes(),
+            pool_idle_timeout_seconds: default_pool_idle_timeout_seconds(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Validate the connection pool settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.pool_idle_timeout_seconds < 1 || self.pool_idle_timeout_seconds > 3600 {
+            errors.add(
+                "pool_idle_timeout_seconds",
+                "Pool idle timeout must be between 1 and 3600 seconds",
+            );
+        }
+
+        if self.pool_max_idle_per_host > 1024 {
+            errors.add(
+                "pool_max_idle_per_host",
+                "Pool max idle connections per host cannot exceed 1024",
+            );
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// paperless-ngx connection settings used by export and integration commands
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PaperlessConfig {
+    /// paperless-ngx base URL (e.g. http://paperless:8000)
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// paperless-ngx API token
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl PaperlessConfig {
+    /// Validate that the settings are complete enough to talk to paperless-ngx;
+    /// errors name the environment variables with `env_prefix`
+    pub fn validate(&self, env_prefix: &str) -> Result<()> {
+        let url = self.url.as_deref().unwrap_or_default();
+        if url.is_empty() {
+            return Err(Error::Config(format!(
+                "paperless-ngx URL must be set (--paperless-url or {}PAPERLESS_URL)",
+                env_prefix
+            )));
+        }
+
+        Url::parse(url)
+            .map_err(|_| Error::Config("paperless-ngx URL must be a valid URL".to_string()))?;
+
+        if self.token.as_deref().unwrap_or_default().is_empty() {
+            return Err(Error::Config(format!(
+                "paperless-ngx API token must be set (--paperless-token or {}PAPERLESS_TOKEN)",
+                env_prefix
+            )));
         }
 
         Ok(())
     }
 }
 
+/// OpenTelemetry settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OtelConfig {
+    /// Propagate W3C trace context (`traceparent`) to the API and accept it in `serve`
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Settings of `serve`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Clients allowed to use `POST /ocr`; empty leaves the server open
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+
+    /// Tenant profiles by name, for the tokens that name one
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+
+    /// Directory of the job queue; defaults to `jobs` in the state directory
+    #[serde(default)]
+    pub queue_dir: Option<PathBuf>,
+
+    /// Jobs processed at the same time
+    #[serde(default = "default_job_workers")]
+    pub workers: usize,
+
+    /// Hours finished jobs and their results are kept
+    #[serde(default = "default_job_retention_hours")]
+    pub job_retention_hours: u64,
+
+    /// `POST /ocr` requests and `POST /jobs` uploads handled at the same
+    /// time; more are answered with 429
+    #[serde(default = "default_max_concurrent_uploads")]
+    pub max_concurrent_uploads: usize,
+
+    /// Jobs waiting for a worker before `POST /jobs` is answered with 429
+    #[serde(default = "default_max_queue_depth")]
+    pub max_queue_depth: usize,
+
+    /// Seconds clients are asked to wait in `Retry-After` when the server is
+    /// busy
+    #[serde(default = "default_retry_after_seconds")]
+    pub retry_after_seconds: u64,
+
+    /// Listen on addresses other than loopback without `tokens`
+    #[serde(default)]
+    pub allow_unauthenticated: bool,
+}
+
+fn default_job_workers() -> usize {
+    2
+}
+
+fn default_job_retention_hours() -> u64 {
+    24
+}
+
+fn default_max_concurrent_uploads() -> usize {
+    8
+}
+
+fn default_max_queue_depth() -> usize {
+    100
+}
+
+fn default_retry_after_seconds() -> u64 {
+    30
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            profiles: BTreeMap::new(),
+            queue_dir: None,
+            workers: default_job_workers(),
+            job_retention_hours: default_job_retention_hours(),
+            max_concurrent_uploads: default_max_concurrent_uploads(),
+            max_queue_depth: default_max_queue_depth(),
+            retry_after_seconds: default_retry_after_seconds(),
+            allow_unauthenticated: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Validate the server settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+        check_tokens(&self.tokens, &mut errors);
+        check_profiles(&self.profiles, &self.tokens, &mut errors);
+        if self.workers == 0 {
+            errors.add("workers", "Workers must be at least 1");
+        }
+        if self.job_retention_hours == 0 {
+            errors.add(
+                "job_retention_hours",
+                "Job retention must be at least 1 hour",
+            );
+        }
+        if self.max_concurrent_uploads == 0 {
+            errors.add(
+                "max_concurrent_uploads",
+                "Concurrent uploads must be at least 1",
+            );
+        }
+        if self.max_queue_depth == 0 {
+            errors.add("max_queue_depth", "Queue depth must be at least 1");
+        }
+        if self.retry_after_seconds == 0 {
+            errors.add(
+                "retry_after_seconds",
+                "Retry-After must be at least 1 second",
+            );
+        }
+        errors.into_config_result()
+    }
+
+    /// Directory of the job queue, if one can be determined
+    pub fn queue_dir_path(&self) -> Option<PathBuf> {
+        self.queue_dir.clone().or_else(crate::paths::queue_dir)
+    }
+}
+
+/// OCR quality policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityConfig {
+    /// Minimum acceptable quality score (0.0 to 1.0); unset disables the check
+    #[serde(default)]
+    pub min_score: Option<f64>,
+
+    /// What to do when the score is below the minimum
+    #[serde(default)]
+    pub on_low_quality: LowQualityAction,
+}
+
+impl QualityConfig {
+    /// Validate the quality policy
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if let Some(min_score) = self.min_score {
+            if !(0.0..=1.0).contains(&min_score) {
+                errors.add(
+                    "min_score",
+                    "Minimum quality score must be between 0.0 and 1.0",
+                );
+            }
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// Spending limits for runs over many files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Pages after which no further files are processed; unset disables the limit
+    #[serde(default)]
+    pub max_pages: Option<u64>,
+
+    /// Estimated cost after which no further files are processed; unset disables the limit
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+
+    /// Price per page used to estimate the cost (Mistral OCR: $1 per 1000 pages)
+    #[serde(default = "default_cost_per_page")]
+    pub cost_per_page: f64,
+}
+
+fn default_cost_per_page() -> f64 {
+    0.001
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_pages: None,
+            max_cost: None,
+            cost_per_page: default_cost_per_page(),
+        }
+    }
+}
+
+impl BudgetConfig {
+    /// Validate the limits
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.max_pages == Some(0) {
+            errors.add("max_pages", "Maximum pages must be greater than 0");
+        }
+
+        if self.max_cost.is_some_and(|max_cost| max_cost <= 0.0) {
+            errors.add("max_cost", "Maximum cost must be greater than 0");
+        }
+
+        if self.cost_per_page < 0.0 {
+            errors.add("cost_per_page", "Cost per page must not be negative");
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Whether a page or cost limit is set
+    pub fn is_limited(&self) -> bool {
+        self.max_pages.is_some() || self.max_cost.is_some()
+    }
+
+    /// Estimated cost of the given number of pages
+    pub fn estimate_cost(&self, pages: u64) -> f64 {
+        pages as f64 * self.cost_per_page
+    }
+
+    /// Fail once the pages processed so far reach a limit
+    pub fn check(&self, files: u64, pages: u64) -> Result<()> {
+        let cost = self.estimate_cost(pages);
+        let exhausted = match (self.max_pages, self.max_cost) {
+            (Some(max_pages), _) if pages >= max_pages => {
+                Some(format!("page budget of {} reached", max_pages))
+            }
+            (_, Some(max_cost)) if cost >= max_cost => {
+                Some(format!("cost budget of ${:.2} reached", max_cost))
+            }
+            _ => None,
+        };
+
+        match exhausted {
+            Some(reason) => Err(Error::BudgetExceeded(format!(
+                "{} after {} file{} ({} pages, estimated ${:.2}); remaining files were not processed",
+                reason,
+                files,
+                if files == 1 { "" } else { "s" },
+                pages,
+                cost
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Mistral AI API key
@@ -96,14 +531,50 @@ pub struct Config {
     #[serde(default = "default_api_base_url")]
     pub api_base_url: String,
 
-    /// Request timeout in seconds
+    /// Allow a plain HTTP API base URL when it points to this machine
+    #[serde(default)]
+    pub allow_insecure_loopback: bool,
+
+    /// Time allowed per document for upload and OCR, including retries, in seconds
     #[serde(default = "default_timeout_seconds")]
     pub timeout_seconds: u64,
 
+    /// Connection establishment timeout in seconds
+    #[serde(default = "default_connect_timeout_seconds")]
+    pub connect_timeout_seconds: u64,
+
     /// Maximum file size in MB
     #[serde(default = "default_max_file_size_mb")]
     pub max_file_size_mb: u64,
 
+    /// Maximum size of an API response body in MB
+    #[serde(default = "default_max_response_size_mb")]
+    pub max_response_size_mb: u64,
+
+    /// MIME types accepted by client-side validation
+    #[serde(default = "default_allowed_mime_types")]
+    pub allowed_mime_types: Vec<String>,
+
+    /// Whether failed content checks reject a file or only warn
+    #[serde(default)]
+    pub validation: ValidationMode,
+
+    /// Maximum number of concurrent API calls across the whole process
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+
+    /// Memory for document buffers across the whole process, in MB; unset disables the limit
+    #[serde(default)]
+    pub memory_budget_mb: Option<u64>,
+
+    /// Reference uploads in OCR requests by signed URL instead of file ID
+    #[serde(default)]
+    pub use_signed_url: bool,
+
+    /// Validity of signed URLs in hours
+    #[serde(default = "default_signed_url_expiry_hours")]
+    pub signed_url_expiry_hours: u32,
+
     /// Logging level
     #[serde(default = "default_log_level")]
     pub log_level: String,
@@ -111,6 +582,125 @@ pub struct Config {
     /// Retry policy configuration
     #[serde(default = "default_retry_policy")]
     pub retry_policy: RetryPolicy,
+
+    /// Name resolution settings
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// HTTP protocol settings
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// paperless-ngx connection settings
+    #[serde(default)]
+    pub paperless: PaperlessConfig,
+
+    /// Mistral OCR model; pin a dated version to keep results stable
+    #[serde(default = "default_ocr_model")]
+    pub ocr_model: String,
+
+    /// Model used for chat-based post-processing (summaries, translation, entities)
+    #[serde(default = "default_chat_model")]
+    pub chat_model: String,
+
+    /// Model used for embeddings of the extracted text (`--embed`)
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+
+    /// Order of day, month and year used to read ambiguous numeric dates
+    #[serde(default)]
+    pub date_order: DateOrder,
+
+    /// Pages with fewer alphanumeric characters than this are treated as blank
+    #[serde(default = "default_blank_page_threshold")]
+    pub blank_page_threshold: usize,
+
+    /// OCR quality policy
+    #[serde(default)]
+    pub quality: QualityConfig,
+
+    /// OCR providers in fallback order
+    #[serde(default = "default_providers")]
+    pub providers: Vec<ProviderKind>,
+
+    /// How the outputs of several providers are combined
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+
+    /// Local Tesseract provider settings
+    #[serde(default)]
+    pub tesseract: TesseractConfig,
+
+    /// Mock provider settings
+    #[serde(default)]
+    pub mock: MockConfig,
+
+    /// PDF rendering for image-only providers
+    #[serde(default)]
+    pub render: RenderConfig,
+
+    /// Text layer extraction of digital-born PDFs
+    #[serde(default)]
+    pub text_layer: TextLayerConfig,
+
+    /// Copying scanned pages out of mixed PDFs
+    #[serde(default)]
+    pub split: SplitConfig,
+
+    /// Page separators and headers in the extracted text
+    #[serde(default)]
+    pub pages: PageLayout,
+
+    /// Encryption of stdout and sidecar outputs
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    /// Page and cost limits for runs over many files
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// Monthly page quota for the Mistral API
+    #[serde(default)]
+    pub quota: QuotaConfig,
+
+    /// Reports sent when runs over several files complete
+    #[serde(default)]
+    pub notify: NotifyConfig,
+
+    /// Persistent cache of OCR results
+    #[serde(default)]
+    pub cache: ResultCacheConfig,
+
+    /// Distributed tracing
+    #[serde(default)]
+    pub otel: OtelConfig,
+
+    /// Settings of `serve`
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    /// Script run on each result before it is written
+    #[serde(default)]
+    pub transform: TransformConfig,
+
+    /// Persistent state (monthly page counts); defaults to the XDG state directory
+    #[serde(default)]
+    pub state_file: Option<PathBuf>,
+
+    /// Prefix of the environment variables overriding settings
+    #[serde(default = "default_env_prefix")]
+    pub env_prefix: String,
+}
+
+/// Prefix of the environment variables, `PAPERLESS_OCR_` unless the build sets
+/// `PAPERLESS_OCR_ENV_PREFIX` (e.g. `PAPERLESS_OCR_ENV_PREFIX=OCR2_ cargo build`)
+pub const DEFAULT_ENV_PREFIX: &str = match option_env!("PAPERLESS_OCR_ENV_PREFIX") {
+    Some(prefix) => prefix,
+    None => "PAPERLESS_OCR_",
+};
+
+fn default_env_prefix() -> String {
+    DEFAULT_ENV_PREFIX.to_string()
 }
 
 fn default_api_base_url() -> String {
@@ -121,8 +711,40 @@ fn default_timeout_seconds() -> u64 {
     30
 }
 
+fn default_connect_timeout_seconds() -> u64 {
+    10
+}
+
+/// Replacement for secrets in displayed configuration
+const REDACTED: &str = "[REDACTED]";
+
+/// Largest configurable file size limit in MB
+pub const MAX_FILE_SIZE_CEILING_MB: u64 = 1024;
+
 fn default_max_file_size_mb() -> u64 {
-    100
+    DEFAULT_MAX_FILE_SIZE_MB
+}
+
+/// Largest configurable response size limit in MB
+pub const MAX_RESPONSE_SIZE_CEILING_MB: u64 = 1024;
+
+fn default_max_response_size_mb() -> u64 {
+    64
+}
+
+fn default_allowed_mime_types() -> Vec<String> {
+    ValidationRules::default().allowed_mime_types
+}
+
+/// Largest configurable memory budget in MB
+pub const MEMORY_BUDGET_CEILING_MB: u64 = 1024 * 1024;
+
+fn default_max_concurrent_requests() -> usize {
+    4
+}
+
+fn default_signed_url_expiry_hours() -> u32 {
+    1
 }
 
 fn default_log_level() -> String {
@@ -133,34 +755,93 @@ fn default_retry_policy() -> RetryPolicy {
     RetryPolicy::default()
 }
 
+fn default_ocr_model() -> String {
+    crate::api::ocr::OCR_MODEL.to_string()
+}
+
+fn default_chat_model() -> String {
+    "mistral-small-latest".to_string()
+}
+
+fn default_embedding_model() -> String {
+    "mistral-embed".to_string()
+}
+
+fn default_blank_page_threshold() -> usize {
+    5
+}
+
+fn default_providers() -> Vec<ProviderKind> {
+    vec![ProviderKind::Mistral]
+}
+
 impl Config {
     /// Load configuration from file with environment variable overrides
     pub fn load() -> Result<Self> {
-        // Load .env file first
-        dotenv::dotenv().ok(); // Ignore errors if .env doesn't exist
+        Self::load_dotenv(&Self::get_config_path());
 
         let mut config = Self::load_from_file()?;
-        config.apply_env_overrides();
+        config.apply_env_overrides()?;
         config.validate()?;
         Ok(config)
     }
 
     /// Load configuration without validation (for CLI override scenarios)
     pub fn load_without_validation() -> Result<Self> {
-        // Load .env file first
-        dotenv::dotenv().ok(); // Ignore errors if .env doesn't exist
+        Self::load_dotenv(&Self::get_config_path());
 
         let mut config = Self::load_from_file().unwrap_or_default();
-        config.apply_env_overrides();
+        config.apply_env_overrides()?;
         Ok(config)
     }
 
+    /// Load a configuration file, if it exists, with environment overrides
+    ///
+    /// Unlike [`Config::load_without_validation`], a file that cannot be read
+    /// or parsed is an error; the settings are not validated.
+    pub fn load_unchecked(config_path: &Path) -> Result<Self> {
+        Self::load_dotenv(config_path);
+
+        let mut config = Self::read_file(config_path)?;
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    /// Load `.env` files without overriding variables that are already set
+    ///
+    /// The `.env` next to the configuration file comes first, so services
+    /// started in another working directory (systemd `WorkingDirectory=/`)
+    /// still find their secrets, then the one in the current directory.
+    fn load_dotenv(config_path: &Path) {
+        if let Some(dir) = config_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+        {
+            dotenv::from_path(dir.join(".env")).ok(); // Ignore errors if .env doesn't exist
+        }
+        dotenv::dotenv().ok();
+    }
+
+    /// Load an env file given with `--env-file`
+    ///
+    /// Unlike the implicit `.env` files it must exist. Variables that are
+    /// already set are kept, and the file is loaded before the implicit ones,
+    /// so its values win over theirs.
+    pub fn load_env_file(path: &Path) -> Result<()> {
+        dotenv::from_path(path).map_err(|e| {
+            Error::Config(format!("Failed to load env file {}: {}", path.display(), e))
+        })
+    }
+
     /// Load configuration from TOML file
     fn load_from_file() -> Result<Self> {
-        let config_path = Self::get_config_path();
+        Self::read_file(&Self::get_config_path())
+    }
 
+    /// Parse a TOML file; the default configuration when it does not exist
+    fn read_file(config_path: &Path) -> Result<Self> {
         if config_path.exists() {
-            let content = fs::read_to_string(&config_path)
+            let content = fs::read_to_string(config_path)
                 .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
 
             let config: Config = toml::from_str(&content)
@@ -175,10 +856,8 @@ impl Config {
 
     /// Load configuration from a specific file path
     pub fn load_from_path(path: &str) -> Result<Self> {
-        // Load .env file first
-        dotenv::dotenv().ok(); // Ignore errors if .env doesn't exist
-
         let config_path = PathBuf::from(path);
+        Self::load_dotenv(&config_path);
 
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)
@@ -187,7 +866,7 @@ impl Config {
             let mut config: Config = toml::from_str(&content)
                 .map_err(|e| Error::Config(format!("Failed to parse config file: {}", e)))?;
 
-            config.apply_env_overrides();
+            config.apply_env_overrides()?;
             config.validate()?;
             Ok(config)
         } else {
@@ -195,76 +874,516 @@ impl Config {
         }
     }
 
+    /// Value of the environment variable `<env_prefix><name>`
+    fn env_var(&self, name: &str) -> std::result::Result<String, env::VarError> {
+        env::var(format!("{}{}", self.env_prefix, name))
+    }
+
     /// Apply environment variable overrides
-    fn apply_env_overrides(&mut self) {
-        if let Ok(api_key) = env::var("PAPERLESS_OCR_API_KEY") {
+    ///
+    /// Variables are named after `env_prefix` (`PAPERLESS_OCR_` by default),
+    /// which only the build or the configuration file can change. Security
+    /// settings with a value that cannot be parsed are an error rather than
+    /// silently left at their default.
+    fn apply_env_overrides(&mut self) -> Result<()> {
+        if let Ok(api_key) = self.env_var("API_KEY") {
             self.api_key = api_key;
         }
 
-        if let Ok(api_base_url) = env::var("PAPERLESS_OCR_API_BASE_URL") {
+        if let Ok(api_base_url) = self.env_var("API_BASE_URL") {
             self.api_base_url = api_base_url;
         }
 
-        if let Ok(timeout) = env::var("PAPERLESS_OCR_TIMEOUT") {
+        if let Ok(allow) = self.env_var("ALLOW_INSECURE_LOOPBACK") {
+            self.allow_insecure_loopback = allow.parse::<bool>().map_err(|_| {
+                Error::Config(format!(
+                    "{}ALLOW_INSECURE_LOOPBACK must be 'true' or 'false', not '{}'",
+                    self.env_prefix, allow
+                ))
+            })?;
+        }
+
+        if let Ok(timeout) = self.env_var("TIMEOUT") {
             if let Ok(timeout_val) = timeout.parse::<u64>() {
                 self.timeout_seconds = timeout_val;
             }
         }
 
-        if let Ok(max_size) = env::var("PAPERLESS_OCR_MAX_FILE_SIZE") {
+        if let Ok(connect_timeout) = self.env_var("CONNECT_TIMEOUT") {
+            if let Ok(connect_timeout_val) = connect_timeout.parse::<u64>() {
+                self.connect_timeout_seconds = connect_timeout_val;
+            }
+        }
+
+        // <prefix>MAX_FILE_SIZE is the older name of the same setting
+        if let Ok(max_size) = self
+            .env_var("MAX_FILE_SIZE_MB")
+            .or_else(|_| self.env_var("MAX_FILE_SIZE"))
+        {
             if let Ok(size_val) = max_size.parse::<u64>() {
                 self.max_file_size_mb = size_val;
             }
         }
 
-        if let Ok(log_level) = env::var("PAPERLESS_OCR_LOG_LEVEL") {
+        if let Ok(max_size) = self.env_var("MAX_RESPONSE_SIZE_MB") {
+            if let Ok(size_val) = max_size.parse::<u64>() {
+                self.max_response_size_mb = size_val;
+            }
+        }
+
+        if let Ok(mime_types) = self.env_var("ALLOWED_MIME_TYPES") {
+            let mime_types: Vec<String> = mime_types
+                .split(',')
+                .map(str::trim)
+                .filter(|mime_type| !mime_type.is_empty())
+                .map(str::to_string)
+                .collect();
+            if !mime_types.is_empty() {
+                self.allowed_mime_types = mime_types;
+            }
+        }
+
+        if let Ok(mode) = self.env_var("VALIDATION") {
+            if let Ok(mode) = mode.parse::<ValidationMode>() {
+                self.validation = mode;
+            }
+        }
+
+        if let Ok(max_concurrent) = self.env_var("MAX_CONCURRENT_REQUESTS") {
+            if let Ok(max_concurrent_val) = max_concurrent.parse::<usize>() {
+                self.max_concurrent_requests = max_concurrent_val;
+            }
+        }
+
+        if let Ok(budget) = self.env_var("MEMORY_BUDGET_MB") {
+            if let Ok(budget) = budget.parse::<u64>() {
+                self.memory_budget_mb = Some(budget);
+            }
+        }
+
+        if let Ok(use_signed_url) = self.env_var("USE_SIGNED_URL") {
+            if let Ok(use_signed_url) = use_signed_url.parse::<bool>() {
+                self.use_signed_url = use_signed_url;
+            }
+        }
+
+        if let Ok(expiry) = self.env_var("SIGNED_URL_EXPIRY_HOURS") {
+            if let Ok(expiry) = expiry.parse::<u32>() {
+                self.signed_url_expiry_hours = expiry;
+            }
+        }
+
+        if let Ok(log_level) = self.env_var("LOG_LEVEL") {
             self.log_level = log_level;
         }
+
+        if let Ok(ocr_model) = self.env_var("OCR_MODEL") {
+            self.ocr_model = ocr_model;
+        }
+
+        if let Ok(chat_model) = self.env_var("CHAT_MODEL") {
+            self.chat_model = chat_model;
+        }
+
+        if let Ok(embedding_model) = self.env_var("EMBEDDING_MODEL") {
+            self.embedding_model = embedding_model;
+        }
+
+        if let Ok(date_order) = self.env_var("DATE_ORDER") {
+            if let Ok(order) = date_order.parse::<DateOrder>() {
+                self.date_order = order;
+            }
+        }
+
+        if let Ok(threshold) = self.env_var("BLANK_PAGE_THRESHOLD") {
+            if let Ok(threshold_val) = threshold.parse::<usize>() {
+                self.blank_page_threshold = threshold_val;
+            }
+        }
+
+        if let Ok(min_quality) = self.env_var("MIN_QUALITY") {
+            if let Ok(min_score) = min_quality.parse::<f64>() {
+                self.quality.min_score = Some(min_score);
+            }
+        }
+
+        if let Ok(action) = self.env_var("ON_LOW_QUALITY") {
+            if let Ok(action) = action.parse::<LowQualityAction>() {
+                self.quality.on_low_quality = action;
+            }
+        }
+
+        if let Ok(providers) = self.env_var("PROVIDERS") {
+            if let Ok(providers) = ProviderKind::parse_list(&providers) {
+                self.providers = providers;
+            }
+        }
+
+        if let Ok(strategy) = self.env_var("MERGE_STRATEGY") {
+            if let Ok(strategy) = strategy.parse::<MergeStrategy>() {
+                self.merge_strategy = strategy;
+            }
+        }
+
+        if let Ok(compression) = self.env_var("REQUEST_COMPRESSION") {
+            if let Ok(compression) = compression.parse::<RequestCompression>() {
+                self.http.request_compression = compression;
+            }
+        }
+
+        if let Ok(version) = self.env_var("HTTP_VERSION") {
+            if let Ok(version) = version.parse::<HttpVersion>() {
+                self.http.version = version;
+            }
+        }
+
+        if let Ok(timeout) = self.env_var("POOL_IDLE_TIMEOUT") {
+            if let Ok(timeout_val) = timeout.parse::<u64>() {
+                self.http.pool_idle_timeout_seconds = timeout_val;
+            }
+        }
+
+        if let Ok(max_idle) = self.env_var("POOL_MAX_IDLE_PER_HOST") {
+            if let Ok(max_idle_val) = max_idle.parse::<usize>() {
+                self.http.pool_max_idle_per_host = max_idle_val;
+            }
+        }
+
+        if let Ok(enabled) = self.env_var("CACHE") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                self.cache.enabled = enabled;
+            }
+        }
+
+        if let Ok(dir) = self.env_var("CACHE_DIR") {
+            self.cache.dir = Some(PathBuf::from(dir));
+        }
+
+        if let Ok(enabled) = self.env_var("OTEL") {
+            if let Ok(enabled) = enabled.parse::<bool>() {
+                self.otel.enabled = enabled;
+            }
+        }
+
+        if let Ok(script) = self.env_var("TRANSFORM_SCRIPT") {
+            self.transform.script =
+                Some(PathBuf::from(script)).filter(|p| !p.as_os_str().is_empty());
+        }
+
+        if let Ok(latency) = self.env_var("MOCK_LATENCY_MS") {
+            if let Ok(latency) = latency.parse::<u64>() {
+                self.mock.latency_ms = latency;
+            }
+        }
+
+        if let Ok(error_rate) = self.env_var("MOCK_ERROR_RATE") {
+            if let Ok(error_rate) = error_rate.parse::<f64>() {
+                self.mock.error_rate = error_rate;
+            }
+        }
+
+        if let Ok(languages) = self.env_var("TESSERACT_LANGUAGES") {
+            self.tesseract.languages = languages;
+        }
+
+        if let Ok(separator) = self.env_var("PAGE_SEPARATOR") {
+            self.pages.separator = unescape(&separator);
+        }
+
+        if let Ok(header) = self.env_var("PAGE_HEADER") {
+            self.pages.header = Some(header).filter(|header| !header.is_empty());
+        }
+
+        if let Ok(dpi) = self.env_var("RENDER_DPI") {
+            if let Ok(dpi) = dpi.parse::<u32>() {
+                self.render.dpi = dpi;
+            }
+        }
+
+        if let Ok(recipients) = self.env_var("ENCRYPT_RECIPIENTS") {
+            self.encryption.recipients = recipients
+                .split(',')
+                .map(str::trim)
+                .filter(|recipient| !recipient.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
+        if let Ok(max_pages) = self.env_var("MAX_PAGES") {
+            if let Ok(max_pages) = max_pages.parse::<u64>() {
+                self.budget.max_pages = Some(max_pages);
+            }
+        }
+
+        if let Ok(max_cost) = self.env_var("MAX_COST") {
+            if let Ok(max_cost) = max_cost.parse::<f64>() {
+                self.budget.max_cost = Some(max_cost);
+            }
+        }
+
+        if let Ok(cost_per_page) = self.env_var("COST_PER_PAGE") {
+            if let Ok(cost_per_page) = cost_per_page.parse::<f64>() {
+                self.budget.cost_per_page = cost_per_page;
+            }
+        }
+
+        if let Ok(monthly_pages) = self.env_var("MONTHLY_QUOTA") {
+            if let Ok(monthly_pages) = monthly_pages.parse::<u64>() {
+                self.quota.monthly_pages = Some(monthly_pages);
+            }
+        }
+
+        if let Ok(enforce) = self.env_var("ENFORCE_QUOTA") {
+            if let Ok(enforce) = enforce.parse::<bool>() {
+                self.quota.enforce = enforce;
+            }
+        }
+
+        if let Ok(state_file) = self.env_var("STATE_FILE") {
+            self.state_file = Some(PathBuf::from(state_file)).filter(|p| !p.as_os_str().is_empty());
+        }
+
+        if let Ok(resolve) = self.env_var("RESOLVE") {
+            let overrides: Result<Vec<ResolveOverride>> = resolve
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::parse)
+                .collect();
+            if let Ok(overrides) = overrides {
+                self.network.resolve = overrides;
+            }
+        }
+
+        if let Ok(preference) = self.env_var("IP_PREFERENCE") {
+            if let Ok(preference) = preference.parse::<IpPreference>() {
+                self.network.ip_preference = preference;
+            }
+        }
+
+        if let Ok(paperless_url) = self.env_var("PAPERLESS_URL") {
+            self.paperless.url = Some(paperless_url);
+        }
+
+        if let Ok(paperless_token) = self.env_var("PAPERLESS_TOKEN") {
+            self.paperless.token = Some(paperless_token);
+        }
+
+        Ok(())
+    }
+
+    /// Rules for validating input files
+    pub fn validation_rules(&self) -> ValidationRules {
+        ValidationRules {
+            allowed_mime_types: self.allowed_mime_types.clone(),
+            max_file_size_mb: self.max_file_size_mb,
+            mime_type: None,
+            mode: self.validation,
+        }
     }
 
     /// Validate configuration according to data model rules
+    ///
+    /// Every invalid setting is reported, not just the first one.
     pub fn validate(&self) -> Result<()> {
-        // Validate API key
-        if self.api_key.is_empty() {
-            return Err(Error::Config("API key must not be empty".to_string()));
+        let mut errors = FieldErrors::new();
+
+        // Validate API key (only needed when the Mistral provider is used)
+        if self.api_key.is_empty() && self.providers.contains(&ProviderKind::Mistral) {
+            errors.add("api_key", "API key must not be empty");
+        }
+
+        // Validate provider chain
+        if self.providers.is_empty() {
+            errors.add("providers", "At least one OCR provider must be configured");
+        }
+        for (i, provider) in self.providers.iter().enumerate() {
+            if self.providers[..i].contains(provider) {
+                errors.add(
+                    "providers",
+                    format!("OCR provider {} is listed more than once", provider),
+                );
+            }
         }
 
         // Validate API base URL
-        Url::parse(&self.api_base_url)
-            .map_err(|_| Error::Config("API base URL must be a valid URL".to_string()))?;
+        if Url::parse(&self.api_base_url).is_err() {
+            errors.add("api_base_url", "API base URL must be a valid URL");
+        }
 
         // Validate timeout range
         if self.timeout_seconds < 1 || self.timeout_seconds > 300 {
-            return Err(Error::Config(
-                "Timeout must be between 1 and 300 seconds".to_string(),
-            ));
+            errors.add(
+                "timeout_seconds",
+                "Timeout must be between 1 and 300 seconds",
+            );
+        }
+
+        if self.connect_timeout_seconds < 1 || self.connect_timeout_seconds > 300 {
+            errors.add(
+                "connect_timeout_seconds",
+                "Connect timeout must be between 1 and 300 seconds",
+            );
         }
 
         // Validate file size range
-        if self.max_file_size_mb < 1 || self.max_file_size_mb > 100 {
-            return Err(Error::Config(
-                "Max file size must be between 1 and 100 MB".to_string(),
-            ));
+        if self.max_file_size_mb < 1 || self.max_file_size_mb > MAX_FILE_SIZE_CEILING_MB {
+            errors.add(
+                "max_file_size_mb",
+                format!(
+                    "Max file size must be between 1 and {} MB",
+                    MAX_FILE_SIZE_CEILING_MB
+                ),
+            );
+        }
+
+        if self.max_response_size_mb < 1 || self.max_response_size_mb > MAX_RESPONSE_SIZE_CEILING_MB
+        {
+            errors.add(
+                "max_response_size_mb",
+                format!(
+                    "Max response size must be between 1 and {} MB",
+                    MAX_RESPONSE_SIZE_CEILING_MB
+                ),
+            );
+        }
+
+        // Validate accepted MIME types
+        if self.allowed_mime_types.is_empty() {
+            errors.add(
+                "allowed_mime_types",
+                "At least one allowed MIME type must be configured",
+            );
+        }
+        for mime_type in &self.allowed_mime_types {
+            let valid = mime_type
+                .split_once('/')
+                .is_some_and(|(top_level, subtype)| !top_level.is_empty() && !subtype.is_empty());
+            if !valid {
+                errors.add(
+                    "allowed_mime_types",
+                    format!(
+                        "Invalid MIME type: '{}'. Expected type/subtype or type/*",
+                        mime_type
+                    ),
+                );
+            }
+        }
+
+        // Validate concurrency limit
+        if self.max_concurrent_requests < 1 || self.max_concurrent_requests > 64 {
+            errors.add(
+                "max_concurrent_requests",
+                "Max concurrent requests must be between 1 and 64",
+            );
+        }
+
+        if self
+            .memory_budget_mb
+            .is_some_and(|budget| !(16..=MEMORY_BUDGET_CEILING_MB).contains(&budget))
+        {
+            errors.add(
+                "memory_budget_mb",
+                format!(
+                    "Memory budget must be between 16 and {} MB",
+                    MEMORY_BUDGET_CEILING_MB
+                ),
+            );
+        }
+
+        // Validate signed URL lifetime
+        if self.signed_url_expiry_hours < 1 || self.signed_url_expiry_hours > 168 {
+            errors.add(
+                "signed_url_expiry_hours",
+                "Signed URL expiry must be between 1 and 168 hours",
+            );
         }
 
         // Validate log level
         let valid_levels = ["error", "warn", "info", "debug", "trace"];
         if !valid_levels.contains(&self.log_level.as_str()) {
-            return Err(Error::Config(format!(
-                "Log level must be one of: {}",
-                valid_levels.join(", ")
-            )));
+            errors.add(
+                "log_level",
+                format!("Log level must be one of: {}", valid_levels.join(", ")),
+            );
         }
 
-        // Validate retry policy
-        self.retry_policy.validate()?;
+        if self.ocr_model.is_empty() {
+            errors.add("ocr_model", "OCR model must not be empty");
+        }
 
-        Ok(())
+        if self.chat_model.is_empty() {
+            errors.add("chat_model", "Chat model must not be empty");
+        }
+
+        if self.embedding_model.is_empty() {
+            errors.add("embedding_model", "Embedding model must not be empty");
+        }
+
+        // Validate nested sections
+        errors.nested("retry_policy", self.retry_policy.validate());
+        errors.nested("http", self.http.validate());
+        errors.nested("quality", self.quality.validate());
+        errors.nested("render", self.render.validate());
+        errors.nested("text_layer", self.text_layer.validate());
+        errors.nested("split", self.split.validate());
+        errors.nested("encryption", self.encryption.validate());
+        errors.nested("budget", self.budget.validate());
+        errors.nested("quota", self.quota.validate());
+        errors.nested("notify", self.notify.validate());
+        errors.nested("cache", self.cache.validate());
+        errors.nested("transform", self.transform.validate());
+        errors.nested("server", self.server.validate());
+        errors.nested("mock", self.mock.validate());
+
+        if self.env_prefix.is_empty()
+            || !self
+                .env_prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            errors.add(
+                "env_prefix",
+                "Environment variable prefix must be non-empty and contain only letters, digits, and underscores",
+            );
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Path of the persistent state file, if one can be determined
+    pub fn state_file_path(&self) -> Option<PathBuf> {
+        self.state_file.clone().or_else(default_state_file)
+    }
+
+    /// Copy with secrets replaced, for display
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if !config.api_key.is_empty() {
+            config.api_key = REDACTED.to_string();
+        }
+        if config.paperless.token.is_some() {
+            config.paperless.token = Some(REDACTED.to_string());
+        }
+        for token in &mut config.server.tokens {
+            token.token = REDACTED.to_string();
+        }
+        if let Some(email) = &mut config.notify.email {
+            if email.smtp_password.is_some() {
+                email.smtp_password = Some(REDACTED.to_string());
+            }
+        }
+        for profile in config.server.profiles.values_mut() {
+            if profile.api_key.is_some() {
+                profile.api_key = Some(REDACTED.to_string());
+            }
+        }
+        config
     }
 
     /// Get the default configuration file path
     /// Search order: current directory -> ~/.config/paperless-ngx-ocr2/
-    fn get_config_path() -> PathBuf {
+    pub fn get_config_path() -> PathBuf {
         // First try current directory
         let current_dir_config = PathBuf::from("config.toml");
         if current_dir_config.exists() {
@@ -302,10 +1421,46 @@ impl Default for Config {
         Self {
             api_key: String::new(), // Will be set via env var or CLI arg
             api_base_url: default_api_base_url(),
+            allow_insecure_loopback: false,
             timeout_seconds: default_timeout_seconds(),
+            connect_timeout_seconds: default_connect_timeout_seconds(),
             max_file_size_mb: default_max_file_size_mb(),
+            max_response_size_mb: default_max_response_size_mb(),
+            allowed_mime_types: default_allowed_mime_types(),
+            validation: ValidationMode::default(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+            memory_budget_mb: None,
+            use_signed_url: false,
+            signed_url_expiry_hours: default_signed_url_expiry_hours(),
             log_level: default_log_level(),
             retry_policy: default_retry_policy(),
+            network: NetworkConfig::default(),
+            http: HttpConfig::default(),
+            paperless: PaperlessConfig::default(),
+            ocr_model: default_ocr_model(),
+            chat_model: default_chat_model(),
+            embedding_model: default_embedding_model(),
+            date_order: DateOrder::default(),
+            blank_page_threshold: default_blank_page_threshold(),
+            quality: QualityConfig::default(),
+            providers: default_providers(),
+            merge_strategy: MergeStrategy::default(),
+            tesseract: TesseractConfig::default(),
+            render: RenderConfig::default(),
+            text_layer: TextLayerConfig::default(),
+            split: SplitConfig::default(),
+            pages: PageLayout::default(),
+            encryption: EncryptionConfig::default(),
+            budget: BudgetConfig::default(),
+            quota: QuotaConfig::default(),
+            notify: NotifyConfig::default(),
+            cache: ResultCacheConfig::default(),
+            otel: OtelConfig::default(),
+            server: ServerConfig::default(),
+            transform: TransformConfig::default(),
+            state_file: None,
+            env_prefix: default_env_prefix(),
+            mock: MockConfig::default(),
         }
     }
 }
@@ -325,6 +1480,18 @@ mod tests {
         assert!(config.api_key.is_empty());
     }
 
+    #[test]
+    fn test_validation_response_size_range() {
+        for (size, valid) in [(0, false), (1, true), (64, true), (1025, false)] {
+            let config = Config {
+                api_key: "sk-test123456789".to_string(),
+                max_response_size_mb: size,
+                ..Config::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "{} MB", size);
+        }
+    }
+
     #[test]
     fn test_validation_valid_config() {
         let config = Config {
@@ -334,6 +1501,7 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
 
         assert!(config.validate().is_ok());
@@ -348,6 +1516,7 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
 
         assert!(config.validate().is_err());
@@ -362,6 +1531,7 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
 
         assert!(config.validate().is_err());
@@ -377,6 +1547,7 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
         assert!(config_low.validate().is_err());
 
@@ -388,6 +1559,7 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
         assert!(config_high.validate().is_err());
     }
@@ -402,6 +1574,7 @@ mod tests {
             max_file_size_mb: 0,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
         assert!(config_low.validate().is_err());
 
@@ -410,13 +1583,83 @@ mod tests {
             api_key: "sk-test123".to_string(),
             api_base_url: "https://api.mistral.ai".to_string(),
             timeout_seconds: 30,
-            max_file_size_mb: 101,
+            max_file_size_mb: MAX_FILE_SIZE_CEILING_MB + 1,
             log_level: "info".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
         assert!(config_high.validate().is_err());
     }
 
+    #[test]
+    fn test_validation_allowed_mime_types() {
+        let config = |allowed_mime_types: &[&str]| Config {
+            api_key: "sk-test123".to_string(),
+            allowed_mime_types: allowed_mime_types.iter().map(|t| t.to_string()).collect(),
+            ..Config::default()
+        };
+
+        assert!(config(&["application/pdf", "image/*"]).validate().is_ok());
+        assert!(config(&[]).validate().is_err());
+        assert!(config(&["tiff"]).validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_connect_timeout_range() {
+        let config = |connect_timeout_seconds| Config {
+            api_key: "sk-test123".to_string(),
+            connect_timeout_seconds,
+            ..Config::default()
+        };
+
+        assert!(config(0).validate().is_err());
+        assert!(config(5).validate().is_ok());
+        assert!(config(301).validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_max_concurrent_requests_range() {
+        let config = |max_concurrent_requests| Config {
+            api_key: "sk-test123".to_string(),
+            max_concurrent_requests,
+            ..Config::default()
+        };
+
+        assert!(config(0).validate().is_err());
+        assert!(config(1).validate().is_ok());
+        assert!(config(65).validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_memory_budget_range() {
+        let config = |memory_budget_mb| Config {
+            api_key: "sk-test123".to_string(),
+            memory_budget_mb,
+            ..Config::default()
+        };
+
+        assert!(config(None).validate().is_ok());
+        assert!(config(Some(8)).validate().is_err());
+        assert!(config(Some(192)).validate().is_ok());
+        assert!(config(Some(MEMORY_BUDGET_CEILING_MB + 1))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_validation_signed_url_expiry_range() {
+        let config = |signed_url_expiry_hours| Config {
+            api_key: "sk-test123".to_string(),
+            use_signed_url: true,
+            signed_url_expiry_hours,
+            ..Config::default()
+        };
+
+        assert!(config(0).validate().is_err());
+        assert!(config(24).validate().is_ok());
+        assert!(config(169).validate().is_err());
+    }
+
     #[test]
     fn test_validation_log_level() {
         let valid_levels = ["error", "warn", "info", "debug", "trace"];
@@ -429,6 +1672,7 @@ mod tests {
                 max_file_size_mb: 50,
                 log_level: level.to_string(),
                 retry_policy: RetryPolicy::default(),
+                ..Config::default()
             };
             assert!(
                 config.validate().is_ok(),
@@ -445,7 +1689,122 @@ mod tests {
             max_file_size_mb: 50,
             log_level: "invalid".to_string(),
             retry_policy: RetryPolicy::default(),
+            ..Config::default()
         };
         assert!(config_invalid.validate().is_err());
     }
+
+    #[test]
+    fn test_retry_next_delay_is_decorrelated() {
+        let policy = RetryPolicy {
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+            ..RetryPolicy::default()
+        };
+
+        let mut previous = Duration::from_millis(policy.base_delay_ms);
+        let mut delays = Vec::new();
+        for attempt in 1..=20 {
+            let delay = policy.next_delay(attempt, previous);
+            assert!(delay >= Duration::from_millis(100), "{:?}", delay);
+            assert!(delay <= previous.saturating_mul(3).min(Duration::from_millis(1000)));
+            delays.push(delay);
+            previous = delay;
+        }
+        // Random draws, not a fixed schedule
+        delays.dedup();
+        assert!(delays.len() > 1);
+    }
+
+    #[test]
+    fn test_validate_reports_all_invalid_fields() {
+        let config = Config {
+            api_key: "sk-test123456789".to_string(),
+            max_concurrent_requests: 0,
+            chat_model: String::new(),
+            retry_policy: RetryPolicy {
+                base_delay_ms: 0,
+                ..RetryPolicy::default()
+            },
+            ..Config::default()
+        };
+
+        let error = config.validate().unwrap_err();
+        assert_eq!(error.exit_code(), 4);
+        let fields: Vec<&str> = error
+            .field_errors()
+            .iter()
+            .map(|field| field.field.as_str())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "max_concurrent_requests",
+                "chat_model",
+                "retry_policy.base_delay_ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_budget_check() {
+        let unlimited = BudgetConfig::default();
+        assert!(unlimited.check(1000, 100_000).is_ok());
+
+        let pages = BudgetConfig {
+            max_pages: Some(100),
+            ..BudgetConfig::default()
+        };
+        assert!(pages.check(3, 99).is_ok());
+        assert!(matches!(pages.check(4, 100), Err(Error::BudgetExceeded(_))));
+
+        let cost = BudgetConfig {
+            max_cost: Some(0.5),
+            ..BudgetConfig::default()
+        };
+        assert!(cost.check(10, 499).is_ok());
+        let error = cost.check(11, 500).unwrap_err();
+        assert_eq!(error.exit_code(), 7);
+        assert!(error.to_string().contains("cost budget of $0.50 reached"));
+
+        assert!(BudgetConfig {
+            max_pages: Some(0),
+            ..BudgetConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_paperless_config_validation() {
+        let missing = PaperlessConfig::default();
+        let error = missing.validate("OCR2_").unwrap_err().to_string();
+        assert!(error.contains("OCR2_PAPERLESS_URL"), "{}", error);
+
+        let no_token = PaperlessConfig {
+            url: Some("http://paperless:8000".to_string()),
+            token: None,
+        };
+        let error = no_token.validate("OCR2_").unwrap_err().to_string();
+        assert!(error.contains("OCR2_PAPERLESS_TOKEN"), "{}", error);
+
+        let complete = PaperlessConfig {
+            url: Some("http://paperless:8000".to_string()),
+            token: Some("abc123".to_string()),
+        };
+        assert!(complete.validate(DEFAULT_ENV_PREFIX).is_ok());
+    }
+
+    #[test]
+    fn test_env_prefix_validation() {
+        for (prefix, valid) in [("OCR2_", true), ("", false), ("OCR-2_", false)] {
+            let config = Config {
+                env_prefix: prefix.to_string(),
+                providers: vec![ProviderKind::Mock],
+                ..Config::default()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "{:?}", prefix);
+        }
+        assert_eq!(Config::default().env_prefix, DEFAULT_ENV_PREFIX);
+    }
 }
@@ -0,0 +1,358 @@
+//! Sender (correspondent) detection
+//!
+//! Collects candidates for the sender of a document from three kinds of
+//! evidence: company names in the letterhead (the first lines of the text),
+//! the domains of e-mail addresses, and account owners printed next to an
+//! IBAN. Candidates naming the same organization are merged and ranked by
+//! score, so paperless-ngx automation can map the best one to a correspondent.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Lines at the start of the text treated as letterhead
+const LETTERHEAD_LINES: usize = 6;
+
+/// Legal form suffixes that mark a line as a company name
+const COMPANY_SUFFIXES: &[&str] = &[
+    "gmbh",
+    "ag",
+    "kg",
+    "ohg",
+    "ug",
+    "e.v.",
+    "inc",
+    "inc.",
+    "llc",
+    "ltd",
+    "ltd.",
+    "limited",
+    "corp",
+    "corp.",
+    "corporation",
+    "co.",
+    "company",
+    "plc",
+    "s.a.",
+    "sa",
+    "sarl",
+    "b.v.",
+    "bv",
+    "n.v.",
+    "gbr",
+    "mbh",
+];
+
+/// Mail providers whose domains say nothing about the sender
+const FREEMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "yahoo.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "icloud.com",
+    "me.com",
+    "aol.com",
+    "gmx.de",
+    "gmx.net",
+    "web.de",
+    "t-online.de",
+    "posteo.de",
+    "proton.me",
+    "protonmail.com",
+];
+
+lazy_static::lazy_static! {
+    /// E-mail address; the domain is captured
+    static ref EMAIL: Regex =
+        Regex::new(r"(?i)\b[a-z0-9._%+-]+@([a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,})\b")
+            .expect("valid regex");
+
+    /// IBAN with optional grouping spaces
+    static ref IBAN: Regex =
+        Regex::new(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){3,7}(?: ?[A-Z0-9]{1,3})?\b")
+            .expect("valid regex");
+
+    /// Account owner label followed by the name
+    static ref ACCOUNT_OWNER: Regex = Regex::new(
+        r"(?im)^\s*(?:account\s+(?:holder|owner|name)|beneficiary|payee|kontoinhaber(?:in)?|inhaber(?:in)?|empfänger(?:in)?|zahlungsempfänger)\s*:?\s+(.+?)\s*$"
+    )
+    .expect("valid regex");
+}
+
+/// Where a correspondent candidate was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorrespondentSource {
+    /// Company name in the first lines of the document
+    Letterhead,
+    /// Company name elsewhere in the document
+    Company,
+    /// Domain of an e-mail address
+    EmailDomain,
+    /// Account owner next to an IBAN
+    IbanOwner,
+}
+
+impl CorrespondentSource {
+    /// Base score of evidence from this source
+    fn score(self) -> f64 {
+        match self {
+            Self::Letterhead => 0.8,
+            Self::IbanOwner => 0.7,
+            Self::EmailDomain => 0.6,
+            Self::Company => 0.4,
+        }
+    }
+}
+
+/// A possible sender of the document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrespondentCandidate {
+    /// Name as written in the document (or derived from the e-mail domain)
+    pub name: String,
+
+    /// Confidence from 0.0 to 1.0; agreeing sources raise it
+    pub score: f64,
+
+    /// Evidence for the candidate
+    pub sources: Vec<CorrespondentSource>,
+}
+
+/// Detect and rank sender candidates in extracted text
+pub fn detect_correspondents(text: &str) -> Vec<CorrespondentCandidate> {
+    let mut evidence: Vec<(String, CorrespondentSource)> = Vec::new();
+
+    for (position, line) in text
+        .lines()
+        .map(clean_line)
+        .filter(|l| !l.is_empty())
+        .enumerate()
+    {
+        if is_company_name(&line) {
+            let source = if position < LETTERHEAD_LINES {
+                CorrespondentSource::Letterhead
+            } else {
+                CorrespondentSource::Company
+            };
+            evidence.push((line, source));
+        }
+    }
+
+    for captures in EMAIL.captures_iter(text) {
+        let domain = captures[1].to_lowercase();
+        if FREEMAIL_DOMAINS.contains(&domain.as_str()) {
+            continue;
+        }
+        if let Some(name) = organization_from_domain(&domain) {
+            evidence.push((name, CorrespondentSource::EmailDomain));
+        }
+    }
+
+    if IBAN.is_match(text) {
+        for captures in ACCOUNT_OWNER.captures_iter(text) {
+            let name = clean_line(&captures[1]);
+            if !name.is_empty() && !IBAN.is_match(&name) {
+                evidence.push((name, CorrespondentSource::IbanOwner));
+            }
+        }
+    }
+
+    let mut candidates: Vec<(String, CorrespondentCandidate)> = Vec::new();
+    for (name, source) in evidence {
+        let key = compact_key(&name);
+        if key.len() < 2 {
+            continue;
+        }
+
+        match candidates
+            .iter_mut()
+            .find(|(existing, _)| same_organization(existing, &key))
+        {
+            Some((_, candidate)) => {
+                if !candidate.sources.contains(&source) {
+                    candidate.sources.push(source);
+                }
+                // Prefer the name from the strongest evidence
+                if source.score() > candidate.sources[0].score() {
+                    candidate.name = name;
+                    candidate
+                        .sources
+                        .sort_by(|a, b| b.score().total_cmp(&a.score()));
+                }
+            }
+            None => candidates.push((
+                key,
+                CorrespondentCandidate {
+                    name,
+                    score: 0.0,
+                    sources: vec![source],
+                },
+            )),
+        }
+    }
+
+    let mut candidates: Vec<CorrespondentCandidate> = candidates
+        .into_iter()
+        .map(|(_, mut candidate)| {
+            // Each additional kind of evidence adds 0.1 to the strongest one
+            let strongest = candidate
+                .sources
+                .iter()
+                .map(|source| source.score())
+                .fold(0.0, f64::max);
+            let bonus = 0.1 * (candidate.sources.len() - 1) as f64;
+            candidate.score = ((strongest + bonus).min(1.0) * 100.0).round() / 100.0;
+            candidate
+        })
+        .collect();
+
+    // Stable sort keeps the document order among equal scores
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+/// Strip markdown decoration and surrounding punctuation from a line
+fn clean_line(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(['#', '*', '_', '>', '-', '|', ' '])
+        .trim_end_matches(['*', '_', '|', ' ', ',', ';', ':'])
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Whether a line looks like a company name with a legal form suffix
+fn is_company_name(line: &str) -> bool {
+    // Labelled values ("Account holder: ...") and addresses are not names
+    if line.contains([':', '@']) {
+        return false;
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if !(2..=8).contains(&words.len()) || line.chars().filter(char::is_ascii_digit).count() > 2 {
+        return false;
+    }
+
+    let starts_capitalized = words[0].chars().next().is_some_and(char::is_uppercase);
+    starts_capitalized
+        && words[1..].iter().any(|word| {
+            let word = word.trim_end_matches(',').to_lowercase();
+            COMPANY_SUFFIXES.contains(&word.as_str())
+        })
+}
+
+/// Organization name from the registrable part of a domain (billing.acme-corp.com -> Acme Corp)
+fn organization_from_domain(domain: &str) -> Option<String> {
+    let labels: Vec<&str> = domain.split('.').collect();
+    // Second-level label, skipping generic second levels such as co.uk
+    let label = match labels.as_slice() {
+        [.., label, "co" | "com" | "org", _] if labels.len() >= 3 => label,
+        [.., label, _] => label,
+        _ => return None,
+    };
+
+    let name: Vec<String> = label
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    (!name.is_empty()).then(|| name.join(" "))
+}
+
+/// Lowercase alphanumerics of a name without legal form suffixes
+fn compact_key(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| word.trim_end_matches(',').to_lowercase())
+        .filter(|word| !COMPANY_SUFFIXES.contains(&word.as_str()))
+        .flat_map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<Vec<char>>()
+        })
+        .collect()
+}
+
+/// Whether two compact keys name the same organization (acme == acmecorp)
+fn same_organization(a: &str, b: &str) -> bool {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    shorter == longer || (shorter.len() >= 4 && longer.starts_with(shorter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letterhead_email_and_iban_evidence_merge() {
+        let text = "# ACME Corporation\n\
+                    Main Street 1, Springfield\n\
+                    billing@acme.com\n\n\
+                    Invoice 42\n\n\
+                    Please pay to:\n\
+                    Account holder: ACME Corp.\n\
+                    IBAN: DE89 3704 0044 0532 0130 00\n\
+                    Contact: jane.doe@gmail.com\n";
+
+        let candidates = detect_correspondents(text);
+        assert_eq!(candidates.len(), 1, "{:?}", candidates);
+        assert_eq!(candidates[0].name, "ACME Corporation");
+        assert_eq!(
+            candidates[0].sources,
+            [
+                CorrespondentSource::Letterhead,
+                CorrespondentSource::EmailDomain,
+                CorrespondentSource::IbanOwner
+            ]
+        );
+        assert_eq!(candidates[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_candidates_are_ranked() {
+        let text = "Stadtwerke Musterstadt GmbH\n\
+                    Kundenservice\n\n\
+                    Sehr geehrte Damen und Herren,\n\
+                    line 2\nline 3\nline 4\nline 5\n\
+                    Ihr Vertrag mit Energie Nord AG\n\
+                    Fragen an service@vertrieb.example-energie.de\n";
+
+        let candidates = detect_correspondents(text);
+        let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Stadtwerke Musterstadt GmbH",
+                "Example Energie",
+                "Ihr Vertrag mit Energie Nord AG"
+            ]
+        );
+        assert_eq!(candidates[2].sources, [CorrespondentSource::Company]);
+    }
+
+    #[test]
+    fn test_organization_from_domain() {
+        assert_eq!(
+            organization_from_domain("billing.acme-corp.com").as_deref(),
+            Some("Acme Corp")
+        );
+        assert_eq!(
+            organization_from_domain("example.co.uk").as_deref(),
+            Some("Example")
+        );
+        assert_eq!(organization_from_domain("localhost"), None);
+    }
+
+    #[test]
+    fn test_iban_owner_requires_iban() {
+        let text = "Payee: Jane Doe\nNo bank details here";
+        assert!(detect_correspondents(text).is_empty());
+    }
+}
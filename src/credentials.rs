@@ -12,6 +12,10 @@ pub struct APICredentials {
     /// Mistral AI API base URL
     pub api_base_url: String,
 
+    /// Whether a plain HTTP base URL is allowed when it points to this machine
+    #[serde(default)]
+    pub allow_insecure_loopback: bool,
+
     /// Whether credentials are valid
     pub is_valid: bool,
 }
@@ -19,9 +23,24 @@ pub struct APICredentials {
 impl APICredentials {
     /// Create new API credentials
     pub fn new(api_key: String, api_base_url: String) -> Result<Self> {
+        Self::with_loopback_policy(api_key, api_base_url, false)
+    }
+
+    /// Create API credentials that also accept plain HTTP to this machine,
+    /// such as a local mock or the fixture server of --record/--replay
+    pub fn new_insecure_loopback(api_key: String, api_base_url: String) -> Result<Self> {
+        Self::with_loopback_policy(api_key, api_base_url, true)
+    }
+
+    fn with_loopback_policy(
+        api_key: String,
+        api_base_url: String,
+        allow_insecure_loopback: bool,
+    ) -> Result<Self> {
         let mut credentials = Self {
             api_key,
             api_base_url,
+            allow_insecure_loopback,
             is_valid: false,
         };
 
@@ -48,14 +67,29 @@ impl APICredentials {
         let url = Url::parse(&self.api_base_url)
             .map_err(|_| Error::Config("API base URL must be a valid URL".to_string()))?;
 
-        // Ensure it's HTTPS
+        // Ensure it's HTTPS; plain HTTP to this machine needs an explicit opt-in
         if url.scheme() != "https" {
-            return Err(Error::Config("API base URL must use HTTPS".to_string()));
+            if !is_loopback(&url) {
+                return Err(Error::Config("API base URL must use HTTPS".to_string()));
+            }
+            if !self.allow_insecure_loopback {
+                return Err(Error::Config(
+                    "API base URL must use HTTPS; set allow_insecure_loopback to use plain HTTP on this machine"
+                        .to_string(),
+                ));
+            }
+        }
+
+        #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+        if url.scheme() == "https" {
+            return Err(Error::Config(
+                "HTTPS API URLs need a build with the native-tls or rustls feature".to_string(),
+            ));
         }
 
         // Validate it points to Mistral AI API
         if let Some(host) = url.host_str() {
-            if !host.contains("mistral") && !host.contains("api.mistral.ai") {
+            if !host.contains("mistral") && !is_loopback(&url) {
                 tracing::warn!("API base URL does not appear to be Mistral AI: {}", host);
             }
         }
@@ -84,6 +118,20 @@ impl APICredentials {
 
     /// Create credentials from configuration
     pub fn from_config(config: &crate::Config) -> Result<Self> {
-        Self::new(config.api_key.clone(), config.api_base_url.clone())
+        Self::with_loopback_policy(
+            config.api_key.clone(),
+            config.api_base_url.clone(),
+            config.allow_insecure_loopback,
+        )
+    }
+}
+
+/// Whether a URL points to the local machine
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(host)) => host == "localhost",
+        Some(url::Host::Ipv4(address)) => address.is_loopback(),
+        Some(url::Host::Ipv6(address)) => address.is_loopback(),
+        None => false,
     }
 }
@@ -0,0 +1,354 @@
+//! Document date detection and normalization
+//!
+//! Scans extracted text for date candidates in the common numeric and written
+//! formats (ISO, day-first, month-first, English and German month names), scores
+//! them by context and position, and returns the most plausible document date.
+
+use crate::error::{Error, Result};
+use chrono::{Datelike, NaiveDate, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Order of day, month and year in ambiguous numeric dates (e.g. 03/04/2024)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DateOrder {
+    /// Day-month-year (most of Europe)
+    #[default]
+    Dmy,
+    /// Month-day-year (United States)
+    Mdy,
+    /// Year-month-day
+    Ymd,
+}
+
+impl FromStr for DateOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "DMY" => Ok(Self::Dmy),
+            "MDY" => Ok(Self::Mdy),
+            "YMD" => Ok(Self::Ymd),
+            other => Err(Error::Config(format!(
+                "Unknown date order: {}. Supported: DMY, MDY, YMD",
+                other
+            ))),
+        }
+    }
+}
+
+/// Words that mark the following date as the document date
+const DOCUMENT_DATE_HINTS: &[&str] = &[
+    "date",
+    "dated",
+    "datum",
+    "invoice date",
+    "rechnungsdatum",
+    "issued",
+    "ausgestellt",
+    "belegdatum",
+];
+
+/// Words that mark the following date as something other than the document date
+const OTHER_DATE_HINTS: &[&str] = &[
+    "due",
+    "fällig",
+    "zahlbar bis",
+    "birth",
+    "geboren",
+    "geb.",
+    "valid until",
+    "gültig bis",
+    "delivery",
+    "lieferdatum",
+];
+
+/// Month names (English and German, full and abbreviated) with their number
+const MONTH_NAMES: &[(&str, u32)] = &[
+    ("january", 1),
+    ("januar", 1),
+    ("jan", 1),
+    ("jänner", 1),
+    ("february", 2),
+    ("februar", 2),
+    ("feb", 2),
+    ("march", 3),
+    ("märz", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("may", 5),
+    ("mai", 5),
+    ("june", 6),
+    ("juni", 6),
+    ("jun", 6),
+    ("july", 7),
+    ("juli", 7),
+    ("jul", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sept", 9),
+    ("sep", 9),
+    ("october", 10),
+    ("oktober", 10),
+    ("oct", 10),
+    ("okt", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("december", 12),
+    ("dezember", 12),
+    ("dec", 12),
+    ("dez", 12),
+];
+
+lazy_static::lazy_static! {
+    /// ISO 8601: 2024-02-01
+    static ref ISO_DATE: Regex = Regex::new(r"\b(\d{4})-(\d{1,2})-(\d{1,2})\b").expect("valid regex");
+
+    /// Numeric with separators: 01.02.2024, 01/02/24, 2024/02/01
+    static ref NUMERIC_DATE: Regex =
+        Regex::new(r"\b(\d{1,4})[./](\d{1,2})[./](\d{2,4})\b").expect("valid regex");
+
+    /// Written day-first: 1 February 2024, 1. Februar 2024
+    static ref DAY_FIRST_DATE: Regex =
+        Regex::new(r"\b(\d{1,2})\.?\s+([a-zä]{3,9})\.?,?\s+(\d{4})\b").expect("valid regex");
+
+    /// Written month-first: February 1, 2024
+    static ref MONTH_FIRST_DATE: Regex =
+        Regex::new(r"\b([a-zä]{3,9})\.?\s+(\d{1,2})(?:st|nd|rd|th)?,?\s+(\d{4})\b")
+            .expect("valid regex");
+}
+
+/// A date found in the text together with its byte position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateCandidate {
+    pub date: NaiveDate,
+    pub position: usize,
+}
+
+/// Detect the most plausible document date in extracted text
+pub fn detect_document_date(text: &str, order: DateOrder) -> Option<NaiveDate> {
+    let today = Utc::now().date_naive();
+    let lowercase = text.to_lowercase();
+
+    find_date_candidates(text, order)
+        .into_iter()
+        .filter(|candidate| candidate.date.year() >= 1900 && candidate.date <= today)
+        .map(|candidate| {
+            let score = score_candidate(&lowercase, &candidate, lowercase.len());
+            (score, candidate)
+        })
+        // Highest score wins; earlier position breaks ties
+        .max_by(|(score_a, a), (score_b, b)| {
+            score_a
+                .total_cmp(score_b)
+                .then_with(|| b.position.cmp(&a.position))
+        })
+        .map(|(_, candidate)| candidate.date)
+}
+
+/// Find all parseable dates in the text
+pub fn find_date_candidates(text: &str, order: DateOrder) -> Vec<DateCandidate> {
+    let lowercase = text.to_lowercase();
+    let mut candidates = Vec::new();
+
+    for caps in ISO_DATE.captures_iter(&lowercase) {
+        if let Some(date) = ymd(&caps[1], &caps[2], &caps[3]) {
+            candidates.push(DateCandidate {
+                date,
+                position: caps.get(0).map_or(0, |m| m.start()),
+            });
+        }
+    }
+
+    for caps in NUMERIC_DATE.captures_iter(&lowercase) {
+        let (a, b, c) = (&caps[1], &caps[2], &caps[3]);
+        let date = if a.len() == 4 {
+            ymd(a, b, c)
+        } else {
+            numeric_date(a, b, c, order)
+        };
+        if let Some(date) = date {
+            candidates.push(DateCandidate {
+                date,
+                position: caps.get(0).map_or(0, |m| m.start()),
+            });
+        }
+    }
+
+    for caps in DAY_FIRST_DATE.captures_iter(&lowercase) {
+        if let Some(month) = month_number(&caps[2]) {
+            if let Some(date) = build_date(&caps[3], month, &caps[1]) {
+                candidates.push(DateCandidate {
+                    date,
+                    position: caps.get(0).map_or(0, |m| m.start()),
+                });
+            }
+        }
+    }
+
+    for caps in MONTH_FIRST_DATE.captures_iter(&lowercase) {
+        if let Some(month) = month_number(&caps[1]) {
+            if let Some(date) = build_date(&caps[3], month, &caps[2]) {
+                candidates.push(DateCandidate {
+                    date,
+                    position: caps.get(0).map_or(0, |m| m.start()),
+                });
+            }
+        }
+    }
+
+    candidates.sort_by_key(|candidate| candidate.position);
+    candidates.dedup();
+    candidates
+}
+
+/// Score a candidate by surrounding context and position in the document
+fn score_candidate(lowercase: &str, candidate: &DateCandidate, text_len: usize) -> f64 {
+    let context_start = floor_char_boundary(lowercase, candidate.position.saturating_sub(30));
+    let context = &lowercase[context_start..candidate.position];
+
+    let mut score = 0.0;
+
+    if DOCUMENT_DATE_HINTS
+        .iter()
+        .any(|hint| context.contains(hint))
+    {
+        score += 2.0;
+    }
+
+    if OTHER_DATE_HINTS.iter().any(|hint| context.contains(hint)) {
+        score -= 3.0;
+    }
+
+    // Letters and invoices carry their date near the top
+    if text_len > 0 {
+        score += 1.0 - candidate.position as f64 / text_len as f64;
+    }
+
+    score
+}
+
+/// Interpret an ambiguous numeric date using the configured order
+fn numeric_date(a: &str, b: &str, c: &str, order: DateOrder) -> Option<NaiveDate> {
+    let (first, second) = (a.parse::<u32>().ok()?, b.parse::<u32>().ok()?);
+
+    // A value above 12 can only be the day, whatever the configured order
+    match order {
+        DateOrder::Ymd => build_date(a, second, c),
+        DateOrder::Mdy if first <= 12 => build_date(c, first, b),
+        DateOrder::Mdy => build_date(c, second, a),
+        DateOrder::Dmy if second <= 12 => build_date(c, second, a),
+        DateOrder::Dmy => build_date(c, first, b),
+    }
+}
+
+/// Build a date from year/month/day strings
+fn ymd(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    build_date(year, month.parse().ok()?, day)
+}
+
+/// Build a date, expanding two-digit years to the closest past century
+fn build_date(year: &str, month: u32, day: &str) -> Option<NaiveDate> {
+    let day = day.parse::<u32>().ok()?;
+    let mut year = year.parse::<i32>().ok()?;
+
+    if year < 100 {
+        let current = Utc::now().year() % 100;
+        year += if year <= current + 1 { 2000 } else { 1900 };
+    }
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Look up a month by (possibly abbreviated) English or German name
+fn month_number(name: &str) -> Option<u32> {
+    MONTH_NAMES
+        .iter()
+        .find(|(month, _)| *month == name)
+        .map(|(_, number)| *number)
+}
+
+/// Largest char boundary at or below the given byte index
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while index > 0 && !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_formats_are_normalized() {
+        let cases = [
+            ("Issued 2024-02-01", date(2024, 2, 1)),
+            ("Datum: 01.02.2024", date(2024, 2, 1)),
+            ("Berlin, 1. Februar 2024", date(2024, 2, 1)),
+            ("London, 1 February 2024", date(2024, 2, 1)),
+            ("February 1st, 2024", date(2024, 2, 1)),
+            ("Date: 01/02/24", date(2024, 2, 1)),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(
+                detect_document_date(text, DateOrder::Dmy),
+                Some(expected),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_date_order_for_ambiguous_dates() {
+        assert_eq!(
+            detect_document_date("03/04/2024", DateOrder::Mdy),
+            Some(date(2024, 3, 4))
+        );
+        assert_eq!(
+            detect_document_date("03/04/2024", DateOrder::Dmy),
+            Some(date(2024, 4, 3))
+        );
+        // 25 cannot be a month, so it is the day in either order
+        assert_eq!(
+            detect_document_date("12/25/2023", DateOrder::Dmy),
+            Some(date(2023, 12, 25))
+        );
+    }
+
+    #[test]
+    fn test_context_prefers_document_date_over_due_date() {
+        let text = "Payment due 15.03.2024\nInvoice date: 01.03.2024\nTotal 42,00 EUR";
+        assert_eq!(
+            detect_document_date(text, DateOrder::Dmy),
+            Some(date(2024, 3, 1))
+        );
+    }
+
+    #[test]
+    fn test_implausible_dates_are_ignored() {
+        assert_eq!(detect_document_date("31.02.2024", DateOrder::Dmy), None);
+        assert_eq!(
+            detect_document_date("Valid 01.01.2999", DateOrder::Dmy),
+            None
+        );
+        assert_eq!(detect_document_date("No dates here", DateOrder::Dmy), None);
+    }
+
+    #[test]
+    fn test_date_order_parsing() {
+        assert_eq!("dmy".parse::<DateOrder>().unwrap(), DateOrder::Dmy);
+        assert_eq!("MDY".parse::<DateOrder>().unwrap(), DateOrder::Mdy);
+        assert!("XYZ".parse::<DateOrder>().is_err());
+    }
+}
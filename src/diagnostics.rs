@@ -0,0 +1,181 @@
+//! Runtime diagnostics for long runs
+//!
+//! Multi-file commands (`eval`, `export`, `compare`) listen for SIGUSR1 and
+//! dump the current API metrics, cache statistics, and the files being
+//! processed to stderr, so a stuck run can be inspected without restarting:
+//!
+//! ```text
+//! kill -USR1 <pid>
+//! ```
+
+use crate::cache::CacheManager;
+use crate::metrics::MetricsCollector;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Files currently being processed
+#[derive(Debug, Default)]
+pub struct InFlightFiles {
+    next_id: AtomicU64,
+    files: Mutex<BTreeMap<u64, (String, Instant)>>,
+}
+
+impl InFlightFiles {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a file; it is removed when the returned guard is dropped
+    pub fn track(&self, file: &str) -> InFlightGuard<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut files) = self.files.lock() {
+            files.insert(id, (file.to_string(), Instant::now()));
+        }
+        InFlightGuard { registry: self, id }
+    }
+
+    /// Files in flight with the seconds since they started, oldest first
+    pub fn snapshot(&self) -> Vec<(String, u64)> {
+        self.files
+            .lock()
+            .map(|files| {
+                files
+                    .values()
+                    .map(|(file, started)| (file.clone(), started.elapsed().as_secs()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Removes a file from the in-flight registry when dropped
+#[derive(Debug)]
+pub struct InFlightGuard<'a> {
+    registry: &'a InFlightFiles,
+    id: u64,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut files) = self.registry.files.lock() {
+            files.remove(&self.id);
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Global registry of files being processed
+    pub static ref GLOBAL_IN_FLIGHT: InFlightFiles = InFlightFiles::new();
+}
+
+/// Build the diagnostics report for the run's metrics and cache
+pub async fn report(metrics: &MetricsCollector, cache: &CacheManager) -> String {
+    let metrics = metrics.get_metrics_json().await;
+    let cache = cache.get_stats().await;
+    let in_flight = GLOBAL_IN_FLIGHT.snapshot();
+
+    let mut lines = vec![
+        "=== paperless-ngx-ocr2 diagnostics ===".to_string(),
+        format!("API metrics: {}", metrics),
+        format!(
+            "Cache: {} upload entries ({} expired), {} OCR entries ({} expired), ~{} bytes",
+            cache.file_upload_cache.active_entries,
+            cache.file_upload_cache.expired_entries,
+            cache.ocr_result_cache.active_entries,
+            cache.ocr_result_cache.expired_entries,
+            cache.total_estimated_size_bytes
+        ),
+        format!("In-flight files: {}", in_flight.len()),
+    ];
+    for (file, seconds) in in_flight {
+        lines.push(format!("  {} ({}s)", file, seconds));
+    }
+
+    lines.join("\n")
+}
+
+/// Aborts the signal listener when dropped
+#[derive(Debug, Default)]
+pub struct SignalListener {
+    handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl SignalListener {
+    /// Run `listener` in the background until dropped
+    pub(crate) fn spawn(listener: impl std::future::Future<Output = ()> + Send + 'static) -> Self {
+        Self {
+            handle: Some(tokio::spawn(listener)),
+        }
+    }
+}
+
+impl Drop for SignalListener {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// Dump diagnostics of the given metrics and cache to stderr on every SIGUSR1
+/// until the listener is dropped
+///
+/// Must be called from within a Tokio runtime. On non-Unix platforms this is a
+/// no-op.
+pub fn listen_for_dump_signal(metrics: MetricsCollector, cache: CacheManager) -> SignalListener {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::user_defined1()) {
+            Ok(mut stream) => {
+                let handle = tokio::spawn(async move {
+                    while stream.recv().await.is_some() {
+                        let report = report(&metrics, &cache).await;
+                        let mut stderr = std::io::stderr().lock();
+                        let _ = writeln!(stderr, "{}", report);
+                    }
+                });
+                return SignalListener {
+                    handle: Some(handle),
+                };
+            }
+            Err(e) => tracing::warn!("Failed to install SIGUSR1 handler: {}", e),
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (metrics, cache);
+
+    SignalListener { handle: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_flight_guard_removes_file() {
+        let registry = InFlightFiles::new();
+        let first = registry.track("a.pdf");
+        {
+            let _second = registry.track("b.pdf");
+            let files: Vec<String> = registry.snapshot().into_iter().map(|(f, _)| f).collect();
+            assert_eq!(files, ["a.pdf", "b.pdf"]);
+        }
+        assert_eq!(registry.snapshot().len(), 1);
+        drop(first);
+        assert!(registry.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_report_lists_in_flight_files() {
+        let _guard = GLOBAL_IN_FLIGHT.track("stuck.pdf");
+        let report = report(&MetricsCollector::new(), &CacheManager::new()).await;
+        assert!(report.contains("API metrics:"));
+        assert!(report.contains("stuck.pdf"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Output encryption with age
+//!
+//! With `--encrypt-output age1...` (or `[encryption] recipients` in the
+//! configuration), the text printed to stdout and sidecar files are encrypted
+//! for the given recipients with the `age` command-line tool, so results of
+//! sensitive documents can be written to shared storage:
+//!
+//! ```toml
+//! [encryption]
+//! command = "age"
+//! recipients = ["age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"]
+//! ```
+//!
+//! Stdout is ASCII-armored; sidecars are binary and get an `.age` suffix.
+
+use crate::error::{Error, FieldErrors, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// Settings for encrypting outputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// `age` executable
+    #[serde(default = "default_age_command")]
+    pub command: String,
+
+    /// age recipients (`age1...` or SSH public keys); empty disables encryption
+    #[serde(default)]
+    pub recipients: Vec<String>,
+}
+
+fn default_age_command() -> String {
+    "age".to_string()
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            command: default_age_command(),
+            recipients: Vec::new(),
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// Whether outputs are encrypted
+    pub fn is_enabled(&self) -> bool {
+        !self.recipients.is_empty()
+    }
+
+    /// Validate the recipients
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        for recipient in &self.recipients {
+            let valid = recipient.starts_with("age1")
+                || recipient.starts_with("ssh-ed25519 ")
+                || recipient.starts_with("ssh-rsa ");
+            if !valid {
+                errors.add(
+                    "recipients",
+                    format!(
+                        "Invalid age recipient: '{}'. Expected an age1... or SSH public key",
+                        recipient
+                    ),
+                );
+            }
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// Encrypt data for the configured recipients
+pub async fn encrypt(data: &[u8], encryption: &EncryptionConfig, armor: bool) -> Result<Vec<u8>> {
+    let mut command = tokio::process::Command::new(&encryption.command);
+    for recipient in &encryption.recipients {
+        command.args(["-r", recipient]);
+    }
+    if armor {
+        command.arg("-a");
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            Error::Config(format!(
+                "Failed to run age command '{}': {}",
+                encryption.command, e
+            ))
+        })?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| Error::internal("age stdin is not available".to_string()))?;
+    // Write concurrently with reading so large outputs cannot fill the pipes
+    let input = data.to_vec();
+    let writer = tokio::spawn(async move {
+        stdin.write_all(&input).await?;
+        stdin.shutdown().await
+    });
+
+    let output = child.wait_with_output().await.map_err(Error::Io)?;
+    writer
+        .await
+        .map_err(|e| Error::internal_from("age input task failed", e))?
+        .map_err(Error::Io)?;
+
+    if !output.status.success() {
+        return Err(Error::api(format!(
+            "age exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recipient_validation() {
+        let encryption = |recipient: &str| EncryptionConfig {
+            recipients: vec![recipient.to_string()],
+            ..EncryptionConfig::default()
+        };
+
+        assert!(!EncryptionConfig::default().is_enabled());
+        assert!(
+            encryption("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            encryption("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI user@host")
+                .validate()
+                .is_ok()
+        );
+        assert!(encryption("AGE-SECRET-KEY-1ABC").validate().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_encrypt_pipes_data_through_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        // Stand-in for age that echoes its arguments and input
+        let command = dir.path().join("age");
+        std::fs::write(&command, "#!/bin/sh\necho \"$@\"\ncat\n").unwrap();
+        std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let encryption = EncryptionConfig {
+            command: command.to_string_lossy().to_string(),
+            recipients: vec!["age1abc".to_string(), "age1def".to_string()],
+        };
+        let output = encrypt(b"secret text", &encryption, true).await.unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "-r age1abc -r age1def -a\nsecret text"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Experimental ensemble merging of provider outputs
+//!
+//! With `merge_strategy = "ensemble"` every configured provider processes the
+//! document instead of only the first one that succeeds. The outputs are
+//! aligned by page index and each page is taken from the provider whose text
+//! most others agree with (ignoring case and whitespace); when no two
+//! providers agree, the page with the highest quality score wins.
+
+use crate::api::ocr::{OCRResponse, Page, UsageInfo};
+use crate::error::{Error, Result};
+use crate::provider::ProviderKind;
+use crate::quality::QualityScore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// How the outputs of several providers are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Use the first provider that succeeds
+    #[default]
+    Fallback,
+    /// Run every provider and pick the best text per page
+    Ensemble,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "fallback" => Ok(Self::Fallback),
+            "ensemble" => Ok(Self::Ensemble),
+            other => Err(Error::Config(format!(
+                "Unknown merge strategy: {}. Supported: fallback, ensemble",
+                other
+            ))),
+        }
+    }
+}
+
+/// Merged response and the provider each page was taken from
+#[derive(Debug, Clone)]
+pub struct MergedResponse {
+    /// Response with one page per page index of any provider
+    pub response: OCRResponse,
+
+    /// Provider of each page, in page order
+    pub sources: Vec<ProviderKind>,
+}
+
+impl MergedResponse {
+    /// Provider that contributed the most pages (the earlier one on ties)
+    pub fn primary_source(&self) -> Option<ProviderKind> {
+        let mut counts: Vec<(ProviderKind, usize)> = Vec::new();
+        for source in &self.sources {
+            match counts.iter_mut().find(|(provider, _)| provider == source) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*source, 1)),
+            }
+        }
+
+        counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(provider, _)| *provider)
+    }
+}
+
+/// Merge provider responses page by page; `outputs` are in provider order
+pub fn merge_responses(outputs: &[(ProviderKind, &OCRResponse)]) -> MergedResponse {
+    let mut candidates: BTreeMap<i32, Vec<(ProviderKind, &Page)>> = BTreeMap::new();
+    for (provider, response) in outputs {
+        for page in &response.pages {
+            candidates
+                .entry(page.index)
+                .or_default()
+                .push((*provider, page));
+        }
+    }
+
+    let mut pages = Vec::with_capacity(candidates.len());
+    let mut sources = Vec::with_capacity(candidates.len());
+    for (index, candidates) in candidates {
+        let (provider, page) = pick_page(&candidates);
+        tracing::debug!("Ensemble page {} taken from {}", index + 1, provider);
+        pages.push(page.clone());
+        sources.push(provider);
+    }
+
+    let models: Vec<&str> = outputs
+        .iter()
+        .map(|(_, response)| response.model.as_str())
+        .collect();
+    let response = OCRResponse {
+        usage_info: UsageInfo {
+            pages_processed: pages.len() as i32,
+            doc_size_bytes: outputs
+                .iter()
+                .map(|(_, response)| response.usage_info.doc_size_bytes)
+                .max()
+                .unwrap_or(0),
+        },
+        pages,
+        model: format!("ensemble({})", models.join(",")),
+        document_annotation: outputs
+            .iter()
+            .find_map(|(_, response)| response.document_annotation.clone()),
+    };
+
+    MergedResponse { response, sources }
+}
+
+/// Pick the candidate with the most agreement, then the highest quality
+fn pick_page<'a>(candidates: &[(ProviderKind, &'a Page)]) -> (ProviderKind, &'a Page) {
+    let normalized: Vec<String> = candidates
+        .iter()
+        .map(|(_, page)| normalize(&page.markdown))
+        .collect();
+
+    let mut best = 0;
+    let mut best_key = (0, f64::MIN);
+    for (position, (_, page)) in candidates.iter().enumerate() {
+        let agreement = normalized
+            .iter()
+            .filter(|text| !text.is_empty() && **text == normalized[position])
+            .count();
+        let quality = QualityScore::from_text(&page.markdown).map_or(0.0, |q| q.score);
+        // Strictly greater keeps the earlier provider on ties
+        if (agreement, quality) > best_key {
+            best = position;
+            best_key = (agreement, quality);
+        }
+    }
+
+    candidates[best]
+}
+
+/// Lowercase and collapse whitespace so formatting differences still agree
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ocr::Dimensions;
+
+    fn response(model: &str, texts: &[&str]) -> OCRResponse {
+        OCRResponse {
+            pages: texts
+                .iter()
+                .enumerate()
+                .map(|(index, text)| Page {
+                    index: index as i32,
+                    markdown: text.to_string(),
+                    images: Vec::new(),
+                    dimensions: Dimensions {
+                        dpi: 0,
+                        height: 0,
+                        width: 0,
+                    },
+                })
+                .collect(),
+            model: model.to_string(),
+            document_annotation: None,
+            usage_info: UsageInfo {
+                pages_processed: texts.len() as i32,
+                doc_size_bytes: 100,
+            },
+        }
+    }
+
+    #[test]
+    fn test_majority_wins() {
+        let mistral = response("mistral-ocr-latest", &["Invoice  number 42"]);
+        let tesseract = response("tesseract", &["invoice number 42"]);
+        let mock = response("mock", &["Invoice number 42 with extra clean words"]);
+
+        let merged = merge_responses(&[
+            (ProviderKind::Mock, &mock),
+            (ProviderKind::Mistral, &mistral),
+            (ProviderKind::Tesseract, &tesseract),
+        ]);
+
+        assert_eq!(merged.sources, [ProviderKind::Mistral]);
+        assert_eq!(merged.response.pages[0].markdown, "Invoice  number 42");
+        assert_eq!(
+            merged.response.model,
+            "ensemble(mock,mistral-ocr-latest,tesseract)"
+        );
+    }
+
+    #[test]
+    fn test_quality_decides_without_majority() {
+        let mistral = response(
+            "mistral-ocr-latest",
+            &["The contract starts in March", "xq7 #@ zzk"],
+        );
+        let tesseract = response("tesseract", &["Th3 c0ntr@ct st#rts", "Payment is due"]);
+
+        let merged = merge_responses(&[
+            (ProviderKind::Mistral, &mistral),
+            (ProviderKind::Tesseract, &tesseract),
+        ]);
+
+        assert_eq!(
+            merged.sources,
+            [ProviderKind::Mistral, ProviderKind::Tesseract]
+        );
+        assert_eq!(merged.response.pages[1].markdown, "Payment is due");
+        assert_eq!(merged.primary_source(), Some(ProviderKind::Mistral));
+    }
+
+    #[test]
+    fn test_pages_missing_from_one_provider() {
+        let mistral = response("mistral-ocr-latest", &["First page"]);
+        let tesseract = response("tesseract", &["First page", "Second page"]);
+
+        let merged = merge_responses(&[
+            (ProviderKind::Mistral, &mistral),
+            (ProviderKind::Tesseract, &tesseract),
+        ]);
+
+        assert_eq!(merged.response.pages.len(), 2);
+        assert_eq!(merged.response.usage_info.pages_processed, 2);
+        assert_eq!(
+            merged.sources,
+            [ProviderKind::Mistral, ProviderKind::Tesseract]
+        );
+    }
+
+    #[test]
+    fn test_parse_merge_strategy() {
+        assert_eq!(
+            "Ensemble".parse::<MergeStrategy>().unwrap(),
+            MergeStrategy::Ensemble
+        );
+        assert_eq!(MergeStrategy::default(), MergeStrategy::Fallback);
+        assert!("vote".parse::<MergeStrategy>().is_err());
+    }
+}
@@ -1,13 +1,25 @@
 //! Error types and handling
 
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error as _;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Underlying error wrapped by [`Error::Validation`], [`Error::Api`], or
+/// [`Error::Internal`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Validation error: {0}")]
-    Validation(String),
+    /// Invalid input; use [`Error::validation`] or [`Error::validation_from`]
+    #[error("Validation error: {}", describe_source(message, source))]
+    Validation {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -15,69 +27,710 @@ pub enum Error {
     #[error("Configuration error: {0}")]
     Config(String),
 
-    #[error("API error: {0}")]
-    Api(String),
+    /// A failed or unusable API call; use [`Error::api`] or [`Error::api_from`]
+    #[error("API error: {}", describe_source(message, source))]
+    Api {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
 
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
-    #[error("Internal error: {0}")]
-    Internal(String),
+    /// An API response with an error status; use [`Error::from_http_status`]
+    /// or [`Error::http`]
+    #[error("{}", describe_http(*status, message, request_id.as_deref()))]
+    Http {
+        status: u16,
+        message: String,
+        /// Provider request ID of the failed call
+        request_id: Option<String>,
+    },
+
+    /// An unexpected failure; use [`Error::internal`] or [`Error::internal_from`]
+    #[error("Internal error: {}", describe_source(message, source))]
+    Internal {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("Insufficient text: {0}")]
+    InsufficientText(String),
+
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    /// Invalid configuration settings, reported together
+    #[error("Configuration error: {}", describe_fields(.0))]
+    InvalidConfig(Vec<FieldError>),
+
+    /// Invalid command-line arguments, reported together
+    #[error("Validation error: {}", describe_fields(.0))]
+    InvalidArguments(Vec<FieldError>),
+
+    /// An API response that could not be deserialized; the source names the
+    /// path of the offending field
+    #[error("API error: {context}")]
+    Parse {
+        context: String,
+        #[source]
+        source: serde_path_to_error::Error<serde_json::Error>,
+    },
+}
+
+/// A stable process exit code of the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ExitCodeInfo {
+    /// Process exit status
+    pub code: i32,
+
+    /// Error types (`error.type` in JSON output) reported with this code
+    pub error_types: &'static [&'static str],
+
+    /// What the code means
+    pub description: &'static str,
+
+    /// Typical reasons for the code
+    pub causes: &'static [&'static str],
+
+    /// Steps that usually resolve it; `{env_prefix}` stands for the prefix
+    /// of the environment variables
+    pub remediation: &'static [&'static str],
+}
+
+/// Every exit code the CLI uses; wrapper scripts can rely on these
+pub const EXIT_CODES: &[ExitCodeInfo] = &[
+    ExitCodeInfo {
+        code: 0,
+        error_types: &[],
+        description: "Success (including warnings for empty text)",
+        causes: &["The document was processed; a warning is logged when no text was found"],
+        remediation: &[],
+    },
+    ExitCodeInfo {
+        code: 2,
+        error_types: &["validation"],
+        description: "Validation error (invalid file format or size, invalid arguments)",
+        causes: &[
+            "The file is not a PDF, PNG, or JPEG, or its content does not match its extension",
+            "The file is larger than max_file_size_mb",
+            "A required argument such as --file is missing or a value is malformed",
+            "The API rejected the request (HTTP 4xx other than rate limits)",
+        ],
+        remediation: &[
+            "Check the file type with `file <path>` and convert unsupported formats",
+            "Raise max_file_size_mb or split the document",
+            "Run with --json to get the invalid arguments in error.fields",
+        ],
+    },
+    ExitCodeInfo {
+        code: 3,
+        error_types: &["file_io"],
+        description: "I/O error (file not found, permission denied)",
+        causes: &[
+            "The input file does not exist or is not readable",
+            "A sidecar, state file, or cache entry could not be written",
+        ],
+        remediation: &[
+            "Check the path and the permissions of the file and its directory",
+            "Make sure the disk is not full and the output directory is writable",
+        ],
+    },
+    ExitCodeInfo {
+        code: 4,
+        error_types: &["api"],
+        description: "Configuration error (missing API key, invalid config)",
+        causes: &[
+            "No API key is set while the Mistral provider is enabled",
+            "config.toml cannot be parsed or contains out-of-range values",
+            "A required external tool (age, tesseract) cannot be run",
+        ],
+        remediation: &[
+            "Set {env_prefix}API_KEY or pass --api-key",
+            "Run with --json to get every invalid setting in error.fields",
+            "Check that configured commands are installed and on PATH",
+        ],
+    },
+    ExitCodeInfo {
+        code: 5,
+        error_types: &["api", "network", "internal"],
+        description: "API or network error (authentication failed, timeout, server error)",
+        causes: &[
+            "The API key is invalid or revoked",
+            "The API stayed rate limited or unavailable after all retries",
+            "The request exceeded timeout_seconds",
+            "DNS, proxy, or TLS problems prevent connecting to the API",
+        ],
+        remediation: &[
+            "Verify the API key and the API base URL",
+            "Lower max_concurrent_requests or raise retry_policy.max_retries",
+            "Raise timeout_seconds for large documents",
+            "Run with --verbose to see the underlying errors",
+        ],
+    },
+    ExitCodeInfo {
+        code: 6,
+        error_types: &["insufficient_text"],
+        description:
+            "Insufficient text (fewer characters than --min-chars, or quality below --min-quality)",
+        causes: &[
+            "The document is blank, a photo, or a very poor scan",
+            "The quality score is below --min-quality with --on-low-quality fail",
+        ],
+        remediation: &[
+            "Rescan at a higher resolution or fall back to another OCR tool",
+            "Lower --min-chars or --min-quality if the document is legitimately short",
+        ],
+    },
+    ExitCodeInfo {
+        code: 7,
+        error_types: &["budget_exceeded"],
+        description:
+            "Budget exceeded (--max-pages, --max-cost or an enforced monthly quota reached)",
+        causes: &[
+            "The run processed --max-pages pages or spent --max-cost",
+            "The monthly page quota is used up and --enforce-quota is set",
+        ],
+        remediation: &[
+            "Raise the limits or process the remaining files in another run",
+            "Wait for the next month or raise quota.monthly_pages",
+        ],
+    },
+];
+
+/// A stable named error code (`E_RATE_LIMIT`), finer-grained than the exit
+/// code it causes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCodeInfo {
+    /// Name printed with errors and accepted by `explain`
+    pub name: &'static str,
+
+    /// Process exit status of errors with this code
+    pub exit_code: i32,
+
+    /// What the code means
+    pub description: &'static str,
+}
+
+/// Every named error code, grouped by exit code
+pub const ERROR_CODES: &[ErrorCodeInfo] = &[
+    ErrorCodeInfo {
+        name: "E_VALIDATION",
+        exit_code: 2,
+        description: "The input file or a value failed validation",
+    },
+    ErrorCodeInfo {
+        name: "E_INVALID_ARGUMENTS",
+        exit_code: 2,
+        description: "One or more command-line arguments are invalid",
+    },
+    ErrorCodeInfo {
+        name: "E_HTTP_CLIENT",
+        exit_code: 2,
+        description: "The API rejected the request (HTTP 4xx other than 429)",
+    },
+    ErrorCodeInfo {
+        name: "E_FILE_IO",
+        exit_code: 3,
+        description: "A file could not be read or written",
+    },
+    ErrorCodeInfo {
+        name: "E_CONFIG",
+        exit_code: 4,
+        description: "The configuration is missing a value or a required tool",
+    },
+    ErrorCodeInfo {
+        name: "E_INVALID_CONFIG",
+        exit_code: 4,
+        description: "One or more settings are invalid",
+    },
+    ErrorCodeInfo {
+        name: "E_RATE_LIMIT",
+        exit_code: 5,
+        description: "The API stayed rate limited (HTTP 429) after all retries",
+    },
+    ErrorCodeInfo {
+        name: "E_HTTP_SERVER",
+        exit_code: 5,
+        description: "The API failed with a server error (HTTP 5xx)",
+    },
+    ErrorCodeInfo {
+        name: "E_API",
+        exit_code: 5,
+        description: "The API returned an error or an unusable response",
+    },
+    ErrorCodeInfo {
+        name: "E_PARSE",
+        exit_code: 5,
+        description: "An API response could not be parsed",
+    },
+    ErrorCodeInfo {
+        name: "E_NETWORK",
+        exit_code: 5,
+        description: "The API could not be reached (DNS, TLS, timeout, connection)",
+    },
+    ErrorCodeInfo {
+        name: "E_INTERNAL",
+        exit_code: 5,
+        description: "An unexpected internal failure",
+    },
+    ErrorCodeInfo {
+        name: "E_INSUFFICIENT_TEXT",
+        exit_code: 6,
+        description: "Too little text was found, or its quality is too low",
+    },
+    ErrorCodeInfo {
+        name: "E_BUDGET_EXCEEDED",
+        exit_code: 7,
+        description: "A page, cost, or quota limit was reached",
+    },
+];
+
+/// Reference entry of a named error code, ignoring case
+pub fn error_code_info(name: &str) -> Option<&'static ErrorCodeInfo> {
+    ERROR_CODES
+        .iter()
+        .find(|info| info.name.eq_ignore_ascii_case(name))
+}
+
+/// Reference entry of an exit code
+pub fn exit_code_info(code: i32) -> Option<&'static ExitCodeInfo> {
+    EXIT_CODES.iter().find(|info| info.code == code)
+}
+
+/// Exit code reference shown in `--help` and by `--explain-exit-codes`
+pub fn exit_code_reference() -> String {
+    let mut reference = String::from("EXIT CODES:\n");
+    for info in EXIT_CODES {
+        reference.push_str(&format!("    {:<4} {}\n", info.code, info.description));
+    }
+    reference.push_str(
+        "\nRun `explain <CODE>` (an exit code or a named code such as E_RATE_LIMIT)\n\
+         for causes and remediation steps.\n",
+    );
+    reference
+}
+
+impl ExitCodeInfo {
+    /// Remediation steps naming environment variables with `env_prefix`
+    pub fn remediation_steps(&self, env_prefix: &str) -> Vec<String> {
+        self.remediation
+            .iter()
+            .map(|step| step.replace("{env_prefix}", env_prefix))
+            .collect()
+    }
+
+    /// JSON description, naming environment variables with `env_prefix`
+    pub fn json_description(&self, env_prefix: &str) -> serde_json::Value {
+        let mut json = serde_json::json!(self);
+        json["remediation"] = self.remediation_steps(env_prefix).into();
+        json
+    }
+
+    /// Long description printed by the `explain` subcommand
+    pub fn explanation(&self, env_prefix: &str) -> String {
+        let mut text = format!("Exit code {}: {}\n", self.code, self.description);
+        if !self.error_types.is_empty() {
+            text.push_str(&format!("\nError types: {}\n", self.error_types.join(", ")));
+        }
+        let names: Vec<&str> = ERROR_CODES
+            .iter()
+            .filter(|info| info.exit_code == self.code)
+            .map(|info| info.name)
+            .collect();
+        if !names.is_empty() {
+            text.push_str(&format!("Error codes: {}\n", names.join(", ")));
+        }
+        let causes: Vec<String> = self.causes.iter().map(|cause| cause.to_string()).collect();
+        for (heading, items) in [
+            ("Likely causes", causes),
+            ("Remediation", self.remediation_steps(env_prefix)),
+        ] {
+            if !items.is_empty() {
+                text.push_str(&format!("\n{}:\n", heading));
+                for item in items {
+                    text.push_str(&format!("  - {}\n", item));
+                }
+            }
+        }
+        text
+    }
 }
 
 impl Error {
+    /// Validation error with a message
+    pub fn validation(message: impl Into<String>) -> Self {
+        Error::Validation {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Validation error caused by `source`
+    pub fn validation_from(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Error::Validation {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// API error with a message
+    pub fn api(message: impl Into<String>) -> Self {
+        Error::Api {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// API error caused by `source`
+    pub fn api_from(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Error::Api {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Internal error with a message
+    pub fn internal(message: impl Into<String>) -> Self {
+        Error::Internal {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Internal error caused by `source`
+    pub fn internal_from(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Error::Internal {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
     /// Get the appropriate exit code for this error
     pub fn exit_code(&self) -> i32 {
+        if self.is_rate_limit() {
+            return 5;
+        }
         match self {
-            Error::Validation(_) => 2,
+            Error::Validation { .. } | Error::InvalidArguments(_) => 2,
+            Error::Http { status, .. } if (400..=499).contains(status) => 2,
             Error::Io(_) => 3,
-            Error::Config(_) => 4,
-            Error::Api(_) | Error::Network(_) | Error::Internal(_) => 5,
+            Error::Config(_) | Error::InvalidConfig(_) => 4,
+            Error::Api { .. }
+            | Error::Http { .. }
+            | Error::Network(_)
+            | Error::Internal { .. }
+            | Error::Parse { .. } => 5,
+            Error::InsufficientText(_) => 6,
+            Error::BudgetExceeded(_) => 7,
+        }
+    }
+
+    /// Stable named code of this error (see [`ERROR_CODES`])
+    pub fn code(&self) -> &'static str {
+        if self.is_rate_limit() {
+            return "E_RATE_LIMIT";
+        }
+        match self.http_status() {
+            Some(400..=499) => return "E_HTTP_CLIENT",
+            Some(500..=599) => return "E_HTTP_SERVER",
+            _ => {}
+        }
+        match self {
+            Error::Http { .. } => "E_API",
+            Error::Validation { .. } => "E_VALIDATION",
+            Error::InvalidArguments(_) => "E_INVALID_ARGUMENTS",
+            Error::Io(_) => "E_FILE_IO",
+            Error::Config(_) => "E_CONFIG",
+            Error::InvalidConfig(_) => "E_INVALID_CONFIG",
+            Error::Api { .. } => "E_API",
+            Error::Parse { .. } => "E_PARSE",
+            Error::Network(_) => "E_NETWORK",
+            Error::Internal { .. } => "E_INTERNAL",
+            Error::InsufficientText(_) => "E_INSUFFICIENT_TEXT",
+            Error::BudgetExceeded(_) => "E_BUDGET_EXCEEDED",
         }
     }
 
     /// Get the error type as string for JSON output
     pub fn error_type(&self) -> &'static str {
         match self {
-            Error::Validation(_) => "validation",
+            Error::Validation { .. } | Error::InvalidArguments(_) => "validation",
+            Error::Http { status, .. } => match status {
+                400..=499 => "validation",
+                500..=599 => "api",
+                _ => "internal",
+            },
             Error::Io(_) => "file_io",
-            Error::Config(_) => "api",
-            Error::Api(_) => "api",
+            Error::Config(_) | Error::InvalidConfig(_) => "api",
+            Error::Api { .. } | Error::Parse { .. } => "api",
             Error::Network(_) => "network",
-            Error::Internal(_) => "internal",
+            Error::Internal { .. } => "internal",
+            Error::InsufficientText(_) => "insufficient_text",
+            Error::BudgetExceeded(_) => "budget_exceeded",
         }
     }
 
     /// Get user-friendly error message
     pub fn user_message(&self) -> String {
         match self {
-            Error::Validation(msg) => format!("Validation error: {}", msg),
             Error::Io(e) => format!("File error: {}", e),
             Error::Config(msg) => format!("Configuration error: {}", msg),
-            Error::Api(msg) => format!("API error: {}", msg),
             Error::Network(e) => format!("Network error: {}", e),
-            Error::Internal(msg) => format!("Internal error: {}", msg),
+            Error::InsufficientText(msg) => format!("Insufficient text: {}", msg),
+            Error::BudgetExceeded(msg) => format!("Budget exceeded: {}", msg),
+            Error::Parse { context, .. } => format!("API error: {}", context),
+            Error::Validation { .. }
+            | Error::Api { .. }
+            | Error::Http { .. }
+            | Error::Internal { .. }
+            | Error::InvalidConfig(_)
+            | Error::InvalidArguments(_) => self.to_string(),
         }
     }
 
+    /// Invalid fields of a configuration or argument validation error
+    pub fn field_errors(&self) -> &[FieldError] {
+        match self {
+            Error::InvalidConfig(fields) | Error::InvalidArguments(fields) => fields,
+            _ => &[],
+        }
+    }
+
+    /// Messages of the underlying errors, outermost first
+    pub fn causes(&self) -> Vec<String> {
+        let mut source = match self {
+            // The message of these variants already includes the wrapped error
+            Error::Io(e) => e.source(),
+            Error::Network(e) => e.source(),
+            Error::Validation { source, .. }
+            | Error::Api { source, .. }
+            | Error::Internal { source, .. } => source.as_ref().and_then(|e| e.source()),
+            _ => self.source(),
+        };
+
+        let mut causes = Vec::new();
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        causes
+    }
+
+    /// User message followed by one "Caused by" line per underlying error
+    pub fn verbose_message(&self) -> String {
+        let mut message = self.user_message();
+        for cause in self.causes() {
+            message.push_str("\n  Caused by: ");
+            message.push_str(&cause);
+        }
+        message
+    }
+
     /// Format error for JSON output
     pub fn to_json_output(&self) -> serde_json::Value {
-        serde_json::json!({
+        let mut output = serde_json::json!({
             "success": false,
             "error": {
                 "type": self.error_type(),
+                "code": self.code(),
+                "exit_code": self.exit_code(),
                 "message": self.user_message(),
                 "details": self.to_string()
             }
-        })
+        });
+        let causes = self.causes();
+        if !causes.is_empty() {
+            output["error"]["causes"] = serde_json::json!(causes);
+        }
+        let fields = self.field_errors();
+        if !fields.is_empty() {
+            output["error"]["fields"] = serde_json::json!(fields);
+        }
+        if let Some(request_id) = self.request_id() {
+            output["error"]["request_id"] = serde_json::json!(request_id);
+        }
+        output
+    }
+
+    /// Provider request ID of the failed API call
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::Http { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an error response or of a network error
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Error::Http { status, .. } => Some(*status),
+            Error::Network(e) => e.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Whether a request failing with this error may succeed when retried
+    ///
+    /// Rate limits, gateway errors, timeouts, and dropped connections are
+    /// transient; other client errors (400, 401, 413, ...), unparseable
+    /// responses, and local errors are not.
+    pub fn is_retryable(&self) -> bool {
+        if let Some(status) = self.http_status() {
+            return Self::is_retryable_status(status);
+        }
+        match self {
+            Error::Network(e) => !e.is_builder() && !e.is_decode() && !e.is_redirect(),
+            _ => self.is_rate_limit(),
+        }
+    }
+
+    /// Whether this error reports an API rate limit (HTTP 429)
+    pub fn is_rate_limit(&self) -> bool {
+        self.http_status() == Some(429)
+    }
+
+    /// Whether a response with this HTTP status is worth retrying
+    pub fn is_retryable_status(status: u16) -> bool {
+        matches!(status, 408 | 429 | 502 | 503 | 504)
     }
 
     /// Create API error from HTTP status code
     pub fn from_http_status(status: u16, message: String) -> Self {
-        match status {
-            400..=499 => Error::Validation(format!("Client error ({}): {}", status, message)),
-            500..=599 => Error::Api(format!("Server error ({}): {}", status, message)),
-            _ => Error::Internal(format!("Unexpected HTTP status ({}): {}", status, message)),
+        Self::http(status, message, None)
+    }
+
+    /// Create API error from HTTP status code and the provider's request ID
+    pub fn http(status: u16, message: String, request_id: Option<String>) -> Self {
+        Error::Http {
+            status,
+            message,
+            request_id,
+        }
+    }
+}
+
+/// Label of the provider request ID appended to API error messages
+const REQUEST_ID_LABEL: &str = " (request ID: ";
+
+/// Append the provider's request ID to an API error message, so support
+/// tickets can reference the failed call
+pub fn with_request_id(message: String, request_id: Option<&str>) -> String {
+    match request_id {
+        Some(request_id) => format!("{}{}{})", message, REQUEST_ID_LABEL, request_id),
+        None => message,
+    }
+}
+
+/// Deserialize a JSON API response, keeping the path of the offending field
+/// (such as `pages[2].index`) in the error's source
+pub fn parse_json<T: DeserializeOwned>(text: &str, context: &str) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_str(text);
+    serde_path_to_error::deserialize(deserializer).map_err(|source| Error::Parse {
+        context: context.to_string(),
+        source,
+    })
+}
+
+/// A setting or argument that failed validation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Setting path (`retry_policy.max_retries`) or flag (`--file`)
+    pub field: String,
+
+    /// What the value must satisfy
+    pub constraint: String,
+}
+
+/// Collects every invalid field so a validation reports all of them at once
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an invalid field
+    pub fn add(&mut self, field: impl Into<String>, constraint: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            constraint: constraint.into(),
+        });
+    }
+
+    /// Record the failures of a nested validation with fields under `prefix`
+    pub fn nested(&mut self, prefix: &str, result: Result<()>) {
+        match result {
+            Ok(()) => {}
+            Err(Error::InvalidConfig(fields) | Error::InvalidArguments(fields)) => {
+                for field in fields {
+                    self.add(format!("{}.{}", prefix, field.field), field.constraint);
+                }
+            }
+            Err(Error::Config(msg)) => self.add(prefix, msg),
+            Err(Error::Validation {
+                message,
+                source: None,
+            }) => self.add(prefix, message),
+            Err(e) => self.add(prefix, e.to_string()),
+        }
+    }
+
+    /// Configuration error listing the invalid settings, if any
+    pub fn into_config_result(self) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(self.0))
         }
     }
+
+    /// Validation error listing the invalid arguments, if any
+    pub fn into_arguments_result(self) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidArguments(self.0))
+        }
+    }
+}
+
+/// Message of an HTTP error, worded like the error kind its status maps to
+fn describe_http(status: u16, message: &str, request_id: Option<&str>) -> String {
+    let message = with_request_id(message.to_string(), request_id);
+    match status {
+        400..=499 => format!("Validation error: Client error ({}): {}", status, message),
+        500..=599 => format!("API error: Server error ({}): {}", status, message),
+        _ => format!(
+            "Internal error: Unexpected HTTP status ({}): {}",
+            status, message
+        ),
+    }
+}
+
+/// Message of a wrapping error, followed by the error it wraps
+fn describe_source(message: &str, source: &Option<BoxError>) -> String {
+    match source {
+        Some(source) => format!("{}: {}", message, source),
+        None => message.to_string(),
+    }
+}
+
+/// One-line description of invalid fields
+fn describe_fields(fields: &[FieldError]) -> String {
+    let described: Vec<String> = fields
+        .iter()
+        .map(|field| format!("{} ({})", field.constraint, field.field))
+        .collect();
+    match described.as_slice() {
+        [single] => single.clone(),
+        _ => format!(
+            "{} invalid fields: {}",
+            described.len(),
+            described.join("; ")
+        ),
+    }
 }
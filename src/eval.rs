@@ -0,0 +1,236 @@
+//! Accuracy evaluation against reference transcripts
+//!
+//! Computes character error rate (CER) and word error rate (WER) of OCR output
+//! against known-good transcripts. Both are the Levenshtein edit distance
+//! divided by the length of the reference, after whitespace normalization.
+
+use serde::Serialize;
+
+/// Accuracy of a single document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DocumentAccuracy {
+    /// Original file name
+    pub file_name: String,
+
+    /// Character error rate (0.0 is a perfect match)
+    pub cer: f64,
+
+    /// Word error rate (0.0 is a perfect match)
+    pub wer: f64,
+
+    /// Number of characters in the normalized reference
+    pub reference_chars: usize,
+
+    /// Number of words in the reference
+    pub reference_words: usize,
+}
+
+impl DocumentAccuracy {
+    /// Compare OCR output with its reference transcript
+    pub fn compare(file_name: &str, reference: &str, hypothesis: &str) -> Self {
+        let reference = normalize_whitespace(reference);
+        let hypothesis = normalize_whitespace(hypothesis);
+
+        let reference_chars: Vec<char> = reference.chars().collect();
+        let hypothesis_chars: Vec<char> = hypothesis.chars().collect();
+        let reference_words: Vec<&str> = reference.split(' ').filter(|w| !w.is_empty()).collect();
+        let hypothesis_words: Vec<&str> = hypothesis.split(' ').filter(|w| !w.is_empty()).collect();
+
+        Self {
+            file_name: file_name.to_string(),
+            cer: error_rate(
+                edit_distance(&reference_chars, &hypothesis_chars),
+                reference_chars.len(),
+                hypothesis_chars.len(),
+            ),
+            wer: error_rate(
+                edit_distance(&reference_words, &hypothesis_words),
+                reference_words.len(),
+                hypothesis_words.len(),
+            ),
+            reference_chars: reference_chars.len(),
+            reference_words: reference_words.len(),
+        }
+    }
+}
+
+/// Accuracy report over a set of documents
+#[derive(Debug, Clone, Serialize)]
+pub struct AccuracyReport {
+    /// Per-document results
+    pub documents: Vec<DocumentAccuracy>,
+
+    /// Character error rate over all documents, weighted by reference length
+    pub cer: f64,
+
+    /// Word error rate over all documents, weighted by reference length
+    pub wer: f64,
+
+    /// Documents without a reference transcript
+    pub missing_reference: Vec<String>,
+
+    /// Documents that could not be processed
+    pub failed: Vec<FailedDocument>,
+}
+
+/// A document that could not be evaluated
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedDocument {
+    pub file_name: String,
+    pub reason: String,
+}
+
+impl AccuracyReport {
+    /// Aggregate per-document results into a report
+    pub fn new(
+        documents: Vec<DocumentAccuracy>,
+        missing_reference: Vec<String>,
+        failed: Vec<FailedDocument>,
+    ) -> Self {
+        let total_chars: usize = documents.iter().map(|d| d.reference_chars).sum();
+        let total_words: usize = documents.iter().map(|d| d.reference_words).sum();
+
+        let weighted = |rate: fn(&DocumentAccuracy) -> f64,
+                        weight: fn(&DocumentAccuracy) -> usize,
+                        total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                documents
+                    .iter()
+                    .map(|d| rate(d) * weight(d) as f64)
+                    .sum::<f64>()
+                    / total as f64
+            }
+        };
+
+        Self {
+            cer: weighted(|d| d.cer, |d| d.reference_chars, total_chars),
+            wer: weighted(|d| d.wer, |d| d.reference_words, total_words),
+            documents,
+            missing_reference,
+            failed,
+        }
+    }
+
+    /// Format the report as an aligned text table
+    pub fn to_human_readable(&self) -> String {
+        let width = self
+            .documents
+            .iter()
+            .map(|d| d.file_name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Document".len());
+
+        let mut lines = vec![format!(
+            "{:<width$}  {:>7}  {:>7}",
+            "Document",
+            "CER",
+            "WER",
+            width = width
+        )];
+        for document in &self.documents {
+            lines.push(format!(
+                "{:<width$}  {:>6.2}%  {:>6.2}%",
+                document.file_name,
+                document.cer * 100.0,
+                document.wer * 100.0,
+                width = width
+            ));
+        }
+        lines.push(format!(
+            "{:<width$}  {:>6.2}%  {:>6.2}%",
+            "Total",
+            self.cer * 100.0,
+            self.wer * 100.0,
+            width = width
+        ));
+
+        for file_name in &self.missing_reference {
+            lines.push(format!("{}: no reference transcript", file_name));
+        }
+        for failure in &self.failed {
+            lines.push(format!(
+                "{}: failed ({})",
+                failure.file_name, failure.reason
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Levenshtein distance between two sequences
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, item_a) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, item_b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(item_a != item_b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Edit distance relative to the reference length
+fn error_rate(distance: usize, reference_len: usize, hypothesis_len: usize) -> f64 {
+    match (reference_len, hypothesis_len) {
+        (0, 0) => 0.0,
+        (0, _) => 1.0,
+        _ => distance as f64 / reference_len as f64,
+    }
+}
+
+/// Collapse all whitespace runs into single spaces
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(edit_distance(&chars("same"), &chars("same")), 0);
+    }
+
+    #[test]
+    fn test_compare_ignores_whitespace_differences() {
+        let accuracy = DocumentAccuracy::compare("a.pdf", "Invoice  42\nACME", "Invoice 42 ACME");
+        assert_eq!(accuracy.cer, 0.0);
+        assert_eq!(accuracy.wer, 0.0);
+    }
+
+    #[test]
+    fn test_compare_counts_errors() {
+        let accuracy = DocumentAccuracy::compare("a.pdf", "total 42 EUR", "tota1 42 EUR");
+        assert_eq!(accuracy.reference_chars, 12);
+        assert!((accuracy.cer - 1.0 / 12.0).abs() < 1e-9);
+        assert!((accuracy.wer - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_report_weights_by_reference_length() {
+        let report = AccuracyReport::new(
+            vec![
+                DocumentAccuracy::compare("short.pdf", "ab", "xb"),
+                DocumentAccuracy::compare("long.pdf", "abcdefgh", "abcdefgh"),
+            ],
+            vec![],
+            vec![],
+        );
+
+        assert!((report.cer - 0.1).abs() < 1e-9);
+        assert!(report.to_human_readable().contains("Total"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Exclude patterns for watched directories
+//!
+//! `watch` skips files that match, in order of increasing precedence:
+//!
+//! - the built-in patterns for files that are still being written or are not
+//!   documents ([`DEFAULT_EXCLUDES`])
+//! - the `.ocrignore` file in the watched directory
+//! - `--exclude` globs
+//!
+//! Patterns use `.gitignore` syntax, so a later `!pattern` includes files
+//! again that an earlier pattern excludes. `.ocrignore` is read on every scan
+//! and may be edited while the watcher runs.
+
+use crate::error::{Error, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Pattern file in the watched directory
+pub const IGNORE_FILE: &str = ".ocrignore";
+
+/// Hidden files (editor and rsync temp files), partial downloads and copies,
+/// and Office lock files
+pub const DEFAULT_EXCLUDES: &[&str] =
+    &[".*", "*.partial", "*.part", "*.tmp", "*.crdownload", "~$*"];
+
+/// Compiled exclude patterns of a directory
+#[derive(Debug)]
+pub struct ExcludeRules {
+    matcher: Gitignore,
+}
+
+impl ExcludeRules {
+    /// Rules for `dir` from the defaults, its `.ocrignore`, and `excludes`
+    ///
+    /// Invalid lines in `.ocrignore` are logged and skipped, so a bad edit does
+    /// not stop a running watcher.
+    pub fn load(dir: &Path, excludes: &[String]) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(dir);
+        for pattern in DEFAULT_EXCLUDES {
+            builder.add_line(None, pattern).map_err(invalid_pattern)?;
+        }
+
+        let ignore_file = dir.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                tracing::warn!(
+                    "Skipping invalid patterns in {}: {}",
+                    ignore_file.display(),
+                    e
+                );
+            }
+        }
+
+        for pattern in excludes {
+            builder.add_line(None, pattern).map_err(invalid_pattern)?;
+        }
+        let matcher = builder.build().map_err(invalid_pattern)?;
+        Ok(Self { matcher })
+    }
+
+    /// Whether the file at `path` in the directory is excluded
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.matcher.matched(path, false).is_ignore()
+    }
+}
+
+/// Check an `--exclude` glob
+pub fn check_pattern(pattern: &str) -> std::result::Result<(), String> {
+    GitignoreBuilder::new("")
+        .add_line(None, pattern)
+        .and_then(|builder| builder.build())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn invalid_pattern(e: ignore::Error) -> Error {
+    Error::validation_from("Invalid exclude pattern", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_defaults_ignore_file_and_excludes() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join(IGNORE_FILE),
+            "# drafts\ndraft-*\n!.keep.pdf\n",
+        )
+        .unwrap();
+        let rules = ExcludeRules::load(dir.path(), &["*.png".to_string()]).unwrap();
+        let excluded = |name: &str| rules.is_excluded(&dir.path().join(name));
+
+        assert!(!excluded("scan.pdf"));
+        for name in [
+            ".scan.pdf.swp",
+            "scan.pdf.partial",
+            "scan.pdf.crdownload",
+            "~$letter.docx",
+            "draft-1.pdf",
+            "photo.png",
+        ] {
+            assert!(excluded(name), "{}", name);
+        }
+        assert!(!excluded(".keep.pdf"));
+
+        assert!(check_pattern("*.tmp").is_ok());
+        assert!(check_pattern("scan{a,b").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Structured field extraction presets
+//!
+//! Presets describe a JSON schema that is sent to the OCR API as a document
+//! annotation format. The API returns the annotation as a JSON string, which is
+//! validated against the preset's field types before it is added to the result.
+
+use crate::api::ocr::AnnotationFormat;
+use crate::error::{parse_json, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Built-in extraction presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractionPreset {
+    /// Invoices and receipts
+    Invoice,
+}
+
+impl FromStr for ExtractionPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "invoice" | "receipt" => Ok(Self::Invoice),
+            other => Err(format!(
+                "Unknown extraction preset: {}. Supported: invoice",
+                other
+            )),
+        }
+    }
+}
+
+impl ExtractionPreset {
+    /// Preset name as used on the command line and in output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Invoice => "invoice",
+        }
+    }
+
+    /// Annotation format requesting this preset's fields from the OCR API
+    pub fn annotation_format(&self) -> AnnotationFormat {
+        match self {
+            Self::Invoice => AnnotationFormat::json_schema(
+                self.name(),
+                serde_json::json!({
+                    "type": "object",
+                    "title": "Invoice",
+                    "properties": {
+                        "vendor": {
+                            "type": ["string", "null"],
+                            "description": "Name of the company or person that issued the invoice or receipt"
+                        },
+                        "invoice_number": {
+                            "type": ["string", "null"],
+                            "description": "Invoice or receipt number exactly as printed"
+                        },
+                        "total": {
+                            "type": ["number", "null"],
+                            "description": "Total amount due including tax"
+                        },
+                        "tax": {
+                            "type": ["number", "null"],
+                            "description": "Total tax (VAT) amount"
+                        },
+                        "currency": {
+                            "type": ["string", "null"],
+                            "description": "ISO 4217 currency code, e.g. EUR or USD"
+                        }
+                    },
+                    "required": ["vendor", "invoice_number", "total", "tax", "currency"],
+                    "additionalProperties": false
+                }),
+            ),
+        }
+    }
+
+    /// Parse and validate the document annotation returned by the OCR API
+    pub fn parse_annotation(&self, annotation: &str) -> Result<serde_json::Value> {
+        match self {
+            Self::Invoice => {
+                let fields: InvoiceFields =
+                    parse_json(annotation, "Failed to parse invoice annotation")?;
+                serde_json::to_value(fields.normalized())
+                    .map_err(|e| Error::internal_from("Failed to serialize JSON", e))
+            }
+        }
+    }
+}
+
+/// Fields extracted by the invoice preset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceFields {
+    #[serde(default)]
+    pub vendor: Option<String>,
+    #[serde(default)]
+    pub invoice_number: Option<String>,
+    #[serde(default)]
+    pub total: Option<f64>,
+    #[serde(default)]
+    pub tax: Option<f64>,
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+impl InvoiceFields {
+    /// Trim values, drop empty strings and upper-case the currency code
+    fn normalized(self) -> Self {
+        let clean = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            vendor: clean(self.vendor),
+            invoice_number: clean(self.invoice_number),
+            total: self.total,
+            tax: self.tax,
+            currency: clean(self.currency).map(|c| c.to_uppercase()),
+        }
+    }
+}
+
+/// Fields extracted with a preset, as reported in the output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extraction {
+    /// Preset used for the extraction
+    pub preset: ExtractionPreset,
+
+    /// Extracted fields; missing values are null
+    pub fields: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_parsing() {
+        assert_eq!("invoice".parse(), Ok(ExtractionPreset::Invoice));
+        assert_eq!("Receipt".parse(), Ok(ExtractionPreset::Invoice));
+        assert!("contract".parse::<ExtractionPreset>().is_err());
+    }
+
+    #[test]
+    fn test_invoice_annotation_format() {
+        let format = serde_json::to_value(ExtractionPreset::Invoice.annotation_format()).unwrap();
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "invoice");
+        assert_eq!(
+            format["json_schema"]["schema"]["required"]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_parse_invoice_annotation() {
+        let fields = ExtractionPreset::Invoice
+            .parse_annotation(
+                r#"{"vendor": " ACME GmbH ", "invoice_number": "RE-2024-042",
+                    "total": 119.0, "tax": 19.0, "currency": "eur"}"#,
+            )
+            .unwrap();
+
+        assert_eq!(fields["vendor"], "ACME GmbH");
+        assert_eq!(fields["invoice_number"], "RE-2024-042");
+        assert_eq!(fields["total"], 119.0);
+        assert_eq!(fields["currency"], "EUR");
+    }
+
+    #[test]
+    fn test_parse_invoice_annotation_with_missing_fields() {
+        let fields = ExtractionPreset::Invoice
+            .parse_annotation(r#"{"vendor": "ACME", "invoice_number": ""}"#)
+            .unwrap();
+
+        assert_eq!(fields["vendor"], "ACME");
+        assert!(fields["invoice_number"].is_null());
+        assert!(fields["total"].is_null());
+
+        assert!(ExtractionPreset::Invoice
+            .parse_annotation(r#"{"total": "lots"}"#)
+            .is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! C ABI for embedding the OCR pipeline
+//!
+//! Built into the `cdylib` so non-Rust applications (e.g. a Python paperless-ngx
+//! plugin via `ctypes`) can run OCR without spawning the binary:
+//!
+//! ```c
+//! char *ocr_extract(const char *path, const char *config_json);
+//! void ocr_free_string(char *result);
+//! ```
+//!
+//! `config_json` is a JSON object with `config.toml` keys applied on top of the
+//! defaults (e.g. `{"api_key": "..."}`), or NULL. The returned string is the
+//! same JSON document the CLI prints with `--json`, including errors, and must
+//! be released with `ocr_free_string`.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::ocr::OCRResult;
+use crate::pipeline::{ocr_file, ProcessingOptions};
+use std::ffi::{c_char, CStr, CString};
+
+/// Extract text from the file at `path`; returns a JSON string owned by the caller
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string. `config_json` must be NULL or
+/// a valid NUL-terminated string. Both must stay valid for the duration of the
+/// call.
+#[no_mangle]
+pub unsafe extern "C" fn ocr_extract(
+    path: *const c_char,
+    config_json: *const c_char,
+) -> *mut c_char {
+    // A panic must not unwind into the C caller
+    let output = catch_panic(|| match extract(path, config_json) {
+        Ok(result) => result.to_json_output(),
+        Err(e) => e.to_json_output(),
+    });
+
+    // serde_json escapes control characters, so the output contains no NUL bytes
+    CString::new(output.to_string())
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Release a string returned by `ocr_extract`
+///
+/// # Safety
+///
+/// `result` must be NULL or a pointer returned by `ocr_extract` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ocr_free_string(result: *mut c_char) {
+    if !result.is_null() {
+        drop(CString::from_raw(result));
+    }
+}
+
+/// Run `f`, turning a panic into the JSON output of an internal error
+fn catch_panic(f: impl FnOnce() -> serde_json::Value) -> serde_json::Value {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        Error::internal(format!("OCR panicked: {}", message)).to_json_output()
+    })
+}
+
+/// Run the pipeline for the C arguments
+unsafe fn extract(path: *const c_char, config_json: *const c_char) -> Result<OCRResult> {
+    let path =
+        c_str(path)?.ok_or_else(|| Error::validation("File path must not be NULL".to_string()))?;
+    let config = config_from_json(c_str(config_json)?)?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(Error::Io)?;
+    runtime.block_on(ocr_file(
+        path,
+        &config,
+        &ProcessingOptions::default(),
+        false,
+    ))
+}
+
+/// Borrow a C string as UTF-8; NULL becomes None
+unsafe fn c_str<'a>(value: *const c_char) -> Result<Option<&'a str>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    CStr::from_ptr(value)
+        .to_str()
+        .map(Some)
+        .map_err(|_| Error::validation("Arguments must be valid UTF-8".to_string()))
+}
+
+/// Build a configuration from JSON overrides on top of the defaults
+pub fn config_from_json(json: Option<&str>) -> Result<Config> {
+    let mut config = serde_json::to_value(Config::default())
+        .map_err(|e| Error::internal_from("Failed to serialize defaults", e))?;
+
+    if let Some(json) = json.filter(|json| !json.trim().is_empty()) {
+        let overrides: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| Error::Config(format!("Invalid configuration JSON: {}", e)))?;
+        let serde_json::Value::Object(overrides) = overrides else {
+            return Err(Error::Config(
+                "Configuration JSON must be an object".to_string(),
+            ));
+        };
+        for (key, value) in overrides {
+            config[key] = value;
+        }
+    }
+
+    let config: Config = serde_json::from_value(config)
+        .map_err(|e| Error::Config(format!("Invalid configuration: {}", e)))?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_json_overrides_defaults() {
+        let config =
+            config_from_json(Some(r#"{"providers": ["mock"], "timeout_seconds": 5}"#)).unwrap();
+        assert_eq!(config.timeout_seconds, 5);
+        assert_eq!(config.blank_page_threshold, 5);
+
+        assert!(config_from_json(Some("[]")).is_err());
+        // The default Mistral provider needs an API key
+        assert!(config_from_json(None).is_err());
+    }
+
+    #[test]
+    fn test_panics_become_error_output() {
+        let output = catch_panic(|| panic!("bad state"));
+        assert_eq!(output["success"], false);
+        assert!(output["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("bad state"));
+
+        let output = catch_panic(|| serde_json::json!({"success": true}));
+        assert_eq!(output["success"], true);
+    }
+}
@@ -5,6 +5,85 @@ use mime_guess::MimeGuess;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
+
+/// MIME types accepted by default (the formats the Mistral OCR API reads)
+pub const DEFAULT_ALLOWED_MIME_TYPES: [&str; 4] =
+    ["application/pdf", "image/png", "image/jpeg", "image/jpg"];
+
+/// Default maximum file size in MB
+pub const DEFAULT_MAX_FILE_SIZE_MB: u64 = 100;
+
+/// How strictly file contents are checked before upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    /// Reject files whose content does not look like their type
+    #[default]
+    Strict,
+    /// Log content check failures as warnings and let the API decide
+    Permissive,
+}
+
+impl FromStr for ValidationMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "permissive" => Ok(Self::Permissive),
+            other => Err(Error::Config(format!(
+                "Unknown validation mode: {}. Supported: strict, permissive",
+                other
+            ))),
+        }
+    }
+}
+
+/// Rules applied when validating an input file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationRules {
+    /// Accepted MIME types; `image/*` accepts a whole top-level type
+    pub allowed_mime_types: Vec<String>,
+
+    /// Maximum file size in MB
+    pub max_file_size_mb: u64,
+
+    /// MIME type to assume instead of detecting it (`--mime-type`)
+    pub mime_type: Option<String>,
+
+    /// Whether content check failures reject the file
+    pub mode: ValidationMode,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            max_file_size_mb: DEFAULT_MAX_FILE_SIZE_MB,
+            mime_type: None,
+            mode: ValidationMode::default(),
+            allowed_mime_types: DEFAULT_ALLOWED_MIME_TYPES
+                .iter()
+                .map(|mime_type| mime_type.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl ValidationRules {
+    /// Whether a MIME type is accepted
+    pub fn allows(&self, mime_type: &str) -> bool {
+        self.allowed_mime_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some("*") => true,
+                Some(prefix) => mime_type
+                    .split_once('/')
+                    .is_some_and(|(top_level, _)| top_level.eq_ignore_ascii_case(prefix)),
+                None => allowed.eq_ignore_ascii_case(mime_type),
+            })
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileUpload {
@@ -30,6 +109,11 @@ pub struct FileUpload {
 impl FileUpload {
     /// Create a new FileUpload from a file path
     pub fn new<P: AsRef<Path>>(file_path: P) -> Result<Self> {
+        Self::new_with_rules(file_path, &ValidationRules::default())
+    }
+
+    /// Create a new FileUpload, validating it against the given rules
+    pub fn new_with_rules<P: AsRef<Path>>(file_path: P, rules: &ValidationRules) -> Result<Self> {
         let path = file_path.as_ref();
         let file_path_str = path.to_string_lossy().to_string();
 
@@ -46,10 +130,10 @@ impl FileUpload {
 
         let file_size = metadata.len();
 
-        // Determine MIME type
-        let mime_type = MimeGuess::from_path(path)
-            .first_or_octet_stream()
-            .to_string();
+        let mime_type = match &rules.mime_type {
+            Some(mime_type) => mime_type.to_lowercase(),
+            None => Self::guess_mime_type(path),
+        };
 
         let mut file_upload = Self {
             file_path: file_path_str,
@@ -61,44 +145,73 @@ impl FileUpload {
         };
 
         // Validate the file
-        file_upload.validate_file()?;
+        file_upload.validate_with_rules(rules)?;
         file_upload.is_valid = true;
 
         Ok(file_upload)
     }
 
+    /// Determine the MIME type from the file content, falling back to the
+    /// extension for formats without a recognizable signature
+    pub fn guess_mime_type<P: AsRef<Path>>(path: P) -> String {
+        let path = path.as_ref();
+        match infer::get_from_path(path) {
+            Ok(Some(kind)) => kind.mime_type().to_string(),
+            _ => MimeGuess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+        }
+    }
+
     /// Validate file according to data model rules
     pub fn validate_file(&self) -> Result<()> {
+        self.validate_with_rules(&ValidationRules::default())
+    }
+
+    /// Validate file against the given rules
+    pub fn validate_with_rules(&self, rules: &ValidationRules) -> Result<()> {
         // Validate file path exists and is readable
         let path = Path::new(&self.file_path);
         if !path.exists() {
-            return Err(Error::Validation(format!(
+            return Err(Error::validation(format!(
                 "File does not exist: {}",
                 self.file_path
             )));
         }
 
         // Validate file size (convert MB to bytes for comparison)
-        let max_size_bytes = 100 * 1024 * 1024; // 100MB in bytes
+        let max_size_bytes = rules.max_file_size_mb * 1024 * 1024;
         if self.file_size > max_size_bytes {
-            return Err(Error::Validation(format!(
-                "File size ({:.2} MB) exceeds maximum allowed size (100 MB)",
-                self.file_size as f64 / (1024.0 * 1024.0)
+            return Err(Error::validation(format!(
+                "File size ({:.2} MB) exceeds maximum allowed size ({} MB)",
+                self.file_size as f64 / (1024.0 * 1024.0),
+                rules.max_file_size_mb
             )));
         }
 
         // Validate MIME type
-        let supported_types = ["application/pdf", "image/png", "image/jpeg", "image/jpg"];
-
-        if !supported_types.contains(&self.mime_type.as_str()) {
-            return Err(Error::Validation(format!(
-                "Unsupported file format: {}. Supported: pdf, png, jpg, jpeg",
-                self.mime_type
+        if !rules.allows(&self.mime_type) {
+            return Err(Error::validation(format!(
+                "Unsupported file format: {}. Supported: {}",
+                self.mime_type,
+                rules.allowed_mime_types.join(", ")
             )));
         }
 
-        // Validate file content by checking magic bytes
-        self.validate_file_content()?;
+        // Validate file content by checking magic bytes; additional allowed
+        // formats are passed through for the server to judge
+        if DEFAULT_ALLOWED_MIME_TYPES.contains(&self.mime_type.as_str()) {
+            match (self.validate_file_content(), rules.mode) {
+                (Ok(()), _) => {}
+                (Err(e @ Error::Validation { .. }), ValidationMode::Permissive) => {
+                    tracing::warn!(
+                        "{}; uploading anyway (permissive validation)",
+                        e.user_message()
+                    );
+                }
+                (Err(e), _) => return Err(e),
+            }
+        }
 
         Ok(())
     }
@@ -112,7 +225,7 @@ impl FileUpload {
         let bytes_read = file.read(&mut buffer).map_err(Error::Io)?;
 
         if bytes_read < 4 {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "File too small to determine format".to_string(),
             ));
         }
@@ -126,7 +239,7 @@ impl FileUpload {
             }
             [0x89, 0x50, 0x4E, 0x47] => Ok(()), // PNG
             [0xFF, 0xD8, 0xFF, _] => Ok(()),    // JPEG
-            _ => Err(Error::Validation(format!(
+            _ => Err(Error::validation(format!(
                 "File does not appear to be a valid PDF, PNG, or JPEG file: {}",
                 self.file_path
             ))),
@@ -152,6 +265,16 @@ impl FileUpload {
         fs::read(&self.file_path).map_err(Error::Io)
     }
 
+    /// Compute the SHA-256 of the file contents as lowercase hex
+    pub fn sha256(&self) -> Result<String> {
+        use sha2::{Digest, Sha256};
+
+        let mut file = fs::File::open(&self.file_path).map_err(Error::Io)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).map_err(Error::Io)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
     /// Get filename from path
     pub fn get_filename(&self) -> String {
         Path::new(&self.file_path)
@@ -179,7 +302,7 @@ impl FileUpload {
             || content.contains("/O ")
             || content.contains("/Filter/Standard")
         {
-            return Err(Error::Validation(
+            return Err(Error::validation(
                 "Password-protected PDF detected. Please provide an unprotected PDF file."
                     .to_string(),
             ));
@@ -196,6 +319,123 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn test_validation_rules_allow() {
+        let rules = ValidationRules {
+            allowed_mime_types: vec!["application/pdf".to_string(), "image/*".to_string()],
+            ..ValidationRules::default()
+        };
+
+        assert!(rules.allows("application/pdf"));
+        assert!(rules.allows("image/tiff"));
+        assert!(!rules.allows("application/msword"));
+        assert!(ValidationRules::default().allows("image/jpeg"));
+        assert!(!ValidationRules::default().allows("image/tiff"));
+    }
+
+    #[test]
+    fn test_mime_type_detected_from_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        // Scanners often write files without an extension
+        let extensionless = dir.path().join("scan0001");
+        fs::write(&extensionless, png).unwrap();
+        assert_eq!(FileUpload::guess_mime_type(&extensionless), "image/png");
+
+        // Content wins over a misleading extension
+        let misnamed = dir.path().join("scan.pdf");
+        fs::write(&misnamed, png).unwrap();
+        assert_eq!(FileUpload::new(&misnamed).unwrap().mime_type, "image/png");
+
+        // Formats without a signature fall back to the extension
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "plain text").unwrap();
+        assert_eq!(FileUpload::guess_mime_type(&text), "text/plain");
+    }
+
+    #[test]
+    fn test_mime_type_override() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("scan.dat");
+        fs::write(&file, b"II*\0tiff data").unwrap();
+        let rules = |mime_type: &str| ValidationRules {
+            allowed_mime_types: vec!["image/*".to_string()],
+            mime_type: Some(mime_type.to_string()),
+            ..ValidationRules::default()
+        };
+
+        let upload = FileUpload::new_with_rules(&file, &rules("Image/TIFF")).unwrap();
+        assert_eq!(upload.mime_type, "image/tiff");
+
+        // Content checks still apply to the overridden type
+        let error = FileUpload::new_with_rules(&file, &rules("image/png")).unwrap_err();
+        assert!(error.to_string().contains("valid PDF, PNG, or JPEG"));
+    }
+
+    #[test]
+    fn test_permissive_validation_downgrades_content_checks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("scan.pdf");
+        fs::write(&file, "%PDF-1.7\n/Encrypt 12 0 R").unwrap();
+        let permissive = ValidationRules {
+            mode: ValidationMode::Permissive,
+            ..ValidationRules::default()
+        };
+
+        assert!(FileUpload::new(&file).is_err());
+        assert!(FileUpload::new_with_rules(&file, &permissive).is_ok());
+
+        // Size and format limits are not recoverable
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "plain text").unwrap();
+        assert!(FileUpload::new_with_rules(&text, &permissive).is_err());
+
+        assert_eq!(
+            "Permissive".parse::<ValidationMode>().unwrap(),
+            ValidationMode::Permissive
+        );
+        assert!("lenient".parse::<ValidationMode>().is_err());
+    }
+
+    #[test]
+    fn test_max_file_size_from_rules() {
+        let upload = FileUpload {
+            file_path: "Cargo.toml".to_string(),
+            file_size: 150 * 1024 * 1024,
+            mime_type: "application/pdf".to_string(),
+            file_id: None,
+            upload_status: None,
+            is_valid: false,
+        };
+        let rules = |max_file_size_mb| ValidationRules {
+            max_file_size_mb,
+            ..ValidationRules::default()
+        };
+
+        let error = upload.validate_with_rules(&rules(10)).unwrap_err();
+        assert!(error.to_string().contains("(10 MB)"));
+        // A raised limit lets the size check pass; the content check runs next
+        let error = upload.validate_with_rules(&rules(200)).unwrap_err();
+        assert!(!error.to_string().contains("exceeds maximum"));
+    }
+
+    #[test]
+    fn test_additional_mime_type_skips_magic_bytes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("scan.tiff");
+        fs::write(&path, b"II*\0tiff data").unwrap();
+
+        assert!(FileUpload::new(&path).is_err());
+
+        let rules = ValidationRules {
+            allowed_mime_types: vec!["image/tiff".to_string()],
+            ..ValidationRules::default()
+        };
+        let upload = FileUpload::new_with_rules(&path, &rules).unwrap();
+        assert_eq!(upload.mime_type, "image/tiff");
+    }
+
     #[test]
     fn test_new_valid_pdf() {
         // Create a temporary PDF file
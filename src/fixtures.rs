@@ -0,0 +1,458 @@
+//! Recording and replaying API interactions
+//!
+//! With `--record DIR` the API calls of a run go through a local proxy that
+//! forwards them to the configured API base URL and saves every exchange as a
+//! JSON file in `DIR` (`0001-POST-v1_files.json`, ...). With `--replay DIR`
+//! the proxy answers from those files instead, so the same run works offline
+//! and deterministically against the real binary. This makes bug reports
+//! reproducible without sharing an API key.
+//!
+//! Fixtures are sanitized: credentials are never written, the API key is
+//! redacted wherever it is echoed back, request bodies (which contain the
+//! documents) are stored only as SHA-256 digest and size, and only the
+//! response headers the client reads are kept.
+
+use crate::api::dns::ApiResolver;
+use crate::api::REQUEST_ID_HEADERS;
+use crate::config::NetworkConfig;
+use crate::error::{Error, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Replacement for the API key in recorded responses
+const REDACTED: &str = "[REDACTED]";
+
+/// Response headers kept in fixtures besides the request ID headers
+const KEPT_HEADERS: &[&str] = &["content-type", "retry-after"];
+
+/// A recorded request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// HTTP method
+    pub method: String,
+
+    /// Path and query relative to the API base URL
+    pub path: String,
+
+    /// SHA-256 of the request body as sent (lowercase hex)
+    pub body_sha256: String,
+
+    /// Size of the request body in bytes
+    pub body_bytes: usize,
+}
+
+/// A recorded response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// HTTP status code
+    pub status: u16,
+
+    /// Kept response headers (lowercase names)
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Decompressed response body
+    #[serde(default)]
+    pub body: String,
+}
+
+/// One request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Load the interactions of a fixture directory in recording order
+pub fn load_interactions(dir: &Path) -> Result<Vec<Interaction>> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        Error::Config(format!(
+            "Cannot read fixture directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path).map_err(Error::Io)?;
+            serde_json::from_str(&content)
+                .map_err(|e| Error::Config(format!("Invalid fixture '{}': {}", path.display(), e)))
+        })
+        .collect()
+}
+
+/// Local server standing in for the API during a run; stops when dropped
+pub struct FixtureServer {
+    url: String,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FixtureServer {
+    /// Base URL to use as the API base URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Forward requests to `upstream` and record them in `dir`
+    pub fn record(
+        dir: &Path,
+        upstream: &str,
+        api_key: &str,
+        network: &NetworkConfig,
+    ) -> Result<Self> {
+        fs::create_dir_all(dir).map_err(Error::Io)?;
+
+        let resolver =
+            ApiResolver::for_url(network.ip_preference, network.resolve.clone(), upstream);
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(resolver))
+            .build()
+            .map_err(|e| Error::internal_from("Failed to create HTTP client", e))?;
+
+        let recorder = Arc::new(Recorder {
+            client,
+            upstream: upstream.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            dir: dir.to_path_buf(),
+            sequence: AtomicUsize::new(0),
+        });
+
+        Self::serve(move |request| {
+            let recorder = recorder.clone();
+            async move { recorder.forward(request).await }
+        })
+    }
+
+    /// Answer requests with the interactions recorded in `dir`
+    pub fn replay(dir: &Path) -> Result<Self> {
+        let interactions = load_interactions(dir)?;
+        if interactions.is_empty() {
+            return Err(Error::Config(format!(
+                "No fixtures found in '{}'",
+                dir.display()
+            )));
+        }
+
+        let player = Arc::new(Player {
+            interactions: Mutex::new(interactions.into_iter().map(Some).collect()),
+        });
+
+        Self::serve(move |request| {
+            let player = player.clone();
+            async move { Ok(player.answer(request).await) }
+        })
+    }
+
+    /// Serve requests on a free loopback port
+    fn serve<H, F>(handler: H) -> Result<Self>
+    where
+        H: Fn(Request<Body>) -> F + Clone + Send + Sync + 'static,
+        F: std::future::Future<Output = std::result::Result<Response<Body>, Infallible>>
+            + Send
+            + 'static,
+    {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).map_err(Error::Io)?;
+        let address = listener.local_addr().map_err(Error::Io)?;
+
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move { Ok::<_, Infallible>(service_fn(handler)) }
+        });
+        let server = hyper::Server::from_tcp(listener)
+            .map_err(|e| Error::internal_from("Failed to start fixture server", e))?
+            .serve(make_service);
+
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::warn!("Fixture server failed: {}", e);
+            }
+        });
+
+        Ok(Self {
+            url: format!("http://{}", address),
+            task,
+        })
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Forwards requests upstream and writes fixtures
+struct Recorder {
+    client: reqwest::Client,
+    upstream: String,
+    api_key: String,
+    dir: PathBuf,
+    sequence: AtomicUsize,
+}
+
+impl Recorder {
+    async fn forward(
+        &self,
+        request: Request<Body>,
+    ) -> std::result::Result<Response<Body>, Infallible> {
+        let (parts, body) = request.into_parts();
+        let path = path_and_query(&parts.uri);
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return Ok(error_response(StatusCode::BAD_REQUEST, &e.to_string())),
+        };
+
+        let mut upstream = self
+            .client
+            .request(parts.method.clone(), format!("{}{}", self.upstream, path));
+        for (name, value) in &parts.headers {
+            // The client negotiates its own connection and compression
+            if !matches!(
+                name.as_str(),
+                "host" | "content-length" | "accept-encoding" | "connection"
+            ) {
+                upstream = upstream.header(name, value);
+            }
+        }
+
+        let response = match upstream.body(body.to_vec()).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                return Ok(error_response(
+                    StatusCode::BAD_GATEWAY,
+                    &format!("Recording proxy could not reach {}: {}", self.upstream, e),
+                ))
+            }
+        };
+
+        let status = response.status().as_u16();
+        let headers = kept_headers(response.headers());
+        let response_body = match response.bytes().await {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => return Ok(error_response(StatusCode::BAD_GATEWAY, &e.to_string())),
+        };
+
+        let interaction = Interaction {
+            request: RecordedRequest {
+                method: parts.method.to_string(),
+                path,
+                body_sha256: format!("{:x}", Sha256::digest(&body)),
+                body_bytes: body.len(),
+            },
+            response: RecordedResponse {
+                status,
+                headers,
+                body: response_body,
+            },
+        };
+        let interaction = self.sanitize(interaction);
+
+        if let Err(e) = self.save(&interaction) {
+            tracing::warn!("Failed to save fixture: {}", e);
+        }
+
+        Ok(to_response(&interaction.response))
+    }
+
+    /// Redact the API key wherever the API echoed it back
+    fn sanitize(&self, mut interaction: Interaction) -> Interaction {
+        if self.api_key.is_empty() {
+            return interaction;
+        }
+
+        let response = &mut interaction.response;
+        response.body = response.body.replace(&self.api_key, REDACTED);
+        for value in response.headers.values_mut() {
+            *value = value.replace(&self.api_key, REDACTED);
+        }
+        interaction.request.path = interaction.request.path.replace(&self.api_key, REDACTED);
+        interaction
+    }
+
+    /// Write an interaction to the next numbered file
+    fn save(&self, interaction: &Interaction) -> Result<()> {
+        let number = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        let endpoint: String = interaction
+            .request
+            .path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .trim_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = self.dir.join(format!(
+            "{:04}-{}-{}.json",
+            number, interaction.request.method, endpoint
+        ));
+
+        let json = serde_json::to_string_pretty(interaction)
+            .map_err(|e| Error::internal_from("Failed to serialize fixture", e))?;
+        fs::write(&path, json + "\n").map_err(Error::Io)?;
+        tracing::debug!("Recorded {}", path.display());
+        Ok(())
+    }
+}
+
+/// Answers requests from recorded interactions
+struct Player {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl Player {
+    async fn answer(&self, request: Request<Body>) -> Response<Body> {
+        let method = request.method().to_string();
+        let path = path_and_query(request.uri());
+        let endpoint = path.split('?').next().unwrap_or_default().to_string();
+
+        let mut interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        // Each interaction is used once in recording order; an exact match
+        // wins over one that differs only in the query
+        let position = interactions
+            .iter()
+            .position(|i| {
+                i.as_ref()
+                    .is_some_and(|i| i.request.method == method && i.request.path == path)
+            })
+            .or_else(|| {
+                interactions.iter().position(|i| {
+                    i.as_ref().is_some_and(|i| {
+                        i.request.method == method
+                            && i.request.path.split('?').next() == Some(endpoint.as_str())
+                    })
+                })
+            });
+
+        match position.and_then(|position| interactions[position].take()) {
+            Some(interaction) => {
+                tracing::debug!("Replaying {} {}", method, path);
+                to_response(&interaction.response)
+            }
+            None => error_response(
+                StatusCode::NOT_IMPLEMENTED,
+                &format!("No recorded interaction left for {} {}", method, path),
+            ),
+        }
+    }
+}
+
+fn path_and_query(uri: &hyper::Uri) -> String {
+    uri.path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+fn kept_headers(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            KEPT_HEADERS.contains(&name.as_str()) || REQUEST_ID_HEADERS.contains(&name.as_str())
+        })
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn to_response(recorded: &RecordedResponse) -> Response<Body> {
+    let mut builder = Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name, value);
+    }
+    builder
+        .body(Body::from(recorded.body.clone()))
+        .unwrap_or_else(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(
+        serde_json::json!({ "message": message }).to_string(),
+    ));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-request-id", "req-1")
+                    .insert_header("set-cookie", "session=abc")
+                    .set_body_json(serde_json::json!({"id": "file-1", "key": "sk-secret"})),
+            )
+            .mount(&upstream)
+            .await;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let recorder = FixtureServer::record(
+            dir.path(),
+            &upstream.uri(),
+            "sk-secret",
+            &NetworkConfig::default(),
+        )
+        .unwrap();
+        let client = reqwest::Client::new();
+        let recorded = client
+            .post(format!("{}/v1/files", recorder.url()))
+            .header("authorization", "Bearer sk-secret")
+            .body("document")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(recorded.headers()["x-request-id"], "req-1");
+        assert!(recorded.headers().get("set-cookie").is_none());
+        let recorded = recorded.text().await.unwrap();
+        drop(recorder);
+
+        let interactions = load_interactions(dir.path()).unwrap();
+        assert_eq!(interactions.len(), 1);
+        assert_eq!(interactions[0].request.path, "/v1/files");
+        assert_eq!(interactions[0].request.body_bytes, 8);
+        let fixture = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap();
+        assert_eq!(fixture.file_name(), "0001-POST-v1_files.json");
+        let content = fs::read_to_string(fixture.path()).unwrap();
+        assert!(!content.contains("sk-secret"), "{}", content);
+
+        let replayer = FixtureServer::replay(dir.path()).unwrap();
+        let replayed = client
+            .post(format!("{}/v1/files", replayer.url()))
+            .body("document")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(replayed.status(), 200);
+        assert_eq!(replayed.text().await.unwrap(), recorded);
+
+        // Each interaction is replayed once
+        let exhausted = client
+            .post(format!("{}/v1/files", replayer.url()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(exhausted.status(), 501);
+    }
+}
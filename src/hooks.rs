@@ -0,0 +1,226 @@
+//! Commands run after each document
+//!
+//! `--on-success` and `--on-failure` run a command after every document, so
+//! results can be handed to other tools without a daemon in between:
+//!
+//! ```text
+//! --on-success 'curl -T {output} https://archive.example.com/'
+//! --on-failure 'mv {input} failed/'
+//! ```
+//!
+//! The command is split into words like a shell would, then the placeholders
+//! are replaced in each word; it is run directly, not through a shell, so file
+//! names cannot inject commands. The same values are set as `OCR_*`
+//! environment variables for scripts (`sh -c 'echo "$OCR_INPUT"'`).
+//!
+//! A failing hook is logged and does not change the outcome of the document.
+
+use crate::error::Error;
+use crate::ocr::OCRResult;
+use std::path::Path;
+use std::process::Stdio;
+
+/// Placeholders and the environment variables carrying the same values
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("input", "OCR_INPUT"),
+    ("name", "OCR_NAME"),
+    ("output", "OCR_OUTPUT"),
+    ("pages", "OCR_PAGES"),
+    ("provider", "OCR_PROVIDER"),
+    ("error", "OCR_ERROR"),
+    ("exit_code", "OCR_EXIT_CODE"),
+];
+
+/// Commands to run after each document
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    /// Command run after a document succeeded
+    pub on_success: Option<String>,
+
+    /// Command run after a document failed
+    pub on_failure: Option<String>,
+}
+
+impl Hooks {
+    /// Run the success hook for a processed document and its result file
+    pub async fn succeeded(&self, input: &Path, output: Option<&Path>, result: &OCRResult) {
+        let Some(ref template) = self.on_success else {
+            return;
+        };
+        let values = [
+            ("input", input.display().to_string()),
+            ("name", file_name(input)),
+            (
+                "output",
+                output
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            ),
+            ("pages", result.page_count().to_string()),
+            ("provider", result.provider.clone().unwrap_or_default()),
+            ("error", String::new()),
+            ("exit_code", "0".to_string()),
+        ];
+        run(template, &values).await;
+    }
+
+    /// Run the failure hook for a document that could not be processed
+    pub async fn failed(&self, input: &Path, error: &Error) {
+        let Some(ref template) = self.on_failure else {
+            return;
+        };
+        let values = [
+            ("input", input.display().to_string()),
+            ("name", file_name(input)),
+            ("output", String::new()),
+            ("pages", String::new()),
+            ("provider", String::new()),
+            ("error", error.user_message()),
+            ("exit_code", error.exit_code().to_string()),
+        ];
+        run(template, &values).await;
+    }
+}
+
+/// Check that a hook command can be split into words and only uses known
+/// placeholders
+pub fn check_template(template: &str) -> std::result::Result<(), String> {
+    let words = shlex::split(template)
+        .ok_or_else(|| "Unbalanced quotes or trailing backslash".to_string())?;
+    if words.is_empty() {
+        return Err("The command is empty".to_string());
+    }
+    for word in &words {
+        for name in placeholders(word) {
+            if !PLACEHOLDERS.iter().any(|(known, _)| *known == name) {
+                let known: Vec<String> = PLACEHOLDERS
+                    .iter()
+                    .map(|(name, _)| format!("{{{}}}", name))
+                    .collect();
+                return Err(format!(
+                    "Unknown placeholder {{{}}}; use {}",
+                    name,
+                    known.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Names of the `{name}` placeholders in a word
+fn placeholders(word: &str) -> impl Iterator<Item = &str> {
+    word.split('{').skip(1).filter_map(|rest| {
+        let (name, _) = rest.split_once('}')?;
+        (!name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+            .then_some(name)
+    })
+}
+
+/// Words of the command with the placeholders replaced
+fn render(template: &str, values: &[(&str, String)]) -> Option<Vec<String>> {
+    let words = shlex::split(template)?;
+    Some(words.iter().map(|word| substitute(word, values)).collect())
+}
+
+/// Replace the placeholders in one pass, so values are never expanded again
+fn substitute(word: &str, values: &[(&str, String)]) -> String {
+    let mut substituted = String::with_capacity(word.len());
+    let mut rest = word;
+    while let Some(start) = rest.find('{') {
+        substituted.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.split_once('}').and_then(|(name, _)| {
+            values
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(_, value)| (name.len(), value))
+        });
+        match value {
+            Some((length, value)) => {
+                substituted.push_str(value);
+                rest = &after[length + 1..];
+            }
+            None => {
+                substituted.push('{');
+                rest = after;
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+async fn run(template: &str, values: &[(&str, String)]) {
+    let Some(words) = render(template, values) else {
+        tracing::warn!("Invalid hook command: {}", template);
+        return;
+    };
+    let Some((program, args)) = words.split_first() else {
+        return;
+    };
+
+    let mut command = tokio::process::Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for (name, variable) in PLACEHOLDERS {
+        if let Some((_, value)) = values.iter().find(|(value_name, _)| value_name == name) {
+            command.env(variable, value);
+        }
+    }
+
+    tracing::debug!("Running hook: {}", words.join(" "));
+    match command.output().await {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::warn!(
+            "Hook '{}' exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => tracing::warn!("Failed to run hook '{}': {}", program, e),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_replaces_placeholders_per_word() {
+        let values = [
+            ("input", "in/{output}.pdf".to_string()),
+            ("output", "out/my scan.pdf.txt; rm -rf /".to_string()),
+        ];
+        assert_eq!(
+            render("cp '{input}' --to={output} {}", &values).unwrap(),
+            [
+                "cp",
+                "in/{output}.pdf",
+                "--to=out/my scan.pdf.txt; rm -rf /",
+                "{}"
+            ]
+        );
+        assert!(render("echo 'unbalanced", &values).is_none());
+    }
+
+    #[test]
+    fn test_check_template() {
+        assert!(check_template("notify-send 'OCR done' {name} {pages}").is_ok());
+        assert!(check_template("jq {} {output}").is_ok());
+        assert!(check_template("echo {inptu}")
+            .unwrap_err()
+            .contains("Unknown placeholder {inptu}"));
+        assert!(check_template("echo 'unbalanced").is_err());
+        assert!(check_template("  ").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Single-shot jobs driven by a manifest
+//!
+//! `batch --job-manifest jobs.json` processes the files listed in a manifest,
+//! writes a results manifest, and exits, for a Kubernetes Job or another
+//! orchestrator that mounts the manifest and reads the results afterwards:
+//!
+//! ```json
+//! {
+//!   "job": "nightly-2024-06-01",
+//!   "output_dir": "/results",
+//!   "files": [
+//!     "/data/inbox/scan.pdf",
+//!     { "path": "/data/inbox/letter.pdf", "output": "letters/42", "id": "42" }
+//!   ]
+//! }
+//! ```
+//!
+//! Relative paths are relative to the working directory. The results manifest
+//! lists every file with its status (`succeeded`, `failed`, or `skipped` when
+//! the run stopped early) and the aggregate status and exit code of the job.
+
+use crate::batch::{BatchInput, BatchReport};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default name of the results manifest in the output directory
+pub const RESULTS_FILE: &str = "job-results.json";
+
+/// Files a job processes and where its results go
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobManifest {
+    /// Name of the job, copied to the results
+    #[serde(default)]
+    pub job: Option<String>,
+
+    /// Directory for the results, unless `--output-dir` is given
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Path of the results manifest (default: `job-results.json` in the
+    /// output directory)
+    #[serde(default)]
+    pub results: Option<PathBuf>,
+
+    /// Documents to process, in order
+    pub files: Vec<JobFile>,
+}
+
+/// A document listed in a job manifest
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum JobFile {
+    /// Path of the document
+    Path(PathBuf),
+
+    /// Document with its output path and ID
+    Entry {
+        /// Path of the document
+        path: PathBuf,
+
+        /// Result path below the output directory, without the `.txt`/`.json`
+        /// suffix (default: the file name)
+        #[serde(default)]
+        output: Option<PathBuf>,
+
+        /// ID of the document for the orchestrator, copied to the results
+        #[serde(default)]
+        id: Option<String>,
+    },
+}
+
+impl JobFile {
+    /// Path of the document
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Path(path) | Self::Entry { path, .. } => path,
+        }
+    }
+
+    /// ID of the document, if any
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Path(_) => None,
+            Self::Entry { id, .. } => id.as_deref(),
+        }
+    }
+
+    /// Result path below the output directory
+    fn relative(&self) -> PathBuf {
+        match self {
+            Self::Entry {
+                output: Some(output),
+                ..
+            } => output.clone(),
+            _ => PathBuf::from(self.path().file_name().unwrap_or_default()),
+        }
+    }
+}
+
+impl JobManifest {
+    /// Read and check a job manifest
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read job manifest {}: {}", path.display(), e),
+            ))
+        })?;
+        let manifest: Self = serde_json::from_str(&content).map_err(|e| {
+            Error::validation_from(format!("Invalid job manifest {}", path.display()), e)
+        })?;
+        manifest.check().map_err(|e| {
+            Error::validation_from(format!("Invalid job manifest {}", path.display()), e)
+        })?;
+        Ok(manifest)
+    }
+
+    fn check(&self) -> std::result::Result<(), String> {
+        if self.files.is_empty() {
+            return Err("`files` is empty".to_string());
+        }
+        let mut outputs: HashMap<PathBuf, &JobFile> = HashMap::new();
+        for file in &self.files {
+            let relative = file.relative();
+            let inside = relative.components().all(|component| {
+                matches!(
+                    component,
+                    std::path::Component::Normal(_) | std::path::Component::CurDir
+                )
+            });
+            if !inside || relative.file_name().is_none() {
+                return Err(format!(
+                    "output of {} must be a relative path inside the output directory",
+                    file.path().display()
+                ));
+            }
+            // Documents of the same name in different directories would
+            // overwrite each other's results
+            let normalized: PathBuf = relative
+                .components()
+                .filter(|component| *component != std::path::Component::CurDir)
+                .collect();
+            if let Some(other) = outputs.insert(normalized, file) {
+                return Err(format!(
+                    "{} and {} both write their results to {}; set `output` for one of them",
+                    other.path().display(),
+                    file.path().display(),
+                    relative.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Documents of the job in manifest order
+    ///
+    /// Missing files are kept, so they fail and are reported like any other
+    /// document instead of failing the whole job.
+    pub fn inputs(&self) -> Vec<BatchInput> {
+        self.files
+            .iter()
+            .map(|file| BatchInput {
+                path: file.path().to_path_buf(),
+                relative: file.relative(),
+            })
+            .collect()
+    }
+
+    /// Where to write the results manifest
+    pub fn results_path(&self, output_dir: Option<&Path>) -> PathBuf {
+        self.results.clone().unwrap_or_else(|| {
+            output_dir
+                .unwrap_or_else(|| Path::new("."))
+                .join(RESULTS_FILE)
+        })
+    }
+}
+
+/// Aggregate status of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Every document was processed
+    Succeeded,
+
+    /// Some documents were processed, others failed or were skipped
+    Partial,
+
+    /// No document was processed
+    Failed,
+}
+
+/// Outcome of a document of a job
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobFileResult {
+    /// ID from the job manifest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Path of the document
+    pub file: String,
+
+    /// `succeeded`, `failed`, or `skipped`
+    pub status: &'static str,
+
+    /// Written result, if any
+    pub output: Option<String>,
+
+    /// Pages extracted
+    pub pages: u64,
+
+    /// Processing time of the document
+    pub duration_ms: u64,
+
+    /// Why the document failed
+    pub error: Option<String>,
+
+    /// Exit code of the failure (see `explain`)
+    pub exit_code: Option<i32>,
+}
+
+/// Results manifest of a job
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobResults {
+    /// Name of the job from the manifest
+    pub job: Option<String>,
+
+    /// Aggregate status
+    pub status: JobStatus,
+
+    /// Exit code of the process
+    pub exit_code: i32,
+
+    /// When the job started
+    pub started_at: DateTime<Utc>,
+
+    /// When the job finished
+    pub finished_at: DateTime<Utc>,
+
+    /// Documents processed
+    pub succeeded: usize,
+
+    /// Documents that failed
+    pub failed: usize,
+
+    /// Documents not attempted because the run stopped early
+    pub skipped: usize,
+
+    /// Every document of the manifest, in manifest order
+    pub files: Vec<JobFileResult>,
+}
+
+impl JobResults {
+    /// Results of a finished job; `error` decides the exit code
+    pub fn new(
+        manifest: &JobManifest,
+        report: &BatchReport,
+        error: Option<&Error>,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        // The batch keeps manifest order and stops early without reordering,
+        // so the report lists the first documents of the manifest
+        let files = manifest
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| match report.files.get(i) {
+                Some(outcome) => JobFileResult {
+                    id: file.id().map(str::to_string),
+                    file: outcome.file.clone(),
+                    status: if outcome.success {
+                        "succeeded"
+                    } else {
+                        "failed"
+                    },
+                    output: outcome.output.clone(),
+                    pages: outcome.pages,
+                    duration_ms: outcome.duration_ms,
+                    error: outcome.error.clone(),
+                    exit_code: outcome.exit_code,
+                },
+                None => JobFileResult {
+                    id: file.id().map(str::to_string),
+                    file: file.path().display().to_string(),
+                    status: "skipped",
+                    output: None,
+                    pages: 0,
+                    duration_ms: 0,
+                    error: None,
+                    exit_code: None,
+                },
+            })
+            .collect();
+
+        let status = if report.succeeded == 0 {
+            JobStatus::Failed
+        } else if error.is_some() || report.failed > 0 || report.skipped > 0 {
+            JobStatus::Partial
+        } else {
+            JobStatus::Succeeded
+        };
+        Self {
+            job: manifest.job.clone(),
+            status,
+            exit_code: error.map_or(0, Error::exit_code),
+            started_at,
+            finished_at: Utc::now(),
+            succeeded: report.succeeded,
+            failed: report.failed,
+            skipped: report.skipped,
+            files,
+        }
+    }
+
+    /// Write the results to `path`
+    ///
+    /// The file is written next to its destination and renamed into place, so
+    /// a reader never sees half of it.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::internal_from("Failed to serialize job results", e))?;
+
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        fs::write(&partial, json).map_err(Error::Io)?;
+        fs::rename(&partial, path).map_err(Error::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_lists_inputs_and_results_cover_every_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("jobs.json");
+        fs::write(
+            &path,
+            r#"{
+                "job": "nightly",
+                "files": [
+                    "in/a.pdf",
+                    {"path": "in/b.pdf", "output": "letters/42", "id": "42"},
+                    "in/c.pdf"
+                ]
+            }"#,
+        )
+        .unwrap();
+        let manifest = JobManifest::load(&path).unwrap();
+
+        let inputs = manifest.inputs();
+        assert_eq!(inputs[0].relative, PathBuf::from("a.pdf"));
+        assert_eq!(inputs[1].path, PathBuf::from("in/b.pdf"));
+        assert_eq!(inputs[1].relative, PathBuf::from("letters/42"));
+        assert_eq!(
+            manifest.results_path(Some(Path::new("out"))),
+            Path::new("out").join(RESULTS_FILE)
+        );
+
+        let mut report = BatchReport::default();
+        report.push_success(Path::new("in/a.pdf"), 1, 10, None);
+        let error = Error::BudgetExceeded("page budget".to_string());
+        report.push_failure(Path::new("in/b.pdf"), &error);
+        report.skipped = 1;
+
+        let results = JobResults::new(&manifest, &report, Some(&error), Utc::now());
+        assert_eq!(results.status, JobStatus::Partial);
+        assert_eq!(results.exit_code, 7);
+        let statuses: Vec<_> = results.files.iter().map(|file| file.status).collect();
+        assert_eq!(statuses, ["succeeded", "failed", "skipped"]);
+        assert_eq!(results.files[1].id.as_deref(), Some("42"));
+        assert_eq!(results.files[1].exit_code, Some(7));
+
+        let written = dir.path().join("results/job.json");
+        results.write(&written).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&written).unwrap()).unwrap();
+        assert_eq!(json["status"], "partial");
+        assert_eq!(json["job"], "nightly");
+    }
+
+    #[test]
+    fn test_invalid_manifests_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("jobs.json");
+        for content in [
+            r#"{"files": []}"#,
+            r#"{"files": ["a.pdf"], "outputs": "x"}"#,
+            r#"{"files": [{"path": "a.pdf", "output": "../a"}]}"#,
+            "not json",
+        ] {
+            fs::write(&path, content).unwrap();
+            let error = JobManifest::load(&path).unwrap_err();
+            assert_eq!(error.exit_code(), 2, "{}", content);
+        }
+
+        // Results of documents with the same name would overwrite each other
+        fs::write(
+            &path,
+            r#"{"files": ["/a/scan.pdf", {"path": "/c/x.pdf", "output": "./scan.pdf"}]}"#,
+        )
+        .unwrap();
+        let error = JobManifest::load(&path).unwrap_err().to_string();
+        assert!(error.contains("/a/scan.pdf and /c/x.pdf"), "{}", error);
+        fs::write(
+            &path,
+            r#"{"files": ["/a/scan.pdf", {"path": "/b/scan.pdf", "output": "b/scan.pdf"}]}"#,
+        )
+        .unwrap();
+        assert!(JobManifest::load(&path).is_ok());
+        assert_eq!(
+            JobManifest::load(&dir.path().join("missing.json"))
+                .unwrap_err()
+                .exit_code(),
+            3
+        );
+    }
+}
@@ -4,25 +4,88 @@
 //! Supports TOML configuration, 12-factor app principles, and provides both
 //! human-readable and JSON output formats.
 
+pub mod access;
 pub mod api;
+pub mod batch;
+pub mod boilerplate;
 pub mod cache;
+#[cfg(feature = "cli")]
+pub mod cache_backup;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cli")]
+pub mod compare;
+pub mod compress;
+pub mod concat;
 pub mod config;
+pub mod correspondents;
 pub mod credentials;
+pub mod dates;
+pub mod diagnostics;
+pub mod encrypt;
+pub mod ensemble;
 pub mod error;
+#[cfg(feature = "cli")]
+pub mod eval;
+#[cfg(feature = "cli")]
+pub mod exclude;
+pub mod extraction;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod file;
+#[cfg(feature = "cli")]
+pub mod fixtures;
+#[cfg(feature = "cli")]
+pub mod hooks;
+pub mod job;
+pub mod lock;
+pub mod log_file;
+pub mod manifest;
+pub mod memory;
 pub mod metrics;
+pub mod notify;
 pub mod ocr;
+pub mod output;
+pub mod page_cache;
+pub mod paperless;
+pub mod paths;
+pub mod pipeline;
+#[cfg(feature = "cli")]
+pub mod plain_text;
+pub mod progress;
+pub mod provider;
+pub mod quality;
+#[cfg(feature = "cli")]
+pub mod queue;
+pub mod quota;
+pub mod render;
+pub mod result_cache;
+pub mod scratch;
+#[cfg(feature = "cli")]
+pub mod server;
+#[cfg(feature = "cli")]
+pub mod sidecar;
+pub mod split;
+pub mod store;
+pub mod summary;
+pub mod tags;
+pub mod tenant;
+pub mod text_layer;
+pub mod trace_context;
+pub mod transform;
+#[cfg(feature = "cli")]
+pub mod watch;
 
-pub use cache::{generate_file_hash, CacheManager, FileCacheKey, OCRCacheKey, GLOBAL_CACHE};
-pub use config::{Config, RetryPolicy};
+pub use cache::{generate_file_hash, CacheManager, FileCacheKey, OCRCacheKey};
+pub use config::{Config, HttpConfig, NetworkConfig, PaperlessConfig, QualityConfig, RetryPolicy};
 pub use credentials::APICredentials;
 pub use error::{Error, Result};
 pub use file::FileUpload;
-pub use metrics::{APIMetrics, FileMetrics, MetricsCollector, GLOBAL_METRICS};
+pub use metrics::{APIMetrics, FileMetrics, MetricsCollector};
 pub use ocr::OCRResult;
 
 /// Initialize the application with proper logging configuration
+#[cfg(feature = "cli")]
 pub fn init_app() -> Result<()> {
     // Initialize tracing subscriber
     tracing_subscriber::fmt()
@@ -43,7 +106,14 @@ pub fn init_app() -> Result<()> {
 }
 
 /// Initialize logging configuration
+#[cfg(feature = "cli")]
 pub fn init_logging(verbose: bool) -> Result<()> {
+    init_logging_to(verbose, None)
+}
+
+/// Initialize logging to stderr, or to a rotating log file
+#[cfg(feature = "cli")]
+pub fn init_logging_to(verbose: bool, log_file: Option<log_file::RotatingFile>) -> Result<()> {
     let log_level = if verbose {
         "paperless_ngx_ocr2=debug"
     } else {
@@ -61,13 +131,20 @@ pub fn init_logging(verbose: bool) -> Result<()> {
         .with_thread_ids(false)
         .with_thread_names(false)
         .with_file(false)
-        .with_line_number(false)
-        .with_writer(std::io::stderr);
+        .with_line_number(false);
 
-    if log_format == "json" {
-        subscriber.json().init();
-    } else {
-        subscriber.init();
+    match (log_file, log_format == "json") {
+        (Some(file), true) => subscriber
+            .with_writer(std::sync::Mutex::new(file))
+            .with_ansi(false)
+            .json()
+            .init(),
+        (Some(file), false) => subscriber
+            .with_writer(std::sync::Mutex::new(file))
+            .with_ansi(false)
+            .init(),
+        (None, true) => subscriber.with_writer(std::io::stderr).json().init(),
+        (None, false) => subscriber.with_writer(std::io::stderr).init(),
     }
 
     tracing::debug!("Logging initialized with level: {}", log_level);
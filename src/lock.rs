@@ -0,0 +1,179 @@
+//! Advisory locks on documents for concurrent workers
+//!
+//! Several `watch` processes may share an inbox, for example one per host on a
+//! network share, or a restarted watcher next to one that has not exited yet.
+//! Before a document is processed, a worker takes an exclusive advisory lock
+//! on `<lock dir>/<sha256 of the content>.lock`; a document whose lock is held
+//! is left to the worker holding it. Keying the lock by content also keeps
+//! copies of the same document in different directories from being processed
+//! twice at the same time.
+//!
+//! The operating system releases the lock when the process exits, so a
+//! crashed worker never leaves a document locked. Locks need a filesystem with
+//! working `flock` (on Windows, exclusive share modes); NFS needs a lock
+//! manager for that.
+
+use crate::error::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Default lock directory inside the watched directory
+pub const LOCK_DIR: &str = ".ocr-locks";
+
+/// Exclusive lock on a document, released and removed when dropped
+#[derive(Debug)]
+pub struct DocumentLock {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl DocumentLock {
+    /// Lock `document` in `lock_dir`, or `None` while another worker holds
+    /// the lock
+    pub fn try_acquire(lock_dir: &Path, document: &Path) -> Result<Option<Self>> {
+        fs::create_dir_all(lock_dir).map_err(Error::Io)?;
+        let path = lock_dir.join(format!("{}.lock", content_hash(document)?));
+
+        let Some(mut file) = try_lock(&path)? else {
+            return Ok(None);
+        };
+
+        // The previous holder removes the file before unlocking it; a lock on
+        // a file that is no longer at `path` does not exclude anyone
+        if !is_same_file(&file, &path) {
+            return Ok(None);
+        }
+
+        // Who holds the lock, for whoever finds it while debugging
+        let _ = file.set_len(0);
+        let _ = writeln!(file, "{}", std::process::id());
+        Ok(Some(Self {
+            path,
+            file: Some(file),
+        }))
+    }
+
+    /// Lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DocumentLock {
+    fn drop(&mut self) {
+        // Remove the file while still holding the lock, then release it
+        let _ = fs::remove_file(&self.path);
+        drop(self.file.take());
+    }
+}
+
+/// SHA-256 of the file contents as lowercase hex
+fn content_hash(path: &Path) -> Result<String> {
+    let mut file = File::open(path).map_err(Error::Io)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(Error::Io)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Open and exclusively lock the file at `path`, or `None` while someone else
+/// holds the lock
+#[cfg(unix)]
+fn try_lock(path: &Path) -> Result<Option<File>> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = open_lock_file(&mut OpenOptions::new(), path)?;
+    // SAFETY: the descriptor belongs to `file`, which outlives the call
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(Some(file));
+    }
+    let error = std::io::Error::last_os_error();
+    match error.kind() {
+        std::io::ErrorKind::WouldBlock => Ok(None),
+        _ => Err(Error::Io(error)),
+    }
+}
+
+/// Open and exclusively lock the file at `path`, or `None` while someone else
+/// holds the lock
+#[cfg(windows)]
+fn try_lock(path: &Path) -> Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    /// Let the holder delete the file, but nobody else open it
+    const FILE_SHARE_DELETE: u32 = 0x4;
+    /// Another handle to the file is open
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    match open_lock_file(OpenOptions::new().share_mode(FILE_SHARE_DELETE), path) {
+        Ok(file) => Ok(Some(file)),
+        Err(Error::Io(e)) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn open_lock_file(options: &mut OpenOptions, path: &Path) -> Result<File> {
+    options
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(Error::Io)
+}
+
+/// Whether the open `file` is still the file at `path`
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Whether the open `file` is still the file at `path`
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    // Without inode numbers, only check that a lock file is still there
+    path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_excludes_the_same_content() {
+        let dir = TempDir::new().unwrap();
+        let locks = dir.path().join(LOCK_DIR);
+        fs::write(dir.path().join("a.pdf"), b"%PDF-1.4\n").unwrap();
+        fs::write(dir.path().join("copy.pdf"), b"%PDF-1.4\n").unwrap();
+        fs::write(dir.path().join("b.pdf"), b"%PDF-1.4\n%other\n").unwrap();
+
+        let lock = DocumentLock::try_acquire(&locks, &dir.path().join("a.pdf"))
+            .unwrap()
+            .unwrap();
+        assert!(lock.path().is_file());
+        assert!(
+            DocumentLock::try_acquire(&locks, &dir.path().join("copy.pdf"))
+                .unwrap()
+                .is_none()
+        );
+        let other = DocumentLock::try_acquire(&locks, &dir.path().join("b.pdf")).unwrap();
+        assert!(other.is_some());
+
+        let path = lock.path().to_path_buf();
+        drop(lock);
+        assert!(!path.exists());
+        assert!(
+            DocumentLock::try_acquire(&locks, &dir.path().join("copy.pdf"))
+                .unwrap()
+                .is_some()
+        );
+
+        assert!(DocumentLock::try_acquire(&locks, &dir.path().join("missing.pdf")).is_err());
+    }
+}
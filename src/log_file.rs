@@ -0,0 +1,236 @@
+//! Log file with size- and time-based rotation
+//!
+//! `--log-file` sends the logs of long-running `watch` and `serve` processes to
+//! a file instead of stderr. The file is rotated once it would grow past
+//! `--log-max-size` megabytes or when the hour or day changes
+//! (`--log-rotation hourly|daily`); the rotated file gets the time of the
+//! rotation as a suffix (`ocr.log.20240601T120000.000`), and only the newest
+//! `--log-keep` rotated files are kept, so the logs cannot fill the disk.
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Local};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Time-based rotation of the log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    /// Rotate by size only
+    #[default]
+    Never,
+    /// Start a new file every hour
+    Hourly,
+    /// Start a new file every day
+    Daily,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(Self::Never),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            other => Err(format!(
+                "Unknown log rotation: {}. Supported: never, hourly, daily",
+                other
+            )),
+        }
+    }
+}
+
+impl LogRotation {
+    /// Period a point in time belongs to; a new period starts a new file
+    fn period(self, time: DateTime<Local>) -> Option<String> {
+        match self {
+            Self::Never => None,
+            Self::Hourly => Some(time.format("%Y-%m-%dT%H").to_string()),
+            Self::Daily => Some(time.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+/// When to rotate the log file and how many rotated files to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Rotate before the file grows past this many bytes
+    pub max_bytes: Option<u64>,
+
+    /// Rotate when the hour or day changes
+    pub rotation: LogRotation,
+
+    /// Rotated files to keep; older ones are deleted
+    pub keep: usize,
+}
+
+/// Appends to a log file and rotates it according to a [`RotationPolicy`]
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    policy: RotationPolicy,
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its directory if needed
+    ///
+    /// A file left over from an earlier period is rotated on the first write.
+    pub fn open(path: PathBuf, policy: RotationPolicy) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+        let file = open_append(&path).map_err(Error::Io)?;
+        let metadata = file.metadata().map_err(Error::Io)?;
+        let modified = metadata.modified().map(DateTime::<Local>::from);
+
+        Ok(Self {
+            period: policy
+                .rotation
+                .period(modified.unwrap_or_else(|_| Local::now())),
+            size: metadata.len(),
+            path,
+            policy,
+            file,
+        })
+    }
+
+    fn should_rotate(&self, incoming: usize, period: &Option<String>) -> bool {
+        let too_large = self.policy.max_bytes.is_some_and(|max_bytes| {
+            self.size > 0 && self.size.saturating_add(incoming as u64) > max_bytes
+        });
+        too_large || (self.size > 0 && *period != self.period)
+    }
+
+    /// Move the current file aside, start a new one, and prune old files
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let stamp = Local::now().format("%Y%m%dT%H%M%S%.3f").to_string();
+        let mut rotated = suffixed(&self.path, &stamp);
+        let mut attempt = 1;
+        while rotated.exists() {
+            rotated = suffixed(&self.path, &format!("{}.{}", stamp, attempt));
+            attempt += 1;
+        }
+        fs::rename(&self.path, &rotated)?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.prune()
+    }
+
+    /// Delete all but the newest `keep` rotated files
+    fn prune(&self) -> io::Result<()> {
+        let mut rotated = rotated_files(&self.path)?;
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.policy.keep);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let period = self.policy.rotation.period(Local::now());
+        if self.should_rotate(buf.len(), &period) {
+            self.rotate()?;
+        }
+        self.period = period;
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Rotated files of `path`: same name followed by a timestamp suffix
+fn rotated_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(name) = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+    else {
+        return Ok(Vec::new());
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let prefix = format!("{}.", name);
+    let mut rotated = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_rotated = file_name
+            .strip_prefix(&prefix)
+            .is_some_and(|suffix| suffix.starts_with(|c: char| c.is_ascii_digit()));
+        if is_rotated {
+            rotated.push(entry.path());
+        }
+    }
+    Ok(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_the_newest_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logs/ocr.log");
+        fs::create_dir_all(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join("logs/ocr.log.old"), b"unrelated").unwrap();
+
+        let policy = RotationPolicy {
+            max_bytes: Some(10),
+            rotation: LogRotation::Never,
+            keep: 2,
+        };
+        let mut file = RotatingFile::open(path.clone(), policy).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        let mut rotated = rotated_files(&path).unwrap();
+        rotated.sort();
+        assert_eq!(rotated.len(), 2);
+        assert_eq!(fs::read_to_string(&rotated[1]).unwrap(), "third\n");
+        assert!(dir.path().join("logs/ocr.log.old").exists());
+    }
+
+    #[test]
+    fn test_rotation_periods() {
+        let time = Local::now();
+        assert_eq!(LogRotation::Never.period(time), None);
+        assert_eq!(
+            LogRotation::Daily.period(time),
+            Some(time.format("%Y-%m-%d").to_string())
+        );
+        assert_eq!("HOURLY".parse::<LogRotation>(), Ok(LogRotation::Hourly));
+        assert!("weekly".parse::<LogRotation>().is_err());
+    }
+}
@@ -21,7 +21,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(0);
         }
         Err(e) => {
-            eprintln!("Error: {}", e);
+            let label = format!("Error [{}]:", e.code());
+            eprintln!("{} {}", cli.color.stderr().failure(&label), e);
             // The CLI execute method will handle proper exit codes internally
             std::process::exit(e.exit_code());
         }
@@ -0,0 +1,324 @@
+//! Run manifests for batch runs
+//!
+//! Every `batch` run writes `run-<timestamp>.json` next to its results, to the
+//! `manifests` directory in the platform state directory when only sidecars
+//! are written, or to `--manifest-dir`. It records what was processed and how,
+//! so a migration can be audited and repeated later:
+//!
+//! - the command line (with the API key redacted) and the tool version
+//! - the SHA-256 of the effective configuration, with secrets redacted
+//! - every input with its size and SHA-256
+//! - every result with its provider, model, pages, timing, output, or error
+
+use crate::batch::{BatchFile, BatchInput, BatchReport};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::ocr::OCRResult;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Versions of the tool and models a run used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestVersions {
+    /// Version of paperless-ngx-ocr2
+    pub tool: String,
+
+    /// Providers in fallback order
+    pub providers: Vec<String>,
+
+    /// Model for summaries, translations, and entities
+    pub chat_model: String,
+
+    /// Model for embeddings
+    pub embedding_model: String,
+}
+
+/// A document given to the run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestInput {
+    /// Path of the document
+    pub path: String,
+
+    /// Path below the given directory, as used for the output
+    pub relative: String,
+
+    /// Size in bytes, if the file could be read
+    pub size_bytes: Option<u64>,
+
+    /// SHA-256 of the contents, if the file could be read
+    pub sha256: Option<String>,
+}
+
+/// Outcome of a document with the provider that produced it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestResult {
+    /// Input document
+    pub file: String,
+
+    /// Whether the document was processed
+    pub success: bool,
+
+    /// Pages extracted
+    pub pages: u64,
+
+    /// Processing time of the document
+    pub duration_ms: u64,
+
+    /// Provider that produced the text
+    pub provider: Option<String>,
+
+    /// OCR model that produced the text
+    pub model: Option<String>,
+
+    /// Written result, if any
+    pub output: Option<String>,
+
+    /// Why the document failed
+    pub error: Option<String>,
+}
+
+/// Record of a batch run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Unique ID of the run
+    pub run_id: String,
+
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+
+    /// When the run finished
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// Command line of the run, with the API key redacted
+    pub command: Vec<String>,
+
+    /// Tool and model versions
+    pub versions: ManifestVersions,
+
+    /// SHA-256 of the effective configuration with secrets redacted
+    pub config_sha256: String,
+
+    /// Documents given to the run, in processing order
+    pub inputs: Vec<ManifestInput>,
+
+    /// Outcomes of the processed documents
+    pub results: Vec<ManifestResult>,
+
+    /// Documents processed
+    pub succeeded: usize,
+
+    /// Documents that failed
+    pub failed: usize,
+
+    /// Documents not attempted because the run stopped early
+    pub skipped: usize,
+}
+
+impl RunManifest {
+    /// Start the manifest of a run over `inputs`, hashing every input
+    pub fn start(inputs: &[BatchInput], config: &Config) -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            command: redact_command(std::env::args()),
+            versions: ManifestVersions {
+                tool: env!("CARGO_PKG_VERSION").to_string(),
+                providers: config
+                    .providers
+                    .iter()
+                    .map(|provider| provider.name().to_string())
+                    .collect(),
+                chat_model: config.chat_model.clone(),
+                embedding_model: config.embedding_model.clone(),
+            },
+            config_sha256: config_sha256(config),
+            inputs: inputs.iter().map(manifest_input).collect(),
+            results: Vec::new(),
+            succeeded: 0,
+            failed: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Record the outcome of a document, with its result when it succeeded
+    pub fn record(&mut self, file: &BatchFile, result: Option<&OCRResult>) {
+        self.results.push(ManifestResult {
+            file: file.file.clone(),
+            success: file.success,
+            pages: file.pages,
+            duration_ms: file.duration_ms,
+            provider: result.and_then(|result| result.provider.clone()),
+            model: result.map(|result| result.model.clone()),
+            output: file.output.clone(),
+            error: file.error.clone(),
+        });
+    }
+
+    /// Take the totals from the report and stop the clock
+    pub fn finish(&mut self, report: &BatchReport) {
+        self.finished_at = Some(Utc::now());
+        self.succeeded = report.succeeded;
+        self.failed = report.failed;
+        self.skipped = report.skipped;
+    }
+
+    /// Write the manifest to `dir/run-<started_at>.json` and return its path
+    ///
+    /// Runs started in the same second get a `-2`, `-3`, ... suffix instead
+    /// of overwriting each other's manifests.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).map_err(Error::Io)?;
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::internal_from("Failed to serialize run manifest", e))?;
+
+        let stem = format!("run-{}", self.started_at.format("%Y-%m-%dT%H:%M:%S"));
+        let mut attempt = 1;
+        loop {
+            let path = match attempt {
+                1 => dir.join(format!("{}.json", stem)),
+                n => dir.join(format!("{}-{}.json", stem, n)),
+            };
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    file.write_all(&json).map_err(Error::Io)?;
+                    return Ok(path);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+    }
+}
+
+/// SHA-256 of the configuration as JSON, with secrets redacted so the hash
+/// identifies the settings and not the credentials
+pub fn config_sha256(config: &Config) -> String {
+    let json = serde_json::to_vec(&config.redacted()).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+fn manifest_input(input: &BatchInput) -> ManifestInput {
+    let hash = || -> std::io::Result<String> {
+        let mut file = fs::File::open(&input.path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    };
+
+    ManifestInput {
+        path: input.path.display().to_string(),
+        relative: input.relative.display().to_string(),
+        size_bytes: fs::metadata(&input.path)
+            .ok()
+            .map(|metadata| metadata.len()),
+        sha256: hash().ok(),
+    }
+}
+
+/// Command line with the value of `-a/--api-key` replaced
+fn redact_command(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    const REDACTED: &str = "[REDACTED]";
+    let mut redact_next = false;
+
+    args.into_iter()
+        .map(|arg| {
+            if std::mem::take(&mut redact_next) {
+                REDACTED.to_string()
+            } else if arg == "--api-key" || arg == "-a" {
+                redact_next = true;
+                arg
+            } else if arg.starts_with("--api-key=") {
+                format!("--api-key={}", REDACTED)
+            } else if arg.starts_with("-a") && !arg.starts_with("--") {
+                format!("-a{}", REDACTED)
+            } else {
+                arg
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redact_command() {
+        let args = [
+            "ocr2",
+            "-a",
+            "sk-1",
+            "--api-key=sk-2",
+            "-ask-3",
+            "batch",
+            "in",
+        ];
+        assert_eq!(
+            redact_command(args.map(String::from)),
+            [
+                "ocr2",
+                "-a",
+                "[REDACTED]",
+                "--api-key=[REDACTED]",
+                "-a[REDACTED]",
+                "batch",
+                "in"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_hash_ignores_credentials() {
+        let config = Config {
+            api_key: "sk-one".to_string(),
+            ..Config::default()
+        };
+        let other_key = Config {
+            api_key: "sk-other".to_string(),
+            ..Config::default()
+        };
+        let other_timeout = Config {
+            timeout_seconds: config.timeout_seconds + 1,
+            ..config.clone()
+        };
+        assert_eq!(config_sha256(&config), config_sha256(&other_key));
+        assert_ne!(config_sha256(&config), config_sha256(&other_timeout));
+    }
+
+    #[test]
+    fn test_write_does_not_overwrite_runs_started_together() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("scan.pdf");
+        fs::write(&input, b"%PDF-1.4\n").unwrap();
+        let inputs = [BatchInput {
+            path: input,
+            relative: PathBuf::from("scan.pdf"),
+        }];
+
+        let mut manifest = RunManifest::start(&inputs, &Config::default());
+        manifest.finish(&BatchReport::default());
+        assert_eq!(manifest.inputs[0].size_bytes, Some(9));
+        assert_eq!(
+            manifest.inputs[0].sha256.as_ref().map(String::len),
+            Some(64)
+        );
+
+        let first = manifest.write(dir.path()).unwrap();
+        let second = manifest.write(dir.path()).unwrap();
+        assert_ne!(first, second);
+        assert!(second.to_string_lossy().ends_with("-2.json"));
+
+        let read: RunManifest = serde_json::from_str(&fs::read_to_string(&first).unwrap()).unwrap();
+        assert_eq!(read, manifest);
+    }
+}
@@ -0,0 +1,178 @@
+//! Memory budget for document buffers
+//!
+//! `memory_budget_mb` bounds the memory held at once by the large buffers of
+//! the documents being processed, so runs in small containers queue work
+//! instead of being killed:
+//!
+//! - documents read into memory for upload (twice their size, since each
+//!   attempt sends a copy)
+//! - decoded page images while PDFs are rendered and pages are run through
+//!   tesseract
+//!
+//! A buffer that does not fit waits until earlier ones are released. A single
+//! buffer larger than the whole budget waits until nothing else is reserved
+//! and then runs alone. Without a budget nothing is ever held back.
+//!
+//! The budget is created once, from the first reservation with a limit.
+//! Reservations under a different limit (a reloaded configuration, a `serve`
+//! request with its own settings) share it, so buffers already in flight stay
+//! counted, and each is capped at the smaller of the two limits.
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bytes per permit; reservations are rounded up to whole KiB
+const UNIT: u64 = 1024;
+
+/// Budget shared by the tasks of a run, with the limit it was created for
+///
+/// Clones share the budget, so a run creates one and hands it to every
+/// document and client.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBudget {
+    shared: Arc<OnceLock<(u64, Arc<Semaphore>)>>,
+}
+
+impl MemoryBudget {
+    /// Get the shared semaphore, created for the first budget, and the units
+    /// a single reservation under `budget_mb` may hold
+    fn semaphore(&self, budget_mb: u64) -> (u64, Arc<Semaphore>) {
+        let units = (budget_mb * 1024 * 1024 / UNIT).clamp(1, u64::from(u32::MAX));
+        let (total, semaphore) = self
+            .shared
+            .get_or_init(|| (units, Arc::new(Semaphore::new(units as usize))));
+        (units.min(*total), semaphore.clone())
+    }
+
+    /// Reserve `bytes` of the budget, waiting while it is used up
+    ///
+    /// The reservation is released when the returned value is dropped.
+    pub async fn reserve(&self, budget_mb: Option<u64>, bytes: u64, what: &str) -> Reservation {
+        let Some(budget_mb) = budget_mb else {
+            return Reservation { _permit: None };
+        };
+        let (total, semaphore) = self.semaphore(budget_mb);
+        let units = bytes.div_ceil(UNIT).clamp(1, total) as u32;
+
+        let permit = match semaphore.clone().try_acquire_many_owned(units) {
+            Ok(permit) => permit,
+            Err(_) => {
+                tracing::debug!(
+                    "Waiting for {} KiB of the {} MB memory budget for {}",
+                    units,
+                    budget_mb,
+                    what
+                );
+                semaphore
+                    .acquire_many_owned(units)
+                    .await
+                    .expect("the memory budget semaphore is never closed")
+            }
+        };
+        Reservation {
+            _permit: Some(permit),
+        }
+    }
+}
+
+/// Part of the memory budget held by a buffer; released when dropped
+#[derive(Debug)]
+pub struct Reservation {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// Memory of a page decoded at `dpi`, assuming an A4 page with four bytes per
+/// pixel
+pub fn page_bytes(dpi: u32) -> u64 {
+    let dpi = f64::from(dpi);
+    ((8.27 * dpi) * (11.69 * dpi) * 4.0) as u64
+}
+
+/// Memory of an image once decoded: the dimensions from a PNG header, or a
+/// page at `dpi` for other formats
+pub fn decoded_image_bytes(image: &Path, dpi: u32) -> u64 {
+    png_dimensions(image)
+        .map(|(width, height)| u64::from(width) * u64::from(height) * 4)
+        .unwrap_or_else(|| page_bytes(dpi))
+}
+
+/// Width and height from the IHDR chunk of a PNG file
+fn png_dimensions(path: &Path) -> Option<(u32, u32)> {
+    use std::io::Read;
+
+    let mut header = [0u8; 24];
+    std::fs::File::open(path)
+        .ok()?
+        .read_exact(&mut header)
+        .ok()?;
+    if &header[..8] != b"\x89PNG\r\n\x1a\n" || &header[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_reservations_wait_for_the_budget() {
+        let budget = MemoryBudget::default();
+        let first = budget.reserve(Some(1), 768 * 1024, "first").await;
+
+        // Only a quarter of the budget is left
+        let second = budget.reserve(Some(1), 512 * 1024, "second");
+        tokio::pin!(second);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut second)
+            .await
+            .is_err());
+
+        drop(first);
+        let second = second.await;
+
+        // Larger than the whole budget: runs once nothing else is reserved
+        drop(second);
+        let _large = budget.reserve(Some(1), 10 * 1024 * 1024, "large").await;
+        let _unlimited = budget.reserve(None, u64::MAX, "unlimited").await;
+    }
+
+    #[tokio::test]
+    async fn test_other_budgets_share_the_first_one() {
+        let budget = MemoryBudget::default();
+        let first = budget.reserve(Some(1), 768 * 1024, "first").await;
+
+        // A larger budget does not start over with an empty semaphore
+        let second = budget.reserve(Some(4), 512 * 1024, "second");
+        tokio::pin!(second);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut second)
+            .await
+            .is_err());
+        drop(first);
+        drop(second.await);
+
+        // A buffer larger than both budgets is capped at the shared one
+        let _large = budget.reserve(Some(4), 10 * 1024 * 1024, "large").await;
+    }
+
+    #[test]
+    fn test_decoded_image_size() {
+        let dir = TempDir::new().unwrap();
+        let png = dir.path().join("page.png");
+        let mut header = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        header.extend_from_slice(&2480u32.to_be_bytes());
+        header.extend_from_slice(&3508u32.to_be_bytes());
+        std::fs::write(&png, header).unwrap();
+
+        assert_eq!(decoded_image_bytes(&png, 300), 2480 * 3508 * 4);
+        assert_eq!(
+            decoded_image_bytes(&dir.path().join("missing.jpg"), 300),
+            page_bytes(300)
+        );
+        assert!((30_000_000..40_000_000).contains(&page_bytes(300)));
+    }
+}
@@ -1,11 +1,147 @@
 //! Metrics collection for monitoring API usage and performance
-
+//!
+//! Latencies are kept in fixed-bucket histograms, overall and per API
+//! operation, so percentiles (p50/p95/p99) can be reported with
+//! `--show-metrics` and in the Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Upper bounds of the latency histogram buckets in milliseconds
+pub const LATENCY_BUCKETS_MS: &[u64] = &[
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000, 300_000,
+];
+
+/// API operation a latency belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiOperation {
+    /// File upload
+    Upload,
+    /// OCR request
+    Ocr,
+    /// File metadata and signed URL requests
+    Files,
+    /// Chat completions (summaries, translation, entities)
+    Chat,
+    /// Embeddings
+    Embeddings,
+}
+
+impl ApiOperation {
+    /// Label used in metrics output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Ocr => "ocr",
+            Self::Files => "files",
+            Self::Chat => "chat",
+            Self::Embeddings => "embeddings",
+        }
+    }
+}
+
+/// Latency distribution over [`LATENCY_BUCKETS_MS`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    /// Observations per bucket; the last bucket has no upper bound
+    counts: Vec<u64>,
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Record one latency
+    pub fn observe(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Sum of all observations
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Mean latency
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(count) if count > 0 => self.sum / count,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Estimated latency below which the fraction `quantile` (0.0 to 1.0) of
+    /// observations fall, interpolated linearly within the bucket
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = (quantile.clamp(0.0, 1.0) * self.count as f64).max(1.0);
+        let mut below = 0u64;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            if count > 0 && (below + count) as f64 >= rank {
+                let lower = bucket
+                    .checked_sub(1)
+                    .map_or(0, |previous| LATENCY_BUCKETS_MS[previous]);
+                // The open-ended bucket (and any bucket) cannot exceed the maximum
+                let max_ms = self.max.as_millis() as u64;
+                let upper = LATENCY_BUCKETS_MS
+                    .get(bucket)
+                    .map_or(max_ms, |&bound| bound.min(max_ms));
+                let fraction = (rank - below as f64) / count as f64;
+                let ms = lower as f64 + (upper.saturating_sub(lower)) as f64 * fraction;
+                return Duration::from_millis(ms.round() as u64);
+            }
+            below += count;
+        }
+
+        self.max
+    }
+
+    /// Cumulative counts per bucket bound (`None` is +Inf), as in Prometheus
+    pub fn cumulative_buckets(&self) -> Vec<(Option<u64>, u64)> {
+        let mut cumulative = 0;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(bucket, &count)| {
+                cumulative += count;
+                (LATENCY_BUCKETS_MS.get(bucket).copied(), cumulative)
+            })
+            .collect()
+    }
+}
+
 /// Performance metrics for API operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct APIMetrics {
     /// Number of successful API calls
     pub successful_calls: u64,
@@ -13,8 +149,10 @@ pub struct APIMetrics {
     pub failed_calls: u64,
     /// Total time spent on API calls
     pub total_duration: Duration,
-    /// Average response time
-    pub average_response_time: Duration,
+    /// Latency of all API calls
+    pub latency: LatencyHistogram,
+    /// Latency per API operation
+    pub operation_latency: BTreeMap<ApiOperation, LatencyHistogram>,
     /// Total bytes uploaded
     pub total_bytes_uploaded: u64,
     /// Total bytes downloaded
@@ -23,21 +161,10 @@ pub struct APIMetrics {
     pub total_retries: u64,
     /// Rate limit hits
     pub rate_limit_hits: u64,
-}
-
-impl Default for APIMetrics {
-    fn default() -> Self {
-        Self {
-            successful_calls: 0,
-            failed_calls: 0,
-            total_duration: Duration::ZERO,
-            average_response_time: Duration::ZERO,
-            total_bytes_uploaded: 0,
-            total_bytes_downloaded: 0,
-            total_retries: 0,
-            rate_limit_hits: 0,
-        }
-    }
+    /// HTTP requests sent, including retries
+    pub requests_sent: u64,
+    /// Connections opened for those requests; the others reused a pooled one
+    pub connections_opened: u64,
 }
 
 impl APIMetrics {
@@ -52,14 +179,22 @@ impl APIMetrics {
         self.total_duration += duration;
         self.total_bytes_uploaded += bytes_uploaded;
         self.total_bytes_downloaded += bytes_downloaded;
-        self.update_average_response_time();
+        self.latency.observe(duration);
     }
 
     /// Record a failed API call
     pub fn record_failure(&mut self, duration: Duration) {
         self.failed_calls += 1;
         self.total_duration += duration;
-        self.update_average_response_time();
+        self.latency.observe(duration);
+    }
+
+    /// Record the latency of one call of an operation
+    pub fn record_operation_latency(&mut self, operation: ApiOperation, duration: Duration) {
+        self.operation_latency
+            .entry(operation)
+            .or_default()
+            .observe(duration);
     }
 
     /// Record a retry
@@ -72,13 +207,20 @@ impl APIMetrics {
         self.rate_limit_hits += 1;
     }
 
-    /// Update average response time
-    fn update_average_response_time(&mut self) {
-        let total_calls = self.successful_calls + self.failed_calls;
-        if total_calls > 0 {
-            self.average_response_time =
-                Duration::from_millis(self.total_duration.as_millis() as u64 / total_calls);
-        }
+    /// Record a sent request and the connections opened for it
+    pub fn record_request_sent(&mut self, connections_opened: u64) {
+        self.requests_sent += 1;
+        self.connections_opened += connections_opened;
+    }
+
+    /// Requests sent over a pooled keep-alive connection
+    pub fn connections_reused(&self) -> u64 {
+        self.requests_sent.saturating_sub(self.connections_opened)
+    }
+
+    /// Average response time
+    pub fn average_response_time(&self) -> Duration {
+        self.latency.mean()
     }
 
     /// Get success rate as percentage
@@ -100,9 +242,235 @@ impl APIMetrics {
     pub fn reset(&mut self) {
         *self = Self::default();
     }
+
+    /// Metrics as JSON, with latency percentiles in milliseconds
+    pub fn to_json(&self) -> serde_json::Value {
+        let operations: serde_json::Map<String, serde_json::Value> = self
+            .operation_latency
+            .iter()
+            .map(|(operation, histogram)| (operation.as_str().to_string(), latency_json(histogram)))
+            .collect();
+
+        serde_json::json!({
+            "successful_calls": self.successful_calls,
+            "failed_calls": self.failed_calls,
+            "total_calls": self.total_calls(),
+            "success_rate_percent": self.success_rate(),
+            "average_response_time_ms": self.average_response_time().as_millis(),
+            "total_duration_ms": self.total_duration.as_millis(),
+            "total_bytes_uploaded": self.total_bytes_uploaded,
+            "total_bytes_downloaded": self.total_bytes_downloaded,
+            "total_retries": self.total_retries,
+            "rate_limit_hits": self.rate_limit_hits,
+            "requests_sent": self.requests_sent,
+            "connections_opened": self.connections_opened,
+            "connections_reused": self.connections_reused(),
+            "latency_ms": latency_json(&self.latency),
+            "operation_latency_ms": operations
+        })
+    }
+
+    /// Human-readable report with latency percentiles
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "API calls: {} ({} failed), {} retries, {} rate limit hits\n\
+             Bytes: {} uploaded, {} downloaded\n\
+             Connections: {} opened, {} reused\n",
+            self.total_calls(),
+            self.failed_calls,
+            self.total_retries,
+            self.rate_limit_hits,
+            self.total_bytes_uploaded,
+            self.total_bytes_downloaded,
+            self.connections_opened,
+            self.connections_reused()
+        );
+        let histograms = std::iter::once(("all", &self.latency)).chain(
+            self.operation_latency
+                .iter()
+                .map(|(operation, histogram)| (operation.as_str(), histogram)),
+        );
+        for (label, histogram) in histograms {
+            if histogram.count() == 0 {
+                continue;
+            }
+            let _ = writeln!(
+                text,
+                "Latency {:<10} n={:<5} p50={}ms p95={}ms p99={}ms max={}ms",
+                label,
+                histogram.count(),
+                histogram.percentile(0.5).as_millis(),
+                histogram.percentile(0.95).as_millis(),
+                histogram.percentile(0.99).as_millis(),
+                histogram.max.as_millis()
+            );
+        }
+        text
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "api_calls_success_total",
+                "Successful API calls",
+                self.successful_calls,
+            ),
+            (
+                "api_calls_failed_total",
+                "Failed API calls",
+                self.failed_calls,
+            ),
+            (
+                "api_retries_total",
+                "Retried API requests",
+                self.total_retries,
+            ),
+            (
+                "api_rate_limit_hits_total",
+                "HTTP 429 responses",
+                self.rate_limit_hits,
+            ),
+            (
+                "api_uploaded_bytes_total",
+                "Bytes sent to the API",
+                self.total_bytes_uploaded,
+            ),
+            (
+                "api_downloaded_bytes_total",
+                "Decompressed bytes received from the API",
+                self.total_bytes_downloaded,
+            ),
+            (
+                "api_connections_opened_total",
+                "Connections opened to the API",
+                self.connections_opened,
+            ),
+            (
+                "api_connections_reused_total",
+                "API requests sent over a pooled connection",
+                self.connections_reused(),
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {}{} {}", PROMETHEUS_PREFIX, name, help);
+            let _ = writeln!(out, "# TYPE {}{} counter", PROMETHEUS_PREFIX, name);
+            let _ = writeln!(out, "{}{} {}", PROMETHEUS_PREFIX, name, value);
+        }
+
+        let histograms: Vec<(&str, &LatencyHistogram)> = std::iter::once(("all", &self.latency))
+            .chain(
+                self.operation_latency
+                    .iter()
+                    .map(|(operation, histogram)| (operation.as_str(), histogram)),
+            )
+            .collect();
+
+        let name = format!("{}api_request_duration_seconds", PROMETHEUS_PREFIX);
+        let _ = writeln!(out, "# HELP {} API request latency", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (operation, histogram) in &histograms {
+            for (bound, count) in histogram.cumulative_buckets() {
+                let le = bound.map_or("+Inf".to_string(), |ms| seconds(Duration::from_millis(ms)));
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+                    name, operation, le, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_sum{{operation=\"{}\"}} {}",
+                name,
+                operation,
+                seconds(histogram.sum())
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{{operation=\"{}\"}} {}",
+                name,
+                operation,
+                histogram.count()
+            );
+        }
+
+        let name = format!("{}api_request_duration_quantile_seconds", PROMETHEUS_PREFIX);
+        let _ = writeln!(
+            out,
+            "# HELP {} Estimated API request latency percentiles",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for (operation, histogram) in &histograms {
+            for quantile in [0.5, 0.95, 0.99] {
+                let _ = writeln!(
+                    out,
+                    "{}{{operation=\"{}\",quantile=\"{}\"}} {}",
+                    name,
+                    operation,
+                    quantile,
+                    seconds(histogram.percentile(quantile))
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// Prefix of all Prometheus metric names
+const PROMETHEUS_PREFIX: &str = "paperless_ocr_";
+
+/// Duration in seconds as Prometheus writes it
+fn seconds(duration: Duration) -> String {
+    format!("{}", duration.as_secs_f64())
+}
+
+/// Percentiles of a histogram as JSON
+fn latency_json(histogram: &LatencyHistogram) -> serde_json::Value {
+    serde_json::json!({
+        "count": histogram.count(),
+        "mean": histogram.mean().as_millis(),
+        "p50": histogram.percentile(0.5).as_millis(),
+        "p95": histogram.percentile(0.95).as_millis(),
+        "p99": histogram.percentile(0.99).as_millis(),
+        "max": histogram.max.as_millis()
+    })
+}
+
+/// Output format of `--show-metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetricsFormat {
+    /// Human-readable summary
+    #[default]
+    Text,
+    /// JSON object
+    Json,
+    /// Prometheus text exposition format
+    Prometheus,
+}
+
+impl FromStr for MetricsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "prometheus" => Ok(Self::Prometheus),
+            other => Err(format!(
+                "Unknown metrics format: {}. Supported: text, json, prometheus",
+                other
+            )),
+        }
+    }
 }
 
-/// Global metrics collector
+/// Collector of API metrics
+///
+/// Clones share the same metrics, so a run (or a tenant of a server) creates
+/// one collector and hands it to every client it builds.
 #[derive(Debug, Clone)]
 pub struct MetricsCollector {
     metrics: Arc<RwLock<APIMetrics>>,
@@ -151,6 +519,12 @@ impl MetricsCollector {
         metrics.record_rate_limit_hit();
     }
 
+    /// Record a sent request and the connections opened for it
+    pub async fn record_request_sent(&self, connections_opened: u64) {
+        let mut metrics = self.metrics.write().await;
+        metrics.record_request_sent(connections_opened);
+    }
+
     /// Get current metrics
     pub async fn get_metrics(&self) -> APIMetrics {
         self.metrics.read().await.clone()
@@ -162,21 +536,27 @@ impl MetricsCollector {
         metrics.reset();
     }
 
+    /// Record the latency of one call of an operation
+    pub async fn record_operation_latency(&self, operation: ApiOperation, duration: Duration) {
+        let mut metrics = self.metrics.write().await;
+        metrics.record_operation_latency(operation, duration);
+    }
+
     /// Get metrics summary as JSON
     pub async fn get_metrics_json(&self) -> serde_json::Value {
+        self.get_metrics().await.to_json()
+    }
+
+    /// Render the metrics in the given format
+    pub async fn render(&self, format: MetricsFormat) -> String {
         let metrics = self.get_metrics().await;
-        serde_json::json!({
-            "successful_calls": metrics.successful_calls,
-            "failed_calls": metrics.failed_calls,
-            "total_calls": metrics.total_calls(),
-            "success_rate_percent": metrics.success_rate(),
-            "average_response_time_ms": metrics.average_response_time.as_millis(),
-            "total_duration_ms": metrics.total_duration.as_millis(),
-            "total_bytes_uploaded": metrics.total_bytes_uploaded,
-            "total_bytes_downloaded": metrics.total_bytes_downloaded,
-            "total_retries": metrics.total_retries,
-            "rate_limit_hits": metrics.rate_limit_hits
-        })
+        match format {
+            MetricsFormat::Text => metrics.to_text(),
+            MetricsFormat::Json => {
+                serde_json::to_string_pretty(&metrics.to_json()).unwrap_or_default() + "\n"
+            }
+            MetricsFormat::Prometheus => metrics.to_prometheus(),
+        }
     }
 
     /// Log metrics summary
@@ -184,10 +564,12 @@ impl MetricsCollector {
         let metrics = self.get_metrics().await;
         if metrics.total_calls() > 0 {
             tracing::info!(
-                "API Metrics Summary: {} calls, {:.1}% success rate, avg response: {}ms, {} bytes uploaded, {} bytes downloaded, {} retries, {} rate limit hits",
+                "API Metrics Summary: {} calls, {:.1}% success rate, p50/p95/p99 response: {}/{}/{}ms, {} bytes uploaded, {} bytes downloaded, {} retries, {} rate limit hits",
                 metrics.total_calls(),
                 metrics.success_rate(),
-                metrics.average_response_time.as_millis(),
+                metrics.latency.percentile(0.5).as_millis(),
+                metrics.latency.percentile(0.95).as_millis(),
+                metrics.latency.percentile(0.99).as_millis(),
                 metrics.total_bytes_uploaded,
                 metrics.total_bytes_downloaded,
                 metrics.total_retries,
@@ -197,10 +579,6 @@ impl MetricsCollector {
     }
 }
 
-lazy_static::lazy_static! {
-    pub static ref GLOBAL_METRICS: MetricsCollector = MetricsCollector::new();
-}
-
 /// Helper macro to measure API call duration
 #[macro_export]
 macro_rules! measure_api_call {
@@ -258,8 +636,8 @@ impl FileMetrics {
         self.total_file_size += file_size;
         self.total_processing_time += processing_time;
 
-        if self.files_processed > 0 {
-            self.average_file_size = self.total_file_size / self.files_processed;
+        if let Some(average_size) = self.total_file_size.checked_div(self.files_processed) {
+            self.average_file_size = average_size;
             self.average_processing_time = Duration::from_millis(
                 self.total_processing_time.as_millis() as u64 / self.files_processed,
             );
@@ -336,5 +714,49 @@ mod tests {
         assert!(json.get("successful_calls").is_some());
         assert!(json.get("success_rate_percent").is_some());
         assert!(json.get("average_response_time_ms").is_some());
+        assert_eq!(json["latency_ms"]["count"], 1);
+        assert_eq!(json["latency_ms"]["p95"], 100);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(0.95), Duration::ZERO);
+
+        // 90 fast calls and 10 slow ones
+        for _ in 0..90 {
+            histogram.observe(Duration::from_millis(80));
+        }
+        for _ in 0..10 {
+            histogram.observe(Duration::from_millis(4_000));
+        }
+
+        assert_eq!(histogram.count(), 100);
+        let p50 = histogram.percentile(0.5);
+        assert!(p50 > Duration::from_millis(50) && p50 <= Duration::from_millis(100));
+        let p95 = histogram.percentile(0.95);
+        assert!(p95 > Duration::from_millis(2_500) && p95 <= Duration::from_millis(4_000));
+        assert_eq!(histogram.percentile(1.0), Duration::from_millis(4_000));
+        assert_eq!(histogram.mean(), Duration::from_millis(472));
+    }
+
+    #[test]
+    fn test_prometheus_output() {
+        let mut metrics = APIMetrics::default();
+        metrics.record_success(Duration::from_millis(300), 10, 20);
+        metrics.record_operation_latency(ApiOperation::Ocr, Duration::from_millis(300));
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE paperless_ocr_api_request_duration_seconds histogram"));
+        assert!(text.contains("paperless_ocr_api_calls_success_total 1"));
+        assert!(text.contains(
+            "paperless_ocr_api_request_duration_seconds_bucket{operation=\"ocr\",le=\"0.25\"} 0"
+        ));
+        assert!(text.contains(
+            "paperless_ocr_api_request_duration_seconds_bucket{operation=\"ocr\",le=\"+Inf\"} 1"
+        ));
+        assert!(text.contains(
+            "paperless_ocr_api_request_duration_quantile_seconds{operation=\"all\",quantile=\"0.99\"} 0.3"
+        ));
     }
 }
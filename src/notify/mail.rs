@@ -0,0 +1,274 @@
+//! SMTP delivery of the run report
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+use super::NO_TLS_BACKEND;
+use super::{EmailConfig, SmtpTls};
+use crate::error::{Error, Result};
+use crate::summary::{FileRecord, RunSummary};
+use chrono::{DateTime, Utc};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::extension::ClientId;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+/// Time allowed for the whole SMTP conversation
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Mail the run report
+pub async fn send_report(
+    email: &EmailConfig,
+    summary: &RunSummary,
+    records: &[FileRecord],
+) -> Result<()> {
+    email.validate()?;
+    let message = build_message(email, summary, records, Utc::now())?;
+    tokio::time::timeout(SMTP_TIMEOUT, send_smtp(email, message))
+        .await
+        .map_err(|_| Error::api(format!("SMTP to {} timed out", email.smtp_host)))?
+}
+
+/// CSV report with one row per file
+pub fn records_csv(records: &[FileRecord]) -> String {
+    let mut csv = String::from("file,status,pages,error\r\n");
+    for record in records {
+        let status = if record.error.is_some() {
+            "failed"
+        } else {
+            "ok"
+        };
+        csv.push_str(&format!(
+            "{},{},{},{}\r\n",
+            csv_field(&record.file),
+            status,
+            record.pages,
+            csv_field(record.error.as_deref().unwrap_or_default())
+        ));
+    }
+    csv
+}
+
+/// Build the MIME message with the summary text and the CSV attachment
+pub fn build_message(
+    email: &EmailConfig,
+    summary: &RunSummary,
+    records: &[FileRecord],
+    date: DateTime<Utc>,
+) -> Result<Message> {
+    let mut body = format!("{}\n", summary.to_line());
+    let failures: Vec<&FileRecord> = records.iter().filter(|r| r.error.is_some()).collect();
+    if !failures.is_empty() {
+        body.push_str("\nFailed files:\n");
+        for record in failures {
+            body.push_str(&format!(
+                "- {}: {}\n",
+                record.file,
+                record.error.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+
+    let mut builder = Message::builder()
+        .from(mailbox(&email.from)?)
+        // Line breaks would start new headers
+        .subject(email.subject.replace(['\r', '\n'], " "))
+        .date(date.into());
+    for recipient in &email.to {
+        builder = builder.to(mailbox(recipient)?);
+    }
+    let csv = ContentType::parse("text/csv; charset=utf-8")
+        .map_err(|e| Error::internal_from("Invalid attachment type", e))?;
+    builder
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body))
+                .singlepart(
+                    Attachment::new("run-report.csv".to_string()).body(records_csv(records), csv),
+                ),
+        )
+        .map_err(|e| Error::internal_from("Failed to build the report e-mail", e))
+}
+
+/// Deliver a message to the SMTP server
+async fn send_smtp(email: &EmailConfig, message: Message) -> Result<()> {
+    let smtp_error = |e: lettre::transport::smtp::Error| {
+        Error::api_from(format!("SMTP to {} failed", email.smtp_host), e)
+    };
+    let builder = match email.tls {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&email.smtp_host),
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.smtp_host)
+            .map_err(smtp_error)?,
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        SmtpTls::Tls => {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&email.smtp_host).map_err(smtp_error)?
+        }
+        #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+        SmtpTls::StartTls | SmtpTls::Tls => return Err(Error::Config(NO_TLS_BACKEND.to_string())),
+    };
+    let hello_name = match email.hello_name {
+        Some(ref name) => ClientId::Domain(name.clone()),
+        // The host name of this machine
+        None => ClientId::default(),
+    };
+    let mut builder = builder
+        .port(email.smtp_port)
+        .hello_name(hello_name)
+        .timeout(Some(SMTP_TIMEOUT));
+    if let (Some(username), Some(password)) = (&email.smtp_username, &email.smtp_password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    builder.build().send(message).await.map_err(smtp_error)?;
+    Ok(())
+}
+
+/// Parse a configured address
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse::<Mailbox>()
+        .map_err(|e| Error::Config(format!("Invalid e-mail address '{}': {}", address, e)))
+}
+
+/// Quote a CSV field when needed
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::tests::{email_config, summary};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn records() -> Vec<FileRecord> {
+        vec![
+            FileRecord {
+                file: "a.pdf".to_string(),
+                pages: 3,
+                error: None,
+            },
+            FileRecord {
+                file: "b, final.pdf".to_string(),
+                pages: 0,
+                error: Some("API error: \"boom\"".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_records_csv() {
+        assert_eq!(
+            records_csv(&records()),
+            "file,status,pages,error\r\na.pdf,ok,3,\r\n\"b, final.pdf\",failed,0,\"API error: \"\"boom\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_message_headers_are_encoded() {
+        let email = EmailConfig {
+            subject: "Bericht für März\r\nBcc: victim@example.com".to_string(),
+            ..email_config(25)
+        };
+        let records = vec![FileRecord {
+            file: "Rechnung für Müller.pdf".to_string(),
+            pages: 1,
+            error: None,
+        }];
+        let message = build_message(&email, &summary(), &records, Utc::now()).unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        let (headers, _) = formatted.split_once("\r\n\r\n").unwrap();
+
+        assert!(formatted.is_ascii());
+        assert!(headers.contains("Subject: Bericht =?utf-8?"), "{}", headers);
+        assert!(!headers.lines().any(|line| line.starts_with("Bcc:")));
+        assert!(formatted.contains("filename=\"run-report.csv\""));
+    }
+
+    /// Scripted relay that records the conversation
+    fn scripted_relay(listener: TcpListener) -> tokio::task::JoinHandle<String> {
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let mut transcript = String::new();
+            writer.write_all(b"220 relay ready\r\n").await.unwrap();
+
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                transcript.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-relay\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            transcript
+        })
+    }
+
+    #[tokio::test]
+    async fn test_send_report_to_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let relay = scripted_relay(listener);
+
+        send_report(&email_config(port), &summary(), &records())
+            .await
+            .unwrap();
+        let transcript = relay.await.unwrap();
+
+        assert!(
+            transcript.contains("MAIL FROM:<ocr@example.com>\r\nRCPT TO:<admin@example.com>\r\n")
+        );
+        assert!(transcript.contains("Subject: paperless-ngx-ocr2 run report\r\n"));
+        assert!(transcript.contains("Summary: 2 files (1 failed), 3 pages"));
+        assert!(transcript.contains("- b, final.pdf: API error: \"boom\"\r\n"));
+        assert!(transcript.contains("filename=\"run-report.csv\""));
+        assert!(transcript.ends_with("QUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_starttls_is_required_before_credentials() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let relay = scripted_relay(listener);
+
+        // The relay does not offer STARTTLS
+        let email = EmailConfig {
+            tls: SmtpTls::StartTls,
+            smtp_username: Some("ocr".to_string()),
+            smtp_password: Some("secret".to_string()),
+            hello_name: Some("ocr.example.com".to_string()),
+            ..email_config(port)
+        };
+        assert!(send_report(&email, &summary(), &records()).await.is_err());
+        let transcript = relay.await.unwrap();
+        assert!(transcript.starts_with("EHLO ocr.example.com\r\n"));
+        assert!(!transcript.contains("AUTH"));
+        assert!(!transcript.contains("MAIL FROM"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Run notifications by e-mail
+//!
+//! With `[notify.email]` configured, a summary report is mailed when a run
+//! over several files completes: the counts of the end-of-run summary, the
+//! failed files, and a CSV attachment with one row per file. Mail is handed
+//! to an SMTP server: a local relay (postfix, a Docker mail relay, ...) as
+//! plain SMTP, or a mail provider's submission server over STARTTLS or TLS
+//! with `smtp_username` and `smtp_password`.
+
+use crate::error::{FieldErrors, Result};
+use crate::summary::RunSummary;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "cli")]
+mod mail;
+
+#[cfg(feature = "cli")]
+pub use mail::{build_message, records_csv, send_report};
+
+/// Reported when STARTTLS or TLS is configured in a build without TLS
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+const NO_TLS_BACKEND: &str =
+    "SMTP over TLS needs a build with the native-tls or rustls feature; use tls = \"none\"";
+
+/// Notification settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    /// E-mail report after runs over several files
+    #[serde(default)]
+    pub email: Option<EmailConfig>,
+}
+
+impl NotifyConfig {
+    /// Validate the configured notifications
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if let Some(email) = &self.email {
+            errors.nested("email", email.validate());
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// SMTP settings of the e-mail report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// SMTP relay host
+    pub smtp_host: String,
+
+    /// SMTP relay port; submission servers usually take 587 with `starttls`
+    /// and 465 with `tls`
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+
+    /// Encryption of the connection
+    #[serde(default)]
+    pub tls: SmtpTls,
+
+    /// User name for SMTP authentication
+    #[serde(default)]
+    pub smtp_username: Option<String>,
+
+    /// Password for SMTP authentication
+    #[serde(default)]
+    pub smtp_password: Option<String>,
+
+    /// Sender address
+    pub from: String,
+
+    /// Recipient addresses
+    pub to: Vec<String>,
+
+    /// Subject line
+    #[serde(default = "default_subject")]
+    pub subject: String,
+
+    /// Name sent with EHLO; default: the host name of this machine
+    #[serde(default)]
+    pub hello_name: Option<String>,
+
+    /// Runs handling fewer files are not reported (single documents from a consume hook)
+    #[serde(default = "default_min_files")]
+    pub min_files: u64,
+}
+
+/// Encryption of the SMTP connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain SMTP, for a relay on the same host or network
+    #[default]
+    None,
+    /// Upgrade the connection with STARTTLS; fails if the server does not
+    /// offer it
+    StartTls,
+    /// TLS from the start (SMTPS)
+    Tls,
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+fn default_subject() -> String {
+    "paperless-ngx-ocr2 run report".to_string()
+}
+
+fn default_min_files() -> u64 {
+    2
+}
+
+impl EmailConfig {
+    /// Validate the SMTP settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.smtp_host.is_empty() {
+            errors.add("smtp_host", "SMTP host must not be empty");
+        }
+
+        if self.to.is_empty() {
+            errors.add("to", "At least one recipient is required");
+        }
+
+        match (&self.smtp_username, &self.smtp_password) {
+            (Some(_), None) | (None, Some(_)) => errors.add(
+                "smtp_password",
+                "smtp_username and smtp_password must be set together",
+            ),
+            // Credentials are never sent in plain text
+            (Some(_), Some(_)) if self.tls == SmtpTls::None => errors.add(
+                "tls",
+                "SMTP authentication needs tls = \"starttls\" or \"tls\"",
+            ),
+            _ => {}
+        }
+
+        #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+        if self.tls != SmtpTls::None {
+            errors.add("tls", NO_TLS_BACKEND);
+        }
+
+        let addresses = self
+            .to
+            .iter()
+            .map(|address| ("to", address))
+            .chain(std::iter::once(("from", &self.from)));
+        for (field, address) in addresses {
+            if !is_address(address) {
+                errors.add(field, format!("Invalid e-mail address: '{}'", address));
+            }
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Whether a run with this summary is reported
+    pub fn should_notify(&self, summary: &RunSummary) -> bool {
+        summary.files > 0 && summary.files >= self.min_files
+    }
+}
+
+/// Minimal address check: something@domain
+fn is_address(address: &str) -> bool {
+    matches!(address.split_once('@'), Some((local, domain)) if !local.is_empty() && domain.contains('.'))
+        && !address.contains(['<', '>', '\r', '\n', ' '])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn email_config(port: u16) -> EmailConfig {
+        EmailConfig {
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: port,
+            tls: SmtpTls::None,
+            smtp_username: None,
+            smtp_password: None,
+            from: "ocr@example.com".to_string(),
+            to: vec!["admin@example.com".to_string()],
+            subject: default_subject(),
+            hello_name: None,
+            min_files: default_min_files(),
+        }
+    }
+
+    pub(super) fn summary() -> RunSummary {
+        RunSummary {
+            files: 2,
+            failed: 1,
+            skipped: 0,
+            pages: 3,
+            cache_hits: 0,
+            retries: 0,
+            duration_ms: 1200,
+        }
+    }
+
+    #[test]
+    fn test_validation_and_threshold() {
+        let email = email_config(25);
+        assert!(email.validate().is_ok());
+        assert!(EmailConfig {
+            to: vec!["not an address".to_string()],
+            ..email.clone()
+        }
+        .validate()
+        .is_err());
+
+        // Credentials go together, and only over TLS
+        let credentials = EmailConfig {
+            smtp_username: Some("ocr".to_string()),
+            smtp_password: Some("secret".to_string()),
+            ..email.clone()
+        };
+        assert!(credentials.validate().is_err());
+        assert!(EmailConfig {
+            tls: SmtpTls::StartTls,
+            ..credentials.clone()
+        }
+        .validate()
+        .is_ok());
+        assert!(EmailConfig {
+            tls: SmtpTls::Tls,
+            smtp_password: None,
+            ..credentials
+        }
+        .validate()
+        .is_err());
+
+        assert!(email.should_notify(&summary()));
+        assert!(!email.should_notify(&RunSummary {
+            files: 1,
+            ..summary()
+        }));
+    }
+}
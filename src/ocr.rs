@@ -1,11 +1,99 @@
 //! OCR result entity and processing
 
+use crate::api::ocr::{DuplicatePage, Page};
+use crate::correspondents::CorrespondentCandidate;
 use crate::error::{Error, Result};
-use chrono::{DateTime, Utc};
+use crate::extraction::Extraction;
+use crate::output::{count, duration, Styler};
+use crate::quality::QualityScore;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Translation of the extracted text
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    /// Target language code (e.g. "en")
+    pub language: String,
+
+    /// Translated text
+    pub text: String,
+}
+
+/// Named entities found in the extracted text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Entities {
+    /// Person names
+    pub people: Vec<String>,
+
+    /// Companies, authorities and other organizations
+    pub organizations: Vec<String>,
+
+    /// Dates as written in the document
+    pub dates: Vec<String>,
+
+    /// Monetary amounts as written in the document
+    pub amounts: Vec<String>,
+}
+
+/// Vector embedding of the extracted text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Model that produced the vector
+    pub model: String,
+
+    /// Embedding vector
+    pub vector: Vec<f32>,
+}
+
+/// Wall-clock time spent in each processing phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// Local file validation
+    pub validation_ms: u64,
+
+    /// File upload to the provider (zero for local providers)
+    pub upload_ms: u64,
+
+    /// OCR request
+    pub ocr_ms: u64,
+
+    /// Whole pipeline including post-processing
+    pub total_ms: u64,
+}
+
+/// A page of the result with its text and image dimensions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultPage {
+    /// Zero-based page index
+    pub index: i32,
+
+    /// Page text as returned by the provider
+    pub markdown: String,
+
+    /// Resolution the page was rendered at
+    pub dpi: i32,
+
+    /// Page width in pixels
+    pub width: i32,
+
+    /// Page height in pixels
+    pub height: i32,
+}
+
+impl From<&Page> for ResultPage {
+    fn from(page: &Page) -> Self {
+        Self {
+            index: page.index,
+            markdown: page.markdown.clone(),
+            dpi: page.dimensions.dpi,
+            width: page.dimensions.width,
+            height: page.dimensions.height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OCRResult {
     /// The OCR extracted text from choices[0].message.content
     pub extracted_text: String,
@@ -16,6 +104,10 @@ pub struct OCRResult {
     /// Model used for OCR processing
     pub model: String,
 
+    /// Provider that produced the text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
     /// Token usage information
     pub usage: Option<HashMap<String, i64>>,
 
@@ -25,8 +117,72 @@ pub struct OCRResult {
     /// Original file size in bytes
     pub file_size: u64,
 
+    /// SHA-256 of the original file contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
     /// When OCR was performed
     pub timestamp: DateTime<Utc>,
+
+    /// Measured processing times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+
+    /// Short summary of the extracted text (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
+    /// Translation of the extracted text (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<Translation>,
+
+    /// Named entities (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Entities>,
+
+    /// Keywords suitable for tagging (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+
+    /// Most plausible document date found in the text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_date: Option<NaiveDate>,
+
+    /// Possible senders of the document, best first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correspondents: Vec<CorrespondentCandidate>,
+
+    /// Embedding of the extracted text (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
+
+    /// Structured fields extracted with a preset (when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction: Option<Extraction>,
+
+    /// Indices of pages detected as blank (empty or below the text threshold)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blank_pages: Vec<i32>,
+
+    /// Pages repeating the text of the preceding page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_pages: Vec<DuplicatePage>,
+
+    /// Indices of pages removed from the extracted text (duplicates)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_pages: Vec<i32>,
+
+    /// Header and footer lines repeated on most pages (digits masked as `#`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repeated_lines: Vec<String>,
+
+    /// Quality heuristic for the extracted text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityScore>,
+
+    /// Pages in page order, as used for the extracted text
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<ResultPage>,
 }
 
 impl OCRResult {
@@ -42,10 +198,27 @@ impl OCRResult {
             extracted_text,
             file_id,
             model,
+            provider: None,
             usage: None,
             file_name,
             file_size,
+            sha256: None,
             timestamp: Utc::now(),
+            timings: None,
+            summary: None,
+            translation: None,
+            entities: None,
+            keywords: None,
+            document_date: None,
+            correspondents: Vec::new(),
+            embedding: None,
+            extraction: None,
+            blank_pages: Vec::new(),
+            duplicate_pages: Vec::new(),
+            removed_pages: Vec::new(),
+            repeated_lines: Vec::new(),
+            quality: None,
+            pages: Vec::new(),
         }
     }
 
@@ -62,10 +235,27 @@ impl OCRResult {
             extracted_text,
             file_id,
             model,
+            provider: None,
             usage,
             file_name,
             file_size,
+            sha256: None,
             timestamp: Utc::now(),
+            timings: None,
+            summary: None,
+            translation: None,
+            entities: None,
+            keywords: None,
+            document_date: None,
+            correspondents: Vec::new(),
+            embedding: None,
+            extraction: None,
+            blank_pages: Vec::new(),
+            duplicate_pages: Vec::new(),
+            removed_pages: Vec::new(),
+            repeated_lines: Vec::new(),
+            quality: None,
+            pages: Vec::new(),
         }
     }
 
@@ -76,32 +266,42 @@ impl OCRResult {
 
         // Validate file_id is not empty
         if self.file_id.is_empty() {
-            return Err(Error::Validation("File ID must not be empty".to_string()));
+            return Err(Error::validation("File ID must not be empty".to_string()));
         }
 
         // Validate model is not empty
         if self.model.is_empty() {
-            return Err(Error::Validation("Model must not be empty".to_string()));
+            return Err(Error::validation("Model must not be empty".to_string()));
         }
 
         // Validate file_name is not empty
         if self.file_name.is_empty() {
-            return Err(Error::Validation("File name must not be empty".to_string()));
+            return Err(Error::validation("File name must not be empty".to_string()));
         }
 
         // Validate file_size is positive
         if self.file_size == 0 {
-            return Err(Error::Validation("File size must be positive".to_string()));
+            return Err(Error::validation("File size must be positive".to_string()));
         }
 
         Ok(())
     }
 
-    /// Get processing time in milliseconds (calculated from timestamp)
+    /// Get processing time in milliseconds
     pub fn get_processing_time_ms(&self) -> u64 {
-        // This is a placeholder - in real implementation, we'd track start time
-        // For now, return a reasonable default
-        2000 // 2 seconds default processing time
+        match self.timings {
+            Some(timings) => timings.total_ms,
+            // Results built without measurements report a nominal 2 seconds
+            None => 2000,
+        }
+    }
+
+    /// Number of pages the provider processed (0 when unknown)
+    pub fn page_count(&self) -> u64 {
+        self.usage
+            .as_ref()
+            .and_then(|usage| usage.get("pages_processed"))
+            .map_or(0, |pages| (*pages).max(0) as u64)
     }
 
     /// Check if extracted text is empty
@@ -111,23 +311,131 @@ impl OCRResult {
 
     /// Format result for human-readable output
     pub fn to_human_readable(&self) -> String {
+        self.render(&Styler::plain())
+    }
+
+    /// Format result for the terminal
+    ///
+    /// The header names the file with its size, page count and processing
+    /// time; single-value findings follow the text as aligned rows.
+    pub fn render(&self, styler: &Styler) -> String {
+        let mut details = vec![format!("{} bytes", self.file_size)];
+        if self.page_count() > 0 {
+            details.push(count(self.page_count(), "page", "pages"));
+        }
+        if let Some(timings) = self.timings {
+            details.push(duration(timings.total_ms));
+        }
+        let details = details.join(", ");
+
         if self.is_empty_text() {
-            format!(
-                "Warning: No text could be extracted from {} ({} bytes). The file may contain only images without text, or the text may not be readable.",
+            return format!(
+                "{} No text could be extracted from {} ({}). The file may contain only images without text, or the text may not be readable.",
+                styler.warning("Warning:"),
                 self.file_name,
-                self.file_size
-            )
-        } else {
-            format!(
-                "Extracted text from {} ({} bytes):\n\n{}",
-                self.file_name, self.file_size, self.extracted_text
-            )
+                details
+            );
+        }
+
+        let mut output = format!(
+            "{} {} {}\n\n{}",
+            styler.success("Extracted text from"),
+            styler.label(&self.file_name),
+            styler.dim(&format!("({}):", details)),
+            self.extracted_text
+        );
+
+        let mut rows = Vec::new();
+        if !self.blank_pages.is_empty() {
+            let pages: Vec<String> = self
+                .blank_pages
+                .iter()
+                .map(|index| (index + 1).to_string())
+                .collect();
+            rows.push(("Blank pages", pages.join(", ")));
+        }
+        if !self.duplicate_pages.is_empty() {
+            let pages: Vec<String> = self
+                .duplicate_pages
+                .iter()
+                .map(|page| format!("{} (of {})", page.index + 1, page.duplicate_of + 1))
+                .collect();
+            let label = if self.removed_pages.is_empty() {
+                "Duplicate pages"
+            } else {
+                "Removed duplicate pages"
+            };
+            rows.push((label, pages.join(", ")));
+        }
+        if let Some(quality) = self.quality {
+            rows.push(("Quality", format!("{:.2}", quality.score)));
         }
+        if let Some(document_date) = self.document_date {
+            rows.push(("Document date", document_date.to_string()));
+        }
+        if let Some(correspondent) = self.correspondents.first() {
+            rows.push((
+                "Correspondent",
+                format!("{} ({:.2})", correspondent.name, correspondent.score),
+            ));
+        }
+        if let Some(ref keywords) = self.keywords {
+            rows.push(("Keywords", keywords.join(", ")));
+        }
+        if !rows.is_empty() {
+            output.push_str(&format!("\n\n{}", styler.rows(&rows)));
+        }
+
+        if let Some(ref summary) = self.summary {
+            output.push_str(&format!("\n\n{}\n{}", styler.label("Summary:"), summary));
+        }
+        if let Some(ref translation) = self.translation {
+            output.push_str(&format!(
+                "\n\n{}\n{}",
+                styler.label(&format!("Translation ({}):", translation.language)),
+                translation.text
+            ));
+        }
+        if let Some(ref entities) = self.entities {
+            let rows: Vec<(&str, String)> = [
+                ("People", &entities.people),
+                ("Organizations", &entities.organizations),
+                ("Dates", &entities.dates),
+                ("Amounts", &entities.amounts),
+            ]
+            .into_iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(label, values)| (label, values.join(", ")))
+            .collect();
+            output.push_str(&format!("\n\n{}", styler.label("Entities:")));
+            output.push_str(&indent(&styler.rows(&rows)));
+        }
+        if let Some(ref extraction) = self.extraction {
+            output.push_str(&format!(
+                "\n\n{}",
+                styler.label(&format!("Extracted fields ({}):", extraction.preset.name()))
+            ));
+            if let Some(fields) = extraction.fields.as_object() {
+                let rows: Vec<(&str, String)> = fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            serde_json::Value::Null => "-".to_string(),
+                            serde_json::Value::String(text) => text.clone(),
+                            other => other.to_string(),
+                        };
+                        (name.as_str(), value)
+                    })
+                    .collect();
+                output.push_str(&indent(&styler.rows(&rows)));
+            }
+        }
+        output
     }
 
     /// Format result for JSON output
     pub fn to_json_output(&self) -> serde_json::Value {
-        serde_json::json!({
+        let mut output = serde_json::json!({
             "success": true,
             "data": {
                 "extracted_text": self.extracted_text,
@@ -136,6 +444,77 @@ impl OCRResult {
                 "processing_time_ms": self.get_processing_time_ms(),
                 "confidence": null // Will be populated if available from API
             }
-        })
+        });
+
+        if let Some(ref sha256) = self.sha256 {
+            output["data"]["sha256"] = serde_json::json!(sha256);
+        }
+        if let Some(timings) = self.timings {
+            output["data"]["validation_ms"] = serde_json::json!(timings.validation_ms);
+            output["data"]["upload_ms"] = serde_json::json!(timings.upload_ms);
+            output["data"]["ocr_ms"] = serde_json::json!(timings.ocr_ms);
+            output["data"]["total_ms"] = serde_json::json!(timings.total_ms);
+        }
+        if let Some(ref provider) = self.provider {
+            output["data"]["provider"] = serde_json::json!(provider);
+        }
+
+        // Optional post-processing fields are only present when requested
+        if let Some(quality) = self.quality {
+            output["data"]["quality"] = serde_json::json!(quality);
+        }
+        if !self.blank_pages.is_empty() {
+            output["data"]["blank_pages"] = serde_json::json!(self.blank_pages);
+        }
+        if !self.duplicate_pages.is_empty() {
+            output["data"]["duplicate_pages"] = serde_json::json!(self.duplicate_pages);
+        }
+        if !self.removed_pages.is_empty() {
+            output["data"]["removed_pages"] = serde_json::json!(self.removed_pages);
+        }
+        if !self.repeated_lines.is_empty() {
+            output["data"]["repeated_lines"] = serde_json::json!(self.repeated_lines);
+        }
+        if let Some(document_date) = self.document_date {
+            output["data"]["document_date"] = serde_json::json!(document_date);
+        }
+        if !self.correspondents.is_empty() {
+            output["data"]["correspondents"] = serde_json::json!(self.correspondents);
+        }
+        if let Some(ref summary) = self.summary {
+            output["data"]["summary"] = serde_json::json!(summary);
+        }
+        if let Some(ref translation) = self.translation {
+            output["data"]["translation"] = serde_json::json!(translation);
+        }
+        if let Some(ref entities) = self.entities {
+            output["data"]["entities"] = serde_json::json!(entities);
+        }
+        if let Some(ref keywords) = self.keywords {
+            output["data"]["keywords"] = serde_json::json!(keywords);
+        }
+        if let Some(ref extraction) = self.extraction {
+            output["data"]["extraction"] = serde_json::json!(extraction);
+        }
+        if let Some(ref embedding) = self.embedding {
+            output["data"]["embedding"] = serde_json::json!(embedding);
+        }
+
+        output
     }
+
+    /// Format result for JSON output with the pages array instead of the joined text
+    pub fn to_pages_json_output(&self) -> serde_json::Value {
+        let mut output = self.to_json_output();
+        if let Some(data) = output["data"].as_object_mut() {
+            data.remove("extracted_text");
+            data.insert("pages".to_string(), serde_json::json!(self.pages));
+        }
+        output
+    }
+}
+
+/// Nested rows below a heading, each on its own indented line
+fn indent(rows: &str) -> String {
+    rows.lines().map(|line| format!("\n  {}", line)).collect()
 }
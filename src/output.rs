@@ -0,0 +1,200 @@
+//! Human-readable terminal output
+//!
+//! Text output goes through a [`Styler`], which colors statuses and aligns
+//! `label: value` rows. `--color` decides whether ANSI colors are used:
+//!
+//! - `auto` (default): only when the stream is a terminal and `NO_COLOR` is
+//!   unset or empty (see <https://no-color.org>)
+//! - `always`: even when piped, regardless of `NO_COLOR`
+//! - `never`: plain text
+//!
+//! Plain output is the same text without escape codes, so scripts parsing the
+//! text see no difference between terminals and pipes.
+
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// Width of the status column in per-file lines (`ok`, `FAILED`)
+pub const STATUS_WIDTH: usize = 6;
+
+/// When to use ANSI colors, from `--color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color terminals unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always color
+    Always,
+    /// Never color
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            other => Err(format!(
+                "Unknown color choice: {}. Supported: auto, always, never",
+                other
+            )),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Whether to color a stream, given whether it is a terminal
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => terminal && !no_color(),
+        }
+    }
+
+    /// Styler for stdout
+    pub fn stdout(self) -> Styler {
+        Styler::new(self.enabled(std::io::stdout().is_terminal()))
+    }
+
+    /// Styler for stderr
+    pub fn stderr(self) -> Styler {
+        Styler::new(self.enabled(std::io::stderr().is_terminal()))
+    }
+}
+
+/// Whether `NO_COLOR` asks for plain output
+fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Formats text for the terminal, with or without ANSI colors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Styler {
+    color: bool,
+}
+
+impl Styler {
+    /// Styler coloring its output when `color` is true
+    pub fn new(color: bool) -> Self {
+        Self { color }
+    }
+
+    /// Styler producing plain text, for files and mail
+    pub fn plain() -> Self {
+        Self::default()
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Successful outcome, in green
+    pub fn success(&self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    /// Failed outcome, in bold red
+    pub fn failure(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    /// Something to look at, in yellow
+    pub fn warning(&self, text: &str) -> String {
+        self.paint("33", text)
+    }
+
+    /// Heading or label, in bold
+    pub fn label(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    /// Secondary detail such as timings, dimmed
+    pub fn dim(&self, text: &str) -> String {
+        self.paint("2", text)
+    }
+
+    /// Status column of a per-file line, padded to [`STATUS_WIDTH`]
+    pub fn status(&self, success: bool) -> String {
+        // Pad before painting so escape codes do not break the alignment
+        if success {
+            self.success(&format!("{:<STATUS_WIDTH$}", "ok"))
+        } else {
+            self.failure(&format!("{:<STATUS_WIDTH$}", "FAILED"))
+        }
+    }
+
+    /// `label: value` rows with the values aligned after the longest label
+    pub fn rows(&self, rows: &[(&str, String)]) -> String {
+        let width = rows
+            .iter()
+            .map(|(label, _)| label.len() + 1)
+            .max()
+            .unwrap_or(0);
+        rows.iter()
+            .map(|(label, value)| {
+                let label = format!("{:<width$}", format!("{}:", label));
+                format!("{} {}", self.label(&label), value)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Count with its singular or plural noun (`1 page`, `3 pages`)
+pub fn count(count: u64, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// Duration for the terminal (`850ms`, `4.2s`)
+pub fn duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        assert_eq!("ALWAYS".parse::<ColorChoice>(), Ok(ColorChoice::Always));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn test_plain_and_colored_output() {
+        let plain = Styler::plain();
+        assert_eq!(plain.status(true), "ok    ");
+        assert_eq!(plain.status(false), "FAILED");
+        assert_eq!(
+            plain.rows(&[("Pages", "3".to_string()), ("Quality", "0.91".to_string())]),
+            "Pages:   3\nQuality: 0.91"
+        );
+
+        let colored = Styler::new(true);
+        assert_eq!(colored.status(true), "\x1b[32mok    \x1b[0m");
+        assert_eq!(colored.failure("x"), "\x1b[1;31mx\x1b[0m");
+    }
+
+    #[test]
+    fn test_count_and_duration() {
+        assert_eq!(count(1, "page", "pages"), "1 page");
+        assert_eq!(count(0, "page", "pages"), "0 pages");
+        assert_eq!(duration(850), "850ms");
+        assert_eq!(duration(4230), "4.2s");
+    }
+}
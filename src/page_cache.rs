@@ -0,0 +1,301 @@
+//! Per-page OCR results
+//!
+//! With `[cache] pages = true`, the text of every PDF page is also stored in the
+//! result cache, keyed by the SHA-256 of the page rendered at a low resolution
+//! and the same options as whole documents. When a document changes, say a
+//! page was added to a contract or one page rescanned, the document no longer
+//! matches its cache entry but most of its pages do; only the others are
+//! copied into a PDF of their own (see [`crate::split`]) and sent to OCR:
+//!
+//! ```toml
+//! [cache]
+//! enabled = true
+//! pages = true
+//! ```
+//!
+//! Like whole documents, pages made with another model or API version are not
+//! reused (see [`Provenance`]).
+//!
+//! Rendering the pages for their hashes needs the `pdf-render` feature and
+//! poppler's `pdftoppm`.
+
+use crate::api::ocr::{OCRResponse, Page, UsageInfo};
+use crate::config::Config;
+use crate::error::Result;
+use crate::provider::{text_page, ProviderKind, ProviderOutput};
+use crate::render::RenderConfig;
+use crate::result_cache::{Provenance, ResultCache};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Resolution pages are rendered at for their hashes; enough to tell pages
+/// apart, cheap to render
+pub const PAGE_HASH_DPI: u32 = 72;
+
+/// A page stored in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+    /// Provider and model that read the page
+    #[serde(flatten)]
+    pub provenance: Provenance,
+
+    /// The page, with its index in the document it was read from
+    pub page: Page,
+}
+
+/// SHA-256 of each page of a PDF rendered at [`PAGE_HASH_DPI`]
+#[cfg(feature = "pdf-render")]
+pub async fn page_hashes(
+    pdf: &Path,
+    render: &RenderConfig,
+    temp: &crate::scratch::RunTemp,
+) -> Result<Vec<String>> {
+    use crate::error::Error;
+    use sha2::{Digest, Sha256};
+
+    let render = RenderConfig {
+        dpi: PAGE_HASH_DPI,
+        ..render.clone()
+    };
+    let pages = crate::render::rasterize(pdf, &render, temp).await?;
+    pages
+        .paths()
+        .iter()
+        .map(|page| {
+            let image = std::fs::read(page).map_err(Error::Io)?;
+            Ok(format!("{:x}", Sha256::digest(image)))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "pdf-render"))]
+pub async fn page_hashes(
+    _pdf: &Path,
+    _render: &RenderConfig,
+    _temp: &crate::scratch::RunTemp,
+) -> Result<Vec<String>> {
+    Err(crate::error::Error::validation(
+        "Per-page caching renders PDF pages; rebuild with the pdf-render feature".to_string(),
+    ))
+}
+
+/// The pages of a document found in the cache
+#[derive(Debug)]
+pub struct PageLookup {
+    hashes: Vec<String>,
+    cached: Vec<Option<CachedPage>>,
+}
+
+impl PageLookup {
+    /// Look up the pages with the given hashes under the given configuration
+    /// and options
+    pub fn new(
+        cache: &ResultCache,
+        app_config: &Config,
+        options: &str,
+        hashes: Vec<String>,
+    ) -> Self {
+        let cached = hashes
+            .iter()
+            .map(|hash| {
+                cache
+                    .get::<CachedPage>(&page_key(hash, options))
+                    .filter(|cached| cached.provenance.is_current(app_config, options))
+            })
+            .collect();
+        Self { hashes, cached }
+    }
+
+    /// Number of pages of the document
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether the document has no pages
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Indices of the pages missing from the cache
+    pub fn missing(&self) -> Vec<usize> {
+        self.cached
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Store the pages a provider read, numbered by their index in the
+    /// document
+    pub fn store(
+        &self,
+        cache: &ResultCache,
+        app_config: &Config,
+        options: &str,
+        output: &ProviderOutput,
+    ) {
+        let provenance = Provenance::of(output, app_config, options);
+        for page in &output.response.pages {
+            let index = page.index as usize;
+            let Some(hash) = self.hashes.get(index) else {
+                continue;
+            };
+            let fresh = CachedPage {
+                provenance: provenance.clone(),
+                page: page.clone(),
+            };
+            if let Err(e) = cache.put(&page_key(hash, options), &fresh) {
+                tracing::warn!("Failed to cache page {}: {}", index + 1, e.user_message());
+            }
+        }
+    }
+
+    /// Put the pages a provider read, numbered by their index in the document,
+    /// between the cached ones
+    ///
+    /// With no output, every page must be cached.
+    pub fn merge(self, output: Option<ProviderOutput>, file_size: u64) -> ProviderOutput {
+        let mut pages: Vec<Option<(ProviderKind, Page)>> = self
+            .cached
+            .into_iter()
+            .map(|cached| cached.map(|cached| (cached.provenance.provider, cached.page)))
+            .collect();
+        let (provider, file_id, model, upload_ms, ocr_ms) = match output {
+            Some(output) => {
+                for page in output.response.pages {
+                    if let Some(slot) = pages.get_mut(page.index as usize) {
+                        *slot = Some((output.provider, page));
+                    }
+                }
+                (
+                    output.provider,
+                    output.file_id,
+                    output.response.model,
+                    output.upload_ms,
+                    output.ocr_ms,
+                )
+            }
+            // Attribute the document to the provider of its first page
+            None => (
+                pages
+                    .iter()
+                    .flatten()
+                    .next()
+                    .map_or(ProviderKind::Mock, |(provider, _)| *provider),
+                "cache".to_string(),
+                "cache".to_string(),
+                0,
+                0,
+            ),
+        };
+
+        let pages: Vec<Page> = pages
+            .into_iter()
+            .enumerate()
+            .map(|(index, cached)| match cached {
+                Some((_, page)) => Page {
+                    index: index as i32,
+                    ..page
+                },
+                None => text_page(index, ""),
+            })
+            .collect();
+
+        ProviderOutput {
+            provider,
+            file_id,
+            response: OCRResponse {
+                usage_info: UsageInfo {
+                    pages_processed: pages.len() as i32,
+                    doc_size_bytes: file_size.min(i32::MAX as u64) as i32,
+                },
+                pages,
+                model,
+                document_annotation: None,
+            },
+            upload_ms,
+            ocr_ms,
+        }
+    }
+}
+
+/// Cache key of a page image under the output-relevant options
+pub fn page_key(image_sha256: &str, options: &str) -> String {
+    ResultCache::key(image_sha256, &format!("page;{}", options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn output(provider: ProviderKind, pages: &[(usize, &str)]) -> ProviderOutput {
+        ProviderOutput {
+            provider,
+            file_id: "file-1".to_string(),
+            response: OCRResponse {
+                usage_info: UsageInfo {
+                    pages_processed: pages.len() as i32,
+                    doc_size_bytes: 10,
+                },
+                pages: pages
+                    .iter()
+                    .map(|(index, text)| text_page(*index, text))
+                    .collect(),
+                model: "mistral-ocr-latest".to_string(),
+                document_annotation: None,
+            },
+            upload_ms: 3,
+            ocr_ms: 7,
+        }
+    }
+
+    #[test]
+    fn test_only_changed_pages_are_missing() {
+        let dir = TempDir::new().unwrap();
+        let cache = ResultCache::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+        let config = Config::default();
+        let hashes = |hashes: &[&str]| hashes.iter().map(|hash| hash.to_string()).collect();
+
+        let first = PageLookup::new(&cache, &config, "opts", hashes(&["a", "b"]));
+        assert_eq!(first.missing(), [0, 1]);
+        let fresh = output(ProviderKind::Mistral, &[(0, "Page A"), (1, "Page B")]);
+        first.store(&cache, &config, "opts", &fresh);
+        let merged = first.merge(Some(fresh), 10);
+        assert_eq!(merged.response.get_extracted_text(), "Page A\n\nPage B");
+
+        // A page was inserted and one rescanned
+        let updated = PageLookup::new(&cache, &config, "opts", hashes(&["a", "new", "b", "c"]));
+        assert_eq!(updated.len(), 4);
+        assert_eq!(updated.missing(), [1, 3]);
+        assert_eq!(
+            PageLookup::new(&cache, &config, "other", hashes(&["a"])).missing(),
+            [0]
+        );
+
+        let fresh = output(ProviderKind::Mock, &[(1, "Page New"), (3, "Page C")]);
+        updated.store(&cache, &config, "opts", &fresh);
+        let merged = updated.merge(Some(fresh), 20);
+        let pages: Vec<(i32, &str)> = merged
+            .response
+            .pages
+            .iter()
+            .map(|page| (page.index, page.markdown.as_str()))
+            .collect();
+        assert_eq!(
+            pages,
+            [(0, "Page A"), (1, "Page New"), (2, "Page B"), (3, "Page C")]
+        );
+        assert_eq!(merged.provider, ProviderKind::Mock);
+        assert_eq!(merged.response.usage_info.pages_processed, 4);
+
+        let cached = PageLookup::new(&cache, &config, "opts", hashes(&["c", "a"]));
+        assert!(cached.missing().is_empty());
+        let merged = cached.merge(None, 5);
+        assert_eq!(merged.provider, ProviderKind::Mock);
+        assert_eq!(merged.response.get_extracted_text(), "Page C\n\nPage A");
+        assert_eq!((merged.upload_ms, merged.ocr_ms), (0, 0));
+    }
+}
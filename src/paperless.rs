@@ -0,0 +1,326 @@
+//! paperless-ngx REST API client
+//!
+//! This module implements the subset of the paperless-ngx API needed to push
+//! OCR results back into an existing archive.
+//! Documentation: https://docs.paperless-ngx.com/api/
+//!
+//! Authentication uses `Authorization: Token <token>` headers. Documents are
+//! located either by the MD5 checksum of the original file (what paperless-ngx
+//! stores as `checksum`) or by their title.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How stored results are matched to paperless-ngx documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStrategy {
+    /// Match by checksum when the original file is available, otherwise by title
+    Auto,
+    /// Match only by MD5 checksum of the original file
+    Checksum,
+    /// Match only by document title
+    Title,
+}
+
+impl FromStr for MatchStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "checksum" => Ok(Self::Checksum),
+            "title" => Ok(Self::Title),
+            other => Err(format!(
+                "Unknown match strategy: {}. Supported: auto, checksum, title",
+                other
+            )),
+        }
+    }
+}
+
+/// Minimal paperless-ngx document representation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperlessDocument {
+    pub id: i64,
+    pub title: String,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Paginated list response returned by paperless-ngx
+#[derive(Debug, Clone, Deserialize)]
+struct DocumentList {
+    count: u64,
+    results: Vec<PaperlessDocument>,
+}
+
+/// paperless-ngx tag with its auto-matching pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperlessTag {
+    pub id: i64,
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub match_text: String,
+    /// How `match` is applied to documents
+    #[serde(default)]
+    pub matching_algorithm: MatchingAlgorithm,
+    /// Whether `match` ignores case
+    #[serde(default = "default_is_insensitive")]
+    pub is_insensitive: bool,
+}
+
+fn default_is_insensitive() -> bool {
+    true
+}
+
+/// Matching algorithm of a paperless-ngx tag, sent as its number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum MatchingAlgorithm {
+    /// Never matched automatically
+    None,
+    /// Any of the words of `match`
+    Any,
+    /// All of the words of `match`
+    All,
+    /// `match` as an exact phrase
+    Literal,
+    /// `match` as a regular expression
+    Regex,
+    /// A phrase similar to `match`
+    Fuzzy,
+    /// Learned by the paperless-ngx classifier
+    #[default]
+    Auto,
+    /// An algorithm this version does not know
+    Unsupported(u8),
+}
+
+impl From<u8> for MatchingAlgorithm {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::None,
+            1 => Self::Any,
+            2 => Self::All,
+            3 => Self::Literal,
+            4 => Self::Regex,
+            5 => Self::Fuzzy,
+            6 => Self::Auto,
+            other => Self::Unsupported(other),
+        }
+    }
+}
+
+impl From<MatchingAlgorithm> for u8 {
+    fn from(algorithm: MatchingAlgorithm) -> Self {
+        match algorithm {
+            MatchingAlgorithm::None => 0,
+            MatchingAlgorithm::Any => 1,
+            MatchingAlgorithm::All => 2,
+            MatchingAlgorithm::Literal => 3,
+            MatchingAlgorithm::Regex => 4,
+            MatchingAlgorithm::Fuzzy => 5,
+            MatchingAlgorithm::Auto => 6,
+            MatchingAlgorithm::Unsupported(other) => other,
+        }
+    }
+}
+
+/// Paginated tag list returned by paperless-ngx
+#[derive(Debug, Clone, Deserialize)]
+struct TagList {
+    next: Option<String>,
+    results: Vec<PaperlessTag>,
+}
+
+/// paperless-ngx API client
+#[derive(Debug, Clone)]
+pub struct PaperlessClient {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl PaperlessClient {
+    /// Create a new client from the validated paperless settings of `config`
+    pub fn new(config: &Config) -> Result<Self> {
+        let paperless = &config.paperless;
+        paperless.validate(&config.env_prefix)?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .user_agent(format!("paperless-ngx-ocr2/{}", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| Error::internal_from("Failed to create HTTP client", e))?;
+
+        Ok(Self {
+            client,
+            base_url: paperless.url.clone().unwrap_or_default(),
+            token: paperless.token.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Build a full URL for an API endpoint
+    pub fn build_url(&self, endpoint: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            endpoint.trim_start_matches('/')
+        )
+    }
+
+    /// Find the document whose original file has the given MD5 checksum
+    pub async fn find_by_checksum(&self, checksum: &str) -> Result<Option<PaperlessDocument>> {
+        self.find_unique(&[("checksum__iexact", checksum)]).await
+    }
+
+    /// Find the document with exactly the given title (case-insensitive)
+    pub async fn find_by_title(&self, title: &str) -> Result<Option<PaperlessDocument>> {
+        self.find_unique(&[("title__iexact", title)]).await
+    }
+
+    /// Replace the content (full text) of a document
+    pub async fn update_content(&self, document_id: i64, content: &str) -> Result<()> {
+        let url = self.build_url(&format!("api/documents/{}/", document_id));
+        tracing::debug!("paperless-ngx request: PATCH {}", url);
+
+        let response = self
+            .client
+            .patch(&url)
+            .header(reqwest::header::AUTHORIZATION, self.auth_header())
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .map_err(Error::Network)?;
+
+        Self::check_status(response).await?;
+        Ok(())
+    }
+
+    /// Fetch all tags, following pagination
+    pub async fn list_tags(&self) -> Result<Vec<PaperlessTag>> {
+        let mut tags = Vec::new();
+        let mut next = Some(self.build_url("api/tags/?page_size=100"));
+
+        while let Some(url) = next {
+            tracing::debug!("paperless-ngx request: GET {}", url);
+            let response = self
+                .client
+                .get(&url)
+                .header(reqwest::header::AUTHORIZATION, self.auth_header())
+                .send()
+                .await
+                .map_err(Error::Network)?;
+
+            let response = Self::check_status(response).await?;
+            let page: TagList = response
+                .json()
+                .await
+                .map_err(|e| Error::api_from("Failed to parse paperless-ngx tag list", e))?;
+
+            tags.extend(page.results);
+            next = page.next;
+        }
+
+        Ok(tags)
+    }
+
+    /// Query documents and return the match only if it is unambiguous
+    async fn find_unique(&self, query: &[(&str, &str)]) -> Result<Option<PaperlessDocument>> {
+        let url = self.build_url("api/documents/");
+        tracing::debug!("paperless-ngx request: GET {} {:?}", url, query);
+
+        let response = self
+            .client
+            .get(&url)
+            .header(reqwest::header::AUTHORIZATION, self.auth_header())
+            .query(query)
+            .send()
+            .await
+            .map_err(Error::Network)?;
+
+        let response = Self::check_status(response).await?;
+        let list: DocumentList = response
+            .json()
+            .await
+            .map_err(|e| Error::api_from("Failed to parse paperless-ngx document list", e))?;
+
+        match list.count {
+            0 => Ok(None),
+            1 => Ok(list.results.into_iter().next()),
+            count => Err(Error::validation(format!(
+                "Ambiguous paperless-ngx match: {} documents found",
+                count
+            ))),
+        }
+    }
+
+    /// Convert non-success HTTP statuses into errors
+    async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        Err(Error::from_http_status(
+            status.as_u16(),
+            format!("paperless-ngx: {}", body),
+        ))
+    }
+
+    /// Get the authorization header value
+    fn auth_header(&self) -> String {
+        format!("Token {}", self.token)
+    }
+}
+
+/// Compute the MD5 checksum paperless-ngx stores for original files
+pub fn file_checksum(data: &[u8]) -> String {
+    use md5::{Digest, Md5};
+
+    format!("{:x}", Md5::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_strategy_parsing() {
+        assert_eq!("auto".parse::<MatchStrategy>(), Ok(MatchStrategy::Auto));
+        assert_eq!(
+            "Checksum".parse::<MatchStrategy>(),
+            Ok(MatchStrategy::Checksum)
+        );
+        assert_eq!("title".parse::<MatchStrategy>(), Ok(MatchStrategy::Title));
+        assert!("filename".parse::<MatchStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_file_checksum_is_md5() {
+        assert_eq!(file_checksum(b""), "d41d8cd98f00b204e9800998ecf8427e");
+    }
+
+    #[test]
+    fn test_build_url() {
+        let config = Config {
+            paperless: crate::config::PaperlessConfig {
+                url: Some("http://paperless:8000/".to_string()),
+                token: Some("token".to_string()),
+            },
+            ..Config::default()
+        };
+        let client = PaperlessClient::new(&config).unwrap();
+        assert_eq!(
+            client.build_url("/api/documents/"),
+            "http://paperless:8000/api/documents/"
+        );
+    }
+}
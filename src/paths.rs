@@ -0,0 +1,47 @@
+//! Platform directories for files kept between runs
+//!
+//! The result cache, the persistent state, run manifests, and the job queue go
+//! to the platform's cache and state directories instead of the current
+//! directory:
+//!
+//! | Files | Linux | macOS | Windows |
+//! |-------|-------|-------|---------|
+//! | Cache | `$XDG_CACHE_HOME/paperless-ngx-ocr2` | `~/Library/Caches/paperless-ngx-ocr2` | `%LOCALAPPDATA%\paperless-ngx-ocr2\cache` |
+//! | State | `$XDG_STATE_HOME/paperless-ngx-ocr2` | `~/Library/Application Support/paperless-ngx-ocr2` | `%LOCALAPPDATA%\paperless-ngx-ocr2\data` |
+//!
+//! On Linux the XDG variables default to `~/.cache` and `~/.local/state`.
+//! Every location can also be set explicitly in the configuration.
+
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "paperless-ngx-ocr2")
+}
+
+/// Directory for files that can be recreated, such as cached results
+pub fn cache_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+/// Directory for files that should survive between runs but are not worth
+/// backing up, such as page counts and run manifests
+///
+/// Only Linux has a state directory; other platforms use local app data.
+pub fn state_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| {
+        dirs.state_dir()
+            .unwrap_or_else(|| dirs.data_local_dir())
+            .to_path_buf()
+    })
+}
+
+/// Default directory for run manifests
+pub fn manifest_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("manifests"))
+}
+
+/// Default directory for the job queue of `serve`
+pub fn queue_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("jobs"))
+}
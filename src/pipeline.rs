@@ -0,0 +1,916 @@
+//! OCR pipeline for a single document
+//!
+//! Validation, the provider chain with the result and page caches, and the
+//! local and chat-based post-processing of the result. The CLI, `serve`, and
+//! the C interface all run documents through [`ocr_file`].
+
+use crate::api::{
+    chat::ChatClient,
+    embeddings::EmbeddingsClient,
+    ocr::{OCRResponse, ResponseFormat},
+    MistralClient,
+};
+use crate::boilerplate::{detect_repeated_lines, strip_repeated_lines};
+use crate::cache::CacheManager;
+use crate::config::Config;
+use crate::credentials::APICredentials;
+use crate::diagnostics::GLOBAL_IN_FLIGHT;
+use crate::ensemble::MergeStrategy;
+use crate::error::{Error, Result};
+use crate::extraction::{Extraction, ExtractionPreset};
+use crate::file::FileUpload;
+use crate::memory::MemoryBudget;
+use crate::metrics::MetricsCollector;
+use crate::ocr::{OCRResult, ResultPage, Timings, Translation};
+use crate::page_cache::{page_hashes, PageLookup};
+use crate::progress::{ProgressEvent, GLOBAL_PROGRESS};
+use crate::provider::{
+    pages_upload, process_ensemble, process_with_fallback, renumber_pages, MistralUpload,
+    ProviderKind, ProviderOutput, ProviderRequest,
+};
+use crate::quality::{LowQualityAction, QualityScore};
+use crate::result_cache::{CachedOutput, Freshness, ResultCache, Revalidations};
+use crate::scratch::RunTemp;
+use crate::split;
+use crate::summary::RunCounters;
+use std::path::Path;
+use std::time::Instant;
+
+/// Optional processing steps requested on the command line
+#[derive(Debug, Clone, Default)]
+pub struct ProcessingOptions {
+    /// Generate a short summary of the extracted text
+    pub summarize: bool,
+
+    /// Translate the extracted text into this language code
+    pub translate_to: Option<String>,
+
+    /// Extract named entities and keywords
+    pub extract_entities: bool,
+
+    /// Request an embedding of the extracted text
+    pub embed: bool,
+
+    /// Extract structured fields with a preset
+    pub extract: Option<ExtractionPreset>,
+
+    /// Omit blank pages from the extracted text
+    pub skip_blank_pages: bool,
+
+    /// Use the text layer of digital-born PDFs instead of the providers
+    pub skip_text_pdfs: bool,
+
+    /// Omit pages repeating the preceding page from the extracted text
+    pub remove_duplicate_pages: bool,
+
+    /// Remove lines repeated on most pages from the extracted text
+    pub strip_headers: bool,
+
+    /// Fail when fewer non-whitespace characters are extracted
+    pub min_chars: Option<usize>,
+
+    /// Write a `<document>.json` sidecar next to the input
+    pub sidecar: bool,
+
+    /// Gzip-compress stdout and sidecars
+    pub compress_output: bool,
+
+    /// Treat the input as this MIME type instead of detecting it
+    pub mime_type: Option<String>,
+
+    /// Free-form guidance sent with the OCR request
+    pub instructions: Option<String>,
+
+    /// Page text format to request from the OCR API
+    pub response_format: Option<ResponseFormat>,
+
+    /// Commands run after each document
+    #[cfg(feature = "cli")]
+    pub hooks: crate::hooks::Hooks,
+
+    /// Collector receiving the API metrics of the run
+    pub metrics: MetricsCollector,
+
+    /// In-memory caches of the run
+    pub cache: CacheManager,
+
+    /// Temporary directory, memory budget, and counters of the run
+    pub run: RunContext,
+}
+
+/// State shared by the documents of a run, or of a server
+///
+/// Clones share the state, so a run creates one context and hands it to every
+/// document it processes.
+#[derive(Debug, Clone, Default)]
+pub struct RunContext {
+    /// Files, pages, and failures for the end-of-run summary
+    pub counters: RunCounters,
+
+    /// Directory for the intermediate files of the documents
+    pub temp: RunTemp,
+
+    /// Background refreshes of stale result cache entries
+    pub revalidations: Revalidations,
+
+    /// Budget for the document buffers held in memory
+    pub memory: MemoryBudget,
+}
+
+/// Run the OCR pipeline for a single file and return the result
+pub async fn ocr_file(
+    input_file_path: &str,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    enable_verbose_logging: bool,
+) -> Result<OCRResult> {
+    let validated = validate_file(input_file_path, app_config, options);
+    ocr_validated(
+        input_file_path,
+        validated,
+        app_config,
+        options,
+        enable_verbose_logging,
+    )
+    .await
+}
+
+/// A document that passed validation, waiting for the provider chain
+pub(crate) struct ValidatedFile {
+    file_upload: FileUpload,
+    validation_ms: u64,
+    upload: Option<MistralUpload>,
+}
+
+/// Validate a document before it is handed to the provider chain
+pub(crate) fn validate_file(
+    input_file_path: &str,
+    app_config: &Config,
+    options: &ProcessingOptions,
+) -> Result<ValidatedFile> {
+    let start_time = Instant::now();
+    let file_upload =
+        validate_input_file(input_file_path, app_config, options.mime_type.as_deref())?;
+    Ok(ValidatedFile {
+        file_upload,
+        validation_ms: start_time.elapsed().as_millis() as u64,
+        upload: None,
+    })
+}
+
+/// Upload a validated document to Mistral ahead of its OCR request, when the
+/// provider chain will send the whole document to Mistral first
+///
+/// A failed upload is left to the provider chain, which uploads the document
+/// again and falls back to the next provider as usual.
+#[cfg(feature = "cli")]
+pub(crate) async fn upload_validated(
+    validated: Result<ValidatedFile>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+) -> Result<ValidatedFile> {
+    let mut validated = validated?;
+    if !uploads_ahead(&validated.file_upload, app_config, options) {
+        return Ok(validated);
+    }
+
+    match crate::provider::upload_mistral(
+        &validated.file_upload,
+        app_config,
+        options.metrics.clone(),
+        options.run.memory.clone(),
+    )
+    .await
+    {
+        Ok(upload) => validated.upload = Some(upload),
+        Err(e) => tracing::debug!(
+            "Failed to upload {} ahead of OCR: {}",
+            validated.file_upload.get_filename(),
+            e.user_message()
+        ),
+    }
+    Ok(validated)
+}
+
+/// Whether the provider chain starts by uploading the whole document to Mistral
+#[cfg(feature = "cli")]
+fn uploads_ahead(
+    file_upload: &FileUpload,
+    app_config: &Config,
+    options: &ProcessingOptions,
+) -> bool {
+    let pdf = file_upload.mime_type == "application/pdf";
+    // The result cache (unused with encrypted outputs) or the text layer may
+    // answer without OCR
+    let cached = app_config.cache.enabled && !app_config.encryption.is_enabled();
+    // Long PDFs are uploaded in chunks
+    let split = pdf && (options.skip_text_pdfs || app_config.split.chunk_pages.is_some());
+    app_config.providers.first() == Some(&ProviderKind::Mistral) && !cached && !split
+}
+
+/// Run the OCR pipeline for a file validated with [`validate_file`]
+///
+/// A failed validation is reported like any other failure of the file.
+pub(crate) async fn ocr_validated(
+    input_file_path: &str,
+    validated: Result<ValidatedFile>,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    enable_verbose_logging: bool,
+) -> Result<OCRResult> {
+    // Stop runs over many files before they exceed the page or cost budget
+    app_config
+        .budget
+        .check(options.run.counters.files(), options.run.counters.pages())?;
+
+    let _in_flight = GLOBAL_IN_FLIGHT.track(input_file_path);
+    GLOBAL_PROGRESS.emit(ProgressEvent::FileStarted {
+        file: input_file_path,
+    });
+
+    let result = match validated {
+        Ok(validated) => {
+            crate::trace_context::in_trace(
+                app_config.otel.enabled,
+                run_pipeline(
+                    input_file_path,
+                    validated,
+                    app_config,
+                    options,
+                    enable_verbose_logging,
+                ),
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match &result {
+        Ok(ocr_result) => {
+            options
+                .run
+                .counters
+                .record_file(input_file_path, ocr_result.page_count());
+            GLOBAL_PROGRESS.emit(ProgressEvent::FileCompleted {
+                file: input_file_path,
+                chars: ocr_result.extracted_text.chars().count(),
+            })
+        }
+        Err(e) => {
+            options
+                .run
+                .counters
+                .record_failure(input_file_path, &e.user_message());
+            GLOBAL_PROGRESS.emit(ProgressEvent::FileFailed {
+                file: input_file_path,
+                error: e.user_message(),
+            })
+        }
+    }
+
+    result
+}
+
+/// Options that change the provider output, as part of the result cache key
+pub(crate) fn cache_options(app_config: &Config, options: &ProcessingOptions) -> String {
+    format!(
+        "providers={:?};merge={:?};extract={:?};instructions={:?};format={:?};mime={:?};min_quality={:?}",
+        app_config.providers,
+        app_config.merge_strategy,
+        options.extract,
+        options.instructions,
+        options.response_format,
+        options.mime_type,
+        app_config.quality.min_score,
+    )
+}
+
+/// Run the provider chain, re-running once when the result is below the
+/// quality minimum
+async fn run_providers(
+    file_upload: &FileUpload,
+    app_config: &Config,
+    provider_request: &ProviderRequest,
+    enable_verbose_logging: bool,
+) -> Result<ProviderOutput> {
+    let mut output = match app_config.merge_strategy {
+        MergeStrategy::Fallback => {
+            process_with_fallback(
+                &app_config.providers,
+                file_upload,
+                app_config,
+                provider_request,
+            )
+            .await?
+        }
+        MergeStrategy::Ensemble => {
+            process_ensemble(
+                &app_config.providers,
+                file_upload,
+                app_config,
+                provider_request,
+            )
+            .await?
+        }
+    };
+
+    if enable_verbose_logging {
+        tracing::info!("OCR processing completed with {}", output.provider);
+    }
+
+    // Re-run once when the result is below the quality minimum, preferring
+    // the providers after the one that produced it
+    let quality_of = |response: &OCRResponse| {
+        QualityScore::from_text(&response.get_extracted_text()).map_or(0.0, |q| q.score)
+    };
+    if let Some(min_score) = app_config.quality.min_score {
+        let score = quality_of(&output.response);
+        if score < min_score && app_config.quality.on_low_quality == LowQualityAction::Rerun {
+            let position = app_config
+                .providers
+                .iter()
+                .position(|provider| *provider == output.provider)
+                .unwrap_or(0);
+            let remaining = &app_config.providers[position + 1..];
+            let rerun_providers = if remaining.is_empty() {
+                std::slice::from_ref(&output.provider)
+            } else {
+                remaining
+            };
+
+            tracing::warn!(
+                "OCR quality {:.2} is below {:.2}, re-running with {}",
+                score,
+                min_score,
+                rerun_providers[0]
+            );
+            match process_with_fallback(rerun_providers, file_upload, app_config, provider_request)
+                .await
+            {
+                Ok(rerun) if quality_of(&rerun.response) > score => output = rerun,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Quality re-run failed: {}", e.user_message()),
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Process a document with a stale cache entry again in the background and
+/// replace the entry
+///
+/// The document is copied first, as files received by `serve` are removed once
+/// their response is sent.
+fn revalidate(
+    file_upload: &FileUpload,
+    app_config: &Config,
+    provider_request: &ProviderRequest,
+    cache: ResultCache,
+    cache_key: String,
+    entry_options: String,
+    enable_verbose_logging: bool,
+) {
+    let copy = provider_request
+        .run
+        .temp
+        .create_dir("revalidate")
+        .and_then(|dir| {
+            let path = dir.path().join(file_upload.get_filename());
+            std::fs::copy(&file_upload.file_path, &path).map_err(Error::Io)?;
+            Ok((dir, path))
+        });
+    let (dir, path) = match copy {
+        Ok(copy) => copy,
+        Err(e) => {
+            tracing::warn!("Failed to refresh the cached result: {}", e.user_message());
+            return;
+        }
+    };
+
+    let file_upload = FileUpload {
+        file_path: path.to_string_lossy().to_string(),
+        ..file_upload.clone()
+    };
+    let app_config = app_config.clone();
+    let provider_request = provider_request.clone();
+    let key = cache_key.clone();
+    let revalidations = provider_request.run.revalidations.clone();
+    revalidations.spawn(
+        &key,
+        crate::trace_context::propagate(async move {
+            let _dir = dir;
+            tracing::debug!(
+                "Refreshing the cached result of {}",
+                file_upload.get_filename()
+            );
+            let refreshed = run_providers(
+                &file_upload,
+                &app_config,
+                &provider_request,
+                enable_verbose_logging,
+            )
+            .await
+            .and_then(|output| {
+                cache.put(
+                    &cache_key,
+                    &CachedOutput::new(output, &app_config, &entry_options),
+                )
+            });
+            if let Err(e) = refreshed {
+                tracing::warn!(
+                    "Failed to refresh the cached result of {}: {}",
+                    file_upload.get_filename(),
+                    e.user_message()
+                );
+            }
+        }),
+    );
+}
+
+/// Run the provider chain on the pages of a PDF missing from the page cache,
+/// taking the others from it
+async fn run_providers_by_page(
+    file_upload: &FileUpload,
+    app_config: &Config,
+    provider_request: &ProviderRequest,
+    cache: &ResultCache,
+    options: &str,
+    enable_verbose_logging: bool,
+) -> Result<ProviderOutput> {
+    let pdf = Path::new(&file_upload.file_path);
+    let hashes = match page_hashes(pdf, &app_config.render, &provider_request.run.temp).await {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to hash the pages of {}: {}; processing it whole",
+                file_upload.get_filename(),
+                e.user_message()
+            );
+            return run_providers(
+                file_upload,
+                app_config,
+                provider_request,
+                enable_verbose_logging,
+            )
+            .await;
+        }
+    };
+
+    let lookup = PageLookup::new(cache, app_config, options, hashes);
+    let missing = lookup.missing();
+    if missing.is_empty() {
+        tracing::debug!("Using the cached pages of {}", file_upload.get_filename());
+        provider_request.run.counters.record_cache_hit();
+        return Ok(lookup.merge(None, file_upload.file_size));
+    }
+
+    let output = if missing.len() == lookup.len() {
+        run_providers(
+            file_upload,
+            app_config,
+            provider_request,
+            enable_verbose_logging,
+        )
+        .await?
+    } else {
+        tracing::info!(
+            "Using {} cached pages of {}; processing the other {}",
+            lookup.len() - missing.len(),
+            file_upload.get_filename(),
+            missing.len()
+        );
+        let pages =
+            split::extract_pages(pdf, &missing, &app_config.split, &provider_request.run.temp)
+                .await?;
+        run_providers(
+            &pages_upload(file_upload, &pages)?,
+            app_config,
+            provider_request,
+            enable_verbose_logging,
+        )
+        .await?
+    };
+
+    // Pages can only be matched to their images when every one was read
+    if output.response.pages.len() != missing.len() {
+        tracing::warn!(
+            "The {} provider returned {} pages for {} pages of {}; not caching them",
+            output.provider,
+            output.response.pages.len(),
+            missing.len(),
+            file_upload.get_filename()
+        );
+        if missing.len() == lookup.len() {
+            return Ok(output);
+        }
+    }
+    let complete = output.response.pages.len() == missing.len();
+    let output = ProviderOutput {
+        response: OCRResponse {
+            pages: renumber_pages(output.provider, &output.response, &missing),
+            ..output.response
+        },
+        ..output
+    };
+    if complete {
+        lookup.store(cache, app_config, options, &output);
+    }
+    Ok(lookup.merge(Some(output), file_upload.file_size))
+}
+
+/// The text layer of a digital-born PDF, or `None` when it needs OCR
+async fn text_layer_output(
+    file_upload: &FileUpload,
+    app_config: &Config,
+    provider_request: &ProviderRequest,
+) -> Option<ProviderOutput> {
+    // A chain with local-text reads the text layer itself
+    if file_upload.mime_type != "application/pdf"
+        || app_config.providers.contains(&ProviderKind::LocalText)
+    {
+        return None;
+    }
+    match crate::provider::process(
+        ProviderKind::LocalText,
+        file_upload,
+        app_config,
+        provider_request,
+    )
+    .await
+    {
+        Ok(output) => Some(output),
+        Err(Error::InsufficientText(reason)) => {
+            tracing::debug!("{}; running OCR", reason);
+            None
+        }
+        // A missing pdftotext must not stop the run; OCR still works
+        Err(e) => {
+            tracing::warn!(
+                "Failed to read the text layer of {}: {}",
+                file_upload.get_filename(),
+                e.user_message()
+            );
+            None
+        }
+    }
+}
+
+/// Extract and post-process a validated file
+async fn run_pipeline(
+    input_file_path: &str,
+    validated: ValidatedFile,
+    app_config: &Config,
+    options: &ProcessingOptions,
+    enable_verbose_logging: bool,
+) -> Result<OCRResult> {
+    if enable_verbose_logging {
+        tracing::info!("Processing OCR command for file: {}", input_file_path);
+    }
+
+    let start_time = Instant::now();
+    let ValidatedFile {
+        file_upload,
+        validation_ms,
+        upload,
+    } = validated;
+    let sha256 = file_upload.sha256()?;
+
+    // Run the provider chain, unless the persistent cache has the result
+    let provider_request = ProviderRequest {
+        annotation_format: options.extract.map(|preset| preset.annotation_format()),
+        instructions: options.instructions.clone(),
+        response_format: options.response_format,
+        metrics: options.metrics.clone(),
+        cache: options.cache.clone(),
+        run: options.run.clone(),
+        upload,
+    };
+    // Cache entries hold the text in plaintext, which must not reach the disk
+    // when outputs are encrypted
+    let cache = if app_config.encryption.is_enabled() {
+        None
+    } else {
+        app_config.cache.open_enabled()
+    };
+    let entry_options = cache_options(app_config, options);
+    let cache_key = ResultCache::key(&sha256, &entry_options);
+    let text_layer = if options.skip_text_pdfs {
+        text_layer_output(&file_upload, app_config, &provider_request).await
+    } else {
+        None
+    };
+    let cached = match text_layer {
+        Some(_) => None,
+        None => cache
+            .as_ref()
+            .and_then(|cache| cache.lookup::<CachedOutput>(&cache_key))
+            .filter(|(entry, _)| entry.provenance.is_current(app_config, &entry_options))
+            .map(|(entry, freshness)| (entry.output, freshness)),
+    };
+    let output = match (text_layer, cached) {
+        (Some(output), _) => {
+            tracing::info!("Using the text layer of {} instead of OCR", input_file_path);
+            output
+        }
+        (None, Some((output, freshness))) => {
+            tracing::debug!("Using the cached OCR result of {}", input_file_path);
+            options.run.counters.record_cache_hit();
+            if let (Freshness::Stale, Some(cache)) = (freshness, cache) {
+                revalidate(
+                    &file_upload,
+                    app_config,
+                    &provider_request,
+                    cache,
+                    cache_key,
+                    entry_options,
+                    enable_verbose_logging,
+                );
+            }
+            ProviderOutput {
+                upload_ms: 0,
+                ocr_ms: 0,
+                ..output
+            }
+        }
+        (None, None) => {
+            let output = match cache {
+                Some(ref cache)
+                    if app_config.cache.pages
+                        && file_upload.mime_type == "application/pdf"
+                        && options.extract.is_none() =>
+                {
+                    // Boxed, as the pipeline future would be large enough to
+                    // overflow the stacks of server threads otherwise
+                    Box::pin(run_providers_by_page(
+                        &file_upload,
+                        app_config,
+                        &provider_request,
+                        cache,
+                        &entry_options,
+                        enable_verbose_logging,
+                    ))
+                    .await?
+                }
+                _ => {
+                    run_providers(
+                        &file_upload,
+                        app_config,
+                        &provider_request,
+                        enable_verbose_logging,
+                    )
+                    .await?
+                }
+            };
+            if let Some(ref cache) = cache {
+                let entry = CachedOutput::new(output.clone(), app_config, &entry_options);
+                if let Err(e) = cache.put(&cache_key, &entry) {
+                    tracing::warn!("Failed to cache the OCR result: {}", e.user_message());
+                }
+            }
+            output
+        }
+    };
+
+    let ProviderOutput {
+        provider,
+        file_id,
+        response: mut ocr_response,
+        upload_ms,
+        ocr_ms,
+    } = output;
+
+    // Validate the structured annotation before the response is consumed
+    let extraction = match (options.extract, &ocr_response.document_annotation) {
+        (Some(preset), Some(annotation)) => Some(Extraction {
+            preset,
+            fields: preset.parse_annotation(annotation)?,
+        }),
+        (Some(preset), None) => {
+            return Err(Error::api(format!(
+                "The {} provider returned no document annotation for the {} preset",
+                provider,
+                preset.name()
+            )))
+        }
+        (None, _) => None,
+    };
+
+    let blank_pages = ocr_response.blank_page_indices(app_config.blank_page_threshold);
+    if !blank_pages.is_empty() {
+        tracing::debug!("Blank pages detected: {:?}", blank_pages);
+    }
+
+    let duplicate_pages = ocr_response.duplicate_pages(app_config.blank_page_threshold);
+    let mut removed_pages = Vec::new();
+    if !duplicate_pages.is_empty() {
+        tracing::debug!("Duplicate pages detected: {:?}", duplicate_pages);
+        if options.remove_duplicate_pages {
+            removed_pages = duplicate_pages.iter().map(|page| page.index).collect();
+            ocr_response.remove_pages(&removed_pages);
+        }
+    }
+
+    let repeated_lines = {
+        let texts: Vec<&str> = ocr_response
+            .ordered_pages(Some(app_config.blank_page_threshold))
+            .into_iter()
+            .map(|page| page.markdown.as_str())
+            .collect();
+        detect_repeated_lines(&texts)
+    };
+
+    let skip_blank = options
+        .skip_blank_pages
+        .then_some(app_config.blank_page_threshold);
+    // Pages keep their raw text; stripping only applies to the merged text
+    let pages: Vec<ResultPage> = ocr_response
+        .ordered_pages(skip_blank)
+        .into_iter()
+        .map(ResultPage::from)
+        .collect();
+    if options.strip_headers && !repeated_lines.is_empty() {
+        tracing::debug!("Stripping repeated lines: {:?}", repeated_lines);
+        for page in &mut ocr_response.pages {
+            page.markdown = strip_repeated_lines(&page.markdown, &repeated_lines);
+        }
+    }
+    let extracted_text = ocr_response.extracted_text(&app_config.pages, skip_blank);
+
+    // Create result from API response
+    let mut result = OCRResult::from_extracted_text(
+        extracted_text,
+        file_id,
+        ocr_response.model,
+        file_upload.get_filename(),
+        file_upload.file_size,
+        {
+            let mut usage_map = std::collections::HashMap::new();
+            usage_map.insert(
+                "pages_processed".to_string(),
+                ocr_response.usage_info.pages_processed as i64,
+            );
+            usage_map.insert(
+                "doc_size_bytes".to_string(),
+                ocr_response.usage_info.doc_size_bytes as i64,
+            );
+            Some(usage_map)
+        },
+    );
+
+    result.provider = Some(provider.to_string());
+    result.sha256 = Some(sha256);
+    result.extraction = extraction;
+    result.blank_pages = blank_pages;
+    result.duplicate_pages = duplicate_pages;
+    result.removed_pages = removed_pages;
+    result.repeated_lines = repeated_lines;
+    result.pages = pages;
+
+    if let Some(min_chars) = options.min_chars {
+        check_min_chars(&result, min_chars)?;
+    }
+
+    result.quality = QualityScore::from_text(&result.extracted_text);
+    if let (Some(min_score), Some(quality)) = (app_config.quality.min_score, result.quality) {
+        if quality.score < min_score {
+            if app_config.quality.on_low_quality == LowQualityAction::Fail {
+                return Err(Error::InsufficientText(format!(
+                    "OCR quality {:.2} of {} is below the minimum {:.2}",
+                    quality.score, result.file_name, min_score
+                )));
+            }
+            tracing::warn!(
+                "OCR quality {:.2} of {} is below the minimum {:.2}",
+                quality.score,
+                result.file_name,
+                min_score
+            );
+        }
+    }
+
+    // Local post-processing
+    result.document_date =
+        crate::dates::detect_document_date(&result.extracted_text, app_config.date_order);
+    result.correspondents = crate::correspondents::detect_correspondents(&result.extracted_text);
+
+    // Optional chat-based post-processing
+    let needs_chat =
+        options.summarize || options.translate_to.is_some() || options.extract_entities;
+    if needs_chat && !result.is_empty_text() {
+        let api_credentials = APICredentials::from_config(app_config)?;
+        let mistral_client = MistralClient::from_config(api_credentials, app_config)?
+            .with_metrics(options.metrics.clone());
+        let chat_client = ChatClient::new(mistral_client, app_config.chat_model.clone());
+
+        if options.summarize {
+            GLOBAL_PROGRESS.emit(ProgressEvent::PostprocessingStarted {
+                file: input_file_path,
+                step: "summary",
+            });
+            result.summary = Some(chat_client.summarize(&result.extracted_text).await?);
+
+            if enable_verbose_logging {
+                tracing::info!("Summary generated with {}", app_config.chat_model);
+            }
+        }
+
+        if let Some(ref language) = options.translate_to {
+            GLOBAL_PROGRESS.emit(ProgressEvent::PostprocessingStarted {
+                file: input_file_path,
+                step: "translation",
+            });
+            let text = chat_client
+                .translate(&result.extracted_text, language)
+                .await?;
+            result.translation = Some(Translation {
+                language: language.clone(),
+                text,
+            });
+
+            if enable_verbose_logging {
+                tracing::info!("Text translated to {}", language);
+            }
+        }
+
+        if options.extract_entities {
+            GLOBAL_PROGRESS.emit(ProgressEvent::PostprocessingStarted {
+                file: input_file_path,
+                step: "entities",
+            });
+            let (entities, keywords) = chat_client.extract_entities(&result.extracted_text).await?;
+            result.entities = Some(entities);
+            result.keywords = Some(keywords);
+
+            if enable_verbose_logging {
+                tracing::info!("Entities and keywords extracted");
+            }
+        }
+    }
+
+    if options.embed && !result.is_empty_text() {
+        GLOBAL_PROGRESS.emit(ProgressEvent::PostprocessingStarted {
+            file: input_file_path,
+            step: "embedding",
+        });
+        let api_credentials = APICredentials::from_config(app_config)?;
+        let mistral_client = MistralClient::from_config(api_credentials, app_config)?
+            .with_metrics(options.metrics.clone());
+        let embeddings_client =
+            EmbeddingsClient::new(mistral_client, app_config.embedding_model.clone());
+        result.embedding = Some(embeddings_client.embed(&result.extracted_text).await?);
+
+        if enable_verbose_logging {
+            tracing::info!("Embedding generated with {}", app_config.embedding_model);
+        }
+    }
+
+    result.timings = Some(Timings {
+        validation_ms,
+        upload_ms,
+        ocr_ms,
+        total_ms: validation_ms + start_time.elapsed().as_millis() as u64,
+    });
+
+    Ok(result)
+}
+
+/// Validate the input file format and size against the configuration
+pub(crate) fn validate_input_file(
+    input_file_path: &str,
+    app_config: &Config,
+    mime_type: Option<&str>,
+) -> Result<FileUpload> {
+    let mut rules = app_config.validation_rules();
+    rules.mime_type = mime_type.map(str::to_string);
+
+    // Validate file exists, is a supported format and within the size limit
+    let file_upload = FileUpload::new_with_rules(input_file_path, &rules)?;
+
+    tracing::debug!(
+        "File validation passed: {} ({} bytes, {})",
+        file_upload.get_filename(),
+        file_upload.file_size,
+        file_upload.mime_type
+    );
+
+    Ok(file_upload)
+}
+
+/// Fail when the extracted text is shorter than the required minimum
+fn check_min_chars(result: &OCRResult, min_chars: usize) -> Result<()> {
+    let char_count = result
+        .extracted_text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count();
+
+    if char_count < min_chars {
+        return Err(Error::InsufficientText(format!(
+            "Extracted {} characters from {}, expected at least {}",
+            char_count, result.file_name, min_chars
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! Markdown to plain text for `--format plain`
+//!
+//! The OCR API returns markdown. For search indexes and other consumers that
+//! want the words only, the markdown is parsed and written back without its
+//! syntax:
+//!
+//! - headings, paragraphs, and block quotes become paragraphs
+//! - emphasis, inline code, and escapes keep their text
+//! - list items keep a `- ` or `1. ` marker, indented by nesting level
+//! - table rows become lines with the cells separated by tabs
+//! - links keep their text (the URL when there is none)
+//! - images keep their alt text, unless it is only the file name, as in the
+//!   `![img-0.jpeg](img-0.jpeg)` references of the OCR API
+//! - HTML is dropped, except that `<br>` breaks the line
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Plain text of a markdown document
+pub fn strip_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut writer = PlainWriter::default();
+    for event in Parser::new_ext(markdown, options) {
+        writer.event(event);
+    }
+    writer.finish()
+}
+
+/// Plain text of joined pages, keeping the page separator
+///
+/// Separators other than whitespace (such as `---`) would be read as markdown,
+/// so each page between them is stripped on its own.
+pub fn strip_pages(text: &str, separator: &str) -> String {
+    if separator.trim().is_empty() {
+        return strip_markdown(text);
+    }
+    text.split(separator)
+        .map(strip_markdown)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[derive(Default)]
+struct PlainWriter {
+    out: String,
+    /// Next number of each open list, `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    /// Cells written in the current table row
+    cells: usize,
+    /// Open images with their URL and where their alt text starts
+    images: Vec<(String, usize)>,
+    /// Open links with their URL and where their text starts
+    links: Vec<(String, usize)>,
+}
+
+impl PlainWriter {
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::Code(text) => self.out.push_str(&text),
+            Event::InlineMath(text) | Event::DisplayMath(text) => self.out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => self.out.push('\n'),
+            Event::InlineHtml(html) if is_line_break(&html) => self.out.push('\n'),
+            Event::Rule => self.block(),
+            Event::TaskListMarker(done) => self.out.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(label) => self.out.push_str(&format!("[{}]", label)),
+            Event::Html(_) | Event::InlineHtml(_) => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph if !self.lists.is_empty() => self.line(),
+            Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::CodeBlock(_)
+            | Tag::Table(_)
+            | Tag::FootnoteDefinition(_)
+            | Tag::DefinitionList => self.block(),
+            Tag::List(first) => {
+                if self.lists.is_empty() {
+                    self.block();
+                }
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.line();
+                let depth = self.lists.len().saturating_sub(1);
+                self.out.push_str(&"  ".repeat(depth));
+                match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        self.out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+            }
+            Tag::TableHead | Tag::TableRow | Tag::DefinitionListTitle => {
+                self.line();
+                self.cells = 0;
+            }
+            Tag::TableCell => {
+                if self.cells > 0 {
+                    self.out.push('\t');
+                }
+                self.cells += 1;
+            }
+            Tag::DefinitionListDefinition => {
+                self.line();
+                self.out.push_str("  ");
+            }
+            Tag::Link { dest_url, .. } => self.links.push((dest_url.to_string(), self.out.len())),
+            Tag::Image { dest_url, .. } => self.images.push((dest_url.to_string(), self.out.len())),
+            Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Superscript
+            | Tag::Subscript
+            | Tag::HtmlBlock
+            | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::Link => {
+                if let Some((url, start)) = self.links.pop() {
+                    if self.out.len() == start {
+                        self.out.push_str(&url);
+                    }
+                }
+            }
+            TagEnd::Image => {
+                if let Some((url, start)) = self.images.pop() {
+                    let alt = self.out[start..].trim();
+                    if alt == url || alt == file_name(&url) {
+                        self.out.truncate(start);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Start a new paragraph
+    fn block(&mut self) {
+        self.trim_end();
+        if !self.out.is_empty() {
+            self.out.push_str("\n\n");
+        }
+    }
+
+    /// Start a new line, unless one has just been started
+    fn line(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn trim_end(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t', '\n']).len();
+        self.out.truncate(trimmed);
+    }
+
+    fn finish(self) -> String {
+        let lines: Vec<&str> = self.out.lines().map(str::trim_end).collect();
+        let mut text = String::with_capacity(self.out.len());
+        let mut blank = 0;
+        for line in lines {
+            if line.is_empty() {
+                blank += 1;
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str(if blank > 0 { "\n\n" } else { "\n" });
+            }
+            text.push_str(line);
+            blank = 0;
+        }
+        text
+    }
+}
+
+/// Whether inline HTML is a `<br>` tag
+fn is_line_break(html: &str) -> bool {
+    let tag = html
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim_end_matches('/')
+        .trim();
+    tag.eq_ignore_ascii_case("br")
+}
+
+/// Last path segment of a URL
+fn file_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_markdown() {
+        let markdown = "# Invoice 42\n\n\
+            Dear **Ms. Smith**, see [our terms](https://example.com/terms) and \
+            <https://example.com>.<br>Thank you!\n\n\
+            ![img-0.jpeg](img-0.jpeg)\n\n\
+            ![Company logo](logo.png)\n\n\
+            | Item | Price |\n|------|------:|\n| Paper | 5.00 |\n| Ink | 12.50 |\n\n\
+            1. First\n2. Second\n   - nested `code`\n\n\
+            > Quoted &amp; escaped \\*text\\*";
+
+        assert_eq!(
+            strip_markdown(markdown),
+            "Invoice 42\n\n\
+             Dear Ms. Smith, see our terms and https://example.com.\nThank you!\n\n\
+             Company logo\n\n\
+             Item\tPrice\nPaper\t5.00\nInk\t12.50\n\n\
+             1. First\n2. Second\n  - nested code\n\n\
+             Quoted & escaped *text*"
+        );
+    }
+
+    #[test]
+    fn test_strip_pages_keeps_markdown_separators() {
+        let text = "# One\n\n---\n\n## Two";
+        assert_eq!(strip_pages(text, "\n\n---\n\n"), "One\n\n---\n\nTwo");
+        assert_eq!(strip_pages("*a*\n\n*b*", "\n\n"), "a\n\nb");
+    }
+}
@@ -0,0 +1,121 @@
+//! Machine-readable progress events
+//!
+//! When enabled with `--progress-json`, each pipeline step writes one JSON
+//! object per line (NDJSON) to stderr, e.g.
+//! `{"event":"upload_started","file":"scan.pdf","bytes":1024,"timestamp":"..."}`.
+//! Wrappers can render progress from these events without parsing human logs.
+
+use crate::summary::RunSummary;
+use chrono::Utc;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A single progress event
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    /// Processing of a file started
+    FileStarted { file: &'a str },
+    /// A provider started processing the file
+    ProviderStarted { file: &'a str, provider: &'a str },
+    /// A provider failed; the next provider in the chain may be tried
+    ProviderFailed {
+        file: &'a str,
+        provider: &'a str,
+        error: String,
+    },
+    /// Upload to the provider started
+    UploadStarted { file: &'a str, bytes: u64 },
+    /// Upload to the provider finished
+    UploadCompleted { file: &'a str, file_id: &'a str },
+    /// OCR request started
+    OcrStarted { file: &'a str, provider: &'a str },
+    /// OCR request finished
+    OcrCompleted { file: &'a str, pages: usize },
+    /// A post-processing step (summary, translation, entities) started
+    PostprocessingStarted { file: &'a str, step: &'a str },
+    /// Processing of a file finished successfully
+    FileCompleted { file: &'a str, chars: usize },
+    /// Processing of a file failed
+    FileFailed { file: &'a str, error: String },
+    /// The run finished; replaces the summary line on stderr
+    RunCompleted(&'a RunSummary),
+}
+
+/// Writes progress events to stderr when enabled
+#[derive(Debug, Default)]
+pub struct ProgressReporter {
+    enabled: AtomicBool,
+}
+
+impl ProgressReporter {
+    /// Create a disabled reporter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable event output
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether events are written
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Write an event as a single JSON line to stderr
+    pub fn emit(&self, event: ProgressEvent<'_>) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        if let Some(line) = Self::to_line(&event) {
+            // Lock stderr so concurrent events never interleave within a line
+            let mut stderr = std::io::stderr().lock();
+            let _ = writeln!(stderr, "{}", line);
+        }
+    }
+
+    /// Serialize an event with its timestamp
+    pub fn to_line(event: &ProgressEvent<'_>) -> Option<String> {
+        let mut value = serde_json::to_value(event).ok()?;
+        value["timestamp"] = serde_json::json!(Utc::now().to_rfc3339());
+        serde_json::to_string(&value).ok()
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Global progress reporter instance
+    pub static ref GLOBAL_PROGRESS: ProgressReporter = ProgressReporter::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serialization() {
+        let line = ProgressReporter::to_line(&ProgressEvent::UploadStarted {
+            file: "scan.pdf",
+            bytes: 1024,
+        })
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(value["event"], "upload_started");
+        assert_eq!(value["file"], "scan.pdf");
+        assert_eq!(value["bytes"], 1024);
+        assert!(value["timestamp"].is_string());
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_reporter_disabled_by_default() {
+        let reporter = ProgressReporter::new();
+        assert!(!reporter.is_enabled());
+        reporter.set_enabled(true);
+        assert!(reporter.is_enabled());
+    }
+}
@@ -0,0 +1,1376 @@
+//! OCR providers and fallback chain
+//!
+//! A provider turns a validated input file into an `OCRResponse`. Providers are
+//! tried in the configured order (`providers = ["mistral", "tesseract"]`); when
+//! one fails the next is tried, and the provider that produced the text is
+//! recorded in the result.
+//!
+//! - `mistral`: Mistral AI Files + OCR APIs
+//! - `tesseract`: local `tesseract` command (images; PDFs with `pdf-render`)
+//! - `mock`: canned text, with optional simulated latency and failures, for
+//!   offline testing of pipelines
+//! - `local-text`: text layer of digital-born PDFs with `pdftotext`, no API
+//!   calls; scanned PDFs fall back to the next provider
+//!
+//! When `local-text` is followed by another provider, mixed PDFs are split:
+//! the pages without a text layer are copied into a PDF of their own (see
+//! [`crate::split`]) for the rest of the chain, and the OCRed pages are merged
+//! with the text layer in page order.
+//!
+//! With `[split] chunk_pages`, PDFs longer than that are split into chunks
+//! which a provider processes concurrently, at most `max_concurrent_requests`
+//! at a time; the pages are reassembled in page order whichever chunk finishes
+//! first.
+
+use crate::api::files::FilesClient;
+use crate::api::ocr::{
+    AnnotationFormat, Dimensions, DocumentChunk, OCRClient, OCROptions, OCRResponse, Page,
+    ResponseFormat, UsageInfo,
+};
+use crate::api::{Deadline, MistralClient};
+use crate::cache::{CacheManager, OCRCacheKey};
+use crate::config::Config;
+use crate::credentials::APICredentials;
+use crate::ensemble::merge_responses;
+use crate::error::{Error, FieldErrors, Result};
+use crate::file::FileUpload;
+use crate::memory::{decoded_image_bytes, MemoryBudget};
+use crate::metrics::MetricsCollector;
+use crate::pipeline::RunContext;
+use crate::progress::{ProgressEvent, GLOBAL_PROGRESS};
+use crate::quota::{check_quota, record_pages};
+use crate::split::{self, PdfPages};
+use crate::text_layer::{self, TextLayer};
+use crate::trace_context;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Available OCR providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Mistral AI OCR API
+    Mistral,
+    /// Local Tesseract installation
+    Tesseract,
+    /// Canned text, no OCR
+    Mock,
+    /// Text layer of digital-born PDFs, no OCR
+    #[serde(rename = "local-text")]
+    LocalText,
+}
+
+impl ProviderKind {
+    /// Provider name as used in configuration and output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mistral => "mistral",
+            Self::Tesseract => "tesseract",
+            Self::Mock => "mock",
+            Self::LocalText => "local-text",
+        }
+    }
+
+    /// Model the provider reads documents with under a configuration; for
+    /// Tesseract, its languages
+    pub fn model(&self, config: &Config) -> String {
+        match self {
+            Self::Mistral => config.ocr_model.clone(),
+            Self::Tesseract => format!("tesseract:{}", config.tesseract.languages),
+            Self::Mock => "mock".to_string(),
+            Self::LocalText => config.text_layer.command.clone(),
+        }
+    }
+
+    /// Parse a comma-separated provider list (e.g. "mistral,tesseract")
+    pub fn parse_list(list: &str) -> Result<Vec<Self>> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mistral" => Ok(Self::Mistral),
+            "tesseract" => Ok(Self::Tesseract),
+            "mock" => Ok(Self::Mock),
+            "local-text" => Ok(Self::LocalText),
+            other => Err(Error::Config(format!(
+                "Unknown OCR provider: {}. Supported: mistral, tesseract, mock, local-text",
+                other
+            ))),
+        }
+    }
+}
+
+/// Settings for the local Tesseract provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TesseractConfig {
+    /// Tesseract executable
+    #[serde(default = "default_tesseract_command")]
+    pub command: String,
+
+    /// Tesseract language codes (e.g. "eng+deu")
+    #[serde(default = "default_tesseract_languages")]
+    pub languages: String,
+}
+
+fn default_tesseract_command() -> String {
+    "tesseract".to_string()
+}
+
+fn default_tesseract_languages() -> String {
+    "eng".to_string()
+}
+
+impl Default for TesseractConfig {
+    fn default() -> Self {
+        Self {
+            command: default_tesseract_command(),
+            languages: default_tesseract_languages(),
+        }
+    }
+}
+
+/// Settings for the mock provider
+///
+/// Besides canned text, the mock can simulate slow and failing OCR so
+/// pipelines can rehearse timeouts, retries, and fallbacks offline:
+///
+/// ```toml
+/// [mock]
+/// pages = ["Invoice 42", "Terms and conditions"]
+/// latency_ms = 2000
+/// latency_jitter_ms = 500
+/// error_rate = 0.2
+/// error_status = 503
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// Text returned for every document; form feeds separate pages
+    #[serde(default = "default_mock_text")]
+    pub text: String,
+
+    /// Text of each page; takes precedence over `text` when not empty
+    #[serde(default)]
+    pub pages: Vec<String>,
+
+    /// Time each document takes
+    #[serde(default)]
+    pub latency_ms: u64,
+
+    /// Random extra time of up to this many milliseconds
+    #[serde(default)]
+    pub latency_jitter_ms: u64,
+
+    /// Fraction of documents that fail, from 0.0 to 1.0
+    #[serde(default)]
+    pub error_rate: f64,
+
+    /// HTTP status of simulated failures (4xx or 5xx)
+    #[serde(default = "default_mock_error_status")]
+    pub error_status: u16,
+}
+
+fn default_mock_text() -> String {
+    "Mock OCR text".to_string()
+}
+
+fn default_mock_error_status() -> u16 {
+    503
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            text: default_mock_text(),
+            pages: Vec::new(),
+            latency_ms: 0,
+            latency_jitter_ms: 0,
+            error_rate: 0.0,
+            error_status: default_mock_error_status(),
+        }
+    }
+}
+
+impl MockConfig {
+    /// Validate the simulation settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            errors.add("error_rate", "Mock error rate must be between 0.0 and 1.0");
+        }
+
+        if !(400..=599).contains(&self.error_status) {
+            errors.add(
+                "error_status",
+                "Mock error status must be an HTTP error status (400-599)",
+            );
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Canned text with form feeds between pages
+    fn canned_text(&self) -> String {
+        if self.pages.is_empty() {
+            self.text.clone()
+        } else {
+            self.pages.join("\u{c}")
+        }
+    }
+
+    /// Simulated processing time of one document
+    fn latency(&self) -> Duration {
+        let jitter = if self.latency_jitter_ms > 0 {
+            rand::thread_rng().gen_range(0..=self.latency_jitter_ms)
+        } else {
+            0
+        };
+        Duration::from_millis(self.latency_ms.saturating_add(jitter))
+    }
+
+    /// Whether this document fails
+    fn fails(&self) -> bool {
+        self.error_rate > 0.0 && rand::random::<f64>() < self.error_rate
+    }
+}
+
+/// Per-request options passed to providers
+#[derive(Debug, Clone, Default)]
+pub struct ProviderRequest {
+    /// Structured document annotation to request (Mistral only)
+    pub annotation_format: Option<AnnotationFormat>,
+
+    /// Free-form guidance for the model (Mistral only)
+    pub instructions: Option<String>,
+
+    /// Page text format to request (Mistral only; local providers return text)
+    pub response_format: Option<ResponseFormat>,
+
+    /// Collector receiving the API metrics of the request
+    pub metrics: MetricsCollector,
+
+    /// In-memory caches of the run, shared by its requests
+    pub cache: CacheManager,
+
+    /// Temporary directory, memory budget, and counters of the run
+    pub run: RunContext,
+
+    /// The document, already uploaded for the Mistral provider
+    pub upload: Option<MistralUpload>,
+}
+
+impl ProviderRequest {
+    /// Key of the OCR response for an uploaded file, covering every option
+    /// that changes the output
+    pub fn ocr_cache_key(&self, file_id: &str, model: &str) -> OCRCacheKey {
+        OCRCacheKey {
+            file_id: file_id.to_string(),
+            model: model.to_string(),
+            instructions: self.instructions.clone(),
+            response_format: self.response_format,
+            annotation_format: self
+                .annotation_format
+                .as_ref()
+                .and_then(|format| serde_json::to_string(format).ok()),
+        }
+    }
+}
+
+/// A document uploaded to the Mistral Files API ahead of its OCR request
+#[derive(Debug, Clone)]
+pub struct MistralUpload {
+    /// Path of the uploaded document
+    pub file_path: String,
+
+    /// Mistral file ID
+    pub file_id: String,
+
+    /// Time spent uploading the file
+    pub upload_ms: u64,
+}
+
+/// Output of a successful provider run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderOutput {
+    /// Provider that produced the response
+    pub provider: ProviderKind,
+
+    /// Remote file ID, or a local identifier for local providers
+    pub file_id: String,
+
+    /// OCR response with one entry per page
+    pub response: OCRResponse,
+
+    /// Time spent uploading the file (zero for local providers)
+    pub upload_ms: u64,
+
+    /// Time spent on the OCR request
+    pub ocr_ms: u64,
+}
+
+/// Run the providers in order and return the first successful output
+pub async fn process_with_fallback(
+    providers: &[ProviderKind],
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    let mut last_error = None;
+    let mut mixed: Option<MixedPdf> = None;
+
+    for (position, provider) in providers.iter().enumerate() {
+        GLOBAL_PROGRESS.emit(ProgressEvent::ProviderStarted {
+            file: &file_upload.file_path,
+            provider: provider.name(),
+        });
+
+        let result = if *provider == ProviderKind::LocalText
+            && position + 1 < providers.len()
+            && mixed.is_none()
+        {
+            match split_text_layer(file_upload, config, request).await {
+                Ok(TextLayerSplit::Complete(output)) => Ok(output),
+                Ok(TextLayerSplit::Mixed(pdf)) => {
+                    tracing::info!(
+                        "{} of {} pages of {} have no text layer; trying {}",
+                        pdf.pages.pages().len(),
+                        pdf.layer.pages().len(),
+                        file_upload.get_filename(),
+                        providers[position + 1]
+                    );
+                    mixed = Some(pdf);
+                    continue;
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            let document = mixed.as_ref().map_or(file_upload, |pdf| &pdf.upload);
+            process_in_chunks(*provider, document, config, request)
+                .await
+                .map(|output| match mixed {
+                    Some(ref pdf) => pdf.merge(output, file_upload.file_size),
+                    None => output,
+                })
+        };
+
+        match result {
+            Ok(output) => {
+                if position > 0 {
+                    tracing::info!("Text extracted with fallback provider {}", provider);
+                }
+                return Ok(output);
+            }
+            Err(e) => {
+                GLOBAL_PROGRESS.emit(ProgressEvent::ProviderFailed {
+                    file: &file_upload.file_path,
+                    provider: provider.name(),
+                    error: e.user_message(),
+                });
+                if position + 1 < providers.len() {
+                    // Scanned documents are expected to fall through local-text
+                    if *provider == ProviderKind::LocalText {
+                        tracing::info!("{}; trying {}", e.user_message(), providers[position + 1]);
+                    } else {
+                        tracing::warn!(
+                            "Provider {} failed: {}; trying {}",
+                            provider,
+                            e.user_message(),
+                            providers[position + 1]
+                        );
+                    }
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| Error::Config("No OCR providers configured".to_string())))
+}
+
+/// Run every provider and merge the successful outputs page by page
+pub async fn process_ensemble(
+    providers: &[ProviderKind],
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    let mut outputs = Vec::new();
+    let mut last_error = None;
+
+    for provider in providers {
+        GLOBAL_PROGRESS.emit(ProgressEvent::ProviderStarted {
+            file: &file_upload.file_path,
+            provider: provider.name(),
+        });
+
+        match process(*provider, file_upload, config, request).await {
+            Ok(output) => outputs.push(output),
+            Err(e) => {
+                GLOBAL_PROGRESS.emit(ProgressEvent::ProviderFailed {
+                    file: &file_upload.file_path,
+                    provider: provider.name(),
+                    error: e.user_message(),
+                });
+                tracing::warn!(
+                    "Provider {} failed: {}; merging the other outputs",
+                    provider,
+                    e.user_message()
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+
+    if outputs.len() < 2 {
+        return outputs.pop().ok_or_else(|| {
+            last_error.unwrap_or_else(|| Error::Config("No OCR providers configured".to_string()))
+        });
+    }
+
+    let responses: Vec<(ProviderKind, &OCRResponse)> = outputs
+        .iter()
+        .map(|output| (output.provider, &output.response))
+        .collect();
+    let merged = merge_responses(&responses);
+
+    // Attribute the result to the provider that contributed the most pages
+    let provider = merged.primary_source().unwrap_or(outputs[0].provider);
+    let file_id = outputs
+        .iter()
+        .find(|output| output.provider == provider)
+        .map_or_else(
+            || outputs[0].file_id.clone(),
+            |output| output.file_id.clone(),
+        );
+    tracing::info!(
+        "Merged {} pages from {} providers",
+        merged.sources.len(),
+        outputs.len()
+    );
+
+    Ok(ProviderOutput {
+        provider,
+        file_id,
+        response: merged.response,
+        upload_ms: outputs.iter().map(|output| output.upload_ms).sum(),
+        ocr_ms: outputs.iter().map(|output| output.ocr_ms).sum(),
+    })
+}
+
+/// Run a single provider
+pub async fn process(
+    provider: ProviderKind,
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    tracing::debug!(
+        "Processing {} with provider {}",
+        file_upload.get_filename(),
+        provider
+    );
+
+    if provider != ProviderKind::Mistral {
+        if request.instructions.is_some() {
+            tracing::warn!("The {} provider ignores --instructions", provider);
+        }
+        // Local providers only return plain text
+        if request.annotation_format.is_some() {
+            return Err(Error::validation(format!(
+                "The {} provider cannot produce a structured document annotation (--extract)",
+                provider
+            )));
+        }
+        if request.response_format == Some(ResponseFormat::Markdown) {
+            return Err(Error::validation(format!(
+                "The {} provider returns plain text and cannot produce --response-format markdown",
+                provider
+            )));
+        }
+    }
+
+    let output = match provider {
+        ProviderKind::Mistral => process_mistral(file_upload, config, request).await?,
+        ProviderKind::Tesseract => {
+            GLOBAL_PROGRESS.emit(ProgressEvent::OcrStarted {
+                file: &file_upload.file_path,
+                provider: provider.name(),
+            });
+            process_tesseract(file_upload, config, request).await?
+        }
+        ProviderKind::Mock => {
+            GLOBAL_PROGRESS.emit(ProgressEvent::OcrStarted {
+                file: &file_upload.file_path,
+                provider: provider.name(),
+            });
+            process_mock(file_upload, config).await?
+        }
+        ProviderKind::LocalText => process_local_text(file_upload, config).await?,
+    };
+
+    GLOBAL_PROGRESS.emit(ProgressEvent::OcrCompleted {
+        file: &file_upload.file_path,
+        pages: output.response.pages.len(),
+    });
+
+    Ok(output)
+}
+
+/// Upload the file and run the Mistral OCR API
+async fn process_mistral(
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    let state_file = config.state_file_path();
+    check_quota(&config.quota, state_file.as_deref())?;
+
+    let api_credentials = APICredentials::from_config(config)?;
+    // One deadline for upload and OCR, including retries and backoff
+    let deadline = Deadline::after(Duration::from_secs(config.timeout_seconds));
+    let mistral_client = MistralClient::from_config(api_credentials, config)?
+        .with_deadline(deadline)
+        .with_metrics(request.metrics.clone())
+        .with_memory(request.run.memory.clone());
+
+    let files_client = FilesClient::new(mistral_client.clone());
+    // Chunks and page subsets are other files than the one uploaded ahead
+    let upload = match request.upload {
+        Some(ref upload) if upload.file_path == file_upload.file_path => upload.clone(),
+        _ => upload_to_mistral(&files_client, file_upload, deadline).await?,
+    };
+
+    let cache_key = request.ocr_cache_key(&upload.file_id, &config.ocr_model);
+    let ocr_start = Instant::now();
+    if let Some(response) = request.cache.ocr_result_cache.get(&cache_key).await {
+        tracing::debug!("OCR response of {} taken from the cache", upload.file_id);
+        return Ok(ProviderOutput {
+            provider: ProviderKind::Mistral,
+            file_id: upload.file_id,
+            response,
+            upload_ms: upload.upload_ms,
+            ocr_ms: elapsed_ms(ocr_start),
+        });
+    }
+
+    GLOBAL_PROGRESS.emit(ProgressEvent::OcrStarted {
+        file: &file_upload.file_path,
+        provider: ProviderKind::Mistral.name(),
+    });
+
+    let options = OCROptions {
+        annotation_format: request.annotation_format.clone(),
+        instructions: request.instructions.clone(),
+        response_format: request.response_format,
+        model: Some(config.ocr_model.clone()),
+    };
+    // Gateways that separate storage and inference need a URL instead of the file ID
+    let document = if config.use_signed_url {
+        let url = deadline
+            .run(files_client.get_signed_url(&upload.file_id, config.signed_url_expiry_hours))
+            .await?;
+        if file_upload.mime_type.starts_with("image/") {
+            DocumentChunk::image_url(url)
+        } else {
+            DocumentChunk::document_url(url)
+        }
+    } else {
+        DocumentChunk::file(upload.file_id.clone())
+    };
+
+    let response = deadline
+        .run(OCRClient::new(mistral_client).process_document(document, options))
+        .await?;
+
+    if let Some(path) = state_file {
+        let pages = response.usage_info.pages_processed.max(0) as u64;
+        if let Err(e) = record_pages(&path, pages) {
+            // Counting must not fail the document; only quota users need to know
+            if config.quota.monthly_pages.is_some() {
+                tracing::warn!("Failed to update {}: {}", path.display(), e.user_message());
+            } else {
+                tracing::debug!("Failed to update {}: {}", path.display(), e.user_message());
+            }
+        }
+    }
+    if let Err(e) = request
+        .cache
+        .ocr_result_cache
+        .put(cache_key, response.clone())
+        .await
+    {
+        tracing::debug!("Failed to cache the OCR response: {}", e.user_message());
+    }
+
+    Ok(ProviderOutput {
+        provider: ProviderKind::Mistral,
+        file_id: upload.file_id,
+        response,
+        upload_ms: upload.upload_ms,
+        ocr_ms: elapsed_ms(ocr_start),
+    })
+}
+
+/// Upload a document to the Mistral Files API ahead of its OCR request, so a
+/// batch can upload the next documents while earlier ones are processed
+pub async fn upload_mistral(
+    file_upload: &FileUpload,
+    config: &Config,
+    metrics: MetricsCollector,
+    memory: MemoryBudget,
+) -> Result<MistralUpload> {
+    let api_credentials = APICredentials::from_config(config)?;
+    let deadline = Deadline::after(Duration::from_secs(config.timeout_seconds));
+    let mistral_client = MistralClient::from_config(api_credentials, config)?
+        .with_deadline(deadline)
+        .with_metrics(metrics)
+        .with_memory(memory);
+    upload_to_mistral(&FilesClient::new(mistral_client), file_upload, deadline).await
+}
+
+/// Upload a document and check that it arrived intact
+async fn upload_to_mistral(
+    files_client: &FilesClient,
+    file_upload: &FileUpload,
+    deadline: Deadline,
+) -> Result<MistralUpload> {
+    GLOBAL_PROGRESS.emit(ProgressEvent::UploadStarted {
+        file: &file_upload.file_path,
+        bytes: file_upload.file_size,
+    });
+    let upload_start = Instant::now();
+    let (upload_response, digest) = deadline.run(files_client.upload_file(file_upload)).await?;
+    tracing::debug!(
+        "Sent {} bytes of {} (SHA-256 {})",
+        digest.bytes,
+        file_upload.get_filename(),
+        digest.sha256
+    );
+    // Catch truncated or failed uploads before they turn into a cryptic OCR error
+    deadline
+        .run(files_client.verify_upload(&upload_response.id, digest.bytes))
+        .await?;
+    tracing::info!("File uploaded successfully: {}", upload_response.id);
+    GLOBAL_PROGRESS.emit(ProgressEvent::UploadCompleted {
+        file: &file_upload.file_path,
+        file_id: &upload_response.id,
+    });
+
+    Ok(MistralUpload {
+        file_path: file_upload.file_path.clone(),
+        file_id: upload_response.id,
+        upload_ms: elapsed_ms(upload_start),
+    })
+}
+
+/// Return the canned text after the simulated latency, or a simulated failure
+async fn process_mock(file_upload: &FileUpload, config: &Config) -> Result<ProviderOutput> {
+    let start_time = Instant::now();
+    let mock = &config.mock;
+
+    // Latency counts against the document timeout like a real API call
+    let deadline = Deadline::after(Duration::from_secs(config.timeout_seconds));
+    deadline
+        .run(async {
+            tokio::time::sleep(mock.latency()).await;
+            Ok(())
+        })
+        .await?;
+
+    if mock.fails() {
+        return Err(Error::from_http_status(
+            mock.error_status,
+            "Simulated mock provider failure".to_string(),
+        ));
+    }
+
+    Ok(ProviderOutput {
+        provider: ProviderKind::Mock,
+        file_id: "mock".to_string(),
+        response: local_response("mock", &mock.canned_text(), file_upload.file_size),
+        upload_ms: 0,
+        ocr_ms: elapsed_ms(start_time),
+    })
+}
+
+/// Run the local tesseract command on an image, or on each page of a PDF
+async fn process_tesseract(
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    let start_time = Instant::now();
+    let text = if file_upload.mime_type == "application/pdf" {
+        tesseract_pdf(file_upload, config, &request.run).await?
+    } else {
+        let image = Path::new(&file_upload.file_path);
+        let _reservation = request
+            .run
+            .memory
+            .reserve(
+                config.memory_budget_mb,
+                decoded_image_bytes(image, config.render.dpi),
+                &file_upload.get_filename(),
+            )
+            .await;
+        run_tesseract(image, &config.tesseract).await?
+    };
+
+    Ok(ProviderOutput {
+        provider: ProviderKind::Tesseract,
+        file_id: "local".to_string(),
+        response: local_response("tesseract", &text, file_upload.file_size),
+        upload_ms: 0,
+        ocr_ms: elapsed_ms(start_time),
+    })
+}
+
+/// Use the text layer of a digital-born PDF instead of OCR
+///
+/// Fails when a page has too little text, so the document goes to OCR.
+async fn process_local_text(file_upload: &FileUpload, config: &Config) -> Result<ProviderOutput> {
+    let start_time = Instant::now();
+    let layer = read_text_layer(file_upload, config).await?;
+    let min_chars = config.text_layer.min_chars_per_page;
+    if !layer.is_complete(min_chars) {
+        return Err(no_text_layer(file_upload, &layer, min_chars));
+    }
+    Ok(text_layer_output(file_upload, &layer, start_time))
+}
+
+/// Text layer of a PDF, or the pages lacking one copied out for OCR
+enum TextLayerSplit {
+    /// Every page has text
+    Complete(ProviderOutput),
+
+    /// Some pages need OCR
+    Mixed(MixedPdf),
+}
+
+/// A PDF with text on some pages, and a PDF of the other pages
+#[derive(Debug)]
+struct MixedPdf {
+    layer: TextLayer,
+    pages: PdfPages,
+    upload: FileUpload,
+}
+
+impl MixedPdf {
+    /// Put the pages a provider read from the split PDF between the pages of
+    /// the text layer
+    fn merge(&self, output: ProviderOutput, file_size: u64) -> ProviderOutput {
+        let ProviderOutput {
+            provider,
+            file_id,
+            response,
+            upload_ms,
+            ocr_ms,
+        } = output;
+
+        let mut ocr_pages = renumber_pages(provider, &response, self.pages.pages()).into_iter();
+        let pages = self
+            .layer
+            .pages()
+            .iter()
+            .enumerate()
+            .map(
+                |(index, text)| match self.pages.pages().binary_search(&index) {
+                    Ok(_) => ocr_pages.next().unwrap_or_else(|| text_page(index, "")),
+                    Err(_) => text_page(index, text),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        ProviderOutput {
+            provider,
+            file_id,
+            response: OCRResponse {
+                usage_info: UsageInfo {
+                    pages_processed: pages.len() as i32,
+                    doc_size_bytes: file_size.min(i32::MAX as u64) as i32,
+                },
+                pages,
+                model: response.model,
+                document_annotation: response.document_annotation,
+            },
+            upload_ms,
+            ocr_ms,
+        }
+    }
+}
+
+/// Read the text layer of a PDF and copy the pages without text into a PDF of
+/// their own
+///
+/// Fails when no page has text, so the whole document goes to OCR.
+async fn split_text_layer(
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<TextLayerSplit> {
+    let start_time = Instant::now();
+    let layer = read_text_layer(file_upload, config).await?;
+    let min_chars = config.text_layer.min_chars_per_page;
+    if layer.is_complete(min_chars) {
+        return Ok(TextLayerSplit::Complete(text_layer_output(
+            file_upload,
+            &layer,
+            start_time,
+        )));
+    }
+    let scanned = layer.pages_without_text(min_chars);
+    if scanned.len() == layer.pages().len() {
+        return Err(no_text_layer(file_upload, &layer, min_chars));
+    }
+
+    let pages = split::extract_pages(
+        Path::new(&file_upload.file_path),
+        &scanned,
+        &config.split,
+        &request.run.temp,
+    )
+    .await?;
+    let upload = pages_upload(file_upload, &pages)?;
+    Ok(TextLayerSplit::Mixed(MixedPdf {
+        layer,
+        pages,
+        upload,
+    }))
+}
+
+/// Run a provider on chunks of a long PDF concurrently, or on the whole
+/// document
+async fn process_in_chunks(
+    provider: ProviderKind,
+    file_upload: &FileUpload,
+    config: &Config,
+    request: &ProviderRequest,
+) -> Result<ProviderOutput> {
+    let chunk_pages = match config.split.chunk_pages {
+        Some(chunk_pages)
+            if provider != ProviderKind::LocalText
+                && file_upload.mime_type == "application/pdf" =>
+        {
+            chunk_pages
+        }
+        _ => return process(provider, file_upload, config, request).await,
+    };
+    let pdf = Path::new(&file_upload.file_path);
+    let page_count = match split::page_count(pdf, &config.split).await {
+        Ok(page_count) => page_count,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to count the pages of {}: {}; processing it whole",
+                file_upload.get_filename(),
+                e.user_message()
+            );
+            return process(provider, file_upload, config, request).await;
+        }
+    };
+    if page_count <= chunk_pages {
+        return process(provider, file_upload, config, request).await;
+    }
+
+    let chunks = split::chunks(page_count, chunk_pages);
+    tracing::info!(
+        "Processing {} pages of {} in {} chunks",
+        page_count,
+        file_upload.get_filename(),
+        chunks.len()
+    );
+
+    // Chunks wait for each other like the API calls they make, so a long scan
+    // does not run ahead of the rest of a batch
+    let limit = Arc::new(Semaphore::new(config.max_concurrent_requests.max(1)));
+    let mut tasks = JoinSet::new();
+    for (position, pages) in chunks.into_iter().enumerate() {
+        let limit = limit.clone();
+        let file_upload = file_upload.clone();
+        let config = config.clone();
+        let request = request.clone();
+        tasks.spawn(trace_context::propagate(async move {
+            let _permit = limit
+                .acquire_owned()
+                .await
+                .map_err(|e| Error::internal_from("Chunk limit closed", e))?;
+            let chunk = split::extract_pages(
+                Path::new(&file_upload.file_path),
+                &pages,
+                &config.split,
+                &request.run.temp,
+            )
+            .await?;
+            let upload = pages_upload(&file_upload, &chunk)?;
+            let output = process(provider, &upload, &config, &request).await?;
+            Ok::<_, Error>((position, pages, output))
+        }));
+    }
+
+    let mut outputs = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        // Returning drops the other tasks, which aborts them
+        let output = joined.map_err(|e| Error::internal_from("Chunk task failed", e))??;
+        outputs.push(output);
+    }
+    outputs.sort_by_key(|(position, ..)| *position);
+    let chunks = outputs
+        .into_iter()
+        .map(|(_, pages, output)| (pages, output))
+        .collect();
+    Ok(merge_chunks(provider, chunks, file_upload.file_size))
+}
+
+/// Join the outputs of the chunks of a PDF, in chunk order, into one output
+fn merge_chunks(
+    provider: ProviderKind,
+    chunks: Vec<(Vec<usize>, ProviderOutput)>,
+    file_size: u64,
+) -> ProviderOutput {
+    let pages: Vec<Page> = chunks
+        .iter()
+        .flat_map(|(indices, output)| renumber_pages(provider, &output.response, indices))
+        .collect();
+    let file_ids: Vec<&str> = chunks
+        .iter()
+        .map(|(_, output)| output.file_id.as_str())
+        .collect();
+    let file_id = file_ids.join(",");
+    // Only the first chunk's annotation is kept; documents put their key
+    // fields up front
+    let (model, document_annotation) = chunks
+        .first()
+        .map(|(_, output)| {
+            (
+                output.response.model.clone(),
+                output.response.document_annotation.clone(),
+            )
+        })
+        .unwrap_or_default();
+
+    ProviderOutput {
+        provider,
+        file_id,
+        response: OCRResponse {
+            usage_info: UsageInfo {
+                pages_processed: pages.len() as i32,
+                doc_size_bytes: file_size.min(i32::MAX as u64) as i32,
+            },
+            pages,
+            model,
+            document_annotation,
+        },
+        // Chunks run concurrently, so the slowest one took the time
+        upload_ms: chunks
+            .iter()
+            .map(|(_, output)| output.upload_ms)
+            .max()
+            .unwrap_or_default(),
+        ocr_ms: chunks
+            .iter()
+            .map(|(_, output)| output.ocr_ms)
+            .max()
+            .unwrap_or_default(),
+    }
+}
+
+/// Pages a provider read from a PDF of some pages of another, numbered by
+/// their indices in the other PDF
+pub fn renumber_pages(
+    provider: ProviderKind,
+    response: &OCRResponse,
+    indices: &[usize],
+) -> Vec<Page> {
+    if response.pages.len() != indices.len() {
+        tracing::warn!(
+            "The {} provider returned {} pages for {} pages",
+            provider,
+            response.pages.len(),
+            indices.len()
+        );
+    }
+    let mut pages = response.ordered_pages(None).into_iter();
+    indices
+        .iter()
+        .map(|&index| match pages.next() {
+            Some(page) => Page {
+                index: index as i32,
+                ..page.clone()
+            },
+            None => text_page(index, ""),
+        })
+        .collect()
+}
+
+/// Upload of a PDF copied out of the uploaded one
+pub fn pages_upload(file_upload: &FileUpload, pages: &PdfPages) -> Result<FileUpload> {
+    Ok(FileUpload {
+        file_path: pages.path().to_string_lossy().to_string(),
+        file_size: std::fs::metadata(pages.path()).map_err(Error::Io)?.len(),
+        file_id: None,
+        upload_status: None,
+        ..file_upload.clone()
+    })
+}
+
+async fn read_text_layer(file_upload: &FileUpload, config: &Config) -> Result<TextLayer> {
+    if file_upload.mime_type != "application/pdf" {
+        return Err(Error::validation(format!(
+            "{} is not a PDF and has no text layer",
+            file_upload.get_filename()
+        )));
+    }
+    text_layer::extract(Path::new(&file_upload.file_path), &config.text_layer).await
+}
+
+fn no_text_layer(file_upload: &FileUpload, layer: &TextLayer, min_chars: usize) -> Error {
+    let pages: Vec<String> = layer
+        .pages_without_text(min_chars)
+        .iter()
+        .map(|index| (index + 1).to_string())
+        .collect();
+    Error::InsufficientText(format!(
+        "{} has no text layer on pages {}",
+        file_upload.get_filename(),
+        pages.join(", ")
+    ))
+}
+
+fn text_layer_output(
+    file_upload: &FileUpload,
+    layer: &TextLayer,
+    start: Instant,
+) -> ProviderOutput {
+    ProviderOutput {
+        provider: ProviderKind::LocalText,
+        file_id: "local".to_string(),
+        response: local_response("pdftotext", &layer.to_text(), file_upload.file_size),
+        upload_ms: 0,
+        ocr_ms: elapsed_ms(start),
+    }
+}
+
+/// Rasterize a PDF and run tesseract on each page; pages are separated by form feeds
+#[cfg(feature = "pdf-render")]
+async fn tesseract_pdf(
+    file_upload: &FileUpload,
+    config: &Config,
+    run: &RunContext,
+) -> Result<String> {
+    // pdftoppm holds one decoded page at a time
+    let reservation = run
+        .memory
+        .reserve(
+            config.memory_budget_mb,
+            crate::memory::page_bytes(config.render.dpi),
+            &file_upload.get_filename(),
+        )
+        .await;
+    let pages =
+        crate::render::rasterize(Path::new(&file_upload.file_path), &config.render, &run.temp)
+            .await?;
+    drop(reservation);
+    tracing::debug!(
+        "Rendered {} pages of {} for tesseract",
+        pages.paths().len(),
+        file_upload.get_filename()
+    );
+
+    let mut texts = Vec::with_capacity(pages.paths().len());
+    for page in pages.paths() {
+        let _reservation = run
+            .memory
+            .reserve(
+                config.memory_budget_mb,
+                decoded_image_bytes(page, config.render.dpi),
+                &page.display().to_string(),
+            )
+            .await;
+        let text = run_tesseract(page, &config.tesseract).await?;
+        texts.push(text.trim_end_matches(['\u{c}', '\n']).to_string());
+    }
+
+    Ok(texts.join("\u{c}"))
+}
+
+#[cfg(not(feature = "pdf-render"))]
+async fn tesseract_pdf(
+    _file_upload: &FileUpload,
+    _config: &Config,
+    _run: &RunContext,
+) -> Result<String> {
+    Err(Error::validation(
+        "The tesseract provider cannot read PDF files; rebuild with the pdf-render feature to rasterize them"
+            .to_string(),
+    ))
+}
+
+/// Run the tesseract command on one image and return its text
+async fn run_tesseract(image: &Path, tesseract: &TesseractConfig) -> Result<String> {
+    let output = tokio::process::Command::new(&tesseract.command)
+        .arg(image)
+        .arg("stdout")
+        .args(["-l", &tesseract.languages])
+        .output()
+        .await
+        .map_err(|e| {
+            Error::Config(format!(
+                "Failed to run tesseract command '{}': {}",
+                tesseract.command, e
+            ))
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::api(format!(
+            "tesseract exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Milliseconds elapsed since the given instant
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Build an OCR response for locally extracted text; form feeds separate pages
+fn local_response(model: &str, text: &str, file_size: u64) -> OCRResponse {
+    let pages: Vec<Page> = text
+        .trim_end_matches(['\u{c}', '\n'])
+        .split('\u{c}')
+        .enumerate()
+        .map(|(index, markdown)| text_page(index, markdown))
+        .collect();
+
+    OCRResponse {
+        usage_info: UsageInfo {
+            pages_processed: pages.len() as i32,
+            doc_size_bytes: file_size.min(i32::MAX as u64) as i32,
+        },
+        pages,
+        model: model.to_string(),
+        document_annotation: None,
+    }
+}
+
+/// A page of locally extracted text, without images or dimensions
+pub fn text_page(index: usize, text: &str) -> Page {
+    Page {
+        index: index as i32,
+        markdown: text.trim().to_string(),
+        images: Vec::new(),
+        dimensions: Dimensions {
+            dpi: 0,
+            height: 0,
+            width: 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_list() {
+        assert_eq!(
+            ProviderKind::parse_list("mistral, Tesseract,local-text").unwrap(),
+            vec![
+                ProviderKind::Mistral,
+                ProviderKind::Tesseract,
+                ProviderKind::LocalText
+            ]
+        );
+        assert!(ProviderKind::parse_list("mistral,abbyy").is_err());
+    }
+
+    #[test]
+    fn test_local_response_splits_pages_on_form_feed() {
+        let response = local_response("tesseract", "Page one\n\u{c}Page two\n\u{c}", 10);
+
+        assert_eq!(response.pages.len(), 2);
+        assert_eq!(response.pages[1].index, 1);
+        assert_eq!(response.get_extracted_text(), "Page one\n\nPage two");
+        assert_eq!(response.usage_info.pages_processed, 2);
+    }
+
+    #[test]
+    fn test_merge_chunks_in_page_order() {
+        let chunk = |pages: Vec<usize>, text: &str| {
+            let output = ProviderOutput {
+                provider: ProviderKind::Mock,
+                file_id: format!("file-{}", pages[0]),
+                response: local_response("mock", text, 10),
+                upload_ms: pages[0] as u64,
+                ocr_ms: 5,
+            };
+            (pages, output)
+        };
+        let merged = merge_chunks(
+            ProviderKind::Mock,
+            vec![
+                chunk(vec![0, 1], "One\u{c}Two"),
+                chunk(vec![2, 3], "Three\u{c}Four"),
+                // A provider returning too few pages leaves the rest blank
+                chunk(vec![4, 5], "Five"),
+            ],
+            100,
+        );
+
+        let pages: Vec<(i32, &str)> = merged
+            .response
+            .pages
+            .iter()
+            .map(|page| (page.index, page.markdown.as_str()))
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                (0, "One"),
+                (1, "Two"),
+                (2, "Three"),
+                (3, "Four"),
+                (4, "Five"),
+                (5, "")
+            ]
+        );
+        assert_eq!(merged.file_id, "file-0,file-2,file-4");
+        assert_eq!(merged.response.usage_info.pages_processed, 6);
+        assert_eq!(merged.upload_ms, 4);
+    }
+
+    #[test]
+    fn test_mock_config() {
+        let mock = MockConfig {
+            pages: vec!["Page one".to_string(), "Page two".to_string()],
+            ..MockConfig::default()
+        };
+        assert!(mock.validate().is_ok());
+        assert_eq!(mock.canned_text(), "Page one\u{c}Page two");
+        assert!(!mock.fails());
+        assert_eq!(mock.latency(), Duration::ZERO);
+
+        let mock = MockConfig {
+            error_rate: 1.0,
+            latency_ms: 100,
+            latency_jitter_ms: 50,
+            ..MockConfig::default()
+        };
+        assert!(mock.fails());
+        assert!((100..=150).contains(&mock.latency().as_millis()));
+
+        for invalid in [
+            MockConfig {
+                error_rate: 1.5,
+                ..MockConfig::default()
+            },
+            MockConfig {
+                error_status: 200,
+                ..MockConfig::default()
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_ocr_cache_key_covers_the_request_options() {
+        let plain = ProviderRequest::default();
+        let key = plain.ocr_cache_key("file-1", "mistral-ocr-latest");
+        assert_eq!(key.file_id, "file-1");
+        assert_eq!(key.instructions, None);
+
+        let tuned = ProviderRequest {
+            instructions: Some("keep tables".to_string()),
+            response_format: Some(ResponseFormat::Text),
+            ..ProviderRequest::default()
+        };
+        let tuned_key = tuned.ocr_cache_key("file-1", "mistral-ocr-latest");
+        assert_eq!(tuned_key.instructions.as_deref(), Some("keep tables"));
+        assert_eq!(tuned_key.response_format, Some(ResponseFormat::Text));
+        assert_ne!(key, tuned_key);
+    }
+
+    #[tokio::test]
+    async fn test_ocr_response_of_an_upload_is_cached() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/ocr"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "pages": [{
+                    "index": 0,
+                    "markdown": "Invoice text",
+                    "images": [],
+                    "dimensions": {"dpi": 200, "height": 100, "width": 100}
+                }],
+                "model": "mistral-ocr-latest",
+                "usage_info": {"pages_processed": 1, "doc_size_bytes": 8}
+            })))
+            // Once for the plain request, once for the one with instructions
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let image = dir.path().join("scan.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n").unwrap();
+        let file_upload = FileUpload::new(&image).unwrap();
+        let config = Config {
+            api_key: "sk-test123456789".to_string(),
+            api_base_url: server.uri(),
+            allow_insecure_loopback: true,
+            state_file: Some(dir.path().join("state.json")),
+            ..Config::default()
+        };
+        let request = ProviderRequest {
+            upload: Some(MistralUpload {
+                file_path: file_upload.file_path.clone(),
+                file_id: "file-1".to_string(),
+                upload_ms: 0,
+            }),
+            ..ProviderRequest::default()
+        };
+
+        for request in [
+            request.clone(),
+            request.clone(),
+            ProviderRequest {
+                instructions: Some("keep tables".to_string()),
+                ..request
+            },
+        ] {
+            let output = process(ProviderKind::Mistral, &file_upload, &config, &request)
+                .await
+                .unwrap();
+            assert_eq!(output.response.pages[0].markdown, "Invoice text");
+        }
+        server.verify().await;
+    }
+}
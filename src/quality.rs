@@ -0,0 +1,167 @@
+//! OCR quality heuristics
+//!
+//! Estimates how readable extracted text is without a reference transcript. The
+//! score combines the share of tokens that look like real words with the share
+//! of characters that are OCR garbage (stray symbols, replacement characters).
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Punctuation and symbols that regularly appear in documents and markdown
+const DOCUMENT_SYMBOLS: &str = ".,;:!?'\"()[]{}-–—/\\%€$£&#*+=@_|<>§°´`’‘“”„«»";
+
+/// Vowels used to tell words from consonant soup like "rnmvl"
+const VOWELS: &str = "aeiouyäöüàáâèéêìíîòóôùúûæøå";
+
+/// Quality heuristic for extracted text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QualityScore {
+    /// Combined score from 0.0 (garbage) to 1.0 (clean text)
+    pub score: f64,
+
+    /// Share of tokens that look like dictionary words
+    pub word_ratio: f64,
+
+    /// Share of non-whitespace characters that are OCR garbage
+    pub garbage_ratio: f64,
+}
+
+impl QualityScore {
+    /// Score extracted text; returns None when there is no text to judge
+    pub fn from_text(text: &str) -> Option<Self> {
+        let tokens: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty() && !token.chars().all(|c| c.is_ascii_digit()))
+            .collect();
+
+        let characters: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        if characters.is_empty() {
+            return None;
+        }
+
+        let garbage = characters.iter().filter(|c| is_garbage(**c)).count();
+        let garbage_ratio = garbage as f64 / characters.len() as f64;
+
+        // Text consisting only of numbers and symbols is judged by garbage alone
+        let word_ratio = if tokens.is_empty() {
+            1.0
+        } else {
+            tokens.iter().filter(|token| looks_like_word(token)).count() as f64
+                / tokens.len() as f64
+        };
+
+        let score = (word_ratio * (1.0 - garbage_ratio)).clamp(0.0, 1.0);
+
+        Some(Self {
+            score: round(score),
+            word_ratio: round(word_ratio),
+            garbage_ratio: round(garbage_ratio),
+        })
+    }
+}
+
+/// What to do when the quality score is below the configured minimum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LowQualityAction {
+    /// Log a warning and keep the result
+    #[default]
+    Warn,
+    /// Run OCR once more and keep the better result
+    Rerun,
+    /// Fail with the insufficient-text exit code
+    Fail,
+}
+
+impl FromStr for LowQualityAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "warn" => Ok(Self::Warn),
+            "rerun" => Ok(Self::Rerun),
+            "fail" => Ok(Self::Fail),
+            other => Err(Error::Config(format!(
+                "Unknown low quality action: {}. Supported: warn, rerun, fail",
+                other
+            ))),
+        }
+    }
+}
+
+/// Whether a token looks like a word rather than misrecognized noise
+fn looks_like_word(token: &str) -> bool {
+    let length = token.chars().count();
+    if !(1..=24).contains(&length) || !token.chars().all(char::is_alphabetic) {
+        return false;
+    }
+
+    let lowercase = token.to_lowercase();
+    if length > 1 && !lowercase.chars().any(|c| VOWELS.contains(c)) {
+        return false;
+    }
+
+    // lower, Title or UPPER case; mixed case like "tHe" is typical OCR noise
+    let rest_lower = token.chars().skip(1).all(|c| !c.is_uppercase());
+    let all_upper = token.chars().all(|c| !c.is_lowercase());
+    rest_lower || all_upper
+}
+
+/// Whether a non-whitespace character is OCR garbage
+fn is_garbage(c: char) -> bool {
+    if c == char::REPLACEMENT_CHARACTER || c.is_control() {
+        return true;
+    }
+    !c.is_alphanumeric() && !DOCUMENT_SYMBOLS.contains(c)
+}
+
+/// Round to three decimals for stable output
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text_scores_high() {
+        let quality = QualityScore::from_text(
+            "Invoice 2024-042\n\nDear Sir or Madam, please find attached the invoice for March.",
+        )
+        .unwrap();
+
+        assert!(quality.score > 0.9, "{:?}", quality);
+        assert_eq!(quality.garbage_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_garbage_text_scores_low() {
+        let quality = QualityScore::from_text("tHe ~~^^ rnmvl ¤¤ xQzt ¦¦ wrthp ~~ kLmN").unwrap();
+        assert!(quality.score < 0.3, "{:?}", quality);
+        assert!(quality.garbage_ratio > 0.1);
+    }
+
+    #[test]
+    fn test_empty_text_has_no_score() {
+        assert!(QualityScore::from_text("  \n\n ").is_none());
+    }
+
+    #[test]
+    fn test_german_text_is_recognized() {
+        let quality =
+            QualityScore::from_text("Sehr geehrte Damen und Herren, anbei die Rechnung für März.")
+                .unwrap();
+        assert_eq!(quality.score, 1.0);
+    }
+
+    #[test]
+    fn test_low_quality_action_parsing() {
+        assert_eq!(
+            "rerun".parse::<LowQualityAction>().unwrap(),
+            LowQualityAction::Rerun
+        );
+        assert!("ignore".parse::<LowQualityAction>().is_err());
+    }
+}
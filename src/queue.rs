@@ -0,0 +1,408 @@
+//! Persisted job queue for `serve`
+//!
+//! `POST /jobs` stores the upload and answers at once with a job ID, so large
+//! documents are not held open for the whole OCR run; clients poll
+//! `GET /jobs/<id>` or name a callback URL that receives the result. Every job
+//! is a directory in the queue directory:
+//!
+//! ```text
+//! <queue dir>/<id>/job.json        state, and the result once finished
+//! <queue dir>/<id>/document.<ext>  uploaded document, removed when finished
+//! ```
+//!
+//! The document keeps only the extension of the client's file name, so no
+//! upload can take the place of the state file; the full name is kept in the
+//! job.
+//!
+//! `job.json` is replaced atomically on every change, so a restart finds each
+//! job in its last state; jobs that were queued or running are queued again.
+//! Finished jobs are removed after the retention period.
+//!
+//! When outputs are encrypted, results are kept in memory instead of
+//! `job.json` so the plaintext never touches the disk; they are lost on a
+//! restart.
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// State file in each job directory
+const JOB_FILE: &str = "job.json";
+
+/// Name of the uploaded document in each job directory, without extension
+const DOCUMENT_FILE: &str = "document";
+
+/// State of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// Waiting for a worker
+    Queued,
+
+    /// Being processed
+    Running,
+
+    /// Processed; the result holds the output
+    Succeeded,
+
+    /// Failed; the result holds the error
+    Failed,
+}
+
+impl JobState {
+    /// Whether the job will not change any more
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// A job and its result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedJob {
+    /// ID of the job
+    pub id: String,
+
+    /// State of the job
+    pub state: JobState,
+
+    /// File name of the uploaded document
+    pub filename: String,
+
+    /// MIME type given with the upload
+    pub mime_type: Option<String>,
+
+    /// Token that submitted the job; only it may read the job
+    pub client: Option<String>,
+
+    /// Tenant profile the job is processed with
+    pub profile: Option<String>,
+
+    /// URL the result is posted to when the job finishes
+    pub callback: Option<String>,
+
+    /// When the job was accepted
+    pub created_at: DateTime<Utc>,
+
+    /// When the job finished
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// The `--json` output of the document once finished
+    pub result: Option<serde_json::Value>,
+}
+
+impl QueuedJob {
+    /// A new job for an upload
+    pub fn new(filename: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            state: JobState::Queued,
+            filename,
+            mime_type: None,
+            client: None,
+            profile: None,
+            callback: None,
+            created_at: Utc::now(),
+            finished_at: None,
+            result: None,
+        }
+    }
+}
+
+/// Jobs stored in a directory, with the queued ones in order of arrival
+#[derive(Debug)]
+pub struct JobQueue {
+    dir: PathBuf,
+    pending: Mutex<VecDeque<String>>,
+    ready: tokio::sync::Notify,
+    /// Results of finished jobs by ID, when they are not written to disk
+    results: Option<Mutex<HashMap<String, serde_json::Value>>>,
+}
+
+impl JobQueue {
+    /// Open the queue in `dir`, queueing the jobs a previous server did not
+    /// finish again
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).map_err(Error::Io)?;
+        let queue = Self {
+            dir: dir.to_path_buf(),
+            pending: Mutex::new(VecDeque::new()),
+            ready: tokio::sync::Notify::new(),
+            results: None,
+        };
+
+        let mut unfinished = Vec::new();
+        for entry in fs::read_dir(dir).map_err(Error::Io)? {
+            let id = entry.map_err(Error::Io)?.file_name();
+            let Some(mut job) = queue.get(&id.to_string_lossy())? else {
+                continue;
+            };
+            if job.state == JobState::Running {
+                job.state = JobState::Queued;
+                queue.save(&job)?;
+            }
+            if job.state == JobState::Queued {
+                unfinished.push((job.created_at, job.id));
+            }
+        }
+        unfinished.sort();
+        if !unfinished.is_empty() {
+            tracing::info!("Resuming {} queued jobs", unfinished.len());
+        }
+        queue
+            .lock()
+            .extend(unfinished.into_iter().map(|(_, id)| id));
+        Ok(queue)
+    }
+
+    /// Keep the results of finished jobs in memory instead of `job.json`
+    pub fn keep_results_in_memory(mut self) -> Self {
+        self.results = Some(Mutex::default());
+        self
+    }
+
+    /// Directory of a job; the uploaded document goes here
+    pub fn job_dir(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    /// Path of a job's uploaded document, with the extension of its file
+    /// name so the file type can be told from it
+    pub fn document_path(&self, job: &QueuedJob) -> PathBuf {
+        let extension = Path::new(&job.filename)
+            .extension()
+            .map(|extension| extension.to_string_lossy())
+            .filter(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()));
+        let name = match extension {
+            Some(extension) => format!("{}.{}", DOCUMENT_FILE, extension),
+            None => DOCUMENT_FILE.to_string(),
+        };
+        self.job_dir(&job.id).join(name)
+    }
+
+    /// Queue a job whose document was written to [`JobQueue::document_path`]
+    pub fn submit(&self, job: &QueuedJob) -> Result<()> {
+        self.save(job)?;
+        self.lock().push_back(job.id.clone());
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// A job by ID, or `None` if there is no such job
+    pub fn get(&self, id: &str) -> Result<Option<QueuedJob>> {
+        // IDs come from request paths; anything but a plain name is unknown
+        if id.is_empty() || id.contains(['/', '\\', '.']) {
+            return Ok(None);
+        }
+        let mut job: QueuedJob = match fs::read(self.job_dir(id).join(JOB_FILE)) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| Error::internal_from(format!("Failed to read job {}", id), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(e)),
+        };
+        if let Some(ref results) = self.results {
+            job.result = lock(results).get(id).cloned();
+        }
+        Ok(Some(job))
+    }
+
+    /// Number of jobs waiting for a worker
+    pub fn depth(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Wait for the next queued job and mark it as running
+    pub async fn next(&self) -> Result<QueuedJob> {
+        loop {
+            // Register for a wakeup before looking, so a job submitted in
+            // between is not missed
+            let ready = self.ready.notified();
+            let id = self.lock().pop_front();
+            match id {
+                Some(id) => {
+                    let Some(mut job) = self.get(&id)? else {
+                        continue;
+                    };
+                    job.state = JobState::Running;
+                    self.save(&job)?;
+                    return Ok(job);
+                }
+                None => ready.await,
+            }
+        }
+    }
+
+    /// Record the result of a job and remove its document
+    pub fn finish(
+        &self,
+        job: &mut QueuedJob,
+        succeeded: bool,
+        result: serde_json::Value,
+    ) -> Result<()> {
+        job.state = if succeeded {
+            JobState::Succeeded
+        } else {
+            JobState::Failed
+        };
+        job.finished_at = Some(Utc::now());
+        job.result = Some(result);
+        match self.results {
+            Some(ref results) => {
+                lock(results).insert(job.id.clone(), job.result.clone().unwrap_or_default());
+                self.save(&QueuedJob {
+                    result: None,
+                    ..job.clone()
+                })?;
+            }
+            None => self.save(job)?,
+        }
+        let _ = fs::remove_file(self.document_path(job));
+        Ok(())
+    }
+
+    /// Remove jobs that finished more than `retention` ago
+    pub fn prune(&self, retention: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - chrono::Duration::from_std(retention).unwrap_or_default();
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir).map_err(Error::Io)? {
+            let entry = entry.map_err(Error::Io)?;
+            let id = entry.file_name();
+            let expired = match self.get(&id.to_string_lossy()).ok().flatten() {
+                Some(job) => job
+                    .finished_at
+                    .is_some_and(|finished_at| finished_at < cutoff),
+                // An upload that never became a job, e.g. after a crash
+                None if entry.file_type().is_ok_and(|kind| kind.is_dir()) => entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| DateTime::<Utc>::from(modified) < cutoff),
+                None => false,
+            };
+            if expired && fs::remove_dir_all(self.dir.join(&id)).is_ok() {
+                if let Some(ref results) = self.results {
+                    lock(results).remove(&*id.to_string_lossy());
+                }
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Write the state of a job, replacing the previous one atomically
+    fn save(&self, job: &QueuedJob) -> Result<()> {
+        let dir = self.job_dir(&job.id);
+        fs::create_dir_all(&dir).map_err(Error::Io)?;
+        let json = serde_json::to_vec_pretty(job)
+            .map_err(|e| Error::internal_from("Failed to serialize job", e))?;
+        let partial = dir.join(format!("{}.partial", JOB_FILE));
+        fs::write(&partial, json).map_err(Error::Io)?;
+        fs::rename(&partial, dir.join(JOB_FILE)).map_err(Error::Io)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<String>> {
+        lock(&self.pending)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn submit(queue: &JobQueue, filename: &str) -> QueuedJob {
+        let job = QueuedJob::new(filename.to_string());
+        fs::create_dir_all(queue.job_dir(&job.id)).unwrap();
+        fs::write(queue.document_path(&job), b"%PDF-1.4\n").unwrap();
+        queue.submit(&job).unwrap();
+        job
+    }
+
+    #[tokio::test]
+    async fn test_jobs_survive_a_restart() {
+        let dir = TempDir::new().unwrap();
+        let queue = JobQueue::open(dir.path()).unwrap();
+        let first = submit(&queue, "a.pdf");
+        let second = submit(&queue, "b.pdf");
+        let third = submit(&queue, "c.pdf");
+        assert_eq!(queue.depth(), 3);
+
+        let mut running = queue.next().await.unwrap();
+        assert_eq!(running.id, first.id);
+        assert_eq!(running.state, JobState::Running);
+        queue
+            .finish(&mut running, true, serde_json::json!({"success": true}))
+            .unwrap();
+        assert!(!queue.document_path(&running).exists());
+        let interrupted = queue.next().await.unwrap();
+        assert_eq!(interrupted.id, second.id);
+        drop(queue);
+
+        // The running job is queued again, before the one that arrived later
+        let queue = JobQueue::open(dir.path()).unwrap();
+        assert_eq!(queue.depth(), 2);
+        assert_eq!(queue.next().await.unwrap().id, second.id);
+        assert_eq!(queue.next().await.unwrap().id, third.id);
+
+        let finished = queue.get(&first.id).unwrap().unwrap();
+        assert_eq!(finished.state, JobState::Succeeded);
+        assert_eq!(finished.result.unwrap()["success"], true);
+        assert!(queue.get("../etc").unwrap().is_none());
+        assert!(queue.get("missing").unwrap().is_none());
+
+        assert_eq!(queue.prune(Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(queue.prune(Duration::ZERO).unwrap(), 1);
+        assert!(queue.get(&first.id).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_results_kept_in_memory_are_not_written() {
+        let dir = TempDir::new().unwrap();
+        let queue = JobQueue::open(dir.path()).unwrap().keep_results_in_memory();
+        let job = submit(&queue, "a.pdf");
+        let mut running = queue.next().await.unwrap();
+        queue
+            .finish(&mut running, true, serde_json::json!({"text": "secret"}))
+            .unwrap();
+
+        let finished = queue.get(&job.id).unwrap().unwrap();
+        assert_eq!(finished.state, JobState::Succeeded);
+        assert_eq!(finished.result.unwrap()["text"], "secret");
+        let stored = fs::read_to_string(queue.job_dir(&job.id).join(JOB_FILE)).unwrap();
+        assert!(!stored.contains("secret"));
+
+        assert_eq!(queue.prune(Duration::ZERO).unwrap(), 1);
+        assert!(queue.results.as_ref().unwrap().lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_names_cannot_replace_the_state_file() {
+        let dir = TempDir::new().unwrap();
+        let queue = JobQueue::open(dir.path()).unwrap();
+
+        for filename in [JOB_FILE, "job.json.partial"] {
+            let job = submit(&queue, filename);
+            assert_eq!(
+                fs::read(queue.document_path(&job)).unwrap(),
+                b"%PDF-1.4\n",
+                "{}",
+                filename
+            );
+
+            let mut running = queue.next().await.unwrap();
+            assert_eq!(running.filename, filename);
+            queue
+                .finish(&mut running, true, serde_json::json!({"success": true}))
+                .unwrap();
+            let finished = queue.get(&job.id).unwrap().unwrap();
+            assert_eq!(finished.state, JobState::Succeeded, "{}", filename);
+        }
+    }
+}
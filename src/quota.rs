@@ -0,0 +1,224 @@
+//! Monthly page quota
+//!
+//! Mistral bills per page. Pages processed by the Mistral provider are counted
+//! per calendar month (UTC) in a small JSON state file, by default
+//! `$XDG_STATE_HOME/paperless-ngx-ocr2/state.json`
+//! (`~/.local/state/paperless-ngx-ocr2/state.json`, see [`crate::paths`] for
+//! macOS and Windows):
+//!
+//! ```json
+//! {"pages_by_month": {"2024-06": 1234}}
+//! ```
+//!
+//! With `[quota] monthly_pages` set, a warning is logged once the month's pages
+//! reach `warn_ratio` of the quota; with `--enforce-quota` the Mistral provider
+//! refuses further documents once the quota is used up.
+
+use crate::error::{Error, FieldErrors, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Monthly quota settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    /// Pages per calendar month; unset disables the quota
+    #[serde(default)]
+    pub monthly_pages: Option<u64>,
+
+    /// Fraction of the quota at which a warning is logged
+    #[serde(default = "default_warn_ratio")]
+    pub warn_ratio: f64,
+
+    /// Refuse documents once the quota is used up instead of only warning
+    #[serde(default)]
+    pub enforce: bool,
+}
+
+fn default_warn_ratio() -> f64 {
+    0.8
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            monthly_pages: None,
+            warn_ratio: default_warn_ratio(),
+            enforce: false,
+        }
+    }
+}
+
+impl QuotaConfig {
+    /// Validate the quota settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.monthly_pages == Some(0) {
+            errors.add("monthly_pages", "Monthly page quota must be greater than 0");
+        }
+
+        if !(0.0..=1.0).contains(&self.warn_ratio) {
+            errors.add(
+                "warn_ratio",
+                "Quota warning ratio must be between 0.0 and 1.0",
+            );
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Warn when the month's usage approaches the quota; fail when enforced and used up
+    pub fn check(&self, used: u64, month: &str) -> Result<()> {
+        let Some(quota) = self.monthly_pages else {
+            return Ok(());
+        };
+
+        if used >= quota {
+            let message = format!(
+                "monthly quota of {} pages used up ({} pages processed in {})",
+                quota, used, month
+            );
+            if self.enforce {
+                return Err(Error::BudgetExceeded(message));
+            }
+            tracing::warn!("{}", message);
+        } else if used as f64 >= quota as f64 * self.warn_ratio {
+            tracing::warn!(
+                "{} of {} monthly pages processed in {} ({:.0}%)",
+                used,
+                quota,
+                month,
+                used as f64 / quota as f64 * 100.0
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Persistent usage counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageState {
+    /// Pages processed per month (`YYYY-MM`)
+    #[serde(default)]
+    pub pages_by_month: BTreeMap<String, u64>,
+}
+
+impl UsageState {
+    /// Load the state file; a missing file is an empty state
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                Error::Config(format!("Invalid state file {}: {}", path.display(), e))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    /// Write the state file, creating its directory
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(Error::Io)?;
+        }
+
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::internal_from("Failed to serialize state", e))?;
+        // Replace atomically so an interrupted write cannot lose the counters
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, content).map_err(Error::Io)?;
+        fs::rename(&temp_path, path).map_err(Error::Io)
+    }
+
+    /// Pages processed in a month
+    pub fn pages_in(&self, month: &str) -> u64 {
+        self.pages_by_month.get(month).copied().unwrap_or(0)
+    }
+}
+
+/// Current month as `YYYY-MM` (UTC)
+pub fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// Default state file location
+pub fn default_state_file() -> Option<PathBuf> {
+    crate::paths::state_dir().map(|dir| dir.join("state.json"))
+}
+
+/// Check the quota before a billed request
+pub fn check_quota(quota: &QuotaConfig, state_file: Option<&Path>) -> Result<()> {
+    let Some(path) = state_file.filter(|_| quota.monthly_pages.is_some()) else {
+        return Ok(());
+    };
+
+    let month = current_month();
+    quota.check(UsageState::load(path)?.pages_in(&month), &month)
+}
+
+/// Add billed pages to the current month
+pub fn record_pages(state_file: &Path, pages: u64) -> Result<()> {
+    let mut state = UsageState::load(state_file)?;
+    *state.pages_by_month.entry(current_month()).or_insert(0) += pages;
+    state.save(state_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_pages_accumulates_per_month() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("state.json");
+
+        assert_eq!(UsageState::load(&path).unwrap(), UsageState::default());
+        record_pages(&path, 3).unwrap();
+        record_pages(&path, 4).unwrap();
+
+        let state = UsageState::load(&path).unwrap();
+        assert_eq!(state.pages_in(&current_month()), 7);
+        assert_eq!(state.pages_in("1999-01"), 0);
+    }
+
+    #[test]
+    fn test_quota_check() {
+        let quota = QuotaConfig {
+            monthly_pages: Some(100),
+            ..QuotaConfig::default()
+        };
+        assert!(quota.check(99, "2024-06").is_ok());
+        assert!(quota.check(150, "2024-06").is_ok());
+
+        let enforced = QuotaConfig {
+            enforce: true,
+            ..quota
+        };
+        assert!(enforced.check(99, "2024-06").is_ok());
+        let error = enforced.check(100, "2024-06").unwrap_err();
+        assert_eq!(error.exit_code(), 7);
+        assert!(error.to_string().contains("100 pages processed in 2024-06"));
+
+        assert!(QuotaConfig::default().check(u64::MAX, "2024-06").is_ok());
+    }
+
+    #[test]
+    fn test_enforced_quota_reads_state_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        let quota = QuotaConfig {
+            monthly_pages: Some(5),
+            enforce: true,
+            ..QuotaConfig::default()
+        };
+
+        assert!(check_quota(&quota, Some(&path)).is_ok());
+        record_pages(&path, 5).unwrap();
+        assert!(check_quota(&quota, Some(&path)).is_err());
+        assert!(check_quota(&quota, None).is_ok());
+    }
+}
@@ -0,0 +1,196 @@
+//! PDF rasterization for image-only providers
+//!
+//! Providers such as the local Tesseract provider only read images. With the
+//! `pdf-render` feature, PDFs are rendered to one PNG per page with poppler's
+//! `pdftoppm` and each page is passed to the provider separately:
+//!
+//! ```toml
+//! [render]
+//! command = "pdftoppm"
+//! dpi = 300
+//! ```
+
+use crate::error::{FieldErrors, Result};
+use serde::{Deserialize, Serialize};
+
+/// Settings for rendering PDF pages to images
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderConfig {
+    /// Poppler `pdftoppm` executable
+    #[serde(default = "default_render_command")]
+    pub command: String,
+
+    /// Rendering resolution in dots per inch
+    #[serde(default = "default_render_dpi")]
+    pub dpi: u32,
+}
+
+fn default_render_command() -> String {
+    "pdftoppm".to_string()
+}
+
+fn default_render_dpi() -> u32 {
+    300
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            command: default_render_command(),
+            dpi: default_render_dpi(),
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Validate the render settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if !(72..=1200).contains(&self.dpi) {
+            errors.add("dpi", "Render DPI must be between 72 and 1200");
+        }
+
+        errors.into_config_result()
+    }
+}
+
+#[cfg(feature = "pdf-render")]
+pub use rasterize::{rasterize, RenderedPages};
+
+#[cfg(feature = "pdf-render")]
+mod rasterize {
+    use super::RenderConfig;
+    use crate::error::{Error, Result};
+    use crate::scratch::{RunTemp, ScratchDir};
+    use std::path::{Path, PathBuf};
+
+    /// Page images in the run's temporary directory, removed on drop
+    #[derive(Debug)]
+    pub struct RenderedPages {
+        _dir: ScratchDir,
+        pages: Vec<PathBuf>,
+    }
+
+    impl RenderedPages {
+        /// Page images in page order
+        pub fn paths(&self) -> &[PathBuf] {
+            &self.pages
+        }
+    }
+
+    /// Render every page of a PDF to a PNG in the run directory of `temp`
+    pub async fn rasterize(
+        pdf: &Path,
+        render: &RenderConfig,
+        temp: &RunTemp,
+    ) -> Result<RenderedPages> {
+        let dir = temp.create_dir("render")?;
+
+        let output = tokio::process::Command::new(&render.command)
+            .arg("-png")
+            .args(["-r", &render.dpi.to_string()])
+            .arg(pdf)
+            .arg(dir.path().join("page"))
+            .output()
+            .await
+            .map_err(|e| {
+                Error::Config(format!(
+                    "Failed to run PDF render command '{}': {}",
+                    render.command, e
+                ))
+            })?;
+
+        if !output.status.success() {
+            return Err(Error::api(format!(
+                "{} exited with {}: {}",
+                render.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let mut pages: Vec<(u32, PathBuf)> = std::fs::read_dir(dir.path())
+            .map_err(Error::Io)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter_map(|path| page_number(&path).map(|number| (number, path)))
+            .collect();
+        pages.sort();
+
+        if pages.is_empty() {
+            return Err(Error::api(format!(
+                "{} produced no page images",
+                render.command
+            )));
+        }
+
+        Ok(RenderedPages {
+            _dir: dir,
+            pages: pages.into_iter().map(|(_, path)| path).collect(),
+        })
+    }
+
+    /// Page number of a `page-<n>.png` file
+    fn page_number(path: &Path) -> Option<u32> {
+        if path.extension()? != "png" {
+            return None;
+        }
+        path.file_stem()?
+            .to_str()?
+            .strip_prefix("page-")?
+            .parse()
+            .ok()
+    }
+
+    #[cfg(all(test, unix))]
+    mod tests {
+        use super::*;
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+
+        #[tokio::test]
+        async fn test_rasterize_orders_pages_numerically() {
+            let dir = TempDir::new().unwrap();
+            // Stand-in for pdftoppm: the last argument is the output prefix
+            let command = dir.path().join("pdftoppm");
+            std::fs::write(
+                &command,
+                "#!/bin/sh\nfor n in 10 2 1; do : > \"$5-$n.png\"; done\n",
+            )
+            .unwrap();
+            std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+            let render = RenderConfig {
+                command: command.to_string_lossy().to_string(),
+                ..RenderConfig::default()
+            };
+            let pages = rasterize(Path::new("scan.pdf"), &render, &RunTemp::default())
+                .await
+                .unwrap();
+            let names: Vec<_> = pages
+                .paths()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect();
+
+            assert_eq!(names, ["page-1.png", "page-2.png", "page-10.png"]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_dpi_range() {
+        let render = |dpi| RenderConfig {
+            dpi,
+            ..RenderConfig::default()
+        };
+
+        assert!(render(300).validate().is_ok());
+        assert!(render(10).validate().is_err());
+        assert!(render(2400).validate().is_err());
+    }
+}
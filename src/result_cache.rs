@@ -0,0 +1,669 @@
+//! Persistent cache of OCR results
+//!
+//! With `[cache] enabled = true`, the provider output for a document is stored
+//! on disk, keyed by the SHA-256 of the file and the options that change the
+//! OCR output (providers, instructions, extraction preset, page format). Running
+//! the same document again, for example in another pipeline step or after a
+//! failed post-processing step, then costs no API calls:
+//!
+//! ```toml
+//! [cache]
+//! enabled = true
+//! dir = "/var/cache/paperless-ngx-ocr2"  # default: <platform cache dir>/results
+//! ttl_hours = 720
+//! ```
+//!
+//! Entries record the provider, model, options, and OCR API version that
+//! produced them. An entry made with another model, say after an upgrade of
+//! this tool or other Tesseract languages, or for another API version is
+//! ignored and the document processed again.
+//!
+//! Entries expire up to `ttl_jitter` (a fraction of the TTL) early, spread by
+//! key, so documents cached in one large batch do not all expire, and get
+//! uploaded again, at the same moment. With `stale_while_revalidate_hours`, an
+//! expired entry is still used for that long while the document is processed
+//! again in the background (see [`Revalidations`]).
+//!
+//! `paperless-ngx-ocr2 cache stats|prune|clear|path` manage the entries;
+//! `cache export|import` back them up (see [`crate::cache_backup`]). The
+//! default directory is `$XDG_CACHE_HOME/paperless-ngx-ocr2/results` on Linux;
+//! see [`crate::paths`] for macOS and Windows.
+
+use crate::api::ocr::OCR_API_VERSION;
+use crate::config::Config;
+use crate::error::{Error, FieldErrors, Result};
+use crate::provider::{ProviderKind, ProviderOutput};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinSet;
+
+/// Settings of the persistent result cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultCacheConfig {
+    /// Store and reuse OCR results
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Cache directory; unset uses the platform cache directory
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// Hours until an entry expires
+    #[serde(default = "default_ttl_hours")]
+    pub ttl_hours: u64,
+
+    /// Fraction of the TTL by which entries may expire early, spread by key
+    #[serde(default = "default_ttl_jitter")]
+    pub ttl_jitter: f64,
+
+    /// Hours past expiry an entry is still used while its document is
+    /// processed again in the background; 0 processes expired documents
+    /// right away
+    #[serde(default)]
+    pub stale_while_revalidate_hours: u64,
+
+    /// Also store the text of each PDF page, so changed documents only pay
+    /// for changed pages (see [`crate::page_cache`])
+    #[serde(default)]
+    pub pages: bool,
+}
+
+fn default_ttl_hours() -> u64 {
+    720
+}
+
+fn default_ttl_jitter() -> f64 {
+    0.1
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+            ttl_hours: default_ttl_hours(),
+            ttl_jitter: default_ttl_jitter(),
+            stale_while_revalidate_hours: 0,
+            pages: false,
+        }
+    }
+}
+
+impl ResultCacheConfig {
+    /// Validate the cache settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.ttl_hours == 0 {
+            errors.add("ttl_hours", "Cache TTL must be at least 1 hour");
+        }
+        if !(0.0..1.0).contains(&self.ttl_jitter) {
+            errors.add("ttl_jitter", "TTL jitter must be at least 0 and below 1");
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Configured directory, or the default one
+    pub fn dir_path(&self) -> Option<PathBuf> {
+        self.dir.clone().or_else(default_cache_dir)
+    }
+
+    /// The cache at the configured location, whether or not it is enabled
+    pub fn open(&self) -> Result<ResultCache> {
+        let dir = self.dir_path().ok_or_else(|| {
+            Error::Config("No cache directory: set cache.dir or HOME".to_string())
+        })?;
+        Ok(ResultCache::new(
+            dir,
+            Duration::from_secs(self.ttl_hours.saturating_mul(3600)),
+        )
+        .with_jitter(self.ttl_jitter)
+        .with_stale_while_revalidate(Duration::from_secs(
+            self.stale_while_revalidate_hours.saturating_mul(3600),
+        )))
+    }
+
+    /// The cache, when enabled
+    pub fn open_enabled(&self) -> Option<ResultCache> {
+        if !self.enabled {
+            return None;
+        }
+        match self.open() {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                tracing::warn!("Result cache disabled: {}", e.user_message());
+                None
+            }
+        }
+    }
+}
+
+/// Default cache directory
+pub fn default_cache_dir() -> Option<PathBuf> {
+    crate::paths::cache_dir().map(|dir| dir.join("results"))
+}
+
+/// Entry counts and size of the cache directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResultCacheStats {
+    pub dir: PathBuf,
+    pub entries: usize,
+    pub expired: usize,
+    pub size_bytes: u64,
+}
+
+impl ResultCacheStats {
+    /// Format for the terminal
+    pub fn to_human_readable(&self) -> String {
+        format!(
+            "Cache directory: {}\nEntries: {} ({} expired)\nSize: {} bytes",
+            self.dir.display(),
+            self.entries,
+            self.expired,
+            self.size_bytes
+        )
+    }
+}
+
+/// What produced a cache entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Provider that produced the result
+    pub provider: ProviderKind,
+
+    /// Model of the provider, see [`ProviderKind::model`]
+    pub model: String,
+
+    /// Model the provider reported in its response; for an alias such as
+    /// `mistral-ocr-latest`, the version it resolved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_model: Option<String>,
+
+    /// SHA-256 of the output-relevant options the entry is keyed by
+    pub options_sha256: String,
+
+    /// Version of the OCR API
+    pub api_version: String,
+}
+
+impl Provenance {
+    /// Provenance of a result of `provider` under a configuration and options
+    pub fn new(provider: ProviderKind, app_config: &Config, options: &str) -> Self {
+        Self {
+            provider,
+            model: provider.model(app_config),
+            resolved_model: None,
+            options_sha256: format!("{:x}", Sha256::digest(options.as_bytes())),
+            api_version: OCR_API_VERSION.to_string(),
+        }
+    }
+
+    /// Provenance of a provider output, with the model its response names
+    pub fn of(output: &ProviderOutput, app_config: &Config, options: &str) -> Self {
+        Self {
+            resolved_model: Some(output.response.model.clone()),
+            ..Self::new(output.provider, app_config, options)
+        }
+    }
+
+    /// Whether the provider would produce the entry the same way now
+    pub fn is_current(&self, app_config: &Config, options: &str) -> bool {
+        let current = Self::new(self.provider, app_config, options);
+        let same = self.model == current.model
+            && self.options_sha256 == current.options_sha256
+            && self.api_version == current.api_version;
+        if !same {
+            tracing::debug!(
+                "Ignoring a cache entry made with {} {} (API {}); now {} (API {})",
+                self.provider,
+                self.model,
+                self.api_version,
+                current.model,
+                current.api_version
+            );
+            return false;
+        }
+        true
+    }
+}
+
+/// Provider output stored in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedOutput {
+    #[serde(flatten)]
+    pub provenance: Provenance,
+
+    pub output: ProviderOutput,
+}
+
+impl CachedOutput {
+    /// Entry of a provider output under a configuration and options
+    pub fn new(output: ProviderOutput, app_config: &Config, options: &str) -> Self {
+        Self {
+            provenance: Provenance::of(&output, app_config, options),
+            output,
+        }
+    }
+}
+
+/// Outcome of `cache warm`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CacheWarmReport {
+    /// Stored results found
+    pub results: usize,
+
+    /// Entries added
+    pub warmed: usize,
+
+    /// Results whose document was already cached
+    pub cached: usize,
+
+    /// Results without their document or SHA-256, or whose document changed
+    pub skipped: usize,
+}
+
+impl CacheWarmReport {
+    /// Format for the terminal
+    pub fn to_human_readable(&self) -> String {
+        format!(
+            "Warmed {} of {} results ({} already cached, {} skipped)",
+            self.warmed, self.results, self.cached, self.skipped
+        )
+    }
+}
+
+/// OCR results stored as one JSON file per key
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+    ttl: Duration,
+    jitter: f64,
+    stale: Duration,
+}
+
+/// Whether a cached value is within its TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Within its TTL
+    Fresh,
+
+    /// Expired, but within the stale-while-revalidate window
+    Stale,
+}
+
+impl ResultCache {
+    /// Cache in `dir` whose entries expire after `ttl`
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self {
+            dir,
+            ttl,
+            jitter: 0.0,
+            stale: Duration::ZERO,
+        }
+    }
+
+    /// Let entries expire up to this fraction of the TTL early, spread by key
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Keep using entries for this long past their expiry, as stale
+    pub fn with_stale_while_revalidate(mut self, stale: Duration) -> Self {
+        self.stale = stale;
+        self
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Key of a file's result under the given output-relevant options
+    pub fn key(file_sha256: &str, options: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(file_sha256.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(options.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Whether a key has an entry that has not expired
+    pub fn contains(&self, key: &str) -> bool {
+        let path = self.entry_path(key);
+        fs::metadata(&path).is_ok_and(|metadata| !self.is_expired(&path, &metadata))
+    }
+
+    /// Stored value of a key, unless missing, expired, or unreadable
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.lookup(key)? {
+            (value, Freshness::Fresh) => Some(value),
+            (_, Freshness::Stale) => None,
+        }
+    }
+
+    /// Stored value of a key with its freshness, unless missing, past the
+    /// stale-while-revalidate window, or unreadable
+    pub fn lookup<T: DeserializeOwned>(&self, key: &str) -> Option<(T, Freshness)> {
+        let path = self.entry_path(key);
+        let metadata = fs::metadata(&path).ok()?;
+        if self.is_dead(&path, &metadata) {
+            tracing::debug!("Cache entry {} expired", key);
+            return None;
+        }
+        let freshness = if self.is_expired(&path, &metadata) {
+            Freshness::Stale
+        } else {
+            Freshness::Fresh
+        };
+
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(value) => Some((value, freshness)),
+            Err(e) => {
+                tracing::debug!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store a value; the file is replaced atomically
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(Error::Io)?;
+        let json = serde_json::to_vec(value)
+            .map_err(|e| Error::internal_from("Failed to serialize cache entry", e))?;
+
+        let path = self.entry_path(key);
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, json).map_err(Error::Io)?;
+        fs::rename(&partial, &path).map_err(Error::Io)
+    }
+
+    /// Count the entries
+    pub fn stats(&self) -> Result<ResultCacheStats> {
+        let mut stats = ResultCacheStats {
+            dir: self.dir.clone(),
+            entries: 0,
+            expired: 0,
+            size_bytes: 0,
+        };
+        for (path, metadata) in self.entries()? {
+            stats.entries += 1;
+            stats.size_bytes += metadata.len();
+            if self.is_expired(&path, &metadata) {
+                stats.expired += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Files of the entries that have not expired
+    pub fn entry_paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|(path, metadata)| !self.is_dead(path, metadata))
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// Remove entries past expiry and the stale-while-revalidate window, and
+    /// return how many were removed
+    pub fn prune(&self) -> Result<usize> {
+        self.remove_where(|path, metadata| self.is_dead(path, metadata))
+    }
+
+    /// Remove every entry and return how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        self.remove_where(|_, _| true)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// TTL of an entry: up to the jitter fraction shorter, by a fraction
+    /// derived from its file name so it is the same on every read
+    fn ttl_of(&self, path: &Path) -> Duration {
+        let name = path.file_name().unwrap_or_default().as_encoded_bytes();
+        let digest = Sha256::digest(name);
+        let spread =
+            u64::from_be_bytes(digest[..8].try_into().unwrap_or_default()) as f64 / u64::MAX as f64;
+        self.ttl.mul_f64(1.0 - self.jitter * spread)
+    }
+
+    fn age(metadata: &fs::Metadata) -> Option<Duration> {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+    }
+
+    fn is_expired(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        Self::age(metadata).is_some_and(|age| age > self.ttl_of(path))
+    }
+
+    /// Expired and past the stale-while-revalidate window
+    fn is_dead(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        Self::age(metadata).is_some_and(|age| age > self.ttl_of(path) + self.stale)
+    }
+
+    /// Entry files with their metadata; a missing directory has none
+    fn entries(&self) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::Io(e)),
+        };
+
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata)))
+            .collect())
+    }
+
+    fn remove_where(&self, remove: impl Fn(&Path, &fs::Metadata) -> bool) -> Result<usize> {
+        let mut removed = 0;
+        for (path, metadata) in self.entries()? {
+            if remove(&path, &metadata) {
+                fs::remove_file(&path).map_err(Error::Io)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Background refreshes of stale cache entries
+///
+/// A document is refreshed once at a time, however often its stale entry is
+/// read. Runs wait for the refreshes still going before they exit; clones
+/// share the refreshes, which are aborted when the last clone is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct Revalidations {
+    tasks: std::sync::Arc<Mutex<JoinSet<()>>>,
+    keys: std::sync::Arc<Mutex<HashSet<String>>>,
+}
+
+impl Revalidations {
+    /// Run `refresh` in the background, unless `key` is being refreshed
+    /// already; returns whether it was started
+    pub fn spawn<F>(&self, key: &str, refresh: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if !lock(&self.keys).insert(key.to_string()) {
+            return false;
+        }
+        let keys = self.keys.clone();
+        let key = key.to_string();
+        let mut tasks = lock(&self.tasks);
+        // Long runs such as `serve` never wait, so finished refreshes are
+        // collected here
+        while let Some(result) = tasks.try_join_next() {
+            log_failed_refresh(result);
+        }
+        tasks.spawn(async move {
+            refresh.await;
+            lock(&keys).remove(&key);
+        });
+        true
+    }
+
+    /// Wait for the refreshes started so far
+    pub async fn wait(&self) {
+        let mut tasks = std::mem::take(&mut *lock(&self.tasks));
+        while let Some(result) = tasks.join_next().await {
+            log_failed_refresh(result);
+        }
+    }
+}
+
+fn log_failed_refresh(result: std::result::Result<(), tokio::task::JoinError>) {
+    if let Err(e) = result {
+        tracing::warn!("Cache refresh failed: {}", e);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_put_get_and_clear() {
+        let dir = TempDir::new().unwrap();
+        let cache = ResultCache::new(dir.path().join("results"), Duration::from_secs(3600));
+
+        let key = ResultCache::key("abc", "providers=[Mock]");
+        assert_ne!(key, ResultCache::key("abc", "providers=[Mistral]"));
+        assert_eq!(cache.get::<String>(&key), None);
+        assert_eq!(cache.stats().unwrap().entries, 0);
+
+        cache.put(&key, &"text".to_string()).unwrap();
+        assert_eq!(cache.get::<String>(&key).as_deref(), Some("text"));
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.entries, stats.expired), (1, 0));
+        assert_eq!(cache.prune().unwrap(), 0);
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.get::<String>(&key), None);
+    }
+
+    #[test]
+    fn test_expired_entries_are_ignored_and_pruned() {
+        let dir = TempDir::new().unwrap();
+        let cache = ResultCache::new(dir.path().to_path_buf(), Duration::ZERO);
+
+        cache.put("key", &1).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(cache.get::<i32>("key"), None);
+        assert_eq!(cache.stats().unwrap().expired, 1);
+        assert_eq!(cache.prune().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_jitter_shortens_the_ttl_by_key() {
+        let dir = TempDir::new().unwrap();
+        let ttl = Duration::from_secs(1000);
+        let cache = ResultCache::new(dir.path().to_path_buf(), ttl).with_jitter(0.5);
+
+        let ttls: Vec<Duration> = (0..20)
+            .map(|i| cache.ttl_of(&cache.entry_path(&format!("key-{}", i))))
+            .collect();
+        assert!(ttls.iter().all(|entry| (ttl / 2..=ttl).contains(entry)));
+        assert!(ttls.iter().any(|entry| *entry != ttls[0]));
+        assert_eq!(cache.ttl_of(&cache.entry_path("key-3")), ttls[3]);
+    }
+
+    #[test]
+    fn test_stale_entries_are_used_until_the_window_ends() {
+        let dir = TempDir::new().unwrap();
+        let cache = ResultCache::new(dir.path().to_path_buf(), Duration::ZERO)
+            .with_stale_while_revalidate(Duration::from_secs(3600));
+
+        cache.put("key", &1).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(cache.get::<i32>("key"), None);
+        assert_eq!(cache.lookup::<i32>("key"), Some((1, Freshness::Stale)));
+        assert!(!cache.contains("key"));
+        assert_eq!(cache.stats().unwrap().expired, 1);
+        assert_eq!(cache.prune().unwrap(), 0);
+
+        let cache = ResultCache::new(dir.path().to_path_buf(), Duration::ZERO);
+        assert_eq!(cache.lookup::<i32>("key"), None);
+        assert_eq!(cache.prune().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_entries_of_other_models_or_api_versions_are_not_current() {
+        let mut config = Config::default();
+        let provenance = Provenance::new(ProviderKind::Tesseract, &config, "opts");
+        assert_eq!(provenance.model, "tesseract:eng");
+        assert!(provenance.is_current(&config, "opts"));
+        assert!(!provenance.is_current(&config, "other"));
+
+        config.tesseract.languages = "eng+deu".to_string();
+        assert!(!provenance.is_current(&config, "opts"));
+
+        let older_api = Provenance {
+            api_version: "v0".to_string(),
+            ..Provenance::new(ProviderKind::Mistral, &config, "opts")
+        };
+        assert!(!older_api.is_current(&config, "opts"));
+
+        // The configured model counts, not the version an alias resolved to
+        let mistral = Provenance {
+            resolved_model: Some("mistral-ocr-2505".to_string()),
+            ..Provenance::new(ProviderKind::Mistral, &config, "opts")
+        };
+        assert_eq!(mistral.model, "mistral-ocr-latest");
+        assert!(mistral.is_current(&config, "opts"));
+        config.ocr_model = "mistral-ocr-2512".to_string();
+        assert!(!mistral.is_current(&config, "opts"));
+    }
+
+    #[tokio::test]
+    async fn test_revalidations_run_once_per_key() {
+        let revalidations = Revalidations::default();
+        let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+
+        assert!(revalidations.spawn("key", async {
+            let _ = receiver.await;
+        }));
+        assert!(!revalidations.spawn("key", async {}));
+        sender.send(()).unwrap();
+        revalidations.wait().await;
+        assert!(revalidations.spawn("key", async {}));
+        revalidations.wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_finished_revalidations_are_collected_without_waiting() {
+        let revalidations = Revalidations::default();
+        for key in ["a", "b", "c"] {
+            assert!(revalidations.clone().spawn(key, async {}));
+            while !lock(&revalidations.keys).is_empty() {
+                tokio::task::yield_now().await;
+            }
+        }
+        // Each spawn collects the refreshes finished before it
+        assert_eq!(lock(&revalidations.tasks).len(), 1);
+    }
+}
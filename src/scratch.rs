@@ -0,0 +1,236 @@
+//! Per-run directory for intermediate files
+//!
+//! Files that only exist while a document is processed, such as PDF pages
+//! rendered for image-only providers and documents received by `serve`, are
+//! created in one directory per run under the system temp directory (`TMPDIR`),
+//! named `paperless-ngx-ocr2-<pid>-<random>`:
+//!
+//! - the files of a document are removed as soon as it is done
+//! - whatever is left is removed when the run ends, successfully or not
+//! - on SIGINT or SIGTERM the directory is removed before the process exits
+//!
+//! With `--keep-temp` nothing is removed and the directory is logged, so the
+//! intermediate files can be inspected after the run.
+
+use crate::diagnostics::SignalListener;
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Exit code after SIGINT (128 + 2)
+const INTERRUPTED: i32 = 130;
+
+/// Exit code after SIGTERM (128 + 15)
+const TERMINATED: i32 = 143;
+
+/// Temporary directory of a run, created on first use; clones share it
+#[derive(Debug, Clone, Default)]
+pub struct RunTemp {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Run directory, while it exists
+    root: Option<PathBuf>,
+
+    /// Keep intermediate files for debugging
+    keep: bool,
+}
+
+impl RunTemp {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Start a run; the run directory is removed when the returned guard is
+    /// dropped, unless `keep` is set
+    pub fn start(&self, keep: bool) -> RunGuard {
+        self.lock().keep = keep;
+        RunGuard { temp: self.clone() }
+    }
+
+    /// Run directory, if it has been created
+    pub fn root(&self) -> Option<PathBuf> {
+        self.lock().root.clone()
+    }
+
+    /// Create a new directory for the intermediate files of one document,
+    /// named after `prefix`
+    pub fn create_dir(&self, prefix: &str) -> Result<ScratchDir> {
+        let mut state = self.lock();
+        let root = match state.root {
+            Some(ref root) => root.clone(),
+            None => {
+                let root = std::env::temp_dir().join(format!(
+                    "paperless-ngx-ocr2-{}-{}",
+                    std::process::id(),
+                    &uuid::Uuid::new_v4().simple().to_string()[..8]
+                ));
+                if state.keep {
+                    tracing::info!("Keeping temporary files in {}", root.display());
+                }
+                state.root = Some(root.clone());
+                root
+            }
+        };
+
+        let path = root.join(format!("{}-{}", prefix, uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&path).map_err(Error::Io)?;
+        Ok(ScratchDir {
+            path,
+            state: self.state.clone(),
+        })
+    }
+
+    /// Remove the run directory and everything in it, unless files are kept
+    pub fn cleanup(&self) {
+        let mut state = self.lock();
+        if state.keep {
+            return;
+        }
+        if let Some(root) = state.root.take() {
+            match std::fs::remove_dir_all(&root) {
+                Ok(()) => tracing::debug!("Removed {}", root.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("Failed to remove {}: {}", root.display(), e),
+            }
+        }
+    }
+}
+
+/// Removes the run directory when the run ends
+#[derive(Debug)]
+pub struct RunGuard {
+    temp: RunTemp,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.temp.cleanup();
+    }
+}
+
+/// Directory for the intermediate files of one document, removed when dropped
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    state: Arc<Mutex<State>>,
+}
+
+impl ScratchDir {
+    /// Path of the directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.keep {
+            return;
+        }
+        let _ = std::fs::remove_dir_all(&self.path);
+        // Only succeeds once the last document is done; recreated on next use
+        if let Some(root) = state.root.as_deref() {
+            let _ = std::fs::remove_dir(root);
+        }
+    }
+}
+
+/// Remove the run directory of `temp` and exit on SIGTERM, and on SIGINT when
+/// `interrupt` is set, until the listener is dropped
+///
+/// Commands that stop gracefully on Ctrl-C (`watch`, `serve`) leave SIGINT to
+/// their own handler; the run guard cleans up when they return. Must be called
+/// from within a Tokio runtime.
+pub fn cleanup_on_signal(temp: RunTemp, interrupt: bool) -> SignalListener {
+    #[cfg(unix)]
+    let terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        Ok(stream) => Some(stream),
+        Err(e) => {
+            tracing::warn!("Failed to install SIGTERM handler: {}", e);
+            None
+        }
+    };
+    #[cfg(not(unix))]
+    if !interrupt {
+        return SignalListener::default();
+    }
+
+    SignalListener::spawn(async move {
+        let interrupted = async {
+            if interrupt && tokio::signal::ctrl_c().await.is_ok() {
+                return INTERRUPTED;
+            }
+            std::future::pending().await
+        };
+        #[cfg(unix)]
+        let terminated = async move {
+            if let Some(mut stream) = terminate {
+                if stream.recv().await.is_some() {
+                    return TERMINATED;
+                }
+            }
+            std::future::pending().await
+        };
+        #[cfg(not(unix))]
+        let terminated = std::future::pending();
+
+        let code = tokio::select! {
+            code = interrupted => code,
+            code = terminated => code,
+        };
+        temp.cleanup();
+        std::process::exit(code);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_dirs_share_the_run_dir_and_are_removed() {
+        let temp = RunTemp::default();
+        let first = temp.create_dir("render").unwrap();
+        let second = temp.create_dir("upload").unwrap();
+        let root = temp.root().unwrap();
+        assert_eq!(first.path().parent(), Some(root.as_path()));
+        assert_eq!(second.path().parent(), Some(root.as_path()));
+        std::fs::write(first.path().join("page-1.png"), b"png").unwrap();
+
+        drop(first);
+        assert!(root.is_dir());
+        drop(second);
+        assert!(!root.exists());
+
+        // A leaked directory is removed with the run directory
+        let leaked = temp.create_dir("render").unwrap();
+        let root = temp.root().unwrap();
+        std::mem::forget(leaked);
+        temp.cleanup();
+        assert!(!root.exists());
+        assert!(temp.root().is_none());
+    }
+
+    #[test]
+    fn test_keep_temp_leaves_files() {
+        let temp = RunTemp::default();
+        temp.lock().keep = true;
+        let dir = temp.create_dir("render").unwrap();
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        temp.cleanup();
+        assert!(path.is_dir());
+
+        std::fs::remove_dir_all(temp.root().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,1044 @@
+//! HTTP server for OCR requests
+//!
+//! `serve` accepts documents over HTTP and answers with the same JSON as
+//! `--json` on the command line, so other services can use the OCR pipeline
+//! without spawning a process per document:
+//!
+//! ```text
+//! GET  /health                       -> {"status": "ok"}
+//! POST /ocr?filename=scan.pdf        -> CLIOutput JSON
+//! POST /jobs?filename=scan.pdf       -> 202 with the queued job
+//! GET  /jobs/<id>                    -> the job, with its CLIOutput JSON once finished
+//! ```
+//!
+//! The request body is the raw document. A `Content-Type` other than
+//! `application/octet-stream` is used as the MIME type instead of detecting it.
+//! `POST /ocr` holds the connection until the document is processed; `POST
+//! /jobs` stores it in the persisted queue (see [`crate::queue`]) and answers
+//! at once, and the result is polled or posted to the `callback` URL.
+//! With `[otel] enabled = true`, a `traceparent` header makes the request part
+//! of the caller's trace, and the API calls for the document carry it on.
+//! With `[[server.tokens]]` configured, `POST /ocr` and the job endpoints
+//! require a bearer token (see [`crate::access`]), whose tenant profile may
+//! select other credentials and models (see [`crate::tenant`]). Without
+//! tokens, the server only listens on loopback addresses unless `[server]
+//! allow_unauthenticated` is set.
+//!
+//! A flood of uploads is shed instead of queued in memory: beyond
+//! `[server] max_concurrent_uploads` uploads in progress, or
+//! `max_queue_depth` waiting jobs for `POST /jobs`, requests are answered with
+//! 429 and a `Retry-After` header.
+
+use crate::access::{Access, Grant, TokenAuth};
+use crate::cli::commands::{ocr_file, run_hooks, ProcessingOptions};
+use crate::cli::CLIOutput;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::queue::{JobQueue, QueuedJob};
+use crate::tenant::resolve_profiles;
+use crate::trace_context::{self, TraceContext};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// How often finished jobs past their retention are removed
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Settings shared by every request
+struct ServerState {
+    config: Config,
+    options: ProcessingOptions,
+    verbose: bool,
+    auth: TokenAuth,
+    profiles: BTreeMap<String, Config>,
+    queue: JobQueue,
+    callbacks: reqwest::Client,
+    uploads: Semaphore,
+}
+
+impl ServerState {
+    /// Configuration for the requests of a tenant profile, `None` for a
+    /// profile that is no longer configured
+    fn config_for(&self, profile: Option<&str>) -> Option<&Config> {
+        match profile {
+            Some(profile) => self.profiles.get(profile),
+            None => Some(&self.config),
+        }
+    }
+
+    /// Room for another upload, or the response shedding it
+    fn admit_upload(
+        &self,
+        queued: bool,
+    ) -> std::result::Result<SemaphorePermit<'_>, Box<Response<Body>>> {
+        let server = &self.config.server;
+        let retry_after = Duration::from_secs(server.retry_after_seconds);
+        if queued && self.queue.depth() >= server.max_queue_depth {
+            tracing::info!("Job queue full; rejecting upload");
+            return Err(Box::new(too_many_requests(
+                "Job queue is full",
+                retry_after,
+            )));
+        }
+        self.uploads.try_acquire().map_err(|_| {
+            tracing::info!("Too many requests in progress; rejecting upload");
+            Box::new(too_many_requests("Server is busy", retry_after))
+        })
+    }
+}
+
+/// OCR server bound to a local address
+pub struct OcrServer {
+    listener: TcpListener,
+    state: Arc<ServerState>,
+}
+
+impl OcrServer {
+    /// Bind to `address`; requests are processed with `config` and `options`
+    ///
+    /// Opens the job queue and queues the jobs a previous server did not
+    /// finish again.
+    pub fn bind(
+        address: SocketAddr,
+        config: Config,
+        options: ProcessingOptions,
+        verbose: bool,
+    ) -> Result<Self> {
+        let auth = TokenAuth::new(&config.server.tokens);
+        if !auth.is_enabled() && !address.ip().is_loopback() {
+            if !config.server.allow_unauthenticated {
+                return Err(Error::Config(format!(
+                    "Refusing to listen on {} without [[server.tokens]]; anyone who can reach it could run OCR on your account. Configure tokens, or pass --allow-unauthenticated or set [server] allow_unauthenticated = true to serve it open",
+                    address
+                )));
+            }
+            tracing::warn!(
+                "Listening on {} without [[server.tokens]]; anyone who can reach it can run OCR on your account",
+                address
+            );
+        }
+        let listener = TcpListener::bind(address).map_err(Error::Io)?;
+        listener.set_nonblocking(true).map_err(Error::Io)?;
+        let profiles = resolve_profiles(&config)?;
+
+        let queue_dir = config.server.queue_dir_path().ok_or_else(|| {
+            Error::Config(
+                "No state directory for the job queue; set [server] queue_dir".to_string(),
+            )
+        })?;
+        let mut queue = JobQueue::open(&queue_dir)?;
+        if config.encryption.is_enabled() {
+            queue = queue.keep_results_in_memory();
+        }
+        let uploads = Semaphore::new(config.server.max_concurrent_uploads);
+        let callbacks = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .user_agent(format!("paperless-ngx-ocr2/{}", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| Error::internal_from("Failed to create HTTP client", e))?;
+
+        Ok(Self {
+            listener,
+            state: Arc::new(ServerState {
+                config,
+                options,
+                verbose,
+                auth,
+                profiles,
+                queue,
+                callbacks,
+                uploads,
+            }),
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(Error::Io)
+    }
+
+    /// Serve requests and process queued jobs until `shutdown` completes
+    ///
+    /// A job still running at shutdown is queued again by the next server.
+    pub async fn run(self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        let state = self.state;
+        let mut tasks: Vec<_> = (0..state.config.server.workers)
+            .map(|_| tokio::spawn(work(state.clone())))
+            .collect();
+        tasks.push(tokio::spawn(prune(state.clone())));
+
+        let make_service = make_service_fn(move |_| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle(&state, request).await) }
+                }))
+            }
+        });
+
+        let served = match hyper::Server::from_tcp(self.listener) {
+            Ok(server) => server
+                .serve(make_service)
+                .with_graceful_shutdown(shutdown)
+                .await
+                .map_err(|e| Error::internal_from("Server failed", e)),
+            Err(e) => Err(Error::internal_from("Failed to start server", e)),
+        };
+        for task in tasks {
+            task.abort();
+        }
+        served
+    }
+}
+
+async fn handle(state: &ServerState, request: Request<Body>) -> Response<Body> {
+    let request_trace = inbound_trace(request.headers());
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let authorization = authorization.as_deref();
+    let path = request.uri().path().to_string();
+
+    match (request.method(), path.as_str()) {
+        (&Method::GET, "/health") => {
+            json_response(StatusCode::OK, &serde_json::json!({ "status": "ok" }))
+        }
+        (&Method::POST, "/ocr") => {
+            let client = match admit(state.auth.authorize(authorization)) {
+                Ok(client) => client,
+                Err(response) => return *response,
+            };
+            let config = client
+                .and_then(|client| state.config_for(client.profile))
+                .unwrap_or(&state.config);
+            let _permit = match state.admit_upload(false) {
+                Ok(permit) => permit,
+                Err(response) => {
+                    // A shed upload does not count against the client's limit
+                    state.auth.refund(client);
+                    return *response;
+                }
+            };
+            match traced(state, process(state, config, request), request_trace).await {
+                Ok(output) => json_response(StatusCode::OK, &output),
+                Err(UploadError { status, error }) => {
+                    tracing::warn!("OCR request failed: {}", error.user_message());
+                    json_response(status, &CLIOutput::from_error(&error))
+                }
+            }
+        }
+        (&Method::POST, "/jobs") => {
+            let client = match admit(state.auth.authorize(authorization)) {
+                Ok(client) => client,
+                Err(response) => return *response,
+            };
+            let permit = match state.admit_upload(true) {
+                Ok(permit) => permit,
+                Err(response) => {
+                    state.auth.refund(client);
+                    return *response;
+                }
+            };
+            let submitted = submit(state, client, request).await;
+            drop(permit);
+            match submitted {
+                Ok(job) => {
+                    let location = format!("/jobs/{}", job.id);
+                    let mut response = json_response(StatusCode::ACCEPTED, &job);
+                    if let Ok(value) = header::HeaderValue::from_str(&location) {
+                        response.headers_mut().insert(header::LOCATION, value);
+                    }
+                    response
+                }
+                Err(UploadError { status, error }) => {
+                    tracing::warn!("Job upload failed: {}", error.user_message());
+                    json_response(status, &CLIOutput::from_error(&error))
+                }
+            }
+        }
+        (&Method::GET, path) if path.starts_with("/jobs/") => {
+            // Polling does not count against the rate limit
+            let client = match admit(state.auth.identify(authorization)) {
+                Ok(client) => client,
+                Err(response) => return *response,
+            };
+            let id = &path["/jobs/".len()..];
+            match state.queue.get(id) {
+                // Other clients' jobs do not exist for the caller
+                Ok(Some(job)) if job.client.as_deref() == client.map(|client| client.name) => {
+                    json_response(StatusCode::OK, &job)
+                }
+                Ok(_) => json_response(
+                    StatusCode::NOT_FOUND,
+                    &serde_json::json!({ "message": format!("No job {}", id) }),
+                ),
+                Err(e) => json_response(error_status(&e), &CLIOutput::from_error(&e)),
+            }
+        }
+        _ => json_response(
+            StatusCode::NOT_FOUND,
+            &serde_json::json!({
+                "message": "Not found; use GET /health, POST /ocr, POST /jobs, or GET /jobs/<id>"
+            }),
+        ),
+    }
+}
+
+/// The client of a granted request, or the response refusing it
+fn admit(access: Access<'_>) -> std::result::Result<Option<Grant<'_>>, Box<Response<Body>>> {
+    match access {
+        Access::Granted(client) => {
+            if let Some(client) = client {
+                tracing::debug!("Request from {}", client.name);
+            }
+            Ok(client)
+        }
+        Access::Denied => Err(Box::new(unauthorized())),
+        Access::Limited(retry_after) => {
+            tracing::info!("Rate limit reached; retry after {:?}", retry_after);
+            Err(Box::new(too_many_requests(
+                "Rate limit reached",
+                retry_after,
+            )))
+        }
+    }
+}
+
+/// Trace context sent by the caller in `traceparent` and `tracestate`
+fn inbound_trace(headers: &header::HeaderMap) -> Option<TraceContext> {
+    let value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    TraceContext::from_headers(
+        value(trace_context::TRACEPARENT),
+        value(trace_context::TRACESTATE),
+    )
+}
+
+/// Process a request as a span of the caller's trace, or of a new trace
+/// without one, when tracing is enabled
+async fn traced<F: Future>(
+    state: &ServerState,
+    future: F,
+    parent: Option<TraceContext>,
+) -> F::Output {
+    if !state.config.otel.enabled {
+        return future.await;
+    }
+    let context = parent.map_or_else(TraceContext::new_root, |parent| parent.child());
+    tracing::debug!("OCR request in trace {}", context.trace_id_hex());
+    trace_context::scope(context, future).await
+}
+
+/// Store the uploaded document in a scratch directory and run the pipeline
+async fn process(
+    state: &ServerState,
+    config: &Config,
+    request: Request<Body>,
+) -> std::result::Result<serde_json::Value, UploadError> {
+    let filename = query_filename(request.uri().query()).unwrap_or_else(|| "upload".to_string());
+    let mime_type = upload_mime_type(request.headers());
+
+    let scratch = state.options.run.temp.create_dir("upload")?;
+    let path = scratch.path().join(&filename);
+    store_body(request.into_body(), &path, config.max_file_size_mb).await?;
+
+    Ok(run_pipeline(state, config, &path, mime_type).await?)
+}
+
+/// Stream a request body to `path`, so large uploads are not held in memory;
+/// a body over `max_file_size_mb` is refused with 413 without reading the rest,
+/// whether or not it announced its length
+async fn store_body(
+    mut body: Body,
+    path: &Path,
+    max_file_size_mb: u64,
+) -> std::result::Result<(), UploadError> {
+    let mut file = tokio::fs::File::create(path).await.map_err(Error::Io)?;
+    let max_bytes = max_file_size_mb * 1024 * 1024;
+    let mut size = 0;
+    while let Some(chunk) = body.data().await {
+        let chunk =
+            chunk.map_err(|e| Error::validation_from("Failed to read the request body", e))?;
+        size += chunk.len() as u64;
+        if size > max_bytes {
+            return Err(UploadError {
+                status: StatusCode::PAYLOAD_TOO_LARGE,
+                error: Error::validation(format!(
+                    "The document exceeds the maximum file size of {} MB",
+                    max_file_size_mb
+                )),
+            });
+        }
+        file.write_all(&chunk).await.map_err(Error::Io)?;
+    }
+    file.flush().await.map_err(Error::Io)?;
+    if size == 0 {
+        return Err(
+            Error::validation("The request body must contain the document".to_string()).into(),
+        );
+    }
+    Ok(())
+}
+
+/// A failed upload and the status it is answered with
+#[derive(Debug)]
+struct UploadError {
+    status: StatusCode,
+    error: Error,
+}
+
+impl From<Error> for UploadError {
+    fn from(error: Error) -> Self {
+        UploadError {
+            status: error_status(&error),
+            error,
+        }
+    }
+}
+
+/// Run the pipeline on a stored document and its hooks
+async fn run_pipeline(
+    state: &ServerState,
+    config: &Config,
+    path: &Path,
+    mime_type: Option<String>,
+) -> Result<serde_json::Value> {
+    let options = ProcessingOptions {
+        mime_type: mime_type.or_else(|| state.options.mime_type.clone()),
+        ..state.options.clone()
+    };
+    let result = ocr_file(&path.to_string_lossy(), config, &options, state.verbose).await;
+    run_hooks(
+        path,
+        result.as_ref().map(|result| (result, None)),
+        config,
+        &options,
+    )
+    .await;
+    Ok(result?.to_json_output())
+}
+
+/// Store an uploaded document in the queue and queue its job
+async fn submit(
+    state: &ServerState,
+    client: Option<Grant<'_>>,
+    request: Request<Body>,
+) -> std::result::Result<QueuedJob, UploadError> {
+    let query = request.uri().query();
+    let mut job = QueuedJob::new(query_filename(query).unwrap_or_else(|| "upload".to_string()));
+    job.mime_type = upload_mime_type(request.headers());
+    job.client = client.map(|client| client.name.to_string());
+    job.profile = client.and_then(|client| client.profile.map(str::to_string));
+    job.callback = query_param(query, "callback")
+        .map(|callback| check_callback(&callback))
+        .transpose()?;
+
+    let path = state.queue.document_path(&job);
+    let stored = async {
+        let dir = state.queue.job_dir(&job.id);
+        tokio::fs::create_dir_all(&dir).await.map_err(Error::Io)?;
+        store_body(request.into_body(), &path, state.config.max_file_size_mb).await?;
+        Ok(state.queue.submit(&job)?)
+    }
+    .await;
+
+    if let Err(e) = stored {
+        let _ = tokio::fs::remove_dir_all(state.queue.job_dir(&job.id)).await;
+        return Err(e);
+    }
+    tracing::info!("Queued job {} for {}", job.id, job.filename);
+    Ok(job)
+}
+
+/// Process queued jobs one after the other
+async fn work(state: Arc<ServerState>) {
+    loop {
+        let mut job = match state.queue.next().await {
+            Ok(job) => job,
+            Err(e) => {
+                tracing::error!("Failed to take a job: {}", e.user_message());
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let output = traced(&state, run_job(&state, &job), None).await;
+        let (succeeded, output) = match output {
+            Ok(output) => (true, output),
+            Err(e) => {
+                tracing::warn!("Job {} failed: {}", job.id, e.user_message());
+                let output = serde_json::to_value(CLIOutput::from_error(&e)).unwrap_or_default();
+                (false, output)
+            }
+        };
+        if let Err(e) = state.queue.finish(&mut job, succeeded, output) {
+            tracing::error!("Failed to store job {}: {}", job.id, e.user_message());
+            continue;
+        }
+        if let Some(ref callback) = job.callback {
+            post_callback(&state.callbacks, callback, &job).await;
+        }
+    }
+}
+
+async fn run_job(state: &ServerState, job: &QueuedJob) -> Result<serde_json::Value> {
+    // A job accepted before its profile was removed is not billed to anyone else
+    let config = state.config_for(job.profile.as_deref()).ok_or_else(|| {
+        Error::Config(format!(
+            "Profile '{}' is no longer configured",
+            job.profile.as_deref().unwrap_or_default()
+        ))
+    })?;
+    let mut output = run_pipeline(
+        state,
+        config,
+        &state.queue.document_path(job),
+        job.mime_type.clone(),
+    )
+    .await?;
+    // The document is stored under a fixed name; report the uploaded one
+    if let Some(file_name) = output
+        .get_mut("data")
+        .and_then(|data| data.get_mut("file_name"))
+    {
+        *file_name = job.filename.clone().into();
+    }
+    Ok(output)
+}
+
+/// Post a finished job to its callback URL; failures are only logged, the
+/// result can still be polled
+async fn post_callback(client: &reqwest::Client, url: &str, job: &QueuedJob) {
+    match client.post(url).json(job).send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => {
+            tracing::warn!("Callback for job {} answered {}", job.id, response.status())
+        }
+        Err(e) => tracing::warn!("Callback for job {} failed: {}", job.id, e),
+    }
+}
+
+/// Remove finished jobs past their retention
+async fn prune(state: Arc<ServerState>) {
+    let retention = Duration::from_secs(state.config.server.job_retention_hours * 3600);
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match state.queue.prune(retention) {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!("Removed {} expired jobs", removed),
+            Err(e) => tracing::warn!("Failed to remove expired jobs: {}", e.user_message()),
+        }
+    }
+}
+
+/// MIME type of an upload from its `Content-Type`, unless it is generic
+fn upload_mime_type(headers: &header::HeaderMap) -> Option<String> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or(value).trim().to_string())
+        .filter(|value| !value.is_empty() && value != "application/octet-stream")
+}
+
+/// Value of a query parameter
+fn query_param(query: Option<&str>, key: &str) -> Option<String> {
+    url::form_urlencoded::parse(query?.as_bytes())
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.to_string())
+}
+
+/// File name from `?filename=`, without any directory components
+fn query_filename(query: Option<&str>) -> Option<String> {
+    query_param(query, "filename").and_then(|value| {
+        Path::new(&value)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    })
+}
+
+/// Check a `?callback=` URL
+fn check_callback(callback: &str) -> Result<String> {
+    match url::Url::parse(callback) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(url.to_string()),
+        _ => Err(Error::validation(format!(
+            "Invalid callback URL '{}'; use an http or https URL",
+            callback
+        ))),
+    }
+}
+
+/// HTTP status for a failed request
+fn error_status(e: &Error) -> StatusCode {
+    match e {
+        Error::Validation { .. } | Error::InvalidArguments(_) => StatusCode::BAD_REQUEST,
+        Error::Http { status, .. } => match status {
+            400..=499 => StatusCode::BAD_REQUEST,
+            500..=599 => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+        Error::InsufficientText(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        Error::BudgetExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        Error::Api { .. } | Error::Parse { .. } | Error::Network(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// 401 for a request without a valid bearer token
+fn unauthorized() -> Response<Body> {
+    let mut response = json_response(
+        StatusCode::UNAUTHORIZED,
+        &serde_json::json!({ "message": "A valid bearer token is required" }),
+    );
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Bearer"),
+    );
+    response
+}
+
+/// 429 for a client over its rate limit or a busy server, with the seconds
+/// until it may retry
+fn too_many_requests(reason: &str, retry_after: Duration) -> Response<Body> {
+    let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = json_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        &serde_json::json!({
+            "message": format!("{}; retry in {} s", reason, seconds),
+        }),
+    );
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, header::HeaderValue::from(seconds));
+    response
+}
+
+fn json_response(status: StatusCode, body: &impl serde::Serialize) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_else(|_| b"{}".to_vec());
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("static response parts are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::TokenConfig;
+    use crate::provider::ProviderKind;
+    use crate::tenant::ProfileConfig;
+    use tempfile::TempDir;
+
+    /// Configuration with the mock provider and the job queue in `queue`
+    fn mock_config(queue: &TempDir) -> Config {
+        let mut config = Config {
+            api_key: String::new(),
+            providers: vec![ProviderKind::Mock],
+            ..Config::default()
+        };
+        config.server.queue_dir = Some(queue.path().to_path_buf());
+        config
+    }
+
+    /// State of a server that does not process jobs
+    fn state(queue: &TempDir, config: Config) -> ServerState {
+        ServerState {
+            uploads: Semaphore::new(config.server.max_concurrent_uploads),
+            config,
+            options: ProcessingOptions::default(),
+            verbose: false,
+            auth: TokenAuth::default(),
+            profiles: BTreeMap::new(),
+            queue: JobQueue::open(queue.path()).unwrap(),
+            callbacks: reqwest::Client::new(),
+        }
+    }
+
+    fn upload(path: &str) -> Request<Body> {
+        Request::post(path)
+            .body(Body::from(&b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"[..]))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_query_filename_strips_directories() {
+        assert_eq!(
+            query_filename(Some("filename=..%2F..%2Fscan.pdf")).as_deref(),
+            Some("scan.pdf")
+        );
+        assert_eq!(query_filename(Some("other=1")), None);
+        assert_eq!(query_filename(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_serves_health_and_ocr() {
+        let queue = TempDir::new().unwrap();
+        let config = mock_config(&queue);
+        let server = OcrServer::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            config,
+            ProcessingOptions::default(),
+            false,
+        )
+        .unwrap();
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(server.run(async {
+            let _ = stopped.await;
+        }));
+
+        let client = reqwest::Client::new();
+        let health = client.get(format!("{}/health", base)).send().await.unwrap();
+        assert_eq!(health.status(), 200);
+
+        let response = client
+            .post(format!("{}/ocr?filename=scan.png", base))
+            .body(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let output: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(output["success"], true);
+        assert_eq!(output["data"]["file_name"], "scan.png");
+
+        let empty = client.post(format!("{}/ocr", base)).send().await.unwrap();
+        assert_eq!(empty.status(), 400);
+
+        stop.send(()).unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_open_servers_only_listen_on_loopback_unless_allowed() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        let bind = |config: &Config| {
+            OcrServer::bind(
+                "0.0.0.0:0".parse().unwrap(),
+                config.clone(),
+                ProcessingOptions::default(),
+                false,
+            )
+        };
+
+        let refused = bind(&config).err().unwrap();
+        assert!(matches!(refused, Error::Config(_)));
+        assert_eq!(refused.exit_code(), 4);
+
+        config.server.allow_unauthenticated = true;
+        assert!(bind(&config).is_ok());
+
+        config.server.allow_unauthenticated = false;
+        config.server.tokens = vec![TokenConfig {
+            name: "home".to_string(),
+            token: "home-token-0123456789".to_string(),
+            requests_per_minute: None,
+            profile: None,
+        }];
+        assert!(bind(&config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ocr_requires_a_token_within_its_rate_limit() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        config.server.tokens = vec![TokenConfig {
+            name: "home".to_string(),
+            token: "home-token-0123456789".to_string(),
+            requests_per_minute: Some(1),
+            profile: None,
+        }];
+        let server = OcrServer::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            config,
+            ProcessingOptions::default(),
+            false,
+        )
+        .unwrap();
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(server.run(async {
+            let _ = stopped.await;
+        }));
+
+        let client = reqwest::Client::new();
+        let ocr = |token: &str| {
+            client
+                .post(format!("{}/ocr?filename=scan.png", base))
+                .bearer_auth(token)
+                .body(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec())
+                .send()
+        };
+        let health = client.get(format!("{}/health", base)).send().await.unwrap();
+        assert_eq!(health.status(), 200);
+
+        let denied = ocr("wrong-token-0123456789").await.unwrap();
+        assert_eq!(denied.status(), 401);
+        assert_eq!(denied.headers()["www-authenticate"], "Bearer");
+        assert_eq!(ocr("home-token-0123456789").await.unwrap().status(), 200);
+        let limited = ocr("home-token-0123456789").await.unwrap();
+        assert_eq!(limited.status(), 429);
+        let retry_after: u64 = limited.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        stop.send(()).unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_tokens_select_the_config_of_their_profile() {
+        let queue = TempDir::new().unwrap();
+        let mut config = Config {
+            api_key: "sk-shared".to_string(),
+            ..Config::default()
+        };
+        config.server.queue_dir = Some(queue.path().to_path_buf());
+        config.server.profiles.insert(
+            "office".to_string(),
+            ProfileConfig {
+                api_key: Some("sk-office".to_string()),
+                ..ProfileConfig::default()
+            },
+        );
+        let server = OcrServer::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            config.clone(),
+            ProcessingOptions::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            server.state.config_for(Some("office")).unwrap().api_key,
+            "sk-office"
+        );
+        assert_eq!(server.state.config_for(None).unwrap().api_key, "sk-shared");
+        assert!(server.state.config_for(Some("home")).is_none());
+
+        config.server.profiles.get_mut("office").unwrap().api_key = Some(String::new());
+        let unbound = OcrServer::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            config,
+            ProcessingOptions::default(),
+            false,
+        );
+        assert_eq!(unbound.err().unwrap().exit_code(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_queued_and_polled() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        config.server.tokens = ["home", "office"]
+            .iter()
+            .map(|name| TokenConfig {
+                name: name.to_string(),
+                token: format!("{}-token-0123456789", name),
+                requests_per_minute: None,
+                profile: None,
+            })
+            .collect();
+        let server = OcrServer::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            config,
+            ProcessingOptions::default(),
+            false,
+        )
+        .unwrap();
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(server.run(async {
+            let _ = stopped.await;
+        }));
+
+        let client = reqwest::Client::new();
+        let accepted = client
+            .post(format!("{}/jobs?filename=scan.png", base))
+            .bearer_auth("home-token-0123456789")
+            .body(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(accepted.status(), 202);
+        let location = accepted.headers()["location"].to_str().unwrap().to_string();
+        let job: QueuedJob = accepted.json().await.unwrap();
+        assert_eq!(location, format!("/jobs/{}", job.id));
+        assert_eq!(job.client.as_deref(), Some("home"));
+
+        let poll = |token: &'static str| {
+            client
+                .get(format!("{}{}", base, location))
+                .bearer_auth(token)
+                .send()
+        };
+        let mut finished = None;
+        for _ in 0..100 {
+            let job: QueuedJob = poll("home-token-0123456789")
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if job.state.is_finished() {
+                finished = Some(job);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let finished = finished.expect("the job did not finish");
+        assert_eq!(finished.state, crate::queue::JobState::Succeeded);
+        let result = finished.result.unwrap();
+        assert_eq!(result["success"], true);
+        assert_eq!(result["data"]["file_name"], "scan.png");
+
+        // Jobs of other clients are not found
+        assert_eq!(poll("office-token-0123456789").await.unwrap().status(), 404);
+        let invalid = client
+            .post(format!("{}/jobs?callback=ftp://example.com", base))
+            .bearer_auth("home-token-0123456789")
+            .body(b"\x89PNG\r\n\x1a\n".to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), 400);
+
+        stop.send(()).unwrap();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_uploads_are_shed_when_the_server_is_busy() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        config.server.max_concurrent_uploads = 1;
+        config.server.max_queue_depth = 2;
+        config.server.retry_after_seconds = 15;
+        let state = state(&queue, config);
+
+        // An upload in progress takes the only slot
+        let permit = state.uploads.try_acquire().unwrap();
+        for path in ["/ocr", "/jobs"] {
+            let busy = handle(&state, upload(path)).await;
+            assert_eq!(busy.status(), 429);
+            assert_eq!(busy.headers()["retry-after"], "15");
+        }
+        drop(permit);
+
+        // Without workers, jobs stay queued until the queue is full
+        for _ in 0..2 {
+            assert_eq!(handle(&state, upload("/jobs")).await.status(), 202);
+        }
+        assert_eq!(handle(&state, upload("/jobs")).await.status(), 429);
+        assert_eq!(state.queue.depth(), 2);
+
+        // Spawned, since the pipeline's future is too large for the test
+        // thread's stack
+        let state = Arc::new(state);
+        let processed = tokio::spawn(async move { handle(&state, upload("/ocr")).await })
+            .await
+            .unwrap();
+        assert_eq!(processed.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_shed_uploads_do_not_use_up_the_rate_limit() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        config.server.max_concurrent_uploads = 1;
+        let tokens = [TokenConfig {
+            name: "home".to_string(),
+            token: "home-token-0123456789".to_string(),
+            requests_per_minute: Some(1),
+            profile: None,
+        }];
+        let state = ServerState {
+            auth: TokenAuth::new(&tokens),
+            ..state(&queue, config)
+        };
+        let authorized = |path: &str| {
+            let mut request = upload(path);
+            request.headers_mut().insert(
+                header::AUTHORIZATION,
+                header::HeaderValue::from_static("Bearer home-token-0123456789"),
+            );
+            request
+        };
+
+        let permit = state.uploads.try_acquire().unwrap();
+        for path in ["/ocr", "/jobs"] {
+            let busy = handle(&state, authorized(path)).await;
+            assert_eq!(busy.status(), 429);
+        }
+        drop(permit);
+
+        // The one request of the minute is still available
+        assert_eq!(handle(&state, authorized("/jobs")).await.status(), 202);
+        let limited = handle(&state, authorized("/jobs")).await;
+        assert_eq!(limited.status(), 429);
+        assert_eq!(state.queue.depth(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_over_the_size_limit_are_refused() {
+        let queue = TempDir::new().unwrap();
+        let mut config = mock_config(&queue);
+        config.max_file_size_mb = 1;
+        let state = state(&queue, config);
+
+        for path in ["/ocr", "/jobs"] {
+            // Chunked, without a Content-Length
+            let (mut sender, body) = Body::channel();
+            let chunks = tokio::spawn(async move {
+                for _ in 0..3 {
+                    if sender.send_data(vec![0; 512 * 1024].into()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            let request = Request::post(path).body(body).unwrap();
+            assert_eq!(handle(&state, request).await.status(), 413);
+            chunks.await.unwrap();
+        }
+        assert_eq!(state.queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_requests_continue_the_callers_trace() {
+        let queue = TempDir::new().unwrap();
+        let mut state = state(&queue, Config::default());
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            trace_context::TRACEPARENT,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let parent = inbound_trace(&headers);
+        assert!(parent.is_some());
+
+        let current = || async { trace_context::current() };
+        assert!(traced(&state, current(), parent.clone()).await.is_none());
+
+        state.config.otel.enabled = true;
+        let context = traced(&state, current(), parent.clone()).await.unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(Some(&context), parent.as_ref());
+        assert!(traced(&state, current(), None).await.is_some());
+    }
+}
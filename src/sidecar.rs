@@ -0,0 +1,174 @@
+//! Sidecar files for document management systems
+//!
+//! With `--sidecar`, the OCR result is written to `<document>.json` next to the
+//! input (e.g. `scan.pdf` -> `scan.pdf.json`). The flat layout carries the text,
+//! the paperless-ngx checksum, SHA-256, page count, detected language, and
+//! timings, and is also accepted by the local result store (`eval --stored`,
+//! `export`). `--compress-output` gzips the sidecar to `<document>.json.gz`, and
+//! with output encryption an `.age` suffix is appended after that.
+
+use crate::compress::{gzip, GZIP_SUFFIX};
+use crate::encrypt::{encrypt, EncryptionConfig};
+use crate::error::{Error, Result};
+use crate::ocr::{Embedding, OCRResult, Timings};
+use crate::paperless::file_checksum;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Contents of a sidecar file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sidecar {
+    /// Original file name
+    pub file_name: String,
+
+    /// Extracted text
+    pub extracted_text: String,
+
+    /// MD5 checksum of the original file, as stored by paperless-ngx
+    pub checksum: String,
+
+    /// SHA-256 of the original file
+    pub sha256: Option<String>,
+
+    /// Number of pages processed
+    pub page_count: Option<i64>,
+
+    /// Detected ISO 639-3 language code of the text (e.g. "eng", "deu")
+    pub language: Option<String>,
+
+    /// Model used for OCR processing
+    pub model: String,
+
+    /// Provider that produced the text
+    pub provider: Option<String>,
+
+    /// When OCR was performed
+    pub created: DateTime<Utc>,
+
+    /// Measured processing times
+    pub timings: Option<Timings>,
+
+    /// Embedding of the extracted text (with `--embed`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
+}
+
+impl Sidecar {
+    /// Build a sidecar from an OCR result and the original file contents
+    pub fn from_result(result: &OCRResult, original: &[u8]) -> Self {
+        Self {
+            file_name: result.file_name.clone(),
+            extracted_text: result.extracted_text.clone(),
+            checksum: file_checksum(original),
+            sha256: result.sha256.clone(),
+            page_count: result
+                .usage
+                .as_ref()
+                .and_then(|usage| usage.get("pages_processed").copied()),
+            language: detect_language(&result.extracted_text),
+            model: result.model.clone(),
+            provider: result.provider.clone(),
+            created: result.timestamp,
+            timings: result.timings,
+            embedding: result.embedding.clone(),
+        }
+    }
+}
+
+/// Path of the sidecar for an input file (`scan.pdf` -> `scan.pdf.json`)
+pub fn sidecar_path(input: &Path) -> PathBuf {
+    let mut path = input.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Path of the sidecar as written, with the `.gz` and `.age` suffixes of
+/// compressed and encrypted sidecars
+pub fn written_sidecar_path(input: &Path, compress: bool, encrypt: bool) -> PathBuf {
+    let mut path = sidecar_path(input);
+    if compress {
+        path.as_mut_os_string().push(GZIP_SUFFIX);
+    }
+    if encrypt {
+        path.as_mut_os_string().push(".age");
+    }
+    path
+}
+
+/// Write the sidecar next to the input file and return its path
+///
+/// Compressed sidecars get a `.gz` suffix. With encryption enabled the sidecar
+/// is encrypted after compression and gets an `.age` suffix.
+pub async fn write_sidecar(
+    input: &Path,
+    result: &OCRResult,
+    compress: bool,
+    encryption: &EncryptionConfig,
+) -> Result<PathBuf> {
+    let original = fs::read(input).map_err(Error::Io)?;
+    let sidecar = Sidecar::from_result(result, &original);
+    let path = written_sidecar_path(input, compress, encryption.is_enabled());
+
+    let mut content = serde_json::to_vec_pretty(&sidecar)
+        .map_err(|e| Error::internal_from("Failed to serialize sidecar", e))?;
+    if compress {
+        content = gzip(&content)?;
+    }
+    if encryption.is_enabled() {
+        content = encrypt(&content, encryption, false).await?;
+    }
+    fs::write(&path, content).map_err(Error::Io)?;
+
+    Ok(path)
+}
+
+/// Detect the language of the text; None when it cannot be determined reliably
+pub fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path_appends_json() {
+        assert_eq!(
+            sidecar_path(Path::new("/docs/scan.pdf")),
+            PathBuf::from("/docs/scan.pdf.json")
+        );
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language(
+                "Sehr geehrte Damen und Herren, anbei erhalten Sie die Rechnung für den Monat März."
+            )
+            .as_deref(),
+            Some("deu")
+        );
+        assert_eq!(detect_language(""), None);
+    }
+
+    #[test]
+    fn test_sidecar_from_result() {
+        let mut result = OCRResult::new(
+            "Invoice".to_string(),
+            "file-1".to_string(),
+            "mistral-ocr-latest".to_string(),
+            "scan.pdf".to_string(),
+            3,
+        );
+        result.usage = Some([("pages_processed".to_string(), 2)].into_iter().collect());
+
+        let sidecar = Sidecar::from_result(&result, b"abc");
+        assert_eq!(sidecar.checksum, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(sidecar.page_count, Some(2));
+        assert_eq!(sidecar.file_name, "scan.pdf");
+    }
+}
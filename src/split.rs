@@ -0,0 +1,287 @@
+//! Page subsets of PDFs
+//!
+//! Mixed PDFs, with digital-born and scanned pages, only need OCR for the
+//! scanned pages. Those pages are copied into a PDF of their own with poppler's
+//! `pdfseparate` and `pdfunite`, so a provider is only sent (and billed for)
+//! what the text layer lacks:
+//!
+//! ```toml
+//! [split]
+//! separate_command = "pdfseparate"
+//! unite_command = "pdfunite"
+//! info_command = "pdfinfo"
+//! chunk_pages = 20
+//! ```
+//!
+//! With `chunk_pages`, longer PDFs are also split into chunks of that many
+//! pages, which providers process concurrently; the pages are put back in
+//! order afterwards.
+
+use crate::error::{Error, FieldErrors, Result};
+use crate::scratch::{RunTemp, ScratchDir};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Settings for copying pages out of PDFs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitConfig {
+    /// Poppler `pdfseparate` executable
+    #[serde(default = "default_separate_command")]
+    pub separate_command: String,
+
+    /// Poppler `pdfunite` executable
+    #[serde(default = "default_unite_command")]
+    pub unite_command: String,
+
+    /// Poppler `pdfinfo` executable, for counting pages
+    #[serde(default = "default_info_command")]
+    pub info_command: String,
+
+    /// Pages per chunk of long PDFs; unset sends PDFs whole
+    #[serde(default)]
+    pub chunk_pages: Option<usize>,
+}
+
+fn default_separate_command() -> String {
+    "pdfseparate".to_string()
+}
+
+fn default_unite_command() -> String {
+    "pdfunite".to_string()
+}
+
+fn default_info_command() -> String {
+    "pdfinfo".to_string()
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        Self {
+            separate_command: default_separate_command(),
+            unite_command: default_unite_command(),
+            info_command: default_info_command(),
+            chunk_pages: None,
+        }
+    }
+}
+
+impl SplitConfig {
+    /// Validate the split settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.separate_command.trim().is_empty() {
+            errors.add("separate_command", "Separate command must not be empty");
+        }
+        if self.unite_command.trim().is_empty() {
+            errors.add("unite_command", "Unite command must not be empty");
+        }
+        if self.info_command.trim().is_empty() {
+            errors.add("info_command", "Info command must not be empty");
+        }
+        if self.chunk_pages == Some(0) {
+            errors.add("chunk_pages", "Chunk pages must be at least 1");
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// A PDF with some pages of another, in the run's temporary directory and
+/// removed on drop
+#[derive(Debug)]
+pub struct PdfPages {
+    _dir: ScratchDir,
+    path: PathBuf,
+    pages: Vec<usize>,
+}
+
+impl PdfPages {
+    /// Path of the PDF
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Indices of the pages in the original PDF, in order
+    pub fn pages(&self) -> &[usize] {
+        &self.pages
+    }
+}
+
+/// Copy the pages with the given indices (0-based, ascending) into a new PDF
+/// named like the original, in the run directory of `temp`
+pub async fn extract_pages(
+    pdf: &Path,
+    pages: &[usize],
+    config: &SplitConfig,
+    temp: &RunTemp,
+) -> Result<PdfPages> {
+    if pages.is_empty() {
+        return Err(Error::validation("No pages to extract".to_string()));
+    }
+    let dir = temp.create_dir("split")?;
+
+    // One pdfseparate run per run of consecutive pages
+    for (first, last) in ranges(pages) {
+        run(
+            &config.separate_command,
+            tokio::process::Command::new(&config.separate_command)
+                .args(["-f", &(first + 1).to_string()])
+                .args(["-l", &(last + 1).to_string()])
+                .arg(pdf)
+                .arg(dir.path().join("page-%d.pdf")),
+        )
+        .await?;
+    }
+
+    let name = pdf
+        .file_name()
+        .map_or_else(|| "pages.pdf".into(), |name| name.to_os_string());
+    let path = dir.path().join(name);
+    run(
+        &config.unite_command,
+        tokio::process::Command::new(&config.unite_command)
+            .args(
+                pages
+                    .iter()
+                    .map(|index| dir.path().join(format!("page-{}.pdf", index + 1))),
+            )
+            .arg(&path),
+    )
+    .await?;
+
+    Ok(PdfPages {
+        _dir: dir,
+        path,
+        pages: pages.to_vec(),
+    })
+}
+
+/// Number of pages of a PDF
+pub async fn page_count(pdf: &Path, config: &SplitConfig) -> Result<usize> {
+    let output = run(
+        &config.info_command,
+        tokio::process::Command::new(&config.info_command).arg(pdf),
+    )
+    .await?;
+    parse_page_count(&String::from_utf8_lossy(&output)).ok_or_else(|| {
+        Error::api(format!(
+            "{} reported no page count for {}",
+            config.info_command,
+            pdf.display()
+        ))
+    })
+}
+
+/// Page indices of each chunk of a PDF with `page_count` pages
+pub fn chunks(page_count: usize, chunk_pages: usize) -> Vec<Vec<usize>> {
+    (0..page_count)
+        .collect::<Vec<_>>()
+        .chunks(chunk_pages.max(1))
+        .map(<[usize]>::to_vec)
+        .collect()
+}
+
+/// The `Pages:` line of `pdfinfo` output
+fn parse_page_count(info: &str) -> Option<usize> {
+    info.lines()
+        .find_map(|line| line.strip_prefix("Pages:"))
+        .and_then(|count| count.trim().parse().ok())
+}
+
+/// First and last index of each run of consecutive pages
+fn ranges(pages: &[usize]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &page in pages {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == page => *last = page,
+            _ => ranges.push((page, page)),
+        }
+    }
+    ranges
+}
+
+/// Run a command and return its output
+async fn run(name: &str, command: &mut tokio::process::Command) -> Result<Vec<u8>> {
+    let output = command
+        .output()
+        .await
+        .map_err(|e| Error::Config(format!("Failed to run PDF split command '{}': {}", name, e)))?;
+
+    if !output.status.success() {
+        return Err(Error::api(format!(
+            "{} exited with {}: {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(ranges(&[0, 1, 2, 5, 7, 8]), [(0, 2), (5, 5), (7, 8)]);
+        assert!(ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_chunks_and_page_count() {
+        assert_eq!(chunks(5, 2), [vec![0, 1], vec![2, 3], vec![4]]);
+        assert_eq!(chunks(2, 20), [vec![0, 1]]);
+        assert_eq!(
+            parse_page_count("Producer:       Scanner\nPages:          200\nEncrypted:      no\n"),
+            Some(200)
+        );
+        assert_eq!(parse_page_count("Pages: many"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extract_pages_unites_the_separated_pages() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        // Stand-ins for poppler: one file per page, then concatenated
+        let separate = dir.path().join("pdfseparate");
+        std::fs::write(
+            &separate,
+            "#!/bin/sh\nfor n in $(seq \"$2\" \"$4\"); do printf \"[$n]\" > \"$(printf \"$6\" \"$n\")\"; done\n",
+        )
+        .unwrap();
+        let unite = dir.path().join("pdfunite");
+        std::fs::write(
+            &unite,
+            "#!/bin/sh\nfor out; do :; done\nwhile [ $# -gt 1 ]; do cat \"$1\"; shift; done > \"$out\"\n",
+        )
+        .unwrap();
+        for command in [&separate, &unite] {
+            std::fs::set_permissions(command, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let config = SplitConfig {
+            separate_command: separate.to_string_lossy().to_string(),
+            unite_command: unite.to_string_lossy().to_string(),
+            ..SplitConfig::default()
+        };
+        let pdf = extract_pages(
+            Path::new("in/scan.pdf"),
+            &[1, 2, 4],
+            &config,
+            &RunTemp::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(pdf.pages(), [1, 2, 4]);
+        assert_eq!(pdf.path().file_name().unwrap(), "scan.pdf");
+        assert_eq!(std::fs::read_to_string(pdf.path()).unwrap(), "[2][3][5]");
+
+        let path = pdf.path().to_path_buf();
+        drop(pdf);
+        assert!(!path.exists());
+    }
+}
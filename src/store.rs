@@ -0,0 +1,322 @@
+//! Local result store for previously extracted OCR text
+//!
+//! A result store is a directory tree of JSON documents produced by this tool,
+//! either the `--json` CLI output saved to disk or sidecar files written next to
+//! the original documents. Both shapes are accepted so existing archives can be
+//! re-used without conversion, as are gzip-compressed files (`.json.gz`).
+
+use crate::compress::{gunzip, is_gzip_path};
+use crate::error::{Error, Result};
+use crate::ocr::Embedding;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single OCR result loaded from the store
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    /// Path of the JSON file the result was loaded from
+    pub result_path: PathBuf,
+
+    /// Original file name the text was extracted from
+    pub file_name: String,
+
+    /// Extracted text
+    pub extracted_text: String,
+
+    /// Path to the original document if it could be located next to the result
+    pub original_path: Option<PathBuf>,
+
+    /// Embedding of the extracted text, when it was stored with `--embed`
+    pub embedding: Option<Embedding>,
+
+    /// SHA-256 of the original file, when it was stored
+    pub sha256: Option<String>,
+
+    /// Provider that produced the text
+    pub provider: Option<String>,
+
+    /// Model used for OCR processing
+    pub model: Option<String>,
+
+    /// Number of pages of the document, when it was stored
+    pub page_count: Option<u64>,
+}
+
+impl StoredResult {
+    /// Parse a stored result from its JSON representation
+    ///
+    /// Returns `Ok(None)` for JSON documents that are not successful OCR results
+    /// (error outputs, unrelated files) so they can be skipped while walking.
+    pub fn from_json(result_path: &Path, value: &Value) -> Result<Option<Self>> {
+        // CLI output wraps the payload in {"success": true, "data": {...}}
+        let data = match value.get("data") {
+            Some(data) => {
+                if value.get("success").and_then(Value::as_bool) != Some(true) {
+                    return Ok(None);
+                }
+                data
+            }
+            None => value,
+        };
+
+        let (Some(file_name), Some(extracted_text)) = (
+            data.get("file_name").and_then(Value::as_str),
+            data.get("extracted_text").and_then(Value::as_str),
+        ) else {
+            return Ok(None);
+        };
+
+        if file_name.is_empty() {
+            return Err(Error::validation(format!(
+                "Stored result has an empty file name: {}",
+                result_path.display()
+            )));
+        }
+
+        let original_path = Self::locate_original(result_path, file_name);
+        let embedding = data
+            .get("embedding")
+            .and_then(|embedding| serde_json::from_value(embedding.clone()).ok());
+        let string = |field: &str| data.get(field).and_then(Value::as_str).map(str::to_string);
+
+        Ok(Some(Self {
+            result_path: result_path.to_path_buf(),
+            file_name: file_name.to_string(),
+            extracted_text: extracted_text.to_string(),
+            original_path,
+            embedding,
+            sha256: string("sha256"),
+            provider: string("provider"),
+            model: string("model"),
+            page_count: data.get("page_count").and_then(Value::as_u64),
+        }))
+    }
+
+    /// Title paperless-ngx assigns by default (file name without extension)
+    pub fn default_title(&self) -> String {
+        Path::new(&self.file_name)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&self.file_name)
+            .to_string()
+    }
+
+    /// Look for the original document in the directory of the result file
+    fn locate_original(result_path: &Path, file_name: &str) -> Option<PathBuf> {
+        let candidate = result_path.parent()?.join(file_name);
+        candidate.is_file().then_some(candidate)
+    }
+}
+
+/// Directory-backed store of OCR results
+#[derive(Debug, Clone)]
+pub struct ResultStore {
+    root: PathBuf,
+}
+
+impl ResultStore {
+    /// Create a store rooted at the given directory
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Get the store root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Walk the store and load every OCR result it contains
+    ///
+    /// Files that cannot be read or are not valid results are logged and
+    /// skipped, so one bad file does not stop an export of the whole archive.
+    pub fn load_all(&self) -> Result<Vec<StoredResult>> {
+        let mut results = Vec::new();
+        for path in self.result_files()? {
+            let mut content = match fs::read(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable result {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if is_gzip_path(&path) {
+                content = match gunzip(&content) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        tracing::warn!("Skipping unreadable result {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+            }
+            let value: Value = match serde_json::from_slice(&content) {
+                Ok(value) => value,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable result {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            match StoredResult::from_json(&path, &value) {
+                Ok(Some(result)) => results.push(result),
+                Ok(None) => tracing::debug!("Skipping non-result JSON file: {}", path.display()),
+                Err(e) => tracing::warn!("Skipping invalid result: {}", e.user_message()),
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Every `.json` and `.json.gz` file below the store root, sorted
+    ///
+    /// Symbolic links to directories are not followed, so a link back up the
+    /// tree cannot make the walk loop.
+    pub fn result_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.root.is_dir() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Result store directory not found: {}", self.root.display()),
+            )));
+        }
+
+        let mut files = Vec::new();
+        Self::collect_json_files(&self.root, &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// Recursively collect `.json` and `.json.gz` files below a directory
+    fn collect_json_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir).map_err(Error::Io)? {
+            let entry = entry.map_err(Error::Io)?;
+            let path = entry.path();
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            // The type of the entry itself; a symlink is not resolved
+            let file_type = entry.file_type().map_err(Error::Io)?;
+            if file_type.is_dir() {
+                if let Err(e) = Self::collect_json_files(&path, files) {
+                    tracing::warn!("Skipping unreadable directory {}: {}", path.display(), e);
+                }
+            } else if file_type.is_symlink() && path.is_dir() {
+                tracing::debug!("Not following symlinked directory {}", path.display());
+            } else if name.ends_with(".json") || name.ends_with(".json.gz") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_cli_output_and_sidecar_shapes() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("invoice.json"),
+            r#"{"success": true, "data": {"extracted_text": "Invoice 42", "file_name": "invoice.pdf", "embedding": {"model": "mistral-embed", "vector": [0.25, -0.5]}}}"#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("letter.pdf"), b"%PDF-1.4").unwrap();
+        fs::write(
+            dir.path().join("nested").join("letter.pdf.json"),
+            r#"{"extracted_text": "Dear Sir", "file_name": "letter.pdf", "sha256": "abc", "provider": "mistral", "model": "mistral-ocr-latest"}"#,
+        )
+        .unwrap();
+
+        let results = ResultStore::new(dir.path()).load_all().unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].file_name, "invoice.pdf");
+        assert_eq!(results[0].default_title(), "invoice");
+        assert!(results[0].original_path.is_none());
+        assert_eq!(results[0].embedding.as_ref().unwrap().vector, [0.25, -0.5]);
+        assert!(results[1].embedding.is_none());
+        assert_eq!(results[1].extracted_text, "Dear Sir");
+        assert!(results[1].original_path.is_some());
+        assert_eq!(results[1].sha256.as_deref(), Some("abc"));
+        assert_eq!(results[1].provider.as_deref(), Some("mistral"));
+    }
+
+    #[test]
+    fn test_skips_error_outputs_and_invalid_json() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("failed.json"),
+            r#"{"success": false, "error": {"type": "api", "message": "boom"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("broken.json"), "{not json").unwrap();
+
+        let results = ResultStore::new(dir.path()).load_all().unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_loads_compressed_sidecars() {
+        let dir = TempDir::new().unwrap();
+        let sidecar = br#"{"extracted_text": "Page 1", "file_name": "book.pdf"}"#;
+        fs::write(
+            dir.path().join("book.pdf.json.gz"),
+            crate::compress::gzip(sidecar).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt.gz"), b"ignored").unwrap();
+
+        let results = ResultStore::new(dir.path()).load_all().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].extracted_text, "Page 1");
+    }
+
+    #[test]
+    fn test_skips_invalid_results() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("unnamed.json"),
+            r#"{"extracted_text": "Page 1", "file_name": ""}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("scan.pdf.json"),
+            r#"{"extracted_text": "Page 1", "file_name": "scan.pdf"}"#,
+        )
+        .unwrap();
+
+        let results = ResultStore::new(dir.path()).load_all().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_name, "scan.pdf");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directories_are_not_followed() {
+        let dir = TempDir::new().unwrap();
+        let nested = dir.path().join("2024");
+        fs::create_dir(&nested).unwrap();
+        fs::write(
+            nested.join("scan.pdf.json"),
+            r#"{"extracted_text": "Page 1", "file_name": "scan.pdf"}"#,
+        )
+        .unwrap();
+        // A link back to the root would otherwise be walked forever
+        std::os::unix::fs::symlink(dir.path(), nested.join("loop")).unwrap();
+
+        let files = ResultStore::new(dir.path()).result_files().unwrap();
+        assert_eq!(files, vec![nested.join("scan.pdf.json")]);
+    }
+
+    #[test]
+    fn test_missing_store_directory() {
+        let result = ResultStore::new("/nonexistent/result/store").load_all();
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,323 @@
+//! End-of-run summary
+//!
+//! Every invocation ends with one line on stderr, e.g.
+//! `Summary: 3 files (1 failed), 12 pages, 0 cache hits, 2 retries in 4.2s`,
+//! unless `--quiet` is given. Commands that process several files also
+//! include the same numbers as a `summary` object in their JSON output.
+
+use crate::cache::CacheManager;
+use crate::metrics::MetricsCollector;
+use crate::output::{count, Styler};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Most file records kept for the e-mail report; later files are only counted
+const MAX_RECORDS: usize = 10_000;
+
+/// Outcome of one file in the current run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileRecord {
+    /// Input file
+    pub file: String,
+
+    /// Pages extracted
+    pub pages: u64,
+
+    /// Why the file failed, None on success
+    pub error: Option<String>,
+}
+
+/// Counters for the files handled by the current run; clones share them
+#[derive(Debug, Clone, Default)]
+pub struct RunCounters {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    started: Mutex<Instant>,
+    files: AtomicU64,
+    failed: AtomicU64,
+    skipped: AtomicU64,
+    pages: AtomicU64,
+    cache_hits: AtomicU64,
+    /// Whether per-file records are kept, only for the e-mail report
+    keep_records: AtomicBool,
+    records: Mutex<Vec<FileRecord>>,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            started: Mutex::new(Instant::now()),
+            files: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+            pages: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            keep_records: AtomicBool::new(false),
+            records: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl RunCounters {
+    /// Create counters starting now
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restart the run clock and clear the counters
+    pub fn start(&self) {
+        *lock(&self.counters.started) = Instant::now();
+        self.counters.files.store(0, Ordering::Relaxed);
+        self.counters.failed.store(0, Ordering::Relaxed);
+        self.counters.skipped.store(0, Ordering::Relaxed);
+        self.counters.pages.store(0, Ordering::Relaxed);
+        self.counters.cache_hits.store(0, Ordering::Relaxed);
+        lock(&self.counters.records).clear();
+    }
+
+    /// Keep a record of each file for [`RunCounters::take_records`]; off by
+    /// default so long `watch` and `serve` runs do not accumulate them
+    pub fn keep_records(&self, keep: bool) {
+        self.counters.keep_records.store(keep, Ordering::Relaxed);
+    }
+
+    /// Record a successfully processed file
+    pub fn record_file(&self, file: &str, pages: u64) {
+        self.counters.files.fetch_add(1, Ordering::Relaxed);
+        self.counters.pages.fetch_add(pages, Ordering::Relaxed);
+        self.push_record(FileRecord {
+            file: file.to_string(),
+            pages,
+            error: None,
+        });
+    }
+
+    /// Record a file that could not be processed
+    pub fn record_failure(&self, file: &str, error: &str) {
+        self.counters.files.fetch_add(1, Ordering::Relaxed);
+        self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        self.push_record(FileRecord {
+            file: file.to_string(),
+            pages: 0,
+            error: Some(error.to_string()),
+        });
+    }
+
+    /// Record a file that was left alone, such as a stored result without a
+    /// matching paperless-ngx document; it is not counted as processed
+    pub fn record_skip(&self) {
+        self.counters.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a result answered from the persistent result cache
+    pub fn record_cache_hit(&self) {
+        self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn push_record(&self, record: FileRecord) {
+        if !self.counters.keep_records.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut records = lock(&self.counters.records);
+        if records.len() < MAX_RECORDS {
+            records.push(record);
+        }
+    }
+
+    /// Outcomes of the files handled since the last call, in completion
+    /// order, up to the first 10,000
+    pub fn take_records(&self) -> Vec<FileRecord> {
+        std::mem::take(&mut *lock(&self.counters.records))
+    }
+
+    /// Files handled so far, including failed ones
+    pub fn files(&self) -> u64 {
+        self.counters.files.load(Ordering::Relaxed)
+    }
+
+    /// Pages extracted so far
+    pub fn pages(&self) -> u64 {
+        self.counters.pages.load(Ordering::Relaxed)
+    }
+
+    /// Combine the counters with the run's cache and API metrics
+    pub async fn summary(&self, metrics: &MetricsCollector, cache: &CacheManager) -> RunSummary {
+        let started = *lock(&self.counters.started);
+        let cache = cache.get_stats().await;
+
+        RunSummary {
+            files: self.counters.files.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            skipped: self.counters.skipped.load(Ordering::Relaxed),
+            pages: self.counters.pages.load(Ordering::Relaxed),
+            cache_hits: cache.file_upload_cache.hits
+                + cache.ocr_result_cache.hits
+                + self.counters.cache_hits.load(Ordering::Relaxed),
+            retries: metrics.get_metrics().await.total_retries,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Totals of a run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    /// Files processed, including failed ones
+    pub files: u64,
+
+    /// Files that could not be processed
+    pub failed: u64,
+
+    /// Files left alone, not counted in `files`
+    pub skipped: u64,
+
+    /// Pages extracted
+    pub pages: u64,
+
+    /// Requests answered from the cache
+    pub cache_hits: u64,
+
+    /// API requests that were retried
+    pub retries: u64,
+
+    /// Wall-clock duration of the run
+    pub duration_ms: u64,
+}
+
+impl RunSummary {
+    /// Format the summary as a single line
+    pub fn to_line(&self) -> String {
+        self.render(&Styler::plain())
+    }
+
+    /// Format the summary line for the terminal, with failures highlighted
+    pub fn render(&self, styler: &Styler) -> String {
+        let failed = if self.failed > 0 {
+            format!(" {}", styler.failure(&format!("({} failed)", self.failed)))
+        } else {
+            String::new()
+        };
+        let skipped = if self.skipped > 0 {
+            format!(", {} skipped", self.skipped)
+        } else {
+            String::new()
+        };
+
+        format!(
+            "{} {}{}{}, {}, {}, {} in {}",
+            styler.label("Summary:"),
+            count(self.files, "file", "files"),
+            failed,
+            skipped,
+            count(self.pages, "page", "pages"),
+            count(self.cache_hits, "cache hit", "cache hits"),
+            count(self.retries, "retry", "retries"),
+            styler.dim(&format!("{:.1}s", self.duration_ms as f64 / 1000.0))
+        )
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_line() {
+        let summary = RunSummary {
+            files: 3,
+            failed: 1,
+            skipped: 0,
+            pages: 12,
+            cache_hits: 0,
+            retries: 2,
+            duration_ms: 4230,
+        };
+        assert_eq!(
+            summary.to_line(),
+            "Summary: 3 files (1 failed), 12 pages, 0 cache hits, 2 retries in 4.2s"
+        );
+
+        let single = RunSummary {
+            files: 1,
+            failed: 0,
+            skipped: 0,
+            pages: 1,
+            cache_hits: 1,
+            retries: 1,
+            duration_ms: 50,
+        };
+        assert_eq!(
+            single.to_line(),
+            "Summary: 1 file, 1 page, 1 cache hit, 1 retry in 0.1s"
+        );
+
+        let skipped = RunSummary {
+            files: 1,
+            skipped: 2,
+            ..single
+        };
+        assert_eq!(
+            skipped.to_line(),
+            "Summary: 1 file, 2 skipped, 1 page, 1 cache hit, 1 retry in 0.1s"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_counters() {
+        let counters = RunCounters::new();
+        counters.keep_records(true);
+        counters.record_file("a.pdf", 3);
+        counters.record_file("b.pdf", 2);
+        counters.record_failure("c.pdf", "API error: boom");
+        counters.record_skip();
+
+        let metrics = MetricsCollector::new();
+        metrics.record_retry().await;
+        let cache = CacheManager::new();
+
+        let summary = counters.summary(&metrics, &cache).await;
+        assert_eq!((summary.files, summary.failed, summary.pages), (3, 1, 5));
+        assert_eq!(summary.skipped, 1);
+        assert_eq!((summary.retries, summary.cache_hits), (1, 0));
+        let records = counters.take_records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].file, "c.pdf");
+        assert_eq!(records[2].error.as_deref(), Some("API error: boom"));
+
+        counters.record_file("d.pdf", 1);
+        counters.start();
+        assert_eq!(counters.summary(&metrics, &cache).await.files, 0);
+        assert!(counters.take_records().is_empty());
+    }
+
+    #[test]
+    fn test_records_are_only_kept_for_reports() {
+        let run = RunCounters::new();
+        run.record_file("a.pdf", 1);
+        assert!(run.take_records().is_empty());
+        assert_eq!(run.files(), 1);
+
+        run.keep_records(true);
+        run.record_file("b.pdf", 2);
+        run.record_failure("c.pdf", "boom");
+        let records = run.take_records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].error.as_deref(), Some("boom"));
+        // Taking the records drains them
+        assert!(run.take_records().is_empty());
+
+        for i in 0..MAX_RECORDS + 5 {
+            run.record_file(&format!("{}.pdf", i), 1);
+        }
+        assert_eq!(run.take_records().len(), MAX_RECORDS);
+    }
+}
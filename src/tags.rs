@@ -0,0 +1,322 @@
+//! Tag suggestions from extracted text
+//!
+//! Matches the names (and `match` patterns) of existing paperless-ngx tags
+//! against the extracted text. A tag whose words appear in the text is a
+//! keyword match with score 1.0; otherwise the most similar run of words of
+//! the same length is compared by normalized Levenshtein similarity, which
+//! catches OCR errors such as "Corporaton".
+//!
+//! Tags with a matching algorithm other than auto are matched the way
+//! paperless-ngx would: `match` holds any or all of the words (quoted phrases
+//! count as one word), an exact phrase, a regular expression, or a phrase
+//! matched fuzzily; tags set to none, or to an algorithm this version does not
+//! know, are never suggested.
+
+use crate::paperless::{MatchingAlgorithm, PaperlessTag};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Default minimum similarity for fuzzy matches
+pub const DEFAULT_MIN_SIMILARITY: f64 = 0.85;
+
+/// Fuzzy matching is skipped for terms shorter than this many characters
+const MIN_FUZZY_LENGTH: usize = 4;
+
+/// How a tag was matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// The tag's words appear verbatim (ignoring case)
+    Keyword,
+    /// Similar words appear in the text
+    Fuzzy,
+    /// The tag's regular expression matches the text
+    Regex,
+}
+
+/// A tag suggested for a document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagSuggestion {
+    /// paperless-ngx tag ID
+    pub id: i64,
+
+    /// Tag name
+    pub name: String,
+
+    /// Similarity from 0.0 to 1.0 (1.0 for keyword matches)
+    pub score: f64,
+
+    /// How the tag was matched
+    #[serde(rename = "match")]
+    pub match_kind: TagMatch,
+
+    /// Words in the text that matched
+    pub matched: String,
+}
+
+/// Suggest tags whose name or match pattern occurs in the text, best first
+pub fn suggest_tags(text: &str, tags: &[PaperlessTag], min_similarity: f64) -> Vec<TagSuggestion> {
+    let words = tokenize(text);
+    let mut suggestions: Vec<TagSuggestion> = tags
+        .iter()
+        .filter_map(|tag| {
+            match_tag(text, &words, tag, min_similarity).map(|(score, match_kind, matched)| {
+                TagSuggestion {
+                    id: tag.id,
+                    name: tag.name.clone(),
+                    score,
+                    match_kind,
+                    matched,
+                }
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.name.cmp(&b.name)));
+    suggestions
+}
+
+/// Match of a tag by its matching algorithm
+fn match_tag(
+    text: &str,
+    words: &[String],
+    tag: &PaperlessTag,
+    min_similarity: f64,
+) -> Option<(f64, TagMatch, String)> {
+    let pattern = tag.match_text.trim();
+    let insensitive = tag.is_insensitive;
+    if pattern.is_empty() && tag.matching_algorithm != MatchingAlgorithm::Auto {
+        return None;
+    }
+
+    match tag.matching_algorithm {
+        MatchingAlgorithm::Auto => [tag.name.as_str(), pattern]
+            .into_iter()
+            .filter_map(|term| match_term(words, term, min_similarity))
+            .max_by(|a, b| a.0.total_cmp(&b.0)),
+        MatchingAlgorithm::Any => split_match(pattern)
+            .into_iter()
+            .find(|term| contains_phrase(text, term, insensitive))
+            .map(|term| (1.0, TagMatch::Keyword, term)),
+        MatchingAlgorithm::All => {
+            let terms = split_match(pattern);
+            terms
+                .iter()
+                .all(|term| contains_phrase(text, term, insensitive))
+                .then(|| (1.0, TagMatch::Keyword, terms.join(" ")))
+        }
+        MatchingAlgorithm::Literal => contains_phrase(text, pattern, insensitive)
+            .then(|| (1.0, TagMatch::Keyword, pattern.to_string())),
+        MatchingAlgorithm::Regex => {
+            let regex = match RegexBuilder::new(pattern)
+                .case_insensitive(insensitive)
+                .build()
+            {
+                Ok(regex) => regex,
+                Err(e) => {
+                    tracing::debug!("Skipping tag {}: invalid regex: {}", tag.name, e);
+                    return None;
+                }
+            };
+            regex
+                .find(text)
+                .map(|found| (1.0, TagMatch::Regex, found.as_str().to_string()))
+        }
+        MatchingAlgorithm::Fuzzy => match_term(words, pattern, min_similarity),
+        MatchingAlgorithm::None | MatchingAlgorithm::Unsupported(_) => None,
+    }
+}
+
+/// Words of a `match` text; a quoted phrase counts as one word
+fn split_match(pattern: &str) -> Vec<String> {
+    let terms = Regex::new(r#""([^"]+)"|(\S+)"#).expect("valid match split regex");
+    terms
+        .captures_iter(pattern)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .map(|term| {
+            term.as_str()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// Whether the text contains the phrase as whole words, allowing any
+/// whitespace between them
+fn contains_phrase(text: &str, phrase: &str, insensitive: bool) -> bool {
+    let words: Vec<String> = phrase.split_whitespace().map(regex::escape).collect();
+    if words.is_empty() {
+        return false;
+    }
+    RegexBuilder::new(&format!(r"\b{}\b", words.join(r"\s+")))
+        .case_insensitive(insensitive)
+        .build()
+        .is_ok_and(|regex| regex.is_match(text))
+}
+
+/// Best match of a term in the text, if good enough
+fn match_term(
+    words: &[String],
+    term: &str,
+    min_similarity: f64,
+) -> Option<(f64, TagMatch, String)> {
+    let term_words = tokenize(term);
+    if term_words.is_empty() || term_words.len() > words.len() {
+        return None;
+    }
+
+    let windows: HashSet<&[String]> = words.windows(term_words.len()).collect();
+    if windows.contains(term_words.as_slice()) {
+        return Some((1.0, TagMatch::Keyword, term_words.join(" ")));
+    }
+
+    let term = term_words.join(" ");
+    let term_length = term.chars().count();
+    if term_length < MIN_FUZZY_LENGTH {
+        return None;
+    }
+
+    windows
+        .into_iter()
+        .map(|window| window.join(" "))
+        .filter(|candidate| {
+            // Strings of very different length cannot reach the minimum
+            let length = candidate.chars().count();
+            length.abs_diff(term_length) as f64
+                <= (1.0 - min_similarity) * length.max(term_length) as f64
+        })
+        .map(|candidate| (strsim::normalized_levenshtein(&term, &candidate), candidate))
+        .filter(|(score, _)| *score >= min_similarity)
+        .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)))
+        .map(|(score, candidate)| (score, TagMatch::Fuzzy, candidate))
+}
+
+/// Lowercase words of the text
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(id: i64, name: &str, match_text: &str) -> PaperlessTag {
+        PaperlessTag {
+            id,
+            name: name.to_string(),
+            match_text: match_text.to_string(),
+            matching_algorithm: MatchingAlgorithm::Auto,
+            is_insensitive: true,
+        }
+    }
+
+    fn tag_with(algorithm: MatchingAlgorithm, match_text: &str) -> PaperlessTag {
+        PaperlessTag {
+            matching_algorithm: algorithm,
+            ..tag(1, "Tag", match_text)
+        }
+    }
+
+    fn matches(tag: PaperlessTag, text: &str) -> Option<String> {
+        suggest_tags(text, &[tag], DEFAULT_MIN_SIMILARITY)
+            .pop()
+            .map(|suggestion| suggestion.matched)
+    }
+
+    #[test]
+    fn test_keyword_and_fuzzy_matches() {
+        let tags = [
+            tag(1, "Invoice", ""),
+            tag(2, "ACME Corporation", ""),
+            tag(3, "Tax", ""),
+            tag(4, "Insurance", "policy number"),
+        ];
+        let text = "INVOICE from Acme Corporaton\nPolicy  number: 12345";
+
+        let suggestions = suggest_tags(text, &tags, DEFAULT_MIN_SIMILARITY);
+        let ids: Vec<i64> = suggestions.iter().map(|s| s.id).collect();
+        assert_eq!(ids, [4, 1, 2]);
+
+        assert_eq!(suggestions[0].match_kind, TagMatch::Keyword);
+        assert_eq!(suggestions[0].matched, "policy number");
+        assert_eq!(suggestions[2].match_kind, TagMatch::Fuzzy);
+        assert_eq!(suggestions[2].matched, "acme corporaton");
+        assert!(suggestions[2].score < 1.0);
+    }
+
+    #[test]
+    fn test_paperless_matching_algorithms() {
+        let text = "Invoice from ACME Corp.\nPolicy   number 12345";
+
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Any, "receipt invoice"), text).as_deref(),
+            Some("invoice")
+        );
+        assert_eq!(
+            matches(
+                tag_with(MatchingAlgorithm::Any, "\"policy number\" receipt"),
+                text
+            )
+            .as_deref(),
+            Some("policy number")
+        );
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Any, "voice"), text),
+            None
+        );
+        assert!(matches(tag_with(MatchingAlgorithm::All, "invoice acme"), text).is_some());
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::All, "invoice receipt"), text),
+            None
+        );
+        assert!(matches(tag_with(MatchingAlgorithm::Literal, "from acme"), text).is_some());
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Literal, "acme from"), text),
+            None
+        );
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Regex, r"number \d+"), text).as_deref(),
+            Some("number 12345")
+        );
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Regex, "(unclosed"), text),
+            None
+        );
+        assert!(matches(
+            tag_with(MatchingAlgorithm::Fuzzy, "ACME Corporaton"),
+            "ACME Corporation"
+        )
+        .is_some());
+
+        // The name is not used unless the algorithm is auto
+        assert_eq!(matches(tag_with(MatchingAlgorithm::Any, ""), "Tag"), None);
+        assert!(matches(tag_with(MatchingAlgorithm::Auto, ""), "Tag").is_some());
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::None, "invoice"), text),
+            None
+        );
+        assert_eq!(
+            matches(tag_with(MatchingAlgorithm::Unsupported(9), "invoice"), text),
+            None
+        );
+
+        let sensitive = PaperlessTag {
+            is_insensitive: false,
+            ..tag_with(MatchingAlgorithm::Literal, "invoice")
+        };
+        assert_eq!(matches(sensitive, text), None);
+    }
+
+    #[test]
+    fn test_short_names_require_exact_words() {
+        let tags = [tag(1, "Tax", ""), tag(2, "Car", "")];
+        let suggestions = suggest_tags("Taxes for the cat", &tags, 0.5);
+        assert!(suggestions.is_empty());
+    }
+}
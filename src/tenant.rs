@@ -0,0 +1,196 @@
+//! Tenant profiles for `serve`
+//!
+//! One server can serve several paperless-ngx instances with their own API
+//! keys, providers, and models, so each is billed to its own account. A
+//! profile overrides settings of the configuration for the requests of the
+//! tokens that name it:
+//!
+//! ```toml
+//! [server.profiles.branch]
+//! api_key = "..."
+//! providers = ["mistral", "tesseract"]
+//! state_file = "/var/lib/paperless-ngx-ocr2/branch.json"
+//!
+//! [[server.tokens]]
+//! name = "paperless-branch"
+//! token = "c4a1f7e2b9d34e8a6f0c2b5d7e9a1f36"
+//! profile = "branch"
+//! ```
+//!
+//! Tokens without a profile use the configuration as it is. A profile's own
+//! `state_file` keeps its monthly page counts apart from the other tenants'.
+
+use crate::access::TokenConfig;
+use crate::config::Config;
+use crate::error::FieldErrors;
+use crate::provider::ProviderKind;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Settings a tenant profile overrides; unset fields keep the configuration's
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Mistral AI API key of the tenant
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// Mistral AI API base URL
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+
+    /// OCR providers in fallback order
+    #[serde(default)]
+    pub providers: Option<Vec<ProviderKind>>,
+
+    /// Model for summaries, translations, and entities
+    #[serde(default)]
+    pub chat_model: Option<String>,
+
+    /// Model for embeddings
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Monthly page counts of the tenant
+    #[serde(default)]
+    pub state_file: Option<PathBuf>,
+}
+
+impl ProfileConfig {
+    /// The configuration for requests of this profile
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(ref api_key) = self.api_key {
+            config.api_key = api_key.clone();
+        }
+        if let Some(ref api_base_url) = self.api_base_url {
+            config.api_base_url = api_base_url.clone();
+        }
+        if let Some(ref providers) = self.providers {
+            config.providers = providers.clone();
+        }
+        if let Some(ref chat_model) = self.chat_model {
+            config.chat_model = chat_model.clone();
+        }
+        if let Some(ref embedding_model) = self.embedding_model {
+            config.embedding_model = embedding_model.clone();
+        }
+        if let Some(ref state_file) = self.state_file {
+            config.state_file = Some(state_file.clone());
+        }
+        config.server.profiles.clear();
+        config
+    }
+}
+
+/// Check the profiles and that every token names an existing one
+pub fn check_profiles(
+    profiles: &BTreeMap<String, ProfileConfig>,
+    tokens: &[TokenConfig],
+    errors: &mut FieldErrors,
+) {
+    for (name, profile) in profiles {
+        if name.trim().is_empty() {
+            errors.add("profiles", "Profile name must not be empty");
+        }
+        if profile.providers.as_ref().is_some_and(Vec::is_empty) {
+            errors.add(
+                format!("profiles.{}.providers", name),
+                "At least one provider is required",
+            );
+        }
+    }
+    for (i, token) in tokens.iter().enumerate() {
+        if let Some(ref profile) = token.profile {
+            if !profiles.contains_key(profile) {
+                errors.add(
+                    format!("tokens[{}].profile", i),
+                    format!("Unknown profile '{}'", profile),
+                );
+            }
+        }
+    }
+}
+
+/// Configurations of the profiles, each checked like the configuration itself
+pub fn resolve_profiles(config: &Config) -> crate::error::Result<BTreeMap<String, Config>> {
+    let mut errors = FieldErrors::new();
+    let mut resolved = BTreeMap::new();
+    for (name, profile) in &config.server.profiles {
+        let profile_config = profile.apply(config);
+        errors.nested(
+            &format!("server.profiles.{}", name),
+            profile_config.validate(),
+        );
+        resolved.insert(name.clone(), profile_config);
+    }
+    errors.into_config_result()?;
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(profile: Option<&str>) -> TokenConfig {
+        TokenConfig {
+            name: "client".to_string(),
+            token: "client-token-0123456789".to_string(),
+            requests_per_minute: None,
+            profile: profile.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_profiles_override_credentials_and_models() {
+        let mut config = Config {
+            api_key: "sk-shared".to_string(),
+            ..Config::default()
+        };
+        config.server.profiles.insert(
+            "office".to_string(),
+            ProfileConfig {
+                api_key: Some("sk-office".to_string()),
+                providers: Some(vec![ProviderKind::Mock]),
+                state_file: Some(PathBuf::from("office.json")),
+                ..ProfileConfig::default()
+            },
+        );
+
+        let profiles = resolve_profiles(&config).unwrap();
+        let office = &profiles["office"];
+        assert_eq!(office.api_key, "sk-office");
+        assert_eq!(office.providers, [ProviderKind::Mock]);
+        assert_eq!(office.state_file, Some(PathBuf::from("office.json")));
+        assert_eq!(office.chat_model, config.chat_model);
+        assert!(office.server.profiles.is_empty());
+
+        config.server.profiles.insert(
+            "empty".to_string(),
+            ProfileConfig {
+                api_key: Some(String::new()),
+                ..ProfileConfig::default()
+            },
+        );
+        let error = resolve_profiles(&config).unwrap_err();
+        assert_eq!(
+            error.field_errors()[0].field,
+            "server.profiles.empty.api_key"
+        );
+    }
+
+    #[test]
+    fn test_tokens_must_name_existing_profiles() {
+        let profiles = BTreeMap::from([("office".to_string(), ProfileConfig::default())]);
+        let mut errors = FieldErrors::new();
+        check_profiles(
+            &profiles,
+            &[token(Some("office")), token(None), token(Some("home"))],
+            &mut errors,
+        );
+        let error = errors.into_config_result().unwrap_err();
+        assert_eq!(error.field_errors().len(), 1);
+        assert_eq!(error.field_errors()[0].field, "tokens[2].profile");
+    }
+}
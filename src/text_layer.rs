@@ -0,0 +1,186 @@
+//! Embedded text of digital-born PDFs
+//!
+//! PDFs exported from office software, or scanned and OCRed before, already
+//! carry a text layer. poppler's `pdftotext` extracts it locally, page by page,
+//! so `--skip-text-pdfs` can use it instead of paying for an OCR call:
+//!
+//! ```toml
+//! [text_layer]
+//! command = "pdftotext"
+//! min_chars_per_page = 50
+//! ```
+//!
+//! A PDF counts as digital-born when every page has at least
+//! `min_chars_per_page` non-whitespace characters; a single scanned page sends
+//! the whole document to OCR.
+
+use crate::error::{Error, FieldErrors, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Settings for extracting the text layer of PDFs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextLayerConfig {
+    /// Poppler `pdftotext` executable
+    #[serde(default = "default_text_layer_command")]
+    pub command: String,
+
+    /// Non-whitespace characters a page needs to count as having text
+    #[serde(default = "default_min_chars_per_page")]
+    pub min_chars_per_page: usize,
+}
+
+fn default_text_layer_command() -> String {
+    "pdftotext".to_string()
+}
+
+fn default_min_chars_per_page() -> usize {
+    50
+}
+
+impl Default for TextLayerConfig {
+    fn default() -> Self {
+        Self {
+            command: default_text_layer_command(),
+            min_chars_per_page: default_min_chars_per_page(),
+        }
+    }
+}
+
+impl TextLayerConfig {
+    /// Validate the text layer settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if self.command.trim().is_empty() {
+            errors.add("command", "Text layer command must not be empty");
+        }
+        if self.min_chars_per_page == 0 {
+            errors.add(
+                "min_chars_per_page",
+                "Minimum characters per page must be at least 1",
+            );
+        }
+
+        errors.into_config_result()
+    }
+}
+
+/// Text of each page of a PDF, in page order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextLayer {
+    pages: Vec<String>,
+}
+
+impl TextLayer {
+    /// Split `pdftotext` output, which ends every page with a form feed
+    pub fn from_pdftotext(output: &str) -> Self {
+        let output = output.strip_suffix('\u{c}').unwrap_or(output);
+        Self {
+            pages: output
+                .split('\u{c}')
+                .map(|page| page.trim().to_string())
+                .collect(),
+        }
+    }
+
+    /// Text of each page
+    pub fn pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    /// Indices of the pages with less text than `min_chars`
+    pub fn pages_without_text(&self, min_chars: usize) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.chars().filter(|c| !c.is_whitespace()).count() < min_chars)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Whether every page has at least `min_chars` characters of text
+    pub fn is_complete(&self, min_chars: usize) -> bool {
+        !self.pages.is_empty() && self.pages_without_text(min_chars).is_empty()
+    }
+
+    /// The pages separated by form feeds, as local providers return them
+    pub fn to_text(&self) -> String {
+        self.pages.join("\u{c}")
+    }
+}
+
+/// Extract the text layer of a PDF
+pub async fn extract(pdf: &Path, config: &TextLayerConfig) -> Result<TextLayer> {
+    let output = tokio::process::Command::new(&config.command)
+        .args(["-enc", "UTF-8"])
+        .arg(pdf)
+        .arg("-")
+        .output()
+        .await
+        .map_err(|e| {
+            Error::Config(format!(
+                "Failed to run text layer command '{}': {}",
+                config.command, e
+            ))
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::api(format!(
+            "{} exited with {}: {}",
+            config.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(TextLayer::from_pdftotext(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_without_text() {
+        let layer =
+            TextLayer::from_pdftotext("Invoice 42\nTotal 100\n\u{c}  \n\u{c}Page three\u{c}");
+        assert_eq!(layer.pages(), ["Invoice 42\nTotal 100", "", "Page three"]);
+        assert_eq!(layer.pages_without_text(5), [1]);
+        assert_eq!(layer.pages_without_text(10), [1, 2]);
+        assert!(!layer.is_complete(5));
+
+        let digital = TextLayer::from_pdftotext("Invoice 42\u{c}Page two\u{c}");
+        assert!(digital.is_complete(5));
+        assert_eq!(digital.to_text(), "Invoice 42\u{c}Page two");
+        assert!(!TextLayer { pages: Vec::new() }.is_complete(1));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extract_runs_the_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        // Stand-in for pdftotext: prints two pages
+        let command = dir.path().join("pdftotext");
+        std::fs::write(&command, "#!/bin/sh\nprintf 'One\\fTwo\\f'\n").unwrap();
+        std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = TextLayerConfig {
+            command: command.to_string_lossy().to_string(),
+            ..TextLayerConfig::default()
+        };
+        let layer = extract(Path::new("scan.pdf"), &config).await.unwrap();
+        assert_eq!(layer.pages(), ["One", "Two"]);
+
+        let missing = TextLayerConfig {
+            command: dir.path().join("missing").to_string_lossy().to_string(),
+            ..TextLayerConfig::default()
+        };
+        let error = extract(Path::new("scan.pdf"), &missing).await.unwrap_err();
+        assert_eq!(error.exit_code(), 4);
+    }
+}
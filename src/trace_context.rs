@@ -0,0 +1,312 @@
+//! W3C Trace Context propagation
+//!
+//! With `[otel] enabled = true`, every document is processed inside a trace
+//! and each outgoing API request carries a `traceparent` header naming it as
+//! the parent, so the OCR calls show up in end-to-end distributed traces:
+//!
+//! - `serve` continues the trace of the `traceparent` (and `tracestate`)
+//!   header of the incoming request
+//! - command-line runs continue the trace in the `TRACEPARENT` environment
+//!   variable, as set by CI systems and other instrumented callers
+//! - otherwise a new trace is started per document
+//!
+//! Only the context is propagated; spans are not exported from this process.
+//! See <https://www.w3.org/TR/trace-context/>.
+
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+/// Header carrying the trace ID and the parent span
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Header carrying vendor-specific trace state, passed on unchanged
+pub const TRACESTATE: &str = "tracestate";
+
+/// Environment variable with the trace context of command-line runs
+pub const TRACEPARENT_ENV: &str = "TRACEPARENT";
+
+/// Environment variable with the trace state of command-line runs
+pub const TRACESTATE_ENV: &str = "TRACESTATE";
+
+/// Sampled flag of the `trace-flags` field
+const SAMPLED: u8 = 0x01;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// Position in a distributed trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// ID of the whole trace
+    pub trace_id: [u8; 16],
+
+    /// ID of the span the next request is a child of
+    pub span_id: [u8; 8],
+
+    /// Trace flags; bit 0 means the caller records the trace
+    pub flags: u8,
+
+    /// Vendor-specific `tracestate`, if any
+    pub state: Option<String>,
+}
+
+impl TraceContext {
+    /// Start a new, sampled trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: random_id(),
+            span_id: random_id(),
+            flags: SAMPLED,
+            state: None,
+        }
+    }
+
+    /// Continue the trace with a new span below this one
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_id(),
+            ..self.clone()
+        }
+    }
+
+    /// Context from `traceparent` and `tracestate` values; `None` when the
+    /// `traceparent` is missing or invalid, which starts a new trace
+    pub fn from_headers(traceparent: Option<&str>, tracestate: Option<&str>) -> Option<Self> {
+        let mut context = traceparent?.parse::<Self>().ok()?;
+        context.state = tracestate
+            .map(str::trim)
+            .filter(|state| !state.is_empty())
+            .map(str::to_string);
+        Some(context)
+    }
+
+    /// Context of a command-line run from `TRACEPARENT` and `TRACESTATE`
+    pub fn from_env() -> Option<Self> {
+        let traceparent = std::env::var(TRACEPARENT_ENV).ok()?;
+        let tracestate = std::env::var(TRACESTATE_ENV).ok();
+        Self::from_headers(Some(&traceparent), tracestate.as_deref())
+    }
+
+    /// Trace ID as 32 lowercase hex digits, for logs
+    pub fn trace_id_hex(&self) -> String {
+        hex(&self.trace_id)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    /// The `traceparent` value: `00-<trace-id>-<span-id>-<flags>`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            self.flags
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = String;
+
+    /// Parse a `traceparent` value
+    ///
+    /// Versions after `00` may append fields, which are ignored as the
+    /// specification requires; version `ff` and all-zero IDs are invalid.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("Invalid traceparent: {}", s);
+        let mut fields = s.trim().split('-');
+        let (Some(version), Some(trace_id), Some(span_id), Some(flags)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+
+        let version = parse_hex::<1>(version).ok_or_else(invalid)?[0];
+        if version == 0xff || (version == 0 && fields.next().is_some()) {
+            return Err(invalid());
+        }
+        let trace_id = parse_hex::<16>(trace_id).ok_or_else(invalid)?;
+        let span_id = parse_hex::<8>(span_id).ok_or_else(invalid)?;
+        let flags = parse_hex::<1>(flags).ok_or_else(invalid)?[0];
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            trace_id,
+            span_id,
+            flags,
+            state: None,
+        })
+    }
+}
+
+/// Context of the trace the current task runs in, if any
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(TraceContext::clone).ok()
+}
+
+/// Run `future` inside the trace `context`
+pub async fn scope<F: Future>(context: TraceContext, future: F) -> F::Output {
+    CURRENT.scope(context, future).await
+}
+
+/// Run `future` in the trace the current task runs in, if any
+///
+/// Spawned tasks do not inherit task-local values, so work spawned on behalf
+/// of a document is wrapped in this to keep its API calls in the trace. The
+/// future is boxed, as the pipeline's futures are large.
+pub fn propagate<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let context = current();
+    let future = Box::pin(future);
+    async move {
+        match context {
+            Some(context) => scope(context, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Run the processing of a document inside a trace when tracing is enabled
+///
+/// A trace the task already runs in (a `serve` request) is kept; otherwise
+/// the trace comes from `TRACEPARENT`, or a new one is started.
+pub async fn in_trace<F: Future>(enabled: bool, future: F) -> F::Output {
+    if !enabled || current().is_some() {
+        return future.await;
+    }
+    let context = match TraceContext::from_env() {
+        Some(parent) => parent.child(),
+        None => TraceContext::new_root(),
+    };
+    tracing::debug!("Processing in trace {}", context.trace_id_hex());
+    scope(context, future).await
+}
+
+/// Add `traceparent` and `tracestate` for a new child span to the headers of
+/// an outgoing request, when the task runs inside a trace
+pub fn inject(headers: &mut reqwest::header::HeaderMap) {
+    use reqwest::header::HeaderValue;
+
+    let Some(context) = current() else {
+        return;
+    };
+    if let Ok(value) = HeaderValue::from_str(&context.child().to_string()) {
+        headers.insert(TRACEPARENT, value);
+    }
+    if let Some(value) = context
+        .state
+        .as_deref()
+        .and_then(|state| HeaderValue::from_str(state).ok())
+    {
+        headers.insert(TRACESTATE, value);
+    }
+}
+
+/// Random non-zero ID
+fn random_id<const N: usize>() -> [u8; N] {
+    loop {
+        let id: [u8; N] = std::array::from_fn(|_| rand::random());
+        if id != [0; N] {
+            return id;
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Exactly `N` bytes from `2 * N` lowercase hex digits
+fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != 2 * N
+        || !s
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_and_format_traceparent() {
+        let context: TraceContext = PARENT.parse().unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.flags, SAMPLED);
+        assert_eq!(context.to_string(), PARENT);
+
+        // Later versions may add fields
+        assert!(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra"
+                .parse::<TraceContext>()
+                .is_ok()
+        );
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(invalid.parse::<TraceContext>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inject_creates_child_span_of_current_trace() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        inject(&mut headers);
+        assert!(headers.is_empty());
+
+        let parent = TraceContext::from_headers(Some(PARENT), Some("vendor=abc")).unwrap();
+        let injected = scope(parent.clone(), async {
+            let mut headers = reqwest::header::HeaderMap::new();
+            inject(&mut headers);
+            headers
+        })
+        .await;
+
+        let child: TraceContext = injected[TRACEPARENT].to_str().unwrap().parse().unwrap();
+        assert_eq!(child.trace_id, parent.trace_id);
+        assert_ne!(child.span_id, parent.span_id);
+        assert_eq!(injected[TRACESTATE], "vendor=abc");
+    }
+
+    #[tokio::test]
+    async fn test_spawned_tasks_continue_the_trace() {
+        let parent: TraceContext = PARENT.parse().unwrap();
+        let spawned = scope(parent.clone(), async {
+            tokio::spawn(propagate(async { current() })).await.unwrap()
+        })
+        .await;
+        assert_eq!(spawned, Some(parent));
+        assert!(tokio::spawn(propagate(async { current() }))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_trace_only_when_enabled() {
+        assert!(in_trace(false, async { current() }).await.is_none());
+        let context = in_trace(true, async { current() }).await.unwrap();
+        let kept = scope(context.clone(), in_trace(true, async { current() })).await;
+        assert_eq!(kept, Some(context));
+    }
+}
@@ -0,0 +1,313 @@
+//! Transform scripts run on each result
+//!
+//! With the `scripting` feature (on by default), a [Rhai](https://rhai.rs)
+//! script can adjust every result before it is written or printed:
+//!
+//! ```toml
+//! [transform]
+//! script = "transform.rhai"
+//! ```
+//!
+//! The script sees the result as the object map `doc` and changes it in place:
+//!
+//! | Field | |
+//! |-------|-|
+//! | `doc.text` | Extracted text; assign to filter or rewrite it |
+//! | `doc.output` | Output path relative to the output directory, without the `.txt`/`.json` suffix; assign to rename or route the result |
+//! | `doc.skip` | Set to `true` to write nothing for the document |
+//! | `doc.input`, `doc.name` | Path and file name of the document |
+//! | `doc.pages`, `doc.provider` | Pages processed and the provider that extracted the text |
+//!
+//! ```text
+//! if doc.text.contains("INVOICE") {
+//!     doc.output = "invoices/" + doc.name;
+//! }
+//! doc.text.replace("CONFIDENTIAL", "");
+//! doc.skip = doc.text.trim().is_empty();
+//! ```
+//!
+//! Scripts cannot touch files or the network. They are read again for every
+//! document, so a running `watch` picks up edits, and stop with an error after
+//! `max_operations` steps so a runaway loop cannot hang a run.
+
+use crate::error::{FieldErrors, Result};
+use crate::ocr::OCRResult;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Settings for the transform script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// Rhai script run on each result; unset runs none
+    #[serde(default)]
+    pub script: Option<PathBuf>,
+
+    /// Operations a script may run per document before it is stopped
+    #[serde(default = "default_max_operations")]
+    pub max_operations: u64,
+}
+
+fn default_max_operations() -> u64 {
+    1_000_000
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            script: None,
+            max_operations: default_max_operations(),
+        }
+    }
+}
+
+impl TransformConfig {
+    /// Validate the transform settings
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = FieldErrors::new();
+
+        if let Some(ref script) = self.script {
+            if cfg!(not(feature = "scripting")) {
+                errors.add(
+                    "script",
+                    "Transform scripts need the scripting feature; rebuild with it enabled",
+                );
+            } else if !script.is_file() {
+                errors.add(
+                    "script",
+                    format!("Transform script not found: {}", script.display()),
+                );
+            }
+        }
+        if self.max_operations == 0 {
+            errors.add("max_operations", "Max operations must be at least 1");
+        }
+
+        errors.into_config_result()
+    }
+
+    /// Whether a script is configured
+    pub fn is_enabled(&self) -> bool {
+        self.script.is_some()
+    }
+}
+
+/// What to do with a result after the script ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Write the result to this path, relative to the output directory
+    Write(PathBuf),
+
+    /// Write nothing for the document
+    Skip,
+}
+
+/// Run the configured script on a result
+///
+/// `relative` is where the result would be written; the returned outcome says
+/// where it goes instead. The result's text is replaced by the script's.
+/// Without a script the result is written to `relative` unchanged.
+pub fn apply(
+    config: &TransformConfig,
+    input: &Path,
+    relative: &Path,
+    result: &mut OCRResult,
+) -> Result<Outcome> {
+    match config.script {
+        Some(ref script) => run(config, script, input, relative, result),
+        None => Ok(Outcome::Write(relative.to_path_buf())),
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn run(
+    config: &TransformConfig,
+    script: &Path,
+    input: &Path,
+    relative: &Path,
+    result: &mut OCRResult,
+) -> Result<Outcome> {
+    use crate::error::Error;
+    use rhai::{Dynamic, Engine, Map, Scope};
+
+    let failed = |e: &dyn std::fmt::Display| {
+        Error::Config(format!(
+            "Transform script {} failed: {}",
+            script.display(),
+            e
+        ))
+    };
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(config.max_operations);
+    engine.on_print(|text| tracing::info!("transform: {}", text));
+    engine.on_debug(|text, _, position| tracing::debug!("transform {}: {}", position, text));
+    let ast = engine
+        .compile_file(script.to_path_buf())
+        .map_err(|e| failed(&e))?;
+
+    let mut doc = Map::new();
+    doc.insert("text".into(), result.extracted_text.clone().into());
+    doc.insert(
+        "output".into(),
+        relative.to_string_lossy().to_string().into(),
+    );
+    doc.insert("skip".into(), false.into());
+    doc.insert("input".into(), input.to_string_lossy().to_string().into());
+    doc.insert(
+        "name".into(),
+        input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+            .into(),
+    );
+    doc.insert("pages".into(), (result.page_count() as i64).into());
+    doc.insert(
+        "provider".into(),
+        result.provider.clone().unwrap_or_default().into(),
+    );
+
+    let mut scope = Scope::new();
+    scope.push("doc", doc);
+    engine
+        .run_ast_with_scope(&mut scope, &ast)
+        .map_err(|e| failed(&e))?;
+
+    let doc = scope
+        .get_value::<Map>("doc")
+        .ok_or_else(|| failed(&"`doc` is no longer an object map"))?;
+    let field = |name: &str| doc.get(name).cloned().unwrap_or(Dynamic::UNIT);
+
+    if field("skip")
+        .as_bool()
+        .map_err(|kind| failed(&format!("`doc.skip` must be a bool, not {}", kind)))?
+    {
+        return Ok(Outcome::Skip);
+    }
+    result.extracted_text = field("text")
+        .into_string()
+        .map_err(|kind| failed(&format!("`doc.text` must be a string, not {}", kind)))?;
+    let output = field("output")
+        .into_string()
+        .map_err(|kind| failed(&format!("`doc.output` must be a string, not {}", kind)))?;
+    Ok(Outcome::Write(relative_output(&output).ok_or_else(
+        || {
+            failed(&format!(
+                "`doc.output` must be a relative path inside the output directory, not '{}'",
+                output
+            ))
+        },
+    )?))
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run(
+    _config: &TransformConfig,
+    _script: &Path,
+    _input: &Path,
+    _relative: &Path,
+    _result: &mut OCRResult,
+) -> Result<Outcome> {
+    Err(crate::error::Error::Config(
+        "Transform scripts need the scripting feature; rebuild with it enabled".to_string(),
+    ))
+}
+
+/// An output path that stays inside the output directory
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+fn relative_output(output: &str) -> Option<PathBuf> {
+    use std::path::Component;
+
+    let path = PathBuf::from(output.trim());
+    let inside = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    (inside && path.file_name().is_some()).then_some(path)
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn result(text: &str) -> OCRResult {
+        OCRResult {
+            extracted_text: text.to_string(),
+            ..OCRResult::default()
+        }
+    }
+
+    #[test]
+    fn test_script_filters_text_and_routes_output() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("transform.rhai");
+        fs::write(
+            &script,
+            r#"
+            if doc.text.contains("INVOICE") {
+                doc.output = "invoices/" + doc.name;
+            }
+            doc.text.replace("CONFIDENTIAL ", "");
+            doc.skip = doc.text.is_empty();
+            "#,
+        )
+        .unwrap();
+        let config = TransformConfig {
+            script: Some(script.clone()),
+            ..TransformConfig::default()
+        };
+        let input = Path::new("in/2024/scan.pdf");
+        let relative = Path::new("2024/scan.pdf");
+
+        let mut invoice = result("CONFIDENTIAL INVOICE 42");
+        assert_eq!(
+            apply(&config, input, relative, &mut invoice).unwrap(),
+            Outcome::Write(PathBuf::from("invoices/scan.pdf"))
+        );
+        assert_eq!(invoice.extracted_text, "INVOICE 42");
+
+        let mut letter = result("Dear reader");
+        assert_eq!(
+            apply(&config, input, relative, &mut letter).unwrap(),
+            Outcome::Write(relative.to_path_buf())
+        );
+        assert_eq!(
+            apply(&config, input, relative, &mut result("")).unwrap(),
+            Outcome::Skip
+        );
+
+        fs::write(&script, r#"doc.output = "../outside.pdf";"#).unwrap();
+        assert!(apply(&config, input, relative, &mut result("x")).is_err());
+    }
+
+    #[test]
+    fn test_runaway_scripts_are_stopped() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("loop.rhai");
+        fs::write(&script, "loop {}").unwrap();
+        let config = TransformConfig {
+            script: Some(script),
+            max_operations: 1_000,
+        };
+
+        let error = apply(
+            &config,
+            Path::new("a.pdf"),
+            Path::new("a.pdf"),
+            &mut result(""),
+        )
+        .unwrap_err();
+        assert_eq!(error.exit_code(), 4);
+        assert_eq!(
+            apply(
+                &TransformConfig::default(),
+                Path::new("a.pdf"),
+                Path::new("a.pdf"),
+                &mut result("")
+            )
+            .unwrap(),
+            Outcome::Write(PathBuf::from("a.pdf"))
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Watching a directory for new documents
+//!
+//! `watch` polls a directory and processes every supported document that is
+//! new or changed since the last scan. Documents whose result in the output
+//! directory (or sidecar) is newer than the document are skipped, so restarting
+//! the watcher does not process the same documents again.
+//!
+//! Scanners and network copies write files incrementally, so a document is
+//! only reported once its size and modification time have not changed for the
+//! settle period; until then it is pending. Files matching the exclude
+//! patterns (see [`crate::exclude`]) are never reported.
+
+use crate::batch::is_supported;
+use crate::error::{Error, Result};
+use crate::exclude::ExcludeRules;
+use crate::file::ValidationRules;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Size and modification time of a file when it was last seen
+type Snapshot = (u64, Option<SystemTime>);
+
+/// Polls a directory for new and changed documents
+#[derive(Debug)]
+pub struct DirectoryWatcher {
+    dir: PathBuf,
+    settle: Duration,
+    excludes: Vec<String>,
+    seen: HashMap<PathBuf, Snapshot>,
+    /// Documents still being written, with when they last changed
+    pending: HashMap<PathBuf, (Snapshot, Instant)>,
+}
+
+impl DirectoryWatcher {
+    /// Watch `dir`; documents are reported once they have not changed for
+    /// `settle`, starting with every document in it
+    pub fn new(dir: PathBuf, settle: Duration) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Watch directory not found: {}", dir.display()),
+            )));
+        }
+        Ok(Self {
+            dir,
+            settle,
+            excludes: Vec::new(),
+            seen: HashMap::new(),
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Also skip files matching `excludes`, in addition to the default
+    /// patterns and the directory's `.ocrignore`
+    pub fn with_excludes(mut self, excludes: Vec<String>) -> Self {
+        self.excludes = excludes;
+        self
+    }
+
+    /// Watched directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Documents added or changed since the previous scan that have settled,
+    /// sorted by path
+    pub fn scan(&mut self, rules: &ValidationRules) -> Result<Vec<PathBuf>> {
+        let now = Instant::now();
+        let mut changed = Vec::new();
+        let mut seen = HashMap::new();
+        let mut pending = HashMap::new();
+        let excludes = ExcludeRules::load(&self.dir, &self.excludes)?;
+
+        for entry in fs::read_dir(&self.dir).map_err(Error::Io)? {
+            let path = entry.map_err(Error::Io)?.path();
+            if excludes.is_excluded(&path) || !is_supported(&path, rules) {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+
+            let snapshot = (metadata.len(), metadata.modified().ok());
+            if self.seen.get(&path) == Some(&snapshot) {
+                seen.insert(path, snapshot);
+                continue;
+            }
+            let unchanged_since = match self.pending.get(&path) {
+                Some((previous, since)) if *previous == snapshot => *since,
+                _ => now,
+            };
+            if now.duration_since(unchanged_since) >= self.settle {
+                changed.push(path.clone());
+                seen.insert(path, snapshot);
+            } else {
+                tracing::debug!("Waiting for {} to settle", path.display());
+                pending.insert(path, (snapshot, unchanged_since));
+            }
+        }
+
+        // Forget removed files so they are processed again if they come back
+        self.seen = seen;
+        self.pending = pending;
+        changed.sort();
+        Ok(changed)
+    }
+
+    /// Time until the next pending document could settle, if any is pending
+    pub fn next_settle(&self) -> Option<Duration> {
+        self.pending
+            .values()
+            .map(|(_, since)| self.settle.saturating_sub(since.elapsed()))
+            .min()
+    }
+}
+
+/// Whether `output` exists and is at least as new as `input`
+pub fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(input), modified(output)) {
+        (Ok(input), Ok(output)) => output >= input,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_reports_new_and_changed_documents() {
+        let dir = TempDir::new().unwrap();
+        let rules = crate::config::Config::default().validation_rules();
+        let mut watcher = DirectoryWatcher::new(dir.path().to_path_buf(), Duration::ZERO).unwrap();
+        assert!(watcher.scan(&rules).unwrap().is_empty());
+
+        let scan = dir.path().join("scan.pdf");
+        fs::write(&scan, b"%PDF-1.4\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"not a document").unwrap();
+        fs::write(dir.path().join("next.pdf.partial"), b"%PDF-1.4\n").unwrap();
+        assert_eq!(watcher.scan(&rules).unwrap(), std::slice::from_ref(&scan));
+        assert!(watcher.scan(&rules).unwrap().is_empty());
+
+        fs::write(&scan, b"%PDF-1.4\n%changed\n").unwrap();
+        assert_eq!(watcher.scan(&rules).unwrap(), [scan]);
+
+        assert!(DirectoryWatcher::new(dir.path().join("missing"), Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_scan_waits_until_documents_settle() {
+        let dir = TempDir::new().unwrap();
+        let rules = crate::config::Config::default().validation_rules();
+        let settle = Duration::from_millis(300);
+        let mut watcher = DirectoryWatcher::new(dir.path().to_path_buf(), settle).unwrap();
+        assert_eq!(watcher.next_settle(), None);
+
+        let scan = dir.path().join("scan.pdf");
+        fs::write(&scan, b"%PDF-1.4\n").unwrap();
+        assert!(watcher.scan(&rules).unwrap().is_empty());
+        assert!(watcher.next_settle().unwrap() <= settle);
+
+        // Still being written: the settle period starts again
+        std::thread::sleep(Duration::from_millis(60));
+        fs::write(&scan, b"%PDF-1.4\n%more pages\n").unwrap();
+        assert!(watcher.scan(&rules).unwrap().is_empty());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(watcher.scan(&rules).unwrap().is_empty());
+
+        std::thread::sleep(watcher.next_settle().unwrap());
+        assert_eq!(watcher.scan(&rules).unwrap(), [scan]);
+        assert_eq!(watcher.next_settle(), None);
+    }
+
+    #[test]
+    fn test_is_up_to_date() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("scan.pdf");
+        let output = dir.path().join("scan.pdf.txt");
+        fs::write(&input, b"%PDF-1.4\n").unwrap();
+        assert!(!is_up_to_date(&input, &output));
+
+        fs::write(&output, b"text").unwrap();
+        assert!(is_up_to_date(&input, &output));
+    }
+}
@@ -314,7 +314,7 @@ async fn test_concurrent_operations_benchmark() {
         
         // Wait for all operations to complete
         for handle in handles {
-            let _ = handle.await.map_err(|e| Error::Internal(format!("Task failed: {}", e)))?;
+            let _ = handle.await.map_err(|e| Error::internal(format!("Task failed: {}", e)))?;
         }
         
         Ok::<(), Error>(())
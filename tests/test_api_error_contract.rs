@@ -256,3 +256,110 @@ async fn test_api_error_contract_logging_format() {
         "User message should not contain API key details"
     );
 }
+
+#[test]
+fn test_api_error_contract_retry_classification() {
+    use paperless_ngx_ocr2::Error;
+
+    // Transient: rate limits, gateway errors, timeouts
+    for status in [408, 429, 502, 503, 504] {
+        let error = Error::from_http_status(status, "transient".to_string());
+        assert_eq!(error.http_status(), Some(status));
+        assert!(error.is_retryable(), "{} should be retried", status);
+    }
+
+    // Permanent: the same request would fail again
+    for status in [400, 401, 403, 404, 413, 500] {
+        let error = Error::from_http_status(status, "permanent".to_string());
+        assert!(!error.is_retryable(), "{} should not be retried", status);
+    }
+
+    assert!(Error::from_http_status(429, "slow down".to_string()).is_rate_limit());
+    assert!(!Error::api("Failed to parse OCR response: EOF".to_string()).is_retryable());
+    assert!(!Error::Config("Missing API key".to_string()).is_retryable());
+}
+
+#[test]
+fn test_api_error_contract_parse_errors_keep_field_path() {
+    use paperless_ngx_ocr2::api::files::FileUploadResponse;
+    use paperless_ngx_ocr2::error::parse_json;
+
+    let error = parse_json::<FileUploadResponse>(
+        r#"{"id": "file-1", "object": "file", "bytes": "many"}"#,
+        "Failed to parse upload response",
+    )
+    .unwrap_err();
+
+    assert_eq!(error.exit_code(), 5);
+    assert_eq!(error.error_type(), "api");
+    assert_eq!(
+        error.user_message(),
+        "API error: Failed to parse upload response"
+    );
+
+    let causes = error.causes();
+    assert_eq!(causes.len(), 1, "{:?}", causes);
+    assert!(
+        causes[0].starts_with("bytes: invalid type"),
+        "{}",
+        causes[0]
+    );
+    assert!(error
+        .verbose_message()
+        .contains("\n  Caused by: bytes: invalid type"));
+    assert_eq!(error.to_json_output()["error"]["causes"][0], causes[0]);
+}
+
+#[test]
+fn test_api_error_contract_wrapped_errors_keep_their_source() {
+    use paperless_ngx_ocr2::Error;
+    use std::error::Error as _;
+
+    let inner = Error::internal_from(
+        "Failed to serialize job",
+        std::io::Error::other("disk full"),
+    );
+    let error = Error::internal_from("Failed to store job", inner);
+
+    assert_eq!(error.exit_code(), 5);
+    assert_eq!(
+        error.user_message(),
+        "Internal error: Failed to store job: Internal error: Failed to serialize job: disk full"
+    );
+    assert!(error.source().unwrap().to_string().ends_with("disk full"));
+    // The message already shows the wrapped error; causes start below it
+    assert_eq!(error.causes(), ["disk full"]);
+
+    let error = Error::validation("Unsupported file type");
+    assert!(error.source().is_none());
+    assert!(error.causes().is_empty());
+}
+
+#[test]
+fn test_api_error_contract_request_id() {
+    use paperless_ngx_ocr2::Error;
+
+    let error = Error::http(
+        500,
+        "Internal failure".to_string(),
+        Some("req-42".to_string()),
+    );
+    assert_eq!(error.request_id(), Some("req-42"));
+    assert_eq!(error.http_status(), Some(500));
+    assert!(error.user_message().contains("(request ID: req-42)"));
+    assert_eq!(error.to_json_output()["error"]["request_id"], "req-42");
+
+    let error = Error::from_http_status(500, "No ID".to_string());
+    assert_eq!(error.request_id(), None);
+    assert!(error.to_json_output()["error"].get("request_id").is_none());
+
+    // Status and request ID are never recovered from message text
+    let error = Error::from_http_status(
+        400,
+        "Client error (429): rate limit (request ID: fake)".to_string(),
+    );
+    assert_eq!(error.http_status(), Some(400));
+    assert_eq!(error.request_id(), None);
+    assert!(!error.is_rate_limit());
+    assert!(!Error::api("rate limit reached".to_string()).is_rate_limit());
+}
@@ -0,0 +1,183 @@
+//! Contract tests for Mistral AI Chat Completions API
+//! These tests validate the chat request/response structures used for post-processing
+
+use paperless_ngx_ocr2::api::chat::{
+    chunk_for_chat, parse_entities, truncate_for_chat, ChatMessage, ChatRequest, ChatResponse,
+    ResponseFormat, MAX_CHAT_INPUT_CHARS,
+};
+use paperless_ngx_ocr2::ocr::{OCRResult, Translation};
+
+#[test]
+fn test_chat_request_contract_structure() {
+    let request = ChatRequest::new(
+        "mistral-small-latest".to_string(),
+        vec![
+            ChatMessage::system("Summarize"),
+            ChatMessage::user("Invoice 42"),
+        ],
+    );
+
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+
+    assert_eq!(json["model"], "mistral-small-latest");
+    assert_eq!(json["messages"][0]["role"], "system");
+    assert_eq!(json["messages"][1]["role"], "user");
+    assert_eq!(json["messages"][1]["content"], "Invoice 42");
+    assert!(
+        json.get("temperature").is_none(),
+        "Unset temperature must not be serialized"
+    );
+    assert!(request.validate().is_ok());
+}
+
+#[test]
+fn test_chat_request_validation() {
+    let no_messages = ChatRequest::new("mistral-small-latest".to_string(), vec![]);
+    assert!(no_messages.validate().is_err());
+
+    let no_model = ChatRequest::new(String::new(), vec![ChatMessage::user("text")]);
+    assert!(no_model.validate().is_err());
+}
+
+#[test]
+fn test_chat_response_contract_parsing() {
+    let response: ChatResponse = serde_json::from_value(serde_json::json!({
+        "id": "cmpl-123",
+        "object": "chat.completion",
+        "model": "mistral-small-latest",
+        "created": 1700000000,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "  Invoice from ACME for 42 EUR.\n"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 8, "total_tokens": 18}
+    }))
+    .expect("Should parse chat response");
+
+    assert_eq!(response.content().unwrap(), "Invoice from ACME for 42 EUR.");
+}
+
+#[test]
+fn test_chat_response_without_choices() {
+    let response: ChatResponse = serde_json::from_value(serde_json::json!({
+        "id": "cmpl-123",
+        "model": "mistral-small-latest",
+        "choices": []
+    }))
+    .unwrap();
+
+    assert!(response.content().is_err());
+}
+
+#[test]
+fn test_truncate_for_chat_respects_char_boundaries() {
+    let short = "Grüße aus Köln";
+    assert_eq!(truncate_for_chat(short), short);
+
+    let long = "ü".repeat(MAX_CHAT_INPUT_CHARS + 10);
+    assert_eq!(
+        truncate_for_chat(&long).chars().count(),
+        MAX_CHAT_INPUT_CHARS
+    );
+}
+
+#[test]
+fn test_summary_in_json_output_only_when_present() {
+    let mut result = OCRResult::new(
+        "Invoice 42".to_string(),
+        "file-123".to_string(),
+        "mistral-ocr-latest".to_string(),
+        "invoice.pdf".to_string(),
+        1024,
+    );
+
+    assert!(result.to_json_output()["data"].get("summary").is_none());
+
+    result.summary = Some("Invoice from ACME".to_string());
+    let json = result.to_json_output();
+    assert_eq!(json["data"]["summary"], "Invoice from ACME");
+    assert!(result
+        .to_human_readable()
+        .contains("Summary:\nInvoice from ACME"));
+}
+
+#[test]
+fn test_chunk_for_chat_splits_on_paragraphs() {
+    assert_eq!(chunk_for_chat("short text"), vec!["short text"]);
+
+    let paragraph = "a".repeat(MAX_CHAT_INPUT_CHARS / 4);
+    let text = [paragraph.as_str(); 5].join("\n\n");
+    let chunks = chunk_for_chat(&text);
+
+    // Three paragraphs fit into the first chunk, the remaining two into the second
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks
+        .iter()
+        .all(|chunk| chunk.chars().count() <= MAX_CHAT_INPUT_CHARS));
+    assert_eq!(
+        chunks.concat().matches('a').count(),
+        paragraph.len() * 5,
+        "No text may be dropped"
+    );
+}
+
+#[test]
+fn test_translation_in_json_output() {
+    let mut result = OCRResult::new(
+        "Rechnung 42".to_string(),
+        "file-123".to_string(),
+        "mistral-ocr-latest".to_string(),
+        "rechnung.pdf".to_string(),
+        1024,
+    );
+    result.translation = Some(Translation {
+        language: "en".to_string(),
+        text: "Invoice 42".to_string(),
+    });
+
+    let json = result.to_json_output();
+    assert_eq!(json["data"]["extracted_text"], "Rechnung 42");
+    assert_eq!(json["data"]["translation"]["language"], "en");
+    assert_eq!(json["data"]["translation"]["text"], "Invoice 42");
+}
+
+#[test]
+fn test_json_response_format_serialization() {
+    let request = ChatRequest {
+        response_format: Some(ResponseFormat::json_object()),
+        ..ChatRequest::new(
+            "mistral-small-latest".to_string(),
+            vec![ChatMessage::user("text")],
+        )
+    };
+
+    let json = serde_json::to_value(&request).unwrap();
+    assert_eq!(json["response_format"]["type"], "json_object");
+}
+
+#[test]
+fn test_parse_entities_reply() {
+    let (entities, keywords) = parse_entities(
+        r#"```json
+{"people": ["Erika Mustermann", " erika mustermann "], "organizations": ["ACME GmbH"],
+ "dates": ["01.02.2024"], "amounts": ["42,00 EUR", ""], "keywords": ["invoice", "Invoice", "hardware"]}
+```"#,
+    )
+    .expect("Should parse fenced JSON reply");
+
+    assert_eq!(entities.people, vec!["Erika Mustermann"]);
+    assert_eq!(entities.organizations, vec!["ACME GmbH"]);
+    assert_eq!(entities.amounts, vec!["42,00 EUR"]);
+    assert_eq!(keywords, vec!["invoice", "hardware"]);
+}
+
+#[test]
+fn test_parse_entities_tolerates_missing_keys() {
+    let (entities, keywords) = parse_entities(r#"{"people": ["Max"]}"#).unwrap();
+    assert_eq!(entities.people, vec!["Max"]);
+    assert!(entities.dates.is_empty());
+    assert!(keywords.is_empty());
+
+    assert!(parse_entities("not json").is_err());
+}
@@ -172,3 +172,27 @@ async fn test_cli_file_argument_valid_image() {
     // Cleanup
     std::fs::remove_file(&temp_path).ok();
 }
+
+#[test]
+fn test_completions_cover_subcommands_and_flags() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish", "nushell"] {
+        Command::cargo_bin("paperless-ngx-ocr2")
+            .unwrap()
+            .args(["--completions", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("suggest-tags"))
+            .stdout(predicate::str::contains("replay"));
+    }
+}
+
+#[test]
+fn test_completions_reject_unknown_shell() {
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--completions", "tcsh"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("elvish, nushell"));
+}
@@ -147,3 +147,256 @@ async fn test_cli_exit_code_file_size_validation() {
     // Cleanup
     std::fs::remove_file(&temp_path).ok();
 }
+
+#[tokio::test]
+async fn test_cli_exit_code_insufficient_text() {
+    // Insufficient text has its own exit code so pipelines can fall back to another OCR path
+    let error = paperless_ngx_ocr2::Error::InsufficientText("0 characters".to_string());
+    assert_eq!(error.exit_code(), 6);
+    assert_eq!(error.error_type(), "insufficient_text");
+
+    let mut cmd = Command::cargo_bin("paperless-ngx-ocr2").unwrap();
+    cmd.arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--min-chars"))
+        .stdout(predicate::str::contains("--fail-on-empty"));
+}
+
+#[tokio::test]
+async fn test_cli_exit_code_enforced_quota() {
+    // A used-up monthly quota refuses Mistral OCR before anything is uploaded
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("scan.png");
+    std::fs::write(&file, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let month = chrono::Utc::now().format("%Y-%m").to_string();
+    let state_file = dir.path().join("state.json");
+    std::fs::write(
+        &state_file,
+        serde_json::json!({"pages_by_month": {month: 10}}).to_string(),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("paperless-ngx-ocr2").unwrap();
+    cmd.arg("--file")
+        .arg(&file)
+        .args([
+            "--api-key",
+            "test-key",
+            "--api-base-url",
+            "http://127.0.0.1:9",
+        ])
+        .arg("--enforce-quota")
+        .env("PAPERLESS_OCR_MONTHLY_QUOTA", "10")
+        .env("PAPERLESS_OCR_STATE_FILE", &state_file)
+        .assert()
+        .failure()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "monthly quota of 10 pages used up",
+        ));
+}
+
+#[tokio::test]
+async fn test_cli_config_error_lists_all_invalid_fields() {
+    // Every invalid setting is reported at once, with machine-readable fields in JSON
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("scan.png");
+    std::fs::write(&file, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    let config = dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\ntimeout_seconds = 0\nlog_level = \"loud\"\n\n[budget]\nmax_pages = 0\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .arg("--file")
+        .arg(&file)
+        .arg("--json")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let fields: Vec<&str> = json["error"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["timeout_seconds", "log_level", "budget.max_pages"]);
+    assert_eq!(
+        json["error"]["fields"][0]["constraint"],
+        "Timeout must be between 1 and 300 seconds"
+    );
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("3 invalid fields"));
+}
+
+#[test]
+fn test_cli_exit_code_reference() {
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("EXIT CODES:"))
+        .stdout(predicate::str::contains("7    Budget exceeded"));
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--explain-exit-codes", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let codes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let codes: Vec<i64> = codes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|info| info["code"].as_i64().unwrap())
+        .collect();
+    assert_eq!(codes, [0, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_cli_json_error_includes_exit_code() {
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args([
+            "--file",
+            "nonexistent_file.pdf",
+            "--api-key",
+            "test-key",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["error"]["exit_code"], 3);
+    assert_eq!(json["error"]["code"], "E_FILE_IO");
+}
+
+#[test]
+fn test_cli_explain_exit_code() {
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["explain", "7"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Exit code 7: Budget exceeded"))
+        .stdout(predicate::str::contains("Likely causes:"))
+        .stdout(predicate::str::contains("Remediation:"));
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--json", "explain", "5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["code"], 5);
+    assert_eq!(
+        info["error_types"],
+        serde_json::json!(["api", "network", "internal"])
+    );
+    assert!(!info["remediation"].as_array().unwrap().is_empty());
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["explain", "9"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown exit code 9"));
+}
+
+#[test]
+fn test_cli_explain_uses_the_configured_env_prefix() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "api_key = \"\"\nenv_prefix = \"OCR2_\"\n").unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["explain", "4"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Set OCR2_API_KEY or pass --api-key",
+        ))
+        .stdout(predicate::str::contains("PAPERLESS_OCR_").not());
+}
+
+#[test]
+fn test_cli_explain_named_error_code() {
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["explain", "E_RATE_LIMIT"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Error code E_RATE_LIMIT:"))
+        .stdout(predicate::str::contains("Exit code 5:"))
+        .stdout(predicate::str::contains("Remediation:"));
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--json", "explain", "e_budget_exceeded"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["name"], "E_BUDGET_EXCEEDED");
+    assert_eq!(info["exit_code"]["code"], 7);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["explain", "E_NOPE"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown error code E_NOPE"));
+}
+
+#[test]
+fn test_cli_error_codes_match_exit_codes() {
+    use paperless_ngx_ocr2::error::{error_code_info, ERROR_CODES};
+    use paperless_ngx_ocr2::Error;
+
+    let errors = [
+        Error::validation("bad".to_string()),
+        Error::Io(std::io::Error::other("disk")),
+        Error::Config("missing".to_string()),
+        Error::api("failed".to_string()),
+        Error::internal("bug".to_string()),
+        Error::InsufficientText("0 characters".to_string()),
+        Error::BudgetExceeded("--max-pages".to_string()),
+        Error::from_http_status(401, "Unauthorized".to_string()),
+        Error::from_http_status(429, "Too Many Requests".to_string()),
+        Error::from_http_status(503, "Unavailable".to_string()),
+    ];
+    for error in errors {
+        let info = error_code_info(error.code()).unwrap();
+        assert_eq!(info.exit_code, error.exit_code(), "{}", info.name);
+    }
+    assert_eq!(
+        Error::from_http_status(429, "Too Many Requests".to_string()).code(),
+        "E_RATE_LIMIT"
+    );
+
+    let mut names: Vec<&str> = ERROR_CODES.iter().map(|info| info.name).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), ERROR_CODES.len());
+}
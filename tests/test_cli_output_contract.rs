@@ -2,7 +2,7 @@
 //! These tests validate that our CLI output conforms to the expected JSON contract
 
 use paperless_ngx_ocr2::error::Error;
-use paperless_ngx_ocr2::ocr::OCRResult;
+use paperless_ngx_ocr2::ocr::{OCRResult, ResultPage};
 
 // ============================================================================
 // CLI OUTPUT CONTRACT TESTS (T011)
@@ -21,6 +21,7 @@ async fn test_cli_output_contract_success_structure() {
         model: "mistral-ocr-latest".to_string(),
         usage: Some(std::collections::HashMap::new()),
         timestamp: chrono::Utc::now(),
+        ..Default::default()
     };
 
     // Get the actual JSON output that the CLI produces
@@ -75,7 +76,7 @@ async fn test_cli_output_contract_error_structure() {
     // Test that Error can be serialized to the expected error format
     // This test validates the actual JSON output structure used by the CLI
 
-    let error = Error::Validation("File not found".to_string());
+    let error = Error::validation("File not found".to_string());
 
     // Get the actual JSON output that the CLI produces
     let json = error.to_json_output();
@@ -128,6 +129,7 @@ async fn test_cli_output_contract_success_data_fields() {
         model: "mistral-ocr-latest".to_string(),
         usage: Some(std::collections::HashMap::new()),
         timestamp: chrono::Utc::now(),
+        ..Default::default()
     };
 
     let json = ocr_result.to_json_output();
@@ -172,10 +174,10 @@ async fn test_cli_output_contract_success_data_fields() {
 async fn test_cli_output_contract_error_type_enum() {
     // Test that error types are correctly mapped
 
-    let validation_error = Error::Validation("Validation failed".to_string());
-    let api_error = Error::Api("API error".to_string());
+    let validation_error = Error::validation("Validation failed".to_string());
+    let api_error = Error::api("API error".to_string());
     let config_error = Error::Config("Config error".to_string());
-    let internal_error = Error::Internal("Internal error".to_string());
+    let internal_error = Error::internal("Internal error".to_string());
 
     // Test validation error
     let json = validation_error.to_json_output();
@@ -214,6 +216,7 @@ async fn test_cli_output_contract_optional_fields() {
         model: "mistral-ocr-latest".to_string(),
         usage: Some(std::collections::HashMap::new()),
         timestamp: chrono::Utc::now(),
+        ..Default::default()
     };
 
     let json = ocr_result_with_confidence.to_json_output();
@@ -233,6 +236,7 @@ async fn test_cli_output_contract_optional_fields() {
         model: "mistral-ocr-latest".to_string(),
         usage: Some(std::collections::HashMap::new()),
         timestamp: chrono::Utc::now(),
+        ..Default::default()
     };
 
     let json = ocr_result_without_confidence.to_json_output();
@@ -256,6 +260,7 @@ async fn test_cli_output_contract_validation() {
         model: "mistral-ocr-latest".to_string(),
         usage: Some(std::collections::HashMap::new()),
         timestamp: chrono::Utc::now(),
+        ..Default::default()
     };
 
     let json = ocr_result.to_json_output();
@@ -274,3 +279,94 @@ async fn test_cli_output_contract_validation() {
     // Validate that data is object
     assert!(json.get("data").unwrap().is_object(), "Data must be object");
 }
+
+#[tokio::test]
+async fn test_cli_output_contract_document_date() {
+    // document_date is an ISO-8601 date string, present only when detected
+    let mut ocr_result = OCRResult {
+        extracted_text: "Rechnungsdatum: 01.02.2024".to_string(),
+        file_name: "invoice.pdf".to_string(),
+        file_size: 1024,
+        file_id: "file_123".to_string(),
+        model: "mistral-ocr-latest".to_string(),
+        ..Default::default()
+    };
+
+    assert!(ocr_result.to_json_output()["data"]
+        .get("document_date")
+        .is_none());
+
+    ocr_result.document_date = chrono::NaiveDate::from_ymd_opt(2024, 2, 1);
+    let json = ocr_result.to_json_output();
+    assert_eq!(json["data"]["document_date"], "2024-02-01");
+}
+
+#[tokio::test]
+async fn test_cli_output_contract_pages_json() {
+    // pages-json replaces the joined text with the per-page array
+    let ocr_result = OCRResult {
+        extracted_text: "First\n\nSecond".to_string(),
+        file_name: "document.pdf".to_string(),
+        file_size: 1024,
+        file_id: "file_123".to_string(),
+        model: "mistral-ocr-latest".to_string(),
+        pages: vec![
+            ResultPage {
+                index: 0,
+                markdown: "First".to_string(),
+                dpi: 200,
+                width: 1654,
+                height: 2339,
+            },
+            ResultPage {
+                index: 1,
+                markdown: "Second".to_string(),
+                dpi: 200,
+                width: 1654,
+                height: 2339,
+            },
+        ],
+        ..Default::default()
+    };
+
+    let json = ocr_result.to_pages_json_output();
+    assert_eq!(json["success"], true);
+    assert!(json["data"].get("extracted_text").is_none());
+    assert_eq!(json["data"]["file_name"], "document.pdf");
+    assert_eq!(json["data"]["pages"][1]["markdown"], "Second");
+    assert_eq!(json["data"]["pages"][0]["dpi"], 200);
+    assert_eq!(json["data"]["pages"][0]["width"], 1654);
+    assert_eq!(json["data"]["pages"][0]["height"], 2339);
+
+    // The regular JSON output keeps the joined text
+    assert!(ocr_result.to_json_output()["data"].get("pages").is_none());
+}
+
+#[tokio::test]
+async fn test_cli_output_contract_correspondents() {
+    // correspondents is a ranked array, present only when candidates were found
+    let mut ocr_result = OCRResult {
+        extracted_text: "ACME Corporation\nInvoice 42".to_string(),
+        file_name: "invoice.pdf".to_string(),
+        file_size: 1024,
+        file_id: "file_123".to_string(),
+        model: "mistral-ocr-latest".to_string(),
+        ..Default::default()
+    };
+
+    assert!(ocr_result.to_json_output()["data"]
+        .get("correspondents")
+        .is_none());
+
+    ocr_result.correspondents =
+        paperless_ngx_ocr2::correspondents::detect_correspondents(&ocr_result.extracted_text);
+    let json = ocr_result.to_json_output();
+    assert_eq!(
+        json["data"]["correspondents"][0]["name"],
+        "ACME Corporation"
+    );
+    assert_eq!(
+        json["data"]["correspondents"][0]["sources"][0],
+        "letterhead"
+    );
+}
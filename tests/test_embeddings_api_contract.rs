@@ -0,0 +1,78 @@
+//! Contract tests for Mistral AI Embeddings API
+//! These tests validate the embeddings request/response structures used by `--embed`
+
+use paperless_ngx_ocr2::api::embeddings::{
+    truncate_for_embedding, EmbeddingRequest, EmbeddingResponse, MAX_EMBEDDING_INPUT_CHARS,
+};
+use paperless_ngx_ocr2::ocr::{Embedding, OCRResult};
+
+#[test]
+fn test_embedding_request_contract_structure() {
+    let request = EmbeddingRequest::new("mistral-embed".to_string(), "Invoice 42");
+
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+
+    assert_eq!(json["model"], "mistral-embed");
+    assert_eq!(json["input"], serde_json::json!(["Invoice 42"]));
+    assert!(request.validate().is_ok());
+}
+
+#[test]
+fn test_embedding_request_validation() {
+    let empty_text = EmbeddingRequest::new("mistral-embed".to_string(), "  \n");
+    assert!(empty_text.validate().is_err());
+
+    let no_model = EmbeddingRequest::new(String::new(), "Invoice 42");
+    assert!(no_model.validate().is_err());
+}
+
+#[test]
+fn test_embedding_response_contract_parsing() {
+    let response: EmbeddingResponse = serde_json::from_value(serde_json::json!({
+        "id": "embd-123",
+        "object": "list",
+        "model": "mistral-embed",
+        "data": [{"object": "embedding", "index": 0, "embedding": [0.1, -0.2, 0.3]}],
+        "usage": {"prompt_tokens": 4, "total_tokens": 4}
+    }))
+    .expect("Should parse embeddings response");
+
+    let embedding = response.into_embedding().unwrap();
+    assert_eq!(embedding.model, "mistral-embed");
+    assert_eq!(embedding.vector, [0.1, -0.2, 0.3]);
+
+    let empty: EmbeddingResponse =
+        serde_json::from_value(serde_json::json!({"model": "mistral-embed", "data": []})).unwrap();
+    assert!(empty.into_embedding().is_err());
+}
+
+#[test]
+fn test_long_text_is_truncated_on_char_boundary() {
+    let text = "ä".repeat(MAX_EMBEDDING_INPUT_CHARS + 10);
+    let truncated = truncate_for_embedding(&text);
+    assert_eq!(truncated.chars().count(), MAX_EMBEDDING_INPUT_CHARS);
+}
+
+#[test]
+fn test_embedding_in_json_output() {
+    let mut result = OCRResult {
+        extracted_text: "Invoice 42".to_string(),
+        file_name: "invoice.pdf".to_string(),
+        file_size: 1024,
+        file_id: "file_123".to_string(),
+        model: "mistral-ocr-latest".to_string(),
+        ..Default::default()
+    };
+    assert!(result.to_json_output()["data"].get("embedding").is_none());
+
+    result.embedding = Some(Embedding {
+        model: "mistral-embed".to_string(),
+        vector: vec![0.5, 0.25],
+    });
+    let json = result.to_json_output();
+    assert_eq!(json["data"]["embedding"]["model"], "mistral-embed");
+    assert_eq!(
+        json["data"]["embedding"]["vector"],
+        serde_json::json!([0.5, 0.25])
+    );
+}
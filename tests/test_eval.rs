@@ -0,0 +1,170 @@
+//! Integration tests for `eval`
+//! These tests evaluate stored results so no API calls are made
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+fn write_result(dir: &TempDir, file_name: &str, text: &str) {
+    let output = serde_json::json!({
+        "success": true,
+        "data": {
+            "extracted_text": text,
+            "file_name": file_name,
+            "file_size": 1024,
+            "processing_time_ms": 2000,
+            "confidence": null
+        }
+    });
+    fs::write(
+        dir.path().join(format!("{}.json", file_name)),
+        output.to_string(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_eval_stored_results_json_report() {
+    let results = TempDir::new().unwrap();
+    let reference = TempDir::new().unwrap();
+
+    write_result(&results, "invoice.pdf", "Invoice 42\nTotal 100 EUR");
+    write_result(&results, "letter.pdf", "Dear Sir");
+    write_result(&results, "orphan.pdf", "No reference");
+    fs::write(
+        reference.path().join("invoice.txt"),
+        "Invoice 42 Total 100 EUR",
+    )
+    .unwrap();
+    fs::write(reference.path().join("letter.txt"), "Dear Sirs").unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--json", "eval", "--stored", "--reference"])
+        .arg(reference.path())
+        .arg("--source")
+        .arg(results.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let documents = json["data"]["documents"].as_array().unwrap();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0]["file_name"], "invoice.pdf");
+    assert_eq!(documents[0]["cer"], 0.0);
+    assert_eq!(documents[1]["wer"], 0.5);
+    assert_eq!(json["data"]["missing_reference"][0], "orphan.pdf");
+}
+
+#[test]
+fn test_eval_human_report() {
+    let results = TempDir::new().unwrap();
+    let reference = TempDir::new().unwrap();
+    write_result(&results, "scan.png", "hello world");
+    fs::write(reference.path().join("scan.txt"), "hello world").unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["eval", "--stored", "--reference"])
+        .arg(reference.path())
+        .arg("--source")
+        .arg(results.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("scan.png"))
+        .stdout(predicate::str::contains("Total"));
+}
+
+#[test]
+fn test_eval_requires_reference_directory() {
+    let results = TempDir::new().unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["eval", "--stored", "--reference", "/nonexistent/references"])
+        .arg("--source")
+        .arg(results.path())
+        .assert()
+        .failure()
+        .code(3);
+}
+
+#[test]
+fn test_eval_stops_at_page_budget() {
+    let documents = TempDir::new().unwrap();
+    let reference = TempDir::new().unwrap();
+    for name in ["a", "b"] {
+        fs::write(
+            documents.path().join(format!("{}.png", name)),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR",
+        )
+        .unwrap();
+        fs::write(reference.path().join(format!("{}.txt", name)), "One Two").unwrap();
+    }
+    let config = reference.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"One\\fTwo\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["eval", "--max-pages", "2", "--reference"])
+        .arg(reference.path())
+        .arg("--source")
+        .arg(documents.path())
+        .assert()
+        .failure()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "page budget of 2 reached after 1 file (2 pages",
+        ))
+        .stderr(predicate::str::contains("Summary: 1 file, 2 pages"));
+}
+
+#[test]
+fn test_eval_skips_documents_without_reference() {
+    let documents = TempDir::new().unwrap();
+    let reference = TempDir::new().unwrap();
+    for name in ["a.png", "b.png"] {
+        fs::write(
+            documents.path().join(name),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR",
+        )
+        .unwrap();
+    }
+    fs::write(reference.path().join("a.txt"), "One").unwrap();
+    let config = reference.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"One\"\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["--json", "eval", "--reference"])
+        .arg(reference.path())
+        .arg("--source")
+        .arg(documents.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["documents"].as_array().unwrap().len(), 1);
+    assert_eq!(json["data"]["missing_reference"][0], "b.png");
+    // Only the document with a reference was sent to OCR
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Summary: 1 file,"),
+        "{:?}",
+        output
+    );
+}
@@ -0,0 +1,120 @@
+//! Integration tests for `export paperless`
+//! These tests run the CLI against a mock paperless-ngx server
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn write_result(dir: &TempDir, name: &str, file_name: &str, text: &str) {
+    let output = serde_json::json!({
+        "success": true,
+        "data": {
+            "extracted_text": text,
+            "file_name": file_name,
+            "file_size": 1024,
+            "processing_time_ms": 2000,
+            "confidence": null
+        }
+    });
+    fs::write(dir.path().join(name), output.to_string()).unwrap();
+}
+
+#[tokio::test]
+async fn test_export_paperless_updates_matched_document() {
+    let server = MockServer::start().await;
+    let store = TempDir::new().unwrap();
+    write_result(&store, "invoice.json", "invoice.pdf", "Invoice 42");
+
+    Mock::given(method("GET"))
+        .and(path("/api/documents/"))
+        .and(query_param("title__iexact", "invoice"))
+        .and(header("authorization", "Token secret-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": 1,
+            "results": [{"id": 7, "title": "invoice"}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path("/api/documents/7/"))
+        .and(body_json(serde_json::json!({"content": "Invoice 42"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": 7,
+            "title": "invoice"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--json", "export", "paperless", "--source"])
+        .arg(store.path())
+        .args(["--paperless-url", &server.uri()])
+        .args(["--paperless-token", "secret-token"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["exported"], 1);
+    assert_eq!(json["data"]["results"][0]["document_id"], 7);
+    assert_eq!(json["summary"]["files"], 1);
+    assert_eq!(json["summary"]["skipped"], 0);
+}
+
+#[tokio::test]
+async fn test_export_paperless_dry_run_reports_unmatched() {
+    let server = MockServer::start().await;
+    let store = TempDir::new().unwrap();
+    write_result(&store, "letter.json", "letter.pdf", "Dear Sir");
+
+    Mock::given(method("GET"))
+        .and(path("/api/documents/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({"count": 0, "results": []})),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["export", "paperless", "--dry-run", "--source"])
+        .arg(store.path())
+        .args(["--paperless-url", &server.uri()])
+        .args(["--paperless-token", "secret-token"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("letter.pdf: unmatched"))
+        // Unmatched results are skipped, not counted as processed files
+        .stderr(predicate::str::contains("Summary: 0 files, 1 skipped"));
+}
+
+#[test]
+fn test_export_paperless_requires_url() {
+    let store = TempDir::new().unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["export", "paperless", "--source"])
+        .arg(store.path())
+        .env_remove("PAPERLESS_OCR_PAPERLESS_URL")
+        .env_remove("PAPERLESS_OCR_PAPERLESS_TOKEN")
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "paperless-ngx URL must be set (--paperless-url or PAPERLESS_OCR_PAPERLESS_URL)",
+        ));
+}
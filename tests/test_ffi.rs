@@ -0,0 +1,53 @@
+//! Tests for the C ABI
+//! These tests use the mock provider so no API calls are made
+#![cfg(feature = "ffi")]
+
+use paperless_ngx_ocr2::ffi::{ocr_extract, ocr_free_string};
+use std::ffi::{CStr, CString};
+use std::fs;
+use tempfile::TempDir;
+
+fn extract(path: &str, config_json: Option<&str>) -> serde_json::Value {
+    let path = CString::new(path).unwrap();
+    let config_json = config_json.map(|json| CString::new(json).unwrap());
+
+    unsafe {
+        let result = ocr_extract(
+            path.as_ptr(),
+            config_json
+                .as_ref()
+                .map_or(std::ptr::null(), |json| json.as_ptr()),
+        );
+        assert!(!result.is_null());
+        let json = CStr::from_ptr(result).to_str().unwrap().to_string();
+        ocr_free_string(result);
+        serde_json::from_str(&json).unwrap()
+    }
+}
+
+#[test]
+fn test_ocr_extract_returns_cli_json() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("scan.png");
+    fs::write(&file, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let output = extract(
+        file.to_str().unwrap(),
+        Some(r#"{"providers": ["mock"], "mock": {"text": "Hello from C"}}"#),
+    );
+
+    assert_eq!(output["success"], true);
+    assert_eq!(output["data"]["extracted_text"], "Hello from C");
+    assert_eq!(output["data"]["file_name"], "scan.png");
+}
+
+#[test]
+fn test_ocr_extract_reports_errors_as_json() {
+    let output = extract("/nonexistent/scan.pdf", Some(r#"{"providers": ["mock"]}"#));
+    assert_eq!(output["success"], false);
+    assert!(output["error"]["message"].is_string());
+
+    let output = extract("/nonexistent/scan.pdf", Some("not json"));
+    assert_eq!(output["success"], false);
+    assert_eq!(output["error"]["type"], "api");
+}
@@ -1,7 +1,10 @@
 //! Contract tests for Mistral AI file upload API
 //! These tests validate that our file upload requests conform to the expected contract
 
-use paperless_ngx_ocr2::api::files::{FileUploadRequest, FileUploadResponse};
+use paperless_ngx_ocr2::api::files::{
+    BodyDigest, FileUploadRequest, FileUploadResponse, UploadDigest,
+};
+use paperless_ngx_ocr2::file::FileUpload;
 
 #[tokio::test]
 async fn test_file_upload_request_contract_structure() {
@@ -379,3 +382,127 @@ async fn test_file_upload_response_contract_validation() {
         "Invalid response (empty ID) should fail validation"
     );
 }
+
+#[tokio::test]
+async fn test_file_retrieval_integrity_contract() {
+    // GET /v1/files/{id} returns the same shape as the upload response
+    let retrieved: FileUploadResponse = serde_json::from_str(
+        r#"{"id":"file-abc123","object":"file","bytes":1024,"created_at":1640995800,"filename":"scan.pdf","purpose":"ocr","status":"processed"}"#,
+    )
+    .unwrap();
+    assert!(retrieved.check_integrity(1024).is_ok());
+
+    let error = retrieved.check_integrity(2048).unwrap_err();
+    assert!(error.to_string().contains("integrity check failed"));
+    assert!(error
+        .to_string()
+        .contains("sent 2048 bytes, server stored 1024 bytes"));
+
+    let failed = FileUploadResponse {
+        status: Some("error".to_string()),
+        ..retrieved
+    };
+    assert!(failed.check_integrity(1024).is_err());
+}
+
+#[tokio::test]
+async fn test_upload_digest_contract() {
+    let response: FileUploadResponse = serde_json::from_str(
+        r#"{"id":"file-abc123","object":"file","bytes":5,"created_at":1640995800,"filename":"scan.pdf","purpose":"ocr"}"#,
+    )
+    .unwrap();
+
+    let digest = UploadDigest::of(b"%PDF-");
+    assert_eq!(digest.bytes, 5);
+    assert_eq!(
+        digest.sha256,
+        "38523c087796e5d5dd1cf9bad1fb026781a838dd9dd2cf8af58b9f6502a46778"
+    );
+    assert!(digest.check(5, &response).is_ok());
+
+    // The file shrank between validation and upload
+    let error = digest.check(8, &response).unwrap_err();
+    assert!(error.to_string().contains("sent 5 of 8 bytes"));
+
+    // The server stored less than was sent
+    let truncated = UploadDigest::of(b"%PDF-1.4");
+    let error = truncated.check(8, &response).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("sent 8 bytes, server stored 5 bytes"));
+}
+
+#[tokio::test]
+async fn test_streamed_file_part_is_counted_and_hashed() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("scan.pdf");
+    let data = b"%PDF-1.4\n".repeat(10_000);
+    std::fs::write(&path, &data).unwrap();
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let digest = BodyDigest::default();
+    let upload = FileUpload::new(&path).unwrap();
+    let form = FileUploadRequest::to_digesting_multipart_form(&upload, "ocr", &digest)
+        .await
+        .unwrap();
+    reqwest::Client::new()
+        .post(server.uri())
+        .multipart(form)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(digest.finish(), UploadDigest::of(&data));
+    digest.reset();
+    assert_eq!(digest.finish(), UploadDigest::of(b""));
+}
+
+#[tokio::test]
+async fn test_streamed_file_part_uses_the_mime_type_override() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // A PNG by content, uploaded with `--mime-type image/tiff`
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("scan.png");
+    std::fs::write(&path, [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]).unwrap();
+    assert_eq!(FileUpload::guess_mime_type(&path), "image/png");
+    let upload = FileUpload {
+        file_path: path.to_string_lossy().to_string(),
+        file_size: 8,
+        mime_type: "image/tiff".to_string(),
+        file_id: None,
+        upload_status: None,
+        is_valid: true,
+    };
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let form =
+        FileUploadRequest::to_digesting_multipart_form(&upload, "ocr", &BodyDigest::default())
+            .await
+            .unwrap();
+    reqwest::Client::new()
+        .post(server.uri())
+        .multipart(form)
+        .send()
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body = String::from_utf8_lossy(&requests[0].body);
+    assert!(body.contains("Content-Type: image/tiff"));
+    assert!(!body.contains("image/png"));
+}
@@ -5,7 +5,7 @@ use predicates::prelude::*;
 use std::fs;
 use std::io::Write;
 use std::time::Instant;
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempDir};
 
 #[tokio::test]
 async fn test_network_timeout_handling() {
@@ -275,3 +275,42 @@ fn test_api_key_redaction_in_network_logs() {
     // This test validates that the log_request method exists and can be called
     // Actual log redaction is tested in the credentials unit tests
 }
+
+#[test]
+fn test_plain_http_to_loopback_requires_opt_in() {
+    use paperless_ngx_ocr2::{APICredentials, Config};
+
+    let key = || "sk-test123456789abcdef".to_string();
+    assert!(APICredentials::new(key(), "http://127.0.0.1:8080".to_string()).is_err());
+    assert!(APICredentials::new(key(), "http://localhost:8080".to_string()).is_err());
+    assert!(
+        APICredentials::new_insecure_loopback(key(), "http://127.0.0.1:8080".to_string()).is_ok()
+    );
+    // The opt-in never covers other hosts
+    assert!(
+        APICredentials::new_insecure_loopback(key(), "http://api.mistral.ai".to_string()).is_err()
+    );
+
+    let config = Config {
+        api_key: key(),
+        api_base_url: "http://[::1]:8080".to_string(),
+        allow_insecure_loopback: true,
+        ..Config::default()
+    };
+    assert!(APICredentials::from_config(&config).is_ok());
+}
+
+#[test]
+fn test_unparsable_insecure_loopback_opt_in_is_a_config_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut cmd = Command::cargo_bin("paperless-ngx-ocr2").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("PAPERLESS_OCR_API_KEY", "sk-test123456789abcdef")
+        .env("PAPERLESS_OCR_ALLOW_INSECURE_LOOPBACK", "yes")
+        .args(["config", "show"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "PAPERLESS_OCR_ALLOW_INSECURE_LOOPBACK must be 'true' or 'false', not 'yes'",
+        ));
+}
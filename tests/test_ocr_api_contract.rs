@@ -1,7 +1,10 @@
 //! Contract tests for Mistral AI OCR API
 //! These tests validate that our OCR API requests and responses conform to the expected contracts
 
-use paperless_ngx_ocr2::api::ocr::{Dimensions, OCRRequest, OCRResponse, Page, UsageInfo};
+use paperless_ngx_ocr2::api::ocr::{
+    unescape, Dimensions, DocumentChunk, DuplicatePage, OCRRequest, OCRResponse, Page, PageLayout,
+    ResponseFormat, UsageInfo,
+};
 
 // ============================================================================
 // OCR API REQUEST CONTRACT TESTS (T009)
@@ -426,3 +429,212 @@ async fn test_ocr_response_contract_multiple_pages() {
         "Helper method should concatenate multiple pages with newlines"
     );
 }
+
+#[tokio::test]
+async fn test_ocr_request_contract_document_annotation_format() {
+    // The annotation format is only sent when a structured extraction is requested
+    let request = OCRRequest::new("file-abc123".to_string());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert!(json.get("document_annotation_format").is_none());
+
+    let mut request = OCRRequest::new("file-abc123".to_string());
+    request.document_annotation_format =
+        Some(paperless_ngx_ocr2::extraction::ExtractionPreset::Invoice.annotation_format());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+
+    let format = &json["document_annotation_format"];
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["strict"], true);
+    assert!(format["json_schema"]["schema"]["properties"]
+        .get("invoice_number")
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_ocr_request_contract_instructions() {
+    // Instructions are only sent when given
+    let request = OCRRequest::new("file-abc123".to_string());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert!(json.get("instructions").is_none());
+
+    let mut request = OCRRequest::new("file-abc123".to_string());
+    request.instructions = Some("preserve table structure".to_string());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert_eq!(json["instructions"], "preserve table structure");
+}
+
+#[tokio::test]
+async fn test_ocr_request_contract_response_format() {
+    let request = OCRRequest::new("file-abc123".to_string());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert!(json.get("response_format").is_none());
+
+    let mut request = OCRRequest::new("file-abc123".to_string());
+    request.response_format = Some("text".parse::<ResponseFormat>().unwrap());
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert_eq!(json["response_format"], "text");
+
+    assert!("html".parse::<ResponseFormat>().is_err());
+}
+
+#[tokio::test]
+async fn test_ocr_response_blank_page_detection() {
+    // Pages with only an image reference or a page number count as blank
+    let page = |index: i32, markdown: &str| Page {
+        index,
+        markdown: markdown.to_string(),
+        images: vec![],
+        dimensions: Dimensions {
+            dpi: 200,
+            height: 2200,
+            width: 1700,
+        },
+    };
+
+    let response = OCRResponse {
+        pages: vec![
+            page(0, "Invoice 42 from ACME"),
+            page(1, "![img-0.jpeg](img-0.jpeg)\n\n"),
+            page(2, "- 3 -"),
+            page(3, "Terms and conditions"),
+        ],
+        model: "mistral-ocr-latest".to_string(),
+        document_annotation: None,
+        usage_info: UsageInfo {
+            pages_processed: 4,
+            doc_size_bytes: 1024,
+        },
+    };
+
+    assert_eq!(response.blank_page_indices(5), vec![1, 2]);
+    assert_eq!(
+        response.get_extracted_text_skipping_blank(5),
+        "Invoice 42 from ACME\n\nTerms and conditions"
+    );
+
+    // A zero threshold still treats pages without any text as blank
+    assert_eq!(response.blank_page_indices(0), vec![1]);
+}
+
+#[tokio::test]
+async fn test_ocr_response_duplicate_page_detection() {
+    let page = |index: i32, markdown: &str| Page {
+        index,
+        markdown: markdown.to_string(),
+        images: vec![],
+        dimensions: Dimensions {
+            dpi: 200,
+            height: 2200,
+            width: 1700,
+        },
+    };
+
+    // Page 2 is a double-feed of page 1 (with a blank back in between), page 4
+    // repeats page 3 with different line breaks, page 6 is a later repeat
+    let mut response = OCRResponse {
+        pages: vec![
+            page(0, "Cover letter"),
+            page(1, "Invoice 42\nTotal: 10 EUR"),
+            page(2, ""),
+            page(3, "Invoice 42 Total: 10 EUR"),
+            page(4, "Terms and conditions"),
+            page(5, "Terms  and\nconditions"),
+            page(6, "Cover letter"),
+        ],
+        model: "mistral-ocr-latest".to_string(),
+        document_annotation: None,
+        usage_info: UsageInfo {
+            pages_processed: 7,
+            doc_size_bytes: 1024,
+        },
+    };
+
+    let duplicates = response.duplicate_pages(5);
+    assert_eq!(
+        duplicates,
+        vec![
+            DuplicatePage {
+                index: 3,
+                duplicate_of: 1
+            },
+            DuplicatePage {
+                index: 5,
+                duplicate_of: 4
+            },
+        ]
+    );
+
+    response.remove_pages(&[3, 5]);
+    assert_eq!(
+        response.get_extracted_text(),
+        "Cover letter\n\nInvoice 42\nTotal: 10 EUR\n\n\n\nTerms and conditions\n\nCover letter"
+    );
+}
+
+#[tokio::test]
+async fn test_ocr_response_page_layout() {
+    let page = |index: i32, markdown: &str| Page {
+        index,
+        markdown: markdown.to_string(),
+        images: vec![],
+        dimensions: Dimensions {
+            dpi: 200,
+            height: 2200,
+            width: 1700,
+        },
+    };
+
+    // Pages arrive out of order
+    let response = OCRResponse {
+        pages: vec![page(2, "Third"), page(0, "First"), page(1, "Second")],
+        model: "mistral-ocr-latest".to_string(),
+        document_annotation: None,
+        usage_info: UsageInfo {
+            pages_processed: 3,
+            doc_size_bytes: 1024,
+        },
+    };
+
+    assert_eq!(response.get_extracted_text(), "First\n\nSecond\n\nThird");
+
+    let layout = PageLayout {
+        separator: unescape("\\f"),
+        header: Some("--- page {page} ---".to_string()),
+    };
+    assert_eq!(
+        response.extracted_text(&layout, None),
+        "--- page 1 ---\nFirst\u{c}--- page 2 ---\nSecond\u{c}--- page 3 ---\nThird"
+    );
+
+    assert_eq!(unescape("a\\nb\\tc\\\\d\\x"), "a\nb\tc\\d\\x");
+}
+
+#[tokio::test]
+async fn test_ocr_request_contract_signed_url_documents() {
+    let request = OCRRequest::for_document(DocumentChunk::document_url(
+        "https://files.example/scan.pdf?sig=abc".to_string(),
+    ));
+    request
+        .validate()
+        .expect("document_url request should be valid");
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert_eq!(json["document"]["type"], "document_url");
+    assert_eq!(
+        json["document"]["document_url"],
+        "https://files.example/scan.pdf?sig=abc"
+    );
+    assert!(json["document"].get("file_id").is_none());
+
+    let request = OCRRequest::for_document(DocumentChunk::image_url(
+        "https://files.example/scan.png?sig=abc".to_string(),
+    ));
+    let json = serde_json::to_value(&request).expect("Should serialize to JSON");
+    assert_eq!(json["document"]["type"], "image_url");
+    assert_eq!(
+        json["document"]["image_url"],
+        "https://files.example/scan.png?sig=abc"
+    );
+
+    let empty = OCRRequest::for_document(DocumentChunk::document_url(String::new()));
+    assert!(empty.validate().is_err());
+}
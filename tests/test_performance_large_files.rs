@@ -56,7 +56,7 @@ fn test_file_size_limit_enforcement() {
 
     let result = file_upload.validate_file();
     assert!(result.is_err());
-    assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    assert!(matches!(result.unwrap_err(), Error::Validation { .. }));
 }
 
 #[test]
@@ -0,0 +1,861 @@
+//! Integration tests for the OCR provider chain
+//! These tests use local providers so no API calls are made
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+fn write_png(dir: &TempDir) -> std::path::PathBuf {
+    let path = dir.path().join("scan.png");
+    fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    path
+}
+
+#[test]
+fn test_mock_provider_without_api_key() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--json", "--providers", "mock", "--file"])
+        .arg(&file)
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "mock");
+    assert_eq!(json["data"]["extracted_text"], "Mock OCR text");
+}
+
+#[test]
+fn test_fallback_to_next_provider_when_mistral_fails() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    // Nothing listens on the discard port, so the Mistral provider fails fast
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--json", "--providers", "mistral,mock"])
+        .args([
+            "--api-key",
+            "test-key",
+            "--api-base-url",
+            "https://127.0.0.1:9",
+        ])
+        .arg("--file")
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "mock");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tesseract_provider_uses_configured_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    // Stand-in for tesseract that prints two pages separated by a form feed
+    let script = dir.path().join("fake-tesseract");
+    fs::write(&script, "#!/bin/sh\nprintf 'Page one\\n\\fPage two\\n'\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "api_key = \"\"\nproviders = [\"tesseract\"]\n\n[tesseract]\ncommand = \"{}\"\n",
+            script.display()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--config")
+        .arg(&config)
+        .arg("--file")
+        .arg(&file)
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .env_remove("PAPERLESS_OCR_PROVIDERS")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Page one\n\nPage two"));
+}
+
+#[cfg(not(feature = "pdf-render"))]
+#[test]
+fn test_all_providers_failing_reports_last_error() {
+    let dir = TempDir::new().unwrap();
+    let pdf = dir.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--providers", "tesseract", "--file"])
+        .arg(&pdf)
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("cannot read PDF files"));
+}
+
+// With pdf-render, tesseract reads PDFs through their rendered pages, so the
+// last error is the render command's
+#[cfg(feature = "pdf-render")]
+#[test]
+fn test_all_providers_failing_reports_last_error() {
+    let dir = TempDir::new().unwrap();
+    let pdf = dir.path().join("doc.pdf");
+    fs::write(&pdf, b"%PDF-1.4\n").unwrap();
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"tesseract\"]\n\n[render]\ncommand = \"missing-pdftoppm\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--config")
+        .arg(&config)
+        .arg("--file")
+        .arg(&pdf)
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .env_remove("PAPERLESS_OCR_PROVIDERS")
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "Failed to run PDF render command 'missing-pdftoppm'",
+        ));
+}
+
+#[test]
+fn test_compare_reports_each_provider() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args([
+            "--json",
+            "compare",
+            "--providers",
+            "mock,tesseract",
+            "--file",
+        ])
+        .arg(&file)
+        .env("PATH", "")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let providers = json["data"]["providers"].as_array().unwrap();
+    assert_eq!(providers[0]["provider"], "mock");
+    assert_eq!(providers[0]["words"], 3);
+    // tesseract is not on the (empty) PATH, so it is reported as failed
+    assert!(providers[1]["error"].is_string());
+    assert!(json["data"]["comparisons"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_compare_requires_two_providers() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["compare", "--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn test_progress_json_events_on_stderr() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--progress-json", "--providers", "mock", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let events: Vec<String> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| {
+            let event: serde_json::Value = serde_json::from_str(line).unwrap();
+            event["event"].as_str().unwrap().to_string()
+        })
+        .collect();
+
+    assert_eq!(
+        events,
+        [
+            "file_started",
+            "provider_started",
+            "ocr_started",
+            "ocr_completed",
+            "file_completed",
+            "run_completed"
+        ]
+    );
+}
+
+#[test]
+fn test_json_output_reports_phase_timings() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--json", "--providers", "mock", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let data = &json["data"];
+    let total = data["total_ms"].as_u64().unwrap();
+
+    assert_eq!(data["upload_ms"], 0);
+    assert!(data["validation_ms"].as_u64().unwrap() <= total);
+    assert!(data["ocr_ms"].as_u64().unwrap() <= total);
+    assert_eq!(data["processing_time_ms"].as_u64().unwrap(), total);
+}
+
+#[test]
+fn test_sidecar_written_next_to_input() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--sidecar", "--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .success();
+
+    let sidecar: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("scan.png.json")).unwrap())
+            .unwrap();
+    assert_eq!(sidecar["file_name"], "scan.png");
+    assert_eq!(sidecar["extracted_text"], "Mock OCR text");
+    assert_eq!(sidecar["page_count"], 1);
+    assert_eq!(sidecar["checksum"].as_str().unwrap().len(), 32);
+    assert!(sidecar["timings"]["total_ms"].is_number());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_encrypt_output_encrypts_stdout_and_sidecar() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    // Stand-in for age that replaces its input with a marker
+    let script = dir.path().join("fake-age");
+    fs::write(&script, "#!/bin/sh\ncat >/dev/null\necho ENCRYPTED\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "api_key = \"\"\nproviders = [\"mock\"]\n\n[encryption]\ncommand = \"{}\"\n\n\
+             [cache]\nenabled = true\ndir = \"{}\"\n",
+            script.display(),
+            dir.path().join("cache").display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--config")
+        .arg(&config)
+        .args(["--sidecar", "--encrypt-output", "age1test", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("ENCRYPTED"));
+    assert!(!stdout.contains("Mock OCR text"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("scan.png.json.age")).unwrap(),
+        "ENCRYPTED\n"
+    );
+    assert!(!dir.path().join("scan.png.json").exists());
+
+    // The result cache would hold the plaintext
+    let cached = fs::read_dir(dir.path().join("cache"))
+        .map(|entries| entries.count())
+        .unwrap_or_default();
+    assert_eq!(cached, 0);
+}
+
+#[test]
+fn test_compress_output_gzips_stdout_and_sidecar() {
+    use std::io::Read;
+
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--json", "--sidecar", "--compress-output"])
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let gunzip = |data: &[u8]| {
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(data)
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+    let json: serde_json::Value = serde_json::from_str(&gunzip(&output.stdout)).unwrap();
+    assert_eq!(json["data"]["extracted_text"], "Mock OCR text");
+
+    let sidecar: serde_json::Value = serde_json::from_str(&gunzip(
+        &fs::read(dir.path().join("scan.png.json.gz")).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(sidecar["file_name"], "scan.png");
+    assert!(!dir.path().join("scan.png.json").exists());
+}
+
+#[test]
+fn test_json_output_includes_sha256() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--json", "--providers", "mock", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["data"]["sha256"],
+        "02a3e298f1533f62558c58e4c70edcab9af5a50d62d925fd5390942020fb0fb8"
+    );
+}
+
+#[test]
+fn test_invalid_resolve_override_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args([
+            "--resolve",
+            "api.mistral.ai:10.0.0.5",
+            "--providers",
+            "mock",
+        ])
+        .arg("--file")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("HOST:PORT:ADDRESS"));
+}
+
+#[test]
+fn test_resolve_override_with_local_provider() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--resolve", "api.mistral.ai:443:[fd00::5]"])
+        .args(["--ip-preference", "ipv4", "--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_allowed_mime_types_from_config() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("scan.tiff");
+    fs::write(&file, b"II*\0tiff data").unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .code(2);
+
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\nallowed_mime_types = [\"application/pdf\", \"image/*\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--config")
+        .arg(&config)
+        .arg("--file")
+        .arg(&file)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_max_file_size_from_env() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("large.png");
+    let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+    data.resize(2 * 1024 * 1024, 0);
+    fs::write(&file, data).unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .env("PAPERLESS_OCR_MAX_FILE_SIZE_MB", "1")
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "exceeds maximum allowed size (1 MB)",
+        ));
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .env("PAPERLESS_OCR_MAX_FILE_SIZE_MB", "2")
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_extensionless_file_and_mime_type_override() {
+    let dir = TempDir::new().unwrap();
+    let png = write_png(&dir);
+    let extensionless = dir.path().join("scan0001");
+    fs::copy(&png, &extensionless).unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--file"])
+        .arg(&extensionless)
+        .assert()
+        .success();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--mime-type", "image/tiff", "--file"])
+        .arg(&extensionless)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Unsupported file format: image/tiff",
+        ));
+}
+
+#[test]
+fn test_permissive_validation() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("scan.png");
+    fs::write(&file, b"\x89PNX not quite a png").unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .code(2);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args([
+            "--providers",
+            "mock",
+            "--validation",
+            "permissive",
+            "--file",
+        ])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+}
+
+#[test]
+fn test_page_separator_and_headers() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"One\\fTwo\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args([
+            "--json",
+            "--page-separator",
+            "\\n---\\n",
+            "--page-header",
+            "--file",
+        ])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""extracted_text": "--- page 1 ---\nOne\n---\n--- page 2 ---\nTwo""#,
+        ));
+}
+
+#[test]
+fn test_instructions_ignored_by_local_providers() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args([
+            "--providers",
+            "mock",
+            "--instructions",
+            "preserve table structure",
+        ])
+        .arg("--file")
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+}
+
+#[test]
+fn test_structured_responses_rejected_by_local_providers() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    for (flag, value, message) in [
+        (
+            "--response-format",
+            "markdown",
+            "cannot produce --response-format markdown",
+        ),
+        (
+            "--extract",
+            "invoice",
+            "cannot produce a structured document annotation",
+        ),
+    ] {
+        Command::cargo_bin("paperless-ngx-ocr2")
+            .unwrap()
+            .args(["--providers", "mock", flag, value, "--file"])
+            .arg(&file)
+            .assert()
+            .failure()
+            .code(2)
+            .stderr(predicate::str::contains(message));
+    }
+
+    // Plain text is what local providers return anyway
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--response-format", "text", "--file"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+}
+
+#[test]
+fn test_run_summary_on_stderr() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--file"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Summary: 1 file, 1 page, 0 cache hits, 0 retries in",
+        ));
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .args(["--providers", "mock", "--quiet", "--file"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Summary:").not());
+}
+
+#[test]
+fn test_pages_json_format() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"One\\fTwo\"\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["--format", "pages-json", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["data"].get("extracted_text").is_none());
+    let pages = json["data"]["pages"].as_array().unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[1]["index"], 1);
+    assert_eq!(pages[1]["markdown"], "Two");
+    for key in ["dpi", "width", "height"] {
+        assert!(pages[0][key].is_number());
+    }
+}
+
+#[test]
+fn test_remove_duplicate_pages() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"Invoice 42\\fInvoice 42\\fTerms\"\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["--json", "--remove-duplicate-pages", "--file"])
+        .arg(&file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["extracted_text"], "Invoice 42\n\nTerms");
+    assert_eq!(
+        json["data"]["duplicate_pages"],
+        serde_json::json!([{"index": 1, "duplicate_of": 0}])
+    );
+    assert_eq!(json["data"]["removed_pages"], serde_json::json!([1]));
+}
+
+#[test]
+fn test_strip_headers_keeps_raw_pages() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"ACME GmbH\\nOne\\nPage 1\\fACME GmbH\\nTwo\\nPage 2\\fACME GmbH\\nThree\\nPage 3\"\n",
+    )
+    .unwrap();
+
+    let run = |format: &str| {
+        let output = Command::cargo_bin("paperless-ngx-ocr2")
+            .unwrap()
+            .arg("--config")
+            .arg(&config)
+            .args(["--format", format, "--strip-headers", "--file"])
+            .arg(&file)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let json = run("json");
+    assert_eq!(json["data"]["extracted_text"], "One\n\nTwo\n\nThree");
+    assert_eq!(
+        json["data"]["repeated_lines"],
+        serde_json::json!(["ACME GmbH", "Page #"])
+    );
+
+    let json = run("pages-json");
+    assert_eq!(
+        json["data"]["pages"][1]["markdown"],
+        "ACME GmbH\nTwo\nPage 2"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ensemble_merge_strategy() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let script = dir.path().join("fake-tesseract");
+    fs::write(
+        &script,
+        "#!/bin/sh\nprintf 'Invoice number 42\\fxq7 #@ zzk\\n'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "api_key = \"\"\nproviders = [\"tesseract\", \"mock\"]\nmerge_strategy = \"ensemble\"\n\n[tesseract]\ncommand = \"{}\"\n\n[mock]\ntext = \"invoice  number 42\\fPayment is due\"\n",
+            script.display()
+        ),
+    )
+    .unwrap();
+
+    // Page 1 is agreed on, page 2 goes to the readable mock text
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["--json", "--file"])
+        .arg(&file)
+        .env_remove("PAPERLESS_OCR_PROVIDERS")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "tesseract");
+    assert_eq!(
+        json["data"]["extracted_text"],
+        "Invoice number 42\n\nPayment is due"
+    );
+}
+
+#[test]
+fn test_show_metrics_prometheus() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--providers", "mock", "--show-metrics=prometheus", "--file"])
+        .arg(&file)
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // The mock provider makes no API calls
+    assert!(
+        stderr.contains("paperless_ocr_api_calls_success_total 0"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("paperless_ocr_api_request_duration_seconds_count{operation=\"all\"} 0")
+    );
+}
+
+#[test]
+fn test_mock_simulated_failures_and_latency() {
+    let dir = TempDir::new().unwrap();
+    let file = write_png(&dir);
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\ntimeout_seconds = 1\n\n[mock]\npages = [\"One\", \"Two\"]\n",
+    )
+    .unwrap();
+    let run = |env: &[(&str, &str)]| {
+        Command::cargo_bin("paperless-ngx-ocr2")
+            .unwrap()
+            .arg("--config")
+            .arg(&config)
+            .args(["--format", "pages-json", "--file"])
+            .arg(&file)
+            .envs(env.iter().copied())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["pages"][1]["markdown"], "Two");
+
+    let output = run(&[("PAPERLESS_OCR_MOCK_ERROR_RATE", "1")]);
+    assert_eq!(output.status.code(), Some(5), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Server error (503): Simulated mock provider failure"));
+
+    // Latency beyond the document timeout fails like a slow API
+    let output = run(&[("PAPERLESS_OCR_MOCK_LATENCY_MS", "3000")]);
+    assert_eq!(output.status.code(), Some(5), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("timed out"));
+}
@@ -0,0 +1,149 @@
+//! Integration tests for `--record` and `--replay`
+//! A run against a mock Mistral API is recorded and then replayed without it
+
+use assert_cmd::Command;
+use std::fs;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+async fn mock_mistral() -> MockServer {
+    let server = MockServer::start().await;
+    let file = serde_json::json!({
+        "id": "file-123",
+        "object": "file",
+        "bytes": PNG.len(),
+        "created_at": 1700000000,
+        "filename": "scan.png",
+        "purpose": "ocr",
+        "status": "processed"
+    });
+
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .and(header("authorization", "Bearer sk-recording-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/ocr"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req-ocr")
+                .set_body_json(serde_json::json!({
+                    "pages": [{
+                        "index": 0,
+                        "markdown": "Recorded invoice text",
+                        "images": [],
+                        "dimensions": {"dpi": 200, "height": 100, "width": 100}
+                    }],
+                    "model": "mistral-ocr-latest",
+                    "document_annotation": null,
+                    "usage_info": {"pages_processed": 1, "doc_size_bytes": PNG.len()}
+                })),
+        )
+        .mount(&server)
+        .await;
+
+    server
+}
+
+fn ocr_command(dir: &TempDir) -> Command {
+    let mut command = Command::cargo_bin("paperless-ngx-ocr2").unwrap();
+    command
+        .current_dir(dir.path())
+        .env("XDG_STATE_HOME", dir.path().join("state"))
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .args(["--json", "--providers", "mistral", "--file"])
+        .arg(dir.path().join("scan.png"));
+    command
+}
+
+#[tokio::test]
+async fn test_recorded_run_replays_offline() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    let fixtures = dir.path().join("fixtures");
+
+    let server = mock_mistral().await;
+    let recorded = ocr_command(&dir)
+        .args([
+            "--api-key",
+            "sk-recording-key",
+            "--api-base-url",
+            &server.uri(),
+        ])
+        .arg("--record")
+        .arg(&fixtures)
+        .output()
+        .unwrap();
+    assert!(recorded.status.success(), "{:?}", recorded);
+    drop(server);
+
+    let mut names: Vec<String> = fs::read_dir(&fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "0001-POST-v1_files.json",
+            "0002-GET-v1_files_file_123.json",
+            "0003-POST-v1_ocr.json"
+        ]
+    );
+    for name in &names {
+        let content = fs::read_to_string(fixtures.join(name)).unwrap();
+        assert!(!content.contains("sk-recording-key"), "{}", content);
+        assert!(!content.contains("IHDR"), "{}", content);
+    }
+
+    // No API key and no server: every response comes from the fixtures
+    let replayed = ocr_command(&dir)
+        .arg("--replay")
+        .arg(&fixtures)
+        .output()
+        .unwrap();
+    assert!(replayed.status.success(), "{:?}", replayed);
+
+    let recorded: serde_json::Value = serde_json::from_slice(&recorded.stdout).unwrap();
+    let replayed: serde_json::Value = serde_json::from_slice(&replayed.stdout).unwrap();
+    assert_eq!(replayed["data"]["extracted_text"], "Recorded invoice text");
+    assert_eq!(
+        replayed["data"]["extracted_text"],
+        recorded["data"]["extracted_text"]
+    );
+    assert_eq!(replayed["data"]["file_id"], recorded["data"]["file_id"]);
+}
+
+#[test]
+fn test_replay_requires_fixtures() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    ocr_command(&dir)
+        .arg("--replay")
+        .arg(dir.path().join("missing"))
+        .assert()
+        .failure()
+        .code(4);
+}
+
+#[test]
+fn test_record_conflicts_with_replay() {
+    let dir = TempDir::new().unwrap();
+
+    ocr_command(&dir)
+        .args(["--record", "a", "--replay", "b"])
+        .assert()
+        .failure()
+        .code(2);
+}
@@ -0,0 +1,1254 @@
+//! Integration tests for the subcommands next to the flat invocation
+//! These tests use the mock provider so no API calls are made
+
+use assert_cmd::Command;
+use paperless_ngx_ocr2::lock::DocumentLock;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::TempDir;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+fn command(dir: &TempDir) -> Command {
+    let mut command = Command::cargo_bin("paperless-ngx-ocr2").unwrap();
+    command
+        .current_dir(dir.path())
+        .env("XDG_CACHE_HOME", dir.path().join("cache"))
+        .env("XDG_STATE_HOME", dir.path().join("state"))
+        .env_remove("PAPERLESS_OCR_API_KEY")
+        .env_remove("PAPERLESS_OCR_PROVIDERS");
+    command
+}
+
+#[test]
+fn test_ocr_subcommand_matches_flat_invocation() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    let flat = command(&dir)
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .output()
+        .unwrap();
+    let subcommand = command(&dir)
+        .args(["ocr", "--providers", "mock", "--file", "scan.png"])
+        .output()
+        .unwrap();
+
+    assert!(flat.status.success(), "{:?}", flat);
+    assert!(subcommand.status.success(), "{:?}", subcommand);
+    // The header ends with the processing time, which differs between runs
+    let without_timing = |stdout: &[u8]| {
+        let stdout = String::from_utf8_lossy(stdout).to_string();
+        let (header, text) = stdout.split_once('\n').unwrap();
+        (
+            header.rsplit_once(", ").unwrap().0.to_string(),
+            text.to_string(),
+        )
+    };
+    assert_eq!(
+        without_timing(&flat.stdout),
+        without_timing(&subcommand.stdout)
+    );
+}
+
+#[test]
+fn test_diff_compares_the_result_with_existing_text() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(dir.path().join("scan.txt"), "Mock 0CR text\n").unwrap();
+
+    let output = command(&dir)
+        .args(["--json", "ocr", "--providers", "mock", "--file", "scan.png"])
+        .args(["--diff", "scan.txt"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["baseline"], "scan.txt");
+    assert_eq!(json["data"]["lines_added"], 1);
+    assert_eq!(json["data"]["lines_removed"], 1);
+    assert!(json["data"]["similarity"].as_f64().unwrap() < 1.0);
+    assert!(json["data"]["diff"]
+        .as_str()
+        .unwrap()
+        .contains("-Mock 0CR text\n+Mock OCR text"));
+
+    command(&dir)
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .args(["--diff", "missing.txt"])
+        .assert()
+        .failure()
+        .code(3);
+}
+
+#[tokio::test]
+async fn test_diff_paperless_compares_with_the_document_content() {
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    let list = |results: serde_json::Value| {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": results.as_array().unwrap().len(),
+            "results": results,
+        }))
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/documents/"))
+        .and(query_param("title__iexact", "scan"))
+        .respond_with(list(serde_json::json!([
+            {"id": 7, "title": "scan", "content": "Mock OCR text"}
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/documents/"))
+        .respond_with(list(serde_json::json!([])))
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    command(&dir)
+        .env("PAPERLESS_OCR_PAPERLESS_URL", server.uri())
+        .env("PAPERLESS_OCR_PAPERLESS_TOKEN", "secret-token")
+        .args([
+            "--providers",
+            "mock",
+            "--file",
+            "scan.png",
+            "--diff-paperless",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "scan.png vs paperless-ngx document 7: 100.0% similar, +0 -0 lines",
+        ));
+}
+
+#[test]
+fn test_file_before_subcommand_is_rejected() {
+    let dir = TempDir::new().unwrap();
+
+    command(&dir)
+        .args(["--file", "scan.png", "batch", "."])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("ocr --file"));
+}
+
+#[test]
+fn test_processing_flags_before_subcommand_are_rejected() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["--providers", "mock", "--sidecar", "ocr", "--file", "a.png"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--providers before a subcommand"))
+        .stderr(predicate::str::contains("--sidecar before a subcommand"));
+}
+
+#[test]
+fn test_batch_writes_results_keeping_subdirectories() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in");
+    fs::create_dir_all(input.join("2024")).unwrap();
+    fs::write(input.join("a.png"), PNG).unwrap();
+    fs::write(input.join("2024/b.png"), PNG).unwrap();
+
+    let output = command(&dir)
+        .args([
+            "--json",
+            "batch",
+            "in",
+            "--recursive",
+            "--output-dir",
+            "out",
+        ])
+        .args(["--providers", "mock"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["succeeded"], 2);
+    assert_eq!(json["summary"]["files"], 2);
+
+    let result: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("out/2024/b.png.json")).unwrap())
+            .unwrap();
+    assert_eq!(result["data"]["extracted_text"], "Mock OCR text");
+    assert!(dir.path().join("out/a.png.json").is_file());
+
+    let manifest = dir.path().join(json["manifest"].as_str().unwrap());
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(manifest).unwrap()).unwrap();
+    assert_eq!(manifest["succeeded"], 2);
+    assert_eq!(manifest["inputs"][0]["relative"], "2024/b.png");
+    assert_eq!(manifest["results"][0]["provider"], "mock");
+    assert_eq!(manifest["versions"]["providers"][0], "mock");
+    assert_eq!(manifest["config_sha256"].as_str().unwrap().len(), 64);
+}
+
+#[test]
+fn test_batch_concat_merges_texts_with_contents() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("in/2024")).unwrap();
+    fs::write(dir.path().join("in/a.png"), PNG).unwrap();
+    fs::write(dir.path().join("in/2024/b.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["batch", "in", "--recursive", "--concat", "archive/all.md"])
+        .args(["--providers", "mock", "--no-manifest"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Combined output: archive/all.md"));
+
+    let combined = fs::read_to_string(dir.path().join("archive/all.md")).unwrap();
+    assert!(combined.starts_with("# Contents\n\n1. [2024/b.png](#2024bpng)\n2. [a.png](#apng)\n"));
+    assert!(combined.contains("\n# 2024/b.png\n\nMock OCR text\n"));
+    assert!(combined.contains("\n# a.png\n\nMock OCR text\n"));
+}
+
+#[test]
+fn test_batch_manifest_can_be_skipped() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["batch", "scan.png", "-o", "out", "--no-manifest"])
+        .args(["--providers", "mock"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Run manifest").not());
+    let written: Vec<_> = fs::read_dir(dir.path().join("out")).unwrap().collect();
+    assert_eq!(written.len(), 1);
+}
+
+#[test]
+fn test_batch_job_manifest_writes_results_with_aggregate_status() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("jobs.json"),
+        r#"{
+            "job": "nightly",
+            "output_dir": "out",
+            "files": [
+                {"path": "scan.png", "output": "letters/42", "id": "42"},
+                "missing.png"
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let output = command(&dir)
+        .args(["batch", "--job-manifest", "jobs.json", "--no-manifest"])
+        .args(["--providers", "mock"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(dir.path().join("out/letters/42.txt").is_file());
+
+    let results: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("out/job-results.json")).unwrap())
+            .unwrap();
+    assert_eq!(results["job"], "nightly");
+    assert_eq!(results["status"], "partial");
+    assert_eq!(results["exit_code"], output.status.code().unwrap());
+    assert_eq!(results["files"][0]["id"], "42");
+    assert_eq!(results["files"][0]["status"], "succeeded");
+    assert_eq!(results["files"][1]["status"], "failed");
+    assert_eq!(results["files"][1]["exit_code"], results["exit_code"]);
+
+    command(&dir)
+        .args(["batch", "scan.png", "--job-manifest", "jobs.json"])
+        .assert()
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn test_batch_requires_a_destination() {
+    let dir = TempDir::new().unwrap();
+
+    command(&dir)
+        .args(["batch", ".", "--providers", "mock"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--output-dir"));
+}
+
+#[test]
+fn test_batch_reports_failures_with_exit_code() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(dir.path().join("empty.pdf"), b"").unwrap();
+
+    command(&dir)
+        .args(["batch", "scan.png", "empty.pdf", "-o", "out"])
+        .args(["--providers", "mock"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("1 succeeded, 1 failed"));
+    assert!(dir.path().join("out/scan.png.txt").is_file());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_batch_runs_hooks_after_each_document() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(dir.path().join("empty.pdf"), b"").unwrap();
+
+    command(&dir)
+        .args(["batch", "scan.png", "empty.pdf", "-o", "out"])
+        .args(["--on-success", "cp {output} '{name} copy.txt'"])
+        .args([
+            "--on-failure",
+            "sh -c 'echo \"$OCR_EXIT_CODE\" > \"failed-$OCR_NAME\"'",
+        ])
+        .args(["--providers", "mock"])
+        .assert()
+        .failure();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("scan.png copy.txt")).unwrap(),
+        fs::read_to_string(dir.path().join("out/scan.png.txt")).unwrap()
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("failed-empty.pdf")).unwrap(),
+        "2\n"
+    );
+
+    command(&dir)
+        .args(["batch", ".", "-o", "out", "--on-success", "echo {outptu}"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown placeholder {outptu}"));
+}
+
+#[test]
+fn test_batch_pipeline_keeps_order_and_stops_at_the_budget() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("in")).unwrap();
+    for i in 0..12 {
+        fs::write(dir.path().join(format!("in/{:02}.png", i)), PNG).unwrap();
+    }
+
+    let output = command(&dir)
+        .args(["--json", "batch", "in", "-o", "out", "--providers", "mock"])
+        .env("PAPERLESS_OCR_MAX_PAGES", "3")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(7), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["succeeded"], 3);
+    assert_eq!(json["data"]["failed"], 1);
+    assert_eq!(json["data"]["skipped"], 8);
+    let files: Vec<&str> = json["data"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["file"].as_str().unwrap())
+        .collect();
+    assert_eq!(files, ["in/00.png", "in/01.png", "in/02.png", "in/03.png"]);
+    assert!(!dir.path().join("out/04.png.txt").exists());
+}
+
+#[test]
+fn test_batch_processes_documents_concurrently() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("in")).unwrap();
+    for i in 0..8 {
+        fs::write(dir.path().join(format!("in/{}.png", i)), PNG).unwrap();
+    }
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"\"\nproviders = [\"mock\"]\nmax_concurrent_requests = 4\n\n\
+         [mock]\nlatency_ms = 400\n",
+    )
+    .unwrap();
+
+    let started = std::time::Instant::now();
+    let output = command(&dir)
+        .args([
+            "--config",
+            "config.toml",
+            "--json",
+            "batch",
+            "in",
+            "-o",
+            "out",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // One at a time, the documents would take 3.2s
+    assert!(started.elapsed() < std::time::Duration::from_millis(2400));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files: Vec<&str> = json["data"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["file"].as_str().unwrap())
+        .collect();
+    let expected: Vec<String> = (0..8).map(|i| format!("in/{}.png", i)).collect();
+    assert_eq!(files, expected);
+}
+
+#[tokio::test]
+async fn test_batch_uploads_each_document_once_ahead_of_ocr() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // A mock Mistral API
+    let server = MockServer::start().await;
+    let file = serde_json::json!({
+        "id": "file-123",
+        "object": "file",
+        "bytes": PNG.len(),
+        "created_at": 1700000000,
+        "filename": "scan.png",
+        "purpose": "ocr",
+        "status": "processed"
+    });
+    Mock::given(method("POST"))
+        .and(path("/v1/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file.clone()))
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/files/file-123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/ocr"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "pages": [{
+                "index": 0,
+                "markdown": "Invoice text",
+                "images": [],
+                "dimensions": {"dpi": 200, "height": 100, "width": 100}
+            }],
+            "model": "mistral-ocr-latest",
+            "document_annotation": null,
+            "usage_info": {"pages_processed": 1, "doc_size_bytes": PNG.len()}
+        })))
+        // Every upload gets the same file ID here, so documents that reach OCR
+        // after the first response is cached are answered from the cache
+        .expect(1..=3)
+        .mount(&server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("in")).unwrap();
+    for name in ["a.png", "b.png", "c.png"] {
+        fs::write(dir.path().join("in").join(name), PNG).unwrap();
+    }
+    let output = command(&dir)
+        .args([
+            "--api-key",
+            "sk-test123456789",
+            "--api-base-url",
+            &server.uri(),
+        ])
+        .env("PAPERLESS_OCR_ALLOW_INSECURE_LOOPBACK", "true")
+        .args(["batch", "in", "-o", "out", "--providers", "mistral"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fs::read_to_string(dir.path().join("out/b.png.txt"))
+        .unwrap()
+        .contains("Invoice text"));
+    // The mock checks that each document was uploaded once
+    server.verify().await;
+}
+
+#[test]
+fn test_batch_order_by_size() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.png"), [PNG, &[0; 20]].concat()).unwrap();
+    fs::write(dir.path().join("b.png"), PNG).unwrap();
+    fs::write(dir.path().join("c.png"), [PNG, &[0; 10]].concat()).unwrap();
+
+    let order = |order: &str| {
+        let output = command(&dir)
+            .args(["--json", "batch", "a.png", "b.png", "c.png", "-o", "out"])
+            .args(["--providers", "mock", "--order", order])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["data"]["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["file"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(order("size-asc"), ["b.png", "c.png", "a.png"]);
+    assert_eq!(order("size-desc"), ["a.png", "c.png", "b.png"]);
+}
+
+#[test]
+fn test_format_plain_strips_markdown() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\n\
+         text = \"# Invoice\\n\\n| Item | Price |\\n|---|---|\\n| Ink | **5** |\"\n",
+    )
+    .unwrap();
+
+    let output = command(&dir)
+        .args([
+            "--config",
+            "config.toml",
+            "--format",
+            "plain",
+            "--file",
+            "scan.png",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Invoice\n\nItem\tPrice\nInk\t5\n"
+    );
+}
+
+/// PDFs with and without a text layer, and a config that reads and splits
+/// them with stand-ins for poppler
+#[cfg(unix)]
+fn text_layer_fixture() -> TempDir {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    for name in ["digital.pdf", "scanned.pdf", "mixed.pdf"] {
+        fs::write(dir.path().join(name), b"%PDF-1.4\n%%EOF\n").unwrap();
+    }
+    // digital.pdf has text on both pages, mixed.pdf on two of three
+    let commands = [
+        (
+            "pdftotext",
+            "#!/bin/sh\ncase \"$3\" in\n\
+             *digital.pdf) printf 'Invoice 42 from ACME\\fTotal due 100 EUR\\f' ;;\n\
+             *mixed.pdf) printf 'Invoice 42 from ACME\\f\\fTotal due 100 EUR\\f' ;;\n\
+             *) printf '\\f\\f' ;;\nesac\n",
+        ),
+        (
+            "pdfseparate",
+            "#!/bin/sh\nfor n in $(seq \"$2\" \"$4\"); do printf '%%PDF-1.4\\n' > \"$(printf \"$6\" \"$n\")\"; done\n",
+        ),
+        ("pdfinfo", "#!/bin/sh\nprintf 'Pages:          3\\n'\n"),
+        (
+            "pdfunite",
+            "#!/bin/sh\nfor out; do :; done\nwhile [ $# -gt 1 ]; do cat \"$1\"; shift; done > \"$out\"\n",
+        ),
+    ];
+    for (name, script) in commands {
+        fs::write(dir.path().join(name), script).unwrap();
+        fs::set_permissions(dir.path().join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::write(
+        dir.path().join("config.toml"),
+        format!(
+            "api_key = \"\"\nproviders = [\"mock\"]\n\n\
+             [text_layer]\ncommand = \"{0}/pdftotext\"\nmin_chars_per_page = 10\n\n\
+             [split]\nseparate_command = \"{0}/pdfseparate\"\nunite_command = \"{0}/pdfunite\"\n\
+             info_command = \"{0}/pdfinfo\"\n",
+            dir.path().display()
+        ),
+    )
+    .unwrap();
+    dir
+}
+
+#[cfg(unix)]
+#[test]
+fn test_skip_text_pdfs_uses_the_text_layer_of_digital_pdfs() {
+    let dir = text_layer_fixture();
+    command(&dir)
+        .args(["--config", "config.toml", "--json", "batch", "digital.pdf"])
+        .args([
+            "scanned.pdf",
+            "-o",
+            "out",
+            "--skip-text-pdfs",
+            "--no-manifest",
+        ])
+        .assert()
+        .success();
+
+    let result = |name: &str| -> serde_json::Value {
+        let path = dir.path().join("out").join(format!("{}.json", name));
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    };
+    let digital = result("digital.pdf");
+    assert_eq!(digital["data"]["provider"], "local-text");
+    assert!(digital["data"]["extracted_text"]
+        .as_str()
+        .unwrap()
+        .contains("Total due 100 EUR"));
+    assert_eq!(result("scanned.pdf")["data"]["provider"], "mock");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_local_text_provider_needs_no_api() {
+    let dir = text_layer_fixture();
+    let ocr = |file: &str, providers: &str| {
+        command(&dir)
+            .args(["--config", "config.toml", "--json", "--file", file])
+            .args(["--provider", providers])
+            .output()
+            .unwrap()
+    };
+
+    let digital = ocr("digital.pdf", "local-text");
+    assert!(digital.status.success(), "{:?}", digital);
+    let json: serde_json::Value = serde_json::from_slice(&digital.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "local-text");
+
+    // Scanned pages fail without an OCR provider to fall back to
+    let scanned = ocr("scanned.pdf", "local-text");
+    assert_eq!(scanned.status.code(), Some(6), "{:?}", scanned);
+    assert!(String::from_utf8_lossy(&scanned.stdout).contains("no text layer on pages 1, 2"));
+
+    let fallback = ocr("scanned.pdf", "local-text,mock");
+    assert!(fallback.status.success(), "{:?}", fallback);
+    let json: serde_json::Value = serde_json::from_slice(&fallback.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "mock");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_long_pdfs_are_processed_in_chunks() {
+    let dir = text_layer_fixture();
+    let config = fs::read_to_string(dir.path().join("config.toml")).unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        config.replace("[split]\n", "[split]\nchunk_pages = 2\n"),
+    )
+    .unwrap();
+
+    // Three pages in chunks of two; the mock reads one page per chunk
+    let output = command(&dir)
+        .args(["--config", "config.toml", "--format", "pages-json"])
+        .args(["--file", "scanned.pdf"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let pages: Vec<_> = json["data"]["pages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|page| (page["index"].as_i64().unwrap(), page["markdown"].clone()))
+        .collect();
+    assert_eq!(
+        pages,
+        [
+            (0, serde_json::json!("Mock OCR text")),
+            (1, serde_json::json!("")),
+            (2, serde_json::json!("Mock OCR text")),
+        ]
+    );
+}
+
+#[cfg(all(unix, feature = "pdf-render"))]
+#[test]
+fn test_page_cache_only_processes_changed_pages() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = text_layer_fixture();
+    // Stand-in for pdftoppm: one image per line after the PDF header
+    fs::write(
+        dir.path().join("pdftoppm"),
+        "#!/bin/sh\nn=0\nwhile read -r line; do case \"$line\" in %*) continue ;; esac; n=$((n+1)); printf '%s' \"$line\" > \"$5-$n.png\"; done < \"$4\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(
+        dir.path().join("pdftoppm"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    let base = fs::read_to_string(dir.path().join("config.toml")).unwrap();
+    let run = |document: &str, mock_pages: &str| {
+        fs::write(
+            dir.path().join("contract.pdf"),
+            format!("%PDF-1.4\n{}", document),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("config.toml"),
+            format!(
+                "{}\n[render]\ncommand = \"{}/pdftoppm\"\n\n\
+                 [cache]\nenabled = true\npages = true\ndir = \"{}/cache\"\n\n\
+                 [mock]\npages = {}\n",
+                base,
+                dir.path().display(),
+                dir.path().display(),
+                mock_pages
+            ),
+        )
+        .unwrap();
+        let output = command(&dir)
+            .args([
+                "--config",
+                "config.toml",
+                "--json",
+                "--file",
+                "contract.pdf",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["data"]["extracted_text"].as_str().unwrap().to_string()
+    };
+
+    assert_eq!(
+        run("terms\nsignature\n", r#"["Terms", "Signature"]"#),
+        "Terms\n\nSignature"
+    );
+    // An inserted page is the only one sent to OCR
+    assert_eq!(
+        run("terms\nannex\nsignature\n", r#"["Annex"]"#),
+        "Terms\n\nAnnex\n\nSignature"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_local_text_routes_scanned_pages_of_mixed_pdfs_to_ocr() {
+    let dir = text_layer_fixture();
+    let mut config = fs::read_to_string(dir.path().join("config.toml")).unwrap();
+    config.push_str("\n[mock]\npages = [\"Signed by hand\"]\n");
+    fs::write(dir.path().join("config.toml"), config).unwrap();
+
+    let output = command(&dir)
+        .args(["--config", "config.toml", "--json", "--file", "mixed.pdf"])
+        .args(["--provider", "local-text,mock"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["provider"], "mock");
+    assert_eq!(
+        json["data"]["extracted_text"],
+        "Invoice 42 from ACME\n\nSigned by hand\n\nTotal due 100 EUR"
+    );
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_batch_transform_script_routes_and_skips_results() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(dir.path().join("draft.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\n\
+         text = \"CONFIDENTIAL INVOICE 42\"\n\n[transform]\nscript = \"transform.rhai\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("transform.rhai"),
+        r#"
+        if doc.text.contains("INVOICE") {
+            doc.output = "invoices/" + doc.name;
+        }
+        doc.text.replace("CONFIDENTIAL ", "");
+        doc.skip = doc.name.starts_with("draft");
+        "#,
+    )
+    .unwrap();
+
+    command(&dir)
+        .args(["--config", "config.toml", "batch", "scan.png", "draft.png"])
+        .args(["-o", "out"])
+        .assert()
+        .success();
+    let text = fs::read_to_string(dir.path().join("out/invoices/scan.png.txt")).unwrap();
+    assert!(text.contains("\n\nINVOICE 42\n"), "{}", text);
+    assert!(!dir.path().join("out/scan.png.txt").exists());
+    assert!(!dir.path().join("out/draft.png.txt").exists());
+    assert!(!dir.path().join("out/invoices/draft.png.txt").exists());
+}
+
+#[test]
+fn test_watch_once_skips_up_to_date_results() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    let watch = |dir: &TempDir| {
+        command(dir)
+            .args(["--json", "watch", ".", "--once", "--sidecar"])
+            .args(["--providers", "mock"])
+            .output()
+            .unwrap()
+    };
+
+    let first = watch(&dir);
+    assert!(first.status.success(), "{:?}", first);
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    let event: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    assert_eq!(event["success"], true);
+    assert!(dir.path().join("scan.png.json").is_file());
+
+    let second = watch(&dir);
+    assert!(second.status.success(), "{:?}", second);
+    let totals: serde_json::Value = serde_json::from_slice(&second.stdout).unwrap();
+    assert_eq!(totals["succeeded"], 0);
+}
+
+#[test]
+fn test_watch_leaves_locked_documents_to_their_worker() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.png"), PNG).unwrap();
+    fs::write(dir.path().join("b.png"), [PNG, b"b"].concat()).unwrap();
+    let locks = dir.path().join("locks");
+    let _held = DocumentLock::try_acquire(&locks, &dir.path().join("a.png"))
+        .unwrap()
+        .unwrap();
+
+    let output = command(&dir)
+        .args(["--json", "watch", ".", "--once", "--sidecar"])
+        .args([
+            "--lock-dir",
+            "locks",
+            "--settle",
+            "0",
+            "--providers",
+            "mock",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!dir.path().join("a.png.json").exists());
+    assert!(dir.path().join("b.png.json").is_file());
+}
+
+#[test]
+fn test_watch_skips_excluded_files() {
+    let dir = TempDir::new().unwrap();
+    for name in ["scan.png", "draft.png", "photo.png", "next.png.partial"] {
+        fs::write(dir.path().join(name), PNG).unwrap();
+    }
+    fs::write(dir.path().join(".ocrignore"), "draft*\n").unwrap();
+
+    let output = command(&dir)
+        .args([
+            "--json",
+            "watch",
+            ".",
+            "--once",
+            "--sidecar",
+            "--settle",
+            "0",
+        ])
+        .args(["--exclude", "photo.*", "--providers", "mock"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let totals: serde_json::Value = serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+    assert_eq!(totals["succeeded"], 1);
+    assert!(dir.path().join("scan.png.json").is_file());
+
+    command(&dir)
+        .args(["watch", ".", "--sidecar", "--exclude", "scan{a"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--exclude"));
+}
+
+#[test]
+fn test_config_show_redacts_secrets_and_validate_reports_errors() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"sk-secret\"\ntimeout_seconds = 0\n",
+    )
+    .unwrap();
+
+    command(&dir)
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("[REDACTED]").and(predicate::str::contains("sk-secret").not()),
+        );
+
+    command(&dir)
+        .args(["config", "path"])
+        .assert()
+        .success()
+        .stdout("config.toml\n");
+
+    command(&dir)
+        .args(["config", "validate"])
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(predicate::str::contains("timeout_seconds"));
+}
+
+#[test]
+fn test_cache_reuses_results_and_reports_stats() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    command(&dir)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("0 cache hits"));
+    command(&dir)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 cache hit"));
+
+    let stats = command(&dir)
+        .args(["--json", "cache", "stats"])
+        .output()
+        .unwrap();
+    assert!(stats.status.success(), "{:?}", stats);
+    let stats: serde_json::Value = serde_json::from_slice(&stats.stdout).unwrap();
+    assert_eq!(stats["entries"], 1);
+
+    command(&dir)
+        .args(["cache", "clear"])
+        .assert()
+        .success()
+        .stdout("Removed 1 cache entries\n");
+}
+
+#[test]
+fn test_cache_warm_reuses_stored_results() {
+    let dir = TempDir::new().unwrap();
+    let archive = dir.path().join("archive");
+    fs::create_dir(&archive).unwrap();
+    fs::write(archive.join("scan.png"), PNG).unwrap();
+    fs::write(
+        archive.join("scan.png.json"),
+        r#"{"file_name": "scan.png", "extracted_text": "Stored text", "provider": "mock"}"#,
+    )
+    .unwrap();
+    // Neither the document nor its hash is left to key this one by
+    fs::write(
+        archive.join("gone.json"),
+        r#"{"file_name": "gone.png", "extracted_text": "Gone", "provider": "mock"}"#,
+    )
+    .unwrap();
+    // Made with another version than the configured model reads with
+    fs::write(
+        archive.join("old.json"),
+        r#"{"file_name": "old.png", "extracted_text": "Old", "provider": "mistral",
+            "model": "mistral-ocr-2410-completion", "sha256": "0123"}"#,
+    )
+    .unwrap();
+
+    let warm = command(&dir)
+        .args(["--json", "cache", "warm", "archive", "--providers", "mock"])
+        .output()
+        .unwrap();
+    assert!(warm.status.success(), "{:?}", warm);
+    let report: serde_json::Value = serde_json::from_slice(&warm.stdout).unwrap();
+    assert_eq!(report["results"], 3);
+    assert_eq!(report["warmed"], 1);
+    assert_eq!(report["skipped"], 2);
+
+    command(&dir)
+        .args(["cache", "warm", "archive", "--providers", "mock"])
+        .assert()
+        .success()
+        .stdout("Warmed 0 of 3 results (1 already cached, 2 skipped)\n");
+
+    fs::copy(archive.join("scan.png"), dir.path().join("scan.png")).unwrap();
+    command(&dir)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Stored text"))
+        .stderr(predicate::str::contains("1 cache hit"));
+
+    // Pinned to the version it was made with, the Mistral result is current
+    command(&dir)
+        .env("PAPERLESS_OCR_OCR_MODEL", "mistral-ocr-2410")
+        .args(["cache", "warm", "archive", "--providers", "mock"])
+        .assert()
+        .success()
+        .stdout("Warmed 1 of 3 results (1 already cached, 1 skipped)\n");
+}
+
+#[test]
+fn test_cache_warm_keeps_the_stored_pages() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("scan.png.json"),
+        r#"{"file_name": "scan.png", "extracted_text": "One\n\nTwo\n\nThree",
+            "provider": "mock", "page_count": 3}"#,
+    )
+    .unwrap();
+
+    command(&dir)
+        .args(["cache", "warm", ".", "--providers", "mock"])
+        .assert()
+        .success()
+        .stdout("Warmed 1 of 1 results (0 already cached, 0 skipped)\n");
+
+    let output = command(&dir)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args([
+            "--providers",
+            "mock",
+            "--format",
+            "pages-json",
+            "--file",
+            "scan.png",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let pages = json["data"]["pages"].as_array().unwrap();
+    assert_eq!(pages.len(), 3);
+    assert_eq!(pages[2]["index"], 2);
+    assert_eq!(pages[2]["markdown"], "Three");
+}
+
+#[test]
+fn test_stale_cache_entries_are_used_and_refreshed() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[cache]\nenabled = true\nttl_hours = 1\nstale_while_revalidate_hours = 24\n",
+    )
+    .unwrap();
+    let run = || {
+        command(&dir)
+            .args([
+                "--config",
+                "config.toml",
+                "--providers",
+                "mock",
+                "--file",
+                "scan.png",
+            ])
+            .assert()
+            .success()
+    };
+    run();
+
+    // Expire the entry
+    let entries = dir.path().join("cache/paperless-ngx-ocr2/results");
+    let entry = fs::read_dir(&entries)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(7200);
+    fs::File::options()
+        .write(true)
+        .open(&entry)
+        .unwrap()
+        .set_modified(two_hours_ago)
+        .unwrap();
+
+    run().stderr(predicate::str::contains("1 cache hit"));
+    let modified = fs::metadata(&entry).unwrap().modified().unwrap();
+    assert!(modified > two_hours_ago + std::time::Duration::from_secs(3600));
+}
+
+#[test]
+fn test_cache_export_and_import() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    command(&dir)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success();
+
+    command(&dir)
+        .args(["cache", "export", "backup.tar.zst"])
+        .assert()
+        .success()
+        .stdout("Exported 1 cache entries and 0 results to backup.tar.zst\n");
+
+    // Another host, with an empty cache
+    let other = TempDir::new().unwrap();
+    fs::copy(
+        dir.path().join("backup.tar.zst"),
+        other.path().join("backup.tar.zst"),
+    )
+    .unwrap();
+    fs::write(other.path().join("scan.png"), PNG).unwrap();
+    command(&other)
+        .args(["cache", "import", "backup.tar.zst"])
+        .assert()
+        .success()
+        .stdout("Imported 1 cache entries and 0 results (0 skipped)\n");
+    command(&other)
+        .env("PAPERLESS_OCR_CACHE", "true")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 cache hit"));
+}
+
+#[test]
+fn test_completions_subcommand() {
+    let dir = TempDir::new().unwrap();
+
+    command(&dir)
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("batch"));
+}
+
+#[test]
+fn test_color_follows_flag_and_no_color() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["batch", "scan.png", "-o", "out", "--color", "always"])
+        .args(["--providers", "mock"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[32mok    \x1b[0m scan.png"))
+        .stderr(predicate::str::contains("\x1b[1mSummary:"));
+
+    // Piped output is plain with auto, and NO_COLOR keeps it plain
+    command(&dir)
+        .env("NO_COLOR", "1")
+        .args(["--providers", "mock", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::starts_with("Extracted text from scan.png (")
+                .and(predicate::str::contains("\x1b[").not()),
+        );
+}
+
+#[test]
+fn test_env_prefix_from_config_file() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "api_key = \"\"\nenv_prefix = \"OCR2_\"\n",
+    )
+    .unwrap();
+
+    // The default names are ignored once the prefix is changed
+    command(&dir)
+        .env("OCR2_PROVIDERS", "mock")
+        .env("PAPERLESS_OCR_PROVIDERS", "mistral")
+        .args(["--json", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+}
+
+#[test]
+fn test_env_files_next_to_config_and_explicit() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+    fs::create_dir(dir.path().join("etc")).unwrap();
+    fs::write(dir.path().join("etc/config.toml"), "api_key = \"\"\n").unwrap();
+    fs::write(
+        dir.path().join("etc/.env"),
+        "PAPERLESS_OCR_PROVIDERS=mock\n",
+    )
+    .unwrap();
+
+    command(&dir)
+        .args(["--config", "etc/config.toml", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+
+    fs::write(dir.path().join("ocr.env"), "PAPERLESS_OCR_PROVIDERS=mock\n").unwrap();
+    command(&dir)
+        .args(["--env-file", "ocr.env", "--file", "scan.png"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Mock OCR text"));
+
+    command(&dir)
+        .args(["--env-file", "missing.env", "--file", "scan.png"])
+        .assert()
+        .failure()
+        .code(4)
+        .stderr(predicate::str::contains("missing.env"));
+}
+
+#[test]
+fn test_sidecar_batch_keeps_manifest_out_of_working_directory() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["batch", "scan.png", "--sidecar", "--providers", "mock"])
+        .assert()
+        .success();
+
+    let manifests: Vec<_> = fs::read_dir(dir.path().join("state/paperless-ngx-ocr2/manifests"))
+        .unwrap()
+        .collect();
+    assert_eq!(manifests.len(), 1);
+    assert!(fs::read_dir(dir.path()).unwrap().all(|entry| !entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .starts_with("run-")));
+}
+
+#[test]
+fn test_watch_logs_to_rotating_file() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("scan.png"), PNG).unwrap();
+
+    command(&dir)
+        .args(["watch", ".", "--once", "--sidecar", "--providers", "mock"])
+        .args(["--log-file", "logs/ocr.log", "--log-max-size", "1"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Watching").not());
+    let log = fs::read_to_string(dir.path().join("logs/ocr.log")).unwrap();
+    assert!(log.contains("Watching"), "{}", log);
+    assert!(!log.contains('\x1b'));
+
+    command(&dir)
+        .args(["--log-keep", "3", "--file", "scan.png"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("--log-file"));
+}
@@ -0,0 +1,94 @@
+//! Integration tests for `suggest-tags`
+//! These tests run the CLI with the mock provider against a mock paperless-ngx server
+
+use assert_cmd::Command;
+use std::fs;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn write_fixtures(dir: &TempDir) -> (std::path::PathBuf, std::path::PathBuf) {
+    let file = dir.path().join("scan.png");
+    fs::write(&file, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let config = dir.path().join("config.toml");
+    fs::write(
+        &config,
+        "api_key = \"\"\nproviders = [\"mock\"]\n\n[mock]\ntext = \"Invoice from ACME Corporaton\"\n",
+    )
+    .unwrap();
+
+    (file, config)
+}
+
+#[tokio::test]
+async fn test_suggest_tags_outputs_matching_tag_ids() {
+    let server = MockServer::start().await;
+    let dir = TempDir::new().unwrap();
+    let (file, config) = write_fixtures(&dir);
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags/"))
+        .and(query_param("page", "2"))
+        .and(header("authorization", "Token secret-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": 4,
+            "next": null,
+            "results": [
+                {"id": 9, "name": "Tax", "match": ""},
+                // Matching disabled in paperless-ngx: never suggested
+                {"id": 11, "name": "Invoice copy", "match": "invoice", "matching_algorithm": 0}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags/"))
+        .and(query_param("page_size", "100"))
+        .and(header("authorization", "Token secret-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": 4,
+            "next": format!("{}/api/tags/?page=2", server.uri()),
+            "results": [
+                {"id": 3, "name": "Invoice", "match": ""},
+                {"id": 5, "name": "ACME Corporation", "match": ""}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let output = Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["--json", "suggest-tags", "--file"])
+        .arg(&file)
+        .args(["--paperless-url", &server.uri()])
+        .args(["--paperless-token", "secret-token"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["tag_ids"], serde_json::json!([3, 5]));
+    assert_eq!(json["data"]["suggestions"][0]["match"], "keyword");
+    assert_eq!(json["data"]["suggestions"][1]["match"], "fuzzy");
+}
+
+#[test]
+fn test_suggest_tags_requires_paperless_url() {
+    let dir = TempDir::new().unwrap();
+    let (file, config) = write_fixtures(&dir);
+
+    Command::cargo_bin("paperless-ngx-ocr2")
+        .unwrap()
+        .arg("--config")
+        .arg(&config)
+        .args(["suggest-tags", "--file"])
+        .arg(&file)
+        .env_remove("PAPERLESS_OCR_PAPERLESS_URL")
+        .assert()
+        .failure()
+        .code(4);
+}
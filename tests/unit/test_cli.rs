@@ -87,7 +87,7 @@ fn test_cli_validation_empty_file() {
     };
     
     assert!(cli.validate().is_err());
-    assert!(matches!(cli.validate().unwrap_err(), Error::Validation(_)));
+    assert!(matches!(cli.validate().unwrap_err(), Error::Validation { .. }));
 }
 
 #[test]
@@ -5,31 +5,31 @@ use paperless_ngx_ocr2::{Error, api::error::{APIError, APIErrorResponse}};
 #[test]
 fn test_error_exit_codes() {
     // Test all constitutional exit codes
-    assert_eq!(Error::Validation("test".to_string()).exit_code(), 2);
+    assert_eq!(Error::validation("test".to_string()).exit_code(), 2);
     assert_eq!(Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "test")).exit_code(), 3);
     assert_eq!(Error::Config("test".to_string()).exit_code(), 4);
-    assert_eq!(Error::Api("test".to_string()).exit_code(), 5);
+    assert_eq!(Error::api("test".to_string()).exit_code(), 5);
     // Create a mock network error for testing
-    let network_error = Error::Internal("Network error".to_string()); // Use Internal as proxy for Network
+    let network_error = Error::internal("Network error".to_string()); // Use Internal as proxy for Network
     assert_eq!(network_error.exit_code(), 5);
-    assert_eq!(Error::Internal("test".to_string()).exit_code(), 5);
+    assert_eq!(Error::internal("test".to_string()).exit_code(), 5);
 }
 
 #[test]
 fn test_error_type_strings() {
-    assert_eq!(Error::Validation("test".to_string()).error_type(), "validation");
+    assert_eq!(Error::validation("test".to_string()).error_type(), "validation");
     assert_eq!(Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "test")).error_type(), "file_io");
     assert_eq!(Error::Config("test".to_string()).error_type(), "api");
-    assert_eq!(Error::Api("test".to_string()).error_type(), "api");
+    assert_eq!(Error::api("test".to_string()).error_type(), "api");
     // Test network error type (using Internal as proxy)
-    let network_error = Error::Internal("Network error".to_string());
+    let network_error = Error::internal("Network error".to_string());
     assert_eq!(network_error.error_type(), "internal");
-    assert_eq!(Error::Internal("test".to_string()).error_type(), "internal");
+    assert_eq!(Error::internal("test".to_string()).error_type(), "internal");
 }
 
 #[test]
 fn test_error_user_messages() {
-    let validation_error = Error::Validation("Invalid input".to_string());
+    let validation_error = Error::validation("Invalid input".to_string());
     assert!(validation_error.user_message().contains("Validation error"));
     assert!(validation_error.user_message().contains("Invalid input"));
     
@@ -40,7 +40,7 @@ fn test_error_user_messages() {
 
 #[test]
 fn test_error_json_output() {
-    let error = Error::Validation("Test validation error".to_string());
+    let error = Error::validation("Test validation error".to_string());
     let json = error.to_json_output();
     
     assert_eq!(json["success"], false);
@@ -53,17 +53,17 @@ fn test_error_json_output() {
 fn test_error_from_http_status() {
     // Test 4xx errors
     let client_error = Error::from_http_status(400, "Bad Request".to_string());
-    assert!(matches!(client_error, Error::Api(_)));
+    assert!(matches!(client_error, Error::Api { .. }));
     assert_eq!(client_error.exit_code(), 5);
     
     // Test 5xx errors
     let server_error = Error::from_http_status(500, "Internal Server Error".to_string());
-    assert!(matches!(server_error, Error::Api(_)));
+    assert!(matches!(server_error, Error::Api { .. }));
     assert_eq!(server_error.exit_code(), 5);
     
     // Test other status codes
     let other_error = Error::from_http_status(200, "OK".to_string());
-    assert!(matches!(other_error, Error::Internal(_)));
+    assert!(matches!(other_error, Error::Internal { .. }));
 }
 
 #[test]
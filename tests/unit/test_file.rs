@@ -50,7 +50,7 @@ fn test_file_upload_new_invalid_format() {
     let result = FileUpload::new("tests/fixtures/invalid.txt");
     
     assert!(result.is_err());
-    assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    assert!(matches!(result.unwrap_err(), Error::Validation { .. }));
 }
 
 #[test]
@@ -59,7 +59,7 @@ fn test_file_upload_new_corrupted_pdf() {
     let result = FileUpload::new("tests/fixtures/corrupted.pdf");
     
     assert!(result.is_err());
-    assert!(matches!(result.unwrap_err(), Error::Validation(_)));
+    assert!(matches!(result.unwrap_err(), Error::Validation { .. }));
 }
 
 #[test]